@@ -27,9 +27,13 @@
 //!
 //! # Features
 //!
-//! This crate has features `std` and `u256_amount`. The former one is default.
-//! When `u256_amount` feature is enabled the type [`TokenAmountU256`] is defined
-//! and implements the [`IsTokenAmount`] interface.
+//! This crate has features `std`, `u256_amount` and `serde`. The first one is
+//! default. When `u256_amount` feature is enabled the type [`TokenAmountU256`]
+//! is defined and implements the [`IsTokenAmount`] interface. When the `serde`
+//! feature is enabled the token IDs and token amounts implement the
+//! `Serialize` and `Deserialize` traits of [serde](https://serde.rs), for use in
+//! off-chain code. Token IDs are represented as hex strings of their bytes, and
+//! token amounts as strings of their decimal value.
 #![cfg_attr(not(feature = "std"), no_std)]
 use concordium_std::{collections::BTreeMap, *};
 #[cfg(not(feature = "std"))]
@@ -875,6 +879,8 @@ impl<T: IsTokenId, A: IsTokenAmount> schema::SchemaType for Cis2Event<T, A> {
     }
 }
 
+impl<T: IsTokenId, A: IsTokenAmount> ContractEvent for Cis2Event<T, A> {}
+
 impl<T: IsTokenId, A: IsTokenAmount> Serial for Cis2Event<T, A> {
     fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
         match self {
@@ -1568,6 +1574,151 @@ impl AsRef<[SupportResult]> for SupportsQueryResponse {
     fn as_ref(&self) -> &[SupportResult] { &self.results }
 }
 
+/// Implementations of the traits of [serde](https://serde.rs) for token IDs
+/// and token amounts.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde_crate::{
+        de, Deserialize as SerdeDeserialize, Deserializer, Serialize as SerdeSerialize, Serializer,
+    };
+
+    /// Encode the token ID as a lowercase hex string of the bytes following the
+    /// length byte of its serialization.
+    fn token_id_to_hex<T: Serial>(id: &T) -> String {
+        let bytes = to_bytes(id);
+        bytes[1..].iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Decode a token ID from a hex string, by deserializing the bytes prefixed
+    /// by their length.
+    fn token_id_from_hex<T: Deserial, E: de::Error>(hex: &str) -> Result<T, E> {
+        if hex.len() % 2 != 0 {
+            return Err(E::custom("Odd number of hex digits in token ID."));
+        }
+        let len = u8::try_from(hex.len() / 2)
+            .map_err(|_| E::custom("Token ID is longer than 255 bytes."))?;
+        let mut bytes = Vec::with_capacity(1 + usize::from(len));
+        bytes.push(len);
+        for i in (0..hex.len()).step_by(2) {
+            let byte = hex
+                .get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| E::custom("Invalid hex digit in token ID."))?;
+            bytes.push(byte);
+        }
+        from_bytes(&bytes).map_err(|_| E::custom("Invalid length of token ID."))
+    }
+
+    macro_rules! token_id_serde {
+        ($name:ty) => {
+            impl SerdeSerialize for $name {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(&token_id_to_hex(self))
+                }
+            }
+
+            impl<'de> SerdeDeserialize<'de> for $name {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let hex = String::deserialize(deserializer)?;
+                    token_id_from_hex(&hex)
+                }
+            }
+        };
+    }
+
+    token_id_serde!(TokenIdVec);
+    token_id_serde!(TokenIdU64);
+    token_id_serde!(TokenIdU32);
+    token_id_serde!(TokenIdU16);
+    token_id_serde!(TokenIdU8);
+    token_id_serde!(TokenIdUnit);
+
+    impl<const N: usize> SerdeSerialize for TokenIdFixed<N> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&token_id_to_hex(self))
+        }
+    }
+
+    impl<'de, const N: usize> SerdeDeserialize<'de> for TokenIdFixed<N> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let hex = String::deserialize(deserializer)?;
+            token_id_from_hex(&hex)
+        }
+    }
+
+    macro_rules! token_amount_serde {
+        ($name:ident, $wrapped:ty) => {
+            impl SerdeSerialize for $name {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(&self.0.to_string())
+                }
+            }
+
+            impl<'de> SerdeDeserialize<'de> for $name {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let amount = String::deserialize(deserializer)?;
+                    amount
+                        .parse::<$wrapped>()
+                        .map($name)
+                        .map_err(|_| de::Error::custom("Invalid token amount."))
+                }
+            }
+        };
+    }
+
+    token_amount_serde!(TokenAmountU128, u128);
+    token_amount_serde!(TokenAmountU64, u64);
+    token_amount_serde!(TokenAmountU32, u32);
+    token_amount_serde!(TokenAmountU16, u16);
+    token_amount_serde!(TokenAmountU8, u8);
+
+    #[cfg(feature = "u256_amount")]
+    impl SerdeSerialize for TokenAmountU256 {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.0.to_string())
+        }
+    }
+
+    #[cfg(feature = "u256_amount")]
+    impl<'de> SerdeDeserialize<'de> for TokenAmountU256 {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let amount = String::deserialize(deserializer)?;
+            primitive_types::U256::from_dec_str(&amount)
+                .map(TokenAmountU256)
+                .map_err(|_| de::Error::custom("Invalid token amount."))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn token_id_json_roundtrip() {
+            let id = TokenIdU32(0x0102_03ff);
+            let json = serde_json::to_string(&id).expect("Serializing should succeed");
+            assert_eq!(json, "\"ff030201\"");
+            assert_eq!(serde_json::from_str::<TokenIdU32>(&json).ok(), Some(id));
+            assert_eq!(serde_json::from_str::<TokenIdUnit>("\"\"").ok(), Some(TokenIdUnit()));
+            let id = TokenIdVec(vec![0xab, 0x01]);
+            assert_eq!(serde_json::from_str::<TokenIdVec>("\"AB01\"").ok(), Some(id));
+            // The length must match the type of the token ID.
+            assert!(serde_json::from_str::<TokenIdU8>("\"0102\"").is_err());
+            assert!(serde_json::from_str::<TokenIdVec>("\"012\"").is_err());
+        }
+
+        #[test]
+        fn token_amount_json_roundtrip() {
+            let amount = TokenAmountU64(u64::MAX);
+            let json = serde_json::to_string(&amount).expect("Serializing should succeed");
+            assert_eq!(json, "\"18446744073709551615\"");
+            assert_eq!(serde_json::from_str::<TokenAmountU64>(&json).ok(), Some(amount));
+            assert!(serde_json::from_str::<TokenAmountU8>("\"256\"").is_err());
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,573 @@
+//! This library extracts the schema embedded in a smart contract module and
+//! writes a JSON description of every entrypoint of the contracts in the
+//! module, for use by off-chain code such as front-ends.
+//!
+//! The schema is embedded in the module by building it with
+//! `cargo concordium build --schema-embed`. Since the module is only available
+//! after it has been built, the library is meant to be used from a test, a
+//! separate build step, or the build script of another crate, e.g., a
+//! front-end crate that depends on the interface of the contracts.
+//!
+//! For every contract `<contract>` in the module, [`write_schema_files`] writes
+//! the files
+//! - `<contract>/init.json` for the init function,
+//! - `<contract>/receive/<entrypoint>.json` for every receive function, and
+//! - `<contract>/event.json` for the events, if the contract declares an event
+//!   type.
+//!
+//! The receive functions are in a directory of their own, so their files do
+//! not clash with the others, e.g., for a receive function named `init`. Names
+//! of contracts and entrypoints which cannot be used as file names, since they
+//! contain a path separator or are `.` or `..`, are rejected with
+//! [`SchemaBuildError::InvalidFileName`].
+//!
+//! The file of an entrypoint describes its parameter, return value and error,
+//! each of which is `null` if the schema does not include it, for example
+//!
+//! ```json
+//! {
+//!   "contract": "my_contract",
+//!   "entrypoint": "transfer",
+//!   "parameter": {
+//!     "amount": "<Amount in microCCD>",
+//!     "to": "<AccountAddress>"
+//!   },
+//!   "returnValue": null,
+//!   "error": null
+//! }
+//! ```
+//!
+//! The description of a type is a JSON template of the value, in the format
+//! expected by tools for serializing parameters according to the schema.
+//!
+//! # Example
+//!
+//! ```ignore
+//! #[test]
+//! fn write_schemas() {
+//!     concordium_schema_build::write_schema_files(
+//!         "concordium-out/module.wasm.v1",
+//!         "schemas",
+//!     )
+//!     .expect("Writing the schema files succeeds");
+//! }
+//! ```
+use concordium_contracts_common::{
+    from_bytes,
+    schema::{ContractV3, Fields, FunctionV2, ModuleV2, ModuleV3, Type},
+};
+use serde_json::{json, Map, Value};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// The name of the custom section in which the schema is embedded.
+const SCHEMA_SECTION_NAME: &str = "concordium-schema";
+
+/// The magic bytes at the start of a Wasm module.
+const WASM_MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+
+/// The length of the prefix of the versioned module output by
+/// `cargo concordium build`, which consists of the version and the length of
+/// the module, both as 4 bytes.
+const VERSIONED_MODULE_PREFIX_LENGTH: usize = 8;
+
+/// The prefix of a versioned schema, which is followed by the version.
+const VERSIONED_SCHEMA_PREFIX: [u8; 2] = [0xff, 0xff];
+
+/// Errors that can occur when extracting the schema of a module and writing
+/// the schema files.
+#[derive(Debug)]
+pub enum SchemaBuildError {
+    /// Reading the module or writing the files failed.
+    Io(std::io::Error),
+    /// The module is not a Wasm module, or its sections are malformed.
+    InvalidModule,
+    /// The module does not contain an embedded schema.
+    MissingSchema,
+    /// The embedded schema is not a versioned schema, or could not be parsed.
+    InvalidSchema,
+    /// The version of the embedded schema is not supported. Only schemas for
+    /// version 1 contracts, i.e., schema versions 2 and 3, are supported.
+    UnsupportedSchemaVersion(u8),
+    /// Serializing the JSON description failed.
+    Json(serde_json::Error),
+    /// The name of a contract or an entrypoint cannot be used as a file name.
+    InvalidFileName(String),
+}
+
+impl fmt::Display for SchemaBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaBuildError::Io(e) => write!(f, "I/O error: {}", e),
+            SchemaBuildError::InvalidModule => write!(f, "The module is not a valid Wasm module."),
+            SchemaBuildError::MissingSchema => write!(
+                f,
+                "The module does not contain an embedded schema. Build it with `cargo concordium \
+                 build --schema-embed`."
+            ),
+            SchemaBuildError::InvalidSchema => {
+                write!(f, "The embedded schema could not be parsed.")
+            }
+            SchemaBuildError::UnsupportedSchemaVersion(version) => {
+                write!(f, "Schema version {} is not supported.", version)
+            }
+            SchemaBuildError::Json(e) => write!(f, "Could not serialize JSON: {}", e),
+            SchemaBuildError::InvalidFileName(name) => {
+                write!(f, "The name {:?} cannot be used as a file name.", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaBuildError {}
+
+impl From<std::io::Error> for SchemaBuildError {
+    fn from(e: std::io::Error) -> Self { SchemaBuildError::Io(e) }
+}
+
+impl From<serde_json::Error> for SchemaBuildError {
+    fn from(e: serde_json::Error) -> Self { SchemaBuildError::Json(e) }
+}
+
+/// Extract the schema embedded in a module. The module can be given either as
+/// a plain Wasm module or as the versioned module output by
+/// `cargo concordium build`. A schema of version 2, which does not include
+/// events, is returned as a version 3 schema without event types.
+pub fn extract_schema(module: &[u8]) -> Result<ModuleV3, SchemaBuildError> {
+    let section =
+        find_custom_section(module, SCHEMA_SECTION_NAME)?.ok_or(SchemaBuildError::MissingSchema)?;
+    if section.len() < 3 || section[..2] != VERSIONED_SCHEMA_PREFIX {
+        return Err(SchemaBuildError::InvalidSchema);
+    }
+    let bytes = &section[3..];
+    match section[2] {
+        2 => {
+            let schema: ModuleV2 =
+                from_bytes(bytes).map_err(|_| SchemaBuildError::InvalidSchema)?;
+            let contracts = schema
+                .contracts
+                .into_iter()
+                .map(|(name, contract)| {
+                    (name, ContractV3 {
+                        init:    contract.init,
+                        receive: contract.receive,
+                        event:   None,
+                    })
+                })
+                .collect();
+            Ok(ModuleV3 {
+                contracts,
+            })
+        }
+        3 => from_bytes(bytes).map_err(|_| SchemaBuildError::InvalidSchema),
+        version => Err(SchemaBuildError::UnsupportedSchemaVersion(version)),
+    }
+}
+
+/// Read the module at `module_path`, extract its embedded schema and write the
+/// schema files of every contract to `out_dir`, which is created if it does
+/// not exist. Return the paths of the written files.
+///
+/// The names of all contracts and entrypoints are checked before any file is
+/// written, so no files are written if one of them is not a valid file name.
+pub fn write_schema_files(
+    module_path: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>, SchemaBuildError> {
+    let module = fs::read(module_path)?;
+    let schema = extract_schema(&module)?;
+    for (contract_name, contract) in schema.contracts.iter() {
+        check_file_name(contract_name)?;
+        for entrypoint in contract.receive.keys() {
+            check_file_name(entrypoint)?;
+        }
+    }
+    let mut written = Vec::new();
+    let mut write = |path: PathBuf, description: Value| -> Result<(), SchemaBuildError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&description)?)?;
+        written.push(path);
+        Ok(())
+    };
+    for (contract_name, contract) in schema.contracts.iter() {
+        let contract_dir = out_dir.as_ref().join(contract_name);
+        write(
+            contract_dir.join("init.json"),
+            entrypoint_description(contract_name, "init", contract.init.as_ref()),
+        )?;
+        for (entrypoint, function) in contract.receive.iter() {
+            write(
+                contract_dir.join("receive").join(format!("{}.json", entrypoint)),
+                entrypoint_description(contract_name, entrypoint, Some(function)),
+            )?;
+        }
+        if let Some(event) = &contract.event {
+            write(
+                contract_dir.join("event.json"),
+                json!({
+                    "contract": contract_name,
+                    "event": type_to_json_template(event),
+                }),
+            )?;
+        }
+    }
+    Ok(written)
+}
+
+/// Check that the name of a contract or an entrypoint can be used as the name
+/// of a file or directory in the output directory, i.e., that it is not empty,
+/// `.` or `..`, and does not contain a path separator.
+fn check_file_name(name: &str) -> Result<(), SchemaBuildError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(&['/', '\\'][..]) {
+        return Err(SchemaBuildError::InvalidFileName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Describe an entrypoint by the templates of its parameter, return value and
+/// error.
+fn entrypoint_description(
+    contract_name: &str,
+    entrypoint: &str,
+    function: Option<&FunctionV2>,
+) -> Value {
+    let template = |ty: Option<&Type>| ty.map_or(Value::Null, type_to_json_template);
+    json!({
+        "contract": contract_name,
+        "entrypoint": entrypoint,
+        "parameter": template(function.and_then(FunctionV2::parameter)),
+        "returnValue": template(function.and_then(FunctionV2::return_value)),
+        "error": template(function.and_then(FunctionV2::error)),
+    })
+}
+
+/// Construct a JSON template of a value of the type, where every primitive
+/// value is replaced by a description of it.
+pub fn type_to_json_template(ty: &Type) -> Value {
+    match ty {
+        Type::Unit => json!([]),
+        Type::Bool => json!("<Bool>"),
+        Type::U8 => json!("<UInt8>"),
+        Type::U16 => json!("<UInt16>"),
+        Type::U32 => json!("<UInt32>"),
+        Type::U64 => json!("<UInt64>"),
+        Type::U128 => json!("<UInt128>"),
+        Type::I8 => json!("<Int8>"),
+        Type::I16 => json!("<Int16>"),
+        Type::I32 => json!("<Int32>"),
+        Type::I64 => json!("<Int64>"),
+        Type::I128 => json!("<Int128>"),
+        Type::Amount => json!("<Amount in microCCD>"),
+        Type::AccountAddress => json!("<AccountAddress>"),
+        Type::ContractAddress => json!({
+            "index": "<UInt64>",
+            "subindex": "<UInt64>",
+        }),
+        Type::Timestamp => json!("<Timestamp (e.g. `2000-01-01T12:00:00Z`)>"),
+        Type::Duration => json!("<Duration (e.g. `10d 1h 42s`)>"),
+        Type::Pair(left, right) => {
+            json!([type_to_json_template(left), type_to_json_template(right)])
+        }
+        Type::List(_, ty) | Type::Set(_, ty) => json!([type_to_json_template(ty)]),
+        Type::Map(_, key, value) => {
+            json!([[type_to_json_template(key), type_to_json_template(value)]])
+        }
+        Type::Array(len, ty) => Value::Array(vec![type_to_json_template(ty); *len as usize]),
+        Type::Struct(fields) => fields_to_json_template(fields),
+        Type::Enum(variants) => {
+            enum_to_json_template(variants.iter().map(|(name, fields)| (name, fields)))
+        }
+        Type::TaggedEnum(variants) => {
+            enum_to_json_template(variants.values().map(|(name, fields)| (name, fields)))
+        }
+        Type::String(_) => json!("<String>"),
+        Type::ContractName(_) => json!({ "contract": "<String>" }),
+        Type::ReceiveName(_) => json!({
+            "contract": "<String>",
+            "func": "<String>",
+        }),
+        Type::ULeb128(_) => json!("<UInt>"),
+        Type::ILeb128(_) => json!("<Int>"),
+        Type::ByteList(_) => json!("<String with lowercase hex>"),
+        Type::ByteArray(len) => {
+            json!(format!("<String of size {} containing lowercase hex characters>", len * 2))
+        }
+    }
+}
+
+/// Construct the JSON template of the fields of a struct or an enum variant.
+fn fields_to_json_template(fields: &Fields) -> Value {
+    match fields {
+        Fields::Named(fields) => Value::Object(
+            fields.iter().map(|(name, ty)| (name.clone(), type_to_json_template(ty))).collect(),
+        ),
+        Fields::Unnamed(fields) => Value::Array(fields.iter().map(type_to_json_template).collect()),
+        Fields::None => json!([]),
+    }
+}
+
+/// Construct the JSON template of an enum, which lists the templates of all
+/// the variants.
+fn enum_to_json_template<'a>(variants: impl Iterator<Item = (&'a String, &'a Fields)>) -> Value {
+    let variants = variants
+        .map(|(name, fields)| {
+            let mut variant = Map::new();
+            variant.insert(name.clone(), fields_to_json_template(fields));
+            Value::Object(variant)
+        })
+        .collect();
+    json!({ "Enum": Value::Array(variants) })
+}
+
+/// Find the contents of the first custom section with the given name in the
+/// module.
+fn find_custom_section<'a>(
+    module: &'a [u8],
+    name: &str,
+) -> Result<Option<&'a [u8]>, SchemaBuildError> {
+    let module = if module.starts_with(&WASM_MAGIC) {
+        module
+    } else if module.len() > VERSIONED_MODULE_PREFIX_LENGTH
+        && module[VERSIONED_MODULE_PREFIX_LENGTH..].starts_with(&WASM_MAGIC)
+    {
+        &module[VERSIONED_MODULE_PREFIX_LENGTH..]
+    } else {
+        return Err(SchemaBuildError::InvalidModule);
+    };
+    // Skip the magic bytes and the version of the Wasm module.
+    let mut cursor = module.get(8..).ok_or(SchemaBuildError::InvalidModule)?;
+    while let Some((&section_id, rest)) = cursor.split_first() {
+        cursor = rest;
+        let section = read_bytes(&mut cursor)?;
+        // Custom sections have ID 0.
+        if section_id == 0 {
+            let mut contents = section;
+            let section_name = read_bytes(&mut contents)?;
+            if section_name == name.as_bytes() {
+                return Ok(Some(contents));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Read a length-prefixed sequence of bytes, where the length is encoded as
+/// an unsigned LEB128 integer, and advance the cursor past it.
+fn read_bytes<'a>(cursor: &mut &'a [u8]) -> Result<&'a [u8], SchemaBuildError> {
+    let len = read_leb128_u32(cursor)? as usize;
+    if len > cursor.len() {
+        return Err(SchemaBuildError::InvalidModule);
+    }
+    let (bytes, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Ok(bytes)
+}
+
+/// Read an unsigned LEB128 integer of at most 32 bits and advance the cursor
+/// past it.
+fn read_leb128_u32(cursor: &mut &[u8]) -> Result<u32, SchemaBuildError> {
+    let mut result: u32 = 0;
+    for i in 0..5 {
+        let (&byte, rest) = cursor.split_first().ok_or(SchemaBuildError::InvalidModule)?;
+        *cursor = rest;
+        let value = u32::from(byte & 0x7f);
+        if i == 4 && value > 0x0f {
+            return Err(SchemaBuildError::InvalidModule);
+        }
+        result |= value << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+    Err(SchemaBuildError::InvalidModule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use concordium_contracts_common::{schema::SizeLength, to_bytes};
+    use std::collections::BTreeMap;
+
+    /// Construct a module consisting only of a custom section with the schema.
+    fn module_with_schema(schema: &ModuleV3) -> Vec<u8> {
+        let mut contents = VERSIONED_SCHEMA_PREFIX.to_vec();
+        contents.push(3);
+        contents.extend(to_bytes(schema));
+        let mut section = vec![SCHEMA_SECTION_NAME.len() as u8];
+        section.extend_from_slice(SCHEMA_SECTION_NAME.as_bytes());
+        section.extend(contents);
+        let mut module = WASM_MAGIC.to_vec();
+        module.extend_from_slice(&[1, 0, 0, 0]);
+        module.push(0);
+        write_leb128_u32(&mut module, section.len() as u32);
+        module.extend(section);
+        module
+    }
+
+    fn write_leb128_u32(out: &mut Vec<u8>, mut value: u32) {
+        while value >= 0x80 {
+            out.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn test_schema() -> ModuleV3 {
+        let parameter = Type::Struct(Fields::Named(vec![
+            ("owner".to_string(), Type::AccountAddress),
+            ("amount".to_string(), Type::Amount),
+        ]));
+        let mut receive = BTreeMap::new();
+        receive.insert("transfer".to_string(), FunctionV2::Param(parameter));
+        receive.insert("view".to_string(), FunctionV2::Rv(Type::String(SizeLength::U32)));
+        let mut contracts = BTreeMap::new();
+        contracts.insert("my_contract".to_string(), ContractV3 {
+            init: None,
+            receive,
+            event: Some(Type::Enum(vec![("Minted".to_string(), Fields::Unnamed(vec![Type::U64]))])),
+        });
+        ModuleV3 {
+            contracts,
+        }
+    }
+
+    #[test]
+    fn extract_schema_from_module() {
+        let module = module_with_schema(&test_schema());
+        let schema = extract_schema(&module).expect("The schema is embedded");
+        assert_eq!(to_bytes(&schema), to_bytes(&test_schema()));
+        // The versioned module output by cargo concordium.
+        let mut versioned = vec![0, 0, 0, 1];
+        versioned.extend_from_slice(&(module.len() as u32).to_be_bytes());
+        versioned.extend_from_slice(&module);
+        let schema = extract_schema(&versioned).expect("The schema is embedded");
+        assert_eq!(to_bytes(&schema), to_bytes(&test_schema()));
+    }
+
+    #[test]
+    fn leb128_roundtrip() {
+        for value in [0, 1, 127, 128, 300, 16384, u32::MAX] {
+            let mut bytes = Vec::new();
+            write_leb128_u32(&mut bytes, value);
+            let mut cursor = &bytes[..];
+            assert_eq!(read_leb128_u32(&mut cursor).expect("Valid LEB128"), value);
+            assert!(cursor.is_empty());
+        }
+        // Five bytes encoding a value larger than u32::MAX.
+        assert!(read_leb128_u32(&mut &[0xff, 0xff, 0xff, 0xff, 0x1f][..]).is_err());
+    }
+
+    #[test]
+    fn extract_schema_errors() {
+        let mut module = WASM_MAGIC.to_vec();
+        module.extend_from_slice(&[1, 0, 0, 0]);
+        assert!(matches!(extract_schema(&module), Err(SchemaBuildError::MissingSchema)));
+        assert!(matches!(extract_schema(&[1, 2, 3]), Err(SchemaBuildError::InvalidModule)));
+        // A section longer than the module.
+        module.extend_from_slice(&[0, 10, 1]);
+        assert!(matches!(extract_schema(&module), Err(SchemaBuildError::InvalidModule)));
+    }
+
+    /// A temporary directory with a module with the schema, which is removed
+    /// when dropped.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str, schema: &ModuleV3) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "concordium-schema-build-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::create_dir_all(&dir).expect("Creating the directory succeeds");
+            fs::write(dir.join("module.wasm"), module_with_schema(schema))
+                .expect("Writing succeeds");
+            TestDir(dir)
+        }
+
+        fn write_schema_files(&self) -> Result<Vec<PathBuf>, SchemaBuildError> {
+            write_schema_files(self.0.join("module.wasm"), self.0.join("schemas"))
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) { let _ = fs::remove_dir_all(&self.0); }
+    }
+
+    /// A schema with a single contract with the given receive functions.
+    fn schema_with_names(contract_name: &str, entrypoints: &[&str]) -> ModuleV3 {
+        let receive = entrypoints
+            .iter()
+            .map(|name| (name.to_string(), FunctionV2::Param(Type::U8)))
+            .collect();
+        let mut contracts = BTreeMap::new();
+        contracts.insert(contract_name.to_string(), ContractV3 {
+            init: None,
+            receive,
+            event: Some(Type::U8),
+        });
+        ModuleV3 {
+            contracts,
+        }
+    }
+
+    #[test]
+    fn write_schema_files_per_entrypoint() {
+        let dir = TestDir::new("entrypoints", &test_schema());
+        let out_dir = dir.0.join("schemas");
+        let written = dir.write_schema_files().expect("Writing the files succeeds");
+        assert_eq!(written.len(), 4);
+
+        let read = |name: &str| -> Value {
+            let path = out_dir.join("my_contract").join(name);
+            serde_json::from_slice(&fs::read(path).expect("The file exists")).expect("Valid JSON")
+        };
+        assert_eq!(
+            read("receive/transfer.json"),
+            json!({
+                "contract": "my_contract",
+                "entrypoint": "transfer",
+                "parameter": { "owner": "<AccountAddress>", "amount": "<Amount in microCCD>" },
+                "returnValue": null,
+                "error": null,
+            })
+        );
+        assert_eq!(read("receive/view.json")["returnValue"], json!("<String>"));
+        assert_eq!(read("init.json")["parameter"], Value::Null);
+        assert_eq!(read("event.json")["event"], json!({ "Enum": [{ "Minted": ["<UInt64>"] }] }));
+    }
+
+    #[test]
+    fn receive_files_do_not_clash() {
+        let dir = TestDir::new("clash", &schema_with_names("my_contract", &["event", "init"]));
+        let written = dir.write_schema_files().expect("Writing the files succeeds");
+        let contract_dir = dir.0.join("schemas").join("my_contract");
+        assert_eq!(written, [
+            contract_dir.join("init.json"),
+            contract_dir.join("receive").join("event.json"),
+            contract_dir.join("receive").join("init.json"),
+            contract_dir.join("event.json"),
+        ]);
+        let entrypoint: Value =
+            serde_json::from_slice(&fs::read(&written[2]).expect("The file exists"))
+                .expect("Valid JSON");
+        assert_eq!(entrypoint["entrypoint"], json!("init"));
+        assert_eq!(entrypoint["parameter"], json!("<UInt8>"));
+    }
+
+    #[test]
+    fn invalid_file_names_are_rejected() {
+        for (contract_name, entrypoint) in
+            [("my_contract", "a/b"), ("my_contract", ".."), ("..", "view"), ("a\\b", "view")]
+        {
+            let dir = TestDir::new("invalid", &schema_with_names(contract_name, &[entrypoint]));
+            assert!(matches!(dir.write_schema_files(), Err(SchemaBuildError::InvalidFileName(_))));
+            // Nothing is written.
+            assert!(!dir.0.join("schemas").exists());
+        }
+    }
+}
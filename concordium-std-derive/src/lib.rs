@@ -4,7 +4,7 @@ extern crate syn;
 #[macro_use]
 extern crate quote;
 
-use concordium_contracts_common::{ContractName, ReceiveName};
+use concordium_contracts_common::{ContractName, EntrypointName, ReceiveName};
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::ToTokens;
@@ -18,9 +18,10 @@ use std::{
     ops::Neg,
 };
 #[cfg(feature = "concordium-quickcheck")]
-use syn::{parse::Parse, parse_quote, FnArg, Item, Lit, NestedMeta, PatType};
+use syn::{parse::Parse, parse_quote, Item, Lit, NestedMeta};
 use syn::{
-    parse::Parser, parse_macro_input, punctuated::*, spanned::Spanned, DataEnum, Ident, Meta, Token,
+    parse::Parser, parse_macro_input, punctuated::*, spanned::Spanned, DataEnum, FnArg, Ident,
+    Meta, PatType, Token,
 };
 
 /// A helper to report meaningful compilation errors
@@ -518,6 +519,27 @@ fn contains_attribute<'a, I: IntoIterator<Item = &'a Meta>>(iter: I, name: &str)
 /// fn some_init(ctx: &impl HasInitContext, state: &mut impl HasStateApi) -> Result<(), MyError> {...}
 /// ```
 ///
+/// ## `event="<Event>"`: Generate schema for events
+/// To make schema generation include the type of the events logged by the
+/// contract, add the attribute `event` and set it equal to a string literal
+/// containing the name of the event type. The event schema is then embedded in
+/// the module schema along with the schemas of the contract functions, which
+/// allows tools to decode the events logged by any function of the contract.
+/// Since the event type belongs to the contract, it is only set on the
+/// init-function. The event type must implement the SchemaType trait, which
+/// for an enum declared with
+/// [`#[concordium_event]`](macro@crate::concordium_event) is a tagged enum
+/// matching the serialization of the events.
+///
+/// ### Example
+/// ```ignore
+/// #[concordium_event]
+/// enum MyEvent { ... }
+///
+/// #[init(contract = "my_contract", event = "MyEvent")]
+/// fn some_init<S: HasStateApi>(ctx: &impl HasInitContext, state_builder: &mut StateBuilder<S>) -> InitResult<MyState> {...}
+/// ```
+///
 /// ## `crypto_primitives`: Function can access cryptographic primitives
 /// Setting the `crypto_primitives` attribute changes the required signature to
 /// include an extra argument `&impl HasCryptoPrimitives`, which provides
@@ -1688,7 +1710,13 @@ const RESERVED_ERROR_CODES: i32 = i32::MIN + 100;
 /// is to be derived.
 ///
 /// The conversion will map the first variant to error code -1, second to -2,
-/// etc.
+/// etc. Since these codes change when variants are inserted or reordered, a
+/// variant can instead be given an explicit error code with the
+/// `#[reject(code = ...)]` attribute, which keeps the code stable across
+/// versions of the contract. Explicit error codes must be negative and must not
+/// be in the range `i32::MIN` to `i32::MIN + 100`, which is reserved for the
+/// errors of `concordium-std`. No two variants can have the same error code,
+/// including the codes of variants without the attribute.
 ///
 /// ### Example
 /// ```ignore
@@ -1697,6 +1725,8 @@ const RESERVED_ERROR_CODES: i32 = i32::MIN + 100;
 ///     IllegalState, // receives error code -1
 ///     WrongSender, // receives error code -2
 ///     TimeExpired(time: Timestamp), // receives error code -3
+///     #[reject(code = -100)]
+///     Paused, // receives error code -100
 ///     ...
 /// }
 /// ```
@@ -1705,7 +1735,7 @@ const RESERVED_ERROR_CODES: i32 = i32::MIN + 100;
 /// fn receive<A: HasActions>(ctx: &impl HasReceiveContext, state: &mut MyState)
 /// -> Result<A, MyError> {...}
 /// ```
-#[proc_macro_derive(Reject, attributes(from))]
+#[proc_macro_derive(Reject, attributes(from, reject))]
 pub fn reject_derive(input: TokenStream) -> TokenStream {
     unwrap_or_report(reject_derive_worker(input))
 }
@@ -1733,11 +1763,14 @@ fn reject_derive_worker(input: TokenStream) -> syn::Result<TokenStream> {
     };
 
     let variant_error_conversions = generate_variant_error_conversions(enum_data, enum_ident)?;
+    let error_codes = variant_error_codes(enum_data)?;
     let buf_var_ident = format_ident!("{}", "buf");
-    let variant_matches = generate_variant_matches(enum_data, enum_ident, &buf_var_ident);
+    let variant_matches =
+        generate_variant_matches(enum_data, enum_ident, &error_codes, &buf_var_ident);
 
     let gen = quote! {
-        /// The from implementation maps the first variant to -1, second to -2, etc.
+        /// The from implementation maps the first variant to -1, second to -2, etc.,
+        /// unless an explicit error code is given by the `#[reject(code = ...)]` attribute.
         /// NB: This differs from the cast `variant as i32` since we cannot easily modify
         /// the variant tags in the derive macro itself.
         #[automatically_derived]
@@ -1757,23 +1790,97 @@ fn reject_derive_worker(input: TokenStream) -> syn::Result<TokenStream> {
     Ok(gen.into())
 }
 
-/// Generate the cases for matching on the enum.
-/// The error codes for variants start at -1 and go downwards.
+/// Determine the error code of each variant of the enum. The error codes
+/// for variants start at -1 and go downwards, unless an explicit code is given
+/// by the `#[reject(code = ...)]` attribute. Ensures that the explicit codes
+/// are valid and that no two variants have the same code.
+fn variant_error_codes(enum_data: &DataEnum) -> syn::Result<Vec<i32>> {
+    let mut codes = Vec::new();
+    let mut used_codes = BTreeMap::new();
+    for (index, variant) in enum_data.variants.iter().enumerate() {
+        let code = match find_reject_code_attribute(&variant.attrs)? {
+            Some(code) => code,
+            None => -(index as i32) - 1,
+        };
+        if let Some(existing) = used_codes.insert(code, &variant.ident) {
+            return Err(syn::Error::new(
+                variant.ident.span(),
+                format!("The error code {} is already used by the variant '{}'.", code, existing),
+            ));
+        }
+        codes.push(code);
+    }
+    Ok(codes)
+}
+
+/// Find a `#[reject(code = ...)]` attribute and return the error code.
+/// Checks that the attribute is only defined once and that the code is
+/// negative and outside the range of error codes reserved by `concordium-std`.
+fn find_reject_code_attribute(attributes: &[syn::Attribute]) -> syn::Result<Option<i32>> {
+    let mut code = None;
+    for attr in attributes.iter().filter(|attr| attr.path.is_ident("reject")) {
+        let (value, span) = attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let key: Ident = input.parse()?;
+            if key != "code" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "The `reject` attribute expects an error code, e.g.: #[reject(code = -42)].",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let negative = input.parse::<Option<Token![-]>>()?.is_some();
+            let literal: syn::LitInt = input.parse()?;
+            let value = literal.base10_parse::<i64>()?;
+            Ok((
+                if negative {
+                    -value
+                } else {
+                    value
+                },
+                literal.span(),
+            ))
+        })?;
+        if code.is_some() {
+            return Err(syn::Error::new(
+                attr.span(),
+                "The error code should only be specified once.",
+            ));
+        }
+        match i32::try_from(value) {
+            Ok(value) if value < 0 && value > RESERVED_ERROR_CODES => code = Some(value),
+            _ => {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "The error code must be negative and greater than {}, since smaller codes \
+                         are reserved.",
+                        RESERVED_ERROR_CODES
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// Generate the cases for matching on the enum, using the given error code for
+/// each variant.
 /// The whole enum is serialized and included in the return_value field, which,
 /// thus, is always `Some`.
 fn generate_variant_matches(
     enum_data: &DataEnum,
     enum_name: &syn::Ident,
+    error_codes: &[i32],
     buf_var_ident: &syn::Ident,
 ) -> proc_macro2::TokenStream {
     let mut match_cases = proc_macro2::TokenStream::new();
-    for (index, variant) in enum_data.variants.iter().enumerate() {
+    for (variant, code) in enum_data.variants.iter().zip(error_codes.iter()) {
         let variant_ident = &variant.ident;
         match variant.fields {
             syn::Fields::Named(_) => {
                 match_cases.extend(quote! {
                     #enum_name::#variant_ident{..} => Reject {
-                        error_code: unsafe { num::NonZeroI32::new_unchecked(-(#index as i32) - 1) },
+                        error_code: unsafe { num::NonZeroI32::new_unchecked(#code) },
                         return_value: Some(#buf_var_ident),
                     },
                 });
@@ -1781,7 +1888,7 @@ fn generate_variant_matches(
             syn::Fields::Unnamed(_) => {
                 match_cases.extend(quote! {
                     #enum_name::#variant_ident(..) => Reject {
-                        error_code: unsafe { num::NonZeroI32::new_unchecked(-(#index as i32) - 1) },
+                        error_code: unsafe { num::NonZeroI32::new_unchecked(#code) },
                         return_value: Some(#buf_var_ident),
                     },
                 });
@@ -1789,7 +1896,7 @@ fn generate_variant_matches(
             syn::Fields::Unit => {
                 match_cases.extend(quote! {
                     #enum_name::#variant_ident => Reject {
-                        error_code: unsafe { num::NonZeroI32::new_unchecked(-(#index as i32) - 1) },
+                        error_code: unsafe { num::NonZeroI32::new_unchecked(#code) },
                         return_value: Some(#buf_var_ident),
                     },
                 });
@@ -2453,3 +2560,502 @@ fn impl_state_clone(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
 
     Ok(gen.into())
 }
+
+/// Generate a typed client for invoking the entrypoints of another smart
+/// contract instance.
+///
+/// The macro is applied to a trait which declares the entrypoints of the
+/// contract as methods without a body. Each method takes at most one
+/// argument, which is the parameter of the entrypoint, and its return type is
+/// the type of the return value of the entrypoint. The trait is replaced by a
+/// struct of the same name, which contains the address of the contract
+/// instance, and which has a method for each entrypoint. The method takes the
+/// host and a reference to the parameter, and invokes the entrypoint using
+/// [`invoke_contract_typed`][invoke_contract_typed]. Methods without a return
+/// type do not expect a return value, and can thus also be used for invoking
+/// `V0` contracts.
+///
+/// By default the name of the method is the name of the entrypoint, and the
+/// entrypoint is invoked with a zero amount. This can be changed by the
+/// optional `#[entrypoint]` attribute on the method:
+/// - `#[entrypoint(name = "...")]` sets the name of the entrypoint.
+/// - `#[entrypoint(payable)]` adds an `amount: Amount` argument to the method,
+///   which is the amount of CCD to send along with the invocation.
+///
+/// # Example
+/// ```ignore
+/// #[concordium_client]
+/// pub trait WeatherClient {
+///     fn get(region: RegionId) -> Weather;
+///     #[entrypoint(name = "set", payable)]
+///     fn set_weather(weather: Weather);
+/// }
+///
+/// let weather = WeatherClient::new(weather_service).get(host, &region)?;
+/// ```
+///
+/// [invoke_contract_typed]: ../concordium_std/trait.HasHost.html#method.invoke_contract_typed
+#[proc_macro_attribute]
+pub fn concordium_client(attr: TokenStream, item: TokenStream) -> TokenStream {
+    unwrap_or_report(concordium_client_worker(attr, item))
+}
+
+// Supported attributes for the methods of a client.
+
+const CLIENT_ATTRIBUTE_NAME: &str = "name";
+const CLIENT_ATTRIBUTE_PAYABLE: &str = "payable";
+
+fn concordium_client_worker(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    if !attr.is_empty() {
+        return Err(syn::Error::new(
+            proc_macro2::TokenStream::from(attr).span(),
+            "#[concordium_client] does not take any attributes.",
+        ));
+    }
+    let ast: syn::ItemTrait =
+        attach_error(syn::parse(item), "#[concordium_client] can only be applied to traits.")?;
+    if !ast.generics.params.is_empty() || !ast.supertraits.is_empty() {
+        return Err(syn::Error::new(
+            ast.generics.span(),
+            "#[concordium_client] does not support generics or supertraits.",
+        ));
+    }
+    let methods = ast
+        .items
+        .iter()
+        .map(|item| match item {
+            syn::TraitItem::Method(method) => client_method_tokens(method),
+            other => Err(syn::Error::new(
+                other.span(),
+                "#[concordium_client] only supports methods declaring entrypoints.",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let attrs = &ast.attrs;
+    let vis = &ast.vis;
+    let client_ident = &ast.ident;
+    let gen = quote! {
+        #(#attrs)*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #vis struct #client_ident {
+            /// The address of the contract instance that is invoked.
+            pub address: concordium_std::ContractAddress,
+        }
+
+        impl #client_ident {
+            /// Construct a client for the contract instance at the given address.
+            #[inline(always)]
+            pub fn new(address: concordium_std::ContractAddress) -> Self {
+                Self {
+                    address,
+                }
+            }
+
+            #(#methods)*
+        }
+    };
+    Ok(gen.into())
+}
+
+/// Generate the method of a client which invokes the entrypoint declared by
+/// the given trait method.
+fn client_method_tokens(method: &syn::TraitItemMethod) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &method.sig;
+    if let Some(default) = &method.default {
+        return Err(syn::Error::new(
+            default.span(),
+            "Methods declaring entrypoints must not have a body.",
+        ));
+    }
+    if sig.receiver().is_some() || sig.inputs.len() > 1 {
+        return Err(syn::Error::new(
+            sig.inputs.span(),
+            "Methods declaring entrypoints take at most one argument, the parameter, and no \
+             `self`.",
+        ));
+    }
+    if !sig.generics.params.is_empty() || sig.asyncness.is_some() || sig.unsafety.is_some() {
+        return Err(syn::Error::new(
+            sig.span(),
+            "Methods declaring entrypoints must not be generic, async, or unsafe.",
+        ));
+    }
+
+    let mut doc_attrs = Vec::new();
+    let mut entrypoint_metas = Vec::new();
+    for attr in method.attrs.iter() {
+        if attr.path.is_ident("entrypoint") {
+            match attr.parse_meta()? {
+                Meta::List(list) => {
+                    for nested in list.nested {
+                        match nested {
+                            syn::NestedMeta::Meta(meta) => entrypoint_metas.push(meta),
+                            syn::NestedMeta::Lit(lit) => {
+                                return Err(syn::Error::new(
+                                    lit.span(),
+                                    "Literals are not supported in the entrypoint attribute.",
+                                ))
+                            }
+                        }
+                    }
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        "The entrypoint attribute expects a list, e.g., #[entrypoint(name = \
+                         \"get\", payable)].",
+                    ))
+                }
+            }
+        } else {
+            doc_attrs.push(attr);
+        }
+    }
+    let mut attributes = parse_attributes(&entrypoint_metas)?;
+    let entrypoint_name = attributes
+        .extract_value(CLIENT_ATTRIBUTE_NAME)
+        .unwrap_or_else(|| syn::LitStr::new(&sig.ident.to_string(), sig.ident.span()));
+    let payable = attributes.extract_flag(CLIENT_ATTRIBUTE_PAYABLE).is_some();
+    attributes.report_all_attributes()?;
+    EntrypointName::new(&entrypoint_name.value())
+        .map_err(|e| syn::Error::new(entrypoint_name.span(), e))?;
+
+    let (parameter_arg, parameter_expr) = match sig.inputs.first() {
+        Some(FnArg::Typed(PatType {
+            pat,
+            ty,
+            ..
+        })) => match pat.as_ref() {
+            syn::Pat::Ident(pat_ident) => {
+                let ident = &pat_ident.ident;
+                (quote!(#ident: &#ty,), quote!(#ident))
+            }
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "The parameter of an entrypoint must be named by an identifier.",
+                ))
+            }
+        },
+        _ => (quote!(), quote!(&())),
+    };
+    let (amount_arg, amount_expr) = if payable {
+        (quote!(amount: concordium_std::Amount,), quote!(amount))
+    } else {
+        (quote!(), quote!(concordium_std::Amount::zero()))
+    };
+    let (return_type, invoke) = match &sig.output {
+        syn::ReturnType::Type(_, ty) => (quote!(#ty), quote! {
+            host.invoke_contract_typed(&self.address, #parameter_expr, entrypoint, #amount_expr)
+        }),
+        syn::ReturnType::Default => (quote!(()), quote! {
+            host.invoke_contract(&self.address, #parameter_expr, entrypoint, #amount_expr)?;
+            Ok(())
+        }),
+    };
+
+    let method_ident = &sig.ident;
+    Ok(quote! {
+        #(#doc_attrs)*
+        pub fn #method_ident<HostState, Host: concordium_std::HasHost<HostState>>(
+            &self,
+            host: &mut Host,
+            #parameter_arg
+            #amount_arg
+        ) -> Result<#return_type, concordium_std::CallContractTypedError<Host::ReturnValueType>> {
+            let entrypoint = concordium_std::EntrypointName::new_unchecked(#entrypoint_name);
+            #invoke
+        }
+    })
+}
+
+/// Declare an enum as a type of events logged by a smart contract.
+///
+/// The macro implements
+/// [`ContractEvent`](../concordium_std/trait.ContractEvent.html), which allows
+/// logging the events with
+/// [`log_event`](../concordium_std/trait.HasLogger.html#method.log_event),
+/// as well as [`Serial`](../concordium_contracts_common/trait.Serial.html) and
+/// `SchemaType` for the enum. Events are serialized as a tag byte identifying
+/// the variant followed by the fields of the variant, and the schema of the
+/// enum is a tagged enum, so that the events can be decoded from the schema
+/// when the enum is specified as the event type of the contract, i.e., by
+/// `#[init(contract = "my_contract", event = "MyEvent")]`.
+///
+/// By default a variant is tagged by its index, as if `Serial` was derived.
+/// This can be changed using attributes on the variants:
+/// - `#[concordium(tag = 255)]` sets the tag of the variant, which allows
+///   following standards such as CIS-2 that specify the tags of events, and
+///   keeping the tags stable when variants are added.
+/// - `#[concordium(nested)]` is used for a variant with a single field which is
+///   itself an event type, e.g., a `Cis2Event`. The variant is serialized
+///   without a tag of its own, and the tags of the nested event type are
+///   included in the schema. Note that it is not checked that the tags of the
+///   nested event type are distinct from the tags of the other variants.
+///
+/// The macro must be placed before any `derive` attributes on the enum. Since
+/// events are only serialized by the contract, `Deserial` is not implemented.
+/// Attributes on the fields of the variants are not supported.
+///
+/// # Example
+/// ```ignore
+/// #[concordium_event]
+/// #[derive(Debug)]
+/// enum MyEvent {
+///     #[concordium(tag = 0)]
+///     NewAdmin {
+///         new_admin: Address,
+///     },
+///     #[concordium(nested)]
+///     Cis2(Cis2Event<ContractTokenId, ContractTokenAmount>),
+/// }
+///
+/// logger.log_event(&MyEvent::NewAdmin {
+///     new_admin,
+/// })?;
+/// ```
+#[proc_macro_attribute]
+pub fn concordium_event(attr: TokenStream, item: TokenStream) -> TokenStream {
+    unwrap_or_report(concordium_event_worker(attr, item))
+}
+
+/// How a variant of an event is tagged when it is serialized.
+#[derive(Clone, Copy)]
+enum EventVariantTag {
+    /// The variant is serialized with the given tag.
+    Tag(u8),
+    /// The variant wraps another event type, which is serialized with its own
+    /// tags.
+    Nested,
+}
+
+// Supported attributes for the variants of an event.
+
+const EVENT_ATTRIBUTE_TAG: &str = "tag";
+const EVENT_ATTRIBUTE_NESTED: &str = "nested";
+
+fn concordium_event_worker(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    if !attr.is_empty() {
+        return Err(syn::Error::new(
+            proc_macro2::TokenStream::from(attr).span(),
+            "#[concordium_event] does not take any attributes.",
+        ));
+    }
+    let mut ast: syn::ItemEnum =
+        attach_error(syn::parse(item), "#[concordium_event] can only be applied to enums.")?;
+
+    let mut tags = Vec::new();
+    let mut used_tags = BTreeMap::new();
+    for (index, variant) in ast.variants.iter_mut().enumerate() {
+        let tag = parse_event_variant_tag(variant, index)?;
+        if let EventVariantTag::Tag(tag) = tag {
+            if let Some(existing) = used_tags.insert(tag, variant.ident.clone()) {
+                return Err(syn::Error::new(
+                    variant.ident.span(),
+                    format!("The tag {} is already used by the variant '{}'.", tag, existing),
+                ));
+            }
+        }
+        tags.push(tag);
+    }
+
+    let enum_ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clauses) = ast.generics.split_for_impl();
+
+    let mut serial_cases = proc_macro2::TokenStream::new();
+    for (variant, tag) in ast.variants.iter().zip(tags.iter()) {
+        let variant_ident = &variant.ident;
+        // The fields are bound to new identifiers to avoid shadowing the writer.
+        let field_idents: Vec<_> =
+            (0..variant.fields.len()).map(|i| format_ident!("field_{}", i)).collect();
+        let pattern = match &variant.fields {
+            syn::Fields::Named(fields) => {
+                let field_names = fields.named.iter().map(|field| &field.ident);
+                quote!({ #(#field_names: #field_idents),* })
+            }
+            syn::Fields::Unnamed(_) => quote!(( #(#field_idents),* )),
+            syn::Fields::Unit => quote!(),
+        };
+        let write_tag = match tag {
+            EventVariantTag::Tag(tag) => quote!(concordium_std::Write::write_u8(out, #tag)?;),
+            EventVariantTag::Nested => quote!(),
+        };
+        serial_cases.extend(quote! {
+            #enum_ident::#variant_ident #pattern => {
+                #write_tag
+                #(concordium_std::Serial::serial(#field_idents, out)?;)*
+                Ok(())
+            }
+        });
+    }
+
+    let schema_type = event_schema_type_tokens(&ast, &tags)?;
+    let gen = quote! {
+        #ast
+
+        #[automatically_derived]
+        impl #impl_generics concordium_std::Serial for #enum_ident #ty_generics #where_clauses {
+            fn serial<W: concordium_std::Write>(&self, out: &mut W) -> Result<(), W::Err> {
+                match self {
+                    #serial_cases
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics concordium_std::ContractEvent for #enum_ident #ty_generics #where_clauses {}
+
+        #schema_type
+    };
+    Ok(gen.into())
+}
+
+/// Parse the tag of an event variant from its `#[concordium(...)]`
+/// attributes, and remove these attributes from the variant.
+fn parse_event_variant_tag(
+    variant: &mut syn::Variant,
+    index: usize,
+) -> syn::Result<EventVariantTag> {
+    if let Some((_, discriminant)) = variant.discriminant.as_ref() {
+        return Err(syn::Error::new(
+            discriminant.span(),
+            "Explicit discriminants are not supported, use #[concordium(tag = ...)] instead.",
+        ));
+    }
+    if let Some(attr) = variant
+        .fields
+        .iter()
+        .flat_map(|field| field.attrs.iter())
+        .find(|attr| attr.path.is_ident(CONCORDIUM_ATTRIBUTE))
+    {
+        return Err(syn::Error::new(
+            attr.span(),
+            "Attributes on the fields of an event are not supported.",
+        ));
+    }
+
+    let mut concordium_attrs = Vec::new();
+    variant.attrs.retain(|attr| {
+        if attr.path.is_ident(CONCORDIUM_ATTRIBUTE) {
+            concordium_attrs.push(attr.clone());
+            false
+        } else {
+            true
+        }
+    });
+    let mut tag = None;
+    let mut nested = None;
+    for attr in concordium_attrs {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "Expected a list of attributes, e.g., #[concordium(tag = 0)].",
+                ))
+            }
+        };
+        for nested_meta in list.nested {
+            match nested_meta {
+                syn::NestedMeta::Meta(Meta::NameValue(mnv))
+                    if mnv.path.is_ident(EVENT_ATTRIBUTE_TAG) && tag.is_none() =>
+                {
+                    match &mnv.lit {
+                        syn::Lit::Int(int) => tag = Some(int.base10_parse::<u8>()?),
+                        other => {
+                            return Err(syn::Error::new(
+                                other.span(),
+                                "The tag of an event must be an integer between 0 and 255.",
+                            ))
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(Meta::Path(path))
+                    if path.is_ident(EVENT_ATTRIBUTE_NESTED) && nested.is_none() =>
+                {
+                    nested = Some(path.span())
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        "Unrecognized or duplicate attribute. The supported attributes are 'tag = \
+                         ...' and 'nested'.",
+                    ))
+                }
+            }
+        }
+    }
+
+    match (tag, nested) {
+        (Some(_), Some(span)) => {
+            Err(syn::Error::new(span, "A nested event variant cannot have a tag of its own."))
+        }
+        (Some(tag), None) => Ok(EventVariantTag::Tag(tag)),
+        (None, Some(span)) => match &variant.fields {
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Ok(EventVariantTag::Nested)
+            }
+            _ => Err(syn::Error::new(
+                span,
+                "A nested event variant must have exactly one unnamed field.",
+            )),
+        },
+        (None, None) => u8::try_from(index).map(EventVariantTag::Tag).map_err(|_| {
+            syn::Error::new(
+                variant.ident.span(),
+                "An event cannot have more than 256 variants without explicit tags.",
+            )
+        }),
+    }
+}
+
+/// Generate the implementation of `SchemaType` for an event, which is a
+/// tagged enum including the tags of nested events.
+#[cfg(feature = "build-schema")]
+fn event_schema_type_tokens(
+    ast: &syn::ItemEnum,
+    tags: &[EventVariantTag],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let enum_ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clauses) = ast.generics.split_for_impl();
+    let mut insert_variants = proc_macro2::TokenStream::new();
+    for (variant, tag) in ast.variants.iter().zip(tags.iter()) {
+        match tag {
+            EventVariantTag::Tag(tag) => {
+                let variant_name = variant.ident.to_string();
+                let fields_tokens = schema_type_fields(&variant.fields)?;
+                insert_variants.extend(quote! {
+                    event_map.insert(#tag, (concordium_std::String::from(#variant_name), #fields_tokens));
+                });
+            }
+            EventVariantTag::Nested => {
+                let nested_ty = &variant.fields.iter().next().unwrap().ty; // safe since nested variants have one field.
+                insert_variants.extend(quote! {
+                    match <#nested_ty as concordium_std::schema::SchemaType>::get_type() {
+                        concordium_std::schema::Type::TaggedEnum(nested) => event_map.extend(nested),
+                        _ => panic!("The schema of a nested event must be a tagged enum."),
+                    }
+                });
+            }
+        }
+    }
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics concordium_std::schema::SchemaType for #enum_ident #ty_generics #where_clauses {
+            fn get_type() -> concordium_std::schema::Type {
+                let mut event_map = concordium_std::collections::BTreeMap::new();
+                #insert_variants
+                concordium_std::schema::Type::TaggedEnum(event_map)
+            }
+        }
+    })
+}
+
+#[cfg(not(feature = "build-schema"))]
+fn event_schema_type_tokens(
+    _ast: &syn::ItemEnum,
+    _tags: &[EventVariantTag],
+) -> syn::Result<proc_macro2::TokenStream> {
+    Ok(proc_macro2::TokenStream::new())
+}
@@ -0,0 +1,129 @@
+//! A bump allocator for contracts, enabled with the `bump-alloc` feature.
+//!
+//! Allocation moves a pointer forward through the heap, growing the memory
+//! when it runs out, which takes only a few instructions and little code.
+//! Deallocation does not reuse memory, except that the most recent allocation
+//! can be freed or resized in place, and the whole heap is reset once all
+//! allocations have been freed. This suits contract invocations, which are
+//! short-lived and whose memory is discarded when they end, but a contract
+//! repeatedly allocating and freeing memory in a long loop will use memory
+//! proportional to the total amount allocated.
+use crate::cell::UnsafeCell;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    arch::wasm32,
+    ptr,
+};
+
+/// The size of a Wasm memory page in bytes.
+const PAGE_SIZE: usize = 65536;
+
+extern "C" {
+    /// The start of the heap, defined by the linker.
+    static __heap_base: u8;
+}
+
+/// A bump allocator. Only a single instance should be used, as the global
+/// allocator, since the instances would otherwise share the memory.
+pub struct BumpAllocator {
+    /// The address of the next free byte, or zero if no memory has been
+    /// allocated yet.
+    next:        UnsafeCell<usize>,
+    /// The number of allocations which have not been freed.
+    allocations: UnsafeCell<usize>,
+}
+
+// Wasm contracts are single-threaded.
+unsafe impl Sync for BumpAllocator {}
+
+impl BumpAllocator {
+    /// Create a new allocator, which starts allocating at the heap base.
+    pub const fn new() -> Self {
+        BumpAllocator {
+            next:        UnsafeCell::new(0),
+            allocations: UnsafeCell::new(0),
+        }
+    }
+
+    /// The start of the heap.
+    #[inline(always)]
+    fn heap_base() -> usize { unsafe { &__heap_base as *const u8 as usize } }
+
+    /// Ensure the memory extends to the given address, growing it if
+    /// needed. Returns whether it succeeded.
+    fn ensure_memory(end: usize) -> bool {
+        let memory_end = wasm32::memory_size(0) * PAGE_SIZE;
+        if end <= memory_end {
+            return true;
+        }
+        let pages = (end - memory_end + PAGE_SIZE - 1) / PAGE_SIZE;
+        wasm32::memory_grow(0, pages) != usize::MAX
+    }
+}
+
+impl Default for BumpAllocator {
+    fn default() -> Self { Self::new() }
+}
+
+unsafe impl GlobalAlloc for BumpAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let next = &mut *self.next.get();
+        if *next == 0 {
+            *next = Self::heap_base();
+        }
+        // The alignment is a power of two.
+        let start = match next.checked_add(layout.align() - 1) {
+            Some(n) => n & !(layout.align() - 1),
+            None => return ptr::null_mut(),
+        };
+        let end = match start.checked_add(layout.size()) {
+            Some(end) => end,
+            None => return ptr::null_mut(),
+        };
+        if !Self::ensure_memory(end) {
+            return ptr::null_mut();
+        }
+        *next = end;
+        *self.allocations.get() += 1;
+        start as *mut u8
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let next = &mut *self.next.get();
+        let allocations = &mut *self.allocations.get();
+        *allocations -= 1;
+        if *allocations == 0 {
+            // Everything has been freed, so the heap can be reused.
+            *next = Self::heap_base();
+        } else if ptr as usize + layout.size() == *next {
+            // The most recent allocation can be freed in place.
+            *next = ptr as usize;
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let next = &mut *self.next.get();
+        // Resize the most recent allocation in place.
+        if ptr as usize + layout.size() == *next {
+            let end = match (ptr as usize).checked_add(new_size) {
+                Some(end) => end,
+                None => return ptr::null_mut(),
+            };
+            if !Self::ensure_memory(end) {
+                return ptr::null_mut();
+            }
+            *next = end;
+            return ptr;
+        }
+        if new_size <= layout.size() {
+            return ptr;
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size());
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
@@ -1,11 +1,14 @@
 use crate::{
+    boxed::Box,
     cell::UnsafeCell,
+    collections::BTreeMap,
     convert::{self, TryInto},
     fmt,
     marker::PhantomData,
     mem, num,
     num::NonZeroU32,
     prims,
+    state_btree::*,
     traits::*,
     types::*,
     vec::Vec,
@@ -144,6 +147,24 @@ impl<T> From<CallContractError<T>> for Reject {
     }
 }
 
+/// Invocation failures are mapped as for [`CallContractError`], and failures to
+/// obtain or parse the return value are mapped as [`ParseError`].
+impl<T> From<CallContractTypedError<T>> for Reject {
+    #[inline(always)]
+    fn from(cce: CallContractTypedError<T>) -> Self {
+        match cce {
+            CallContractTypedError::Call(error) => error.into(),
+            CallContractTypedError::MissingReturnValue
+            | CallContractTypedError::ParseReturnValue => ParseError::default().into(),
+        }
+    }
+}
+
+impl<T> From<CallContractError<T>> for CallContractTypedError<T> {
+    #[inline(always)]
+    fn from(cce: CallContractError<T>) -> Self { CallContractTypedError::Call(cce) }
+}
+
 /// MissingModule is i32::MIN + 22,
 /// MissingContract is i32::MIN + 23,
 /// UnsupportedModuleVersion is i32::MIN + 24.
@@ -180,6 +201,31 @@ impl From<QueryContractBalanceError> for Reject {
     }
 }
 
+/// Query account public keys error missing account is i32::MIN + 27.
+impl From<QueryAccountPublicKeysError> for Reject {
+    #[inline(always)]
+    fn from(_: QueryAccountPublicKeysError) -> Self {
+        unsafe { crate::num::NonZeroI32::new_unchecked(i32::MIN + 27).into() }
+    }
+}
+
+/// Check account signature error codes:
+/// MissingAccount is i32::MIN + 28,
+/// MalformedData is i32::MIN + 29.
+impl From<CheckAccountSignatureError> for Reject {
+    #[inline(always)]
+    fn from(error: CheckAccountSignatureError) -> Self {
+        match error {
+            CheckAccountSignatureError::MissingAccount => unsafe {
+                crate::num::NonZeroI32::new_unchecked(i32::MIN + 28).into()
+            },
+            CheckAccountSignatureError::MalformedData => unsafe {
+                crate::num::NonZeroI32::new_unchecked(i32::MIN + 29).into()
+            },
+        }
+    }
+}
+
 /// Return values are intended to be produced by writing to the
 /// [ExternReturnValue] buffer, either in a high-level interface via
 /// serialization, or in a low-level interface by manually using the [Write]
@@ -777,6 +823,11 @@ const GENERIC_MAP_PREFIX: u64 = 1;
 /// Initial location to store in [NEXT_ITEM_PREFIX_KEY]. For example, the
 /// initial call to "new_state_box" will allocate the box at this location.
 pub(crate) const INITIAL_NEXT_ITEM_PREFIX: [u8; 8] = 2u64.to_le_bytes();
+/// The key at which a [`StateSet`] stores its number of elements is this,
+/// followed by the prefix of the set. Item prefixes are allocated starting
+/// from [INITIAL_NEXT_ITEM_PREFIX], so the key never starts with the prefix of
+/// a collection, and cannot collide with an element of any set.
+const SET_LEN_KEY_PREFIX: [u8; 8] = NEXT_ITEM_PREFIX_KEY;
 
 impl HasStateApi for ExternStateApi {
     type EntryType = StateEntry;
@@ -935,6 +986,20 @@ where
     }
 
     /// Get an entry for the given key.
+    ///
+    /// This looks up the key only once, which makes it the preferred way of
+    /// updating a value which might not be in the map yet. For example,
+    /// incrementing a counter per account:
+    ///
+    /// ```no_run
+    /// # use concordium_std::*;
+    /// fn increment<S: HasStateApi>(
+    ///     counters: &mut StateMap<AccountAddress, u64, S>,
+    ///     account: AccountAddress,
+    /// ) {
+    ///     *counters.entry(account).or_insert(0) += 1;
+    /// }
+    /// ```
     pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
         let key_bytes = self.key_with_map_prefix(&key);
         // Unwrapping is safe because iter() holds a reference to the stateset.
@@ -1242,16 +1307,15 @@ where
         let key_bytes = self.key_with_set_prefix(&value);
         match self.state_api.entry(key_bytes) {
             EntryRaw::Vacant(vac) => {
+                let len = self.len();
                 let _ = vac.insert_raw(&[]);
+                self.set_len(len.saturating_add(1));
                 true
             }
             EntryRaw::Occupied(_) => false,
         }
     }
 
-    /// Returns `true` if the set contains no elements.
-    pub fn is_empty(&self) -> bool { self.state_api.lookup_entry(&self.prefix).is_none() }
-
     /// Returns `true` if the set contains a value.
     pub fn contains(&self, value: &T) -> bool {
         let key_bytes = self.key_with_set_prefix(value);
@@ -1268,6 +1332,7 @@ where
         // serializable values cannot have pointers to other parts of state.
         // Unwrapping is safe when only using the high-level API.
         self.state_api.delete_prefix(&self.prefix).unwrap_abort();
+        self.state_api.delete_prefix(&self.len_key()).unwrap_abort();
     }
 
     /// Removes a value from the set. Returns whether the value was present in
@@ -1279,13 +1344,31 @@ where
         match self.state_api.entry(key_bytes) {
             EntryRaw::Vacant(_) => false,
             EntryRaw::Occupied(occ) => {
+                let len = self.len();
                 // Unwrapping is safe, because iter() keeps a reference to the stateset.
                 self.state_api.delete_entry(occ.get()).unwrap_abort();
+                self.set_len(len.saturating_sub(1));
                 true
             }
         }
     }
 
+    /// Returns `true` if every element of the set is also in the `other` set.
+    pub fn is_subset(&self, other: &StateSet<T, S>) -> bool
+    where
+        T: DeserialWithState<S>, {
+        self.len() <= other.len() && self.iter().all(|value| other.contains(&value))
+    }
+
+    /// Adds every element of the `other` set to the set.
+    pub fn union_with(&mut self, other: &StateSet<T, S>)
+    where
+        T: DeserialWithState<S>, {
+        for value in other.values() {
+            self.insert(value);
+        }
+    }
+
     fn key_with_set_prefix(&self, key: &T) -> Vec<u8> {
         let mut key_with_prefix = self.prefix.to_vec();
         key.serial(&mut key_with_prefix).unwrap_abort();
@@ -1320,6 +1403,61 @@ impl<T, S: HasStateApi> StateSet<T, S> {
             _ => crate::trap(),
         }
     }
+
+    /// Get an iterator over the elements in the `StateSet`, in the same order
+    /// as [`iter`](Self::iter), which returns the elements by value rather
+    /// than as [`StateRef`]s. This avoids cloning the elements when they are
+    /// needed by value, e.g., when collecting them or inserting them into
+    /// another collection.
+    pub fn values(&self) -> StateSetValues<T, S> {
+        StateSetValues {
+            iter: self.iter(),
+        }
+    }
+
+    /// Returns `true` if the set contains no elements.
+    ///
+    /// Elements are stored at keys starting with the prefix of the set, so
+    /// this looks for any entry with the prefix.
+    pub fn is_empty(&self) -> bool { self.iter().state_iter.is_none() }
+
+    /// Returns the number of elements in the set.
+    ///
+    /// The number is maintained on insertion and removal, so this does not
+    /// iterate over the set. Sets written by an earlier version of this
+    /// library have no stored number until their first insertion or removal,
+    /// and until then this counts the entries of the set, without
+    /// deserializing the elements.
+    pub fn len(&self) -> u32 {
+        match self.state_api.lookup_entry(&self.len_key()) {
+            // Unwrapping is safe when only using the high-level API.
+            Some(mut entry) => u32::deserial(&mut entry).unwrap_abort(),
+            None => match self.iter().state_iter.as_mut() {
+                Some(state_iter) => state_iter.count() as u32,
+                None => 0,
+            },
+        }
+    }
+
+    /// Store the number of elements in the entry owned by the set.
+    fn set_len(&mut self, len: u32) {
+        match self.state_api.entry(self.len_key()) {
+            EntryRaw::Vacant(vac) => {
+                // Unwrapping is safe, because iterators do not lock this entry.
+                vac.insert(&len).unwrap_abort();
+            }
+            EntryRaw::Occupied(mut occ) => occ.insert(&len),
+        }
+    }
+
+    /// The key of the entry holding the number of elements, see
+    /// [SET_LEN_KEY_PREFIX].
+    fn len_key(&self) -> [u8; 16] {
+        let mut key = [0u8; 16];
+        key[..8].copy_from_slice(&SET_LEN_KEY_PREFIX);
+        key[8..].copy_from_slice(&self.prefix);
+        key
+    }
 }
 
 impl<T: Serial, S: HasStateApi> StateBox<T, S> {
@@ -1452,6 +1590,39 @@ where
         mem::replace(value, new_val)
     }
 
+    /// Set the value. Overwrites the existing one.
+    /// In contrast to [`replace`](Self::replace), this does not load the
+    /// existing value if it is not yet loaded. Note that if the type `T`
+    /// contains references to state, e.g., is a [`StateBox`], then the
+    /// existing value is not deleted, so prefer [`replace`](Self::replace)
+    /// in that case to avoid space leaks.
+    pub fn set(&mut self, new_val: T) {
+        let inner = self.inner.get_mut();
+        match inner {
+            StateBoxInner::Loaded {
+                entry,
+                modified,
+                value,
+            } => {
+                entry.move_to_start();
+                new_val.serial(entry).unwrap_abort();
+                *value = new_val;
+                *modified = false;
+            }
+            StateBoxInner::Reference {
+                prefix,
+            } => {
+                let mut entry = self.state_api.lookup_entry(prefix).unwrap_abort();
+                new_val.serial(&mut entry).unwrap_abort();
+                *inner = StateBoxInner::Loaded {
+                    entry,
+                    modified: false,
+                    value: new_val,
+                };
+            }
+        }
+    }
+
     /// Update the existing value with the given function.
     /// The supplied function may return some data, which is then returned by
     /// [`update`](Self::update).
@@ -1548,6 +1719,16 @@ where
     }
 }
 
+impl<'a, T, S: HasStateApi> Iterator for StateSetValues<'a, T, S>
+where
+    T: DeserialWithState<S>,
+{
+    type Item = T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> { self.iter.next().map(|value| value.value) }
+}
+
 // # Trait implementations for Parameter
 
 impl Default for ExternParameter {
@@ -1814,6 +1995,12 @@ const INVOKE_QUERY_CONTRACT_BALANCE_TAG: u32 = 3;
 /// Tag of the query exchange rates operation expected by the host. See
 /// [prims::invoke].
 const INVOKE_QUERY_EXCHANGE_RATES_TAG: u32 = 4;
+/// Tag of the query account public keys operation expected by the host. See
+/// [prims::invoke].
+const INVOKE_QUERY_ACCOUNT_PUBLIC_KEYS_TAG: u32 = 5;
+/// Tag of the check account signature operation expected by the host. See
+/// [prims::invoke].
+const INVOKE_CHECK_ACCOUNT_SIGNATURE_TAG: u32 = 6;
 
 /// Check whether the response code from calling `invoke` is encoding a failure
 /// and map out the byte used for the error code.
@@ -1982,6 +2169,48 @@ fn parse_query_contract_balance_response_code(
     }
 }
 
+/// Decode the account public keys response code.
+///
+/// - Success if the last 5 bytes are all zero:
+///   - the first 3 bytes encodes the return value index.
+/// - In case of failure the 4th byte is used, and encodes the enviroment
+///   failure where:
+///    - '0x02' encodes missing account.
+fn parse_query_account_public_keys_response_code(
+    code: u64,
+) -> Result<ExternCallResponse, QueryAccountPublicKeysError> {
+    if let Some(error_code) = get_invoke_failure_code(code) {
+        if error_code == 0x02 {
+            Err(QueryAccountPublicKeysError)
+        } else {
+            unsafe { crate::hint::unreachable_unchecked() }
+        }
+    } else {
+        // Map out the 3 bytes encoding the return value index.
+        let return_value_index = NonZeroU32::new((code >> 40) as u32).unwrap_abort();
+        Ok(ExternCallResponse::new(return_value_index))
+    }
+}
+
+/// Decode the check account signature response code.
+///
+/// - Success if the last 5 bytes are all zero, in which case the signatures are
+///   valid.
+/// - In case of failure the 4th byte is used, and encodes the enviroment
+///   failure where:
+///    - '0x02' encodes missing account.
+///    - '0x0a' encodes that the signatures could not be parsed.
+///    - '0x0b' encodes that the signatures are not valid.
+fn parse_check_account_signature_response_code(code: u64) -> CheckAccountSignatureResult {
+    match get_invoke_failure_code(code) {
+        None => Ok(true),
+        Some(0x02) => Err(CheckAccountSignatureError::MissingAccount),
+        Some(0x0a) => Err(CheckAccountSignatureError::MalformedData),
+        Some(0x0b) => Ok(false),
+        Some(_) => unsafe { crate::hint::unreachable_unchecked() },
+    }
+}
+
 /// Decode the exchange rate response code.
 ///
 /// - Success if the last 5 bytes are all zero:
@@ -1999,6 +2228,34 @@ fn parse_query_exchange_rates_response_code(code: u64) -> ExternCallResponse {
     }
 }
 
+/// Print a debug message along with its location. Used by the
+/// [`concordium_dbg!`](crate::concordium_dbg) macro, not meant to be called
+/// directly.
+#[doc(hidden)]
+#[cfg(any(feature = "debug", not(target_arch = "wasm32")))]
+pub fn debug_print(message: fmt::Arguments, filename: &str, line: u32, column: u32) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let mut buffer = String::new();
+        // Writing to a string cannot fail.
+        let _ = fmt::Write::write_fmt(&mut buffer, message);
+        unsafe {
+            prims::debug_print(
+                buffer.as_ptr(),
+                buffer.len() as u32,
+                filename.as_ptr(),
+                filename.len() as u32,
+                line,
+                column,
+            )
+        };
+    }
+    #[cfg(all(not(target_arch = "wasm32"), feature = "std"))]
+    eprintln!("[{}:{}:{}] {}", filename, line, column, message);
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "std")))]
+    let _ = (message, filename, line, column);
+}
+
 /// Helper factoring out the common behaviour of invoke_transfer for the two
 /// extern hosts below.
 fn invoke_transfer_worker(receiver: &AccountAddress, amount: Amount) -> TransferResult {
@@ -2060,6 +2317,42 @@ fn query_contract_balance_worker(address: &ContractAddress) -> QueryContractBala
     Ok(Amount::deserial(&mut return_value).unwrap_abort())
 }
 
+/// Helper factoring out the common behaviour of account_public_keys for the
+/// two extern hosts below.
+fn query_account_public_keys_worker(address: &AccountAddress) -> QueryAccountPublicKeysResult {
+    let response = unsafe {
+        prims::invoke(
+            INVOKE_QUERY_ACCOUNT_PUBLIC_KEYS_TAG,
+            AsRef::<[u8]>::as_ref(&address).as_ptr(),
+            32,
+        )
+    };
+    let mut return_value = parse_query_account_public_keys_response_code(response)?;
+    Ok(AccountPublicKeys::deserial(&mut return_value).unwrap_abort())
+}
+
+/// Helper factoring out the common behaviour of check_account_signature for
+/// the two extern hosts below.
+fn check_account_signature_worker(
+    address: &AccountAddress,
+    signatures: &AccountSignatures,
+    data: &[u8],
+) -> CheckAccountSignatureResult {
+    let mut parameter = Vec::with_capacity(ACCOUNT_ADDRESS_SIZE + 4 + data.len());
+    parameter.extend_from_slice(address.as_ref());
+    parameter.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    parameter.extend_from_slice(data);
+    signatures.serial(&mut parameter).unwrap_abort();
+    let response = unsafe {
+        prims::invoke(
+            INVOKE_CHECK_ACCOUNT_SIGNATURE_TAG,
+            parameter.as_ptr(),
+            parameter.len() as u32,
+        )
+    };
+    parse_check_account_signature_response_code(response)
+}
+
 /// Helper factoring out the common behaviour of exchange_rates for the
 /// two extern hosts below.
 fn query_exchange_rates_worker() -> ExchangeRates {
@@ -2094,6 +2387,24 @@ where
         StateSet::open(self.state_api.clone(), prefix)
     }
 
+    /// Create a new empty [`StateBTreeSet`].
+    pub fn new_btree_set<K>(&mut self) -> StateBTreeSet<K, S> {
+        StateBTreeSet::open(self.new_map())
+    }
+
+    /// Create a new empty [`StateBTreeMap`].
+    pub fn new_btree_map<K, V>(&mut self) -> StateBTreeMap<K, V, S> {
+        let key_value = self.new_map();
+        let key_order = self.new_btree_set();
+        StateBTreeMap::open(key_value, key_order)
+    }
+
+    /// Create a new empty [`StateDeque`].
+    pub fn new_deque<T>(&mut self) -> StateDeque<T, S> { StateDeque::open(self.new_map()) }
+
+    /// Create a new empty [`StateVec`].
+    pub fn new_vec<T>(&mut self) -> StateVec<T, S> { StateVec::open(self.new_map()) }
+
     /// Create a new [`StateBox`] and insert the `value` into the state.
     /// This stores the serialized value in the contract state. Thus **if the
     /// `StateBox` is dropped without calling [`delete`](StateBox::delete)
@@ -2277,6 +2588,21 @@ where
         query_contract_balance_worker(&address)
     }
 
+    #[inline(always)]
+    fn account_public_keys(&self, address: AccountAddress) -> QueryAccountPublicKeysResult {
+        query_account_public_keys_worker(&address)
+    }
+
+    #[inline(always)]
+    fn check_account_signature(
+        &self,
+        address: AccountAddress,
+        signatures: &AccountSignatures,
+        data: &[u8],
+    ) -> CheckAccountSignatureResult {
+        check_account_signature_worker(&address, signatures, data)
+    }
+
     #[inline(always)]
     fn exchange_rates(&self) -> ExchangeRates { query_exchange_rates_worker() }
 
@@ -2342,6 +2668,21 @@ impl HasHost<ExternStateApi> for ExternLowLevelHost {
         query_contract_balance_worker(&address)
     }
 
+    #[inline(always)]
+    fn account_public_keys(&self, address: AccountAddress) -> QueryAccountPublicKeysResult {
+        query_account_public_keys_worker(&address)
+    }
+
+    #[inline(always)]
+    fn check_account_signature(
+        &self,
+        address: AccountAddress,
+        signatures: &AccountSignatures,
+        data: &[u8],
+    ) -> CheckAccountSignatureResult {
+        check_account_signature_worker(&address, signatures, data)
+    }
+
     #[inline(always)]
     fn exchange_rates(&self) -> ExchangeRates { query_exchange_rates_worker() }
 
@@ -2621,6 +2962,88 @@ impl<A: fmt::Debug, E> ExpectErrReport for Result<A, E> {
     }
 }
 
+impl AmountArithmetic for Amount {
+    #[inline]
+    fn checked_add(self, other: Self) -> Option<Self> {
+        self.micro_ccd.checked_add(other.micro_ccd).map(Amount::from_micro_ccd)
+    }
+
+    #[inline]
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        self.micro_ccd.checked_sub(other.micro_ccd).map(Amount::from_micro_ccd)
+    }
+
+    #[inline]
+    fn checked_mul(self, factor: u64) -> Option<Self> {
+        self.micro_ccd.checked_mul(factor).map(Amount::from_micro_ccd)
+    }
+
+    fn mul_ratio(self, numerator: u64, denominator: u64) -> Option<Self> {
+        let product = u128::from(self.micro_ccd) * u128::from(numerator);
+        let result = product.checked_div(u128::from(denominator))?;
+        result.try_into().ok().map(Amount::from_micro_ccd)
+    }
+}
+
+impl TimestampArithmetic for Timestamp {
+    fn has_elapsed(self, duration: Duration, now: Timestamp) -> bool {
+        self.checked_add(duration).map_or(false, |end| now >= end)
+    }
+
+    fn time_remaining(self, duration: Duration, now: Timestamp) -> Duration {
+        let end = self.timestamp_millis().saturating_add(duration.millis());
+        Duration::from_millis(end.saturating_sub(now.timestamp_millis()))
+    }
+}
+
+impl BasisPoints {
+    /// The largest number of basis points, which is 100%.
+    pub const MAX: BasisPoints = BasisPoints(10_000);
+
+    /// Construct the fraction from a number of basis points. Returns [`None`]
+    /// if the number is above 10000.
+    pub fn new(basis_points: u16) -> Option<Self> {
+        if basis_points <= Self::MAX.0 {
+            Some(BasisPoints(basis_points))
+        } else {
+            None
+        }
+    }
+
+    /// Return the number of basis points.
+    #[inline(always)]
+    pub fn get(self) -> u16 { self.0 }
+
+    /// Compute the fraction of the amount, rounded down. The result is at most
+    /// the amount.
+    pub fn of(self, amount: Amount) -> Amount {
+        // Unwrapping is safe since the numerator is at most the denominator,
+        // so the result is at most the amount.
+        amount.mul_ratio(u64::from(self.0), u64::from(Self::MAX.0)).unwrap_abort()
+    }
+
+    /// Add two fractions, returning [`None`] if the sum is above 100%.
+    pub fn checked_add(self, other: Self) -> Option<Self> { Self::new(self.0 + other.0) }
+
+    /// Return the remaining fraction up to 100%, e.g., the share of the seller
+    /// after a fee.
+    pub fn complement(self) -> Self { BasisPoints(Self::MAX.0 - self.0) }
+}
+
+impl Serial for BasisPoints {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> { self.0.serial(out) }
+}
+
+impl Deserial for BasisPoints {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        BasisPoints::new(source.get()?).ok_or_else(ParseError::default)
+    }
+}
+
+impl schema::SchemaType for BasisPoints {
+    fn get_type() -> schema::Type { schema::Type::U16 }
+}
+
 impl<A> UnwrapAbort for Option<A> {
     type Unwrap = A;
 
@@ -2754,10 +3177,11 @@ where
 {
     fn delete(mut self) {
         // Statesets cannot contain state types (e.g. StateBox), so there is nothing to
-        // delete, apart from the set itself.
+        // delete, apart from the set itself and its number of elements.
 
         // Unwrapping is safe when only using the high-level API.
         self.state_api.delete_prefix(&self.prefix).unwrap_abort();
+        self.state_api.delete_prefix(&self.len_key()).unwrap_abort();
     }
 }
 
@@ -2826,6 +3250,238 @@ impl schema::SchemaType for SignatureEcdsaSecp256k1 {
     fn get_type() -> concordium_contracts_common::schema::Type { schema::Type::ByteArray(64) }
 }
 
+impl Serial for PublicKey {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        match self {
+            PublicKey::Ed25519(key) => {
+                out.write_u8(0)?;
+                key.serial(out)
+            }
+        }
+    }
+}
+
+impl Deserial for PublicKey {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        match source.read_u8()? {
+            0 => Ok(PublicKey::Ed25519(PublicKeyEd25519::deserial(source)?)),
+            _ => Err(ParseError::default()),
+        }
+    }
+}
+
+/// Serialize a map of keys and credentials of an account, which is prefixed
+/// by its length in a single byte.
+fn serial_key_map<V: Serial, W: Write>(map: &BTreeMap<u8, V>, out: &mut W) -> Result<(), W::Err> {
+    let len: u8 = map.len().try_into().map_err(|_| W::Err::default())?;
+    out.write_u8(len)?;
+    for (index, value) in map.iter() {
+        out.write_u8(*index)?;
+        value.serial(out)?;
+    }
+    Ok(())
+}
+
+/// Deserialize a map of keys and credentials of an account, which is prefixed
+/// by its length in a single byte. The indices must be strictly increasing.
+fn deserial_key_map<V: Deserial, R: Read>(source: &mut R) -> ParseResult<BTreeMap<u8, V>> {
+    let len = source.read_u8()?;
+    let mut map = BTreeMap::new();
+    let mut previous = None;
+    for _ in 0..len {
+        let index = source.read_u8()?;
+        if previous.map_or(false, |previous| previous >= index) {
+            return Err(ParseError::default());
+        }
+        previous = Some(index);
+        map.insert(index, V::deserial(source)?);
+    }
+    Ok(map)
+}
+
+/// Deserialize a threshold, which must be non-zero.
+fn deserial_threshold<R: Read>(source: &mut R) -> ParseResult<num::NonZeroU8> {
+    num::NonZeroU8::new(source.read_u8()?).ok_or_else(ParseError::default)
+}
+
+impl Serial for CredentialPublicKeys {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        serial_key_map(&self.keys, out)?;
+        out.write_u8(self.threshold.get())
+    }
+}
+
+impl Deserial for CredentialPublicKeys {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        Ok(CredentialPublicKeys {
+            keys:      deserial_key_map(source)?,
+            threshold: deserial_threshold(source)?,
+        })
+    }
+}
+
+impl Serial for AccountPublicKeys {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        serial_key_map(&self.keys, out)?;
+        out.write_u8(self.threshold.get())
+    }
+}
+
+impl Deserial for AccountPublicKeys {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        Ok(AccountPublicKeys {
+            keys:      deserial_key_map(source)?,
+            threshold: deserial_threshold(source)?,
+        })
+    }
+}
+
+impl Serial for Signature {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        match self {
+            Signature::Ed25519(signature) => {
+                out.write_u8(0)?;
+                signature.serial(out)
+            }
+        }
+    }
+}
+
+impl Deserial for Signature {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        match source.read_u8()? {
+            0 => Ok(Signature::Ed25519(SignatureEd25519::deserial(source)?)),
+            _ => Err(ParseError::default()),
+        }
+    }
+}
+
+impl schema::SchemaType for Signature {
+    fn get_type() -> schema::Type {
+        schema::Type::Enum(crate::vec![(
+            String::from("Ed25519"),
+            schema::Fields::Unnamed(crate::vec![schema::Type::ByteArray(64)]),
+        )])
+    }
+}
+
+impl Serial for CredentialSignatures {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        serial_key_map(&self.sigs, out)
+    }
+}
+
+impl Deserial for CredentialSignatures {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        Ok(CredentialSignatures {
+            sigs: deserial_key_map(source)?,
+        })
+    }
+}
+
+impl schema::SchemaType for CredentialSignatures {
+    fn get_type() -> schema::Type {
+        schema::Type::Map(
+            schema::SizeLength::U8,
+            Box::new(schema::Type::U8),
+            Box::new(<Signature as schema::SchemaType>::get_type()),
+        )
+    }
+}
+
+impl Serial for AccountSignatures {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        serial_key_map(&self.sigs, out)
+    }
+}
+
+impl Deserial for AccountSignatures {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        Ok(AccountSignatures {
+            sigs: deserial_key_map(source)?,
+        })
+    }
+}
+
+impl schema::SchemaType for AccountSignatures {
+    fn get_type() -> schema::Type {
+        schema::Type::Map(
+            schema::SizeLength::U8,
+            Box::new(schema::Type::U8),
+            Box::new(<CredentialSignatures as schema::SchemaType>::get_type()),
+        )
+    }
+}
+
+impl PermitMessage {
+    /// Create a message for calling the entrypoint with the given parameter,
+    /// which is serialized into the payload.
+    pub fn new<P: Serial>(
+        contract_address: ContractAddress,
+        nonce: u64,
+        timestamp: Timestamp,
+        entry_point: OwnedEntrypointName,
+        parameter: &P,
+    ) -> Self {
+        PermitMessage {
+            contract_address,
+            nonce,
+            timestamp,
+            entry_point,
+            payload: to_bytes(parameter),
+        }
+    }
+
+    /// Deserialize the payload as the parameter of the entrypoint.
+    pub fn parameter<P: Deserial>(&self) -> ParseResult<P> { from_bytes(&self.payload) }
+}
+
+impl Serial for PermitMessage {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        self.contract_address.serial(out)?;
+        self.nonce.serial(out)?;
+        self.timestamp.serial(out)?;
+        self.entry_point.serial(out)?;
+        let len: u16 = self.payload.len().try_into().map_err(|_| W::Err::default())?;
+        len.serial(out)?;
+        out.write_all(&self.payload)
+    }
+}
+
+impl Deserial for PermitMessage {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        let contract_address = source.get()?;
+        let nonce = source.get()?;
+        let timestamp = source.get()?;
+        let entry_point = source.get()?;
+        let len: u16 = source.get()?;
+        let mut payload = crate::vec![0; len.into()];
+        source.read_exact(&mut payload)?;
+        Ok(PermitMessage {
+            contract_address,
+            nonce,
+            timestamp,
+            entry_point,
+            payload,
+        })
+    }
+}
+
+impl schema::SchemaType for PermitMessage {
+    fn get_type() -> schema::Type {
+        schema::Type::Struct(schema::Fields::Named(crate::vec![
+            (String::from("contract_address"), <ContractAddress as schema::SchemaType>::get_type()),
+            (String::from("nonce"), <u64 as schema::SchemaType>::get_type()),
+            (String::from("timestamp"), <Timestamp as schema::SchemaType>::get_type()),
+            (String::from("entry_point"), <OwnedEntrypointName as schema::SchemaType>::get_type()),
+            (
+                String::from("payload"),
+                schema::Type::List(schema::SizeLength::U16, Box::new(schema::Type::U8)),
+            ),
+        ]))
+    }
+}
+
 impl Serial for HashSha2256 {
     fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> { self.0.serial(out) }
 }
@@ -25,8 +25,9 @@
 //! without many further allocations. Frequent small allocations will have bad
 //! performance, and should be avoided.
 //!
-//! In the future it will be possible to opt-out of the global allocator via a
-//! feature.
+//! Alternatively, the [`bump-alloc`](#bump-alloc-use-a-bump-allocator) feature
+//! selects a bump allocator, which is smaller and faster still, at the cost of
+//! not reusing freed memory.
 //!
 //! # Panic handler
 //! When compiled without the `std` feature this crate sets the panic handler
@@ -41,8 +42,11 @@
 //! This library has the following features:
 //! [`std`](#std-build-with-the-rust-standard-library),
 //! [`build-schema`](#build-schema-build-for-generating-a-module-schema),
-//! [`wasm-test`](#wasm-test-build-for-testing-in-wasm), and
-//! [`crypto-primitives`][crypto-feature].
+//! [`wasm-test`](#wasm-test-build-for-testing-in-wasm),
+//! [`crypto-primitives`][crypto-feature],
+//! [`serde`](#serde-json-serialization-of-chain-types),
+//! [`debug`](#debug-print-debug-messages-from-wasm), and
+//! [`bump-alloc`](#bump-alloc-use-a-bump-allocator).
 //!
 //! [crypto-feature]:
 //! #crypto-primitives-for-testing-crypto-with-actual-implementations
@@ -110,6 +114,45 @@
 //! `crypto-primitives` feature when targeting `wasm32-unknown-unknown`.
 //! The issue arises when compiling the [`secp256k1`](https://docs.rs/secp256k1/latest/secp256k1/) crate.
 //!
+//! ## `serde`: JSON serialization of chain types
+//! Build with this feature to implement the `Serialize` and `Deserialize`
+//! traits of [serde](https://serde.rs) for the base chain types, such as
+//! [`AccountAddress`], [`ContractAddress`], [`Amount`], [`Timestamp`] and
+//! [`Duration`], using their canonical string encodings, e.g., base58check
+//! for account addresses and a string of microCCD for amounts. This is
+//! intended for off-chain code, such as tests and fixtures reading and
+//! writing JSON, and it requires the `std` feature.
+//!
+//! ## `debug`: Print debug messages from Wasm
+//! **WARNING** Building with this feature enabled is meant for debugging, and
+//! the result cannot be deployed on chain.
+//!
+//! The [`concordium_dbg!`] macro prints a message, along with the location of
+//! the macro call, which is useful for tracing values while developing a
+//! contract. In native unit tests the message is printed to the standard error,
+//! which the test runner captures and shows for failing tests. When compiled
+//! to Wasm, the macro does nothing unless the `debug` feature is enabled, in
+//! which case the message is passed to the `debug_print` host function, which
+//! `cargo-concordium` provides when running tests and invoking contracts
+//! locally. The chain does not provide the host function, so a module built
+//! with the feature cannot be deployed.
+//!
+//! ## `bump-alloc`: Use a bump allocator
+//! Build with this feature to use a bump allocator as the global allocator
+//! when compiling to Wasm, instead of `wee_alloc`. Allocation only moves a
+//! pointer forward, and freed memory is not reused, except for the most recent
+//! allocation and once everything has been freed. Since an invocation of a
+//! contract is short-lived and its memory is discarded afterwards, this is
+//! usually a good trade: the allocator adds less code to the module and
+//! allocation costs less energy. Contracts which allocate and free a lot of
+//! memory in a loop, e.g., by building a new vector in every iteration, can
+//! however use considerably more memory, and should measure the energy cost
+//! with both allocators using `cargo concordium run`. The script
+//! `examples/bump-alloc/compare-allocators.sh` in the repository of this
+//! library does so for some of the examples, along with the module sizes.
+//!
+//! The feature has no effect on other targets, such as native unit tests.
+//!
 //! # Traits
 //! To support testing of smart contracts most of the functionality is
 //! accessible via traits. This library generally provides two implementations
@@ -133,6 +176,8 @@
 //! - [HasHost] for invoking operations on the host and accessing the state
 //! - [HasCryptoPrimitives] for using cryptographic primitives such as hashing
 //!   and signature verification.
+//! - [HasRandomness] for writing logic that depends on pseudo-random numbers in
+//!   a way that can be tested deterministically.
 //!
 //! # Signalling errors
 //! On the Wasm level contracts can signal errors by returning a negative i32
@@ -151,7 +196,9 @@
 //! With respect to error **codes**, the intention is that smart contract
 //! writers will write their own custom, precise, error types and either
 //! manually implement `Reject: From<E>` for their type `E`, or use the [Reject
-//! macro](./derive.Reject.html) which supports the common use cases.
+//! macro](./derive.Reject.html) which supports the common use cases. The macro
+//! assigns error codes by the position of the variants, unless a variant is
+//! given a stable error code with the `#[reject(code = ...)]` attribute.
 //!
 //! In addition to the custom errors that signal contract-specific error
 //! conditions this library provides some common error cases that most contracts
@@ -186,6 +233,9 @@
 //! | [UpgradeError::UnsupportedModuleVersion] | `-2147483624` |
 //! | [QueryAccountBalanceError] | `-2147483623` |
 //! | [QueryContractBalanceError] | `-2147483622` |
+//! | [QueryAccountPublicKeysError] | `-2147483621` |
+//! | [CheckAccountSignatureError::MissingAccount] | `-2147483620` |
+//! | [CheckAccountSignatureError::MalformedData] | `-2147483619` |
 //!
 //! [1]: https://doc.rust-lang.org/std/primitive.unit.html
 //! Other error codes may be added in the future and custom error codes should
@@ -235,8 +285,8 @@ fn abort_panic(_info: &core::panic::PanicInfo) -> ! {
 /// Re-export.
 #[cfg(not(feature = "std"))]
 pub use alloc::{
-    borrow::ToOwned, boxed, boxed::Box, format, rc, string, string::String, string::ToString, vec,
-    vec::Vec,
+    borrow::Cow, borrow::ToOwned, boxed, boxed::Box, format, rc, string, string::String,
+    string::ToString, vec, vec::Vec,
 };
 /// Re-export.
 #[cfg(not(feature = "std"))]
@@ -247,8 +297,8 @@ pub(crate) use std::vec;
 /// Re-export.
 #[cfg(feature = "std")]
 pub use std::{
-    boxed, boxed::Box, cell, cmp, convert, fmt, hash, hint, iter, marker, mem, num, ops, rc,
-    string::String, vec::Vec,
+    borrow::Cow, boxed, boxed::Box, cell, cmp, convert, fmt, hash, hint, iter, marker, mem, num,
+    ops, rc, string::String, vec::Vec,
 };
 
 /// Re-export.
@@ -262,20 +312,35 @@ pub mod collections {
     pub use concordium_contracts_common::{HashMap, HashSet};
 }
 
+#[cfg(all(feature = "bump-alloc", target_arch = "wasm32"))]
+pub mod bump_alloc;
 pub mod constants;
 mod impls;
+pub mod merkle;
 pub mod prims;
+mod state_btree;
+mod state_deque;
+mod state_vec;
 mod traits;
 mod types;
 pub use concordium_contracts_common::*;
 pub use concordium_std_derive::*;
 pub use impls::*;
+pub use state_btree::*;
+pub use state_deque::*;
+pub use state_vec::*;
 pub use traits::*;
 pub use types::*;
 
 extern crate wee_alloc;
 // Use `wee_alloc` as the global allocator to reduce code size.
+#[cfg(not(all(feature = "bump-alloc", target_arch = "wasm32")))]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+// Use the bump allocator instead when selected.
+#[cfg(all(feature = "bump-alloc", target_arch = "wasm32"))]
+#[global_allocator]
+static ALLOC: bump_alloc::BumpAllocator = bump_alloc::BumpAllocator::new();
+
 pub mod test_infrastructure;
@@ -0,0 +1,170 @@
+//! Verification of Merkle proofs using the SHA2-256 host function.
+//!
+//! A Merkle tree commits to a list of leaves by a single hash, the root, which
+//! can be stored in the contract state instead of the leaves. That a leaf is
+//! part of the tree is then shown by a proof, consisting of the hashes of the
+//! siblings of the nodes on the path from the leaf to the root. This allows,
+//! e.g., an airdrop to thousands of accounts where the contract only stores
+//! the root, and every account claims with a proof of its entitlement.
+//!
+//! The tree is built as follows:
+//!  - The hash of a leaf is `SHA2-256(0x00 || leaf)`.
+//!  - The hash of an inner node is `SHA2-256(0x01 || left || right)`, where the
+//!    hashes of the two children are ordered such that `left <= right`. Because
+//!    of the ordering, a proof does not need to specify on which side every
+//!    sibling is.
+//!  - If a level of the tree has an odd number of nodes, the last node is moved
+//!    to the next level unchanged.
+//!
+//! The different prefixes of leaves and inner nodes ensure that an inner node
+//! cannot be passed off as a leaf.
+//!
+//! The trees are typically built off-chain, but [`compute_root`] and
+//! [`compute_proof`] are provided for building trees in the same way, e.g., in
+//! tests.
+use crate::{traits::HasCryptoPrimitives, types::HashSha2256, vec::Vec};
+
+/// The byte prepended to leaves before hashing them.
+const LEAF_PREFIX: u8 = 0;
+
+/// The byte prepended to the hashes of the children of an inner node before
+/// hashing them.
+const NODE_PREFIX: u8 = 1;
+
+/// Hash a leaf of the tree.
+pub fn hash_leaf(crypto_primitives: &impl HasCryptoPrimitives, leaf: &[u8]) -> HashSha2256 {
+    let mut data = Vec::with_capacity(1 + leaf.len());
+    data.push(LEAF_PREFIX);
+    data.extend_from_slice(leaf);
+    crypto_primitives.hash_sha2_256(&data)
+}
+
+/// Hash an inner node of the tree from the hashes of its children, which can
+/// be given in any order.
+pub fn hash_node(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    child: &HashSha2256,
+    other_child: &HashSha2256,
+) -> HashSha2256 {
+    let (left, right) = if child <= other_child {
+        (child, other_child)
+    } else {
+        (other_child, child)
+    };
+    let mut data = [0u8; 65];
+    data[0] = NODE_PREFIX;
+    data[1..33].copy_from_slice(&left.0);
+    data[33..].copy_from_slice(&right.0);
+    crypto_primitives.hash_sha2_256(&data)
+}
+
+/// Verify that the leaf is part of the tree with the given root, using the
+/// proof, which lists the hashes of the siblings from the leaf to the root.
+pub fn verify_proof(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    root: &HashSha2256,
+    leaf: &[u8],
+    proof: &[HashSha2256],
+) -> bool {
+    let hash = proof.iter().fold(hash_leaf(crypto_primitives, leaf), |hash, sibling| {
+        hash_node(crypto_primitives, &hash, sibling)
+    });
+    hash == *root
+}
+
+/// Compute the root of the tree with the given leaves. Return [None] if there
+/// are no leaves.
+pub fn compute_root<L: AsRef<[u8]>>(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    leaves: &[L],
+) -> Option<HashSha2256> {
+    let mut level = hash_leaves(crypto_primitives, leaves);
+    while level.len() > 1 {
+        level = next_level(crypto_primitives, &level);
+    }
+    level.first().copied()
+}
+
+/// Compute the proof for the leaf at the given index in the tree with the
+/// given leaves. Return [None] if the index is out of bounds.
+pub fn compute_proof<L: AsRef<[u8]>>(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    leaves: &[L],
+    index: usize,
+) -> Option<Vec<HashSha2256>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut level = hash_leaves(crypto_primitives, leaves);
+    let mut index = index;
+    let mut proof = Vec::new();
+    while level.len() > 1 {
+        // The last node of a level with an odd number of nodes has no sibling.
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = next_level(crypto_primitives, &level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+fn hash_leaves<L: AsRef<[u8]>>(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    leaves: &[L],
+) -> Vec<HashSha2256> {
+    leaves.iter().map(|leaf| hash_leaf(crypto_primitives, leaf.as_ref())).collect()
+}
+
+/// Compute the next level of the tree, by hashing pairs of nodes and moving a
+/// last unpaired node up unchanged.
+fn next_level(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    level: &[HashSha2256],
+) -> Vec<HashSha2256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair.get(1) {
+            Some(other) => hash_node(crypto_primitives, &pair[0], other),
+            None => pair[0],
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "crypto-primitives"))]
+mod tests {
+    use super::*;
+    use crate::test_infrastructure::TestCryptoPrimitives;
+
+    #[test]
+    fn proofs_verify_for_all_leaves() {
+        let crypto_primitives = TestCryptoPrimitives::new();
+        for count in 1..12u32 {
+            let leaves: Vec<_> = (0..count).map(|i| i.to_le_bytes()).collect();
+            let root = compute_root(&crypto_primitives, &leaves).expect("There are leaves");
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof =
+                    compute_proof(&crypto_primitives, &leaves, index).expect("Index is in bounds");
+                assert!(verify_proof(&crypto_primitives, &root, leaf, &proof));
+                assert!(!verify_proof(&crypto_primitives, &root, &count.to_le_bytes(), &proof));
+            }
+            assert!(compute_proof(&crypto_primitives, &leaves, leaves.len()).is_none());
+        }
+        assert!(compute_root::<[u8; 4]>(&crypto_primitives, &[]).is_none());
+    }
+
+    #[test]
+    fn inner_node_is_not_a_leaf() {
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let leaves = [[0u8], [1u8], [2u8], [3u8]];
+        let root = compute_root(&crypto_primitives, &leaves).expect("There are leaves");
+        let proof = compute_proof(&crypto_primitives, &leaves, 0).expect("Index is in bounds");
+        // Present the hash of the first two leaves, with the rest of the proof.
+        let inner = hash_node(
+            &crypto_primitives,
+            &hash_leaf(&crypto_primitives, &[0]),
+            &hash_leaf(&crypto_primitives, &[1]),
+        );
+        assert!(!verify_proof(&crypto_primitives, &root, &inner.0, &proof[1..]));
+    }
+}
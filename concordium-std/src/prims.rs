@@ -24,6 +24,8 @@ extern "C" {
     ///   - 2 for query an account balance.
     ///   - 3 for query a contract balance.
     ///   - 4 for query the exchange rates.
+    ///   - 5 for query the public keys of an account.
+    ///   - 6 for check the signature of an account.
     /// - `start`, pointer to the start of the invoke payload
     /// - `length`, length of the payload
     /// - if the last 5 bytes are 0 then the call succeeded. In this case the
@@ -280,6 +282,18 @@ extern "C" {
         column: u32,
     );
 
+    #[cfg(all(feature = "debug", target_arch = "wasm32"))]
+    /// Print a debug message along with the location it was printed from.
+    /// Only provided by tools such as `cargo-concordium`, and not by the chain.
+    pub(crate) fn debug_print(
+        message_start: *const u8,
+        message_length: u32,
+        filename_start: *const u8,
+        filename_length: u32,
+        line: u32,
+        column: u32,
+    );
+
     #[cfg(all(feature = "wasm-test", feature = "concordium-quickcheck", target_arch = "wasm32"))]
     /// Generating random numbers for randomised testing.
     /// Not available for contracts deployed on the chain.
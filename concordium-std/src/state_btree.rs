@@ -0,0 +1,1114 @@
+//! Ordered collections in the contract state, based on a B-tree.
+//!
+//! The [`StateMap`] and [`StateSet`] iterate their items ordered by the
+//! serialization of the keys, which rarely matches the order of the keys
+//! themselves, e.g., for little-endian integers. Finding the smallest key, or
+//! the keys in a range, therefore requires iterating over the whole
+//! collection. The [`StateBTreeMap`] and [`StateBTreeSet`] instead keep their
+//! keys ordered by [`Ord`] in a B-tree, where every node is a separate entry
+//! in the state. This makes lookups of the smallest and largest key, the
+//! nearest key above or below a given key, and iteration over a range,
+//! logarithmic in the size of the collection.
+use crate::{
+    cmp::Ordering,
+    mem,
+    ops::{Bound, RangeBounds},
+    traits::*,
+    types::*,
+    vec::Vec,
+};
+use concordium_contracts_common::*;
+
+/// The minimum number of keys in a node other than the root is
+/// `MIN_DEGREE - 1`, and the maximum number of keys in a node is
+/// `2 * MIN_DEGREE - 1`.
+const MIN_DEGREE: usize = 8;
+
+/// The identifier of a node, unique within a collection.
+pub(crate) type NodeId = u32;
+
+/// A node of the B-tree. The node is a leaf if it has no children, and
+/// otherwise it has one more child than keys, where the keys in the child at
+/// index `i` are between the keys at index `i - 1` and `i`.
+#[derive(Debug)]
+pub(crate) struct Node<K> {
+    pub(crate) keys:     Vec<K>,
+    pub(crate) children: Vec<NodeId>,
+}
+
+impl<K> Node<K> {
+    fn is_leaf(&self) -> bool { self.children.is_empty() }
+
+    fn is_full(&self) -> bool { self.keys.len() == 2 * MIN_DEGREE - 1 }
+
+    /// Split a full node in two halves around the median key, which is
+    /// returned together with the right half. The node keeps the left half.
+    fn split(&mut self) -> (K, Node<K>) {
+        let right = Node {
+            keys:     self.keys.split_off(MIN_DEGREE),
+            children: if self.is_leaf() {
+                Vec::new()
+            } else {
+                self.children.split_off(MIN_DEGREE)
+            },
+        };
+        // Unwrapping is safe since the node is full.
+        let median = self.keys.pop().unwrap_abort();
+        (median, right)
+    }
+}
+
+impl<K: Serial> Serial for Node<K> {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        self.keys.serial(out)?;
+        self.children.serial(out)
+    }
+}
+
+impl<K: Deserial> Deserial for Node<K> {
+    fn deserial<R: Read>(source: &mut R) -> ParseResult<Self> {
+        let keys = Vec::deserial(source)?;
+        let children = Vec::deserial(source)?;
+        Ok(Node {
+            keys,
+            children,
+        })
+    }
+}
+
+#[derive(Debug)]
+/// An ordered set of _flat_ values in the contract state, based on a B-tree.
+///
+/// In contrast to the [`StateSet`], the values are ordered by their [`Ord`]
+/// implementation rather than by their serialization, and the set supports
+/// finding the [`first`](Self::first) and [`last`](Self::last) value, the
+/// nearest value [`higher`](Self::higher) or [`lower`](Self::lower) than a
+/// given value, and iterating over a [`range`](Self::range) of values.
+///
+/// Every node of the tree is stored as a separate entry in the state, holding
+/// up to 15 values. An operation loads and stores the nodes on the path from
+/// the root to the value, so it is more costly than the corresponding
+/// operation on a [`StateSet`]. Prefer the [`StateSet`] if the order of the
+/// values is not needed.
+///
+/// The [`SchemaType`](schema::SchemaType) of the [`StateBTreeSet`]
+/// describes only how it is stored, not its values, since these are stored in
+/// the nodes in separate entries. To view the values, collect them with
+/// [`iter`](Self::iter) in a view function.
+///
+/// New sets can be constructed using the
+/// [`new_btree_set`][StateBuilder::new_btree_set] method on the
+/// [`StateBuilder`]. Like the [`StateSet`], the set must be
+/// [cleared](Self::clear) or [deleted](Deletable::delete) when it is no longer
+/// needed, and sets cannot be nested.
+///
+/// ```
+/// # use concordium_std::*;
+/// # use concordium_std::test_infrastructure::*;
+/// # let mut state_builder = TestStateBuilder::new();
+/// let mut expiries = state_builder.new_btree_set();
+/// expiries.insert(300u64);
+/// expiries.insert(100u64);
+/// expiries.insert(200u64);
+/// assert_eq!(expiries.first().as_deref(), Some(&100));
+/// assert_eq!(expiries.higher(&100).as_deref(), Some(&200));
+/// let expired: Vec<u64> = expiries.range(..=200).map(|v| *v).collect();
+/// assert_eq!(expired, [100, 200]);
+/// ```
+pub struct StateBTreeSet<K, S> {
+    pub(crate) nodes:        StateMap<NodeId, Node<K>, S>,
+    pub(crate) root:         Option<NodeId>,
+    pub(crate) len:          u32,
+    pub(crate) next_node_id: NodeId,
+}
+
+#[derive(Debug)]
+/// An ordered map in the contract state, based on a B-tree.
+///
+/// The map combines a [`StateMap`] of the values with a [`StateBTreeSet`] of
+/// the keys, so lookups of a value by its key are as cheap as for the
+/// [`StateMap`], while the keys are ordered by their [`Ord`] implementation.
+/// This supports finding the [`first_key`](Self::first_key) and
+/// [`last_key`](Self::last_key), the nearest key [`higher`](Self::higher) or
+/// [`lower`](Self::lower) than a given key, and iterating over a
+/// [`range`](Self::range) of keys, as needed by, e.g., order books and expiry
+/// queues. Updates cost more than for the [`StateMap`], since they also update
+/// the tree of keys.
+///
+/// Like for the [`StateBTreeSet`], the [`SchemaType`](schema::SchemaType) of
+/// the map describes only how it is stored, not its keys and values.
+///
+/// New maps can be constructed using the
+/// [`new_btree_map`][StateBuilder::new_btree_map] method on the
+/// [`StateBuilder`]. Like the [`StateMap`], the map must be
+/// [cleared](Self::clear) or [deleted](Deletable::delete) when it is no longer
+/// needed.
+///
+/// ```
+/// # use concordium_std::*;
+/// # use concordium_std::test_infrastructure::*;
+/// # let mut state_builder = TestStateBuilder::new();
+/// // Bids by price.
+/// let mut bids = state_builder.new_btree_map();
+/// bids.insert(20u64, AccountAddress([0u8; 32]));
+/// bids.insert(35u64, AccountAddress([1u8; 32]));
+/// bids.insert(10u64, AccountAddress([2u8; 32]));
+/// assert_eq!(bids.last_key().as_deref(), Some(&35));
+/// assert_eq!(bids.lower(&20).as_deref(), Some(&10));
+/// ```
+pub struct StateBTreeMap<K, V, S> {
+    pub(crate) key_value: StateMap<K, V, S>,
+    pub(crate) key_order: StateBTreeSet<K, S>,
+}
+
+/// An iterator over a range of the values of a [`StateBTreeSet`], in
+/// increasing order.
+///
+/// This `struct` is created by the [`iter`][StateBTreeSet::iter] and
+/// [`range`][StateBTreeSet::range] methods on [`StateBTreeSet`]. See their
+/// documentation for more.
+pub struct StateBTreeSetIter<'a, K, S> {
+    set:   &'a StateBTreeSet<K, S>,
+    /// The nodes on the path to the next value, with their remaining values
+    /// and the children to the right of those.
+    stack: Vec<(crate::vec::IntoIter<K>, crate::vec::IntoIter<NodeId>)>,
+    /// The bound of the range above, if any.
+    upper: Bound<K>,
+}
+
+/// An iterator over a range of the entries of a [`StateBTreeMap`], in
+/// increasing order of keys.
+///
+/// This `struct` is created by the [`iter`][StateBTreeMap::iter] and
+/// [`range`][StateBTreeMap::range] methods on [`StateBTreeMap`]. See their
+/// documentation for more.
+pub struct StateBTreeMapIter<'a, K, V, S> {
+    key_value: &'a StateMap<K, V, S>,
+    keys:      StateBTreeSetIter<'a, K, S>,
+}
+
+impl<K, S> StateBTreeSet<K, S>
+where
+    S: HasStateApi,
+{
+    pub(crate) fn open(nodes: StateMap<NodeId, Node<K>, S>) -> Self {
+        Self {
+            nodes,
+            root: None,
+            len: 0,
+            next_node_id: 0,
+        }
+    }
+
+    /// Return the number of values in the set.
+    pub fn len(&self) -> u32 { self.len }
+
+    /// Return `true` if the set contains no values.
+    pub fn is_empty(&self) -> bool { self.root.is_none() }
+
+    /// Clears the set, removing all values.
+    pub fn clear(&mut self) {
+        // Unwrapping is safe when only using the high-level API.
+        self.nodes.state_api.delete_prefix(&self.nodes.prefix).unwrap_abort();
+        self.root = None;
+        self.len = 0;
+        self.next_node_id = 0;
+    }
+
+    /// The key of the state entry of a node. This matches the key of the node
+    /// in the `nodes` map.
+    fn node_key(&self, id: NodeId) -> Vec<u8> {
+        let mut key = self.nodes.prefix.to_vec();
+        key.extend_from_slice(&id.to_le_bytes());
+        key
+    }
+
+    fn delete_node(&mut self, id: NodeId) {
+        let key = self.node_key(id);
+        if let Some(entry) = self.nodes.state_api.lookup_entry(&key) {
+            // Unwrapping is safe when only using the high-level API.
+            self.nodes.state_api.delete_entry(entry).unwrap_abort();
+        }
+    }
+}
+
+impl<K, S> StateBTreeSet<K, S>
+where
+    S: HasStateApi,
+    K: Serialize + Ord,
+{
+    /// Insert a value into the set. Returns `false` if the set already
+    /// contained the value.
+    pub fn insert(&mut self, value: K) -> bool {
+        let root_id = match self.root {
+            None => {
+                let root_id = self.create_node(&Node {
+                    keys:     crate::vec![value],
+                    children: Vec::new(),
+                });
+                self.root = Some(root_id);
+                self.len = 1;
+                return true;
+            }
+            Some(root_id) => root_id,
+        };
+        let mut node_id = root_id;
+        let mut node = self.load_node(root_id);
+        if node.is_full() {
+            // Grow the tree by splitting the root under a new root.
+            let (median, right) = node.split();
+            let right_id = self.create_node(&right);
+            self.store_node(root_id, &node);
+            node = Node {
+                keys:     crate::vec![median],
+                children: crate::vec![root_id, right_id],
+            };
+            node_id = self.create_node(&node);
+            self.root = Some(node_id);
+        }
+        // Descend to the leaf where the value belongs, splitting full nodes on
+        // the way, such that a split never has to propagate upwards.
+        loop {
+            let pos = match node.keys.binary_search(&value) {
+                Ok(_) => return false,
+                Err(pos) => pos,
+            };
+            if node.is_leaf() {
+                node.keys.insert(pos, value);
+                self.store_node(node_id, &node);
+                self.len += 1;
+                return true;
+            }
+            let mut child_id = node.children[pos];
+            let mut child = self.load_node(child_id);
+            if child.is_full() {
+                let (median, right) = child.split();
+                let right_id = self.create_node(&right);
+                self.store_node(child_id, &child);
+                let ordering = value.cmp(&median);
+                node.keys.insert(pos, median);
+                node.children.insert(pos + 1, right_id);
+                self.store_node(node_id, &node);
+                match ordering {
+                    Ordering::Equal => return false,
+                    Ordering::Greater => {
+                        child_id = right_id;
+                        child = right;
+                    }
+                    Ordering::Less => {}
+                }
+            }
+            node_id = child_id;
+            node = child;
+        }
+    }
+
+    /// Return `true` if the set contains the value.
+    pub fn contains(&self, value: &K) -> bool {
+        let mut node_id = match self.root {
+            None => return false,
+            Some(root_id) => root_id,
+        };
+        loop {
+            let node = self.load_node(node_id);
+            match node.keys.binary_search(value) {
+                Ok(_) => return true,
+                Err(_) if node.is_leaf() => return false,
+                Err(pos) => node_id = node.children[pos],
+            }
+        }
+    }
+
+    /// Remove a value from the set. Returns `true` if the value was in the
+    /// set.
+    pub fn remove(&mut self, value: &K) -> bool {
+        let mut node_id = match self.root {
+            None => return false,
+            Some(root_id) => root_id,
+        };
+        let mut node = self.load_node(node_id);
+        // Descend to the value, making sure every node below the root has at
+        // least `MIN_DEGREE` keys before entering it, such that removing a key
+        // never has to propagate upwards.
+        loop {
+            match node.keys.binary_search(value) {
+                Ok(pos) if node.is_leaf() => {
+                    node.keys.remove(pos);
+                    if node.keys.is_empty() {
+                        // Only the root can become empty.
+                        self.delete_node(node_id);
+                        self.root = None;
+                    } else {
+                        self.store_node(node_id, &node);
+                    }
+                    self.len -= 1;
+                    return true;
+                }
+                Ok(pos) => {
+                    // Replace the value by its predecessor or successor, if
+                    // they can be removed from a child with enough keys.
+                    // Otherwise merge the children around it, and remove the
+                    // value from the merged child.
+                    let left_id = node.children[pos];
+                    let left = self.load_node(left_id);
+                    if left.keys.len() >= MIN_DEGREE {
+                        node.keys[pos] = self.remove_extreme(left_id, left, true);
+                        self.store_node(node_id, &node);
+                        self.len -= 1;
+                        return true;
+                    }
+                    let right_id = node.children[pos + 1];
+                    let right = self.load_node(right_id);
+                    if right.keys.len() >= MIN_DEGREE {
+                        node.keys[pos] = self.remove_extreme(right_id, right, false);
+                        self.store_node(node_id, &node);
+                        self.len -= 1;
+                        return true;
+                    }
+                    let (merged_id, merged) = self.merge_children(node_id, node, pos, left, right);
+                    node_id = merged_id;
+                    node = merged;
+                }
+                Err(_) if node.is_leaf() => return false,
+                Err(pos) => {
+                    let (child_id, child) = self.prepare_child(node_id, node, pos);
+                    node_id = child_id;
+                    node = child;
+                }
+            }
+        }
+    }
+
+    /// Return the smallest value in the set, if any.
+    pub fn first(&self) -> Option<StateRef<K>> { self.extreme(false) }
+
+    /// Return the largest value in the set, if any.
+    pub fn last(&self) -> Option<StateRef<K>> { self.extreme(true) }
+
+    /// Return the smallest value in the set which is strictly larger than the
+    /// given value, if any.
+    pub fn higher(&self, value: &K) -> Option<StateRef<K>> { self.nearest(value, true, false) }
+
+    /// Return the smallest value in the set which is equal to or larger than
+    /// the given value, if any.
+    pub fn eq_or_higher(&self, value: &K) -> Option<StateRef<K>> { self.nearest(value, true, true) }
+
+    /// Return the largest value in the set which is strictly smaller than the
+    /// given value, if any.
+    pub fn lower(&self, value: &K) -> Option<StateRef<K>> { self.nearest(value, false, false) }
+
+    /// Return the largest value in the set which is equal to or smaller than
+    /// the given value, if any.
+    pub fn eq_or_lower(&self, value: &K) -> Option<StateRef<K>> { self.nearest(value, false, true) }
+
+    /// Get an iterator over the values of the set, in increasing order.
+    pub fn iter(&self) -> StateBTreeSetIter<'_, K, S> {
+        StateBTreeSetIter::new(self, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Get an iterator over the values of the set within the range, in
+    /// increasing order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> StateBTreeSetIter<'_, K, S>
+    where
+        K: Clone, {
+        StateBTreeSetIter::new(self, range.start_bound().cloned(), range.end_bound().cloned())
+    }
+
+    /// Load a node from the state.
+    fn load_node(&self, id: NodeId) -> Node<K> {
+        // Unwrapping is safe when only using the high-level API.
+        let mut entry = self.nodes.state_api.lookup_entry(&self.node_key(id)).unwrap_abort();
+        Node::deserial(&mut entry).unwrap_abort()
+    }
+
+    /// Store a node in the state, replacing the existing one, if any.
+    fn store_node(&mut self, id: NodeId, node: &Node<K>) {
+        let key = self.node_key(id);
+        match self.nodes.state_api.entry(key) {
+            EntryRaw::Vacant(vac) => {
+                // Unwrapping is safe when only using the high-level API.
+                let _ = vac.insert(node).unwrap_abort();
+            }
+            EntryRaw::Occupied(mut occ) => occ.insert(node),
+        }
+    }
+
+    /// Store a new node in the state, returning its identifier.
+    fn create_node(&mut self, node: &Node<K>) -> NodeId {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+        self.store_node(id, node);
+        id
+    }
+
+    /// Merge the children at `pos` and `pos + 1` of a node together with the
+    /// key at `pos` into the left child, which is returned. If the node is the
+    /// root and becomes empty, the merged child becomes the new root.
+    fn merge_children(
+        &mut self,
+        node_id: NodeId,
+        mut node: Node<K>,
+        pos: usize,
+        mut left: Node<K>,
+        right: Node<K>,
+    ) -> (NodeId, Node<K>) {
+        let left_id = node.children[pos];
+        let right_id = node.children.remove(pos + 1);
+        left.keys.push(node.keys.remove(pos));
+        left.keys.extend(right.keys);
+        left.children.extend(right.children);
+        self.delete_node(right_id);
+        if node.keys.is_empty() {
+            self.delete_node(node_id);
+            self.root = Some(left_id);
+        } else {
+            self.store_node(node_id, &node);
+        }
+        self.store_node(left_id, &left);
+        (left_id, left)
+    }
+
+    /// Load the child at `pos` of a node, making sure it has at least
+    /// `MIN_DEGREE` keys by moving a key from a sibling through the node, or by
+    /// merging it with a sibling.
+    fn prepare_child(
+        &mut self,
+        node_id: NodeId,
+        mut node: Node<K>,
+        pos: usize,
+    ) -> (NodeId, Node<K>) {
+        let child_id = node.children[pos];
+        let mut child = self.load_node(child_id);
+        if child.keys.len() >= MIN_DEGREE {
+            return (child_id, child);
+        }
+        let left = if pos > 0 {
+            let left_id = node.children[pos - 1];
+            let mut left = self.load_node(left_id);
+            if left.keys.len() >= MIN_DEGREE {
+                // Unwrapping is safe since the sibling has keys.
+                let key = left.keys.pop().unwrap_abort();
+                child.keys.insert(0, mem::replace(&mut node.keys[pos - 1], key));
+                if let Some(grandchild) = left.children.pop() {
+                    child.children.insert(0, grandchild);
+                }
+                self.store_node(left_id, &left);
+                self.store_node(node_id, &node);
+                self.store_node(child_id, &child);
+                return (child_id, child);
+            }
+            Some(left)
+        } else {
+            None
+        };
+        if pos + 1 < node.children.len() {
+            let right_id = node.children[pos + 1];
+            let mut right = self.load_node(right_id);
+            if right.keys.len() >= MIN_DEGREE {
+                let key = right.keys.remove(0);
+                child.keys.push(mem::replace(&mut node.keys[pos], key));
+                if !right.is_leaf() {
+                    child.children.push(right.children.remove(0));
+                }
+                self.store_node(right_id, &right);
+                self.store_node(node_id, &node);
+                self.store_node(child_id, &child);
+                return (child_id, child);
+            }
+            self.merge_children(node_id, node, pos, child, right)
+        } else {
+            // A node has at least two children, so the last child has a left
+            // sibling.
+            let left = left.unwrap_abort();
+            self.merge_children(node_id, node, pos - 1, left, child)
+        }
+    }
+
+    /// Remove the largest or smallest value of the subtree of a node with at
+    /// least `MIN_DEGREE` keys.
+    fn remove_extreme(&mut self, mut node_id: NodeId, mut node: Node<K>, largest: bool) -> K {
+        loop {
+            if node.is_leaf() {
+                // Unwrapping is safe since the node has keys.
+                let value = if largest {
+                    node.keys.pop().unwrap_abort()
+                } else {
+                    node.keys.remove(0)
+                };
+                self.store_node(node_id, &node);
+                return value;
+            }
+            let pos = if largest {
+                node.children.len() - 1
+            } else {
+                0
+            };
+            let (child_id, child) = self.prepare_child(node_id, node, pos);
+            node_id = child_id;
+            node = child;
+        }
+    }
+
+    /// Return the largest or smallest value of the set.
+    fn extreme(&self, largest: bool) -> Option<StateRef<K>> {
+        let mut node = self.load_node(self.root?);
+        loop {
+            let child_id = if largest {
+                node.children.last()
+            } else {
+                node.children.first()
+            };
+            match child_id.copied() {
+                Some(child_id) => node = self.load_node(child_id),
+                None if largest => return node.keys.pop().map(StateRef::new),
+                None => return node.keys.into_iter().next().map(StateRef::new),
+            }
+        }
+    }
+
+    /// Return the value nearest to the given value, either above or below it,
+    /// and possibly equal to it.
+    fn nearest(&self, value: &K, above: bool, or_equal: bool) -> Option<StateRef<K>> {
+        let mut nearest = None;
+        let mut node = self.load_node(self.root?);
+        loop {
+            // The index of the first key above the value, or of the first key
+            // equal to or above the value.
+            let pos = match node.keys.binary_search(value) {
+                Ok(pos) if or_equal => return Some(StateRef::new(node.keys.swap_remove(pos))),
+                Ok(pos) if above => pos + 1,
+                Ok(pos) | Err(pos) => pos,
+            };
+            // Every key in the child at `pos` is between the keys at `pos - 1`
+            // and `pos`, so it is nearer than either of them.
+            let child_id = node.children.get(pos).copied();
+            if above && pos < node.keys.len() {
+                nearest = Some(node.keys.swap_remove(pos));
+            } else if !above && pos > 0 {
+                nearest = Some(node.keys.swap_remove(pos - 1));
+            }
+            match child_id {
+                Some(child_id) => node = self.load_node(child_id),
+                None => return nearest.map(StateRef::new),
+            }
+        }
+    }
+}
+
+impl<'a, K, S> StateBTreeSetIter<'a, K, S>
+where
+    S: HasStateApi,
+    K: Serialize + Ord,
+{
+    fn new(set: &'a StateBTreeSet<K, S>, lower: Bound<K>, upper: Bound<K>) -> Self {
+        let mut iter = StateBTreeSetIter {
+            set,
+            stack: Vec::new(),
+            upper,
+        };
+        // Descend to the first value within the lower bound, keeping the
+        // values which are not below the bound on the way.
+        let mut next = set.root;
+        while let Some(node_id) = next {
+            let mut node = set.load_node(node_id);
+            let pos = match &lower {
+                Bound::Unbounded => 0,
+                Bound::Included(lower) => match node.keys.binary_search(lower) {
+                    Ok(pos) | Err(pos) => pos,
+                },
+                Bound::Excluded(lower) => match node.keys.binary_search(lower) {
+                    Ok(pos) => pos + 1,
+                    Err(pos) => pos,
+                },
+            };
+            let keys = node.keys.split_off(pos);
+            let mut children = if node.is_leaf() {
+                Vec::new()
+            } else {
+                node.children.split_off(pos)
+            }
+            .into_iter();
+            next = children.next();
+            iter.stack.push((keys.into_iter(), children));
+        }
+        iter
+    }
+
+    /// Push the path to the smallest value of the subtree of a node.
+    fn push_leftmost(&mut self, node_id: NodeId) {
+        let mut next = Some(node_id);
+        while let Some(node_id) = next {
+            let node = self.set.load_node(node_id);
+            let mut children = node.children.into_iter();
+            next = children.next();
+            self.stack.push((node.keys.into_iter(), children));
+        }
+    }
+}
+
+impl<'a, K, S> Iterator for StateBTreeSetIter<'a, K, S>
+where
+    S: HasStateApi,
+    K: Serialize + Ord + 'a,
+{
+    type Item = StateRef<'a, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (keys, children) = self.stack.last_mut()?;
+            match keys.next() {
+                Some(key) => {
+                    // The subtree right of the key holds the values following it.
+                    if let Some(child_id) = children.next() {
+                        self.push_leftmost(child_id);
+                    }
+                    let within = match &self.upper {
+                        Bound::Unbounded => true,
+                        Bound::Included(upper) => key <= *upper,
+                        Bound::Excluded(upper) => key < *upper,
+                    };
+                    if !within {
+                        self.stack.clear();
+                        return None;
+                    }
+                    return Some(StateRef::new(key));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, S> StateBTreeMap<K, V, S>
+where
+    S: HasStateApi,
+{
+    pub(crate) fn open(key_value: StateMap<K, V, S>, key_order: StateBTreeSet<K, S>) -> Self {
+        Self {
+            key_value,
+            key_order,
+        }
+    }
+
+    /// Return the number of entries in the map.
+    pub fn len(&self) -> u32 { self.key_order.len() }
+
+    /// Return `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool { self.key_order.is_empty() }
+}
+
+impl<K, V, S> StateBTreeMap<K, V, S>
+where
+    S: HasStateApi,
+    K: Serialize + Ord + Clone,
+    V: Serial + DeserialWithState<S>,
+{
+    /// Inserts the value with the given key. If a value already exists at the
+    /// given key it is replaced and the old value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old_value = self.key_value.insert(key.clone(), value);
+        if old_value.is_none() {
+            self.key_order.insert(key);
+        }
+        old_value
+    }
+
+    /// Lookup the value with the given key. Return [None] if there is no value
+    /// with the given key.
+    pub fn get(&self, key: &K) -> Option<StateRef<V>> { self.key_value.get(key) }
+
+    /// Lookup a mutable reference to the value with the given key. Return
+    /// [None] if there is no value with the given key.
+    pub fn get_mut(&self, key: &K) -> Option<StateRefMut<V, S>> { self.key_value.get_mut(key) }
+
+    /// Return `true` if the map contains a value with the given key.
+    pub fn contains_key(&self, key: &K) -> bool { self.key_value.get(key).is_some() }
+
+    /// Remove a key from the map, returning the value at the key if the key was
+    /// previously in the map.
+    ///
+    /// *Caution*: If `V` is a [StateBox], [StateMap], then it is
+    /// important to call [`Deletable::delete`] on the value returned when
+    /// you're finished with it. Otherwise, it will remain in the contract
+    /// state.
+    #[must_use]
+    pub fn remove_and_get(&mut self, key: &K) -> Option<V> {
+        let old_value = self.key_value.remove_and_get(key);
+        if old_value.is_some() {
+            self.key_order.remove(key);
+        }
+        old_value
+    }
+
+    /// Remove a key from the map.
+    /// This also deletes the value in the state.
+    pub fn remove(&mut self, key: &K)
+    where
+        V: Deletable, {
+        if let Some(v) = self.remove_and_get(key) {
+            v.delete()
+        }
+    }
+
+    /// Clears the map, removing all entries.
+    /// This also includes values pointed at, if `V`, for example, is a
+    /// [StateBox].
+    pub fn clear(&mut self)
+    where
+        V: Deletable, {
+        self.key_value.clear();
+        self.key_order.clear();
+    }
+
+    /// Return the smallest key in the map, if any.
+    pub fn first_key(&self) -> Option<StateRef<K>> { self.key_order.first() }
+
+    /// Return the largest key in the map, if any.
+    pub fn last_key(&self) -> Option<StateRef<K>> { self.key_order.last() }
+
+    /// Return the smallest key in the map which is strictly larger than the
+    /// given key, if any.
+    pub fn higher(&self, key: &K) -> Option<StateRef<K>> { self.key_order.higher(key) }
+
+    /// Return the smallest key in the map which is equal to or larger than the
+    /// given key, if any.
+    pub fn eq_or_higher(&self, key: &K) -> Option<StateRef<K>> { self.key_order.eq_or_higher(key) }
+
+    /// Return the largest key in the map which is strictly smaller than the
+    /// given key, if any.
+    pub fn lower(&self, key: &K) -> Option<StateRef<K>> { self.key_order.lower(key) }
+
+    /// Return the largest key in the map which is equal to or smaller than the
+    /// given key, if any.
+    pub fn eq_or_lower(&self, key: &K) -> Option<StateRef<K>> { self.key_order.eq_or_lower(key) }
+
+    /// Get an iterator over the entries of the map, in increasing order of
+    /// keys.
+    pub fn iter(&self) -> StateBTreeMapIter<'_, K, V, S> {
+        StateBTreeMapIter {
+            key_value: &self.key_value,
+            keys:      self.key_order.iter(),
+        }
+    }
+
+    /// Get an iterator over the entries of the map with keys within the range,
+    /// in increasing order of keys.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> StateBTreeMapIter<'_, K, V, S> {
+        StateBTreeMapIter {
+            key_value: &self.key_value,
+            keys:      self.key_order.range(range),
+        }
+    }
+}
+
+impl<'a, K, V, S> Iterator for StateBTreeMapIter<'a, K, V, S>
+where
+    S: HasStateApi,
+    K: Serialize + Ord + 'a,
+    V: Serial + DeserialWithState<S> + 'a,
+{
+    type Item = (StateRef<'a, K>, StateRef<'a, V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        // Unwrapping is safe since every key in the tree has a value.
+        let value = self.key_value.get(&key).unwrap_abort();
+        Some((key, value))
+    }
+}
+
+impl<K, S> Serial for StateBTreeSet<K, S> {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        self.nodes.serial(out)?;
+        self.root.serial(out)?;
+        self.len.serial(out)?;
+        self.next_node_id.serial(out)
+    }
+}
+
+impl<K, V, S> Serial for StateBTreeMap<K, V, S> {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        self.key_value.serial(out)?;
+        self.key_order.serial(out)
+    }
+}
+
+impl<K, S> DeserialWithState<S> for StateBTreeSet<K, S>
+where
+    S: HasStateApi,
+{
+    fn deserial_with_state<R: Read>(state: &S, source: &mut R) -> ParseResult<Self> {
+        let nodes = StateMap::deserial_with_state(state, source)?;
+        let root = source.get()?;
+        let len = source.get()?;
+        let next_node_id = source.get()?;
+        Ok(Self {
+            nodes,
+            root,
+            len,
+            next_node_id,
+        })
+    }
+}
+
+impl<K, V, S> DeserialWithState<S> for StateBTreeMap<K, V, S>
+where
+    S: HasStateApi,
+{
+    fn deserial_with_state<R: Read>(state: &S, source: &mut R) -> ParseResult<Self> {
+        let key_value = StateMap::deserial_with_state(state, source)?;
+        let key_order = StateBTreeSet::deserial_with_state(state, source)?;
+        Ok(Self::open(key_value, key_order))
+    }
+}
+
+impl<K, S> schema::SchemaType for StateBTreeSet<K, S> {
+    fn get_type() -> schema::Type {
+        schema::Type::Struct(schema::Fields::Named(crate::vec![
+            (crate::String::from("prefix"), schema::Type::ByteArray(8)),
+            (crate::String::from("root"), <Option<NodeId> as schema::SchemaType>::get_type()),
+            (crate::String::from("len"), schema::Type::U32),
+            (crate::String::from("next_node_id"), schema::Type::U32),
+        ]))
+    }
+}
+
+impl<K, V, S> schema::SchemaType for StateBTreeMap<K, V, S> {
+    fn get_type() -> schema::Type {
+        schema::Type::Struct(schema::Fields::Named(crate::vec![
+            (crate::String::from("prefix"), schema::Type::ByteArray(8)),
+            (
+                crate::String::from("key_order"),
+                <StateBTreeSet<K, S> as schema::SchemaType>::get_type(),
+            ),
+        ]))
+    }
+}
+
+impl<K, S> Deletable for StateBTreeSet<K, S>
+where
+    S: HasStateApi,
+{
+    fn delete(mut self) { self.clear(); }
+}
+
+impl<K, V, S> Deletable for StateBTreeMap<K, V, S>
+where
+    S: HasStateApi,
+    K: Serialize + Ord + Clone,
+    V: Serial + DeserialWithState<S> + Deletable,
+{
+    fn delete(mut self) { self.clear(); }
+}
+
+unsafe impl<K, S: HasStateApi> StateClone<S> for StateBTreeSet<K, S> {
+    unsafe fn clone_state(&self, cloned_state_api: &S) -> Self {
+        Self {
+            nodes:        self.nodes.clone_state(cloned_state_api),
+            root:         self.root,
+            len:          self.len,
+            next_node_id: self.next_node_id,
+        }
+    }
+}
+
+unsafe impl<K, V, S: HasStateApi> StateClone<S> for StateBTreeMap<K, V, S> {
+    unsafe fn clone_state(&self, cloned_state_api: &S) -> Self {
+        Self {
+            key_value: self.key_value.clone_state(cloned_state_api),
+            key_order: self.key_order.clone_state(cloned_state_api),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NodeId, StateBTreeSet, MIN_DEGREE};
+    use crate::{collections::BTreeSet, ops::Bound, test_infrastructure::*};
+
+    /// Check the invariants of the subtree of a node, returning its height.
+    fn check_node(
+        set: &StateBTreeSet<u32, TestStateApi>,
+        node_id: NodeId,
+        is_root: bool,
+        lower: Option<u32>,
+        upper: Option<u32>,
+    ) -> usize {
+        let node = set.load_node(node_id);
+        assert!(node.keys.len() < 2 * MIN_DEGREE, "Too many keys in a node");
+        assert!(is_root || node.keys.len() >= MIN_DEGREE - 1, "Too few keys in a node");
+        assert!(!node.keys.is_empty(), "Empty node");
+        assert!(node.keys.windows(2).all(|w| w[0] < w[1]), "Keys are not sorted");
+        assert!(node.keys.iter().all(|k| lower.map_or(true, |l| l < *k)), "Key below subtree");
+        assert!(node.keys.iter().all(|k| upper.map_or(true, |u| *k < u)), "Key above subtree");
+        if node.is_leaf() {
+            return 1;
+        }
+        assert_eq!(node.children.len(), node.keys.len() + 1);
+        let heights: Vec<usize> = (0..node.children.len())
+            .map(|i| {
+                let lower = if i == 0 {
+                    lower
+                } else {
+                    Some(node.keys[i - 1])
+                };
+                let upper = node.keys.get(i).copied().or(upper);
+                check_node(set, node.children[i], false, lower, upper)
+            })
+            .collect();
+        assert!(heights.windows(2).all(|w| w[0] == w[1]), "Leaves at different depths");
+        heights[0] + 1
+    }
+
+    /// Compare the set against a `BTreeSet` with the same values.
+    fn check_set(set: &StateBTreeSet<u32, TestStateApi>, expected: &BTreeSet<u32>) {
+        if let Some(root) = set.root {
+            check_node(set, root, true, None, None);
+        }
+        assert_eq!(set.len() as usize, expected.len());
+        assert_eq!(set.is_empty(), expected.is_empty());
+        let values: Vec<u32> = set.iter().map(|v| *v).collect();
+        assert_eq!(values, expected.iter().copied().collect::<Vec<_>>());
+        assert_eq!(set.first().map(|v| *v), expected.iter().next().copied());
+        assert_eq!(set.last().map(|v| *v), expected.iter().next_back().copied());
+    }
+
+    #[test]
+    fn btree_set_insert_remove() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut set = state_builder.new_btree_set();
+        let mut expected = BTreeSet::new();
+        // A linear congruential generator, to get a reproducible sequence of
+        // insertions and removals.
+        let mut seed: u64 = 42;
+        for round in 0..3000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let value = ((seed >> 33) % 500) as u32;
+            // Mostly insert in the first rounds and mostly remove in the last
+            // rounds, to both grow and shrink the tree.
+            let insert = (seed >> 20) % 3000 >= round;
+            if insert {
+                assert_eq!(set.insert(value), expected.insert(value));
+            } else {
+                assert_eq!(set.remove(&value), expected.remove(&value));
+            }
+            assert_eq!(set.contains(&value), expected.contains(&value));
+            if round % 100 == 0 {
+                check_set(&set, &expected);
+            }
+        }
+        check_set(&set, &expected);
+        for value in expected.iter() {
+            assert!(set.remove(value));
+        }
+        check_set(&set, &BTreeSet::new());
+        assert!(set.root.is_none());
+    }
+
+    #[test]
+    fn btree_set_nearest_and_range() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut set = state_builder.new_btree_set();
+        let expected: BTreeSet<u32> = (0..300).map(|i| i * 3).collect();
+        for value in expected.iter().rev() {
+            set.insert(*value);
+        }
+        check_set(&set, &expected);
+        for value in 0..910 {
+            let higher = expected.range((Bound::Excluded(value), Bound::Unbounded)).next();
+            let lower = expected.range(..value).next_back();
+            assert_eq!(set.higher(&value).map(|v| *v), higher.copied());
+            assert_eq!(set.lower(&value).map(|v| *v), lower.copied());
+            assert_eq!(
+                set.eq_or_higher(&value).map(|v| *v),
+                expected.range(value..).next().copied()
+            );
+            assert_eq!(
+                set.eq_or_lower(&value).map(|v| *v),
+                expected.range(..=value).next_back().copied()
+            );
+        }
+        let range: Vec<u32> = set.range(100..=200).map(|v| *v).collect();
+        assert_eq!(range, expected.range(100..=200).copied().collect::<Vec<_>>());
+        let range: Vec<u32> =
+            set.range((Bound::Excluded(99), Bound::Excluded(201))).map(|v| *v).collect();
+        assert_eq!(range, expected.range(100..201).copied().collect::<Vec<_>>());
+        assert_eq!(set.range(1000..).count(), 0);
+    }
+
+    #[test]
+    fn btree_map_insert_get_remove() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut map = state_builder.new_btree_map();
+        for key in (0..100u64).rev() {
+            assert!(map.insert(key, key * 10).is_none());
+        }
+        assert_eq!(map.insert(5, 0), Some(50));
+        assert_eq!(map.len(), 100);
+        assert_eq!(*map.get(&5).expect("Key should exist"), 0);
+        map.get_mut(&6).expect("Key should exist").update(|v| *v += 1);
+        assert_eq!(*map.get(&6).expect("Key should exist"), 61);
+
+        assert_eq!(map.remove_and_get(&0), Some(0));
+        assert_eq!(map.remove_and_get(&0), None);
+        assert!(!map.contains_key(&0));
+        assert_eq!(map.first_key().map(|k| *k), Some(1));
+        assert_eq!(map.last_key().map(|k| *k), Some(99));
+        let entries: Vec<(u64, u64)> = map.range(7..10).map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(entries, [(7, 70), (8, 80), (9, 90)]);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.get(&5).is_none());
+        assert!(map.iter().next().is_none());
+    }
+
+    #[test]
+    fn btree_map_survives_serialization() {
+        let state = TestStateApi::new();
+        let mut state_builder = TestStateBuilder::open(state.clone());
+        let mut map = state_builder.new_btree_map();
+        for key in 0..50u32 {
+            map.insert(key, key);
+        }
+        let bytes = crate::to_bytes(&map);
+        let restored: super::StateBTreeMap<u32, u32, _> =
+            crate::DeserialWithState::deserial_with_state(&state, &mut crate::Cursor::new(bytes))
+                .expect("Deserializing should succeed");
+        assert_eq!(restored.len(), 50);
+        assert_eq!(restored.higher(&48).map(|k| *k), Some(49));
+        assert_eq!(restored.iter().count(), 50);
+    }
+
+    #[test]
+    fn btree_schema_matches_serialization() {
+        use crate::schema::{Fields, SchemaType, Type};
+        let mut state_builder = TestStateBuilder::new();
+        let mut map = state_builder.new_btree_map();
+        // The prefixes of the two maps, the root as `None`, the length and the
+        // next node ID.
+        assert_eq!(crate::to_bytes(&map).len(), 8 + 8 + 1 + 4 + 4);
+        map.insert(1u32, 2u32);
+        // The root as `Some`.
+        assert_eq!(crate::to_bytes(&map).len(), 8 + 8 + 1 + 4 + 4 + 4);
+        let set_type = match <super::StateBTreeMap<u32, u32, TestStateApi>>::get_type() {
+            Type::Struct(Fields::Named(fields)) => {
+                assert_eq!(fields.len(), 2);
+                assert_eq!(fields[0], (crate::String::from("prefix"), Type::ByteArray(8)));
+                assert_eq!(fields[1].0, "key_order");
+                fields[1].1.clone()
+            }
+            _ => panic!("The schema should be a struct with named fields"),
+        };
+        match set_type {
+            Type::Struct(Fields::Named(fields)) => {
+                let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+                assert_eq!(names, ["prefix", "root", "len", "next_node_id"]);
+                assert_eq!(fields[1].1, <Option<u32>>::get_type());
+            }
+            _ => panic!("The schema should be a struct with named fields"),
+        }
+    }
+}
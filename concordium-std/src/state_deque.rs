@@ -0,0 +1,338 @@
+//! A double-ended queue in the contract state.
+//!
+//! The [`StateDeque`] stores every element in a separate entry in the state,
+//! keyed by its position in a ring of [`u32`] positions, together with the
+//! position of the front and the number of elements. Adding and removing
+//! elements at either end therefore only touches the entry of that element.
+use crate::{traits::*, types::*};
+use concordium_contracts_common::*;
+
+/// A double-ended queue of elements in the contract state, similar to a
+/// [`VecDeque`](crate::collections::VecDeque), where every element is stored
+/// in a separate entry in the state.
+///
+/// Elements can be added and removed at both ends, which makes it suitable
+/// for first-in-first-out queues, e.g., of orders to match, jobs to process,
+/// or escrows ordered by their expiry.
+///
+/// As with the [`StateVec`], the front position and the number of elements
+/// are stored in the [`StateDeque`] itself, so it must be written back to the
+/// state after elements are added or removed.
+///
+/// New queues can be constructed using the
+/// [`new_deque`][StateBuilder::new_deque] method on the [`StateBuilder`].
+///
+/// ```
+/// # use concordium_std::*;
+/// # use concordium_std::test_infrastructure::*;
+/// # let mut state_builder = TestStateBuilder::new();
+/// let mut jobs = state_builder.new_deque();
+/// jobs.push_back(1u64);
+/// jobs.push_back(2u64);
+/// jobs.push_front(0u64);
+/// assert_eq!(jobs.pop_front(), Some(0));
+/// assert_eq!(jobs.front().as_deref(), Some(&1));
+/// assert_eq!(jobs.pop_back(), Some(2));
+/// ```
+///
+/// The [`SchemaType`](schema::SchemaType) of the [`StateDeque`] describes
+/// only how it is stored, not its elements, since these are stored in
+/// separate entries. To view the elements, collect them with
+/// [`iter`](Self::iter) in a view function.
+///
+/// **Dropping the [`StateDeque`] does not delete its elements from the
+/// state.** Use [`delete`](Deletable::delete) or [`clear`](StateDeque::clear)
+/// to remove them.
+#[derive(Debug)]
+pub struct StateDeque<T, S> {
+    pub(crate) elements: StateMap<u32, T, S>,
+    /// The position of the front element.
+    pub(crate) head:     u32,
+    pub(crate) len:      u32,
+}
+
+#[derive(Debug)]
+/// An iterator over the elements of a [`StateDeque`], from front to back.
+///
+/// This `struct` is created by the [`iter`][StateDeque::iter] method on
+/// [`StateDeque`]. See its documentation for more.
+pub struct StateDequeIter<'a, T, S> {
+    deque: &'a StateDeque<T, S>,
+    /// The index of the next element from the front.
+    front: u32,
+    /// One more than the index of the next element from the back.
+    back:  u32,
+}
+
+impl<T, S> StateDeque<T, S>
+where
+    S: HasStateApi,
+{
+    pub(crate) fn open(elements: StateMap<u32, T, S>) -> Self {
+        Self {
+            elements,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Return the number of elements in the queue.
+    pub fn len(&self) -> u32 { self.len }
+
+    /// Return `true` if the queue contains no elements.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// The position in the ring of the element at the given index.
+    fn position(&self, index: u32) -> u32 { self.head.wrapping_add(index) }
+}
+
+impl<T, S> StateDeque<T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S>,
+{
+    /// Add an element to the back of the queue.
+    ///
+    /// Panics if the queue already contains [`u32::MAX`] elements.
+    pub fn push_back(&mut self, value: T) {
+        let position = self.position(self.len);
+        self.len = self.len.checked_add(1).unwrap_abort();
+        let _ = self.elements.insert(position, value);
+    }
+
+    /// Add an element to the front of the queue.
+    ///
+    /// Panics if the queue already contains [`u32::MAX`] elements.
+    pub fn push_front(&mut self, value: T) {
+        self.len = self.len.checked_add(1).unwrap_abort();
+        self.head = self.head.wrapping_sub(1);
+        let _ = self.elements.insert(self.head, value);
+    }
+
+    /// Remove the element at the back of the queue and return it, or [None]
+    /// if the queue is empty.
+    ///
+    /// *Caution*: If `T` is a [StateBox], [StateMap], then it is
+    /// important to call [`Deletable::delete`] on the value returned when
+    /// you're finished with it. Otherwise, it will remain in the contract
+    /// state.
+    #[must_use]
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        let position = self.position(self.len);
+        self.elements.remove_and_get(&position)
+    }
+
+    /// Remove the element at the front of the queue and return it, or [None]
+    /// if the queue is empty.
+    ///
+    /// *Caution*: If `T` is a [StateBox], [StateMap], then it is
+    /// important to call [`Deletable::delete`] on the value returned when
+    /// you're finished with it. Otherwise, it will remain in the contract
+    /// state.
+    #[must_use]
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        let position = self.head;
+        self.head = self.head.wrapping_add(1);
+        self.elements.remove_and_get(&position)
+    }
+
+    /// Return the element at the front of the queue, or [None] if it is
+    /// empty.
+    pub fn front(&self) -> Option<StateRef<T>> { self.get(0) }
+
+    /// Return the element at the back of the queue, or [None] if it is empty.
+    pub fn back(&self) -> Option<StateRef<T>> { self.get(self.len.checked_sub(1)?) }
+
+    /// Lookup the element at the given index, counted from the front. Return
+    /// [None] if the index is out of bounds.
+    pub fn get(&self, index: u32) -> Option<StateRef<T>> {
+        if index < self.len {
+            self.elements.get(&self.position(index))
+        } else {
+            None
+        }
+    }
+
+    /// Lookup a mutable reference to the element at the given index, counted
+    /// from the front. Return [None] if the index is out of bounds.
+    pub fn get_mut(&self, index: u32) -> Option<StateRefMut<T, S>> {
+        if index < self.len {
+            self.elements.get_mut(&self.position(index))
+        } else {
+            None
+        }
+    }
+
+    /// Clears the queue, removing all elements.
+    /// This also includes values pointed at, if `T`, for example, is a
+    /// [StateBox].
+    pub fn clear(&mut self)
+    where
+        T: Deletable, {
+        self.elements.clear();
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Get an iterator over the elements of the queue, from front to back.
+    pub fn iter(&self) -> StateDequeIter<'_, T, S> {
+        StateDequeIter {
+            deque: self,
+            front: 0,
+            back:  self.len,
+        }
+    }
+}
+
+impl<'a, T, S> Iterator for StateDequeIter<'a, T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + 'a,
+{
+    type Item = StateRef<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let index = self.front;
+        self.front += 1;
+        self.deque.get(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.back - self.front) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T, S> DoubleEndedIterator for StateDequeIter<'a, T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.deque.get(self.back)
+    }
+}
+
+impl<'a, T, S> ExactSizeIterator for StateDequeIter<'a, T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + 'a,
+{
+}
+
+impl<T, S> Serial for StateDeque<T, S> {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        self.elements.serial(out)?;
+        self.head.serial(out)?;
+        self.len.serial(out)
+    }
+}
+
+impl<T, S> DeserialWithState<S> for StateDeque<T, S>
+where
+    S: HasStateApi,
+{
+    fn deserial_with_state<R: Read>(state: &S, source: &mut R) -> ParseResult<Self> {
+        let elements = StateMap::deserial_with_state(state, source)?;
+        let head = source.get()?;
+        let len = source.get()?;
+        Ok(Self {
+            elements,
+            head,
+            len,
+        })
+    }
+}
+
+impl<T, S> schema::SchemaType for StateDeque<T, S> {
+    fn get_type() -> schema::Type {
+        schema::Type::Struct(schema::Fields::Named(crate::vec![
+            (crate::String::from("prefix"), schema::Type::ByteArray(8)),
+            (crate::String::from("head"), schema::Type::U32),
+            (crate::String::from("len"), schema::Type::U32),
+        ]))
+    }
+}
+
+impl<T, S> Deletable for StateDeque<T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + Deletable,
+{
+    fn delete(mut self) { self.clear(); }
+}
+
+unsafe impl<T, S: HasStateApi> StateClone<S> for StateDeque<T, S> {
+    unsafe fn clone_state(&self, cloned_state_api: &S) -> Self {
+        Self {
+            elements: self.elements.clone_state(cloned_state_api),
+            head:     self.head,
+            len:      self.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{collections::VecDeque, test_infrastructure::*};
+
+    #[test]
+    fn deque_push_pop_both_ends() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut deque = state_builder.new_deque();
+        let mut expected = VecDeque::new();
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+        // Pushing to the front first wraps the positions around below zero.
+        for i in 0..200u32 {
+            match i % 5 {
+                0 | 1 => {
+                    deque.push_front(i);
+                    expected.push_front(i);
+                }
+                2 | 3 => {
+                    deque.push_back(i);
+                    expected.push_back(i);
+                }
+                _ => assert_eq!(deque.pop_front(), expected.pop_front()),
+            }
+            assert_eq!(deque.len() as usize, expected.len());
+            assert_eq!(deque.front().map(|v| *v), expected.front().copied());
+            assert_eq!(deque.back().map(|v| *v), expected.back().copied());
+        }
+        let values: Vec<u32> = deque.iter().map(|v| *v).collect();
+        assert_eq!(values, expected.iter().copied().collect::<Vec<_>>());
+        let values: Vec<u32> = deque.iter().rev().map(|v| *v).collect();
+        assert_eq!(values, expected.iter().rev().copied().collect::<Vec<_>>());
+        deque.get_mut(3).expect("Index is in bounds").set(1000);
+        expected[3] = 1000;
+        assert_eq!(*deque.get(3).expect("Index is in bounds"), 1000);
+        while let Some(value) = expected.pop_back() {
+            assert_eq!(deque.pop_back(), Some(value));
+        }
+        assert!(deque.is_empty());
+        assert!(deque.front().is_none());
+    }
+
+    #[test]
+    fn deque_clear() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut deque = state_builder.new_deque();
+        deque.push_front(1u8);
+        deque.push_back(2u8);
+        deque.clear();
+        assert!(deque.is_empty());
+        assert!(deque.iter().next().is_none());
+        deque.push_back(3u8);
+        assert_eq!(deque.iter().map(|v| *v).collect::<Vec<_>>(), [3]);
+    }
+}
@@ -0,0 +1,306 @@
+//! An indexable list in the contract state.
+//!
+//! The [`StateVec`] stores every element in a separate entry in the state,
+//! keyed by its index, together with the number of elements. This makes
+//! appending, removing the last element, and accessing an element by index
+//! cheap, independently of the number of elements, since only the accessed
+//! element is loaded.
+use crate::{traits::*, types::*};
+use concordium_contracts_common::*;
+
+/// A growable list of elements in the contract state, similar to a
+/// [`Vec`](crate::vec::Vec), where every element is stored in a separate entry
+/// in the state.
+///
+/// Elements can only be added and removed at the end, which makes it suitable
+/// for append-only logs and lists which are accessed by index, e.g., the
+/// purchase history of an account, or the proposals of a DAO.
+///
+/// The number of elements is stored in the [`StateVec`] itself. Thus, the
+/// [`StateVec`] must be written back to the state after elements are added
+/// or removed, e.g., by being part of the contract state, which is written
+/// back after a mutable receive method.
+///
+/// New vectors can be constructed using the
+/// [`new_vec`][StateBuilder::new_vec] method on the [`StateBuilder`].
+///
+/// ```
+/// # use concordium_std::*;
+/// # use concordium_std::test_infrastructure::*;
+/// # let mut state_builder = TestStateBuilder::new();
+/// let mut history = state_builder.new_vec();
+/// history.push(100u64);
+/// history.push(250u64);
+/// assert_eq!(history.len(), 2);
+/// assert_eq!(history.get(1).as_deref(), Some(&250));
+/// assert_eq!(history.pop(), Some(250));
+/// ```
+///
+/// ## Type parameters
+///
+/// The `StateVec<T, S>` is parametrized by the type of _elements_, `T`, and
+/// the type of the low-level state, `S`. As with the [`StateMap`], the type
+/// `S` should be left generic in the contract code.
+///
+/// **Dropping the [`StateVec`] does not delete its elements from the state.**
+/// Use [`delete`](Deletable::delete) or [`clear`](StateVec::clear) to remove
+/// them.
+#[derive(Debug)]
+pub struct StateVec<T, S> {
+    pub(crate) elements: StateMap<u32, T, S>,
+    pub(crate) len:      u32,
+}
+
+#[derive(Debug)]
+/// An iterator over the elements of a [`StateVec`], in order of their index.
+///
+/// This `struct` is created by the [`iter`][StateVec::iter] method on
+/// [`StateVec`]. See its documentation for more.
+pub struct StateVecIter<'a, T, S> {
+    vec:   &'a StateVec<T, S>,
+    /// The index of the next element from the front.
+    front: u32,
+    /// One more than the index of the next element from the back.
+    back:  u32,
+}
+
+impl<T, S> StateVec<T, S>
+where
+    S: HasStateApi,
+{
+    pub(crate) fn open(elements: StateMap<u32, T, S>) -> Self {
+        Self {
+            elements,
+            len: 0,
+        }
+    }
+
+    /// Return the number of elements in the vector.
+    pub fn len(&self) -> u32 { self.len }
+
+    /// Return `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+}
+
+impl<T, S> StateVec<T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S>,
+{
+    /// Append an element to the end of the vector.
+    ///
+    /// Panics if the vector already contains [`u32::MAX`] elements.
+    pub fn push(&mut self, value: T) {
+        let index = self.len;
+        self.len = self.len.checked_add(1).unwrap_abort();
+        let _ = self.elements.insert(index, value);
+    }
+
+    /// Remove the last element from the vector and return it, or [None] if
+    /// the vector is empty.
+    ///
+    /// *Caution*: If `T` is a [StateBox], [StateMap], then it is
+    /// important to call [`Deletable::delete`] on the value returned when
+    /// you're finished with it. Otherwise, it will remain in the contract
+    /// state.
+    #[must_use]
+    pub fn pop(&mut self) -> Option<T> {
+        let index = self.len.checked_sub(1)?;
+        self.len = index;
+        self.elements.remove_and_get(&index)
+    }
+
+    /// Lookup the element at the given index. Return [None] if the index is
+    /// out of bounds.
+    pub fn get(&self, index: u32) -> Option<StateRef<T>> {
+        if index < self.len {
+            self.elements.get(&index)
+        } else {
+            None
+        }
+    }
+
+    /// Lookup a mutable reference to the element at the given index. Return
+    /// [None] if the index is out of bounds.
+    pub fn get_mut(&self, index: u32) -> Option<StateRefMut<T, S>> {
+        if index < self.len {
+            self.elements.get_mut(&index)
+        } else {
+            None
+        }
+    }
+
+    /// Replace the element at the given index, returning the old element.
+    /// Return [None] and leave the vector unchanged if the index is out of
+    /// bounds.
+    ///
+    /// *Caution*: If `T` is a [StateBox], [StateMap], then it is
+    /// important to call [`Deletable::delete`] on the value returned when
+    /// you're finished with it. Otherwise, it will remain in the contract
+    /// state.
+    #[must_use]
+    pub fn set(&mut self, index: u32, value: T) -> Option<T> {
+        if index < self.len {
+            self.elements.insert(index, value)
+        } else {
+            None
+        }
+    }
+
+    /// Return the last element of the vector, or [None] if it is empty.
+    pub fn last(&self) -> Option<StateRef<T>> { self.get(self.len.checked_sub(1)?) }
+
+    /// Clears the vector, removing all elements.
+    /// This also includes values pointed at, if `T`, for example, is a
+    /// [StateBox].
+    pub fn clear(&mut self)
+    where
+        T: Deletable, {
+        self.elements.clear();
+        self.len = 0;
+    }
+
+    /// Get an iterator over the elements of the vector, in order of their
+    /// index.
+    pub fn iter(&self) -> StateVecIter<'_, T, S> {
+        StateVecIter {
+            vec:   self,
+            front: 0,
+            back:  self.len,
+        }
+    }
+}
+
+impl<'a, T, S> Iterator for StateVecIter<'a, T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + 'a,
+{
+    type Item = StateRef<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let index = self.front;
+        self.front += 1;
+        self.vec.get(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.back - self.front) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T, S> DoubleEndedIterator for StateVecIter<'a, T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.vec.get(self.back)
+    }
+}
+
+impl<'a, T, S> ExactSizeIterator for StateVecIter<'a, T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + 'a,
+{
+}
+
+impl<T, S> Serial for StateVec<T, S> {
+    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        self.elements.serial(out)?;
+        self.len.serial(out)
+    }
+}
+
+impl<T, S> DeserialWithState<S> for StateVec<T, S>
+where
+    S: HasStateApi,
+{
+    fn deserial_with_state<R: Read>(state: &S, source: &mut R) -> ParseResult<Self> {
+        let elements = StateMap::deserial_with_state(state, source)?;
+        let len = source.get()?;
+        Ok(Self {
+            elements,
+            len,
+        })
+    }
+}
+
+impl<T, S> Deletable for StateVec<T, S>
+where
+    S: HasStateApi,
+    T: Serial + DeserialWithState<S> + Deletable,
+{
+    fn delete(mut self) { self.clear(); }
+}
+
+unsafe impl<T, S: HasStateApi> StateClone<S> for StateVec<T, S> {
+    unsafe fn clone_state(&self, cloned_state_api: &S) -> Self {
+        Self {
+            elements: self.elements.clone_state(cloned_state_api),
+            len:      self.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_infrastructure::*;
+
+    #[test]
+    fn vec_push_pop_get_set() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut vec = state_builder.new_vec();
+        assert!(vec.is_empty());
+        assert_eq!(vec.pop(), None);
+        for i in 0..300u64 {
+            vec.push(i * 2);
+        }
+        assert_eq!(vec.len(), 300);
+        assert_eq!(*vec.get(150).expect("Index is in bounds"), 300);
+        assert!(vec.get(300).is_none());
+        assert_eq!(*vec.last().expect("Vector is not empty"), 598);
+
+        assert_eq!(vec.set(10, 7), Some(20));
+        assert_eq!(vec.set(300, 7), None);
+        assert_eq!(vec.len(), 300);
+        vec.get_mut(11).expect("Index is in bounds").update(|v| *v += 1);
+        assert_eq!(*vec.get(10).expect("Index is in bounds"), 7);
+        assert_eq!(*vec.get(11).expect("Index is in bounds"), 23);
+
+        assert_eq!(vec.pop(), Some(598));
+        assert_eq!(vec.len(), 299);
+        assert!(vec.get(299).is_none());
+        vec.push(1);
+        assert_eq!(*vec.get(299).expect("Index is in bounds"), 1);
+    }
+
+    #[test]
+    fn vec_iter_and_clear() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut vec = state_builder.new_vec();
+        for i in 0..5u32 {
+            vec.push(i);
+        }
+        let values: Vec<u32> = vec.iter().map(|v| *v).collect();
+        assert_eq!(values, [0, 1, 2, 3, 4]);
+        let values: Vec<u32> = vec.iter().rev().map(|v| *v).collect();
+        assert_eq!(values, [4, 3, 2, 1, 0]);
+        assert_eq!(vec.iter().len(), 5);
+
+        vec.clear();
+        assert!(vec.is_empty());
+        assert!(vec.iter().next().is_none());
+        vec.push(9);
+        assert_eq!(*vec.get(0).expect("Index is in bounds"), 9);
+    }
+}
@@ -338,7 +338,16 @@ impl<'a> Read for TestParameterCursor<'a> {
     fn read(&mut self, buf: &mut [u8]) -> ParseResult<usize> { self.cursor.read(buf) }
 }
 
-impl<'a> HasParameter for TestParameterCursor<'a> {}
+impl<'a> HasParameter for TestParameterCursor<'a> {
+    /// Borrow the next `len` bytes of the parameter without copying them.
+    fn read_bytes_ref(&mut self, len: u32) -> ParseResult<Cow<[u8]>> {
+        let start = self.cursor.offset;
+        let end = start.checked_add(len as usize).ok_or_else(ParseError::default)?;
+        let bytes = self.cursor.data.get(start..end).ok_or_else(ParseError::default)?;
+        self.cursor.offset = end;
+        Ok(Cow::Borrowed(bytes))
+    }
+}
 
 // Setters for testing-context
 impl TestChainMeta {
@@ -540,6 +549,46 @@ impl<'a> HasReceiveContext for TestReceiveContext<'a> {
     }
 }
 
+/// A seedable source of pseudo-random numbers implementing [`HasRandomness`],
+/// intended for unit testing contracts with random-dependent logic.
+///
+/// The same seed always produces the same sequence of numbers, making the
+/// outcome of tests reproducible. The numbers are **not** suitable for any
+/// use outside of testing.
+/// Use only in unit tests!
+///
+/// ```rust
+/// # use concordium_std::{*, test_infrastructure::*};
+/// let mut first = TestRandomness::new(42);
+/// let mut second = TestRandomness::new(42);
+/// assert_eq!(first.next_u64(), second.next_u64());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TestRandomness {
+    state: u64,
+}
+
+impl TestRandomness {
+    /// Create a new source of pseudo-random numbers from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+        }
+    }
+}
+
+impl HasRandomness for TestRandomness {
+    // This is the SplitMix64 generator, which is small and has good statistical
+    // properties for testing purposes.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
 /// A logger that simply accumulates all the logged items to be inspected at the
 /// end of execution.
 pub struct TestLogger {
@@ -590,6 +639,233 @@ pub fn report_error(message: &str, filename: &str, line: u32, column: u32) {
 #[cfg(not(all(feature = "wasm-test", target_arch = "wasm32")))]
 pub fn report_error(_message: &str, _filename: &str, _line: u32, _column: u32) {}
 
+/// Parse a timestamp in the ISO 8601 format, e.g., `2023-01-31T12:30:00Z`,
+/// for writing readable test fixtures. A date without a time, e.g.,
+/// `2023-01-31`, is midnight UTC. The time may have milliseconds, e.g.,
+/// `12:30:00.250`, and must otherwise end with `Z` or an offset from UTC,
+/// e.g., `+02:00`. Returns [`None`] if the text is not in this format or the
+/// time is before the Unix epoch.
+///
+/// ```
+/// # use concordium_std::*;
+/// # use concordium_std::test_infrastructure::*;
+/// let ends = timestamp_from_iso8601("1970-01-02T00:00:01Z").unwrap();
+/// assert_eq!(ends.timestamp_millis(), 86_401_000);
+/// ```
+pub fn timestamp_from_iso8601(text: &str) -> Option<Timestamp> {
+    /// Parse a fixed number of decimal digits.
+    fn digits(text: &str) -> Option<i64> {
+        if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        text.parse().ok()
+    }
+
+    if !text.is_ascii() {
+        return None;
+    }
+    let (date, time) = match text.find('T') {
+        Some(pos) => (&text[..pos], Some(&text[pos + 1..])),
+        None => (text, None),
+    };
+    if date.len() != 10 || &date[4..5] != "-" || &date[7..8] != "-" {
+        return None;
+    }
+    let year = digits(&date[..4])?;
+    let month = digits(&date[5..7])?;
+    let day = digits(&date[8..10])?;
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+    // The number of days since the epoch, counting years from March, such
+    // that the leap day is the last day of the year.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let mut millis = days * 86_400_000;
+    if let Some(time) = time {
+        let (clock, offset) = match time.find(|c: char| c == 'Z' || c == '+' || c == '-') {
+            Some(pos) => time.split_at(pos),
+            None => return None,
+        };
+        let (clock, fraction) = match clock.find('.') {
+            Some(pos) => (&clock[..pos], Some(&clock[pos + 1..])),
+            None => (clock, None),
+        };
+        if clock.len() != 8 || &clock[2..3] != ":" || &clock[5..6] != ":" {
+            return None;
+        }
+        let hours = digits(&clock[..2])?;
+        let minutes = digits(&clock[3..5])?;
+        let seconds = digits(&clock[6..8])?;
+        if hours > 23 || minutes > 59 || seconds > 59 {
+            return None;
+        }
+        millis += ((hours * 60 + minutes) * 60 + seconds) * 1000;
+        if let Some(fraction) = fraction {
+            if fraction.len() != 3 {
+                return None;
+            }
+            millis += digits(fraction)?;
+        }
+        if offset != "Z" {
+            if offset.len() != 6 || &offset[3..4] != ":" {
+                return None;
+            }
+            let offset_hours = digits(&offset[1..3])?;
+            let offset_minutes = digits(&offset[4..6])?;
+            if offset_hours > 23 || offset_minutes > 59 {
+                return None;
+            }
+            let offset_millis = (offset_hours * 60 + offset_minutes) * 60_000;
+            // A time ahead of UTC is earlier in UTC.
+            if offset.starts_with('+') {
+                millis -= offset_millis;
+            } else {
+                millis += offset_millis;
+            }
+        }
+    }
+    millis.try_into().ok().map(Timestamp::from_timestamp_millis)
+}
+
+/// An error that is raised when decoding a parameter with
+/// [`parameter_from_hex`] or [`parameter_from_base64`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ParameterDecodeError {
+    /// The length of the text is not valid for the encoding, i.e., it is odd
+    /// for hex, or not a multiple of four for base64.
+    InvalidLength,
+    /// The character starting at the byte offset `index` of the text is not
+    /// valid in the encoding.
+    InvalidCharacter {
+        /// The byte offset of the invalid character in the text.
+        index: usize,
+    },
+    /// The base64 text has padding before its end, or more padding than
+    /// allowed, or the bits covered by the padding are not zero.
+    InvalidPadding,
+}
+
+impl fmt::Display for ParameterDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterDecodeError::InvalidLength => {
+                write!(f, "The length of the text is not valid for the encoding.")
+            }
+            ParameterDecodeError::InvalidCharacter {
+                index,
+            } => write!(f, "Invalid character at byte offset {} of the text.", index),
+            ParameterDecodeError::InvalidPadding => write!(f, "Invalid padding of the text."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParameterDecodeError {}
+
+/// Decode a parameter from a hex string, such as the parameters shown by block
+/// explorers, for reproducing a transaction in a test. Both lowercase and
+/// uppercase hex digits are accepted.
+///
+/// ```
+/// # use concordium_std::test_infrastructure::*;
+/// let parameter = parameter_from_hex("2a00000000000000").unwrap();
+/// let mut ctx = TestReceiveContext::empty();
+/// ctx.set_parameter(&parameter);
+/// ```
+pub fn parameter_from_hex(text: &str) -> Result<Vec<u8>, ParameterDecodeError> {
+    fn digit(bytes: &[u8], index: usize) -> Result<u8, ParameterDecodeError> {
+        match bytes[index] {
+            b @ b'0'..=b'9' => Ok(b - b'0'),
+            b @ b'a'..=b'f' => Ok(b - b'a' + 10),
+            b @ b'A'..=b'F' => Ok(b - b'A' + 10),
+            _ => Err(ParameterDecodeError::InvalidCharacter {
+                index,
+            }),
+        }
+    }
+
+    let bytes = text.as_bytes();
+    if bytes.len() % 2 != 0 {
+        return Err(ParameterDecodeError::InvalidLength);
+    }
+    (0..bytes.len()).step_by(2).map(|i| Ok(digit(bytes, i)? << 4 | digit(bytes, i + 1)?)).collect()
+}
+
+/// Decode a parameter from a string in the standard base64 encoding with
+/// padding, such as the parameters found in the logs of dApps, for reproducing
+/// a transaction in a test.
+///
+/// ```
+/// # use concordium_std::test_infrastructure::*;
+/// assert_eq!(parameter_from_base64("KgAAAAAAAAA="), parameter_from_hex("2a00000000000000"));
+/// ```
+pub fn parameter_from_base64(text: &str) -> Result<Vec<u8>, ParameterDecodeError> {
+    fn sextet(byte: u8) -> Option<u8> {
+        match byte {
+            b'A'..=b'Z' => Some(byte - b'A'),
+            b'a'..=b'z' => Some(byte - b'a' + 26),
+            b'0'..=b'9' => Some(byte - b'0' + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let bytes = text.as_bytes();
+    if bytes.len() % 4 != 0 {
+        return Err(ParameterDecodeError::InvalidLength);
+    }
+    let padding = bytes.iter().rev().take(2).take_while(|&&b| b == b'=').count();
+    let data = &bytes[..bytes.len() - padding];
+    let mut parameter = Vec::with_capacity(data.len() / 4 * 3 + 2);
+    // The sextets of the current group of four characters.
+    let mut group: u32 = 0;
+    for (index, &byte) in data.iter().enumerate() {
+        let value = match sextet(byte) {
+            Some(value) => value,
+            None if byte == b'=' => return Err(ParameterDecodeError::InvalidPadding),
+            None => {
+                return Err(ParameterDecodeError::InvalidCharacter {
+                    index,
+                })
+            }
+        };
+        group = group << 6 | u32::from(value);
+        if index % 4 == 3 {
+            parameter.extend_from_slice(&group.to_be_bytes()[1..]);
+            group = 0;
+        }
+    }
+    // The last group has 18 bits with one padding character, and 12 bits with
+    // two, of which the bits beyond the last byte must be zero.
+    match padding {
+        1 if group & 0b11 == 0 => {
+            parameter.extend_from_slice(&[(group >> 10) as u8, (group >> 2) as u8])
+        }
+        2 if group & 0b1111 == 0 => parameter.push((group >> 4) as u8),
+        0 => {}
+        _ => return Err(ParameterDecodeError::InvalidPadding),
+    }
+    Ok(parameter)
+}
+
 #[derive(Debug, PartialEq, Eq)]
 /// An error that is raised when operating with `Seek`, `Write`, `Read`, or
 /// `HasStateEntry` trait methods of the `TestStateApi` type.
@@ -1275,6 +1551,10 @@ type MockFnMap<State> = BTreeMap<(ContractAddress, OwnedEntrypointName), MockFn<
 /// A map from module references to the mocked result.
 type MockUpgradeMap = BTreeMap<ModuleReference, UpgradeResult>;
 
+/// A mocking function for [`HasHost::check_account_signature`].
+type MockFnCheckAccountSignature =
+    Box<dyn FnMut(AccountAddress, &AccountSignatures, &[u8]) -> bool>;
+
 /// A [`Host`](HasHost) implementation used for unit testing smart contracts.
 ///
 /// The host provides a way to set up mock responses to transfers, and to
@@ -1289,32 +1569,79 @@ pub struct TestHost<State> {
     /// Functions that mock responses to calls.
     // This is Rc+RefCell because it needs to be cloneable. There might be another way to make the
     // MockFn cloneable, but this seemed like the easiest option.
-    mocking_fns:             Rc<RefCell<MockFnMap<State>>>,
+    mocking_fns:               Rc<RefCell<MockFnMap<State>>>,
     /// Transfers the contract has made during its execution.
-    transfers:               RefCell<Vec<(AccountAddress, Amount)>>,
+    transfers:                 RefCell<Vec<(AccountAddress, Amount)>>,
     /// The contract balance. This is updated during execution based on contract
     /// invocations, e.g., a successful transfer from the contract decreases it.
-    contract_balance:        RefCell<Amount>,
+    contract_balance:          RefCell<Amount>,
     /// The address of the instance. This is used when querying its own balance.
-    contract_address:        Option<ContractAddress>,
+    contract_address:          Option<ContractAddress>,
     /// Map from module references to the mocked results of upgrading to this
     /// particular module.
-    mocking_upgrades:        RefCell<MockUpgradeMap>,
+    mocking_upgrades:          RefCell<MockUpgradeMap>,
     /// StateBuilder for the state.
-    state_builder:           StateBuilder<TestStateApi>,
+    state_builder:             StateBuilder<TestStateApi>,
     /// State of the instance.
-    state:                   State,
+    state:                     State,
     /// Account balances, is used when querying the balance of an account.
-    query_account_balances:  RefCell<BTreeMap<AccountAddress, AccountBalance>>,
+    query_account_balances:    RefCell<BTreeMap<AccountAddress, AccountBalance>>,
     /// Contract balances, is used when querying the balance of a contract.
-    query_contract_balances: RefCell<BTreeMap<ContractAddress, Amount>>,
+    query_contract_balances:   RefCell<BTreeMap<ContractAddress, Amount>>,
+    /// Account public keys, is used when querying the public keys of an
+    /// account.
+    query_account_public_keys: BTreeMap<AccountAddress, AccountPublicKeys>,
+    /// Function that mocks checking signatures of accounts. If not set up,
+    /// signatures are checked against the account public keys.
+    account_signature_mock:    Rc<RefCell<Option<MockFnCheckAccountSignature>>>,
     /// Current exchange rates, is used when querying the exchange rates.
-    query_exchange_rates:    Option<ExchangeRates>,
+    query_exchange_rates:      Option<ExchangeRates>,
     /// List of accounts that will cause a contract invocation to fail.
-    missing_accounts:        BTreeSet<AccountAddress>,
+    missing_accounts:          BTreeSet<AccountAddress>,
     /// List of contracts that will cause a query for contract balance to result
     /// in a missing contract error.
-    missing_contracts:       BTreeSet<ContractAddress>,
+    missing_contracts:         BTreeSet<ContractAddress>,
+}
+
+/// Check the signatures on the data by the account against the public keys
+/// and thresholds of the account, in the same way as the chain.
+#[cfg(feature = "crypto-primitives")]
+fn check_signatures_with_keys(
+    address: AccountAddress,
+    keys: &AccountPublicKeys,
+    signatures: &AccountSignatures,
+    data: &[u8],
+) -> bool {
+    let crypto_primitives = TestCryptoPrimitives::new();
+    let mut message = address.0.to_vec();
+    message.extend_from_slice(&[0u8; 8]);
+    message.extend_from_slice(data);
+    let hash = crypto_primitives.hash_sha2_256(&message);
+    let mut valid_credentials = 0usize;
+    for (credential_index, credential_signatures) in signatures.sigs.iter() {
+        // Signatures by credentials or keys not on the account are invalid.
+        let credential_keys = match keys.keys.get(credential_index) {
+            Some(credential_keys) => credential_keys,
+            None => return false,
+        };
+        let mut valid_signatures = 0usize;
+        for (key_index, signature) in credential_signatures.sigs.iter() {
+            let valid = match (credential_keys.keys.get(key_index), signature) {
+                (Some(PublicKey::Ed25519(key)), Signature::Ed25519(signature)) => {
+                    crypto_primitives.verify_ed25519_signature(*key, *signature, &hash.0)
+                }
+                (None, _) => false,
+            };
+            if !valid {
+                return false;
+            }
+            valid_signatures += 1;
+        }
+        if valid_signatures >= usize::from(credential_keys.threshold.get()) {
+            valid_credentials += 1;
+        }
+    }
+    valid_credentials >= usize::from(keys.threshold.get())
 }
 
 impl<State: Serial + DeserialWithState<TestStateApi> + StateClone<TestStateApi>> HasHost<State>
@@ -1509,6 +1836,51 @@ impl<State: Serial + DeserialWithState<TestStateApi> + StateClone<TestStateApi>>
         }
     }
 
+    fn account_public_keys(&self, address: AccountAddress) -> QueryAccountPublicKeysResult {
+        if self.missing_accounts.contains(&address) {
+            Err(QueryAccountPublicKeysError)
+        } else if let Some(keys) = self.query_account_public_keys.get(&address) {
+            Ok(keys.clone())
+        } else {
+            fail!("No account public keys for {:?} have been set up.", address)
+        }
+    }
+
+    /// Check the signatures on the data by the account.
+    ///
+    /// If a mock has been set up with `setup_check_account_signature_mock` it
+    /// is used. Otherwise, the signatures are checked against the keys set up
+    /// with `setup_query_account_public_keys`, which requires the
+    /// "crypto-primitives" feature.
+    fn check_account_signature(
+        &self,
+        address: AccountAddress,
+        signatures: &AccountSignatures,
+        data: &[u8],
+    ) -> CheckAccountSignatureResult {
+        if self.missing_accounts.contains(&address) {
+            return Err(CheckAccountSignatureError::MissingAccount);
+        }
+        if let Some(ref mut mock) = *self.account_signature_mock.borrow_mut() {
+            return Ok(mock(address, signatures, data));
+        }
+        #[cfg(feature = "crypto-primitives")]
+        {
+            match self.query_account_public_keys.get(&address) {
+                Some(keys) => Ok(check_signatures_with_keys(address, keys, signatures, data)),
+                None => fail!("No account public keys for {:?} have been set up.", address),
+            }
+        }
+        #[cfg(not(feature = "crypto-primitives"))]
+        {
+            fail!(
+                "Checking account signatures against the account public keys requires the \
+                 \"crypto-primitives\" feature. Alternatively, set up a mock with \
+                 `setup_check_account_signature_mock`."
+            )
+        }
+    }
+
     fn exchange_rates(&self) -> ExchangeRates {
         if let Some(exchange_rates) = self.query_exchange_rates {
             exchange_rates
@@ -1586,6 +1958,8 @@ impl<State: Serial + DeserialWithState<TestStateApi>> TestHost<State> {
             missing_contracts: BTreeSet::new(),
             query_account_balances: RefCell::new(BTreeMap::new()),
             query_contract_balances: RefCell::new(BTreeMap::new()),
+            query_account_public_keys: BTreeMap::new(),
+            account_signature_mock: Rc::new(RefCell::new(None)),
             query_exchange_rates: None,
         }
     }
@@ -1688,6 +2062,41 @@ impl<State: Serial + DeserialWithState<TestStateApi>> TestHost<State> {
         self.query_account_balances.borrow_mut().insert(address, account_balance);
     }
 
+    /// Setup the public keys of an account.
+    /// This is used to resolve queries for the public keys of accounts.
+    ///
+    /// This method panics if the address provided is already setup as a missing
+    /// account.
+    ///
+    /// Note: Setting up the public keys for the same address will overwrite
+    /// the previous setup for that address.
+    pub fn setup_query_account_public_keys(
+        &mut self,
+        address: AccountAddress,
+        public_keys: AccountPublicKeys,
+    ) {
+        if self.missing_accounts.contains(&address) {
+            fail!(
+                "Setting up the query account public keys for the provided address is not \
+                 possible, because the address is marked as a missing account."
+            )
+        }
+        self.query_account_public_keys.insert(address, public_keys);
+    }
+
+    /// Set up a mock for checking the signatures of accounts, which is given
+    /// the account, the signatures and the data, and returns whether the
+    /// signatures are valid. This replaces checking the signatures against
+    /// the account public keys, which requires the "crypto-primitives" feature.
+    ///
+    /// Accounts marked as missing still result in a
+    /// [`CheckAccountSignatureError::MissingAccount`] error.
+    pub fn setup_check_account_signature_mock<F>(&mut self, mock: F)
+    where
+        F: FnMut(AccountAddress, &AccountSignatures, &[u8]) -> bool + 'static, {
+        *self.account_signature_mock.borrow_mut() = Some(Box::new(mock));
+    }
+
     /// Setup a balance for a contract.
     /// This is used to resolve queries for contract balances.
     ///
@@ -1714,8 +2123,24 @@ impl<State: Serial + DeserialWithState<TestStateApi>> TestHost<State> {
 
     /// Set the current exchange rates.
     /// This is used for resolving a query for the current exchange rates.
+    /// Querying the exchange rates without setting them first will panic.
     ///
     /// Note: Setting this again overwrites the previously set exchange rates.
+    ///
+    /// ```no_run
+    /// # use concordium_std::test_infrastructure::*;
+    /// # use concordium_std::*;
+    /// # let mut host = TestHost::new((), TestStateBuilder::new());
+    /// host.set_exchange_rates(ExchangeRates {
+    ///     euro_per_energy:    ExchangeRate::new_unchecked(1, 50_000),
+    ///     micro_ccd_per_euro: ExchangeRate::new_unchecked(3_000_000, 1),
+    /// });
+    /// // 1 euro cent is worth 30_000 microCCD at this rate.
+    /// assert_eq!(
+    ///     host.exchange_rates().convert_euro_cent_to_amount(1),
+    ///     Amount::from_micro_ccd(30_000)
+    /// );
+    /// ```
     pub fn set_exchange_rates(&mut self, exchange_rates: ExchangeRates) {
         self.query_exchange_rates = Some(exchange_rates);
     }
@@ -1752,7 +2177,7 @@ impl<State: Serial + DeserialWithState<TestStateApi>> TestHost<State> {
     /// This differs from the default, where all accounts are assumed to exist.
     ///
     /// This method panics if the address provided is already setup for a query
-    /// account balance.
+    /// account balance or query account public keys.
     pub fn make_account_missing(&mut self, account: AccountAddress) {
         if self.query_account_balances.borrow().get(&account).is_some() {
             fail!(
@@ -1760,6 +2185,12 @@ impl<State: Serial + DeserialWithState<TestStateApi>> TestHost<State> {
                  query account balance."
             )
         }
+        if self.query_account_public_keys.contains_key(&account) {
+            fail!(
+                "The account cannot be setup as a missing account. It is already setup for a \
+                 query account public keys."
+            )
+        }
         self.missing_accounts.insert(account);
     }
 
@@ -1789,20 +2220,22 @@ impl<State: StateClone<TestStateApi>> TestHost<State> {
     fn checkpoint(&self) -> Self {
         let cloned_state_api = self.state_builder.state_api.clone_deep();
         Self {
-            mocking_fns:             self.mocking_fns.clone(),
-            transfers:               self.transfers.clone(),
-            contract_balance:        self.contract_balance.clone(),
-            contract_address:        self.contract_address,
-            mocking_upgrades:        self.mocking_upgrades.clone(),
-            state_builder:           StateBuilder {
+            mocking_fns:               self.mocking_fns.clone(),
+            transfers:                 self.transfers.clone(),
+            contract_balance:          self.contract_balance.clone(),
+            contract_address:          self.contract_address,
+            mocking_upgrades:          self.mocking_upgrades.clone(),
+            state_builder:             StateBuilder {
                 state_api: cloned_state_api.clone(),
             },
-            state:                   unsafe { self.state.clone_state(&cloned_state_api) },
-            missing_accounts:        self.missing_accounts.clone(),
-            missing_contracts:       self.missing_contracts.clone(),
-            query_account_balances:  self.query_account_balances.clone(),
-            query_contract_balances: self.query_contract_balances.clone(),
-            query_exchange_rates:    self.query_exchange_rates,
+            state:                     unsafe { self.state.clone_state(&cloned_state_api) },
+            missing_accounts:          self.missing_accounts.clone(),
+            missing_contracts:         self.missing_contracts.clone(),
+            query_account_balances:    self.query_account_balances.clone(),
+            query_contract_balances:   self.query_contract_balances.clone(),
+            query_account_public_keys: self.query_account_public_keys.clone(),
+            account_signature_mock:    self.account_signature_mock.clone(),
+            query_exchange_rates:      self.query_exchange_rates,
         }
     }
 
@@ -1896,10 +2329,12 @@ mod test {
         cell::RefCell,
         rc::Rc,
         test_infrastructure::{TestStateBuilder, TestStateEntry},
-        Deletable, DeserialWithState, EntryRaw, HasStateApi, HasStateEntry, StateBox, StateClone,
-        StateMap, StateSet, INITIAL_NEXT_ITEM_PREFIX,
+        Deletable, DeserialWithState, EntryRaw, HasParameter, HasStateApi, HasStateEntry, StateBox,
+        StateClone, StateMap, StateSet, INITIAL_NEXT_ITEM_PREFIX,
+    };
+    use concordium_contracts_common::{
+        to_bytes, Cursor, Deserial, ParseResult, Read, Seek, SeekFrom, Write,
     };
-    use concordium_contracts_common::{to_bytes, Cursor, Deserial, Read, Seek, SeekFrom, Write};
 
     #[test]
     fn test_testhost_balance_queries_reflect_transfers() {
@@ -1925,6 +2360,189 @@ mod test {
         assert_eq!(self_new_balance, Amount::from_micro_ccd(1000));
     }
 
+    #[test]
+    fn test_testhost_account_public_keys_query() {
+        use super::*;
+        let mut host = TestHost::new((), TestStateBuilder::new());
+
+        let account = AccountAddress([0; 32]);
+        let missing_account = AccountAddress([1; 32]);
+        let mut keys = BTreeMap::new();
+        keys.insert(0, PublicKey::Ed25519(PublicKeyEd25519([2; 32])));
+        keys.insert(3, PublicKey::Ed25519(PublicKeyEd25519([3; 32])));
+        let mut credentials = BTreeMap::new();
+        credentials.insert(0, CredentialPublicKeys {
+            keys,
+            threshold: num::NonZeroU8::new(2).unwrap(),
+        });
+        let public_keys = AccountPublicKeys {
+            keys:      credentials,
+            threshold: num::NonZeroU8::new(1).unwrap(),
+        };
+        host.setup_query_account_public_keys(account, public_keys.clone());
+        host.make_account_missing(missing_account);
+
+        assert_eq!(host.account_public_keys(account).expect("Should succeed"), public_keys);
+        assert!(host.account_public_keys(missing_account).is_err());
+
+        // The keys survive a roundtrip through the serialization used by the host.
+        let bytes = to_bytes(&public_keys);
+        assert_eq!(bytes.len(), 1 + 1 + 1 + 2 * (1 + 1 + 32) + 1 + 1);
+        assert_eq!(from_bytes::<AccountPublicKeys>(&bytes), Ok(public_keys));
+    }
+
+    #[test]
+    fn test_testhost_check_permit_signature_mock() {
+        use super::*;
+        let mut host = TestHost::new((), TestStateBuilder::new());
+
+        let signer = AccountAddress([0; 32]);
+        let missing_account = AccountAddress([1; 32]);
+        let message = PermitMessage::new(
+            ContractAddress::new(1, 0),
+            3,
+            Timestamp::from_timestamp_millis(100),
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            &42u64,
+        );
+        let signed = to_bytes(&message);
+        host.setup_check_account_signature_mock(move |account, _signatures, data| {
+            account == signer && data == &signed[..]
+        });
+        host.make_account_missing(missing_account);
+
+        let signatures = AccountSignatures::default();
+        assert!(host.check_permit_signature(signer, &signatures, &message).unwrap());
+        let mut other_message = message.clone();
+        other_message.nonce = 4;
+        assert!(!host.check_permit_signature(signer, &signatures, &other_message).unwrap());
+        assert!(matches!(
+            host.check_permit_signature(missing_account, &signatures, &message),
+            Err(CheckAccountSignatureError::MissingAccount)
+        ));
+
+        // The payload is prefixed by its length in two bytes.
+        assert_eq!(signed.len(), 16 + 8 + 8 + 2 + "transfer".len() + 2 + 8);
+        assert_eq!(from_bytes::<PermitMessage>(&signed), Ok(message.clone()));
+        assert_eq!(message.parameter::<u64>(), Ok(42));
+    }
+
+    #[test]
+    #[cfg(feature = "crypto-primitives")]
+    fn test_testhost_check_account_signature() {
+        use super::*;
+        use sha2::Digest;
+        let mut host = TestHost::new((), TestStateBuilder::new());
+
+        let account = AccountAddress([0; 32]);
+        let signing_keys: Vec<_> =
+            (0..3u8).map(|i| ed25519_zebra::SigningKey::from([i + 1; 32])).collect();
+        let mut keys = BTreeMap::new();
+        for (i, signing_key) in signing_keys.iter().enumerate() {
+            let key = <[u8; 32]>::from(ed25519_zebra::VerificationKey::from(signing_key));
+            keys.insert(i as u8, PublicKey::Ed25519(PublicKeyEd25519(key)));
+        }
+        let mut credentials = BTreeMap::new();
+        credentials.insert(0, CredentialPublicKeys {
+            keys,
+            threshold: num::NonZeroU8::new(2).unwrap(),
+        });
+        host.setup_query_account_public_keys(account, AccountPublicKeys {
+            keys:      credentials,
+            threshold: num::NonZeroU8::new(1).unwrap(),
+        });
+
+        let data = b"message";
+        let mut message = account.0.to_vec();
+        message.extend_from_slice(&[0u8; 8]);
+        message.extend_from_slice(data);
+        let hash = sha2::Sha256::digest(&message);
+        let sign = |indices: &[u8]| {
+            let mut sigs = BTreeMap::new();
+            for i in indices {
+                let signature = signing_keys[usize::from(*i)].sign(&hash);
+                sigs.insert(*i, Signature::Ed25519(SignatureEd25519(signature.into())));
+            }
+            let mut signatures = AccountSignatures::default();
+            signatures.sigs.insert(0, CredentialSignatures {
+                sigs,
+            });
+            signatures
+        };
+
+        assert_eq!(host.check_account_signature(account, &sign(&[0, 2]), data), Ok(true));
+        assert_eq!(host.check_account_signature(account, &sign(&[1]), data), Ok(false));
+        assert_eq!(host.check_account_signature(account, &sign(&[0, 1]), b"other"), Ok(false));
+    }
+
+    #[test]
+    fn test_read_bytes_ref_borrows_parameter() {
+        use super::*;
+        let parameter = [1u8, 2, 3, 4, 5];
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_parameter(&parameter);
+        let mut cursor = ctx.parameter_cursor();
+        let first: u8 = cursor.get().expect("Reading a byte should succeed");
+        assert_eq!(first, 1);
+        match cursor.read_bytes_ref(3).expect("Reading bytes should succeed") {
+            Cow::Borrowed(bytes) => {
+                assert_eq!(bytes, &[2, 3, 4]);
+                assert_eq!(bytes.as_ptr(), parameter[1..].as_ptr(), "The bytes are not copied");
+            }
+            Cow::Owned(_) => panic!("The bytes should be borrowed"),
+        }
+        assert!(cursor.read_bytes_ref(2).is_err(), "Only one byte remains");
+        assert_eq!(cursor.read_bytes_ref(1).expect("Reading bytes should succeed").as_ref(), &[5]);
+    }
+
+    /// A parameter using the default implementation of `read_bytes_ref`, as
+    /// the parameter on chain does.
+    struct DefaultParameter<'a>(Cursor<&'a [u8]>);
+
+    impl<'a> AsRef<[u8]> for DefaultParameter<'a> {
+        fn as_ref(&self) -> &[u8] { self.0.as_ref() }
+    }
+
+    impl<'a> Read for DefaultParameter<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> ParseResult<usize> { self.0.read(buf) }
+    }
+
+    impl<'a> Seek for DefaultParameter<'a> {
+        type Err = ();
+
+        fn seek(&mut self, seek: SeekFrom) -> Result<u32, Self::Err> { self.0.seek(seek) }
+
+        fn cursor_position(&self) -> u32 { self.0.cursor_position() }
+    }
+
+    impl<'a> HasParameter for DefaultParameter<'a> {}
+
+    #[test]
+    fn test_read_bytes_ref_default_checks_length() {
+        let parameter = [1u8, 2, 3, 4, 5];
+        let mut cursor = DefaultParameter(Cursor::new(&parameter[..]));
+        assert_eq!(cursor.read_bytes_ref(2).expect("Reading bytes should succeed").as_ref(), &[
+            1, 2
+        ]);
+        // A length far beyond the parameter fails without allocating it.
+        assert!(cursor.read_bytes_ref(u32::MAX).is_err(), "Only three bytes remain");
+        assert!(cursor.read_bytes_ref(4).is_err(), "Only three bytes remain");
+        assert_eq!(cursor.cursor_position(), 2, "Nothing is read on failure");
+        assert_eq!(cursor.read_bytes_ref(3).expect("Reading bytes should succeed").as_ref(), &[
+            3, 4, 5
+        ]);
+    }
+
+    #[test]
+    fn test_concordium_dbg() {
+        // The messages go to the standard error and are not recorded, so this
+        // only checks that each form of the macro compiles and runs.
+        let value = 42;
+        concordium_dbg!();
+        concordium_dbg!("The value is {}", value);
+        concordium_dbg!("The value is {value:?}, twice is {}", value * 2, value = value);
+    }
+
     #[test]
     fn test_testhost_balance_queries_reflect_invoke() {
         use super::*;
@@ -1956,6 +2574,90 @@ mod test {
         assert_eq!(self_new_balance, Amount::from_micro_ccd(2000));
     }
 
+    #[test]
+    fn test_testhost_exchange_rates_query() {
+        use super::*;
+        let mut host = TestHost::new((), TestStateBuilder::new());
+
+        let exchange_rates = ExchangeRates {
+            euro_per_energy:    ExchangeRate::new_unchecked(1, 50_000),
+            micro_ccd_per_euro: ExchangeRate::new_unchecked(50_000, 1),
+        };
+        host.set_exchange_rates(exchange_rates);
+        assert_eq!(host.exchange_rates(), exchange_rates);
+
+        // Setting the rates again overwrites the previous setup.
+        let new_exchange_rates = ExchangeRates {
+            euro_per_energy:    ExchangeRate::new_unchecked(1, 50_000),
+            micro_ccd_per_euro: ExchangeRate::new_unchecked(3_000_001, 7),
+        };
+        host.set_exchange_rates(new_exchange_rates);
+        assert_eq!(host.exchange_rates(), new_exchange_rates);
+    }
+
+    #[test]
+    fn test_testhost_exchange_rates_conversions_round_down() {
+        use super::*;
+        let mut host = TestHost::new((), TestStateBuilder::new());
+
+        // 1 EUR = 3 CCD, i.e. 30_000 microCCD per euro cent.
+        host.set_exchange_rates(ExchangeRates {
+            euro_per_energy:    ExchangeRate::new_unchecked(1, 50_000),
+            micro_ccd_per_euro: ExchangeRate::new_unchecked(3_000_000, 1),
+        });
+        let rates = host.exchange_rates();
+        assert_eq!(rates.convert_euro_cent_to_amount(150), Amount::from_micro_ccd(4_500_000));
+        assert_eq!(rates.convert_amount_to_euro_cent(Amount::from_micro_ccd(4_500_000)), 150);
+        // Anything below a full euro cent is rounded down.
+        assert_eq!(rates.convert_amount_to_euro_cent(Amount::from_micro_ccd(29_999)), 0);
+
+        // A rate that does not divide evenly: 1 EUR = 3_000_001 / 7 microCCD.
+        host.set_exchange_rates(ExchangeRates {
+            euro_per_energy:    ExchangeRate::new_unchecked(1, 50_000),
+            micro_ccd_per_euro: ExchangeRate::new_unchecked(3_000_001, 7),
+        });
+        let rates = host.exchange_rates();
+        // 100 * 3_000_001 / (7 * 100) = 428_571.57..
+        assert_eq!(rates.convert_euro_cent_to_amount(100), Amount::from_micro_ccd(428_571));
+        // 428_571 * 7 * 100 / 3_000_001 = 99.99..
+        assert_eq!(rates.convert_amount_to_euro_cent(Amount::from_micro_ccd(428_571)), 99);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_testhost_exchange_rates_query_fails_if_not_set() {
+        use super::*;
+        let host = TestHost::new((), TestStateBuilder::new());
+        host.exchange_rates();
+    }
+
+    #[test]
+    fn test_randomness_is_reproducible() {
+        use super::*;
+        let mut first = TestRandomness::new(1234);
+        let mut second = TestRandomness::new(1234);
+        let mut other = TestRandomness::new(4321);
+        let first_values: Vec<u64> = (0..10).map(|_| first.next_u64()).collect();
+        let second_values: Vec<u64> = (0..10).map(|_| second.next_u64()).collect();
+        let other_values: Vec<u64> = (0..10).map(|_| other.next_u64()).collect();
+        assert_eq!(first_values, second_values, "Same seed should give the same sequence.");
+        assert_ne!(first_values, other_values, "Different seeds should give different sequences.");
+    }
+
+    #[test]
+    fn test_randomness_gen_below_is_in_range() {
+        use super::*;
+        let mut rng = TestRandomness::new(0);
+        let mut seen = [false; 7];
+        for _ in 0..1000 {
+            let value = rng.gen_below(7);
+            assert!(value < 7, "Value out of range.");
+            seen[value as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s), "Every value in the range should be produced.");
+        assert_eq!(rng.gen_below(1), 0);
+    }
+
     #[test]
     // Perform a number of operations from Seek, Read, Write and HasStateApi
     // classes on the TestStateApi structure and check that they behave as
@@ -2256,6 +2958,78 @@ mod test {
         assert!(!set.contains(&42));
     }
 
+    #[test]
+    fn stateset_len() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut set = state_builder.new_set();
+        assert!(set.is_empty());
+        assert!(set.insert(1u32));
+        assert!(set.insert(2u32));
+        assert!(!set.insert(2u32));
+        assert_eq!(set.len(), 2);
+        assert!(!set.is_empty());
+        assert!(!set.remove(&3));
+        assert!(set.remove(&1));
+        assert_eq!(set.len(), 1);
+        assert!(set.remove(&2));
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn stateset_len_of_unit_and_legacy_sets() {
+        let mut state_builder = TestStateBuilder::new();
+        // A value with an empty serialization is stored at the prefix of the set,
+        // which does not collide with the stored number of elements.
+        let mut units = state_builder.new_set::<()>();
+        assert!(units.insert(()));
+        assert_eq!(units.len(), 1);
+        assert_eq!(units.values().count(), 1);
+        assert!(units.remove(&()));
+        assert_eq!(units.len(), 0);
+
+        // Sets written by an earlier version have no stored number of elements.
+        let mut legacy = state_builder.new_set::<u8>();
+        for value in 0..3u8 {
+            let mut key = legacy.prefix.to_vec();
+            key.push(value);
+            legacy.state_api.create_entry(&key).expect("Creating the entry succeeds");
+        }
+        assert_eq!(legacy.len(), 3);
+        assert!(legacy.insert(3));
+        assert_eq!(legacy.len(), 4);
+        assert!(legacy.remove(&0));
+        assert_eq!(legacy.len(), 3);
+        legacy.clear();
+        assert_eq!(legacy.len(), 0);
+        assert!(legacy.is_empty());
+    }
+
+    #[test]
+    fn stateset_subset_union_and_values() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut small = state_builder.new_set();
+        let mut large = state_builder.new_set();
+        for i in 0..10u32 {
+            large.insert(i);
+        }
+        small.insert(3u32);
+        small.insert(7u32);
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+
+        small.insert(20);
+        assert!(!small.is_subset(&large));
+        large.union_with(&small);
+        assert_eq!(large.len(), 11);
+        assert!(small.is_subset(&large));
+
+        let mut values: Vec<u32> = large.values().collect();
+        values.sort_unstable();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 20]);
+        assert_eq!(large.iter().count(), 11);
+    }
+
     #[test]
     fn stateset_clear() {
         let mut state_builder = TestStateBuilder::new();
@@ -2292,6 +3066,26 @@ mod test {
         assert_eq!(*statebox.get(), boxed_value);
     }
 
+    #[test]
+    fn statebox_set_without_loading() {
+        let state = TestStateApi::new();
+        let mut state_builder = TestStateBuilder::open(state.clone());
+        let statebox = state_builder.new_box(String::from("A long description"));
+        let bytes = to_bytes(&statebox);
+        drop(statebox);
+
+        let mut statebox =
+            StateBox::<String, _>::deserial_with_state(&state, &mut Cursor::new(&bytes))
+                .expect("Deserializing should succeed");
+        statebox.set(String::from("Short"));
+        assert_eq!(*statebox.get(), "Short");
+        drop(statebox);
+
+        let statebox = StateBox::<String, _>::deserial_with_state(&state, &mut Cursor::new(&bytes))
+            .expect("Deserializing should succeed");
+        assert_eq!(*statebox.get(), "Short");
+    }
+
     #[test]
     fn a_new_entry_can_not_be_created_under_a_locked_subtree() {
         let expected_value: u64 = 123123123;
@@ -2514,4 +3308,167 @@ mod test {
         assert_eq!(*b2_ref_clone.get().get(), 101010);
         assert_eq!(b2_loaded_clone_cursor_pos, b2_loaded_cursor_pos);
     }
+
+    #[test]
+    fn amount_checked_arithmetic() {
+        use crate::AmountArithmetic;
+        use concordium_contracts_common::Amount;
+        let max = Amount::from_micro_ccd(u64::MAX);
+        let one = Amount::from_micro_ccd(1);
+        assert_eq!(one.checked_add(one), Some(Amount::from_micro_ccd(2)));
+        assert_eq!(max.checked_add(one), None);
+        assert_eq!(Amount::zero().checked_sub(one), None);
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(Amount::from_micro_ccd(7).checked_mul(3), Some(Amount::from_micro_ccd(21)));
+        // The intermediate product does not fit in 64 bits.
+        assert_eq!(max.mul_ratio(3, 4), Some(Amount::from_micro_ccd(u64::MAX / 4 * 3 + 2)));
+        assert_eq!(Amount::from_micro_ccd(10).mul_ratio(1, 3), Some(Amount::from_micro_ccd(3)));
+        assert_eq!(max.mul_ratio(2, 1), None);
+        assert_eq!(one.mul_ratio(1, 0), None);
+    }
+
+    #[test]
+    fn basis_points_of_amount() {
+        use crate::BasisPoints;
+        use concordium_contracts_common::{from_bytes, Amount};
+        let fee = BasisPoints::new(250).expect("250 basis points are valid");
+        assert_eq!(fee.of(Amount::from_micro_ccd(1_000_000)), Amount::from_micro_ccd(25_000));
+        assert_eq!(fee.complement().get(), 9_750);
+        assert_eq!(BasisPoints::MAX.of(Amount::from_micro_ccd(u64::MAX)).micro_ccd, u64::MAX);
+        assert_eq!(fee.checked_add(BasisPoints::MAX), None);
+        assert!(BasisPoints::new(10_001).is_none());
+        assert_eq!(from_bytes::<BasisPoints>(&to_bytes(&10_000u16)), Ok(BasisPoints::MAX));
+        assert!(from_bytes::<BasisPoints>(&to_bytes(&10_001u16)).is_err());
+    }
+
+    #[test]
+    fn timestamp_period_helpers() {
+        use crate::TimestampArithmetic;
+        use concordium_contracts_common::{Duration, Timestamp};
+        let start = Timestamp::from_timestamp_millis(1_000);
+        let period = Duration::from_millis(500);
+        assert!(!start.has_elapsed(period, Timestamp::from_timestamp_millis(1_499)));
+        assert!(start.has_elapsed(period, Timestamp::from_timestamp_millis(1_500)));
+        assert_eq!(
+            start.time_remaining(period, Timestamp::from_timestamp_millis(1_200)),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            start.time_remaining(period, Timestamp::from_timestamp_millis(2_000)),
+            Duration::from_millis(0)
+        );
+        let last = Timestamp::from_timestamp_millis(u64::MAX);
+        assert!(!last.has_elapsed(period, last));
+    }
+
+    #[test]
+    fn timestamp_from_iso8601_formats() {
+        use super::timestamp_from_iso8601;
+        let millis = |text| timestamp_from_iso8601(text).map(|t| t.timestamp_millis());
+        assert_eq!(millis("1970-01-01"), Some(0));
+        assert_eq!(millis("2000-03-01T00:00:00Z"), Some(951_868_800_000));
+        assert_eq!(millis("2024-02-29T12:30:15.250Z"), Some(1_709_209_815_250));
+        assert_eq!(millis("2024-02-29T14:30:15.250+02:00"), Some(1_709_209_815_250));
+        assert_eq!(millis("2024-02-29T10:00:15.250-02:30"), Some(1_709_209_815_250));
+        assert_eq!(millis("2023-02-29"), None);
+        assert_eq!(millis("2023-13-01"), None);
+        assert_eq!(millis("2023-01-01T24:00:00Z"), None);
+        assert_eq!(millis("2023-01-01T12:00:00"), None);
+        assert_eq!(millis("2023-01-01T12:00:00+24:00"), None);
+        assert_eq!(millis("2023-01-01T12:00:00-01:60"), None);
+        assert_eq!(millis("1969-12-31T23:59:59Z"), None);
+    }
+
+    #[test]
+    fn parameter_from_hex_decodes() {
+        use super::{parameter_from_hex, ParameterDecodeError};
+        assert_eq!(parameter_from_hex(""), Ok(Vec::new()));
+        assert_eq!(parameter_from_hex("00ff7Fa0"), Ok(vec![0x00, 0xff, 0x7f, 0xa0]));
+        assert_eq!(parameter_from_hex("abc"), Err(ParameterDecodeError::InvalidLength));
+        assert_eq!(
+            parameter_from_hex("0g"),
+            Err(ParameterDecodeError::InvalidCharacter {
+                index: 1,
+            })
+        );
+        assert_eq!(
+            ParameterDecodeError::InvalidCharacter {
+                index: 1,
+            }
+            .to_string(),
+            "Invalid character at byte offset 1 of the text."
+        );
+    }
+
+    #[test]
+    fn parameter_from_base64_decodes() {
+        use super::{parameter_from_base64, ParameterDecodeError};
+        assert_eq!(parameter_from_base64(""), Ok(Vec::new()));
+        assert_eq!(parameter_from_base64("TWFu"), Ok(b"Man".to_vec()));
+        assert_eq!(parameter_from_base64("TWE="), Ok(b"Ma".to_vec()));
+        assert_eq!(parameter_from_base64("TQ=="), Ok(b"M".to_vec()));
+        assert_eq!(parameter_from_base64("+/+/"), Ok(vec![0xfb, 0xff, 0xbf]));
+        assert_eq!(parameter_from_base64("TWE"), Err(ParameterDecodeError::InvalidLength));
+        assert_eq!(
+            parameter_from_base64("TW-u"),
+            Err(ParameterDecodeError::InvalidCharacter {
+                index: 2,
+            })
+        );
+        assert_eq!(parameter_from_base64("T=Fu"), Err(ParameterDecodeError::InvalidPadding));
+        assert_eq!(parameter_from_base64("T==="), Err(ParameterDecodeError::InvalidPadding));
+        // The padded bits must be zero.
+        assert_eq!(parameter_from_base64("TR=="), Err(ParameterDecodeError::InvalidPadding));
+    }
+
+    #[test]
+    fn invoke_contract_typed_parses_return_value() {
+        use super::{MockFn, TestHost};
+        use crate::{CallContractError, CallContractTypedError, HasHost};
+        use concordium_contracts_common::{
+            Amount, ContractAddress, EntrypointName, OwnedEntrypointName,
+        };
+        let mut host = TestHost::new((), TestStateBuilder::new());
+        let counter = ContractAddress::new(1, 0);
+        host.setup_mock_entrypoint(
+            counter,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let offset: u64 = concordium_contracts_common::from_bytes(parameter.0)
+                    .expect("Parameter is a u64");
+                Ok((false, 40 + offset))
+            }),
+        );
+        host.setup_mock_entrypoint(
+            counter,
+            OwnedEntrypointName::new_unchecked("missing".into()),
+            MockFn::returning_err::<()>(CallContractError::MissingEntrypoint),
+        );
+        host.setup_mock_entrypoint(
+            counter,
+            OwnedEntrypointName::new_unchecked("v0".into()),
+            MockFn::new_v0::<(), _>(|_parameter, _amount, _balance, _state| Ok(false)),
+        );
+        let get = EntrypointName::new_unchecked("get");
+        let value: u64 = host
+            .invoke_contract_typed(&counter, &2u64, get, Amount::zero())
+            .expect("Invocation succeeds");
+        assert_eq!(value, 42);
+        let result: Result<[u8; 16], _> =
+            host.invoke_contract_typed(&counter, &2u64, get, Amount::zero());
+        assert!(matches!(result, Err(CallContractTypedError::ParseReturnValue)));
+        let result: Result<u32, _> =
+            host.invoke_contract_typed(&counter, &2u64, get, Amount::zero());
+        assert!(matches!(result, Err(CallContractTypedError::ParseReturnValue)));
+        let missing = EntrypointName::new_unchecked("missing");
+        let result: Result<u64, _> =
+            host.invoke_contract_typed(&counter, &(), missing, Amount::zero());
+        assert!(matches!(
+            result,
+            Err(CallContractTypedError::Call(CallContractError::MissingEntrypoint))
+        ));
+        let v0 = EntrypointName::new_unchecked("v0");
+        let result: Result<u64, _> = host.invoke_contract_typed(&counter, &(), v0, Amount::zero());
+        assert!(matches!(result, Err(CallContractTypedError::MissingReturnValue)));
+    }
 }
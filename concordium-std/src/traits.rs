@@ -6,10 +6,12 @@
 use crate::vec::Vec;
 use crate::{
     types::{LogError, StateError},
-    CallContractResult, EntryRaw, ExchangeRates, HashKeccak256, HashSha2256, HashSha3256, Key,
-    OccupiedEntryRaw, PublicKeyEcdsaSecp256k1, PublicKeyEd25519, QueryAccountBalanceResult,
-    QueryContractBalanceResult, ReadOnlyCallContractResult, SignatureEcdsaSecp256k1,
-    SignatureEd25519, StateBuilder, TransferResult, UpgradeResult, VacantEntryRaw,
+    AccountSignatures, CallContractResult, CallContractTypedError, CheckAccountSignatureResult,
+    Cow, EntryRaw, ExchangeRates, HashKeccak256, HashSha2256, HashSha3256, Key, OccupiedEntryRaw,
+    PermitMessage, PublicKeyEcdsaSecp256k1, PublicKeyEd25519, QueryAccountBalanceResult,
+    QueryAccountPublicKeysResult, QueryContractBalanceResult, ReadOnlyCallContractResult,
+    SignatureEcdsaSecp256k1, SignatureEd25519, StateBuilder, TransferResult, UpgradeResult,
+    VacantEntryRaw,
 };
 use concordium_contracts_common::*;
 
@@ -20,7 +22,28 @@ use concordium_contracts_common::*;
 ///
 /// The reuse of `Read` methods is the reason for the slightly strange choice of
 /// methods of this trait.
-pub trait HasParameter: Read + Seek + HasSize {}
+pub trait HasParameter: Read + Seek + HasSize {
+    /// Read the next `len` bytes of the parameter, e.g., a large blob of
+    /// metadata or a proof which the contract hashes or forwards, borrowing
+    /// them instead of copying them when the parameter is already in the memory
+    /// of the contract, as in tests.
+    ///
+    /// On chain the parameter is held by the host, so the bytes are copied
+    /// once, directly into the returned buffer. This avoids deserializing the
+    /// bytes into a vector and then copying them again.
+    ///
+    /// Fails if fewer than `len` bytes remain, in which case nothing is read.
+    fn read_bytes_ref(&mut self, len: u32) -> ParseResult<Cow<[u8]>> {
+        // Check the length before allocating, so a length read from the
+        // parameter cannot cause a large allocation.
+        if len > self.size().saturating_sub(self.cursor_position()) {
+            return Err(ParseError::default());
+        }
+        let mut bytes = crate::vec![0u8; len as usize];
+        self.read_exact(&mut bytes)?;
+        Ok(Cow::Owned(bytes))
+    }
+}
 
 /// Objects which can access call responses from contract invocations.
 ///
@@ -287,6 +310,34 @@ pub trait HasHost<State>: Sized {
         self.invoke_contract_raw(to, Parameter(&param), method, amount)
     }
 
+    /// Like [`invoke_contract`](Self::invoke_contract), except that the return
+    /// value is also automatically deserialized. This is convenient when
+    /// invoking `V1` contracts that return a value, e.g., when querying
+    /// another contract.
+    ///
+    /// Fails with
+    /// [`MissingReturnValue`](CallContractTypedError::MissingReturnValue)
+    /// if the invoked contract did not return a value, which is the case for
+    /// `V0` contracts, and with
+    /// [`ParseReturnValue`](CallContractTypedError::ParseReturnValue) if the
+    /// return value could not be parsed as `R`, or has bytes left over after
+    /// parsing it.
+    fn invoke_contract_typed<P: Serial, R: Deserial>(
+        &mut self,
+        to: &ContractAddress,
+        parameter: &P,
+        method: EntrypointName,
+        amount: Amount,
+    ) -> Result<R, CallContractTypedError<Self::ReturnValueType>> {
+        let (_, return_value) = self.invoke_contract(to, parameter, method, amount)?;
+        let mut return_value = return_value.ok_or(CallContractTypedError::MissingReturnValue)?;
+        let value = return_value.get().map_err(|_| CallContractTypedError::ParseReturnValue)?;
+        if !matches!(return_value.read(&mut [0u8; 1]), Ok(0)) {
+            return Err(CallContractTypedError::ParseReturnValue);
+        }
+        Ok(value)
+    }
+
     /// Upgrade the module for this instance to a given module. The new module
     /// must contain a smart contract with a matching name.
     /// Invocations of this instance after the point of a successful upgrade,
@@ -395,6 +446,57 @@ pub trait HasHost<State>: Sized {
     /// including the amount transferred as part of the invocation.
     fn contract_balance(&self, address: ContractAddress) -> QueryContractBalanceResult;
 
+    /// Get the public keys of an account, grouped by credential, along with
+    /// the thresholds of how many keys must sign for a credential, and how
+    /// many credentials must sign for the account. This is useful for
+    /// contracts checking signatures by accounts, e.g., permits or multisig
+    /// wallets. The query is supported from protocol version 6.
+    ///
+    /// ```ignore
+    /// let keys = host.account_public_keys(account)?;
+    /// for (credential_index, credential_keys) in keys.keys.iter() {
+    ///     // credential_keys.threshold of credential_keys.keys must sign.
+    /// }
+    /// ```
+    fn account_public_keys(&self, address: AccountAddress) -> QueryAccountPublicKeysResult;
+
+    /// Check the signatures on the data by the account, with respect to the
+    /// public keys and thresholds of the account, as returned by
+    /// [`account_public_keys`](Self::account_public_keys). The result is
+    /// whether the signatures are valid.
+    ///
+    /// The signatures are on the SHA2-256 hash of the account address, eight
+    /// zero bytes and the data, which is what wallets sign when asked to sign
+    /// a message with an account, and which ensures that the signed data
+    /// cannot be a transaction. The check is supported from protocol version
+    /// 6.
+    fn check_account_signature(
+        &self,
+        address: AccountAddress,
+        signatures: &AccountSignatures,
+        data: &[u8],
+    ) -> CheckAccountSignatureResult;
+
+    /// Check the signatures on the [`PermitMessage`] by the signer, using
+    /// [`check_account_signature`](Self::check_account_signature) on the
+    /// serialized message. This does not check the contract address, the
+    /// nonce or the timestamp of the message, which is left to the caller.
+    ///
+    /// ```ignore
+    /// ensure!(message.contract_address == ctx.self_address(), Error::WrongContract);
+    /// ensure!(ctx.metadata().slot_time() < message.timestamp, Error::Expired);
+    /// ensure_eq!(message.nonce, expected_nonce, Error::NonceMismatch);
+    /// ensure!(host.check_permit_signature(signer, &signatures, &message)?, Error::WrongSignature);
+    /// ```
+    fn check_permit_signature(
+        &self,
+        signer: AccountAddress,
+        signatures: &AccountSignatures,
+        message: &PermitMessage,
+    ) -> CheckAccountSignatureResult {
+        self.check_account_signature(signer, signatures, &to_bytes(message))
+    }
+
     /// Get an immutable reference to the contract state.
     fn state(&self) -> &State;
 
@@ -463,9 +565,29 @@ pub trait HasLogger {
         }
         self.log_raw(&out)
     }
+
+    #[inline(always)]
+    /// Log an event of a type declared with the
+    /// [`#[concordium_event]`](crate::concordium_event) macro. This is the same
+    /// as [`log`](Self::log), but ensures that only events of a type intended
+    /// for the event log, and included in the schema, are logged.
+    fn log_event<E: ContractEvent>(&mut self, event: &E) -> Result<(), LogError> { self.log(event) }
 }
 
+/// Types of events logged by a contract. Implementations are generated by the
+/// [`#[concordium_event]`](crate::concordium_event) macro, and the events are
+/// logged using [`log_event`](HasLogger::log_event).
+pub trait ContractEvent: Serial {}
+
 /// Objects which provide cryptographic primitives.
+///
+/// The primitives are those provided by the chain to smart contracts, i.e.,
+/// ed25519 and ecdsa over secp256k1 signature verification, and the SHA2-256,
+/// SHA3-256 and Keccak-256 hash functions. In particular, the chain does not
+/// provide verification of BLS signatures, and implementing it in a contract is
+/// prohibitively expensive. Attestations by a set of validators or oracles
+/// should instead be verified as individual ed25519 signatures, checking that
+/// a threshold of the configured keys have signed.
 pub trait HasCryptoPrimitives {
     /// Verify an ed25519 signature.
     fn verify_ed25519_signature(
@@ -494,6 +616,53 @@ pub trait HasCryptoPrimitives {
     fn hash_keccak_256(&self, data: &[u8]) -> HashKeccak256;
 }
 
+/// Objects which provide a stream of pseudo-random numbers.
+///
+/// There is no source of randomness available to smart contracts on chain,
+/// so contracts that need randomness (for example to draw a lottery winner)
+/// have to derive it from data that no single party controls, such as secrets
+/// revealed by several participants in a commit-reveal scheme. Writing the
+/// random-dependent logic against this trait, instead of directly against the
+/// derived bytes, allows the logic to be unit tested with reproducible
+/// outcomes by using
+/// [`TestRandomness`](./test_infrastructure/struct.TestRandomness.html).
+///
+/// ```no_run
+/// # use concordium_std::*;
+/// /// Pick the index of the winner among `num_participants` participants.
+/// fn draw_winner(rng: &mut impl HasRandomness, num_participants: u64) -> u64 {
+///     rng.gen_below(num_participants)
+/// }
+/// ```
+pub trait HasRandomness {
+    /// Get the next pseudo-random `u64`.
+    fn next_u64(&mut self) -> u64;
+
+    /// Get the next pseudo-random `u32`.
+    #[inline(always)]
+    fn next_u32(&mut self) -> u32 { (self.next_u64() >> 32) as u32 }
+
+    /// Get a pseudo-random number in the range `0..bound`. The numbers are
+    /// uniformly distributed, i.e., there is no bias towards smaller
+    /// numbers.
+    ///
+    /// Traps if `bound` is `0`.
+    fn gen_below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            crate::trap()
+        }
+        // Reject the values in the incomplete range at the top to avoid modulo
+        // bias.
+        let zone = u64::MAX - (u64::MAX - bound + 1) % bound;
+        loop {
+            let value = self.next_u64();
+            if value <= zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
 /// Add optimized unwrap behaviour that aborts the process instead of
 /// panicking.
 pub trait UnwrapAbort {
@@ -534,6 +703,40 @@ pub trait ExpectNoneReport {
     fn expect_none_report(self, msg: &str);
 }
 
+/// Checked arithmetic on amounts, for computing fees and shares of amounts
+/// without overflowing. The operations return [`None`] instead of overflowing
+/// or underflowing.
+pub trait AmountArithmetic: Sized {
+    /// Add two amounts, returning [`None`] on overflow.
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// Subtract an amount, returning [`None`] if the result would be negative.
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    /// Multiply the amount by a factor, returning [`None`] on overflow.
+    fn checked_mul(self, factor: u64) -> Option<Self>;
+
+    /// Compute the amount multiplied by `numerator / denominator`, rounded
+    /// down. The multiplication is done with 128-bit intermediate values, so
+    /// it does not overflow. Returns [`None`] if the `denominator` is zero or
+    /// the result does not fit in an amount.
+    fn mul_ratio(self, numerator: u64, denominator: u64) -> Option<Self>;
+}
+
+/// Helpers for deadlines and periods, i.e., a duration counted from a
+/// timestamp. Adding and subtracting durations, and computing the duration
+/// between two timestamps, is provided by [`Timestamp`] itself.
+pub trait TimestampArithmetic: Sized {
+    /// Return `true` if the `duration` counted from `self` has elapsed at
+    /// time `now`, i.e., `now` is at or after `self + duration`. A period
+    /// ending after the largest timestamp never elapses.
+    fn has_elapsed(self, duration: Duration, now: Timestamp) -> bool;
+
+    /// Return the time remaining at time `now` until the `duration` counted
+    /// from `self` has elapsed, which is zero if it has elapsed.
+    fn time_remaining(self, duration: Duration, now: Timestamp) -> Duration;
+}
+
 /// The `DeserialWithState` trait provides a means of reading structures from
 /// byte-sources ([`Read`]) for types that also need a reference to a
 /// [`HasStateApi`] type.
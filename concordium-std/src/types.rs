@@ -1,8 +1,14 @@
-use concordium_contracts_common::{Amount, ExchangeRate};
+use concordium_contracts_common::{
+    Amount, ContractAddress, ExchangeRate, OwnedEntrypointName, Timestamp,
+};
 
 use crate::{
-    cell::UnsafeCell, cmp::max, marker::PhantomData, num::NonZeroU32, Cursor, HasStateApi, Serial,
-    Vec,
+    cell::UnsafeCell,
+    cmp::max,
+    collections::BTreeMap,
+    marker::PhantomData,
+    num::{NonZeroU32, NonZeroU8},
+    Cursor, HasStateApi, Serial, Vec,
 };
 
 #[derive(Debug)]
@@ -199,6 +205,12 @@ pub struct StateMapIterMut<'a, K, V, S: HasStateApi> {
 /// The cost of updates to the set are dependent on the serialized size of the
 /// value `T`.
 ///
+/// The set maintains the number of its values in a separate entry in the
+/// state, whose key cannot collide with a value, so [`len`](StateSet::len)
+/// does not iterate over the set. Sets created with an earlier version of this
+/// library have no such entry. For them [`len`](StateSet::len) counts the
+/// values until the first insertion or removal, which stores the number.
+///
 /// New sets can be constructed using the
 /// [`new_set`][StateBuilder::new_set] method on the [`StateBuilder`].
 ///
@@ -328,6 +340,16 @@ pub struct StateSetIter<'a, T, S: HasStateApi> {
     pub(crate) _marker_lifetime: PhantomData<&'a T>,
 }
 
+/// An iterator over the elements of a [`StateSet`], returning them by value.
+///
+/// Ordered by `T` serialized to bytes.
+///
+/// This `struct` is created by the [`values`][StateSet::values] method on
+/// [`StateSet`]. See its documentation for more.
+pub struct StateSetValues<'a, T, S: HasStateApi> {
+    pub(crate) iter: StateSetIter<'a, T, S>,
+}
+
 #[derive(Debug)]
 /// A pointer type for data in the state.
 ///
@@ -337,6 +359,23 @@ pub struct StateSetIter<'a, T, S: HasStateApi> {
 /// your state. This is useful when part of your state isn't used in every
 /// receive method.
 ///
+/// When the contract state is loaded, only the location of the value is read.
+/// The value itself is deserialized on the first access, and the deserialized
+/// value is cached for the rest of the invocation. A value which was only read
+/// is not written back to the state, and a value can be overwritten without
+/// loading it using [`set`](StateBox::set).
+///
+/// ```no_run
+/// # use concordium_std::*;
+/// #[derive(Serial, DeserialWithState)]
+/// #[concordium(state_parameter = "S")]
+/// struct MyState<S: HasStateApi> {
+///     counter:  u64,
+///     // Only deserialized by receive methods which access it.
+///     metadata: StateBox<Vec<u8>, S>,
+/// }
+/// ```
+///
 /// The type parameter `T` is the type stored in the box. The type parameter `S`
 /// is the state.
 pub struct StateBox<T: Serial, S: HasStateApi> {
@@ -676,6 +715,19 @@ pub enum CallContractError<ReturnValueType> {
     Trap,
 }
 
+#[derive(Debug, Clone, Copy)]
+/// Errors that may occur when invoking a contract entrypoint with
+/// [`invoke_contract_typed`](crate::HasHost::invoke_contract_typed).
+pub enum CallContractTypedError<ReturnValueType> {
+    /// Invoking the contract entrypoint failed.
+    Call(CallContractError<ReturnValueType>),
+    /// The invoked contract did not produce a return value, which happens if
+    /// it is a `V0` contract.
+    MissingReturnValue,
+    /// The return value could not be parsed as the expected type.
+    ParseReturnValue,
+}
+
 #[repr(i32)]
 #[derive(Debug, Clone)]
 /// Errors that may occur when transferring CCD to an account.
@@ -737,6 +789,29 @@ pub type QueryAccountBalanceResult = Result<AccountBalance, QueryAccountBalanceE
 /// [`QueryContractBalanceError`] and result to [`Amount`].
 pub type QueryContractBalanceResult = Result<Amount, QueryContractBalanceError>;
 
+/// Error for querying the public keys of an account.
+/// No account found for the provided account address.
+#[derive(Debug, Copy, Clone)]
+pub struct QueryAccountPublicKeysError;
+
+/// A wrapper around [`Result`] that fixes the error variant to
+/// [`QueryAccountPublicKeysError`] and result to [`AccountPublicKeys`].
+pub type QueryAccountPublicKeysResult = Result<AccountPublicKeys, QueryAccountPublicKeysError>;
+
+/// Errors that may occur when checking the signature of an account.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckAccountSignatureError {
+    /// No account found for the provided account address.
+    MissingAccount,
+    /// The signatures could not be parsed by the host.
+    MalformedData,
+}
+
+/// A wrapper around [`Result`] that fixes the error variant to
+/// [`CheckAccountSignatureError`] and result to [`bool`], which is whether the
+/// signatures are valid.
+pub type CheckAccountSignatureResult = Result<bool, CheckAccountSignatureError>;
+
 /// A type representing the attributes, lazily acquired from the host.
 #[derive(Clone, Copy, Default)]
 pub struct AttributesCursor {
@@ -884,6 +959,41 @@ macro_rules! ensure_ne {
     };
 }
 
+/// Print a debug message, formatted as with [`format!`], along with the
+/// location of the macro call.
+///
+/// In native builds, such as unit tests, the message is printed to the
+/// standard error. When compiled to Wasm the macro does nothing, unless the
+/// `debug` feature is enabled, in which case the message is passed to the
+/// `debug_print` host function provided by `cargo-concordium`. See the
+/// [crate documentation](crate#debug-print-debug-messages-from-wasm) for
+/// more.
+///
+/// ```ignore
+/// concordium_dbg!("Balance before the transfer: {}", host.self_balance());
+/// ```
+#[cfg(any(feature = "debug", not(target_arch = "wasm32")))]
+#[macro_export]
+macro_rules! concordium_dbg {
+    () => {
+        $crate::debug_print(format_args!(""), file!(), line!(), column!())
+    };
+    ($($arg:tt)+) => {
+        $crate::debug_print(format_args!($($arg)+), file!(), line!(), column!())
+    };
+}
+
+/// Print a debug message, formatted as with [`format!`], along with the
+/// location of the macro call.
+///
+/// Without the `debug` feature this does nothing when compiled to Wasm, and
+/// the arguments are not evaluated.
+#[cfg(not(any(feature = "debug", not(target_arch = "wasm32"))))]
+#[macro_export]
+macro_rules! concordium_dbg {
+    ($($arg:tt)*) => {{}};
+}
+
 // Macros for failing a test
 
 /// The `fail` macro is used for testing as a substitute for the panic macro.
@@ -1098,6 +1208,86 @@ pub struct SignatureEd25519(pub [u8; 64]);
 #[repr(transparent)]
 pub struct SignatureEcdsaSecp256k1(pub [u8; 64]);
 
+/// A signature by a key of an account.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Signature {
+    /// Signature for an Ed25519 message.
+    Ed25519(SignatureEd25519),
+}
+
+/// The signatures by the keys of a credential on an account.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct CredentialSignatures {
+    /// The signatures by the key with the given index.
+    pub sigs: BTreeMap<KeyIndex, Signature>,
+}
+
+/// The signatures by the credentials of an account, which are checked against
+/// the keys and thresholds of the account with
+/// [`check_account_signature`](crate::HasHost::check_account_signature).
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct AccountSignatures {
+    /// The signatures by the credential with the given index.
+    pub sigs: BTreeMap<CredentialIndex, CredentialSignatures>,
+}
+
+/// The message an account signs to authorize a contract to act on its behalf,
+/// e.g., by the `permit` entrypoint of the CIS-3 standard. The signed bytes
+/// are the serialization of the message, i.e., the contract address, the
+/// nonce, the timestamp, the entrypoint and the payload, with the payload
+/// prefixed by its length in two bytes.
+///
+/// Contracts must check that the message is meant for them, that it has not
+/// expired and that the nonce is the expected one for the signer, before
+/// checking the signature with
+/// [`check_permit_signature`](crate::HasHost::check_permit_signature).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PermitMessage {
+    /// The contract instance the message is meant for.
+    pub contract_address: ContractAddress,
+    /// The nonce of the signer, preventing replays.
+    pub nonce:            u64,
+    /// The time until which the message can be used.
+    pub timestamp:        Timestamp,
+    /// The entrypoint to call on behalf of the signer.
+    pub entry_point:      OwnedEntrypointName,
+    /// The serialized parameter to call the entrypoint with.
+    pub payload:          Vec<u8>,
+}
+
+/// A public key of an account, used for checking signatures by the account.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PublicKey {
+    /// Public key for Ed25519.
+    Ed25519(PublicKeyEd25519),
+}
+
+/// Index of a credential on an account.
+pub type CredentialIndex = u8;
+
+/// Index of a key of a credential.
+pub type KeyIndex = u8;
+
+/// The public keys of a credential on an account, along with the number of
+/// keys which must sign for the credential to sign.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CredentialPublicKeys {
+    /// The keys of the credential.
+    pub keys:      BTreeMap<KeyIndex, PublicKey>,
+    /// The number of keys which must sign.
+    pub threshold: NonZeroU8,
+}
+
+/// The public keys of all the credentials on an account, along with the
+/// number of credentials which must sign for the account to sign.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountPublicKeys {
+    /// The keys of the credentials.
+    pub keys:      BTreeMap<CredentialIndex, CredentialPublicKeys>,
+    /// The number of credentials which must sign.
+    pub threshold: NonZeroU8,
+}
+
 /// Sha2 digest with 256 bits (32 bytes).
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
 #[repr(transparent)]
@@ -1180,3 +1370,19 @@ pub enum StateError {
     /// No nodes exist with the given prefix.
     SubtreeWithPrefixNotFound,
 }
+
+/// A fraction in basis points, i.e., hundredths of a percent, between 0 and
+/// 10000 basis points, which is 100%. This is the usual way of specifying fees,
+/// royalties and shares of amounts.
+///
+/// The value is serialized as a [`u16`], and deserializing a value above 10000
+/// fails.
+///
+/// ```
+/// # use concordium_std::*;
+/// let fee = BasisPoints::new(250).unwrap(); // 2.5%
+/// assert_eq!(fee.of(Amount::from_ccd(10)), Amount::from_micro_ccd(250_000));
+/// assert!(BasisPoints::new(10_001).is_none());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct BasisPoints(pub(crate) u16);
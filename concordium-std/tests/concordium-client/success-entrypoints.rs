@@ -0,0 +1,33 @@
+//! Ensure `#[concordium_client]` generates a client for entrypoints with and
+//! without parameters, return values, and custom names, and that the client
+//! methods can be called with a generic host.
+use concordium_std::*;
+
+#[derive(Serialize)]
+struct Weather {
+    temperature: i16,
+}
+
+#[concordium_client]
+pub trait WeatherClient {
+    /// Get the weather in a region.
+    fn get(region: u32) -> Weather;
+    fn reset();
+    #[entrypoint(name = "set", payable)]
+    fn set_weather(weather: Weather);
+}
+
+fn query<H: HasHost<()>>(
+    host: &mut H,
+    client: WeatherClient,
+) -> Result<Weather, CallContractTypedError<H::ReturnValueType>> {
+    client.reset(host)?;
+    let weather = client.get(host, &7)?;
+    client.set_weather(host, &weather, Amount::from_micro_ccd(1))?;
+    Ok(weather)
+}
+
+fn main() {
+    let _ = query::<ExternHost<()>>;
+    let _ = WeatherClient::new(ContractAddress::new(0, 0));
+}
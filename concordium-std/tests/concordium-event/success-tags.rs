@@ -0,0 +1,45 @@
+//! Ensure `#[concordium_event]` serializes variants with their default or
+//! explicit tags, and nested events with the tags of the nested event.
+use concordium_std::*;
+
+#[concordium_event]
+#[derive(Debug)]
+enum InnerEvent {
+    #[concordium(tag = 200)]
+    Minted(u32),
+}
+
+#[concordium_event]
+#[derive(Debug)]
+enum Event {
+    Created {
+        owner: AccountAddress,
+        value: u8,
+    },
+    #[concordium(tag = 255)]
+    Paused,
+    Updated(u16, bool),
+    #[concordium(nested)]
+    Inner(InnerEvent),
+}
+
+fn main() {
+    let owner = AccountAddress([1u8; 32]);
+    let mut created = vec![0];
+    created.extend_from_slice(&[1u8; 32]);
+    created.push(7);
+    assert_eq!(
+        to_bytes(&Event::Created {
+            owner,
+            value: 7,
+        }),
+        created
+    );
+    assert_eq!(to_bytes(&Event::Paused), [255]);
+    assert_eq!(to_bytes(&Event::Updated(3, true)), [2, 3, 0, 1]);
+    assert_eq!(to_bytes(&Event::Inner(InnerEvent::Minted(5))), [200, 5, 0, 0, 0]);
+
+    let mut logger = test_infrastructure::TestLogger::init();
+    logger.log_event(&Event::Paused).expect("Logging succeeds");
+    assert_eq!(logger.logs, [vec![255]]);
+}
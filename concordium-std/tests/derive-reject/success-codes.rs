@@ -0,0 +1,31 @@
+//! Ensure `derive(Reject)` maps variants without the `reject` attribute to
+//! codes by their position, and variants with it to the given code.
+use concordium_std::*;
+
+#[derive(Serial, Reject)]
+enum MyError {
+    #[from(ParseError)]
+    ParseParams,
+    #[reject(code = -42)]
+    Unauthorized,
+    LogFull,
+    #[reject(code = -1000)]
+    Paused {
+        until: u64,
+    },
+}
+
+fn code(error: MyError) -> i32 { Reject::from(error).error_code.get() }
+
+fn main() {
+    assert_eq!(code(MyError::ParseParams), -1);
+    assert_eq!(code(MyError::Unauthorized), -42);
+    assert_eq!(code(MyError::LogFull), -3);
+    assert_eq!(
+        code(MyError::Paused {
+            until: 1,
+        }),
+        -1000
+    );
+    assert_eq!(code(ParseError::default().into()), -1);
+}
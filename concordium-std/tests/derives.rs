@@ -12,3 +12,21 @@ fn deletable() {
     let t = trybuild::TestCases::new();
     t.pass("tests/derive-deletable/success-*.rs");
 }
+
+#[test]
+fn concordium_client() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/concordium-client/success-*.rs");
+}
+
+#[test]
+fn concordium_event() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/concordium-event/success-*.rs");
+}
+
+#[test]
+fn reject() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/derive-reject/success-*.rs");
+}
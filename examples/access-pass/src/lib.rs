@@ -0,0 +1,899 @@
+//! Pay-per-view access passes using the Concordium Token Standard CIS2.
+//!
+//! # Description
+//! Streamers register their streams with `add_stream`, giving the price of
+//! access, how long access lasts and the URL of the metadata of the access
+//! passes. A viewer buys access to a stream with `buy_access`, which sends the
+//! payment to the streamer and mints a new access pass to the viewer. Every
+//! access pass is an NFT, which grants access to one stream until it expires.
+//!
+//! Other contracts and off-chain services gate access to a stream using
+//! `has_access`, which checks that an account owns an access pass to the
+//! stream which has not expired. Since the access passes are CIS2 tokens, they
+//! can be transferred to other addresses as any other CIS2 token, passing the
+//! access on with them. Expired access passes are not burned, but no longer
+//! grant access.
+//!
+//! Note: The word 'address' refers to either an account address or a
+//! contract address.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// List of supported standards by this contract address.
+const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
+    [CIS0_STANDARD_IDENTIFIER, CIS2_STANDARD_IDENTIFIER];
+
+// Types
+
+/// Contract token ID type.
+/// The access passes are numbered in the order they are bought.
+type ContractTokenId = TokenIdU32;
+
+/// Contract token amount.
+/// Since the access passes are non-fungible the total supply of any token will
+/// be at most 1 and it is fine to use a small type for representing token
+/// amounts.
+type ContractTokenAmount = TokenAmountU8;
+
+/// Identifies a stream in this contract.
+pub type StreamId = u64;
+
+/// A stream which viewers can buy access to.
+#[derive(Serialize, SchemaType, Clone, Debug, PartialEq, Eq)]
+pub struct Stream {
+    /// The streamer, who receives the payments.
+    pub streamer:     AccountAddress,
+    /// The price of access.
+    pub price:        Amount,
+    /// How long access lasts from when it is bought.
+    pub duration:     Duration,
+    /// The metadata URL of the access passes to the stream.
+    pub metadata_url: MetadataUrl,
+}
+
+/// An access pass, granting access to a stream until it expires.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccessPass {
+    /// The stream the pass grants access to.
+    pub stream_id: StreamId,
+    /// When the pass expires.
+    pub expires:   Timestamp,
+}
+
+/// The parameter for the contract function `add_stream`. The sender becomes
+/// the streamer.
+#[derive(Serialize, SchemaType)]
+struct AddStreamParams {
+    /// The ID of the new stream.
+    stream_id:    StreamId,
+    /// The price of access.
+    price:        Amount,
+    /// How long access lasts from when it is bought.
+    duration:     Duration,
+    /// The metadata URL of the access passes to the stream.
+    metadata_url: MetadataUrl,
+}
+
+/// The parameter for the contract function `has_access`.
+#[derive(Serialize, SchemaType)]
+struct HasAccessParams {
+    /// The account to check.
+    account:   AccountAddress,
+    /// The stream to check access to.
+    stream_id: StreamId,
+}
+
+/// The state for each address.
+#[derive(Serial, DeserialWithState, Deletable, StateClone)]
+#[concordium(state_parameter = "S")]
+struct AddressState<S> {
+    /// The access passes owned by this address.
+    owned_tokens: StateSet<ContractTokenId, S>,
+    /// The address which are currently enabled as operators for this address.
+    operators:    StateSet<Address, S>,
+}
+
+impl<S: HasStateApi> AddressState<S> {
+    fn empty(state_builder: &mut StateBuilder<S>) -> Self {
+        AddressState {
+            owned_tokens: state_builder.new_set(),
+            operators:    state_builder.new_set(),
+        }
+    }
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The state for each address.
+    state:         StateMap<Address, AddressState<S>, S>,
+    /// The registered streams.
+    streams:       StateMap<StreamId, Stream, S>,
+    /// All of the access passes.
+    all_tokens:    StateMap<ContractTokenId, AccessPass, S>,
+    /// The token ID of the next access pass.
+    next_token_id: u32,
+}
+
+/// The custom errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum CustomContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed to invoke a contract.
+    InvokeContractError,
+    /// Only accounts can register streams.
+    OnlyAccount,
+    /// A stream with the given ID already exists.
+    StreamAlreadyExists,
+    /// There is no stream with the given ID.
+    UnknownStream,
+    /// The CCD sent along is not the price of access.
+    WrongPayment,
+    /// The expiry of the access pass cannot be represented.
+    ExpiryOverflow,
+    /// All token IDs have been used.
+    NoTokenIdsLeft,
+}
+
+/// Wrapping the custom errors in a type with CIS2 errors.
+type ContractError = Cis2Error<CustomContractError>;
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to CustomContractError.
+impl From<LogError> for CustomContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to CustomContractError.
+impl<T> From<CallContractError<T>> for CustomContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Mapping CustomContractError to ContractError
+impl From<CustomContractError> for ContractError {
+    fn from(c: CustomContractError) -> Self { Cis2Error::Custom(c) }
+}
+
+// Functions for creating, updating and querying the contract state.
+impl<S: HasStateApi> State<S> {
+    /// Creates a new state with no streams and no access passes.
+    fn empty(state_builder: &mut StateBuilder<S>) -> Self {
+        State {
+            state:         state_builder.new_map(),
+            streams:       state_builder.new_map(),
+            all_tokens:    state_builder.new_map(),
+            next_token_id: 0,
+        }
+    }
+
+    /// Mint a new access pass with a given address as the owner, returning
+    /// the token ID of the pass.
+    fn mint(
+        &mut self,
+        pass: AccessPass,
+        owner: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<ContractTokenId> {
+        let token_id = TokenIdU32(self.next_token_id);
+        self.next_token_id =
+            self.next_token_id.checked_add(1).ok_or(CustomContractError::NoTokenIdsLeft)?;
+        self.all_tokens.insert(token_id, pass);
+
+        let mut owner_state =
+            self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
+        owner_state.owned_tokens.insert(token_id);
+        Ok(token_id)
+    }
+
+    /// Check that the token ID currently exists in this contract.
+    #[inline(always)]
+    fn contains_token(&self, token_id: &ContractTokenId) -> bool {
+        self.all_tokens.get(token_id).is_some()
+    }
+
+    /// Get the metadata URL of an access pass, which is the one of its stream.
+    /// Results in an error if the token ID does not exist in the state.
+    fn metadata_url(&self, token_id: &ContractTokenId) -> ContractResult<MetadataUrl> {
+        let pass = self.all_tokens.get(token_id).ok_or(ContractError::InvalidTokenId)?;
+        let stream = self.streams.get(&pass.stream_id).ok_or(ContractError::InvalidTokenId)?;
+        Ok(stream.metadata_url.clone())
+    }
+
+    /// Get the current balance of a given token ID for a given address.
+    /// Results in an error if the token ID does not exist in the state.
+    /// Since this contract only contains NFTs, the balance will always be
+    /// either 1 or 0.
+    fn balance(
+        &self,
+        token_id: &ContractTokenId,
+        address: &Address,
+    ) -> ContractResult<ContractTokenAmount> {
+        ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
+        let balance = self
+            .state
+            .get(address)
+            .map(|address_state| u8::from(address_state.owned_tokens.contains(token_id)))
+            .unwrap_or(0);
+        Ok(balance.into())
+    }
+
+    /// Check if an address owns an access pass to a stream which has not
+    /// expired at the given time.
+    fn has_access(&self, address: &Address, stream_id: StreamId, now: Timestamp) -> bool {
+        self.state
+            .get(address)
+            .map(|address_state| {
+                address_state.owned_tokens.iter().any(|token_id| {
+                    self.all_tokens
+                        .get(&*token_id)
+                        .map(|pass| pass.stream_id == stream_id && now < pass.expires)
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Check if a given address is an operator of a given owner address.
+    fn is_operator(&self, address: &Address, owner: &Address) -> bool {
+        self.state
+            .get(owner)
+            .map(|address_state| address_state.operators.contains(address))
+            .unwrap_or(false)
+    }
+
+    /// Update the state with a transfer of some token.
+    /// Results in an error if the token ID does not exist in the state or if
+    /// the from address have insufficient tokens to do the transfer.
+    fn transfer(
+        &mut self,
+        token_id: &ContractTokenId,
+        amount: ContractTokenAmount,
+        from: &Address,
+        to: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<()> {
+        ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
+        // A zero transfer does not modify the state.
+        if amount == 0.into() {
+            return Ok(());
+        }
+        // Since this contract only contains NFTs, no one will have an amount greater
+        // than 1. And since the amount cannot be the zero at this point, the
+        // address must have insufficient funds for any amount other than 1.
+        ensure_eq!(amount, 1.into(), ContractError::InsufficientFunds);
+
+        {
+            let mut from_address_state =
+                self.state.get_mut(from).ok_or(ContractError::InsufficientFunds)?;
+            // Find and remove the token from the owner, if nothing is removed, we know the
+            // address did not own the token.
+            let from_had_the_token = from_address_state.owned_tokens.remove(token_id);
+            ensure!(from_had_the_token, ContractError::InsufficientFunds);
+        }
+
+        // Add the token to the new owner.
+        let mut to_address_state =
+            self.state.entry(*to).or_insert_with(|| AddressState::empty(state_builder));
+        to_address_state.owned_tokens.insert(*token_id);
+        Ok(())
+    }
+
+    /// Update the state adding a new operator for a given address.
+    /// Succeeds even if the `operator` is already an operator for the
+    /// `address`.
+    fn add_operator(
+        &mut self,
+        owner: &Address,
+        operator: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) {
+        let mut owner_state =
+            self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
+        owner_state.operators.insert(*operator);
+    }
+
+    /// Update the state removing an operator for a given address.
+    /// Succeeds even if the `operator` is _not_ an operator for the `address`.
+    fn remove_operator(&mut self, owner: &Address, operator: &Address) {
+        self.state.entry(*owner).and_modify(|address_state| {
+            address_state.operators.remove(operator);
+        });
+    }
+}
+
+// Contract functions
+
+/// Initialize contract instance with no streams.
+#[init(contract = "access_pass", event = "Cis2Event<ContractTokenId, ContractTokenAmount>")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    // Construct the initial contract state.
+    Ok(State::empty(state_builder))
+}
+
+/// Register a stream with the sender as the streamer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - A stream with the given ID already exists.
+#[receive(
+    contract = "access_pass",
+    name = "add_stream",
+    parameter = "AddStreamParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_add_stream<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let params: AddStreamParams = ctx.parameter_cursor().get()?;
+    let streamer = match ctx.sender() {
+        Address::Account(account_address) => account_address,
+        Address::Contract(_) => bail!(CustomContractError::OnlyAccount.into()),
+    };
+    host.state_mut()
+        .streams
+        .entry(params.stream_id)
+        .vacant_or(CustomContractError::StreamAlreadyExists)?
+        .insert(Stream {
+            streamer,
+            price: params.price,
+            duration: params.duration,
+            metadata_url: params.metadata_url,
+        });
+    Ok(())
+}
+
+/// Buy access to a stream, paying the price of access, which is sent to the
+/// streamer. Mints a new access pass to the sender, which expires when the
+/// duration of access has passed.
+/// Logs a `Mint` and a `TokenMetadata` event for the access pass.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The stream does not exist.
+/// - The amount sent along is not the price of access.
+/// - The expiry of the access pass overflows.
+/// - Fails to log Mint event
+/// - Fails to log TokenMetadata event
+#[receive(
+    contract = "access_pass",
+    name = "buy_access",
+    parameter = "StreamId",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_buy_access<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let stream_id: StreamId = ctx.parameter_cursor().get()?;
+    let owner = ctx.sender();
+
+    let (state, builder) = host.state_and_builder();
+    let stream = state.streams.get(&stream_id).ok_or(CustomContractError::UnknownStream)?.clone();
+    ensure_eq!(amount, stream.price, CustomContractError::WrongPayment.into());
+    let expires = ctx
+        .metadata()
+        .slot_time()
+        .checked_add(stream.duration)
+        .ok_or(CustomContractError::ExpiryOverflow)?;
+
+    // Mint the access pass in the state.
+    let token_id = state.mint(
+        AccessPass {
+            stream_id,
+            expires,
+        },
+        &owner,
+        builder,
+    )?;
+
+    // Event for minted access pass.
+    logger.log(&Cis2Event::Mint(MintEvent {
+        token_id,
+        amount: ContractTokenAmount::from(1),
+        owner,
+    }))?;
+
+    // Metadata URL for the access pass.
+    logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+        token_id,
+        metadata_url: stream.metadata_url,
+    }))?;
+
+    // The transfer always succeeds, since the streamer is an account and the
+    // payment was just received by the contract.
+    host.invoke_transfer(&stream.streamer, amount).unwrap_abort();
+    Ok(())
+}
+
+/// Check whether an account owns an access pass to a stream, which has not
+/// expired.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "access_pass",
+    name = "has_access",
+    parameter = "HasAccessParams",
+    return_value = "bool",
+    error = "ContractError"
+)]
+fn contract_has_access<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<bool> {
+    // Parse the parameter.
+    let params: HasAccessParams = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    Ok(host.state().has_access(&Address::Account(params.account), params.stream_id, now))
+}
+
+/// Get the access pass of a token ID, i.e. the stream and the expiry.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The `token_id` does not exist.
+#[receive(
+    contract = "access_pass",
+    name = "access_pass",
+    parameter = "ContractTokenId",
+    return_value = "AccessPass",
+    error = "ContractError"
+)]
+fn contract_access_pass<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<AccessPass> {
+    // Parse the parameter.
+    let token_id: ContractTokenId = ctx.parameter_cursor().get()?;
+    host.state().all_tokens.get(&token_id).map(|pass| *pass).ok_or(ContractError::InvalidTokenId)
+}
+
+type TransferParameter = TransferParams<ContractTokenId, ContractTokenAmount>;
+
+/// Execute a list of token transfers, in the order of the list.
+///
+/// Logs a `Transfer` event and invokes a receive hook function for every
+/// transfer in the list.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the transfers fail to be executed, which could be if:
+///     - The `token_id` does not exist.
+///     - The sender is not the owner of the token, or an operator for this
+///       specific `token_id` and `from` address.
+///     - The token is not owned by the `from`.
+/// - Fails to log event.
+/// - Any of the receive hook function calls rejects.
+#[receive(
+    contract = "access_pass",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_transfer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let TransferParams(transfers): TransferParameter = ctx.parameter_cursor().get()?;
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+
+    for Transfer {
+        token_id,
+        amount,
+        from,
+        to,
+        data,
+    } in transfers
+    {
+        let (state, builder) = host.state_and_builder();
+        // Authenticate the sender for this transfer
+        ensure!(from == sender || state.is_operator(&sender, &from), ContractError::Unauthorized);
+        let to_address = to.address();
+        // Update the contract state
+        state.transfer(&token_id, amount, &from, &to_address, builder)?;
+
+        // Log transfer event
+        logger.log(&Cis2Event::Transfer(TransferEvent {
+            token_id,
+            amount,
+            from,
+            to: to_address,
+        }))?;
+
+        // If the receiver is a contract: invoke the receive hook function.
+        if let Receiver::Contract(address, function) = to {
+            let parameter = OnReceivingCis2Params {
+                token_id,
+                amount,
+                from,
+                data,
+            };
+            host.invoke_contract(
+                &address,
+                &parameter,
+                function.as_entrypoint_name(),
+                Amount::zero(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Enable or disable addresses as operators of the sender address.
+/// Logs an `UpdateOperator` event.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Fails to log event.
+#[receive(
+    contract = "access_pass",
+    name = "updateOperator",
+    parameter = "UpdateOperatorParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_operator<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let UpdateOperatorParams(params) = ctx.parameter_cursor().get()?;
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+    let (state, builder) = host.state_and_builder();
+    for param in params {
+        // Update the operator in the state.
+        match param.update {
+            OperatorUpdate::Add => state.add_operator(&sender, &param.operator, builder),
+            OperatorUpdate::Remove => state.remove_operator(&sender, &param.operator),
+        }
+
+        // Log the appropriate event
+        logger.log(&Cis2Event::<ContractTokenId, ContractTokenAmount>::UpdateOperator(
+            UpdateOperatorEvent {
+                owner:    sender,
+                operator: param.operator,
+                update:   param.update,
+            },
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Takes a list of queries. Each query is an owner address and some address to
+/// check as an operator of the owner address.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "access_pass",
+    name = "operatorOf",
+    parameter = "OperatorOfQueryParams",
+    return_value = "OperatorOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_operator_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<OperatorOfQueryResponse> {
+    // Parse the parameter.
+    let params: OperatorOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for address being an operator of owner.
+        let is_operator = host.state().is_operator(&query.address, &query.owner);
+        response.push(is_operator);
+    }
+    let result = OperatorOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenIDs used by this contract.
+type ContractBalanceOfQueryParams = BalanceOfQueryParams<ContractTokenId>;
+/// Response type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenAmounts used by this contract.
+type ContractBalanceOfQueryResponse = BalanceOfQueryResponse<ContractTokenAmount>;
+
+/// Get the balance of given token IDs and addresses.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "access_pass",
+    name = "balanceOf",
+    parameter = "ContractBalanceOfQueryParams",
+    return_value = "ContractBalanceOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ContractBalanceOfQueryResponse> {
+    // Parse the parameter.
+    let params: ContractBalanceOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for balance.
+        let amount = host.state().balance(&query.token_id, &query.address)?;
+        response.push(amount);
+    }
+    let result = ContractBalanceOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `tokenMetadata` specialized to the
+/// subset of TokenIDs used by this contract.
+type ContractTokenMetadataQueryParams = TokenMetadataQueryParams<ContractTokenId>;
+
+/// Get the token metadata URLs and checksums given a list of token IDs.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "access_pass",
+    name = "tokenMetadata",
+    parameter = "ContractTokenMetadataQueryParams",
+    return_value = "TokenMetadataQueryResponse",
+    error = "ContractError"
+)]
+fn contract_token_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<TokenMetadataQueryResponse> {
+    // Parse the parameter.
+    let params: ContractTokenMetadataQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for token_id in params.queries {
+        // Get the metadata URL of the token, failing if it does not exist.
+        let metadata_url = host.state().metadata_url(&token_id)?;
+        response.push(metadata_url);
+    }
+    let result = TokenMetadataQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Get the supported standards given a list of standard identifiers.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "access_pass",
+    name = "supports",
+    parameter = "SupportsQueryParams",
+    return_value = "SupportsQueryResponse",
+    error = "ContractError"
+)]
+fn contract_supports<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<SupportsQueryResponse> {
+    // Parse the parameter.
+    let params: SupportsQueryParams = ctx.parameter_cursor().get()?;
+
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for std_id in params.queries {
+        if SUPPORTS_STANDARDS.contains(&std_id.as_standard_identifier()) {
+            response.push(SupportResult::Support);
+        } else {
+            response.push(SupportResult::NoSupport);
+        }
+    }
+    let result = SupportsQueryResponse::from(response);
+    Ok(result)
+}
+
+// Tests
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const STREAMER: AccountAddress = AccountAddress([0u8; 32]);
+    const VIEWER: AccountAddress = AccountAddress([1u8; 32]);
+    const VIEWER_ADDR: Address = Address::Account(VIEWER);
+    const FRIEND: AccountAddress = AccountAddress([2u8; 32]);
+    const STREAM: StreamId = 7;
+    const PRICE: Amount = Amount::from_micro_ccd(5_000);
+    const DURATION: u64 = 3_600_000;
+
+    /// Test helper function which creates the metadata URL of the access
+    /// passes to `STREAM`.
+    fn metadata_url() -> MetadataUrl {
+        MetadataUrl {
+            url:  "https://some.example/stream/7".to_string(),
+            hash: None,
+        }
+    }
+
+    /// Test helper function which creates a host where `STREAMER` has
+    /// registered `STREAM`.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let state = State::empty(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter_bytes = to_bytes(&AddStreamParams {
+            stream_id:    STREAM,
+            price:        PRICE,
+            duration:     Duration::from_millis(DURATION),
+            metadata_url: metadata_url(),
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(STREAMER));
+        ctx.set_parameter(&parameter_bytes);
+        contract_add_stream(&ctx, &mut host).expect_report("Adding the stream should pass");
+        host
+    }
+
+    /// Test helper function which buys access to `STREAM` as `VIEWER`, sending
+    /// along the given amount.
+    fn buy_access(
+        host: &mut TestHost<State<TestStateApi>>,
+        amount: Amount,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&STREAM);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(VIEWER_ADDR);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_buy_access(&ctx, host, amount, logger)
+    }
+
+    /// Test helper function which checks access of an account to `STREAM` at
+    /// the given time.
+    fn has_access(
+        host: &TestHost<State<TestStateApi>>,
+        account: AccountAddress,
+        slot_time: u64,
+    ) -> bool {
+        let parameter_bytes = to_bytes(&HasAccessParams {
+            account,
+            stream_id: STREAM,
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        contract_has_access(&ctx, host).expect_report("Checking access should pass")
+    }
+
+    /// Test buying access mints an access pass to the buyer, pays the streamer
+    /// and logs the appropriate events.
+    #[concordium_test]
+    fn test_buy_access() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+
+        let result = buy_access(&mut host, PRICE, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+
+        let token_id = TokenIdU32(0);
+        let balance = host
+            .state()
+            .balance(&token_id, &VIEWER_ADDR)
+            .expect_report("Token is expected to exist");
+        claim_eq!(balance, 1.into(), "The buyer should own the access pass");
+        claim_eq!(host.get_transfers(), [(STREAMER, PRICE)], "The streamer should be paid");
+        claim_eq!(
+            logger.logs,
+            [
+                to_bytes(&Cis2Event::Mint(MintEvent {
+                    token_id,
+                    amount: ContractTokenAmount::from(1),
+                    owner: VIEWER_ADDR,
+                })),
+                to_bytes(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+                    token_id,
+                    metadata_url: metadata_url(),
+                })),
+            ],
+            "Incorrect events emitted"
+        );
+    }
+
+    /// Test buying access fails, when the payment is not the price or the
+    /// stream does not exist.
+    #[concordium_test]
+    fn test_buy_access_wrong_payment() {
+        let mut host = new_host();
+        let result = buy_access(&mut host, Amount::from_micro_ccd(4_999), &mut TestLogger::init());
+        claim_eq!(
+            result,
+            Err(ContractError::Custom(CustomContractError::WrongPayment)),
+            "Error is expected to be WrongPayment"
+        );
+
+        let parameter_bytes = to_bytes(&(STREAM + 1));
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(VIEWER_ADDR);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_buy_access(&ctx, &mut host, PRICE, &mut TestLogger::init());
+        claim_eq!(
+            result,
+            Err(ContractError::Custom(CustomContractError::UnknownStream)),
+            "Error is expected to be UnknownStream"
+        );
+    }
+
+    /// Test access is granted until the access pass expires.
+    #[concordium_test]
+    fn test_has_access_expires() {
+        let mut host = new_host();
+        claim!(!has_access(&host, VIEWER, 0), "No access before buying");
+        buy_access(&mut host, PRICE, &mut TestLogger::init()).expect_report("Buying should pass");
+
+        claim!(has_access(&host, VIEWER, 0), "Access right after buying");
+        claim!(has_access(&host, VIEWER, DURATION - 1), "Access until the pass expires");
+        claim!(!has_access(&host, VIEWER, DURATION), "No access once the pass expires");
+    }
+
+    /// Test transferring an access pass passes the access on.
+    #[concordium_test]
+    fn test_transfer_passes_access() {
+        let mut host = new_host();
+        buy_access(&mut host, PRICE, &mut TestLogger::init()).expect_report("Buying should pass");
+
+        let parameter = TransferParams::from(vec![Transfer {
+            token_id: TokenIdU32(0),
+            amount:   ContractTokenAmount::from(1),
+            from:     VIEWER_ADDR,
+            to:       Receiver::from_account(FRIEND),
+            data:     AdditionalData::empty(),
+        }]);
+        let parameter_bytes = to_bytes(&parameter);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(VIEWER_ADDR);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_transfer(&ctx, &mut host, &mut TestLogger::init());
+        claim!(result.is_ok(), "Results in rejection");
+
+        claim!(!has_access(&host, VIEWER, 0), "The seller should lose access");
+        claim!(has_access(&host, FRIEND, 0), "The buyer should gain access");
+    }
+}
@@ -0,0 +1,831 @@
+//! A constant product automated market maker (AMM) smart contract example,
+//! pairing CCD with a CIS-2 token.
+//!
+//! # Description
+//! An instance of this contract is a liquidity pool holding reserves of CCD
+//! and of a single token of a CIS-2 contract, set at initialization.
+//!
+//! Liquidity providers add CCD and tokens to the pool in the ratio of the
+//! reserves, and receive shares of the pool in return. The first provider sets
+//! the initial ratio, and receives a share for every micro CCD added. Shares
+//! can be redeemed at any time for the same part of both reserves.
+//!
+//! Traders swap CCD for tokens or tokens for CCD, at a price which keeps the
+//! product of the reserves constant, i.e. `x * y = k`. A fee, in basis points
+//! of the input, is kept in the pool, which makes the product grow with every
+//! swap, and pays the liquidity providers.
+//!
+//! Every operation takes a limit on what the sender is willing to receive or
+//! pay, and rejects if the state of the pool changed such that the limit is
+//! exceeded before the transaction is executed.
+//!
+//! All calculations are done in integer arithmetic, rounding in favour of the
+//! pool. The products of reserves and amounts can exceed 64 bits, so they are
+//! calculated with 128-bit intermediate results, and results which do not fit
+//! into 64 bits are rejected.
+//!
+//! Tokens are taken from the sender by transferring them to the contract,
+//! which requires the contract to be an operator of the sender in the token
+//! contract.
+//!
+//! # Operations
+//! The contract allows for
+//!  - `addLiquidity`, which adds the CCD sent along and tokens to the pool;
+//!  - `removeLiquidity`, which redeems shares for CCD and tokens;
+//!  - `swapCcdForTokens`, which swaps the CCD sent along for tokens;
+//!  - `swapTokensForCcd`, which swaps tokens for CCD;
+//!  - `view`, which returns the reserves and the total number of shares;
+//!  - `sharesOf`, which returns the shares of an account.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// Token ID type of the pooled token. Using `TokenIdVec` allows pooling tokens
+/// from any CIS2 contract, since all token IDs serialize the same way.
+type ContractTokenId = TokenIdVec;
+
+/// Token amount type used for the reserve of the pooled token.
+type ContractTokenAmount = TokenAmountU64;
+
+/// A token, identified by the contract and the token ID.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    /// The address of the CIS2 contract.
+    pub contract: ContractAddress,
+    /// The ID of the token in the CIS2 contract.
+    pub id:       ContractTokenId,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The pooled token.
+    token:            TokenInfo,
+    /// The fee kept in the pool, in basis points of the input of a swap.
+    fee_basis_points: BasisPoints,
+    /// The CCD in the pool.
+    ccd_reserve:      Amount,
+    /// The tokens in the pool.
+    token_reserve:    ContractTokenAmount,
+    /// The total number of shares of the pool.
+    total_shares:     u64,
+    /// The shares of every liquidity provider.
+    shares:           StateMap<AccountAddress, u64, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The pooled token.
+    pub token:            TokenInfo,
+    /// The fee kept in the pool, in basis points of the input of a swap.
+    pub fee_basis_points: u16,
+}
+
+/// The parameter type for the contract function `addLiquidity`.
+#[derive(Serialize, SchemaType)]
+pub struct AddLiquidityParams {
+    /// The most tokens the sender is willing to add. When the pool is empty,
+    /// exactly this amount is added.
+    pub max_tokens: ContractTokenAmount,
+    /// The fewest shares the sender is willing to receive.
+    pub min_shares: u64,
+}
+
+/// The parameter type for the contract function `removeLiquidity`.
+#[derive(Serialize, SchemaType)]
+pub struct RemoveLiquidityParams {
+    /// The shares to redeem.
+    pub shares:     u64,
+    /// The least CCD the sender is willing to receive.
+    pub min_ccd:    Amount,
+    /// The fewest tokens the sender is willing to receive.
+    pub min_tokens: ContractTokenAmount,
+}
+
+/// The parameter type for the contract function `swapCcdForTokens`.
+#[derive(Serialize, SchemaType)]
+pub struct SwapCcdForTokensParams {
+    /// The fewest tokens the sender is willing to receive.
+    pub min_tokens_out: ContractTokenAmount,
+}
+
+/// The parameter type for the contract function `swapTokensForCcd`.
+#[derive(Serialize, SchemaType)]
+pub struct SwapTokensForCcdParams {
+    /// The tokens to swap.
+    pub tokens_in:   ContractTokenAmount,
+    /// The least CCD the sender is willing to receive.
+    pub min_ccd_out: Amount,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct PoolView {
+    pub token:            TokenInfo,
+    pub fee_basis_points: u16,
+    pub ccd_reserve:      Amount,
+    pub token_reserve:    ContractTokenAmount,
+    pub total_shares:     u64,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum AmmEvent {
+    /// A provider added liquidity and received shares.
+    AddLiquidity {
+        provider: AccountAddress,
+        ccd:      Amount,
+        tokens:   ContractTokenAmount,
+        shares:   u64,
+    },
+    /// A provider redeemed shares for liquidity.
+    RemoveLiquidity {
+        provider: AccountAddress,
+        ccd:      Amount,
+        tokens:   ContractTokenAmount,
+        shares:   u64,
+    },
+    /// A trader swapped CCD for tokens.
+    SwapCcdForTokens {
+        trader:     AccountAddress,
+        ccd_in:     Amount,
+        tokens_out: ContractTokenAmount,
+    },
+    /// A trader swapped tokens for CCD.
+    SwapTokensForCcd {
+        trader:    AccountAddress,
+        tokens_in: ContractTokenAmount,
+        ccd_out:   Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed invoking the token contract.
+    InvokeContractError,
+    /// Raised when a contract calls the pool; Only accounts can use it.
+    OnlyAccount,
+    /// Raised when initializing with a fee above 10000 basis points.
+    InvalidFee,
+    /// Raised when an amount is zero, or an operation results in nothing
+    /// being exchanged.
+    InvalidAmount,
+    /// Raised when swapping with an empty pool.
+    EmptyPool,
+    /// Raised when the result of an operation exceeds the limit given by the
+    /// sender.
+    SlippageExceeded,
+    /// Raised when redeeming more shares than the sender has.
+    InsufficientShares,
+    /// Raised when a calculation overflows.
+    Overflow,
+    /// Raised when receiving other tokens than the pooled token.
+    UnexpectedToken,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Convert a 128-bit intermediate result back to 64 bits.
+fn to_u64(value: u128) -> ContractResult<u64> {
+    ensure!(value <= u128::from(u64::MAX), ContractError::Overflow);
+    Ok(value as u64)
+}
+
+/// Compute `a * b / c`, rounding down. This is [`AmountArithmetic::mul_ratio`]
+/// for token amounts and shares, which are not CCD amounts.
+fn mul_div(a: u64, b: u64, c: u64) -> ContractResult<u64> {
+    to_u64(u128::from(a) * u128::from(b) / u128::from(c))
+}
+
+/// Compute `a * b / c`, rounding up.
+fn mul_div_ceil(a: u64, b: u64, c: u64) -> ContractResult<u64> {
+    let c = u128::from(c);
+    to_u64((u128::from(a) * u128::from(b) + c - 1) / c)
+}
+
+/// Compute the output of a swap of `input` into a pool with the given
+/// reserves, keeping the fee in the pool. The product of the reserves after
+/// the swap is at least the product before.
+fn swap_output(
+    input: u64,
+    input_reserve: u64,
+    output_reserve: u64,
+    fee_basis_points: BasisPoints,
+) -> ContractResult<u64> {
+    // Rounding the input after the fee down favours the pool. Both factors of
+    // the numerator fit into 64 bits, so the product fits into 128 bits.
+    let input_after_fee = mul_div(
+        input,
+        u64::from(fee_basis_points.complement().get()),
+        u64::from(BasisPoints::MAX.get()),
+    )?;
+    let numerator = u128::from(input_after_fee) * u128::from(output_reserve);
+    let denominator = u128::from(input_reserve) + u128::from(input_after_fee);
+    to_u64(numerator / denominator)
+}
+
+/// Add to a token amount, checking for overflow.
+fn add_tokens(
+    a: ContractTokenAmount,
+    b: ContractTokenAmount,
+) -> ContractResult<ContractTokenAmount> {
+    Ok(ContractTokenAmount::from(a.0.checked_add(b.0).ok_or(ContractError::Overflow)?))
+}
+
+/// Take tokens from the account into the pool.
+fn take_tokens<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    from: AccountAddress,
+    amount: ContractTokenAmount,
+) -> ContractResult<()> {
+    let to = Receiver::from_contract(
+        ctx.self_address(),
+        OwnedEntrypointName::new_unchecked("onReceivingCIS2".into()),
+    );
+    transfer_tokens(host, Address::Account(from), to, amount)
+}
+
+/// Send tokens from the pool to the account.
+fn send_tokens<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    to: AccountAddress,
+    amount: ContractTokenAmount,
+) -> ContractResult<()> {
+    transfer_tokens(host, Address::Contract(ctx.self_address()), Receiver::from_account(to), amount)
+}
+
+/// Transfer the pooled token in the token contract.
+fn transfer_tokens<S: HasStateApi>(
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    from: Address,
+    to: Receiver,
+    amount: ContractTokenAmount,
+) -> ContractResult<()> {
+    let token = host.state().token.clone();
+    let transfer = Transfer {
+        token_id: token.id,
+        amount,
+        from,
+        to,
+        data: AdditionalData::empty(),
+    };
+    host.invoke_contract(
+        &token.contract,
+        &TransferParams::from(vec![transfer]),
+        EntrypointName::new_unchecked("transfer"),
+        Amount::zero(),
+    )?;
+    Ok(())
+}
+
+// Contract functions
+
+/// Initialize an empty pool for the given token.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The fee is above 10000 basis points.
+#[init(contract = "amm", parameter = "InitParams", event = "AmmEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    Ok(State {
+        token:            params.token,
+        fee_basis_points: BasisPoints::new(params.fee_basis_points)
+            .ok_or(ContractError::InvalidFee)?,
+        ccd_reserve:      Amount::zero(),
+        token_reserve:    ContractTokenAmount::from(0),
+        total_shares:     0,
+        shares:           state_builder.new_map(),
+    })
+}
+
+/// Add the CCD sent along, and tokens in the ratio of the reserves, to the
+/// pool. The sender receives shares in the ratio of the CCD added to the CCD
+/// in the pool. When the pool is empty, the sender sets the ratio by adding
+/// exactly `max_tokens`, and receives a share for every micro CCD.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No CCD is sent along, or no tokens are added to an empty pool.
+/// - More than `max_tokens` tokens or fewer than `min_shares` shares would be
+///   exchanged.
+/// - It fails to transfer the tokens to the pool.
+/// - It fails to log the event.
+#[receive(
+    contract = "amm",
+    name = "addLiquidity",
+    parameter = "AddLiquidityParams",
+    return_value = "u64",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_add_liquidity<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<u64> {
+    let params: AddLiquidityParams = ctx.parameter_cursor().get()?;
+    let provider = sender_account(ctx)?;
+    ensure!(amount > Amount::zero(), ContractError::InvalidAmount);
+
+    let state = host.state_mut();
+    let (tokens, shares) = if state.total_shares == 0 {
+        ensure!(params.max_tokens > ContractTokenAmount::from(0), ContractError::InvalidAmount);
+        (params.max_tokens, amount.micro_ccd)
+    } else {
+        // Round the tokens up and the shares down, in favour of the pool.
+        let ccd_reserve = state.ccd_reserve.micro_ccd;
+        let tokens = mul_div_ceil(amount.micro_ccd, state.token_reserve.0, ccd_reserve)?;
+        let shares = mul_div(amount.micro_ccd, state.total_shares, ccd_reserve)?;
+        ensure!(shares > 0, ContractError::InvalidAmount);
+        (ContractTokenAmount::from(tokens), shares)
+    };
+    ensure!(
+        tokens <= params.max_tokens && shares >= params.min_shares,
+        ContractError::SlippageExceeded
+    );
+
+    state.token_reserve = add_tokens(state.token_reserve, tokens)?;
+    state.total_shares = state.total_shares.checked_add(shares).ok_or(ContractError::Overflow)?;
+    // The CCD reserve cannot overflow, since it is at most the total supply.
+    state.ccd_reserve += amount;
+    *state.shares.entry(provider).or_insert(0) += shares;
+
+    take_tokens(ctx, host, provider, tokens)?;
+    logger.log(&AmmEvent::AddLiquidity {
+        provider,
+        ccd: amount,
+        tokens,
+        shares,
+    })?;
+    Ok(shares)
+}
+
+/// Redeem shares of the sender for the same part of both reserves, rounding
+/// down.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The shares are zero, or more than the sender has.
+/// - Less than `min_ccd` CCD or fewer than `min_tokens` tokens would be
+///   received.
+/// - It fails to transfer the tokens to the sender.
+/// - It fails to log the event.
+#[receive(
+    contract = "amm",
+    name = "removeLiquidity",
+    parameter = "RemoveLiquidityParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_remove_liquidity<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: RemoveLiquidityParams = ctx.parameter_cursor().get()?;
+    let provider = sender_account(ctx)?;
+    ensure!(params.shares > 0, ContractError::InvalidAmount);
+
+    let state = host.state_mut();
+    let owned = state.shares.get(&provider).map(|s| *s).unwrap_or(0);
+    ensure!(params.shares <= owned, ContractError::InsufficientShares);
+    // The shares are at most the total shares, so the CCD is at most the
+    // reserve, and unwrapping is safe.
+    let ccd = state.ccd_reserve.mul_ratio(params.shares, state.total_shares).unwrap_abort();
+    let tokens = ContractTokenAmount::from(mul_div(
+        params.shares,
+        state.token_reserve.0,
+        state.total_shares,
+    )?);
+    ensure!(ccd >= params.min_ccd && tokens >= params.min_tokens, ContractError::SlippageExceeded);
+
+    state.ccd_reserve -= ccd;
+    state.token_reserve -= tokens;
+    state.total_shares -= params.shares;
+    if params.shares == owned {
+        state.shares.remove(&provider);
+    } else {
+        state.shares.insert(provider, owned - params.shares);
+    }
+
+    // The transfer always succeeds, since the provider is the sender and the
+    // CCD is part of the reserve held by the contract.
+    host.invoke_transfer(&provider, ccd).unwrap_abort();
+    send_tokens(ctx, host, provider, tokens)?;
+    logger.log(&AmmEvent::RemoveLiquidity {
+        provider,
+        ccd,
+        tokens,
+        shares: params.shares,
+    })?;
+    Ok(())
+}
+
+/// Swap the CCD sent along for tokens. Returns the tokens received.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The pool is empty.
+/// - No tokens would be received, or fewer than `min_tokens_out`.
+/// - It fails to transfer the tokens to the sender.
+/// - It fails to log the event.
+#[receive(
+    contract = "amm",
+    name = "swapCcdForTokens",
+    parameter = "SwapCcdForTokensParams",
+    return_value = "ContractTokenAmount",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_swap_ccd_for_tokens<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ContractTokenAmount> {
+    let params: SwapCcdForTokensParams = ctx.parameter_cursor().get()?;
+    let trader = sender_account(ctx)?;
+    let state = host.state_mut();
+    ensure!(state.total_shares > 0, ContractError::EmptyPool);
+    let tokens_out = ContractTokenAmount::from(swap_output(
+        amount.micro_ccd,
+        state.ccd_reserve.micro_ccd,
+        state.token_reserve.0,
+        state.fee_basis_points,
+    )?);
+    ensure!(tokens_out > ContractTokenAmount::from(0), ContractError::InvalidAmount);
+    ensure!(tokens_out >= params.min_tokens_out, ContractError::SlippageExceeded);
+
+    state.ccd_reserve += amount;
+    state.token_reserve -= tokens_out;
+
+    send_tokens(ctx, host, trader, tokens_out)?;
+    logger.log(&AmmEvent::SwapCcdForTokens {
+        trader,
+        ccd_in: amount,
+        tokens_out,
+    })?;
+    Ok(tokens_out)
+}
+
+/// Swap tokens of the sender for CCD. Returns the CCD received.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The pool is empty.
+/// - No CCD would be received, or less than `min_ccd_out`.
+/// - It fails to transfer the tokens to the pool.
+/// - It fails to log the event.
+#[receive(
+    contract = "amm",
+    name = "swapTokensForCcd",
+    parameter = "SwapTokensForCcdParams",
+    return_value = "Amount",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_swap_tokens_for_ccd<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<Amount> {
+    let params: SwapTokensForCcdParams = ctx.parameter_cursor().get()?;
+    let trader = sender_account(ctx)?;
+    let state = host.state_mut();
+    ensure!(state.total_shares > 0, ContractError::EmptyPool);
+    let ccd_out = Amount::from_micro_ccd(swap_output(
+        params.tokens_in.0,
+        state.token_reserve.0,
+        state.ccd_reserve.micro_ccd,
+        state.fee_basis_points,
+    )?);
+    ensure!(ccd_out > Amount::zero(), ContractError::InvalidAmount);
+    ensure!(ccd_out >= params.min_ccd_out, ContractError::SlippageExceeded);
+
+    state.token_reserve = add_tokens(state.token_reserve, params.tokens_in)?;
+    state.ccd_reserve -= ccd_out;
+
+    take_tokens(ctx, host, trader, params.tokens_in)?;
+    // The transfer always succeeds, since the trader is the sender and the CCD
+    // is part of the reserve held by the contract.
+    host.invoke_transfer(&trader, ccd_out).unwrap_abort();
+    logger.log(&AmmEvent::SwapTokensForCcd {
+        trader,
+        tokens_in: params.tokens_in,
+        ccd_out,
+    })?;
+    Ok(ccd_out)
+}
+
+/// Accept the pooled token when it is added to the pool. Rejects any other
+/// tokens.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the token contract, or the token is not the pooled
+///   token.
+#[receive(
+    contract = "amm",
+    name = "onReceivingCIS2",
+    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    error = "ContractError"
+)]
+fn contract_on_receiving_cis2<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let params: OnReceivingCis2Params<ContractTokenId, ContractTokenAmount> =
+        ctx.parameter_cursor().get()?;
+    let token = &host.state().token;
+    ensure!(
+        ctx.sender() == Address::Contract(token.contract) && params.token_id == token.id,
+        ContractError::UnexpectedToken
+    );
+    Ok(())
+}
+
+/// View the reserves and the total number of shares of the pool.
+#[receive(contract = "amm", name = "view", return_value = "PoolView")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<PoolView> {
+    let state = host.state();
+    Ok(PoolView {
+        token:            state.token.clone(),
+        fee_basis_points: state.fee_basis_points.get(),
+        ccd_reserve:      state.ccd_reserve,
+        token_reserve:    state.token_reserve,
+        total_shares:     state.total_shares,
+    })
+}
+
+/// View the shares of an account.
+#[receive(contract = "amm", name = "sharesOf", parameter = "AccountAddress", return_value = "u64")]
+fn contract_shares_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<u64> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().shares.get(&account).map(|s| *s).unwrap_or(0))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const TOKEN_CONTRACT: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index:    2,
+        subindex: 0,
+    };
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const FEE_BASIS_POINTS: u16 = 30;
+
+    fn tokens(amount: u64) -> ContractTokenAmount { ContractTokenAmount::from(amount) }
+
+    /// Set up a host with an empty pool, and a token contract accepting every
+    /// transfer of the pooled token.
+    fn initial_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            token:            TokenInfo {
+                contract: TOKEN_CONTRACT,
+                id:       TokenIdVec(vec![1]),
+            },
+            fee_basis_points: FEE_BASIS_POINTS,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.setup_mock_entrypoint(
+            TOKEN_CONTRACT,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: TransferParams<ContractTokenId, ContractTokenAmount> =
+                    from_bytes(parameter.0).expect_report("Invalid transfer parameter");
+                claim_eq!(params.0.len(), 1);
+                claim_eq!(params.0[0].token_id, TokenIdVec(vec![1]));
+                Ok((false, ()))
+            }),
+        );
+        host
+    }
+
+    fn receive_ctx<'a>(
+        sender: AccountAddress,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx.set_parameter(parameter_bytes);
+        ctx
+    }
+
+    fn add_liquidity(
+        host: &mut TestHost<State<TestStateApi>>,
+        provider: AccountAddress,
+        ccd: u64,
+        max_tokens: u64,
+        min_shares: u64,
+    ) -> ContractResult<u64> {
+        let parameter_bytes = to_bytes(&AddLiquidityParams {
+            max_tokens: tokens(max_tokens),
+            min_shares,
+        });
+        let amount = Amount::from_micro_ccd(ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        let ctx = receive_ctx(provider, &parameter_bytes);
+        contract_add_liquidity(&ctx, host, amount, &mut TestLogger::init())
+    }
+
+    fn swap_ccd(
+        host: &mut TestHost<State<TestStateApi>>,
+        ccd: u64,
+        min_tokens_out: u64,
+    ) -> ContractResult<ContractTokenAmount> {
+        let parameter_bytes = to_bytes(&SwapCcdForTokensParams {
+            min_tokens_out: tokens(min_tokens_out),
+        });
+        let amount = Amount::from_micro_ccd(ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        let ctx = receive_ctx(BOB, &parameter_bytes);
+        contract_swap_ccd_for_tokens(&ctx, host, amount, &mut TestLogger::init())
+    }
+
+    fn swap_tokens(
+        host: &mut TestHost<State<TestStateApi>>,
+        tokens_in: u64,
+        min_ccd_out: u64,
+    ) -> ContractResult<Amount> {
+        let parameter_bytes = to_bytes(&SwapTokensForCcdParams {
+            tokens_in:   tokens(tokens_in),
+            min_ccd_out: Amount::from_micro_ccd(min_ccd_out),
+        });
+        let ctx = receive_ctx(BOB, &parameter_bytes);
+        contract_swap_tokens_for_ccd(&ctx, host, &mut TestLogger::init())
+    }
+
+    /// The product of the reserves.
+    fn product(host: &TestHost<State<TestStateApi>>) -> u128 {
+        let state = host.state();
+        u128::from(state.ccd_reserve.micro_ccd) * u128::from(state.token_reserve.0)
+    }
+
+    #[concordium_test]
+    /// Test that the first provider sets the ratio, and later providers add
+    /// tokens in that ratio, rounded up, for shares rounded down.
+    fn test_add_liquidity() {
+        let mut host = initial_host();
+        let res = add_liquidity(&mut host, ALICE, 1_000_000, 0, 0);
+        claim_eq!(res, Err(ContractError::InvalidAmount), "The first provider must add tokens");
+        let shares = add_liquidity(&mut host, ALICE, 1_000_000, 500_000, 0)
+            .expect_report("Adding to an empty pool should pass");
+        claim_eq!(shares, 1_000_000);
+
+        // Adding 1001 micro CCD requires 500.5 tokens, rounded up.
+        let res = add_liquidity(&mut host, BOB, 1_001, 500, 0);
+        claim_eq!(res, Err(ContractError::SlippageExceeded), "Too few tokens are offered");
+        let res = add_liquidity(&mut host, BOB, 1_001, 501, 1_002);
+        claim_eq!(res, Err(ContractError::SlippageExceeded), "Too few shares are received");
+        let shares =
+            add_liquidity(&mut host, BOB, 1_001, 501, 1_001).expect_report("Adding should pass");
+        claim_eq!(shares, 1_001);
+        claim_eq!(
+            contract_view(&TestReceiveContext::empty(), &host),
+            Ok(PoolView {
+                token:            host.state().token.clone(),
+                fee_basis_points: FEE_BASIS_POINTS,
+                ccd_reserve:      Amount::from_micro_ccd(1_001_001),
+                token_reserve:    tokens(500_501),
+                total_shares:     1_001_001,
+            })
+        );
+    }
+
+    #[concordium_test]
+    /// Test that swaps follow the constant product formula with the fee kept
+    /// in the pool, and respect the limit of the trader.
+    fn test_swaps() {
+        let mut host = initial_host();
+        claim_eq!(swap_ccd(&mut host, 10_000, 0), Err(ContractError::EmptyPool));
+        add_liquidity(&mut host, ALICE, 1_000_000, 500_000, 0).expect_report("Adding should pass");
+
+        // 9970 * 500000 / (1000000 + 9970) = 4935.8
+        claim_eq!(swap_ccd(&mut host, 10_000, 4_936), Err(ContractError::SlippageExceeded));
+        let before = product(&host);
+        let out = swap_ccd(&mut host, 10_000, 4_935).expect_report("Swapping CCD should pass");
+        claim_eq!(out, tokens(4_935));
+        claim!(product(&host) > before, "The fee should grow the product");
+
+        let before = product(&host);
+        let out = swap_tokens(&mut host, 4_935, 0).expect_report("Swapping tokens should pass");
+        claim!(out < Amount::from_micro_ccd(10_000), "The fees should be paid twice");
+        claim!(product(&host) > before, "The fee should grow the product");
+        claim_eq!(host.get_transfers(), [(BOB, out)]);
+        claim_eq!(host.self_balance(), host.state().ccd_reserve);
+    }
+
+    #[concordium_test]
+    /// Test that shares are redeemed for the same part of both reserves,
+    /// including the fees earned.
+    fn test_remove_liquidity() {
+        let mut host = initial_host();
+        add_liquidity(&mut host, ALICE, 1_000_000, 500_000, 0).expect_report("Adding should pass");
+        add_liquidity(&mut host, BOB, 1_000_000, 500_000, 0).expect_report("Adding should pass");
+        swap_ccd(&mut host, 100_000, 0).expect_report("Swapping should pass");
+
+        let parameter_bytes = to_bytes(&RemoveLiquidityParams {
+            shares:     1_000_001,
+            min_ccd:    Amount::zero(),
+            min_tokens: tokens(0),
+        });
+        let ctx = receive_ctx(ALICE, &parameter_bytes);
+        let res = contract_remove_liquidity(&ctx, &mut host, &mut TestLogger::init());
+        claim_eq!(res, Err(ContractError::InsufficientShares));
+
+        let parameter_bytes = to_bytes(&RemoveLiquidityParams {
+            shares:     1_000_000,
+            min_ccd:    Amount::from_micro_ccd(1_050_000),
+            min_tokens: tokens(0),
+        });
+        let ctx = receive_ctx(ALICE, &parameter_bytes);
+        contract_remove_liquidity(&ctx, &mut host, &mut TestLogger::init())
+            .expect_report("Removing should pass");
+        claim_eq!(host.get_transfers(), [(ALICE, Amount::from_micro_ccd(1_050_000))]);
+        claim_eq!(host.state().shares.get(&ALICE).map(|s| *s), None);
+        claim_eq!(host.state().total_shares, 1_000_000);
+        claim_eq!(host.state().ccd_reserve, Amount::from_micro_ccd(1_050_000));
+    }
+
+    #[concordium_test]
+    /// Test that swaps with reserves close to the 64-bit limit do not overflow,
+    /// and that results which do not fit into 64 bits are rejected.
+    fn test_large_reserves() {
+        let mut host = initial_host();
+        let large = u64::MAX / 4;
+        add_liquidity(&mut host, ALICE, large, large, 0).expect_report("Adding should pass");
+        let out = swap_ccd(&mut host, large, 0).expect_report("Swapping should pass");
+        // Doubling the CCD reserve takes almost half of the tokens.
+        claim!(out < tokens(large / 2) && out > tokens(large / 2 - large / 500));
+
+        claim_eq!(mul_div(u64::MAX, u64::MAX, 1), Err(ContractError::Overflow));
+        claim_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+    }
+}
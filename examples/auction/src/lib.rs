@@ -2,8 +2,8 @@
 //!
 //! Accounts can invoke the bid function to participate in the auction.
 //! An account has to send some CCD when invoking the bid function.
-//! This CCD amount has to exceed the current highest bid by a minimum raise
-//! to be accepted by the smart contract.
+//! This CCD amount has to be at least the minimum bid, and exceed the current
+//! highest bid by a minimum raise to be accepted by the smart contract.
 //!
 //! The minimum raise is set when initializing and is defined in Euro cent.
 //! The contract uses the current exchange rate used by the chain by the time of
@@ -17,9 +17,10 @@
 //! Bids have to be placed before the auction ends. The participant with the
 //! highest bid (the last bidder) wins the auction.
 //!
-//! After the auction ends, any account can finalize the auction. The owner of
-//! the smart contract instance receives the highest bid (the balance of this
-//! contract) when the auction is finalized. This can be done only once.
+//! After the auction ends, any account can finalize the auction. The
+//! beneficiary, chosen by the owner when starting the auction, receives the
+//! highest bid (the balance of this contract) when the auction is finalized.
+//! This can be done only once.
 //!
 //! Terminology: `Accounts` are derived from a public/private key pair.
 //! `Contract` instances are created by deploying a smart contract
@@ -58,6 +59,10 @@ pub struct State {
     item:           String,
     /// Time when auction ends (to be displayed by the front-end)
     end:            Timestamp,
+    /// The smallest bid accepted.
+    minimum_bid:    Amount,
+    /// The account receiving the highest bid when the auction is finalized.
+    beneficiary:    AccountAddress,
 }
 
 /// Type of the parameter to the `init` function
@@ -69,6 +74,10 @@ struct InitParameter {
     end:           Timestamp,
     /// The minimum accepted raise to over bid the current bidder in Euro cent.
     minimum_raise: u64,
+    /// The smallest bid accepted.
+    minimum_bid:   Amount,
+    /// The account receiving the highest bid when the auction is finalized.
+    beneficiary:   AccountAddress,
 }
 
 /// `bid` function errors
@@ -79,6 +88,8 @@ enum BidError {
     OnlyAccount,
     /// Raised when new bid amount is lower than current highest bid.
     BidBelowCurrentBid,
+    /// Raised when new bid amount is lower than the minimum bid.
+    BidBelowMinimumBid,
     /// Raised when a new bid amount is raising the current highest bid
     /// with less than the minimum raise.
     BidBelowMinimumRaise,
@@ -112,6 +123,8 @@ fn auction_init<S: HasStateApi>(
         minimum_raise:  parameter.minimum_raise,
         item:           parameter.item,
         end:            parameter.end,
+        minimum_bid:    parameter.minimum_bid,
+        beneficiary:    parameter.beneficiary,
     };
     Ok(state)
 }
@@ -145,6 +158,8 @@ fn auction_bid<S: HasStateApi>(
 
     // Ensure that the new bid exceeds the highest bid so far
     ensure!(amount > previous_balance, BidError::BidBelowCurrentBid);
+    // Ensure that the new bid is at least the minimum bid
+    ensure!(amount >= state.minimum_bid, BidError::BidBelowMinimumBid);
 
     // Calculate the difference between the previous bid and the new bid in CCD.
     let amount_difference = amount - previous_balance;
@@ -190,8 +205,7 @@ fn view_highest_bid<S: HasStateApi>(
 }
 
 /// Receive function used to finalize the auction. It sends the highest bid (the
-/// current balance of this smart contract) to the owner of the smart contract
-/// instance.
+/// current balance of this smart contract) to the beneficiary of the auction.
 #[receive(contract = "auction", name = "finalize", mutable, error = "FinalizeError")]
 fn auction_finalize<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
@@ -211,16 +225,16 @@ fn auction_finalize<S: HasStateApi>(
 
     if let Some(account_address) = state.highest_bidder {
         // Marking the highest bid (the last bidder) as winner of the auction
+        let beneficiary = state.beneficiary;
         host.state_mut().auction_state = AuctionState::Sold(account_address);
-        let owner = ctx.owner();
         let balance = host.self_balance();
-        // Sending the highest bid (the balance of this contract) to the owner of the
-        // smart contract instance;
-        // This transfer (given enough NRG of course) always succeeds because the
-        // `owner` exists since it deployed the smart contract instance.
+        // Sending the highest bid (the balance of this contract) to the
+        // beneficiary;
+        // This transfer (given enough NRG of course) always succeeds as long as
+        // the `beneficiary` chosen when starting the auction exists.
         // If an account exists, and the contract has the funds then the
         // transfer will always succeed.
-        host.invoke_transfer(&owner, balance).unwrap_abort();
+        host.invoke_transfer(&beneficiary, balance).unwrap_abort();
     }
     Ok(())
 }
@@ -236,6 +250,11 @@ mod tests {
     static ADDRESS_COUNTER: AtomicU8 = AtomicU8::new(0);
     const AUCTION_END: u64 = 1;
     const ITEM: &str = "Starry night by Van Gogh";
+    const MINIMUM_BID: Amount = Amount {
+        micro_ccd: 50,
+    };
+    // The generated accounts count up from zero, so this does not collide.
+    const BENEFICIARY: AccountAddress = AccountAddress([255; 32]);
 
     fn expect_error<E, T>(expr: Result<T, E>, err: E, msg: &str)
     where
@@ -250,6 +269,8 @@ mod tests {
             item:          ITEM.into(),
             end:           Timestamp::from_timestamp_millis(AUCTION_END),
             minimum_raise: 100,
+            minimum_bid:   MINIMUM_BID,
+            beneficiary:   BENEFICIARY,
         }
     }
 
@@ -327,7 +348,8 @@ mod tests {
     /// 4. Someone tries to finalize the auction before
     /// its end time. Attempt fails.
     /// 5. Dave successfully finalizes the auction after its end time.
-    /// Carol (the owner of the contract) collects the highest bid amount.
+    /// The beneficiary collects the highest bid amount, not Carol (the owner
+    /// of the contract).
     /// 6. Attempts to subsequently bid or finalize fail.
     fn test_auction_bid_and_finalize() {
         let parameter_bytes = create_parameter_bytes(&item_end_parameter());
@@ -389,8 +411,8 @@ mod tests {
         // functions are checked here.
         claim_eq!(
             &transfers[..],
-            &[(alice, amount), (alice, amount + amount), (carol, winning_amount),],
-            "Transferring CCD to Alice/Carol should work"
+            &[(alice, amount), (alice, amount + amount), (BENEFICIARY, winning_amount),],
+            "Transferring CCD to Alice/the beneficiary should work"
         );
         claim_eq!(
             host.state().auction_state,
@@ -485,4 +507,98 @@ mod tests {
         let res = auction_bid(&ctx1, &mut host, Amount::zero());
         expect_error(res, BidError::BidBelowCurrentBid, "Bidding zero should fail");
     }
+    #[concordium_test]
+    /// Bids below the minimum bid should be rejected, and a bid of exactly the
+    /// minimum bid should be accepted.
+    fn test_auction_bid_minimum_bid() {
+        let ctx1 = new_account_ctx().1;
+        let parameter_bytes = create_parameter_bytes(&item_end_parameter());
+        let ctx = parametrized_init_ctx(&parameter_bytes);
+
+        let mut state_builder = TestStateBuilder::new();
+
+        // initializing auction
+        let initial_state =
+            auction_init(&ctx, &mut state_builder).expect("Initialization should succeed.");
+
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_exchange_rates(ExchangeRates {
+            euro_per_energy:    ExchangeRate::new_unchecked(1, 1),
+            micro_ccd_per_euro: ExchangeRate::new_unchecked(1, 1),
+        });
+
+        let below_minimum = MINIMUM_BID - Amount::from_micro_ccd(1);
+        host.set_self_balance(below_minimum);
+        let res = auction_bid(&ctx1, &mut host, below_minimum);
+        expect_error(
+            res,
+            BidError::BidBelowMinimumBid,
+            "Bidding below the minimum bid should fail",
+        );
+
+        // A bid of exactly the minimum bid is accepted.
+        bid(&mut host, &ctx1, MINIMUM_BID, Amount::zero());
+    }
+
+    #[concordium_test]
+    /// Bids placed exactly at the auction end should be accepted, and bids
+    /// placed after it should be rejected.
+    fn test_auction_bid_end_time() {
+        let (alice, alice_ctx) = new_account_ctx();
+        let parameter_bytes = create_parameter_bytes(&item_end_parameter());
+        let ctx = parametrized_init_ctx(&parameter_bytes);
+
+        let mut state_builder = TestStateBuilder::new();
+
+        // initializing auction
+        let initial_state =
+            auction_init(&ctx, &mut state_builder).expect("Initialization should succeed.");
+
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_exchange_rates(ExchangeRates {
+            euro_per_energy:    ExchangeRate::new_unchecked(1, 1),
+            micro_ccd_per_euro: ExchangeRate::new_unchecked(1, 1),
+        });
+
+        // Alice bids at the last moment (`new_account_ctx` uses AUCTION_END).
+        let amount = Amount::from_micro_ccd(200);
+        bid(&mut host, &alice_ctx, amount, Amount::zero());
+        claim_eq!(host.state().highest_bidder, Some(alice), "Alice should be the highest bidder");
+
+        // Bob bids a millisecond too late.
+        let bob = new_account();
+        let bob_ctx = new_ctx(bob, bob, AUCTION_END + 1);
+        let bob_amount = Amount::from_micro_ccd(500);
+        host.set_self_balance(amount + bob_amount);
+        let res = auction_bid(&bob_ctx, &mut host, bob_amount);
+        expect_error(res, BidError::BidTooLate, "Bidding after the auction end should fail");
+        claim_eq!(host.state().highest_bidder, Some(alice), "Alice should still be the highest");
+    }
+
+    #[concordium_test]
+    /// Finalizing an auction without bids should succeed without transferring
+    /// anything, and leave the item unsold.
+    fn test_auction_finalize_without_bids() {
+        let parameter_bytes = create_parameter_bytes(&item_end_parameter());
+        let ctx = parametrized_init_ctx(&parameter_bytes);
+
+        let mut state_builder = TestStateBuilder::new();
+
+        // initializing auction
+        let initial_state =
+            auction_init(&ctx, &mut state_builder).expect("Initialization should succeed.");
+
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let account = new_account();
+        let finalize_ctx = new_ctx(account, account, AUCTION_END + 1);
+        auction_finalize(&finalize_ctx, &mut host).expect_report("Finalizing should work");
+
+        claim!(host.get_transfers().is_empty(), "Nothing should be transferred");
+        claim_eq!(
+            host.state().auction_state,
+            AuctionState::NotSoldYet,
+            "The item should not be sold without bids"
+        );
+    }
 }
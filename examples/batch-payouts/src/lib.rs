@@ -0,0 +1,230 @@
+//! # Batch payouts
+//!
+//! A stateless contract paying out many accounts in one transaction, e.g., the
+//! earnings of creators at the end of a payout period. The sender calls
+//! `distribute` with a list of accounts and amounts, sending along at least
+//! the sum of the amounts.
+//!
+//! A payout to an account which does not exist does not fail the whole batch.
+//! Instead, the payout is skipped and reported with a `Skipped` event, so the
+//! sender can correct the account and pay it in a later batch. The skipped
+//! amounts and anything sent along above the sum are returned to the sender,
+//! and a `Distributed` event summarizes the batch.
+//!
+//! Note that a transaction can log a limited number of events, which limits
+//! the number of payouts which can be skipped in one batch.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The payouts to make, as pairs of the receiving account and the amount.
+pub type Payouts = Vec<(AccountAddress, Amount)>;
+
+/// The contract has no state.
+#[derive(Serialize, SchemaType)]
+struct State;
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum PayoutEvent {
+    /// A payout was skipped, since the account does not exist.
+    Skipped {
+        to:     AccountAddress,
+        amount: Amount,
+    },
+    /// A batch was distributed.
+    Distributed {
+        /// The number of payouts made.
+        paid:     u32,
+        /// The total amount paid out.
+        total:    Amount,
+        /// The amount returned to the sender.
+        returned: Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract distributes the payouts.
+    OnlyAccount,
+    /// Raised when the sum of the payouts overflows.
+    Overflow,
+    /// Raised when the amount sent along is less than the sum of the payouts.
+    InsufficientAmount,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Initialize the contract.
+#[init(contract = "batch_payouts", event = "PayoutEvent")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+) -> InitResult<State> {
+    Ok(State)
+}
+
+/// Pay out the amounts to the accounts, returning the amounts of missing
+/// accounts and the surplus to the sender.
+/// Logs a `Skipped` event for every missing account, and a `Distributed`
+/// event.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The sum of the payouts overflows or exceeds the amount sent along.
+/// - It fails to log the events.
+#[receive(
+    contract = "batch_payouts",
+    name = "distribute",
+    parameter = "Payouts",
+    error = "ContractError",
+    enable_logger,
+    payable
+)]
+fn contract_distribute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let payouts: Payouts = ctx.parameter_cursor().get()?;
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let sum = payouts.iter().try_fold(0u64, |sum, (_, payout)| {
+        sum.checked_add(payout.micro_ccd).ok_or(ContractError::Overflow)
+    })?;
+    ensure!(sum <= amount.micro_ccd, ContractError::InsufficientAmount);
+
+    let mut paid = 0;
+    let mut total = Amount::zero();
+    for (to, payout) in payouts {
+        // Transfers can only fail for missing accounts, since the contract
+        // received at least the sum of the payouts.
+        match host.invoke_transfer(&to, payout) {
+            Ok(()) => {
+                paid += 1;
+                total += payout;
+            }
+            Err(_) => logger.log(&PayoutEvent::Skipped {
+                to,
+                amount: payout,
+            })?,
+        }
+    }
+
+    // The transfer always succeeds, since the sender is an existing account,
+    // and the contract received more than it paid out.
+    let returned = amount - total;
+    if returned > Amount::zero() {
+        host.invoke_transfer(&sender, returned).unwrap_abort();
+    }
+    logger.log(&PayoutEvent::Distributed {
+        paid,
+        total,
+        returned,
+    })?;
+    Ok(())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const PLATFORM: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+
+    fn distribute(
+        host: &TestHost<State>,
+        payouts: Payouts,
+        amount: Amount,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&payouts);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(PLATFORM));
+        ctx.set_parameter(&parameter_bytes);
+        contract_distribute(&ctx, host, amount, logger)
+    }
+
+    #[concordium_test]
+    /// Test that every account is paid, and the surplus is returned.
+    fn test_distribute() {
+        let mut host = TestHost::new(State, TestStateBuilder::new());
+        host.set_self_balance(Amount::from_micro_ccd(100));
+        let mut logger = TestLogger::init();
+        let payouts = vec![(ALICE, Amount::from_micro_ccd(60)), (BOB, Amount::from_micro_ccd(30))];
+        let res = distribute(&host, payouts.clone(), Amount::from_micro_ccd(89), &mut logger);
+        claim_eq!(res, Err(ContractError::InsufficientAmount));
+
+        distribute(&host, payouts, Amount::from_micro_ccd(100), &mut logger)
+            .expect_report("Distributing should pass");
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(60)),
+            (BOB, Amount::from_micro_ccd(30)),
+            (PLATFORM, Amount::from_micro_ccd(10)),
+        ]);
+        claim_eq!(logger.logs, [to_bytes(&PayoutEvent::Distributed {
+            paid:     2,
+            total:    Amount::from_micro_ccd(90),
+            returned: Amount::from_micro_ccd(10),
+        })]);
+    }
+
+    #[concordium_test]
+    /// Test that missing accounts are skipped and reported, and their amounts
+    /// returned, without failing the batch.
+    fn test_skip_missing_accounts() {
+        let mut host = TestHost::new(State, TestStateBuilder::new());
+        host.set_self_balance(Amount::from_micro_ccd(90));
+        host.make_account_missing(BOB);
+        let mut logger = TestLogger::init();
+        let payouts = vec![
+            (ALICE, Amount::from_micro_ccd(60)),
+            (BOB, Amount::from_micro_ccd(20)),
+            (CAROL, Amount::from_micro_ccd(10)),
+        ];
+        distribute(&host, payouts, Amount::from_micro_ccd(90), &mut logger)
+            .expect_report("Distributing should pass");
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(60)),
+            (CAROL, Amount::from_micro_ccd(10)),
+            (PLATFORM, Amount::from_micro_ccd(20)),
+        ]);
+        claim_eq!(logger.logs, [
+            to_bytes(&PayoutEvent::Skipped {
+                to:     BOB,
+                amount: Amount::from_micro_ccd(20),
+            }),
+            to_bytes(&PayoutEvent::Distributed {
+                paid:     2,
+                total:    Amount::from_micro_ccd(70),
+                returned: Amount::from_micro_ccd(20),
+            }),
+        ]);
+    }
+}
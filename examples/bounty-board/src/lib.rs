@@ -0,0 +1,529 @@
+//! # A bounty board for clip editors
+//!
+//! Streamers `post` bounties for work like editing clips, sending the reward
+//! along, which the contract holds in escrow until the bounty is settled.
+//! Until the deadline of a bounty, editors `submit` the hash of their work,
+//! e.g., of the edited clip, which they deliver off-chain. An editor submits at
+//! most once per bounty.
+//!
+//! The poster settles a bounty in one of the following ways:
+//! - The poster `approve`s a submission, at any time before the bounty is
+//!   otherwise settled, which releases the reward to its editor.
+//! - If no work was submitted by the deadline, the poster can `refund` the
+//!   reward.
+//!
+//! Once work is submitted, the poster cannot take the reward back. If the
+//! poster disputes all submissions and approves none of them within the review
+//! period after the deadline, the review times out, and the editors who
+//! submitted work split the reward equally, each claiming their share with
+//! `claimTimeout`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// Identifies a bounty.
+pub type BountyId = u64;
+
+/// The status of a bounty.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BountyStatus {
+    /// The bounty awaits submissions or a review.
+    Open,
+    /// The reward was released to the editor of the approved submission.
+    Paid {
+        editor: AccountAddress,
+    },
+    /// The reward was refunded to the poster, since no work was submitted.
+    Refunded,
+    /// The review timed out, and the editors who submitted work are claiming
+    /// their shares.
+    TimedOut {
+        remaining:   Amount,
+        claims_left: u32,
+    },
+}
+
+/// A bounty.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounty {
+    /// The account which posted the bounty.
+    pub poster:      AccountAddress,
+    /// The reward held in escrow.
+    pub reward:      Amount,
+    /// The last time work can be submitted.
+    pub deadline:    Timestamp,
+    /// The number of submissions.
+    pub submissions: u32,
+    /// The status of the bounty.
+    pub status:      BountyStatus,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// How long posters have after the deadline to approve a submission.
+    review_period: Duration,
+    /// The ID of the next bounty.
+    next_id:       BountyId,
+    /// The bounties.
+    bounties:      StateMap<BountyId, Bounty, S>,
+    /// The hash of the work submitted by every editor to every bounty. Entries
+    /// are removed when editors claim their share after the review timed out.
+    submissions:   StateMap<(BountyId, AccountAddress), HashSha2256, S>,
+}
+
+/// The parameter type for the contract function `submit`.
+#[derive(Serialize, SchemaType)]
+struct SubmitParams {
+    /// The bounty to submit work to.
+    bounty_id: BountyId,
+    /// The hash of the work.
+    work_hash: HashSha2256,
+}
+
+/// The parameter type for the contract function `approve`.
+#[derive(Serialize, SchemaType)]
+struct ApproveParams {
+    /// The bounty to settle.
+    bounty_id: BountyId,
+    /// The editor of the approved submission.
+    editor:    AccountAddress,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when a contract posts, submits to or reclaims a bounty.
+    OnlyAccount,
+    /// Raised when posting a bounty without a reward.
+    ZeroReward,
+    /// Raised when posting a bounty with a deadline which has passed.
+    DeadlineInPast,
+    /// Raised when the bounty does not exist.
+    UnknownBounty,
+    /// Raised when the bounty was settled already.
+    NotOpen,
+    /// Raised when submitting after the deadline.
+    DeadlinePassed,
+    /// Raised when an editor submits to the same bounty twice.
+    AlreadySubmitted,
+    /// Raised when someone else than the poster approves or refunds.
+    Unauthorized,
+    /// Raised when approving an editor, or claiming a share, without a
+    /// submission.
+    NoSubmission,
+    /// Raised when refunding before the deadline, or claiming a share before
+    /// the review timed out.
+    TooEarly,
+    /// Raised when refunding a bounty with submissions.
+    HasSubmissions,
+    /// Raised when claiming a share of a bounty without submissions.
+    NoSubmissions,
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Get a bounty, rejecting if it does not exist.
+fn get_bounty<S: HasStateApi>(state: &State<S>, bounty_id: BountyId) -> ContractResult<Bounty> {
+    state.bounties.get(&bounty_id).map(|bounty| *bounty).ok_or(ContractError::UnknownBounty)
+}
+
+/// Init function that creates an empty bounty board with the review period.
+#[init(contract = "bounty_board", parameter = "Duration")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let review_period: Duration = ctx.parameter_cursor().get()?;
+    Ok(State {
+        review_period,
+        next_id: 0,
+        bounties: state_builder.new_map(),
+        submissions: state_builder.new_map(),
+    })
+}
+
+/// Post a bounty with the deadline, sending the reward along. Returns the ID
+/// of the bounty.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No CCD is sent.
+/// - The deadline has passed.
+#[receive(
+    contract = "bounty_board",
+    name = "post",
+    parameter = "Timestamp",
+    return_value = "BountyId",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn contract_post<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<BountyId> {
+    let deadline: Timestamp = ctx.parameter_cursor().get()?;
+    let poster = sender_account(ctx)?;
+    ensure!(amount > Amount::zero(), ContractError::ZeroReward);
+    ensure!(deadline > ctx.metadata().slot_time(), ContractError::DeadlineInPast);
+
+    let state = host.state_mut();
+    let bounty_id = state.next_id;
+    state.next_id += 1;
+    state.bounties.insert(bounty_id, Bounty {
+        poster,
+        reward: amount,
+        deadline,
+        submissions: 0,
+        status: BountyStatus::Open,
+    });
+    Ok(bounty_id)
+}
+
+/// Submit the hash of work to a bounty.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The bounty does not exist, or was settled already.
+/// - The deadline has passed.
+/// - The sender already submitted work to the bounty.
+#[receive(
+    contract = "bounty_board",
+    name = "submit",
+    parameter = "SubmitParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_submit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let params: SubmitParams = ctx.parameter_cursor().get()?;
+    let editor = sender_account(ctx)?;
+    let state = host.state_mut();
+    let mut bounty = get_bounty(state, params.bounty_id)?;
+    ensure_eq!(bounty.status, BountyStatus::Open, ContractError::NotOpen);
+    ensure!(ctx.metadata().slot_time() <= bounty.deadline, ContractError::DeadlinePassed);
+
+    state
+        .submissions
+        .entry((params.bounty_id, editor))
+        .vacant_or(ContractError::AlreadySubmitted)?
+        .insert(params.work_hash);
+    bounty.submissions += 1;
+    state.bounties.insert(params.bounty_id, bounty);
+    Ok(())
+}
+
+/// Approve the submission of an editor, releasing the reward to the editor.
+/// Only the poster can approve.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The bounty does not exist, or was settled already.
+/// - The sender is not the poster.
+/// - The editor did not submit work to the bounty.
+#[receive(
+    contract = "bounty_board",
+    name = "approve",
+    parameter = "ApproveParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_approve<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let params: ApproveParams = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let mut bounty = get_bounty(state, params.bounty_id)?;
+    ensure_eq!(bounty.status, BountyStatus::Open, ContractError::NotOpen);
+    ensure!(ctx.sender().matches_account(&bounty.poster), ContractError::Unauthorized);
+    ensure!(
+        state.submissions.get(&(params.bounty_id, params.editor)).is_some(),
+        ContractError::NoSubmission
+    );
+
+    bounty.status = BountyStatus::Paid {
+        editor: params.editor,
+    };
+    state.bounties.insert(params.bounty_id, bounty);
+    // The transfer always succeeds, since the editor is an account which
+    // submitted work and the contract holds the reward.
+    host.invoke_transfer(&params.editor, bounty.reward).unwrap_abort();
+    Ok(())
+}
+
+/// Refund the reward of a bounty without submissions to the poster, after the
+/// deadline.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The bounty does not exist, or was settled already.
+/// - The sender is not the poster.
+/// - The deadline has not passed.
+/// - Work was submitted to the bounty.
+#[receive(
+    contract = "bounty_board",
+    name = "refund",
+    parameter = "BountyId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let bounty_id: BountyId = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let mut bounty = get_bounty(state, bounty_id)?;
+    ensure_eq!(bounty.status, BountyStatus::Open, ContractError::NotOpen);
+    ensure!(ctx.sender().matches_account(&bounty.poster), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() > bounty.deadline, ContractError::TooEarly);
+    ensure_eq!(bounty.submissions, 0, ContractError::HasSubmissions);
+
+    bounty.status = BountyStatus::Refunded;
+    state.bounties.insert(bounty_id, bounty);
+    // The transfer always succeeds, since the poster is the sender and the
+    // contract holds the reward.
+    host.invoke_transfer(&bounty.poster, bounty.reward).unwrap_abort();
+    Ok(())
+}
+
+/// Claim the share of the reward of the sender, after the review of a bounty
+/// timed out without an approval. The reward is split equally among the
+/// editors who submitted work, with the last claim getting any remainder.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The bounty does not exist, or was settled otherwise.
+/// - The review period after the deadline has not passed.
+/// - No work was submitted to the bounty.
+/// - The sender did not submit work, or claimed their share already.
+#[receive(
+    contract = "bounty_board",
+    name = "claimTimeout",
+    parameter = "BountyId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_claim_timeout<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let bounty_id: BountyId = ctx.parameter_cursor().get()?;
+    let editor = sender_account(ctx)?;
+    let state = host.state_mut();
+    let mut bounty = get_bounty(state, bounty_id)?;
+    let (remaining, claims_left) = match bounty.status {
+        BountyStatus::Open => {
+            let review_end = bounty.deadline.checked_add(state.review_period);
+            ensure!(
+                review_end.map_or(false, |end| ctx.metadata().slot_time() > end),
+                ContractError::TooEarly
+            );
+            ensure!(bounty.submissions > 0, ContractError::NoSubmissions);
+            (bounty.reward, bounty.submissions)
+        }
+        BountyStatus::TimedOut {
+            remaining,
+            claims_left,
+        } => (remaining, claims_left),
+        _ => bail!(ContractError::NotOpen),
+    };
+    ensure!(
+        state.submissions.remove_and_get(&(bounty_id, editor)).is_some(),
+        ContractError::NoSubmission
+    );
+
+    // Since every editor who submitted claims once, `claims_left` is positive.
+    let share = if claims_left == 1 {
+        remaining
+    } else {
+        Amount::from_micro_ccd(remaining.micro_ccd / u64::from(claims_left))
+    };
+    bounty.status = BountyStatus::TimedOut {
+        remaining:   remaining - share,
+        claims_left: claims_left - 1,
+    };
+    state.bounties.insert(bounty_id, bounty);
+    // The transfer always succeeds, since the editor is the sender and the
+    // contract holds the remaining reward.
+    host.invoke_transfer(&editor, share).unwrap_abort();
+    Ok(())
+}
+
+/// View a bounty.
+#[receive(
+    contract = "bounty_board",
+    name = "bounty",
+    parameter = "BountyId",
+    return_value = "Bounty",
+    error = "ContractError"
+)]
+fn contract_bounty<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Bounty> {
+    let bounty_id: BountyId = ctx.parameter_cursor().get()?;
+    get_bounty(host.state(), bounty_id)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const POSTER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+    const DEADLINE: u64 = 100;
+    const REVIEW_PERIOD: u64 = 50;
+    const REWARD: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    /// Create a host where `POSTER` posted bounty 0.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&Duration::from_millis(REVIEW_PERIOD));
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter_bytes = to_bytes(&Timestamp::from_timestamp_millis(DEADLINE));
+        let mut ctx = ctx_at(POSTER, 0);
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(REWARD);
+        let id = contract_post(&ctx, &mut host, REWARD).expect_report("Posting should pass");
+        claim_eq!(id, 0);
+        host
+    }
+
+    fn submit(
+        host: &mut TestHost<State<TestStateApi>>,
+        editor: AccountAddress,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&SubmitParams {
+            bounty_id: 0,
+            work_hash: HashSha2256([editor.0[0]; 32]),
+        });
+        let mut ctx = ctx_at(editor, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        contract_submit(&ctx, host)
+    }
+
+    fn approve(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        editor: AccountAddress,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&ApproveParams {
+            bounty_id: 0,
+            editor,
+        });
+        let mut ctx = ctx_at(sender, DEADLINE + 1);
+        ctx.set_parameter(&parameter_bytes);
+        contract_approve(&ctx, host)
+    }
+
+    fn refund(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&0u64);
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        contract_refund(&ctx, host)
+    }
+
+    fn claim_timeout(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&0u64);
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        contract_claim_timeout(&ctx, host)
+    }
+
+    #[concordium_test]
+    /// Test that the poster approving a submission releases the reward to its
+    /// editor.
+    fn test_approve() {
+        let mut host = new_host();
+        submit(&mut host, ALICE, DEADLINE).expect_report("Submitting should pass");
+        claim_eq!(submit(&mut host, ALICE, DEADLINE), Err(ContractError::AlreadySubmitted));
+        claim_eq!(submit(&mut host, BOB, DEADLINE + 1), Err(ContractError::DeadlinePassed));
+        claim_eq!(approve(&mut host, ALICE, ALICE), Err(ContractError::Unauthorized));
+        claim_eq!(approve(&mut host, POSTER, BOB), Err(ContractError::NoSubmission));
+        approve(&mut host, POSTER, ALICE).expect_report("Approving should pass");
+        claim_eq!(host.get_transfers(), [(ALICE, REWARD)]);
+        claim_eq!(approve(&mut host, POSTER, ALICE), Err(ContractError::NotOpen));
+    }
+
+    #[concordium_test]
+    /// Test that the poster can refund a bounty without submissions after the
+    /// deadline.
+    fn test_refund() {
+        let mut host = new_host();
+        let res = refund(&mut host, POSTER, DEADLINE);
+        claim_eq!(res, Err(ContractError::TooEarly));
+        let res = refund(&mut host, ALICE, DEADLINE + 1);
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        refund(&mut host, POSTER, DEADLINE + 1).expect_report("Refunding should pass");
+        claim_eq!(host.get_transfers(), [(POSTER, REWARD)]);
+        claim_eq!(submit(&mut host, ALICE, 0), Err(ContractError::NotOpen));
+    }
+
+    #[concordium_test]
+    /// Test that the editors split the reward when the review times out, and
+    /// that the poster cannot refund once work was submitted.
+    fn test_review_timeout() {
+        let mut host = new_host();
+        for editor in [ALICE, BOB, CAROL] {
+            submit(&mut host, editor, 0).expect_report("Submitting should pass");
+        }
+        let timeout = DEADLINE + REVIEW_PERIOD;
+        let res = refund(&mut host, POSTER, timeout + 1);
+        claim_eq!(res, Err(ContractError::HasSubmissions));
+        let res = claim_timeout(&mut host, ALICE, timeout);
+        claim_eq!(res, Err(ContractError::TooEarly));
+
+        for editor in [ALICE, BOB, CAROL] {
+            claim_timeout(&mut host, editor, timeout + 1).expect_report("Claiming should pass");
+        }
+        let res = claim_timeout(&mut host, ALICE, timeout + 1);
+        claim_eq!(res, Err(ContractError::NoSubmission), "Shares are claimed once");
+        let res = claim_timeout(&mut host, POSTER, timeout + 1);
+        claim_eq!(res, Err(ContractError::NoSubmission));
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(333)),
+            (BOB, Amount::from_micro_ccd(333)),
+            (CAROL, Amount::from_micro_ccd(334))
+        ]);
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+}
@@ -0,0 +1,47 @@
+#!/usr/bin/env bash
+# Build some of the examples with the default allocator and with the bump
+# allocator, and report the size of the modules and the energy used by their
+# init functions, and by the `view` function of `cis2_nft`.
+#
+# Requires `cargo concordium` to be installed. Run from any directory:
+#
+#     examples/bump-alloc/compare-allocators.sh
+set -euo pipefail
+
+examples_dir="$(cd "$(dirname "$0")/.." && pwd)"
+out_dir="$(mktemp -d)"
+trap 'rm -rf "$out_dir"' EXIT
+
+# The examples, with the names of their contracts.
+examples=("fib:fib" "cis2-nft:cis2_nft" "nametoken:NameToken")
+energy=1000000
+
+# Print the energy reported by `cargo concordium run`.
+energy_spent() {
+    sed -n 's/.*Interpreter energy spent is \([0-9]*\).*/\1/p'
+}
+
+printf "%-12s %-10s %12s %12s %12s\n" example allocator "size (bytes)" "init energy" "view energy"
+for entry in "${examples[@]}"; do
+    example="${entry%%:*}"
+    contract="${entry##*:}"
+    for allocator in default bump-alloc; do
+        features=()
+        if [ "$allocator" = bump-alloc ]; then
+            features=(--features concordium-std/bump-alloc)
+        fi
+        module="$out_dir/$example-$allocator.wasm.v1"
+        state="$out_dir/$example-$allocator.state"
+        (cd "$examples_dir/$example" &&
+            cargo concordium build --out "$module" -- ${features[@]+"${features[@]}"} >/dev/null 2>&1)
+        size=$(wc -c <"$module")
+        init_energy=$(cargo concordium run init --module "$module" --contract "$contract" \
+            --energy "$energy" --out-bin "$state" 2>&1 | energy_spent)
+        view_energy="-"
+        if [ "$contract" = cis2_nft ]; then
+            view_energy=$(cargo concordium run update --module "$module" --contract "$contract" \
+                --entrypoint view --state-bin "$state" --energy "$energy" 2>&1 | energy_spent)
+        fi
+        printf "%-12s %-10s %12s %12s %12s\n" "$example" "$allocator" "$size" "$init_energy" "$view_energy"
+    done
+done
@@ -0,0 +1,113 @@
+//! # A contract built with the bump allocator
+//!
+//! This contract is built with the `bump-alloc` feature of `concordium-std`,
+//! which replaces the default allocator with a bump allocator. The contract
+//! joins a list of strings, which allocates a string and grows it for every
+//! string added, and the tests check the behaviour of the allocator when run
+//! in Wasm with `cargo concordium test`.
+//!
+//! The script `compare-allocators.sh` builds some of the other examples with
+//! both allocators and reports the module sizes and the energy used.
+#![cfg_attr(not(feature = "std"), no_std)]
+use concordium_std::*;
+
+/// Set up the contract, which has no state.
+#[init(contract = "bump_alloc")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+) -> InitResult<()> {
+    Ok(())
+}
+
+/// Join the strings of the parameter, separated by spaces.
+#[receive(
+    contract = "bump_alloc",
+    name = "join",
+    parameter = "Vec<String>",
+    return_value = "String"
+)]
+fn contract_join<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<(), StateApiType = S>,
+) -> ReceiveResult<String> {
+    let words: Vec<String> = ctx.parameter_cursor().get()?;
+    let mut joined = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            joined.push(' ');
+        }
+        joined.push_str(word);
+    }
+    Ok(joined)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    /// The address of the data of a box, for comparing allocations.
+    fn address<T>(value: &T) -> usize { value as *const T as usize }
+
+    #[concordium_test]
+    fn test_join() {
+        let parameter = to_bytes(&vec![String::from("hello"), String::from("world")]);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_parameter(&parameter);
+        let host = TestHost::new((), TestStateBuilder::new());
+
+        let joined = contract_join(&ctx, &host).expect_report("Joining fails");
+        claim_eq!(joined, "hello world");
+    }
+
+    /// Freeing the most recent allocation makes its memory available for the
+    /// next allocation.
+    #[concordium_test]
+    fn test_dealloc_last_allocation() {
+        let first = Box::new([1u64; 4]);
+        let second = Box::new([2u64; 4]);
+        let second_address = address(&*second);
+        drop(second);
+        let third = Box::new([3u64; 4]);
+        #[cfg(target_arch = "wasm32")]
+        claim_eq!(address(&*third), second_address, "The freed memory should be reused");
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = second_address;
+        claim_eq!(*first, [1u64; 4]);
+        claim_eq!(*third, [3u64; 4]);
+    }
+
+    /// Growing the most recent allocation does not move it.
+    #[concordium_test]
+    fn test_realloc_last_allocation_in_place() {
+        let mut bytes: Vec<u8> = Vec::with_capacity(4);
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        let address_before = bytes.as_ptr() as usize;
+        bytes.reserve_exact(1000);
+        #[cfg(target_arch = "wasm32")]
+        claim_eq!(bytes.as_ptr() as usize, address_before, "The allocation should grow in place");
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = address_before;
+        claim!(bytes.capacity() >= 1004);
+        claim_eq!(bytes, [1, 2, 3, 4]);
+    }
+
+    /// Growing an earlier allocation moves it after the most recent one and
+    /// keeps the contents of both.
+    #[concordium_test]
+    fn test_realloc_earlier_allocation_copies() {
+        let mut first: Vec<u8> = Vec::with_capacity(3);
+        first.extend_from_slice(&[1, 2, 3]);
+        let second: Vec<u8> = vec![9; 16];
+        first.reserve_exact(100);
+        #[cfg(target_arch = "wasm32")]
+        claim!(
+            first.as_ptr() > second.as_ptr(),
+            "The allocation should be moved after the last one"
+        );
+        first.push(4);
+        claim_eq!(first, [1, 2, 3, 4]);
+        claim_eq!(second, [9; 16]);
+    }
+}
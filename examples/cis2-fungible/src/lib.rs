@@ -0,0 +1,1180 @@
+//! A fungible token example implementation of the Concordium Token Standard
+//! CIS2.
+//!
+//! # Description
+//! An instance of this smart contract contains a single fungible token type,
+//! identified by the contract address together with the unit token ID.
+//!
+//! The contract is initialized with no tokens and the account initializing
+//! the contract as the admin. Only the admin can mint new tokens, using the
+//! `mint` function, and burn tokens, using the `burn` function. The admin can
+//! only burn tokens it owns or is an operator of, and can hand over the admin
+//! role using `updateAdmin`. The total supply of tokens is tracked, and can
+//! be queried together with the admin using `view`.
+//!
+//! Note: The word 'address' refers to either an account address or a
+//! contract address.
+//!
+//! As follows from the CIS2 specification, the contract has a `transfer`
+//! function for transferring an amount of tokens from one address to another
+//! address. An address can enable and disable one or more addresses as
+//! operators with the `updateOperator` function. An operator of some address
+//! is allowed to transfer any tokens owned by this address.
+//!
+//! The contract has the queries `balanceOf`, `operatorOf` and `tokenMetadata`
+//! required by the CIS2 specification, and logs the standard CIS2 events.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// The id of the only token in this contract.
+const TOKEN_ID: ContractTokenId = TokenIdUnit();
+
+/// List of supported standards by this contract address.
+const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
+    [CIS0_STANDARD_IDENTIFIER, CIS2_STANDARD_IDENTIFIER];
+
+// Types
+
+/// Contract token ID type.
+/// Since this contract will only ever contain this one token type, we use the
+/// smallest possible token ID.
+type ContractTokenId = TokenIdUnit;
+
+/// Contract token amount type.
+type ContractTokenAmount = TokenAmountU64;
+
+/// The state tracked for each address.
+#[derive(Serial, DeserialWithState, Deletable, StateClone)]
+#[concordium(state_parameter = "S")]
+struct AddressState<S> {
+    /// The number of tokens owned by this address.
+    balance:   ContractTokenAmount,
+    /// The addresses which are currently enabled as operators for this
+    /// address.
+    operators: StateSet<Address, S>,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The admin address can mint and burn tokens, and transfer the admin
+    /// address to a new address.
+    admin:        Address,
+    /// The number of tokens in existence.
+    total_supply: ContractTokenAmount,
+    /// Map specifying the `AddressState` (balance and operators) for every
+    /// address.
+    token:        StateMap<Address, AddressState<S>, S>,
+    /// The metadata URL of the token.
+    metadata_url: MetadataUrl,
+}
+
+/// The parameter type for the contract function `mint`.
+#[derive(Serialize, SchemaType)]
+struct MintParams {
+    /// The owner of the newly minted tokens.
+    owner:  Address,
+    /// The amount of tokens to mint.
+    amount: ContractTokenAmount,
+}
+
+/// The parameter type for the contract function `burn`.
+#[derive(Serialize, SchemaType)]
+struct BurnParams {
+    /// The owner of the tokens to burn.
+    owner:  Address,
+    /// The amount of tokens to burn.
+    amount: ContractTokenAmount,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The URL following the specification RFC1738.
+    url:  String,
+    /// The hash of the document stored at the above URL.
+    hash: Option<Sha256>,
+}
+
+/// The return type for the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ReturnBasicState {
+    /// The admin address.
+    admin:        Address,
+    /// The number of tokens in existence.
+    total_supply: ContractTokenAmount,
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum CustomContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed to invoke a contract.
+    InvokeContractError,
+    /// Minting would make the total supply exceed the largest token amount.
+    SupplyOverflow,
+}
+
+type ContractError = Cis2Error<CustomContractError>;
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for CustomContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to CustomContractError.
+impl<T> From<CallContractError<T>> for CustomContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Mapping CustomContractError to ContractError
+impl From<CustomContractError> for ContractError {
+    fn from(c: CustomContractError) -> Self { Cis2Error::Custom(c) }
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Creates a new state with no one owning any tokens.
+    fn new(state_builder: &mut StateBuilder<S>, admin: Address, metadata_url: MetadataUrl) -> Self {
+        State {
+            admin,
+            total_supply: 0u64.into(),
+            token: state_builder.new_map(),
+            metadata_url,
+        }
+    }
+
+    /// Get the current balance of a given token id for a given address.
+    /// Results in an error if the token id does not exist in the state.
+    fn balance(
+        &self,
+        token_id: &ContractTokenId,
+        address: &Address,
+    ) -> ContractResult<ContractTokenAmount> {
+        ensure_eq!(token_id, &TOKEN_ID, ContractError::InvalidTokenId);
+        Ok(self.token.get(address).map(|s| s.balance).unwrap_or_else(|| 0u64.into()))
+    }
+
+    /// Check if an address is an operator of a specific owner address.
+    fn is_operator(&self, address: &Address, owner: &Address) -> bool {
+        self.token
+            .get(owner)
+            .map(|address_state| address_state.operators.contains(address))
+            .unwrap_or(false)
+    }
+
+    /// Update the state with a transfer.
+    /// Results in an error if the token id does not exist in the state or if
+    /// the from address has insufficient tokens to do the transfer.
+    fn transfer(
+        &mut self,
+        token_id: &ContractTokenId,
+        amount: ContractTokenAmount,
+        from: &Address,
+        to: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<()> {
+        ensure_eq!(token_id, &TOKEN_ID, ContractError::InvalidTokenId);
+        if amount == 0u64.into() {
+            return Ok(());
+        }
+        {
+            let mut from_state =
+                self.token.get_mut(from).ok_or(ContractError::InsufficientFunds)?;
+            ensure!(from_state.balance >= amount, ContractError::InsufficientFunds);
+            from_state.balance -= amount;
+        }
+        let mut to_state = self.token.entry(*to).or_insert_with(|| AddressState {
+            balance:   0u64.into(),
+            operators: state_builder.new_set(),
+        });
+        // Cannot overflow, since no balance exceeds the total supply.
+        to_state.balance += amount;
+
+        Ok(())
+    }
+
+    /// Update the state adding a new operator for a given address.
+    /// Succeeds even if the `operator` is already an operator for the
+    /// `address`.
+    fn add_operator(
+        &mut self,
+        owner: &Address,
+        operator: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) {
+        let mut owner_state = self.token.entry(*owner).or_insert_with(|| AddressState {
+            balance:   0u64.into(),
+            operators: state_builder.new_set(),
+        });
+        owner_state.operators.insert(*operator);
+    }
+
+    /// Update the state removing an operator for a given address.
+    /// Succeeds even if the `operator` is not an operator for the `address`.
+    fn remove_operator(&mut self, owner: &Address, operator: &Address) {
+        self.token.entry(*owner).and_modify(|address_state| {
+            address_state.operators.remove(operator);
+        });
+    }
+
+    /// Mint an amount of tokens with a given address as the owner.
+    /// Results in an error if the total supply would overflow.
+    fn mint(
+        &mut self,
+        amount: ContractTokenAmount,
+        owner: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<()> {
+        let total_supply =
+            self.total_supply.0.checked_add(amount.0).ok_or(CustomContractError::SupplyOverflow)?;
+        self.total_supply = total_supply.into();
+        let mut owner_state = self.token.entry(*owner).or_insert_with(|| AddressState {
+            balance:   0u64.into(),
+            operators: state_builder.new_set(),
+        });
+        owner_state.balance += amount;
+        Ok(())
+    }
+
+    /// Burn an amount of tokens owned by a given address.
+    /// Results in an error if the owner address has insufficient tokens to do
+    /// the burn.
+    fn burn(&mut self, amount: ContractTokenAmount, owner: &Address) -> ContractResult<()> {
+        if amount == 0u64.into() {
+            return Ok(());
+        }
+
+        let mut owner_state = self.token.get_mut(owner).ok_or(ContractError::InsufficientFunds)?;
+        ensure!(owner_state.balance >= amount, ContractError::InsufficientFunds);
+        owner_state.balance -= amount;
+        self.total_supply -= amount;
+        Ok(())
+    }
+}
+
+// Contract functions
+
+/// Initialize contract instance with no tokens and the account initializing
+/// the contract as the admin.
+/// Logs a `Mint` event with no amount and a `TokenMetadata` event for the
+/// token.
+#[init(
+    contract = "cis2_fungible",
+    enable_logger,
+    parameter = "InitParams",
+    event = "Cis2Event<ContractTokenId, ContractTokenAmount>"
+)]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+    logger: &mut impl HasLogger,
+) -> InitResult<State<S>> {
+    // Parse the parameter.
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    let admin = Address::Account(ctx.init_origin());
+    let metadata_url = MetadataUrl {
+        url:  params.url,
+        hash: params.hash,
+    };
+
+    // Construct the initial contract state.
+    let state = State::new(state_builder, admin, metadata_url.clone());
+
+    // Log event for the token with no amount, to announce its existence.
+    logger.log(&Cis2Event::Mint(MintEvent {
+        token_id: TOKEN_ID,
+        amount:   ContractTokenAmount::from(0u64),
+        owner:    admin,
+    }))?;
+
+    // Log event for where to find metadata for the token.
+    logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+        token_id: TOKEN_ID,
+        metadata_url,
+    }))?;
+
+    Ok(state)
+}
+
+/// Mint new tokens with a given address as the owner of these tokens.
+/// Logs a `Mint` event.
+///
+/// It rejects if:
+/// - The sender is not the admin.
+/// - It fails to parse the parameter.
+/// - The total supply would overflow.
+/// - Fails to log event.
+#[receive(
+    contract = "cis2_fungible",
+    name = "mint",
+    parameter = "MintParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_mint<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Check that only the admin is authorized to mint.
+    ensure_eq!(ctx.sender(), host.state().admin, ContractError::Unauthorized);
+
+    // Parse the parameter.
+    let params: MintParams = ctx.parameter_cursor().get()?;
+
+    let (state, builder) = host.state_and_builder();
+    state.mint(params.amount, &params.owner, builder)?;
+
+    logger.log(&Cis2Event::Mint(MintEvent {
+        token_id: TOKEN_ID,
+        amount:   params.amount,
+        owner:    params.owner,
+    }))?;
+    Ok(())
+}
+
+/// Burn tokens owned by a given address.
+/// Logs a `Burn` event.
+///
+/// It rejects if:
+/// - The sender is not the admin.
+/// - The admin is not the owner of the tokens, or an operator of the owner.
+/// - It fails to parse the parameter.
+/// - The owner has insufficient tokens.
+/// - Fails to log event.
+#[receive(
+    contract = "cis2_fungible",
+    name = "burn",
+    parameter = "BurnParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_burn<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+    let state = host.state_mut();
+    // Check that only the admin is authorized to burn.
+    ensure_eq!(sender, state.admin, ContractError::Unauthorized);
+
+    // Parse the parameter.
+    let params: BurnParams = ctx.parameter_cursor().get()?;
+
+    // Authenticate the sender for the owner of the tokens.
+    ensure!(
+        sender == params.owner || state.is_operator(&sender, &params.owner),
+        ContractError::Unauthorized
+    );
+    state.burn(params.amount, &params.owner)?;
+
+    logger.log(&Cis2Event::Burn(BurnEvent {
+        token_id: TOKEN_ID,
+        amount:   params.amount,
+        owner:    params.owner,
+    }))?;
+    Ok(())
+}
+
+/// Transfer the admin address to a new admin address.
+///
+/// It rejects if:
+/// - Sender is not the current admin of the contract instance.
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "cis2_fungible",
+    name = "updateAdmin",
+    parameter = "Address",
+    error = "ContractError",
+    mutable
+)]
+fn contract_update_admin<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Check that only the current admin is authorized to update the admin address.
+    ensure_eq!(ctx.sender(), host.state().admin, ContractError::Unauthorized);
+
+    // Parse the parameter.
+    let new_admin = ctx.parameter_cursor().get()?;
+
+    // Update the admin variable.
+    host.state_mut().admin = new_admin;
+    Ok(())
+}
+
+/// Function to view the admin and the total supply of tokens.
+#[receive(
+    contract = "cis2_fungible",
+    name = "view",
+    return_value = "ReturnBasicState",
+    error = "ContractError"
+)]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ReturnBasicState> {
+    let state = ReturnBasicState {
+        admin:        host.state().admin,
+        total_supply: host.state().total_supply,
+    };
+    Ok(state)
+}
+
+// Contract functions required by the CIS-2 standard
+
+type TransferParameter = TransferParams<ContractTokenId, ContractTokenAmount>;
+
+/// Execute a list of token transfers, in the order of the list.
+///
+/// Logs a `Transfer` event and invokes a receive hook function for every
+/// transfer in the list.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the transfers fail to be executed, which could be if:
+///     - The `token_id` does not exist.
+///     - The sender is not the owner of the token, or an operator for this
+///       specific `token_id` and `from` address.
+///     - The token is not owned by the `from`.
+/// - Fails to log event.
+/// - Any of the receive hook function calls rejects.
+#[receive(
+    contract = "cis2_fungible",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_transfer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let TransferParams(transfers): TransferParameter = ctx.parameter_cursor().get()?;
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+
+    for Transfer {
+        token_id,
+        amount,
+        from,
+        to,
+        data,
+    } in transfers
+    {
+        let (state, builder) = host.state_and_builder();
+        // Authenticate the sender for this transfer
+        ensure!(from == sender || state.is_operator(&sender, &from), ContractError::Unauthorized);
+        let to_address = to.address();
+        // Update the contract state
+        state.transfer(&token_id, amount, &from, &to_address, builder)?;
+
+        // Log transfer event
+        logger.log(&Cis2Event::Transfer(TransferEvent {
+            token_id,
+            amount,
+            from,
+            to: to_address,
+        }))?;
+
+        // If the receiver is a contract: invoke the receive hook function.
+        if let Receiver::Contract(address, function) = to {
+            let parameter = OnReceivingCis2Params {
+                token_id,
+                amount,
+                from,
+                data,
+            };
+            host.invoke_contract(
+                &address,
+                &parameter,
+                function.as_entrypoint_name(),
+                Amount::zero(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Enable or disable addresses as operators of the sender address.
+/// Logs an `UpdateOperator` event.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Fails to log event.
+#[receive(
+    contract = "cis2_fungible",
+    name = "updateOperator",
+    parameter = "UpdateOperatorParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_operator<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let UpdateOperatorParams(params) = ctx.parameter_cursor().get()?;
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+
+    let (state, state_builder) = host.state_and_builder();
+    for param in params {
+        // Update the operator in the state.
+        match param.update {
+            OperatorUpdate::Add => state.add_operator(&sender, &param.operator, state_builder),
+            OperatorUpdate::Remove => state.remove_operator(&sender, &param.operator),
+        }
+
+        // Log the appropriate event
+        logger.log(&Cis2Event::<ContractTokenId, ContractTokenAmount>::UpdateOperator(
+            UpdateOperatorEvent {
+                owner:    sender,
+                operator: param.operator,
+                update:   param.update,
+            },
+        ))?;
+    }
+    Ok(())
+}
+
+/// Parameter type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenIDs used by this contract.
+type ContractBalanceOfQueryParams = BalanceOfQueryParams<ContractTokenId>;
+
+/// Response type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenAmounts used by this contract.
+type ContractBalanceOfQueryResponse = BalanceOfQueryResponse<ContractTokenAmount>;
+
+/// Get the balance of given token IDs and addresses.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "cis2_fungible",
+    name = "balanceOf",
+    parameter = "ContractBalanceOfQueryParams",
+    return_value = "ContractBalanceOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ContractBalanceOfQueryResponse> {
+    // Parse the parameter.
+    let params: ContractBalanceOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for balance.
+        let amount = host.state().balance(&query.token_id, &query.address)?;
+        response.push(amount);
+    }
+    let result = ContractBalanceOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Takes a list of queries. Each query contains an owner address and some
+/// address that will be checked if it is an operator to the owner address.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "cis2_fungible",
+    name = "operatorOf",
+    parameter = "OperatorOfQueryParams",
+    return_value = "OperatorOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_operator_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<OperatorOfQueryResponse> {
+    // Parse the parameter.
+    let params: OperatorOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state if the `address` being an `operator` of `owner`.
+        let is_operator = host.state().is_operator(&query.address, &query.owner);
+        response.push(is_operator);
+    }
+    let result = OperatorOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `tokenMetadata` specialized to the
+/// subset of TokenIDs used by this contract.
+type ContractTokenMetadataQueryParams = TokenMetadataQueryParams<ContractTokenId>;
+
+/// Get the token metadata URLs and checksums given a list of token IDs.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "cis2_fungible",
+    name = "tokenMetadata",
+    parameter = "ContractTokenMetadataQueryParams",
+    return_value = "TokenMetadataQueryResponse",
+    error = "ContractError"
+)]
+fn contract_token_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<TokenMetadataQueryResponse> {
+    // Parse the parameter.
+    let params: ContractTokenMetadataQueryParams = ctx.parameter_cursor().get()?;
+
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for token_id in params.queries {
+        // Check the token exists.
+        ensure_eq!(token_id, TOKEN_ID, ContractError::InvalidTokenId);
+
+        response.push(host.state().metadata_url.clone());
+    }
+    let result = TokenMetadataQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Get the supported standards given a list of standard identifiers.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "cis2_fungible",
+    name = "supports",
+    parameter = "SupportsQueryParams",
+    return_value = "SupportsQueryResponse",
+    error = "ContractError"
+)]
+fn contract_supports<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<SupportsQueryResponse> {
+    // Parse the parameter.
+    let params: SupportsQueryParams = ctx.parameter_cursor().get()?;
+
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for std_id in params.queries {
+        if SUPPORTS_STANDARDS.contains(&std_id.as_standard_identifier()) {
+            response.push(SupportResult::Support);
+        } else {
+            response.push(SupportResult::NoSupport);
+        }
+    }
+    let result = SupportsQueryResponse::from(response);
+    Ok(result)
+}
+
+// Tests
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ACCOUNT_0: AccountAddress = AccountAddress([0u8; 32]);
+    const ADDRESS_0: Address = Address::Account(ACCOUNT_0);
+    const ACCOUNT_1: AccountAddress = AccountAddress([1u8; 32]);
+    const ADDRESS_1: Address = Address::Account(ACCOUNT_1);
+    const ADMIN_ACCOUNT: AccountAddress = AccountAddress([2u8; 32]);
+    const ADMIN_ADDRESS: Address = Address::Account(ADMIN_ACCOUNT);
+
+    // The metadata url for the token.
+    const TOKEN_METADATA_URL: &str = "https://some.example/token/fungible";
+
+    fn metadata_url() -> MetadataUrl {
+        MetadataUrl {
+            url:  TOKEN_METADATA_URL.to_string(),
+            hash: None,
+        }
+    }
+
+    /// Test helper function which creates a contract state where ADDRESS_0 owns
+    /// 400 tokens.
+    fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
+        let mut state = State::new(state_builder, ADMIN_ADDRESS, metadata_url());
+        state.mint(400u64.into(), &ADDRESS_0, state_builder).expect_report("Failed to setup state");
+        state
+    }
+
+    /// Test initialization succeeds with no tokens, the invoker as the admin
+    /// and the token announced in the logs.
+    #[concordium_test]
+    fn test_init() {
+        // Setup the context
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(ADMIN_ACCOUNT);
+        let parameter_bytes = to_bytes(&InitParams {
+            url:  TOKEN_METADATA_URL.to_string(),
+            hash: None,
+        });
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut builder = TestStateBuilder::new();
+
+        // Call the contract function.
+        let result = contract_init(&ctx, &mut builder, &mut logger);
+
+        // Check the result
+        let state = result.expect_report("Contract initialization failed");
+
+        // Check the state
+        claim_eq!(state.admin, ADMIN_ADDRESS, "The invoker should be the admin");
+        claim_eq!(state.total_supply, 0u64.into(), "No tokens should exist");
+
+        // Check the logs
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&Cis2Event::Mint(MintEvent {
+                    token_id: TOKEN_ID,
+                    amount:   ContractTokenAmount::from(0u64),
+                    owner:    ADMIN_ADDRESS,
+                })),
+                to_bytes(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+                    token_id:     TOKEN_ID,
+                    metadata_url: metadata_url(),
+                })),
+            ],
+            "Incorrect events emitted"
+        );
+    }
+
+    /// Test minting succeeds, increases the balance and the total supply, and
+    /// logs a `Mint` event.
+    #[concordium_test]
+    fn test_mint() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADMIN_ADDRESS);
+        let parameter_bytes = to_bytes(&MintParams {
+            owner:  ADDRESS_1,
+            amount: 100u64.into(),
+        });
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+
+        // Check the result
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state
+        let balance =
+            host.state().balance(&TOKEN_ID, &ADDRESS_1).expect_report("Token is expected to exist");
+        claim_eq!(balance, 100u64.into(), "Minted tokens should be owned by the given address");
+        claim_eq!(host.state().total_supply, 500u64.into(), "Total supply should increase");
+
+        // Check the logs
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&Cis2Event::Mint(MintEvent {
+                token_id: TOKEN_ID,
+                amount:   ContractTokenAmount::from(100u64),
+                owner:    ADDRESS_1,
+            }))],
+            "Incorrect event emitted"
+        );
+    }
+
+    /// Test minting fails, when the sender is not the admin.
+    #[concordium_test]
+    fn test_mint_not_authorized() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+        let parameter_bytes = to_bytes(&MintParams {
+            owner:  ADDRESS_0,
+            amount: 100u64.into(),
+        });
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+
+        // Check the result
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(err, ContractError::Unauthorized, "Error is expected to be Unauthorized");
+    }
+
+    /// Test minting fails, when the total supply would overflow.
+    #[concordium_test]
+    fn test_mint_overflow() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADMIN_ADDRESS);
+        let parameter_bytes = to_bytes(&MintParams {
+            owner:  ADDRESS_1,
+            amount: u64::MAX.into(),
+        });
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+
+        // Check the result
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(
+            err,
+            ContractError::Custom(CustomContractError::SupplyOverflow),
+            "Error is expected to be SupplyOverflow"
+        );
+    }
+
+    /// Test burning succeeds, when the admin is an operator of the owner, and
+    /// decreases the balance and the total supply.
+    #[concordium_test]
+    fn test_burn() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADMIN_ADDRESS);
+        let parameter_bytes = to_bytes(&BurnParams {
+            owner:  ADDRESS_0,
+            amount: 150u64.into(),
+        });
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.add_operator(&ADDRESS_0, &ADMIN_ADDRESS, &mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_burn(&ctx, &mut host, &mut logger);
+
+        // Check the result
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state
+        let balance =
+            host.state().balance(&TOKEN_ID, &ADDRESS_0).expect_report("Token is expected to exist");
+        claim_eq!(balance, 250u64.into(), "Balance should decrease by the burned amount");
+        claim_eq!(host.state().total_supply, 250u64.into(), "Total supply should decrease");
+
+        // Check the logs
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&Cis2Event::Burn(BurnEvent {
+                token_id: TOKEN_ID,
+                amount:   ContractTokenAmount::from(150u64),
+                owner:    ADDRESS_0,
+            }))],
+            "Incorrect event emitted"
+        );
+    }
+
+    /// Test burning fails, when the admin is neither the owner nor an operator
+    /// of the owner.
+    #[concordium_test]
+    fn test_burn_not_operator() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADMIN_ADDRESS);
+        let parameter_bytes = to_bytes(&BurnParams {
+            owner:  ADDRESS_0,
+            amount: 150u64.into(),
+        });
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_burn(&ctx, &mut host, &mut logger);
+
+        // Check the result
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(err, ContractError::Unauthorized, "Error is expected to be Unauthorized");
+        claim_eq!(host.state().total_supply, 400u64.into(), "Total supply should not change");
+    }
+
+    /// Test transfer succeeds, when `from` is the sender.
+    #[concordium_test]
+    fn test_transfer_account() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let transfer = Transfer {
+            token_id: TOKEN_ID,
+            amount:   ContractTokenAmount::from(100),
+            from:     ADDRESS_0,
+            to:       Receiver::from_account(ACCOUNT_1),
+            data:     AdditionalData::empty(),
+        };
+        let parameter = TransferParams::from(vec![transfer]);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state.
+        let balance0 =
+            host.state().balance(&TOKEN_ID, &ADDRESS_0).expect_report("Token is expected to exist");
+        let balance1 =
+            host.state().balance(&TOKEN_ID, &ADDRESS_1).expect_report("Token is expected to exist");
+        claim_eq!(
+            balance0,
+            300.into(),
+            "Token owner balance should be decreased by the transferred amount."
+        );
+        claim_eq!(
+            balance1,
+            100.into(),
+            "Token receiver balance should be increased by the transferred amount"
+        );
+        claim_eq!(host.state().total_supply, 400u64.into(), "Total supply should not change");
+
+        // Check the logs.
+        claim_eq!(logger.logs.len(), 1, "Only one event should be logged");
+        claim_eq!(
+            logger.logs[0],
+            to_bytes(&Cis2Event::Transfer(TransferEvent {
+                from:     ADDRESS_0,
+                to:       ADDRESS_1,
+                token_id: TOKEN_ID,
+                amount:   ContractTokenAmount::from(100),
+            })),
+            "Incorrect event emitted"
+        )
+    }
+
+    /// Test transfer fails, when the owner has insufficient tokens.
+    #[concordium_test]
+    fn test_transfer_insufficient_funds() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let transfer = Transfer {
+            token_id: TOKEN_ID,
+            amount:   ContractTokenAmount::from(401),
+            from:     ADDRESS_0,
+            to:       Receiver::from_account(ACCOUNT_1),
+            data:     AdditionalData::empty(),
+        };
+        let parameter = TransferParams::from(vec![transfer]);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(
+            err,
+            ContractError::InsufficientFunds,
+            "Error is expected to be InsufficientFunds"
+        )
+    }
+
+    /// Test transfer succeeds when sender is not the owner, but is an operator
+    /// of the owner, and that the balances can be queried with `balanceOf`.
+    #[concordium_test]
+    fn test_operator_transfer() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_1);
+
+        // and parameter.
+        let transfer = Transfer {
+            from:     ADDRESS_0,
+            to:       Receiver::from_account(ACCOUNT_1),
+            token_id: TOKEN_ID,
+            amount:   ContractTokenAmount::from(100),
+            data:     AdditionalData::empty(),
+        };
+        let parameter = TransferParams::from(vec![transfer]);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.add_operator(&ADDRESS_0, &ADDRESS_1, &mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the balances using `balanceOf`.
+        let parameter = ContractBalanceOfQueryParams {
+            queries: vec![
+                BalanceOfQuery {
+                    token_id: TOKEN_ID,
+                    address:  ADDRESS_0,
+                },
+                BalanceOfQuery {
+                    token_id: TOKEN_ID,
+                    address:  ADDRESS_1,
+                },
+            ],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result: ContractResult<ContractBalanceOfQueryResponse> =
+            contract_balance_of(&ctx, &host);
+        claim_eq!(
+            result.expect_report("Failed getting result value").0,
+            [300.into(), 100.into()],
+            "Balances should reflect the transfer"
+        );
+    }
+
+    /// Test adding an operator succeeds and the appropriate event is logged.
+    #[concordium_test]
+    fn test_add_operator() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let update = UpdateOperator {
+            operator: ADDRESS_1,
+            update:   OperatorUpdate::Add,
+        };
+        let parameter = UpdateOperatorParams(vec![update]);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_update_operator(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check `ADDRESS_1` is an operator in the query response of `operatorOf`.
+        let parameter = OperatorOfQueryParams {
+            queries: vec![OperatorOfQuery {
+                address: ADDRESS_1,
+                owner:   ADDRESS_0,
+            }],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result: ContractResult<OperatorOfQueryResponse> = contract_operator_of(&ctx, &host);
+        claim_eq!(
+            result.expect_report("Failed getting result value").0,
+            [true],
+            "Account should be an operator in the query response"
+        );
+
+        // Check the logs.
+        claim_eq!(logger.logs.len(), 1, "One event should be logged");
+        claim_eq!(
+            logger.logs[0],
+            to_bytes(&Cis2Event::<ContractTokenId, ContractTokenAmount>::UpdateOperator(
+                UpdateOperatorEvent {
+                    owner:    ADDRESS_0,
+                    operator: ADDRESS_1,
+                    update:   OperatorUpdate::Add,
+                }
+            )),
+            "Incorrect event emitted"
+        )
+    }
+
+    /// Test updating the admin succeeds for the admin and hands over minting.
+    #[concordium_test]
+    fn test_update_admin() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADMIN_ADDRESS);
+        let parameter_bytes = to_bytes(&ADDRESS_1);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_update_admin(&ctx, &mut host);
+
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state using `view`.
+        let view = contract_view(&ctx, &host).expect_report("Failed getting result value");
+        claim_eq!(view, ReturnBasicState {
+            admin:        ADDRESS_1,
+            total_supply: 400u64.into(),
+        });
+
+        // The old admin can no longer update the admin.
+        let result: ContractResult<()> = contract_update_admin(&ctx, &mut host);
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(err, ContractError::Unauthorized, "Error is expected to be Unauthorized");
+    }
+}
@@ -8,16 +8,25 @@
 //! In this example the contract is initialized with no tokens, and tokens can
 //! be minted through a `mint` contract function, which will only succeed for
 //! the contract owner. No functionality to burn token is defined in this
-//! example.
+//! example. The total supply of each token type is tracked and can be queried
+//! for a batch of token types using `totalSupply`.
+//!
+//! The balances are kept in a single map indexed by the token ID together with
+//! the owner address, instead of in a map of balances for every address. This
+//! way a balance is read or updated with a single lookup, and only the balances
+//! actually held take up space in the state. The operators are kept in a
+//! similar flat set of owner and operator pairs.
 //!
 //! Note: The word 'address' refers to either an account address or a
 //! contract address.
 //!
 //! As follows from the CIS2 specification, the contract has a `transfer`
 //! function for transferring an amount of a specific token type from one
-//! address to another address. An address can enable and disable one or more
-//! addresses as operators. An operator of some address is allowed to transfer
-//! any tokens owned by this address.
+//! address to another address, and a `balanceOf` function for querying
+//! balances. Both take a batch, so any number of transfers or queries of
+//! different token types are done in a single call. An address can enable and
+//! disable one or more addresses as operators. An operator of some address is
+//! allowed to transfer any tokens owned by this address.
 //!
 //! This contract also contains an example of a function to be called when
 //! receiving tokens. In which case the contract will forward the tokens to
@@ -68,23 +77,12 @@ struct SetImplementorsParams {
     implementors: Vec<ContractAddress>,
 }
 
-/// The state for each address.
-#[derive(Serial, DeserialWithState, Deletable, StateClone)]
-#[concordium(state_parameter = "S")]
-struct AddressState<S> {
-    /// The amount of tokens owned by this address.
-    balances:  StateMap<ContractTokenId, ContractTokenAmount, S>,
-    /// The address which are currently enabled as operators for this address.
-    operators: StateSet<Address, S>,
-}
-
-impl<S: HasStateApi> AddressState<S> {
-    fn empty(state_builder: &mut StateBuilder<S>) -> Self {
-        AddressState {
-            balances:  state_builder.new_map(),
-            operators: state_builder.new_set(),
-        }
-    }
+/// The parameter type for the contract function `totalSupply`.
+#[derive(Serialize, SchemaType)]
+struct TotalSupplyQueryParams {
+    /// The token IDs to query the total supply of.
+    #[concordium(size_length = 2)]
+    queries: Vec<ContractTokenId>,
 }
 
 /// The contract state,
@@ -94,10 +92,14 @@ impl<S: HasStateApi> AddressState<S> {
 #[derive(Serial, DeserialWithState, StateClone)]
 #[concordium(state_parameter = "S")]
 struct State<S> {
-    /// The state of addresses.
-    state:        StateMap<Address, AddressState<S>, S>,
-    /// All of the token IDs
-    tokens:       StateSet<ContractTokenId, S>,
+    /// The amount of tokens of each token ID owned by each address. Addresses
+    /// not owning any tokens of a token ID have no entry.
+    balances:     StateMap<(ContractTokenId, Address), ContractTokenAmount, S>,
+    /// The pairs of owner and operator addresses, for which the operator is
+    /// currently enabled as an operator of the owner.
+    operators:    StateSet<(Address, Address), S>,
+    /// All of the token IDs with the total supply of each.
+    tokens:       StateMap<ContractTokenId, ContractTokenAmount, S>,
     /// Map with contract addresses providing implementations of additional
     /// standards.
     implementors: StateMap<StandardIdentifierOwned, Vec<ContractAddress>, S>,
@@ -119,6 +121,9 @@ enum CustomContractError {
     ContractOnly,
     /// Failed to invoke a contract.
     InvokeContractError,
+    /// Minting would make the total supply of a token exceed the largest token
+    /// amount.
+    SupplyOverflow,
 }
 
 type ContractError = Cis2Error<CustomContractError>;
@@ -157,30 +162,44 @@ impl<S: HasStateApi> State<S> {
     /// Construct a state with no tokens
     fn empty(state_builder: &mut StateBuilder<S>) -> Self {
         State {
-            state:        state_builder.new_map(),
-            tokens:       state_builder.new_set(),
+            balances:     state_builder.new_map(),
+            operators:    state_builder.new_set(),
+            tokens:       state_builder.new_map(),
             implementors: state_builder.new_map(),
         }
     }
 
     /// Mints an amount of tokens with a given address as the owner.
+    /// Results in an error if the total supply of the token would overflow.
     fn mint(
         &mut self,
         token_id: &ContractTokenId,
         amount: ContractTokenAmount,
         owner: &Address,
-        state_builder: &mut StateBuilder<S>,
-    ) {
-        self.tokens.insert(*token_id);
-        let mut owner_state =
-            self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
-        let mut owner_balance = owner_state.balances.entry(*token_id).or_insert(0.into());
+    ) -> ContractResult<()> {
+        {
+            let mut supply = self.tokens.entry(*token_id).or_insert(0.into());
+            let new_supply =
+                supply.0.checked_add(amount.0).ok_or(CustomContractError::SupplyOverflow)?;
+            *supply = new_supply.into();
+        }
+        // Cannot overflow, since no balance exceeds the total supply.
+        let mut owner_balance = self.balances.entry((*token_id, *owner)).or_insert(0.into());
         *owner_balance += amount;
+        Ok(())
     }
 
     /// Check that the token ID currently exists in this contract.
     #[inline(always)]
-    fn contains_token(&self, token_id: &ContractTokenId) -> bool { self.tokens.contains(token_id) }
+    fn contains_token(&self, token_id: &ContractTokenId) -> bool {
+        self.tokens.get(token_id).is_some()
+    }
+
+    /// Get the total supply of a given token id.
+    /// Results in an error if the token id does not exist in the state.
+    fn total_supply(&self, token_id: &ContractTokenId) -> ContractResult<ContractTokenAmount> {
+        self.tokens.get(token_id).map(|supply| *supply).ok_or(ContractError::InvalidTokenId)
+    }
 
     /// Get the current balance of a given token id for a given address.
     /// Results in an error if the token id does not exist in the state.
@@ -190,18 +209,13 @@ impl<S: HasStateApi> State<S> {
         address: &Address,
     ) -> ContractResult<ContractTokenAmount> {
         ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
-        let balance = self.state.get(address).map_or(0.into(), |address_state| {
-            address_state.balances.get(token_id).map_or(0.into(), |x| *x)
-        });
+        let balance = self.balances.get(&(*token_id, *address)).map_or(0.into(), |x| *x);
         Ok(balance)
     }
 
     /// Check if an address is an operator of a given owner address.
     fn is_operator(&self, address: &Address, owner: &Address) -> bool {
-        self.state
-            .get(owner)
-            .map(|address_state| address_state.operators.contains(address))
-            .unwrap_or(false)
+        self.operators.contains(&(*owner, *address))
     }
 
     /// Update the state with a transfer.
@@ -213,7 +227,6 @@ impl<S: HasStateApi> State<S> {
         amount: ContractTokenAmount,
         from: &Address,
         to: &Address,
-        state_builder: &mut StateBuilder<S>,
     ) -> ContractResult<()> {
         ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
         // A zero transfer does not modify the state.
@@ -221,24 +234,20 @@ impl<S: HasStateApi> State<S> {
             return Ok(());
         }
 
-        // Get the `from` state and balance, if not present it will fail since the
+        // Get the `from` balance, if not present it will fail since the
         // balance is interpreted as 0 and the transfer amount must be more than
-        // 0 as this point.;
+        // 0 as this point.
         {
-            let mut from_address_state =
-                self.state.entry(*from).occupied_or(ContractError::InsufficientFunds)?;
-            let mut from_balance = from_address_state
+            let mut from_balance = self
                 .balances
-                .entry(*token_id)
+                .entry((*token_id, *from))
                 .occupied_or(ContractError::InsufficientFunds)?;
             ensure!(*from_balance >= amount, ContractError::InsufficientFunds);
             *from_balance -= amount;
         }
 
-        let mut to_address_state =
-            self.state.entry(*to).or_insert_with(|| AddressState::empty(state_builder));
-        let mut to_address_balance = to_address_state.balances.entry(*token_id).or_insert(0.into());
-        *to_address_balance += amount;
+        let mut to_balance = self.balances.entry((*token_id, *to)).or_insert(0.into());
+        *to_balance += amount;
 
         Ok(())
     }
@@ -246,23 +255,14 @@ impl<S: HasStateApi> State<S> {
     /// Update the state adding a new operator for a given address.
     /// Succeeds even if the `operator` is already an operator for the
     /// `address`.
-    fn add_operator(
-        &mut self,
-        owner: &Address,
-        operator: &Address,
-        state_builder: &mut StateBuilder<S>,
-    ) {
-        let mut owner_state =
-            self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
-        owner_state.operators.insert(*operator);
+    fn add_operator(&mut self, owner: &Address, operator: &Address) {
+        self.operators.insert((*owner, *operator));
     }
 
     /// Update the state removing an operator for a given address.
     /// Succeeds even if the `operator` is not an operator for the `address`.
     fn remove_operator(&mut self, owner: &Address, operator: &Address) {
-        self.state.entry(*owner).and_modify(|address_state| {
-            address_state.operators.remove(operator);
-        });
+        self.operators.remove(&(*owner, *operator));
     }
 
     /// Check if state contains any implementors for a given standard.
@@ -304,16 +304,11 @@ fn contract_init<S: HasStateApi>(
     Ok(State::empty(state_builder))
 }
 
-#[derive(Serialize, SchemaType)]
-struct ViewAddressState {
-    balances:  Vec<(ContractTokenId, ContractTokenAmount)>,
-    operators: Vec<Address>,
-}
-
 #[derive(Serialize, SchemaType)]
 struct ViewState {
-    state:  Vec<(Address, ViewAddressState)>,
-    tokens: Vec<ContractTokenId>,
+    balances:  Vec<((ContractTokenId, Address), ContractTokenAmount)>,
+    operators: Vec<(Address, Address)>,
+    tokens:    Vec<(ContractTokenId, ContractTokenAmount)>,
 }
 
 /// View function for testing. This reports on the entire state of the contract
@@ -326,29 +321,13 @@ fn contract_view<S: HasStateApi>(
 ) -> ReceiveResult<ViewState> {
     let state = host.state();
 
-    let mut inner_state = Vec::new();
-    for (k, a_state) in state.state.iter() {
-        let mut balances = Vec::new();
-        let mut operators = Vec::new();
-        for (token_id, amount) in a_state.balances.iter() {
-            balances.push((*token_id, *amount));
-        }
-        for o in a_state.operators.iter() {
-            operators.push(*o);
-        }
-
-        inner_state.push((*k, ViewAddressState {
-            balances,
-            operators,
-        }));
-    }
-    let mut tokens = Vec::new();
-    for v in state.tokens.iter() {
-        tokens.push(*v);
-    }
+    let balances = state.balances.iter().map(|(key, amount)| (*key, *amount)).collect();
+    let operators = state.operators.iter().map(|pair| *pair).collect();
+    let tokens = state.tokens.iter().map(|(token_id, supply)| (*token_id, *supply)).collect();
 
     Ok(ViewState {
-        state: inner_state,
+        balances,
+        operators,
         tokens,
     })
 }
@@ -363,6 +342,7 @@ fn contract_view<S: HasStateApi>(
 /// - The sender is not the contract instance owner.
 /// - Fails to parse parameter.
 /// - Any of the tokens fails to be minted, which could be if:
+///     - The total supply of the token would overflow.
 ///     - Fails to log Mint event.
 ///     - Fails to log TokenMetadata event.
 ///
@@ -391,10 +371,10 @@ fn contract_mint<S: HasStateApi>(
     // Parse the parameter.
     let params: MintParams = ctx.parameter_cursor().get()?;
 
-    let (state, builder) = host.state_and_builder();
+    let state = host.state_mut();
     for (token_id, token_amount) in params.tokens {
         // Mint the token in the state.
-        state.mint(&token_id, token_amount, &params.owner, builder);
+        state.mint(&token_id, token_amount, &params.owner)?;
 
         // Event for minted token.
         logger.log(&Cis2Event::Mint(MintEvent {
@@ -457,12 +437,12 @@ fn contract_transfer<S: HasStateApi>(
         data,
     } in transfers
     {
-        let (state, builder) = host.state_and_builder();
+        let state = host.state_mut();
         // Authenticate the sender for this transfer
         ensure!(from == sender || state.is_operator(&sender, &from), ContractError::Unauthorized);
         let to_address = to.address();
         // Update the contract state
-        state.transfer(&token_id, amount, &from, &to_address, builder)?;
+        state.transfer(&token_id, amount, &from, &to_address)?;
 
         // Log transfer event
         logger.log(&Cis2Event::Transfer(TransferEvent {
@@ -515,11 +495,11 @@ fn contract_update_operator<S: HasStateApi>(
     // Get the sender who invoked this contract function.
     let sender = ctx.sender();
 
-    let (state, builder) = host.state_and_builder();
+    let state = host.state_mut();
     for param in params {
         // Update the operator in the state.
         match param.update {
-            OperatorUpdate::Add => state.add_operator(&sender, &param.operator, builder),
+            OperatorUpdate::Add => state.add_operator(&sender, &param.operator),
             OperatorUpdate::Remove => state.remove_operator(&sender, &param.operator),
         }
 
@@ -572,6 +552,33 @@ fn contract_balance_of<S: HasStateApi>(
     Ok(result)
 }
 
+/// Get the total supply of given token IDs.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "cis2_multi",
+    name = "totalSupply",
+    parameter = "TotalSupplyQueryParams",
+    return_value = "Vec<ContractTokenAmount>",
+    error = "ContractError"
+)]
+fn contract_total_supply<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Vec<ContractTokenAmount>> {
+    // Parse the parameter.
+    let params: TotalSupplyQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for token_id in params.queries {
+        // Query the state for the total supply.
+        response.push(host.state().total_supply(&token_id)?);
+    }
+    Ok(response)
+}
+
 /// Takes a list of queries. Each query is an owner address and some address to
 /// check as an operator of the owner address.
 ///
@@ -772,8 +779,8 @@ mod tests {
     /// id `TOKEN_0` and id `TOKEN_1` owned by `ADDRESS_0`
     fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
         let mut state = State::empty(state_builder);
-        state.mint(&TOKEN_0, 400.into(), &ADDRESS_0, state_builder);
-        state.mint(&TOKEN_1, 1.into(), &ADDRESS_0, state_builder);
+        state.mint(&TOKEN_0, 400.into(), &ADDRESS_0).expect_report("Failed to mint TOKEN_0");
+        state.mint(&TOKEN_1, 1.into(), &ADDRESS_0).expect_report("Failed to mint TOKEN_1");
         state
     }
 
@@ -993,7 +1000,7 @@ mod tests {
         let mut logger = TestLogger::init();
         let mut state_builder = TestStateBuilder::new();
         let mut state = initial_state(&mut state_builder);
-        state.add_operator(&ADDRESS_0, &ADDRESS_1, &mut state_builder);
+        state.add_operator(&ADDRESS_0, &ADDRESS_1);
         let mut host = TestHost::new(state, state_builder);
 
         // Call the contract function.
@@ -1101,4 +1108,179 @@ mod tests {
             "Incorrect event emitted"
         )
     }
+    /// Test a batch of transfers of different token types succeeds, and the
+    /// balances can be queried in a single batch.
+    #[concordium_test]
+    fn test_batch_transfer() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let transfers = vec![
+            Transfer {
+                token_id: TOKEN_0,
+                amount:   ContractTokenAmount::from(100),
+                from:     ADDRESS_0,
+                to:       Receiver::from_account(ACCOUNT_1),
+                data:     AdditionalData::empty(),
+            },
+            Transfer {
+                token_id: TOKEN_1,
+                amount:   ContractTokenAmount::from(1),
+                from:     ADDRESS_0,
+                to:       Receiver::from_account(ACCOUNT_1),
+                data:     AdditionalData::empty(),
+            },
+        ];
+        let parameter = TransferParams::from(transfers);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(logger.logs.len(), 2, "A transfer event should be logged for each transfer");
+
+        // Check the balances with a single batch query.
+        let parameter = ContractBalanceOfQueryParams {
+            queries: vec![
+                BalanceOfQuery {
+                    token_id: TOKEN_0,
+                    address:  ADDRESS_0,
+                },
+                BalanceOfQuery {
+                    token_id: TOKEN_0,
+                    address:  ADDRESS_1,
+                },
+                BalanceOfQuery {
+                    token_id: TOKEN_1,
+                    address:  ADDRESS_0,
+                },
+                BalanceOfQuery {
+                    token_id: TOKEN_1,
+                    address:  ADDRESS_1,
+                },
+            ],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result: ContractResult<ContractBalanceOfQueryResponse> =
+            contract_balance_of(&ctx, &host);
+        claim_eq!(
+            result.expect_report("Failed getting result value").0,
+            [300.into(), 100.into(), 0.into(), 1.into()],
+            "Balances should reflect the transfers"
+        );
+    }
+
+    /// Test a batch of transfers is rejected as a whole, when one of the
+    /// transfers fails.
+    #[concordium_test]
+    fn test_batch_transfer_insufficient_funds() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let transfers = vec![
+            Transfer {
+                token_id: TOKEN_0,
+                amount:   ContractTokenAmount::from(100),
+                from:     ADDRESS_0,
+                to:       Receiver::from_account(ACCOUNT_1),
+                data:     AdditionalData::empty(),
+            },
+            Transfer {
+                token_id: TOKEN_1,
+                amount:   ContractTokenAmount::from(2),
+                from:     ADDRESS_0,
+                to:       Receiver::from_account(ACCOUNT_1),
+                data:     AdditionalData::empty(),
+            },
+        ];
+        let parameter = TransferParams::from(transfers);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(
+            err,
+            ContractError::InsufficientFunds,
+            "Error is expected to be InsufficientFunds"
+        )
+    }
+
+    /// Test the total supply is tracked per token type, and is unaffected by
+    /// transfers.
+    #[concordium_test]
+    fn test_total_supply() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+        ctx.set_owner(ACCOUNT_0);
+
+        // and parameter.
+        let mut tokens = collections::BTreeMap::new();
+        tokens.insert(TOKEN_0, 50.into());
+        let parameter = MintParams {
+            owner: ADDRESS_1,
+            tokens,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state
+            .transfer(&TOKEN_0, 100.into(), &ADDRESS_0, &ADDRESS_1)
+            .expect_report("Failed to transfer");
+        let mut host = TestHost::new(state, state_builder);
+
+        // Mint more of `TOKEN_0`.
+        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Call the contract function.
+        let parameter = TotalSupplyQueryParams {
+            queries: vec![TOKEN_0, TOKEN_1],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result: ContractResult<Vec<ContractTokenAmount>> = contract_total_supply(&ctx, &host);
+
+        // Check the result.
+        claim_eq!(
+            result.expect_report("Failed getting result value"),
+            vec![450.into(), 1.into()],
+            "The total supply should only change when minting"
+        );
+
+        // Query a token which does not exist.
+        let parameter = TotalSupplyQueryParams {
+            queries: vec![TokenIdU8(7)],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result: ContractResult<Vec<ContractTokenAmount>> = contract_total_supply(&ctx, &host);
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(err, ContractError::InvalidTokenId, "Error is expected to be InvalidTokenId")
+    }
 }
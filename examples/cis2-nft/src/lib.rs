@@ -7,8 +7,9 @@
 //!
 //! In this example the contract is initialized with no tokens, and tokens can
 //! be minted through a `mint` contract function, which will only succeed for
-//! the contract owner. No functionality to burn token is defined in this
-//! example.
+//! the contract owner. Every token is minted with the URL of its metadata and
+//! optionally the hash of the metadata, which are returned by `tokenMetadata`.
+//! No functionality to burn token is defined in this example.
 //!
 //! Note: The word 'address' refers to either an account address or a
 //! contract address.
@@ -17,17 +18,14 @@
 //! function for transferring an amount of a specific token type from one
 //! address to another address. An address can enable and disable one or more
 //! addresses as operators. An operator of some address is allowed to transfer
-//! any tokens owned by this address.
+//! any tokens owned by this address. When tokens are transferred to a contract,
+//! the receive hook function named in the transfer is invoked on the contract.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use concordium_cis2::*;
 use concordium_std::*;
 
-/// The baseurl for the token metadata, gets appended with the token ID as hex
-/// encoding before emitted in the TokenMetadata event.
-const TOKEN_METADATA_BASE_URL: &str = "https://some.example/token/";
-
 /// List of supported standards by this contract address.
 const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
     [CIS0_STANDARD_IDENTIFIER, CIS2_STANDARD_IDENTIFIER];
@@ -49,9 +47,10 @@ type ContractTokenAmount = TokenAmountU8;
 struct MintParams {
     /// Owner of the newly minted tokens.
     owner:  Address,
-    /// A collection of tokens to mint.
+    /// A collection of tokens to mint, each with the URL and optionally the
+    /// hash of its metadata.
     #[concordium(size_length = 1)]
-    tokens: collections::BTreeSet<ContractTokenId>,
+    tokens: collections::BTreeMap<ContractTokenId, MetadataUrl>,
 }
 
 /// The state for each address.
@@ -81,8 +80,8 @@ impl<S: HasStateApi> AddressState<S> {
 struct State<S> {
     /// The state for each address.
     state:        StateMap<Address, AddressState<S>, S>,
-    /// All of the token IDs
-    all_tokens:   StateSet<ContractTokenId, S>,
+    /// All of the token IDs, with the metadata URL of each token.
+    all_tokens:   StateMap<ContractTokenId, MetadataUrl, S>,
     /// Map with contract addresses providing implementations of additional
     /// standards.
     implementors: StateMap<StandardIdentifierOwned, Vec<ContractAddress>, S>,
@@ -147,19 +146,24 @@ impl<S: HasStateApi> State<S> {
     fn empty(state_builder: &mut StateBuilder<S>) -> Self {
         State {
             state:        state_builder.new_map(),
-            all_tokens:   state_builder.new_set(),
+            all_tokens:   state_builder.new_map(),
             implementors: state_builder.new_map(),
         }
     }
 
-    /// Mint a new token with a given address as the owner
+    /// Mint a new token with a given address as the owner and the metadata
+    /// at the given URL.
     fn mint(
         &mut self,
         token: ContractTokenId,
+        metadata_url: MetadataUrl,
         owner: &Address,
         state_builder: &mut StateBuilder<S>,
     ) -> ContractResult<()> {
-        ensure!(self.all_tokens.insert(token), CustomContractError::TokenIdAlreadyExists.into());
+        self.all_tokens
+            .entry(token)
+            .vacant_or(CustomContractError::TokenIdAlreadyExists)?
+            .insert(metadata_url);
 
         let mut owner_state =
             self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
@@ -170,7 +174,13 @@ impl<S: HasStateApi> State<S> {
     /// Check that the token ID currently exists in this contract.
     #[inline(always)]
     fn contains_token(&self, token_id: &ContractTokenId) -> bool {
-        self.all_tokens.contains(token_id)
+        self.all_tokens.get(token_id).is_some()
+    }
+
+    /// Get the metadata URL of a token.
+    /// Results in an error if the token ID does not exist in the state.
+    fn metadata_url(&self, token_id: &ContractTokenId) -> ContractResult<MetadataUrl> {
+        self.all_tokens.get(token_id).map(|url| url.clone()).ok_or(ContractError::InvalidTokenId)
     }
 
     /// Get the current balance of a given token ID for a given address.
@@ -277,14 +287,6 @@ impl<S: HasStateApi> State<S> {
     }
 }
 
-/// Build a string from TOKEN_METADATA_BASE_URL appended with the token ID
-/// encoded as hex.
-fn build_token_metadata_url(token_id: &ContractTokenId) -> String {
-    let mut token_metadata_url = String::from(TOKEN_METADATA_BASE_URL);
-    token_metadata_url.push_str(&token_id.to_string());
-    token_metadata_url
-}
-
 // Contract functions
 
 /// Initialize contract instance with no token types initially.
@@ -327,7 +329,7 @@ fn contract_view<S: HasStateApi>(
             operators,
         }));
     }
-    let all_tokens = state.all_tokens.iter().map(|x| *x).collect();
+    let all_tokens = state.all_tokens.iter().map(|(x, _)| *x).collect();
 
     Ok(ViewState {
         state: inner_state,
@@ -337,9 +339,8 @@ fn contract_view<S: HasStateApi>(
 
 /// Mint new tokens with a given address as the owner of these tokens.
 /// Can only be called by the contract owner.
-/// Logs a `Mint` and a `TokenMetadata` event for each token.
-/// The url for the token metadata is the token ID encoded in hex, appended on
-/// the `TOKEN_METADATA_BASE_URL`.
+/// Logs a `Mint` and a `TokenMetadata` event for each token, with the metadata
+/// URL given for the token.
 ///
 /// It rejects if:
 /// - The sender is not the contract instance owner.
@@ -376,9 +377,9 @@ fn contract_mint<S: HasStateApi>(
 
     let (state, builder) = host.state_and_builder();
 
-    for &token_id in params.tokens.iter() {
+    for (token_id, metadata_url) in params.tokens {
         // Mint the token in the state.
-        state.mint(token_id, &params.owner, builder)?;
+        state.mint(token_id, metadata_url.clone(), &params.owner, builder)?;
 
         // Event for minted NFT.
         logger.log(&Cis2Event::Mint(MintEvent {
@@ -390,10 +391,7 @@ fn contract_mint<S: HasStateApi>(
         // Metadata URL for the NFT.
         logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
             token_id,
-            metadata_url: MetadataUrl {
-                url:  build_token_metadata_url(&token_id),
-                hash: None,
-            },
+            metadata_url,
         }))?;
     }
     Ok(())
@@ -609,13 +607,8 @@ fn contract_token_metadata<S: HasStateApi>(
     // Build the response.
     let mut response = Vec::with_capacity(params.queries.len());
     for token_id in params.queries {
-        // Check the token exists.
-        ensure!(host.state().contains_token(&token_id), ContractError::InvalidTokenId);
-
-        let metadata_url = MetadataUrl {
-            url:  build_token_metadata_url(&token_id),
-            hash: None,
-        };
+        // Get the metadata URL of the token, failing if it does not exist.
+        let metadata_url = host.state().metadata_url(&token_id)?;
         response.push(metadata_url);
     }
     let result = TokenMetadataQueryResponse::from(response);
@@ -694,13 +687,34 @@ mod tests {
     const TOKEN_0: ContractTokenId = TokenIdU32(0);
     const TOKEN_1: ContractTokenId = TokenIdU32(42);
     const TOKEN_2: ContractTokenId = TokenIdU32(43);
+    const RECEIVER_CONTRACT: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+
+    /// Test helper function which creates the metadata URL of a token, with
+    /// the hash of the metadata for all but `TOKEN_2`.
+    fn metadata_url(token_id: ContractTokenId) -> MetadataUrl {
+        MetadataUrl {
+            url:  format!("https://some.example/token/{}", token_id),
+            hash: if token_id == TOKEN_2 {
+                None
+            } else {
+                Some([token_id.0 as u8; 32])
+            },
+        }
+    }
 
     /// Test helper function which creates a contract state with two tokens with
     /// id `TOKEN_0` and id `TOKEN_1` owned by `ADDRESS_0`
     fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
         let mut state = State::empty(state_builder);
-        state.mint(TOKEN_0, &ADDRESS_0, state_builder).expect_report("Failed to mint TOKEN_0");
-        state.mint(TOKEN_1, &ADDRESS_0, state_builder).expect_report("Failed to mint TOKEN_1");
+        state
+            .mint(TOKEN_0, metadata_url(TOKEN_0), &ADDRESS_0, state_builder)
+            .expect_report("Failed to mint TOKEN_0");
+        state
+            .mint(TOKEN_1, metadata_url(TOKEN_1), &ADDRESS_0, state_builder)
+            .expect_report("Failed to mint TOKEN_1");
         state
     }
 
@@ -733,10 +747,10 @@ mod tests {
         ctx.set_owner(ACCOUNT_0);
 
         // and parameter.
-        let mut tokens = collections::BTreeSet::new();
-        tokens.insert(TOKEN_0);
-        tokens.insert(TOKEN_1);
-        tokens.insert(TOKEN_2);
+        let mut tokens = collections::BTreeMap::new();
+        tokens.insert(TOKEN_0, metadata_url(TOKEN_0));
+        tokens.insert(TOKEN_1, metadata_url(TOKEN_1));
+        tokens.insert(TOKEN_2, metadata_url(TOKEN_2));
         let parameter = MintParams {
             tokens,
             owner: ADDRESS_0,
@@ -794,10 +808,7 @@ mod tests {
             logger.logs.contains(&to_bytes(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
                 TokenMetadataEvent {
                     token_id:     TOKEN_0,
-                    metadata_url: MetadataUrl {
-                        url:  format!("{}00000000", TOKEN_METADATA_BASE_URL),
-                        hash: None,
-                    },
+                    metadata_url: metadata_url(TOKEN_0),
                 }
             ))),
             "Expected an event for token metadata for TOKEN_0"
@@ -806,10 +817,7 @@ mod tests {
             logger.logs.contains(&to_bytes(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
                 TokenMetadataEvent {
                     token_id:     TOKEN_1,
-                    metadata_url: MetadataUrl {
-                        url:  format!("{}2A000000", TOKEN_METADATA_BASE_URL),
-                        hash: None,
-                    },
+                    metadata_url: metadata_url(TOKEN_1),
                 }
             ))),
             "Expected an event for token metadata for TOKEN_1"
@@ -1048,4 +1056,185 @@ mod tests {
             "Incorrect event emitted"
         )
     }
+    /// Test minting fails, when one of the token IDs already exists.
+    #[concordium_test]
+    fn test_mint_existing_token() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+        ctx.set_owner(ACCOUNT_0);
+
+        // and parameter.
+        let mut tokens = collections::BTreeMap::new();
+        tokens.insert(TOKEN_1, metadata_url(TOKEN_1));
+        tokens.insert(TOKEN_2, metadata_url(TOKEN_2));
+        let parameter = MintParams {
+            tokens,
+            owner: ADDRESS_1,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(
+            err,
+            ContractError::Custom(CustomContractError::TokenIdAlreadyExists),
+            "Error is expected to be TokenIdAlreadyExists"
+        )
+    }
+
+    /// Test the token metadata query returns the metadata URLs and hashes
+    /// given when minting, and fails for unknown tokens.
+    #[concordium_test]
+    fn test_token_metadata() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+
+        // and parameter.
+        let parameter = ContractTokenMetadataQueryParams {
+            queries: vec![TOKEN_1, TOKEN_0],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<TokenMetadataQueryResponse> =
+            contract_token_metadata(&ctx, &host);
+
+        // Check the result.
+        claim_eq!(
+            to_bytes(&result.expect_report("Failed getting result value")),
+            to_bytes(&TokenMetadataQueryResponse::from(vec![
+                metadata_url(TOKEN_1),
+                metadata_url(TOKEN_0)
+            ])),
+            "The metadata URLs should be the ones given when minting"
+        );
+
+        // Query a token which does not exist.
+        let parameter = ContractTokenMetadataQueryParams {
+            queries: vec![TOKEN_2],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result: ContractResult<TokenMetadataQueryResponse> =
+            contract_token_metadata(&ctx, &host);
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(err, ContractError::InvalidTokenId, "Error is expected to be InvalidTokenId")
+    }
+
+    /// Test transferring to a contract invokes the receive hook function named
+    /// in the transfer.
+    #[concordium_test]
+    fn test_transfer_to_contract() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let transfer = Transfer {
+            token_id: TOKEN_0,
+            amount:   ContractTokenAmount::from(1),
+            from:     ADDRESS_0,
+            to:       Receiver::from_contract(
+                RECEIVER_CONTRACT,
+                OwnedEntrypointName::new_unchecked("onReceivingCIS2".to_string()),
+            ),
+            data:     AdditionalData::empty(),
+        };
+        let parameter = TransferParams::from(vec![transfer]);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Set up the receive hook of the receiving contract.
+        host.setup_mock_entrypoint(
+            RECEIVER_CONTRACT,
+            OwnedEntrypointName::new_unchecked("onReceivingCIS2".to_string()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: OnReceivingCis2Params<ContractTokenId, ContractTokenAmount> =
+                    from_bytes(parameter.0).expect_report("Invalid hook parameter");
+                claim_eq!(params.token_id, TOKEN_0, "The hook should be told the token");
+                claim_eq!(params.from, ADDRESS_0, "The hook should be told the sender");
+                Ok((false, ()))
+            }),
+        );
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state.
+        let balance = host
+            .state()
+            .balance(&TOKEN_0, &Address::Contract(RECEIVER_CONTRACT))
+            .expect_report("Token is expected to exist");
+        claim_eq!(balance, 1.into(), "The receiving contract should own the token");
+    }
+
+    /// Test transferring to a contract fails, when the receive hook function
+    /// rejects.
+    #[concordium_test]
+    fn test_transfer_to_contract_hook_rejects() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+
+        // and parameter.
+        let transfer = Transfer {
+            token_id: TOKEN_0,
+            amount:   ContractTokenAmount::from(1),
+            from:     ADDRESS_0,
+            to:       Receiver::from_contract(
+                RECEIVER_CONTRACT,
+                OwnedEntrypointName::new_unchecked("onReceivingCIS2".to_string()),
+            ),
+            data:     AdditionalData::empty(),
+        };
+        let parameter = TransferParams::from(vec![transfer]);
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Set up the receive hook of the receiving contract to reject.
+        host.setup_mock_entrypoint(
+            RECEIVER_CONTRACT,
+            OwnedEntrypointName::new_unchecked("onReceivingCIS2".to_string()),
+            MockFn::returning_err::<()>(CallContractError::Trap),
+        );
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_transfer(&ctx, &mut host, &mut logger);
+
+        // Check the result.
+        let err = result.expect_err_report("Expected to fail");
+        claim_eq!(
+            err,
+            ContractError::Custom(CustomContractError::InvokeContractError),
+            "Error is expected to be InvokeContractError"
+        )
+    }
 }
@@ -34,14 +34,11 @@
 //! the admin address to a new address, and update the metadata URL.
 #![cfg_attr(not(feature = "std"), no_std)]
 use concordium_cis2::{Cis2Event, *};
-use concordium_std::{collections::BTreeMap, *};
+use concordium_std::*;
 
 /// The id of the wCCD token in this contract.
 const TOKEN_ID_WCCD: ContractTokenId = TokenIdUnit();
 
-/// Tag for the NewAdmin event.
-pub const NEW_ADMIN_EVENT_TAG: u8 = 0;
-
 /// List of supported standards by this contract address.
 const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
     [CIS0_STANDARD_IDENTIFIER, CIS2_STANDARD_IDENTIFIER];
@@ -181,104 +178,21 @@ struct SetPausedParams {
     paused: bool,
 }
 
-/// A NewAdminEvent introduced by this smart contract.
-#[derive(Serial, SchemaType)]
-#[repr(transparent)]
-struct NewAdminEvent {
-    /// New admin address.
-    new_admin: Address,
-}
-
-/// Tagged events to be serialized for the event log.
+/// Tagged events to be serialized for the event log. The events specified in
+/// the CIS-2 library are nested, such that they keep their tags.
+#[concordium_event]
 enum WccdEvent {
-    NewAdmin(NewAdminEvent),
+    /// A new admin was set.
+    #[concordium(tag = 0)]
+    NewAdmin {
+        /// New admin address.
+        new_admin: Address,
+    },
+    /// An event specified by the CIS-2 standard.
+    #[concordium(nested)]
     Cis2Event(Cis2Event<ContractTokenId, ContractTokenAmount>),
 }
 
-impl Serial for WccdEvent {
-    fn serial<W: Write>(&self, out: &mut W) -> Result<(), W::Err> {
-        match self {
-            WccdEvent::NewAdmin(event) => {
-                out.write_u8(NEW_ADMIN_EVENT_TAG)?;
-                event.serial(out)
-            }
-            WccdEvent::Cis2Event(event) => event.serial(out),
-        }
-    }
-}
-
-/// Manual implementation of the `WccdEventSchema` which includes both the
-/// events specified in this contract and the events specified in the CIS-2
-/// library. The events are tagged to distinguish them on-chain.
-impl schema::SchemaType for WccdEvent {
-    fn get_type() -> schema::Type {
-        let mut event_map = BTreeMap::new();
-        event_map.insert(
-            NEW_ADMIN_EVENT_TAG,
-            (
-                "NewAdmin".to_string(),
-                schema::Fields::Named(vec![(String::from("new_admin"), Address::get_type())]),
-            ),
-        );
-        event_map.insert(
-            TRANSFER_EVENT_TAG,
-            (
-                "Transfer".to_string(),
-                schema::Fields::Named(vec![
-                    (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("amount"), ContractTokenAmount::get_type()),
-                    (String::from("from"), Address::get_type()),
-                    (String::from("to"), Address::get_type()),
-                ]),
-            ),
-        );
-        event_map.insert(
-            MINT_EVENT_TAG,
-            (
-                "Mint".to_string(),
-                schema::Fields::Named(vec![
-                    (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("amount"), ContractTokenAmount::get_type()),
-                    (String::from("owner"), Address::get_type()),
-                ]),
-            ),
-        );
-        event_map.insert(
-            BURN_EVENT_TAG,
-            (
-                "Burn".to_string(),
-                schema::Fields::Named(vec![
-                    (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("amount"), ContractTokenAmount::get_type()),
-                    (String::from("owner"), Address::get_type()),
-                ]),
-            ),
-        );
-        event_map.insert(
-            UPDATE_OPERATOR_EVENT_TAG,
-            (
-                "UpdateOperator".to_string(),
-                schema::Fields::Named(vec![
-                    (String::from("update"), OperatorUpdate::get_type()),
-                    (String::from("owner"), Address::get_type()),
-                    (String::from("operator"), Address::get_type()),
-                ]),
-            ),
-        );
-        event_map.insert(
-            TOKEN_METADATA_EVENT_TAG,
-            (
-                "TokenMetadata".to_string(),
-                schema::Fields::Named(vec![
-                    (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("metadata_url"), MetadataUrl::get_type()),
-                ]),
-            ),
-        );
-        schema::Type::TaggedEnum(event_map)
-    }
-}
-
 /// The different errors the contract can produce.
 #[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
 enum CustomContractError {
@@ -527,14 +441,14 @@ fn contract_init<S: HasStateApi>(
     // Construct the initial contract state.
     let state = State::new(state_builder, invoker, metadata_url.clone());
     // Log event for the newly minted token.
-    logger.log(&WccdEvent::Cis2Event(Cis2Event::Mint(MintEvent {
+    logger.log_event(&WccdEvent::Cis2Event(Cis2Event::Mint(MintEvent {
         token_id: TOKEN_ID_WCCD,
         amount:   ContractTokenAmount::from(0u64),
         owner:    invoker,
     })))?;
 
     // Log event for where to find metadata for the token
-    logger.log(&WccdEvent::Cis2Event(Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
+    logger.log_event(&WccdEvent::Cis2Event(Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
         TokenMetadataEvent {
             token_id: TOKEN_ID_WCCD,
             metadata_url,
@@ -542,9 +456,9 @@ fn contract_init<S: HasStateApi>(
     )))?;
 
     // Log event for the new admin.
-    logger.log(&WccdEvent::NewAdmin(NewAdminEvent {
+    logger.log_event(&WccdEvent::NewAdmin {
         new_admin: invoker,
-    }))?;
+    })?;
 
     Ok(state)
 }
@@ -581,7 +495,7 @@ fn contract_wrap<S: HasStateApi>(
     state.mint(&TOKEN_ID_WCCD, amount.micro_ccd.into(), &receive_address, state_builder)?;
 
     // Log the newly minted tokens.
-    logger.log(&WccdEvent::Cis2Event(Cis2Event::Mint(MintEvent {
+    logger.log_event(&WccdEvent::Cis2Event(Cis2Event::Mint(MintEvent {
         token_id: TOKEN_ID_WCCD,
         amount:   ContractTokenAmount::from(amount.micro_ccd),
         owner:    sender,
@@ -591,7 +505,7 @@ fn contract_wrap<S: HasStateApi>(
     // Only executes the `OnReceivingCis2` hook if the receiver is not the sender
     // and the receiver is a contract.
     if sender != receive_address {
-        logger.log(&WccdEvent::Cis2Event(Cis2Event::Transfer(TransferEvent {
+        logger.log_event(&WccdEvent::Cis2Event(Cis2Event::Transfer(TransferEvent {
             token_id: TOKEN_ID_WCCD,
             amount:   ContractTokenAmount::from(amount.micro_ccd),
             from:     sender,
@@ -651,7 +565,7 @@ fn contract_unwrap<S: HasStateApi>(
     state.burn(&TOKEN_ID_WCCD, params.amount, &params.owner)?;
 
     // Log the burning of tokens.
-    logger.log(&WccdEvent::Cis2Event(Cis2Event::Burn(BurnEvent {
+    logger.log_event(&WccdEvent::Cis2Event(Cis2Event::Burn(BurnEvent {
         token_id: TOKEN_ID_WCCD,
         amount:   params.amount,
         owner:    params.owner,
@@ -703,9 +617,9 @@ fn contract_update_admin<S: HasStateApi>(
     host.state_mut().admin = new_admin;
 
     // Log a new admin event.
-    logger.log(&WccdEvent::NewAdmin(NewAdminEvent {
+    logger.log_event(&WccdEvent::NewAdmin {
         new_admin,
-    }))?;
+    })?;
 
     Ok(())
 }
@@ -774,7 +688,7 @@ fn contract_state_set_metadata_url<S: HasStateApi>(
     *host.state_mut().metadata_url = metadata_url.clone();
 
     // Log an event including the new metadata for this token.
-    logger.log(&WccdEvent::Cis2Event(Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
+    logger.log_event(&WccdEvent::Cis2Event(Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
         TokenMetadataEvent {
             token_id: TOKEN_ID_WCCD,
             metadata_url,
@@ -839,7 +753,7 @@ fn contract_transfer<S: HasStateApi>(
         state.transfer(&token_id, amount, &from, &to_address, builder)?;
 
         // Log transfer event
-        logger.log(&WccdEvent::Cis2Event(Cis2Event::Transfer(TransferEvent {
+        logger.log_event(&WccdEvent::Cis2Event(Cis2Event::Transfer(TransferEvent {
             token_id,
             amount,
             from,
@@ -901,7 +815,7 @@ fn contract_update_operator<S: HasStateApi>(
         }
 
         // Log the appropriate event
-        logger.log(&WccdEvent::Cis2Event(
+        logger.log_event(&WccdEvent::Cis2Event(
             Cis2Event::<ContractTokenId, ContractTokenAmount>::UpdateOperator(
                 UpdateOperatorEvent {
                     owner:    sender,
@@ -1247,9 +1161,9 @@ mod tests {
             "Missing event with metadata for the token"
         );
         claim!(
-            logger.logs.contains(&to_bytes(&WccdEvent::NewAdmin(NewAdminEvent {
+            logger.logs.contains(&to_bytes(&WccdEvent::NewAdmin {
                 new_admin: ADDRESS_0,
-            }))),
+            })),
             "Missing event for the new admin"
         );
     }
@@ -1732,9 +1646,9 @@ mod tests {
 
         // Check the event
         claim!(
-            logger.logs.contains(&to_bytes(&WccdEvent::NewAdmin(NewAdminEvent {
+            logger.logs.contains(&to_bytes(&WccdEvent::NewAdmin {
                 new_admin: NEW_ADMIN_ADDRESS,
-            }))),
+            })),
             "Missing event for the new admin"
         );
     }
@@ -1937,4 +1851,94 @@ mod tests {
             "Unwrap should fail because contract is paused"
         );
     }
+    /// Test helper function which sums the wCCD balances of all addresses.
+    fn total_wccd<S: HasStateApi>(state: &State<S>) -> u64 {
+        state.token.iter().map(|(_, address_state)| address_state.balance.0).sum()
+    }
+
+    /// Test the CCD held by the contract and the wCCD in circulation stay in
+    /// sync through a sequence of wraps, transfers and unwraps.
+    #[concordium_test]
+    fn test_ccd_and_wccd_stay_in_sync() {
+        // Set up the state and host. The 400 wCCD of the initial state are
+        // backed by 400 CCD held by the contract.
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(Amount::from_micro_ccd(400));
+        let mut logger = TestLogger::init();
+        let mut ctx = TestReceiveContext::empty();
+
+        // ADDRESS_1 wraps 100 CCD for itself. The balance of the contract
+        // includes the incoming amount.
+        let parameter_bytes = to_bytes(&WrapParams {
+            to:   Receiver::from_account(ACCOUNT_1),
+            data: AdditionalData::empty(),
+        });
+        ctx.set_sender(ADDRESS_1);
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(Amount::from_micro_ccd(500));
+        contract_wrap(&ctx, &mut host, Amount::from_micro_ccd(100), &mut logger)
+            .expect_report("Wrapping failed");
+        claim_eq!(total_wccd(host.state()), host.self_balance().micro_ccd);
+
+        // ADDRESS_0 wraps 50 CCD for ADDRESS_1.
+        ctx.set_sender(ADDRESS_0);
+        host.set_self_balance(Amount::from_micro_ccd(550));
+        contract_wrap(&ctx, &mut host, Amount::from_micro_ccd(50), &mut logger)
+            .expect_report("Wrapping failed");
+        claim_eq!(total_wccd(host.state()), host.self_balance().micro_ccd);
+
+        // ADDRESS_1 transfers 30 wCCD to ADDRESS_0.
+        let parameter_bytes = to_bytes(&TransferParams::from(vec![Transfer {
+            token_id: TOKEN_ID_WCCD,
+            amount:   ContractTokenAmount::from(30),
+            from:     ADDRESS_1,
+            to:       Receiver::from_account(ACCOUNT_0),
+            data:     AdditionalData::empty(),
+        }]));
+        ctx.set_sender(ADDRESS_1);
+        ctx.set_parameter(&parameter_bytes);
+        contract_transfer(&ctx, &mut host, &mut logger).expect_report("Transferring failed");
+        claim_eq!(total_wccd(host.state()), host.self_balance().micro_ccd);
+
+        // ADDRESS_1 unwraps 120 wCCD to ACCOUNT_0, and ADDRESS_0 unwraps all of
+        // its wCCD to itself.
+        let parameter_bytes = to_bytes(&UnwrapParams {
+            amount:   ContractTokenAmount::from(120),
+            owner:    ADDRESS_1,
+            receiver: Receiver::from_account(ACCOUNT_0),
+            data:     AdditionalData::empty(),
+        });
+        ctx.set_parameter(&parameter_bytes);
+        contract_unwrap(&ctx, &mut host, &mut logger).expect_report("Unwrapping failed");
+        claim_eq!(total_wccd(host.state()), host.self_balance().micro_ccd);
+
+        let parameter_bytes = to_bytes(&UnwrapParams {
+            amount:   ContractTokenAmount::from(430),
+            owner:    ADDRESS_0,
+            receiver: Receiver::from_account(ACCOUNT_0),
+            data:     AdditionalData::empty(),
+        });
+        ctx.set_sender(ADDRESS_0);
+        ctx.set_parameter(&parameter_bytes);
+        contract_unwrap(&ctx, &mut host, &mut logger).expect_report("Unwrapping failed");
+
+        // Only the wCCD of ADDRESS_1 remains, backed by the same amount of CCD.
+        claim_eq!(
+            host.state().balance(&TOKEN_ID_WCCD, &ADDRESS_1),
+            Ok(0u64.into()),
+            "ADDRESS_1 should have no wCCD left"
+        );
+        claim_eq!(total_wccd(host.state()), 0, "All wCCD should be unwrapped");
+        claim_eq!(host.self_balance(), Amount::zero(), "All CCD should be paid out");
+        claim_eq!(
+            host.get_transfers(),
+            vec![
+                (ACCOUNT_0, Amount::from_micro_ccd(120)),
+                (ACCOUNT_0, Amount::from_micro_ccd(430))
+            ],
+            "The unwrapped CCD should be transferred to the receivers"
+        );
+    }
 }
@@ -0,0 +1,30 @@
+//! Reusable components for the example smart contracts.
+//!
+//! Each component is a small piece of contract state together with the
+//! functions operating on it, which a contract embeds in its own state. This
+//! lets the examples share cross-cutting concerns instead of reimplementing
+//! them.
+//!
+//! The components are
+//!  - [`Pausable`](pausable::Pausable), which lets a contract be paused, for
+//!    instance by its owner, and rejects operations while paused.
+//!  - [`Roles`](roles::Roles), which keeps track of the addresses holding each
+//!    role, together with the [`ensure_role!`] macro for checking them.
+//!  - [`Ownable`](ownable::Ownable), which keeps track of the owner of a
+//!    contract and transfers the ownership in two steps.
+//!
+//! Besides the components, [`sender_account`](sender::sender_account) gets the
+//! sender of a message for the operations which only accounts can perform, and
+//! [`testing`] builds the contexts used by the unit tests of the examples.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod ownable;
+pub mod pausable;
+pub mod roles;
+pub mod sender;
+pub mod testing;
+
+pub use ownable::{Ownable, OwnableError, OwnershipEvent};
+pub use pausable::{ContractPaused, Pausable};
+pub use roles::{Role, RoleChange, RoleEvent, Roles};
+pub use sender::{sender_account, OnlyAccount};
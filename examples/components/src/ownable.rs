@@ -0,0 +1,138 @@
+//! A component for transferring the ownership of a contract in two steps.
+//!
+//! The owner of a contract instance is fixed when it is created, so contracts
+//! which need a transferable owner keep track of it in their state using
+//! [`Ownable`]. The transfer is done in two steps: the owner proposes a new
+//! owner using [`transfer_ownership`](Ownable::transfer_ownership), and the new
+//! owner completes the transfer using
+//! [`accept_ownership`](Ownable::accept_ownership). This prevents a contract
+//! from being locked forever by a transfer to a mistyped address.
+//!
+//! Both steps return an [`OwnershipEvent`] for the contract to log, possibly as
+//! part of its own event type.
+use concordium_std::*;
+
+/// The owner of a contract, and the proposed new owner while an ownership
+/// transfer is in progress.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ownable {
+    owner:         Address,
+    pending_owner: Option<Address>,
+}
+
+/// The events of an ownership transfer.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnershipEvent {
+    /// The owner proposed a new owner.
+    TransferStarted {
+        owner:     Address,
+        new_owner: Address,
+    },
+    /// The proposed new owner accepted the ownership.
+    Transferred {
+        previous_owner: Address,
+        new_owner:      Address,
+    },
+}
+
+/// The errors of an ownership transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnableError {
+    /// Only the owner can do this.
+    NotOwner,
+    /// Only the proposed new owner can do this.
+    NotPendingOwner,
+}
+
+impl Ownable {
+    /// The given address as the owner, without any transfer in progress.
+    pub fn new(owner: Address) -> Self {
+        Self {
+            owner,
+            pending_owner: None,
+        }
+    }
+
+    /// The current owner.
+    pub fn owner(&self) -> Address { self.owner }
+
+    /// The proposed new owner, if an ownership transfer is in progress.
+    pub fn pending_owner(&self) -> Option<Address> { self.pending_owner }
+
+    /// Return an error if the address is not the owner.
+    pub fn ensure_owner(&self, address: &Address) -> Result<(), OwnableError> {
+        if *address == self.owner {
+            Ok(())
+        } else {
+            Err(OwnableError::NotOwner)
+        }
+    }
+
+    /// Propose a new owner, replacing any earlier proposal. The ownership is
+    /// only transferred once the new owner accepts it.
+    /// Only the owner can do this.
+    pub fn transfer_ownership(
+        &mut self,
+        sender: Address,
+        new_owner: Address,
+    ) -> Result<OwnershipEvent, OwnableError> {
+        self.ensure_owner(&sender)?;
+        self.pending_owner = Some(new_owner);
+        Ok(OwnershipEvent::TransferStarted {
+            owner: self.owner,
+            new_owner,
+        })
+    }
+
+    /// Accept a proposed ownership transfer, making the sender the owner.
+    /// Only the proposed new owner can do this.
+    pub fn accept_ownership(&mut self, sender: Address) -> Result<OwnershipEvent, OwnableError> {
+        if self.pending_owner != Some(sender) {
+            return Err(OwnableError::NotPendingOwner);
+        }
+        let previous_owner = self.owner;
+        self.owner = sender;
+        self.pending_owner = None;
+        Ok(OwnershipEvent::Transferred {
+            previous_owner,
+            new_owner: sender,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: Address = Address::Account(AccountAddress([0; 32]));
+    const BOB: Address = Address::Account(AccountAddress([1; 32]));
+    const CHARLIE: Address = Address::Account(AccountAddress([2; 32]));
+
+    #[test]
+    fn test_two_step_transfer() {
+        let mut ownable = Ownable::new(ALICE);
+
+        assert_eq!(ownable.transfer_ownership(BOB, CHARLIE), Err(OwnableError::NotOwner));
+        assert_eq!(
+            ownable.transfer_ownership(ALICE, BOB),
+            Ok(OwnershipEvent::TransferStarted {
+                owner:     ALICE,
+                new_owner: BOB,
+            })
+        );
+        assert_eq!(ownable.owner(), ALICE, "The owner should not change before acceptance.");
+        assert_eq!(ownable.pending_owner(), Some(BOB));
+
+        assert_eq!(ownable.accept_ownership(CHARLIE), Err(OwnableError::NotPendingOwner));
+        assert_eq!(
+            ownable.accept_ownership(BOB),
+            Ok(OwnershipEvent::Transferred {
+                previous_owner: ALICE,
+                new_owner:      BOB,
+            })
+        );
+        assert_eq!(ownable.owner(), BOB);
+        assert_eq!(ownable.pending_owner(), None);
+        assert_eq!(ownable.ensure_owner(&ALICE), Err(OwnableError::NotOwner));
+    }
+}
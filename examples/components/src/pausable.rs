@@ -0,0 +1,95 @@
+//! A component for pausing a contract.
+//!
+//! A contract embeds a [`Pausable`] in its state, and calls
+//! [`ensure_not_paused`](Pausable::ensure_not_paused) at the start of every
+//! operation that must not be performed while paused. Who can pause and
+//! unpause the contract is up to the contract.
+//!
+//! The error [`ContractPaused`] is returned while paused, and contracts convert
+//! it to their own error type by implementing `From<ContractPaused>`.
+//!
+//! ```ignore
+//! #[derive(Serial, DeserialWithState)]
+//! #[concordium(state_parameter = "S")]
+//! struct State<S> {
+//!     pausable: Pausable,
+//!     // ...
+//! }
+//!
+//! impl From<ContractPaused> for Error {
+//!     fn from(_: ContractPaused) -> Self { Error::ContractPaused }
+//! }
+//!
+//! #[receive(contract = "counter", name = "increment", mutable)]
+//! fn increment<S: HasStateApi>(
+//!     ctx: &impl HasReceiveContext,
+//!     host: &mut impl HasHost<State<S>, StateApiType = S>,
+//! ) -> Result<(), Error> {
+//!     host.state().pausable.ensure_not_paused()?;
+//!     // ...
+//! }
+//! ```
+use concordium_std::*;
+
+/// Whether a contract is paused.
+///
+/// It is serialized as a single `bool`, so a `paused: bool` field in the state
+/// of a contract can be replaced by a `Pausable` without changing the layout
+/// of the state.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pausable {
+    paused: bool,
+}
+
+/// The error returned by [`Pausable::ensure_not_paused`] when the contract is
+/// paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractPaused;
+
+impl Pausable {
+    /// A contract which is not paused.
+    pub fn new() -> Self { Self::default() }
+
+    /// Whether the contract is paused.
+    pub fn is_paused(&self) -> bool { self.paused }
+
+    /// Pause or unpause the contract.
+    pub fn set_paused(&mut self, paused: bool) { self.paused = paused; }
+
+    /// Pause the contract.
+    pub fn pause(&mut self) { self.set_paused(true) }
+
+    /// Unpause the contract.
+    pub fn unpause(&mut self) { self.set_paused(false) }
+
+    /// Return an error if the contract is paused.
+    pub fn ensure_not_paused(&self) -> Result<(), ContractPaused> {
+        if self.paused {
+            Err(ContractPaused)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_not_paused() {
+        let mut pausable = Pausable::new();
+        assert_eq!(pausable.ensure_not_paused(), Ok(()));
+        pausable.pause();
+        assert_eq!(pausable.ensure_not_paused(), Err(ContractPaused));
+        pausable.unpause();
+        assert_eq!(pausable.ensure_not_paused(), Ok(()));
+    }
+
+    #[test]
+    fn test_serialized_as_bool() {
+        let mut pausable = Pausable::new();
+        pausable.pause();
+        assert_eq!(to_bytes(&pausable), to_bytes(&true));
+    }
+}
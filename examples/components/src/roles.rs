@@ -0,0 +1,160 @@
+//! A component for role-based access control.
+//!
+//! A contract embeds [`Roles`] in its state, and uses
+//! [`ensure_role!`](crate::ensure_role) to check that the sender has the role
+//! required for an operation. Roles are identified by a [`Role`], for which the
+//! contract defines a constant for each of its roles.
+//!
+//! Granting and revoking roles logs a [`RoleEvent`], so the contract should
+//! include `RoleEvent` in its event schema, or in its own event type.
+//!
+//! ```ignore
+//! const UPDATER: Role = 0;
+//!
+//! #[receive(contract = "oracle", name = "update", mutable)]
+//! fn update<S: HasStateApi>(
+//!     ctx: &impl HasReceiveContext,
+//!     host: &mut impl HasHost<State<S>, StateApiType = S>,
+//! ) -> Result<(), Error> {
+//!     ensure_role!(host.state().roles, UPDATER, ctx.sender(), Error::Unauthorized);
+//!     // ...
+//! }
+//! ```
+use concordium_std::*;
+
+/// A role, as defined by the contract using [`Roles`].
+pub type Role = u8;
+
+/// The addresses holding each role.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+pub struct Roles<S> {
+    members: StateSet<(Role, Address), S>,
+}
+
+/// A role being granted to or revoked from an address.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct RoleChange {
+    /// The role.
+    pub role:    Role,
+    /// The address the role was granted to or revoked from.
+    pub address: Address,
+}
+
+/// The event logged when roles change.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum RoleEvent {
+    /// A role was granted to an address which did not have it.
+    Granted(RoleChange),
+    /// A role was revoked from an address which had it.
+    Revoked(RoleChange),
+}
+
+impl<S: HasStateApi> Roles<S> {
+    /// No addresses with any roles.
+    pub fn new(state_builder: &mut StateBuilder<S>) -> Self {
+        Self {
+            members: state_builder.new_set(),
+        }
+    }
+
+    /// Whether the address has the role.
+    pub fn has_role(&self, role: Role, address: &Address) -> bool {
+        self.members.contains(&(role, *address))
+    }
+
+    /// Grant the role to the address, logging a [`RoleEvent::Granted`] if the
+    /// address did not already have it.
+    pub fn grant_role(
+        &mut self,
+        role: Role,
+        address: Address,
+        logger: &mut impl HasLogger,
+    ) -> Result<(), LogError> {
+        if self.members.insert((role, address)) {
+            logger.log(&RoleEvent::Granted(RoleChange {
+                role,
+                address,
+            }))?;
+        }
+        Ok(())
+    }
+
+    /// Revoke the role from the address, logging a [`RoleEvent::Revoked`] if
+    /// the address had it.
+    pub fn revoke_role(
+        &mut self,
+        role: Role,
+        address: Address,
+        logger: &mut impl HasLogger,
+    ) -> Result<(), LogError> {
+        if self.members.remove(&(role, address)) {
+            logger.log(&RoleEvent::Revoked(RoleChange {
+                role,
+                address,
+            }))?;
+        }
+        Ok(())
+    }
+}
+
+/// Return early with the given error if the address does not have the role.
+///
+/// `ensure_role!(roles, role, address, error)` is short for
+/// `ensure!(roles.has_role(role, &address), error)`.
+#[macro_export]
+macro_rules! ensure_role {
+    ($roles:expr, $role:expr, $address:expr, $err:expr) => {{
+        if !$roles.has_role($role, &$address) {
+            return Err($err);
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ADMIN: Role = 0;
+    const UPDATER: Role = 1;
+    const ALICE: Address = Address::Account(AccountAddress([0; 32]));
+
+    #[test]
+    fn test_grant_and_revoke_role() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut roles = Roles::new(&mut state_builder);
+        let mut logger = TestLogger::init();
+
+        roles.grant_role(UPDATER, ALICE, &mut logger).unwrap();
+        assert!(roles.has_role(UPDATER, &ALICE));
+        assert!(!roles.has_role(ADMIN, &ALICE), "Only the granted role should be held.");
+
+        // Granting a role twice does not log another event.
+        roles.grant_role(UPDATER, ALICE, &mut logger).unwrap();
+        roles.revoke_role(UPDATER, ALICE, &mut logger).unwrap();
+        assert!(!roles.has_role(UPDATER, &ALICE));
+
+        let change = RoleChange {
+            role:    UPDATER,
+            address: ALICE,
+        };
+        assert_eq!(logger.logs, vec![
+            to_bytes(&RoleEvent::Granted(change)),
+            to_bytes(&RoleEvent::Revoked(change))
+        ]);
+    }
+
+    #[test]
+    fn test_ensure_role() {
+        fn check(roles: &Roles<TestStateApi>) -> Result<(), &'static str> {
+            ensure_role!(roles, ADMIN, ALICE, "Not an admin");
+            Ok(())
+        }
+        let mut state_builder = TestStateBuilder::new();
+        let mut roles = Roles::new(&mut state_builder);
+        assert_eq!(check(&roles), Err("Not an admin"));
+        roles.grant_role(ADMIN, ALICE, &mut TestLogger::init()).unwrap();
+        assert_eq!(check(&roles), Ok(()));
+    }
+}
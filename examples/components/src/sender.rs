@@ -0,0 +1,55 @@
+//! A helper for operations which only accounts can perform.
+//!
+//! Contracts call [`sender_account`] to get the account sending a message, for
+//! instance to pay it or to keep track of its balance. The error
+//! [`OnlyAccount`] is returned when the sender is a contract, and contracts
+//! convert it to their own error type by implementing `From<OnlyAccount>`.
+//!
+//! ```ignore
+//! impl From<OnlyAccount> for Error {
+//!     fn from(_: OnlyAccount) -> Self { Error::OnlyAccount }
+//! }
+//!
+//! #[receive(contract = "bank", name = "withdraw", mutable)]
+//! fn withdraw<S: HasStateApi>(
+//!     ctx: &impl HasReceiveContext,
+//!     host: &mut impl HasHost<State<S>, StateApiType = S>,
+//! ) -> Result<(), Error> {
+//!     let account = sender_account(ctx)?;
+//!     // ...
+//! }
+//! ```
+use concordium_std::*;
+
+/// The error returned by [`sender_account`] when the sender is a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnlyAccount;
+
+/// Get the sender, rejecting if it is a contract.
+pub fn sender_account(ctx: &impl HasReceiveContext) -> Result<AccountAddress, OnlyAccount> {
+    match ctx.sender() {
+        Address::Account(account) => Ok(account),
+        Address::Contract(_) => Err(OnlyAccount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ACCOUNT: AccountAddress = AccountAddress([1; 32]);
+    const CONTRACT: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+
+    #[test]
+    fn test_sender_account() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(ACCOUNT));
+        assert_eq!(sender_account(&ctx), Ok(ACCOUNT));
+        ctx.set_sender(Address::Contract(CONTRACT));
+        assert_eq!(sender_account(&ctx), Err(OnlyAccount));
+    }
+}
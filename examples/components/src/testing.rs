@@ -0,0 +1,34 @@
+//! Helpers for the unit tests of the example smart contracts.
+//!
+//! Most tests send a message from an account at some point in time, so the
+//! contexts built here only set the sender, the slot time and, optionally, the
+//! parameter. Tests which also need the owner or the address of the instance
+//! set them on the returned context.
+//!
+//! ```ignore
+//! let mut ctx = ctx_at(ALICE, 1_000);
+//! ctx.set_owner(OWNER);
+//! let result = contract_withdraw(&ctx, &mut host);
+//! ```
+use concordium_std::{test_infrastructure::*, *};
+
+/// A receive context for a message sent by the account `sender` at the slot
+/// time `slot_time`, in milliseconds.
+pub fn ctx_at<'a>(sender: AccountAddress, slot_time: u64) -> TestReceiveContext<'a> {
+    let mut ctx = TestReceiveContext::empty();
+    ctx.set_sender(Address::Account(sender));
+    ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+    ctx
+}
+
+/// A receive context as returned by [`ctx_at`], with the serialized parameter
+/// `parameter_bytes`.
+pub fn ctx_with_parameter<'a>(
+    sender: AccountAddress,
+    slot_time: u64,
+    parameter_bytes: &'a [u8],
+) -> TestReceiveContext<'a> {
+    let mut ctx = ctx_at(sender, slot_time);
+    ctx.set_parameter(parameter_bytes);
+    ctx
+}
@@ -0,0 +1,815 @@
+//! A verifiable credential registry smart contract example, following the
+//! surface of the CIS-4 standard.
+//!
+//! # Description
+//! An instance of this contract is a registry of credentials issued by a
+//! single issuer, which is the account initializing the instance. A credential
+//! is identified by the public key of its holder, and the registry records its
+//! validity period, whether the holder can revoke it, and its metadata. The
+//! credential itself is kept off-chain by the holder, who uses the registry to
+//! prove that the credential was issued and is still valid.
+//!
+//! # Operations
+//! The issuer can
+//!  - `registerCredential`, which registers a new credential;
+//!  - `revokeCredentialIssuer`, which revokes a credential;
+//!  - `updateIssuerKey` and `updateIssuerMetadata`, which replace the public
+//!    key and metadata of the issuer.
+//!
+//! The holder of a credential can revoke it, if the credential was registered
+//! as holder revocable, by signing a revocation message with the key of the
+//! credential. Anyone can submit the signed message using
+//! `revokeCredentialHolder`, which verifies the signature on-chain. Each
+//! credential has a revocation nonce, which must be part of the signed
+//! message, to prevent replaying it.
+//!
+//! Anyone can query the `issuer` key, a `credentialEntry`, and the
+//! `credentialStatus` of a credential, which is computed from whether it is
+//! revoked and its validity period at the time of the query.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::MetadataUrl;
+use concordium_std::*;
+
+/// The domain separation string prepended to the revocation messages signed by
+/// holders, ensuring that a signature cannot be reused for other purposes.
+pub const SIGNING_DOMAIN: &[u8] = b"WEB3ID:REVOKE";
+
+/// The identifier of a credential, which is the public key of its holder.
+pub type CredentialHolderId = PublicKeyEd25519;
+
+/// The information about a credential provided by the issuer.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct CredentialInfo {
+    /// The identifier of the credential.
+    pub holder_id:        CredentialHolderId,
+    /// Whether the holder is allowed to revoke the credential.
+    pub holder_revocable: bool,
+    /// The time from which the credential is valid.
+    pub valid_from:       Timestamp,
+    /// The time until which the credential is valid, if it ever expires.
+    pub valid_until:      Option<Timestamp>,
+    /// The metadata describing the credential.
+    pub metadata_url:     MetadataUrl,
+}
+
+/// A registered credential, and the return type of `credentialEntry`.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct CredentialEntry {
+    /// The information provided by the issuer.
+    pub credential_info:  CredentialInfo,
+    /// The schema of the credential, which is the same for all credentials of
+    /// the registry.
+    pub schema_ref:       MetadataUrl,
+    /// The nonce which must be part of the next revocation message signed by
+    /// the holder.
+    pub revocation_nonce: u64,
+}
+
+/// The status of a credential, and the return type of `credentialStatus`.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialStatus {
+    /// The credential is within its validity period and not revoked.
+    Active,
+    /// The credential has been revoked.
+    Revoked,
+    /// The validity period of the credential has ended.
+    Expired,
+    /// The validity period of the credential has not started yet.
+    NotActivated,
+}
+
+/// A credential as it is stored in the state.
+#[derive(Debug, Serialize, Clone)]
+struct CredentialState {
+    /// The information provided by the issuer.
+    info:             CredentialInfo,
+    /// The nonce which must be part of the next revocation message signed by
+    /// the holder.
+    revocation_nonce: u64,
+    /// Whether the credential has been revoked.
+    revoked:          bool,
+}
+
+impl CredentialState {
+    /// Compute the status of the credential at the given time.
+    fn status(&self, now: Timestamp) -> CredentialStatus {
+        if self.revoked {
+            CredentialStatus::Revoked
+        } else if now < self.info.valid_from {
+            CredentialStatus::NotActivated
+        } else if self.info.valid_until.map_or(false, |valid_until| valid_until <= now) {
+            CredentialStatus::Expired
+        } else {
+            CredentialStatus::Active
+        }
+    }
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The account of the issuer, which is the only account allowed to
+    /// register and revoke credentials as the issuer.
+    issuer_account:  AccountAddress,
+    /// The public key of the issuer.
+    issuer_key:      PublicKeyEd25519,
+    /// The metadata describing the issuer.
+    issuer_metadata: MetadataUrl,
+    /// The schema of the credentials issued.
+    schema_ref:      MetadataUrl,
+    /// The registered credentials, including revoked and expired ones.
+    credentials:     StateMap<CredentialHolderId, CredentialState, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The public key of the issuer.
+    issuer_key:      PublicKeyEd25519,
+    /// The metadata describing the issuer.
+    issuer_metadata: MetadataUrl,
+    /// The schema of the credentials issued.
+    schema_ref:      MetadataUrl,
+}
+
+/// The parameter type for the contract function `revokeCredentialIssuer`.
+#[derive(Serialize, SchemaType)]
+pub struct RevokeCredentialIssuerParam {
+    /// The credential to revoke.
+    pub credential_id: CredentialHolderId,
+    /// An optional reason for the revocation.
+    pub reason:        Option<String>,
+}
+
+/// The data making a holder signature valid for a single revocation only.
+#[derive(Serialize, SchemaType)]
+pub struct SigningData {
+    /// The contract instance the signature is meant for.
+    pub contract_address: ContractAddress,
+    /// The entrypoint the signature is meant for.
+    pub entry_point:      OwnedEntrypointName,
+    /// The revocation nonce of the credential.
+    pub nonce:            u64,
+    /// The time until which the signature can be used.
+    pub timestamp:        Timestamp,
+}
+
+/// The revocation message signed by the holder.
+#[derive(Serialize, SchemaType)]
+pub struct RevocationDataHolder {
+    /// The credential to revoke.
+    pub credential_id: CredentialHolderId,
+    /// The data making the signature valid for a single revocation only.
+    pub signing_data:  SigningData,
+    /// An optional reason for the revocation.
+    pub reason:        Option<String>,
+}
+
+/// The parameter type for the contract function `revokeCredentialHolder`.
+#[derive(Serialize, SchemaType)]
+pub struct RevokeCredentialHolderParam {
+    /// The signature of the holder on [`SIGNING_DOMAIN`] followed by the
+    /// serialized `data`.
+    pub signature: SignatureEd25519,
+    /// The revocation message.
+    pub data:      RevocationDataHolder,
+}
+
+/// Who revoked a credential.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum Revoker {
+    /// The issuer of the registry.
+    Issuer,
+    /// The holder of the credential.
+    Holder,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType)]
+pub enum CredentialEvent {
+    /// A credential was registered.
+    Register {
+        holder_id:  CredentialHolderId,
+        schema_ref: MetadataUrl,
+    },
+    /// A credential was revoked.
+    Revoke {
+        holder_id: CredentialHolderId,
+        revoker:   Revoker,
+        reason:    Option<String>,
+    },
+    /// The public key of the issuer was replaced.
+    IssuerKey(PublicKeyEd25519),
+    /// The metadata of the issuer was replaced.
+    IssuerMetadata(MetadataUrl),
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when someone else than the issuer acts as the issuer.
+    Unauthorized,
+    /// Raised when registering a credential which is already registered.
+    CredentialAlreadyExists,
+    /// Raised when the credential is not registered.
+    CredentialNotFound,
+    /// Raised when the validity period of a credential ends before it starts.
+    InvalidValidityPeriod,
+    /// Raised when revoking a credential which is revoked or expired.
+    NotRevocable,
+    /// Raised when the holder revokes a credential which is not holder
+    /// revocable.
+    NotHolderRevocable,
+    /// Raised when the signed message is meant for another contract instance
+    /// or entrypoint.
+    WrongSigningData,
+    /// Raised when the nonce of the signed message is not the revocation
+    /// nonce of the credential.
+    NonceMismatch,
+    /// Raised when the signed message can no longer be used.
+    SignatureExpired,
+    /// Raised when the signature is not valid for the key of the credential.
+    InvalidSignature,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Ensure the sender is the account of the issuer.
+fn ensure_issuer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&state.issuer_account), ContractError::Unauthorized);
+    Ok(())
+}
+
+/// Mark the credential as revoked, if it is active or not activated yet.
+fn revoke_credential<S: HasStateApi>(
+    now: Timestamp,
+    state: &mut State<S>,
+    credential_id: &CredentialHolderId,
+) -> ContractResult<()> {
+    let mut credential =
+        state.credentials.get_mut(credential_id).ok_or(ContractError::CredentialNotFound)?;
+    match credential.status(now) {
+        CredentialStatus::Active | CredentialStatus::NotActivated => {}
+        CredentialStatus::Revoked | CredentialStatus::Expired => bail!(ContractError::NotRevocable),
+    }
+    credential.revoked = true;
+    Ok(())
+}
+
+// Contract functions
+
+/// Initialize the registry, with the sender as the issuer.
+#[init(contract = "credential_registry", parameter = "InitParameter", event = "CredentialEvent")]
+fn init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let param: InitParameter = ctx.parameter_cursor().get()?;
+    Ok(State {
+        issuer_account:  ctx.init_origin(),
+        issuer_key:      param.issuer_key,
+        issuer_metadata: param.issuer_metadata,
+        schema_ref:      param.schema_ref,
+        credentials:     state_builder.new_map(),
+    })
+}
+
+/// Register a new credential. Can only be called by the issuer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the issuer.
+/// - The validity period ends before it starts.
+/// - A credential with the same holder id is already registered.
+/// - It fails to log the event.
+#[receive(
+    contract = "credential_registry",
+    name = "registerCredential",
+    parameter = "CredentialInfo",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn register_credential<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let info: CredentialInfo = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure_issuer(ctx, state)?;
+    if let Some(valid_until) = info.valid_until {
+        ensure!(info.valid_from < valid_until, ContractError::InvalidValidityPeriod);
+    }
+    let holder_id = info.holder_id;
+    state.credentials.entry(holder_id).vacant_or(ContractError::CredentialAlreadyExists)?.insert(
+        CredentialState {
+            info,
+            revocation_nonce: 0,
+            revoked: false,
+        },
+    );
+    logger.log(&CredentialEvent::Register {
+        holder_id,
+        schema_ref: state.schema_ref.clone(),
+    })?;
+    Ok(())
+}
+
+/// Revoke a credential as the issuer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the issuer.
+/// - The credential is not registered.
+/// - The credential is revoked or expired.
+/// - It fails to log the event.
+#[receive(
+    contract = "credential_registry",
+    name = "revokeCredentialIssuer",
+    parameter = "RevokeCredentialIssuerParam",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn revoke_credential_issuer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let param: RevokeCredentialIssuerParam = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure_issuer(ctx, state)?;
+    revoke_credential(ctx.metadata().slot_time(), state, &param.credential_id)?;
+    logger.log(&CredentialEvent::Revoke {
+        holder_id: param.credential_id,
+        revoker:   Revoker::Issuer,
+        reason:    param.reason,
+    })?;
+    Ok(())
+}
+
+/// Revoke a credential on behalf of its holder, using a revocation message
+/// signed by the holder. Anyone can submit the message.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The credential is not registered.
+/// - The credential is not holder revocable.
+/// - The message is meant for another contract instance or entrypoint.
+/// - The message has expired.
+/// - The nonce of the message is not the revocation nonce of the credential.
+/// - The signature is not valid for the holder id of the credential.
+/// - The credential is revoked or expired.
+/// - It fails to log the event.
+#[receive(
+    contract = "credential_registry",
+    name = "revokeCredentialHolder",
+    parameter = "RevokeCredentialHolderParam",
+    error = "ContractError",
+    crypto_primitives,
+    enable_logger,
+    mutable
+)]
+fn revoke_credential_holder<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let param: RevokeCredentialHolderParam = ctx.parameter_cursor().get()?;
+    let data = param.data;
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    {
+        let mut credential = state
+            .credentials
+            .get_mut(&data.credential_id)
+            .ok_or(ContractError::CredentialNotFound)?;
+        ensure!(credential.info.holder_revocable, ContractError::NotHolderRevocable);
+
+        let signing_data = &data.signing_data;
+        ensure!(
+            signing_data.contract_address == ctx.self_address()
+                && signing_data.entry_point.as_entrypoint_name()
+                    == EntrypointName::new_unchecked("revokeCredentialHolder"),
+            ContractError::WrongSigningData
+        );
+        ensure!(now < signing_data.timestamp, ContractError::SignatureExpired);
+        ensure_eq!(signing_data.nonce, credential.revocation_nonce, ContractError::NonceMismatch);
+
+        let mut message = SIGNING_DOMAIN.to_vec();
+        message.extend(to_bytes(&data));
+        ensure!(
+            crypto_primitives.verify_ed25519_signature(
+                data.credential_id,
+                param.signature,
+                &message
+            ),
+            ContractError::InvalidSignature
+        );
+        credential.revocation_nonce += 1;
+    }
+    revoke_credential(now, state, &data.credential_id)?;
+    logger.log(&CredentialEvent::Revoke {
+        holder_id: data.credential_id,
+        revoker:   Revoker::Holder,
+        reason:    data.reason,
+    })?;
+    Ok(())
+}
+
+/// Replace the public key of the issuer. Can only be called by the issuer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the issuer.
+/// - It fails to log the event.
+#[receive(
+    contract = "credential_registry",
+    name = "updateIssuerKey",
+    parameter = "PublicKeyEd25519",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn update_issuer_key<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let key: PublicKeyEd25519 = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure_issuer(ctx, state)?;
+    state.issuer_key = key;
+    logger.log(&CredentialEvent::IssuerKey(key))?;
+    Ok(())
+}
+
+/// Replace the metadata of the issuer. Can only be called by the issuer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the issuer.
+/// - It fails to log the event.
+#[receive(
+    contract = "credential_registry",
+    name = "updateIssuerMetadata",
+    parameter = "MetadataUrl",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn update_issuer_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let metadata: MetadataUrl = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure_issuer(ctx, state)?;
+    state.issuer_metadata = metadata.clone();
+    logger.log(&CredentialEvent::IssuerMetadata(metadata))?;
+    Ok(())
+}
+
+/// View the public key of the issuer.
+#[receive(contract = "credential_registry", name = "issuer", return_value = "PublicKeyEd25519")]
+fn issuer<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<PublicKeyEd25519> {
+    Ok(host.state().issuer_key)
+}
+
+/// View the entry of a credential.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The credential is not registered.
+#[receive(
+    contract = "credential_registry",
+    name = "credentialEntry",
+    parameter = "CredentialHolderId",
+    return_value = "CredentialEntry",
+    error = "ContractError"
+)]
+fn credential_entry<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<CredentialEntry> {
+    let credential_id: CredentialHolderId = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    let credential =
+        state.credentials.get(&credential_id).ok_or(ContractError::CredentialNotFound)?;
+    Ok(CredentialEntry {
+        credential_info:  credential.info.clone(),
+        schema_ref:       state.schema_ref.clone(),
+        revocation_nonce: credential.revocation_nonce,
+    })
+}
+
+/// View the status of a credential at the current time.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The credential is not registered.
+#[receive(
+    contract = "credential_registry",
+    name = "credentialStatus",
+    parameter = "CredentialHolderId",
+    return_value = "CredentialStatus",
+    error = "ContractError"
+)]
+fn credential_status<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<CredentialStatus> {
+    let credential_id: CredentialHolderId = ctx.parameter_cursor().get()?;
+    let credential =
+        host.state().credentials.get(&credential_id).ok_or(ContractError::CredentialNotFound)?;
+    Ok(credential.status(ctx.metadata().slot_time()))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const ISSUER: AccountAddress = AccountAddress([0; 32]);
+    const OTHER: AccountAddress = AccountAddress([1; 32]);
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index:    10,
+        subindex: 0,
+    };
+    const ISSUER_KEY: PublicKeyEd25519 = PublicKeyEd25519([10; 32]);
+    const HOLDER: CredentialHolderId = PublicKeyEd25519([11; 32]);
+    const VALID_FROM: u64 = 100;
+    const VALID_UNTIL: u64 = 200;
+
+    fn metadata(url: &str) -> MetadataUrl {
+        MetadataUrl {
+            url:  url.into(),
+            hash: None,
+        }
+    }
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            issuer_key:      ISSUER_KEY,
+            issuer_metadata: metadata("https://issuer.example/metadata.json"),
+            schema_ref:      metadata("https://issuer.example/schema.json"),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(ISSUER);
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(
+        sender: AccountAddress,
+        slot_time: u64,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_with_parameter(sender, slot_time, parameter_bytes);
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx
+    }
+
+    fn register_holder(host: &mut TestHost<State<TestStateApi>>, holder_revocable: bool) {
+        let parameter_bytes = to_bytes(&CredentialInfo {
+            holder_id: HOLDER,
+            holder_revocable,
+            valid_from: Timestamp::from_timestamp_millis(VALID_FROM),
+            valid_until: Some(Timestamp::from_timestamp_millis(VALID_UNTIL)),
+            metadata_url: metadata("https://issuer.example/credential.json"),
+        });
+        let mut logger = TestLogger::init();
+        register_credential(&ctx_at(ISSUER, 0, &parameter_bytes), host, &mut logger)
+            .expect_report("Registering should pass");
+    }
+
+    fn status_at(host: &TestHost<State<TestStateApi>>, slot_time: u64) -> CredentialStatus {
+        let parameter_bytes = to_bytes(&HOLDER);
+        credential_status(&ctx_at(OTHER, slot_time, &parameter_bytes), host)
+            .expect_report("Querying the status should pass")
+    }
+
+    fn holder_param(nonce: u64, entry_point: &str, timestamp: u64) -> RevokeCredentialHolderParam {
+        RevokeCredentialHolderParam {
+            signature: SignatureEd25519([1; 64]),
+            data:      RevocationDataHolder {
+                credential_id: HOLDER,
+                signing_data:  SigningData {
+                    contract_address: SELF_ADDRESS,
+                    entry_point: OwnedEntrypointName::new_unchecked(entry_point.into()),
+                    nonce,
+                    timestamp: Timestamp::from_timestamp_millis(timestamp),
+                },
+                reason:        Some("Lost key".into()),
+            },
+        }
+    }
+
+    #[concordium_test]
+    /// Test registering a credential, and that its status follows its validity
+    /// period.
+    fn test_register_credential() {
+        let mut host = new_host();
+        register_holder(&mut host, false);
+
+        claim_eq!(status_at(&host, VALID_FROM - 1), CredentialStatus::NotActivated);
+        claim_eq!(status_at(&host, VALID_FROM), CredentialStatus::Active);
+        claim_eq!(status_at(&host, VALID_UNTIL), CredentialStatus::Expired);
+
+        let parameter_bytes = to_bytes(&HOLDER);
+        let entry = credential_entry(&ctx_at(OTHER, 0, &parameter_bytes), &host)
+            .expect_report("Querying the entry should pass");
+        claim_eq!(entry.credential_info.metadata_url.url, "https://issuer.example/credential.json");
+        claim_eq!(entry.schema_ref.url, "https://issuer.example/schema.json");
+        claim_eq!(entry.revocation_nonce, 0);
+    }
+
+    #[concordium_test]
+    /// Test that only the issuer can register credentials, and that
+    /// credentials cannot be registered twice or with an empty validity
+    /// period.
+    fn test_register_credential_rejected() {
+        let mut host = new_host();
+        let mut info = CredentialInfo {
+            holder_id:        HOLDER,
+            holder_revocable: true,
+            valid_from:       Timestamp::from_timestamp_millis(VALID_FROM),
+            valid_until:      Some(Timestamp::from_timestamp_millis(VALID_FROM)),
+            metadata_url:     metadata("https://issuer.example/credential.json"),
+        };
+        let mut logger = TestLogger::init();
+        let parameter_bytes = to_bytes(&info);
+        let res = register_credential(&ctx_at(ISSUER, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::InvalidValidityPeriod));
+
+        info.valid_until = None;
+        let parameter_bytes = to_bytes(&info);
+        let res = register_credential(&ctx_at(OTHER, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the issuer can register");
+        register_credential(&ctx_at(ISSUER, 0, &parameter_bytes), &mut host, &mut logger)
+            .expect_report("Registering should pass");
+        let res = register_credential(&ctx_at(ISSUER, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::CredentialAlreadyExists));
+        claim_eq!(logger.logs.len(), 1, "Only one event should be logged");
+    }
+
+    #[concordium_test]
+    /// Test that the issuer can revoke a credential once, and that the event is
+    /// logged.
+    fn test_revoke_credential_issuer() {
+        let mut host = new_host();
+        register_holder(&mut host, false);
+
+        let parameter_bytes = to_bytes(&RevokeCredentialIssuerParam {
+            credential_id: HOLDER,
+            reason:        None,
+        });
+        let mut logger = TestLogger::init();
+        let res = revoke_credential_issuer(
+            &ctx_at(OTHER, VALID_FROM, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the issuer can revoke");
+        let res = revoke_credential_issuer(
+            &ctx_at(ISSUER, VALID_UNTIL, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::NotRevocable), "Expired credentials cannot be revoked");
+
+        revoke_credential_issuer(
+            &ctx_at(ISSUER, VALID_FROM, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        )
+        .expect_report("Revoking should pass");
+        claim_eq!(status_at(&host, VALID_FROM), CredentialStatus::Revoked);
+        claim_eq!(logger.logs, [to_bytes(&CredentialEvent::Revoke {
+            holder_id: HOLDER,
+            revoker:   Revoker::Issuer,
+            reason:    None,
+        })]);
+        let res = revoke_credential_issuer(
+            &ctx_at(ISSUER, VALID_FROM, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::NotRevocable), "Credentials cannot be revoked twice");
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that a holder can revoke a credential with a signed message, which
+    /// is checked for the contract, entrypoint, expiry, nonce and signature.
+    fn test_revoke_credential_holder() {
+        let mut host = new_host();
+        register_holder(&mut host, true);
+        let crypto_primitives = TestCryptoPrimitives::new();
+        crypto_primitives.setup_verify_ed25519_signature_mock(|key, signature, message| {
+            let param = holder_param(0, "revokeCredentialHolder", VALID_UNTIL);
+            let mut expected = SIGNING_DOMAIN.to_vec();
+            expected.extend(to_bytes(&param.data));
+            key == HOLDER && signature == param.signature && message == &expected[..]
+        });
+        let mut logger = TestLogger::init();
+
+        let mut revoke = |param: &RevokeCredentialHolderParam| {
+            let parameter_bytes = to_bytes(param);
+            revoke_credential_holder(
+                &ctx_at(OTHER, VALID_FROM, &parameter_bytes),
+                &mut host,
+                &mut logger,
+                &crypto_primitives,
+            )
+        };
+        let res = revoke(&holder_param(0, "revokeCredentialIssuer", VALID_UNTIL));
+        claim_eq!(res, Err(ContractError::WrongSigningData));
+        let res = revoke(&holder_param(0, "revokeCredentialHolder", VALID_FROM));
+        claim_eq!(res, Err(ContractError::SignatureExpired));
+        let res = revoke(&holder_param(1, "revokeCredentialHolder", VALID_UNTIL));
+        claim_eq!(res, Err(ContractError::NonceMismatch));
+        let res = revoke(&holder_param(0, "revokeCredentialHolder", VALID_UNTIL - 1));
+        claim_eq!(res, Err(ContractError::InvalidSignature));
+        revoke(&holder_param(0, "revokeCredentialHolder", VALID_UNTIL))
+            .expect_report("Revoking with a valid signature should pass");
+        let res = revoke(&holder_param(1, "revokeCredentialHolder", VALID_UNTIL));
+        claim_eq!(res, Err(ContractError::InvalidSignature), "The signature cannot be replayed");
+
+        claim_eq!(status_at(&host, VALID_FROM), CredentialStatus::Revoked);
+        claim_eq!(logger.logs, [to_bytes(&CredentialEvent::Revoke {
+            holder_id: HOLDER,
+            revoker:   Revoker::Holder,
+            reason:    Some("Lost key".into()),
+        })]);
+    }
+
+    #[concordium_test]
+    /// Test that holders cannot revoke credentials which are not holder
+    /// revocable.
+    fn test_revoke_credential_holder_not_revocable() {
+        let mut host = new_host();
+        register_holder(&mut host, false);
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let mut logger = TestLogger::init();
+
+        let parameter_bytes = to_bytes(&holder_param(0, "revokeCredentialHolder", VALID_UNTIL));
+        let res = revoke_credential_holder(
+            &ctx_at(OTHER, VALID_FROM, &parameter_bytes),
+            &mut host,
+            &mut logger,
+            &crypto_primitives,
+        );
+        claim_eq!(res, Err(ContractError::NotHolderRevocable));
+        claim_eq!(status_at(&host, VALID_FROM), CredentialStatus::Active);
+    }
+
+    #[concordium_test]
+    /// Test that only the issuer can replace the issuer key.
+    fn test_update_issuer_key() {
+        let mut host = new_host();
+        let new_key = PublicKeyEd25519([12; 32]);
+        let parameter_bytes = to_bytes(&new_key);
+        let mut logger = TestLogger::init();
+
+        let res = update_issuer_key(&ctx_at(OTHER, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the issuer can update the key");
+        update_issuer_key(&ctx_at(ISSUER, 0, &parameter_bytes), &mut host, &mut logger)
+            .expect_report("Updating the key should pass");
+
+        let ctx = ctx_at(OTHER, 0, &[]);
+        claim_eq!(issuer(&ctx, &host), Ok(new_key));
+        claim_eq!(logger.logs, [to_bytes(&CredentialEvent::IssuerKey(new_key))]);
+    }
+}
@@ -0,0 +1,370 @@
+//! # Implementation of an all-or-nothing crowdfunding smart contract
+//!
+//! The owner of the contract instance starts a campaign with a funding goal and
+//! a deadline. Until the deadline, any account can `contribute` CCD to the
+//! campaign, and the contract keeps track of the total contributed by every
+//! account.
+//!
+//! After the deadline, the outcome depends on whether the goal was reached:
+//! - If the total raised is at least the goal, the owner can `withdraw` all the
+//!   contributions.
+//! - Otherwise, every contributor can `reclaim` their own contributions.
+//!
+//! Either way, the funds either go to the owner in full or are returned to the
+//! contributors in full.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The amount the campaign aims to raise.
+    goal:          Amount,
+    /// The last time a contribution can be made.
+    deadline:      Timestamp,
+    /// The total amount contributed.
+    total_raised:  Amount,
+    /// The total amount contributed by every account. Entries are removed when
+    /// contributions are reclaimed.
+    contributions: StateMap<AccountAddress, Amount, S>,
+    /// Whether the owner has withdrawn the funds.
+    withdrawn:     bool,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The amount the campaign aims to raise.
+    goal:     Amount,
+    /// The last time a contribution can be made, using the RFC 3339 format (https://tools.ietf.org/html/rfc3339)
+    deadline: Timestamp,
+}
+
+/// The `return_value` type of the `view` function.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The amount the campaign aims to raise.
+    goal:         Amount,
+    /// The last time a contribution can be made.
+    deadline:     Timestamp,
+    /// The total amount contributed.
+    total_raised: Amount,
+    /// Whether the owner has withdrawn the funds.
+    withdrawn:    bool,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a goal of zero.
+    ZeroGoal,
+    /// Raised when a contract tries to contribute or reclaim; Only accounts
+    /// are allowed to contribute.
+    OnlyAccount,
+    /// Raised when contributing without sending any CCD.
+    ZeroContribution,
+    /// Raised when contributing after the deadline.
+    CampaignEnded,
+    /// Raised when withdrawing or reclaiming before the deadline.
+    CampaignActive,
+    /// Raised when someone else than the owner withdraws.
+    Unauthorized,
+    /// Raised when withdrawing while the goal was not reached.
+    GoalNotReached,
+    /// Raised when reclaiming while the goal was reached.
+    GoalReached,
+    /// Raised when withdrawing a second time.
+    AlreadyWithdrawn,
+    /// Raised when reclaiming without contributions, e.g., because they were
+    /// reclaimed already.
+    NothingToReclaim,
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Whether the total raised reached the goal.
+    fn goal_reached(&self) -> bool { self.total_raised >= self.goal }
+}
+
+/// Init function that starts a new campaign.
+#[init(contract = "crowdfunding", parameter = "InitParameter")]
+fn crowdfunding_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.goal > Amount::zero(), ContractError::ZeroGoal.into());
+    Ok(State {
+        goal:          parameter.goal,
+        deadline:      parameter.deadline,
+        total_raised:  Amount::zero(),
+        contributions: state_builder.new_map(),
+        withdrawn:     false,
+    })
+}
+
+/// Contribute the CCD sent along to the campaign. An account can contribute
+/// several times.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The deadline has passed.
+/// - No CCD is sent.
+#[receive(
+    contract = "crowdfunding",
+    name = "contribute",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn crowdfunding_contribute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> Result<(), ContractError> {
+    let contributor = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() <= state.deadline, ContractError::CampaignEnded);
+    ensure!(amount > Amount::zero(), ContractError::ZeroContribution);
+
+    *state.contributions.entry(contributor).or_insert(Amount::zero()) += amount;
+    state.total_raised += amount;
+    Ok(())
+}
+
+/// Withdraw all contributions to the owner, after the deadline if the goal was
+/// reached.
+///
+/// It rejects if:
+/// - The sender is not the owner.
+/// - The deadline has not passed.
+/// - The goal was not reached.
+/// - The funds have already been withdrawn.
+#[receive(contract = "crowdfunding", name = "withdraw", error = "ContractError", mutable)]
+fn crowdfunding_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let owner = ctx.owner();
+    ensure!(ctx.sender().matches_account(&owner), ContractError::Unauthorized);
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() > state.deadline, ContractError::CampaignActive);
+    ensure!(state.goal_reached(), ContractError::GoalNotReached);
+    ensure!(!state.withdrawn, ContractError::AlreadyWithdrawn);
+    state.withdrawn = true;
+    let total_raised = state.total_raised;
+
+    // The transfer always succeeds, since the owner is the sender and the
+    // contract holds all the contributions.
+    host.invoke_transfer(&owner, total_raised).unwrap_abort();
+    Ok(())
+}
+
+/// Reclaim the contributions of the sender, after the deadline if the goal was
+/// not reached.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The deadline has not passed.
+/// - The goal was reached.
+/// - The sender has no contributions to reclaim.
+#[receive(contract = "crowdfunding", name = "reclaim", error = "ContractError", mutable)]
+fn crowdfunding_reclaim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let contributor = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() > state.deadline, ContractError::CampaignActive);
+    ensure!(!state.goal_reached(), ContractError::GoalReached);
+    let contribution =
+        state.contributions.remove_and_get(&contributor).ok_or(ContractError::NothingToReclaim)?;
+
+    // The transfer always succeeds, since the contributor is the sender and
+    // the contract holds all the contributions.
+    host.invoke_transfer(&contributor, contribution).unwrap_abort();
+    Ok(())
+}
+
+/// View function that returns the total contributed by the given account.
+#[receive(
+    contract = "crowdfunding",
+    name = "contributionOf",
+    parameter = "AccountAddress",
+    return_value = "Amount"
+)]
+fn crowdfunding_contribution_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let contributor: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().contributions.get(&contributor).map(|c| *c).unwrap_or_else(Amount::zero))
+}
+
+/// View function that returns the general information about the campaign.
+#[receive(contract = "crowdfunding", name = "view", return_value = "ViewState")]
+fn crowdfunding_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        goal:         state.goal,
+        deadline:     state.deadline,
+        total_raised: state.total_raised,
+        withdrawn:    state.withdrawn,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const DEADLINE: u64 = 100;
+    const GOAL: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            goal:     GOAL,
+            deadline: Timestamp::from_timestamp_millis(DEADLINE),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            crowdfunding_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(sender: AccountAddress, slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_at(sender, slot_time);
+        ctx.set_owner(OWNER);
+        ctx
+    }
+
+    fn contribute(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+        micro_ccd: u64,
+    ) -> Result<(), ContractError> {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        crowdfunding_contribute(&ctx_at(sender, slot_time), host, amount)
+    }
+
+    fn contribution_of(host: &TestHost<State<TestStateApi>>, account: AccountAddress) -> Amount {
+        let parameter_bytes = to_bytes(&account);
+        let mut ctx = ctx_at(account, 0);
+        ctx.set_parameter(&parameter_bytes);
+        crowdfunding_contribution_of(&ctx, host).expect_report("Querying should pass")
+    }
+
+    #[concordium_test]
+    /// Test that a goal of zero is rejected.
+    fn test_init_zero_goal() {
+        let parameter_bytes = to_bytes(&InitParameter {
+            goal:     Amount::zero(),
+            deadline: Timestamp::from_timestamp_millis(DEADLINE),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let res = crowdfunding_init(&ctx, &mut TestStateBuilder::new());
+        claim_eq!(res.map(|_| ()), Err(ContractError::ZeroGoal.into()));
+    }
+
+    #[concordium_test]
+    /// Test that the owner can withdraw after the deadline when the goal is
+    /// reached, and contributors cannot reclaim.
+    fn test_goal_reached() {
+        let mut host = new_host();
+        contribute(&mut host, ALICE, 0, 400).expect_report("Contributing should pass");
+        contribute(&mut host, BOB, 50, 500).expect_report("Contributing should pass");
+        contribute(&mut host, ALICE, DEADLINE, 100).expect_report("Contributing should pass");
+        claim_eq!(contribution_of(&host, ALICE), Amount::from_micro_ccd(500));
+        claim_eq!(host.state().total_raised, GOAL);
+
+        let res = crowdfunding_withdraw(&ctx_at(OWNER, DEADLINE), &mut host);
+        claim_eq!(res, Err(ContractError::CampaignActive), "Withdrawing early should fail");
+        let res = crowdfunding_withdraw(&ctx_at(ALICE, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the owner can withdraw");
+        let res = crowdfunding_reclaim(&ctx_at(ALICE, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::GoalReached), "Reclaiming should fail");
+
+        crowdfunding_withdraw(&ctx_at(OWNER, DEADLINE + 1), &mut host)
+            .expect_report("Withdrawing should pass");
+        claim_eq!(host.get_transfers(), [(OWNER, GOAL)]);
+        let res = crowdfunding_withdraw(&ctx_at(OWNER, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::AlreadyWithdrawn), "Withdrawing twice should fail");
+    }
+
+    #[concordium_test]
+    /// Test that contributors can reclaim their contributions after the
+    /// deadline when the goal is missed, and the owner cannot withdraw.
+    fn test_goal_missed() {
+        let mut host = new_host();
+        contribute(&mut host, ALICE, 0, 300).expect_report("Contributing should pass");
+        contribute(&mut host, BOB, 0, 200).expect_report("Contributing should pass");
+        contribute(&mut host, ALICE, 10, 100).expect_report("Contributing should pass");
+
+        let res = crowdfunding_reclaim(&ctx_at(ALICE, DEADLINE), &mut host);
+        claim_eq!(res, Err(ContractError::CampaignActive), "Reclaiming early should fail");
+        let res = crowdfunding_withdraw(&ctx_at(OWNER, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::GoalNotReached), "Withdrawing should fail");
+
+        crowdfunding_reclaim(&ctx_at(ALICE, DEADLINE + 1), &mut host)
+            .expect_report("Reclaiming should pass");
+        let res = crowdfunding_reclaim(&ctx_at(ALICE, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToReclaim), "Reclaiming twice should fail");
+        let res = crowdfunding_reclaim(&ctx_at(OWNER, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToReclaim), "Non-contributors get nothing");
+        crowdfunding_reclaim(&ctx_at(BOB, DEADLINE + 1), &mut host)
+            .expect_report("Reclaiming should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(400)),
+            (BOB, Amount::from_micro_ccd(200))
+        ]);
+        claim_eq!(contribution_of(&host, ALICE), Amount::zero());
+        claim_eq!(host.self_balance(), Amount::zero(), "Everything should be returned");
+    }
+
+    #[concordium_test]
+    /// Test that contributions after the deadline, without CCD or from
+    /// contracts are rejected.
+    fn test_contribute_rejected() {
+        let mut host = new_host();
+
+        let res = contribute(&mut host, ALICE, DEADLINE + 1, 1_000);
+        claim_eq!(res, Err(ContractError::CampaignEnded), "Contributing late should fail");
+        let res = contribute(&mut host, ALICE, 0, 0);
+        claim_eq!(res, Err(ContractError::ZeroContribution), "Contributing nothing should fail");
+
+        let mut ctx = ctx_at(ALICE, 0);
+        ctx.set_sender(Address::Contract(ContractAddress {
+            index:    1,
+            subindex: 0,
+        }));
+        let res = crowdfunding_contribute(&ctx, &mut host, GOAL);
+        claim_eq!(res, Err(ContractError::OnlyAccount), "Contracts cannot contribute");
+        claim_eq!(host.state().total_raised, Amount::zero());
+    }
+}
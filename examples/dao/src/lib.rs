@@ -0,0 +1,631 @@
+//! # Implementation of a DAO governance smart contract
+//!
+//! A fixed set of members, set when initializing the contract, governs the
+//! contract by voting on proposals. A proposal is a contract call, that is, a
+//! contract address, an entrypoint, a serialized parameter and an amount of
+//! CCD, which is stored in the state until it is executed. The CCD sent along
+//! with the call is taken from the balance of the contract, which anyone can
+//! fund using `deposit`.
+//!
+//! The life-cycle of a proposal is:
+//! - A member creates it using `propose`, which opens the voting period.
+//! - Until the voting period ends, every member can vote yes or no once using
+//!   `vote`.
+//! - After the voting period, the proposal has passed if it has more yes votes
+//!   than no votes, and at least the quorum of yes votes.
+//! - A passed proposal is timelocked for a period after the voting ends, giving
+//!   everyone time to react to its outcome, after which anyone can `execute`
+//!   it. A proposal is executed at most once.
+//!
+//! If the call of a proposal fails, the execution is rejected as a whole, and
+//! the proposal can be executed again later.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// Identifier of a proposal.
+pub type ProposalId = u64;
+
+/// A contract call to be executed when a proposal passes.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct PendingCall {
+    /// The contract to invoke.
+    contract:   ContractAddress,
+    /// The entrypoint to invoke.
+    entrypoint: OwnedEntrypointName,
+    /// The serialized parameter to the entrypoint.
+    #[concordium(size_length = 2)]
+    parameter:  Vec<u8>,
+    /// The amount of CCD to send along, from the balance of the contract.
+    amount:     Amount,
+}
+
+/// A proposal together with the state of its vote.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    /// The member who created the proposal.
+    proposer:    AccountAddress,
+    /// A description of the proposal (to be displayed by the front-end).
+    description: String,
+    /// The call to execute when the proposal passes.
+    call:        PendingCall,
+    /// The last time a vote can be cast.
+    voting_end:  Timestamp,
+    /// The number of yes votes.
+    yes_votes:   u32,
+    /// The number of no votes.
+    no_votes:    u32,
+    /// Whether the proposal has been executed.
+    executed:    bool,
+}
+
+/// A vote on a proposal.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum Vote {
+    Yes,
+    No,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The accounts allowed to propose and vote.
+    members:          StateSet<AccountAddress, S>,
+    /// How long a proposal can be voted on after it was created.
+    voting_period:    Duration,
+    /// How long a passed proposal is locked after the voting has ended.
+    timelock:         Duration,
+    /// The minimum number of yes votes for a proposal to pass.
+    quorum:           u32,
+    /// The identifier of the next proposal.
+    next_proposal_id: ProposalId,
+    /// The proposals.
+    proposals:        StateMap<ProposalId, Proposal, S>,
+    /// The votes cast by every member on every proposal.
+    votes:            StateMap<(ProposalId, AccountAddress), Vote, S>,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The accounts allowed to propose and vote.
+    #[concordium(size_length = 2)]
+    members:       Vec<AccountAddress>,
+    /// How long a proposal can be voted on after it was created.
+    voting_period: Duration,
+    /// How long a passed proposal is locked after the voting has ended.
+    timelock:      Duration,
+    /// The minimum number of yes votes for a proposal to pass.
+    quorum:        u32,
+}
+
+/// Type of the parameter to the `propose` function.
+#[derive(Serialize, SchemaType)]
+pub struct ProposeParameter {
+    /// A description of the proposal.
+    pub description: String,
+    /// The call to execute when the proposal passes.
+    pub call:        PendingCall,
+}
+
+/// Type of the parameter to the `vote` function.
+#[derive(Serialize, SchemaType)]
+pub struct VoteParameter {
+    /// The proposal to vote on.
+    pub proposal_id: ProposalId,
+    /// The vote.
+    pub vote:        Vote,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum DaoEvent {
+    /// A member created a proposal.
+    Proposed {
+        id:       ProposalId,
+        proposer: AccountAddress,
+    },
+    /// A member voted on a proposal.
+    Voted {
+        id:    ProposalId,
+        voter: AccountAddress,
+        vote:  Vote,
+    },
+    /// A passed proposal was executed.
+    Executed {
+        id: ProposalId,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when initializing with a quorum of zero or larger than the
+    /// number of members.
+    InvalidQuorum,
+    /// Raised when the sender is not a member.
+    NotMember,
+    /// Raised when the proposal does not exist.
+    UnknownProposal,
+    /// Raised when voting after the voting period has ended.
+    VotingClosed,
+    /// Raised when a member votes on the same proposal twice.
+    AlreadyVoted,
+    /// Raised when executing before the voting period has ended.
+    VotingActive,
+    /// Raised when executing a proposal which did not pass.
+    NotPassed,
+    /// Raised when executing a passed proposal before the timelock has ended.
+    TimelockActive,
+    /// Raised when executing a proposal a second time.
+    AlreadyExecuted,
+    /// Raised when the call of the proposal failed.
+    InvokeContractError,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+impl Proposal {
+    /// Whether the proposal has more yes than no votes, and at least the
+    /// quorum of yes votes.
+    fn passed(&self, quorum: u32) -> bool {
+        self.yes_votes > self.no_votes && self.yes_votes >= quorum
+    }
+}
+
+/// Get the sender if it is a member.
+fn sender_member<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+) -> ContractResult<AccountAddress> {
+    match ctx.sender() {
+        Address::Account(account) if state.members.contains(&account) => Ok(account),
+        _ => bail!(ContractError::NotMember),
+    }
+}
+
+/// Init function that sets up the members and the voting rules.
+#[init(contract = "dao", parameter = "InitParameter", event = "DaoEvent")]
+fn dao_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    let mut members = state_builder.new_set();
+    for member in parameter.members {
+        members.insert(member);
+    }
+    let member_count = members.iter().count();
+    ensure!(
+        parameter.quorum > 0 && parameter.quorum as usize <= member_count,
+        ContractError::InvalidQuorum.into()
+    );
+    Ok(State {
+        members,
+        voting_period: parameter.voting_period,
+        timelock: parameter.timelock,
+        quorum: parameter.quorum,
+        next_proposal_id: 0,
+        proposals: state_builder.new_map(),
+        votes: state_builder.new_map(),
+    })
+}
+
+/// Receive CCD to fund the calls of proposals. Anyone can deposit.
+#[receive(contract = "dao", name = "deposit", payable)]
+fn dao_deposit<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Create a proposal, opening its voting period. Returns the ID of the
+/// proposal.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not a member.
+/// - It fails to log the event.
+#[receive(
+    contract = "dao",
+    name = "propose",
+    parameter = "ProposeParameter",
+    return_value = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn dao_propose<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ProposalId> {
+    let parameter: ProposeParameter = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let proposer = sender_member(ctx, state)?;
+    let voting_end = ctx
+        .metadata()
+        .slot_time()
+        .checked_add(state.voting_period)
+        .unwrap_or_else(|| Timestamp::from_timestamp_millis(u64::MAX));
+
+    let id = state.next_proposal_id;
+    state.next_proposal_id += 1;
+    state.proposals.insert(id, Proposal {
+        proposer,
+        description: parameter.description,
+        call: parameter.call,
+        voting_end,
+        yes_votes: 0,
+        no_votes: 0,
+        executed: false,
+    });
+
+    logger.log(&DaoEvent::Proposed {
+        id,
+        proposer,
+    })?;
+    Ok(id)
+}
+
+/// Vote on a proposal.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not a member.
+/// - The proposal does not exist.
+/// - The voting period has ended.
+/// - The sender has already voted on the proposal.
+/// - It fails to log the event.
+#[receive(
+    contract = "dao",
+    name = "vote",
+    parameter = "VoteParameter",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn dao_vote<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let parameter: VoteParameter = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let voter = sender_member(ctx, state)?;
+    let id = parameter.proposal_id;
+
+    let mut proposal = state.proposals.get_mut(&id).ok_or(ContractError::UnknownProposal)?;
+    ensure!(ctx.metadata().slot_time() <= proposal.voting_end, ContractError::VotingClosed);
+    state.votes.entry((id, voter)).vacant_or(ContractError::AlreadyVoted)?.insert(parameter.vote);
+    match parameter.vote {
+        Vote::Yes => proposal.yes_votes += 1,
+        Vote::No => proposal.no_votes += 1,
+    }
+
+    logger.log(&DaoEvent::Voted {
+        id,
+        voter,
+        vote: parameter.vote,
+    })?;
+    Ok(())
+}
+
+/// Execute the call of a passed proposal, after the timelock has ended. Anyone
+/// can execute a proposal.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The proposal does not exist.
+/// - The voting period has not ended.
+/// - The proposal did not pass.
+/// - The timelock has not ended.
+/// - The proposal has already been executed.
+/// - The call fails, e.g., because the contract does not hold enough CCD.
+/// - It fails to log the event.
+#[receive(
+    contract = "dao",
+    name = "execute",
+    parameter = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn dao_execute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    let quorum = state.quorum;
+    let timelock = state.timelock;
+
+    let call = {
+        let mut proposal = state.proposals.get_mut(&id).ok_or(ContractError::UnknownProposal)?;
+        ensure!(now > proposal.voting_end, ContractError::VotingActive);
+        ensure!(proposal.passed(quorum), ContractError::NotPassed);
+        let unlocked = proposal
+            .voting_end
+            .checked_add(timelock)
+            .unwrap_or_else(|| Timestamp::from_timestamp_millis(u64::MAX));
+        ensure!(now > unlocked, ContractError::TimelockActive);
+        ensure!(!proposal.executed, ContractError::AlreadyExecuted);
+        // Mark the proposal as executed before making the call, such that the
+        // invoked contract cannot execute it again.
+        proposal.executed = true;
+        proposal.call.clone()
+    };
+
+    host.invoke_contract_raw(
+        &call.contract,
+        Parameter(&call.parameter),
+        call.entrypoint.as_entrypoint_name(),
+        call.amount,
+    )?;
+
+    logger.log(&DaoEvent::Executed {
+        id,
+    })?;
+    Ok(())
+}
+
+/// View function that returns a proposal and the state of its vote.
+#[receive(
+    contract = "dao",
+    name = "viewProposal",
+    parameter = "ProposalId",
+    return_value = "Proposal",
+    error = "ContractError"
+)]
+fn dao_view_proposal<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Proposal> {
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+    let proposal = host.state().proposals.get(&id).ok_or(ContractError::UnknownProposal)?;
+    Ok(proposal.clone())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([0; 32]);
+    const BOB: AccountAddress = AccountAddress([1; 32]);
+    const CAROL: AccountAddress = AccountAddress([2; 32]);
+    const DAVE: AccountAddress = AccountAddress([3; 32]);
+    const TARGET: ContractAddress = ContractAddress {
+        index:    7,
+        subindex: 0,
+    };
+    const VOTING_PERIOD: u64 = 100;
+    const TIMELOCK: u64 = 50;
+    /// The first time a passed proposal created at time 0 can be executed.
+    const UNLOCKED: u64 = VOTING_PERIOD + TIMELOCK + 1;
+
+    fn call() -> PendingCall {
+        PendingCall {
+            contract:   TARGET,
+            entrypoint: OwnedEntrypointName::new_unchecked("receive".into()),
+            parameter:  vec![1, 2, 3],
+            amount:     Amount::from_micro_ccd(10),
+        }
+    }
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            members:       vec![ALICE, BOB, CAROL],
+            voting_period: Duration::from_millis(VOTING_PERIOD),
+            timelock:      Duration::from_millis(TIMELOCK),
+            quorum:        2,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = dao_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(Amount::from_micro_ccd(100));
+        host
+    }
+
+    fn propose(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        logger: &mut TestLogger,
+    ) -> ContractResult<ProposalId> {
+        let parameter_bytes = to_bytes(&ProposeParameter {
+            description: "Fund the target".into(),
+            call:        call(),
+        });
+        let mut ctx = ctx_at(sender, 0);
+        ctx.set_parameter(&parameter_bytes);
+        dao_propose(&ctx, host, logger)
+    }
+
+    fn vote(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        proposal_id: ProposalId,
+        vote: Vote,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&VoteParameter {
+            proposal_id,
+            vote,
+        });
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        dao_vote(&ctx, host, logger)
+    }
+
+    fn execute(
+        host: &mut TestHost<State<TestStateApi>>,
+        id: ProposalId,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&id);
+        let mut ctx = ctx_at(DAVE, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        dao_execute(&ctx, host, logger)
+    }
+
+    fn setup_target(host: &mut TestHost<State<TestStateApi>>) {
+        host.setup_mock_entrypoint(
+            TARGET,
+            OwnedEntrypointName::new_unchecked("receive".into()),
+            MockFn::new_v1(|parameter, amount, _balance, _state| {
+                claim_eq!(parameter.0, [1, 2, 3], "The stored parameter should be passed");
+                claim_eq!(amount, Amount::from_micro_ccd(10), "The stored amount should be sent");
+                Ok((false, ()))
+            }),
+        );
+    }
+
+    #[concordium_test]
+    /// Test that the quorum has to be reachable by the members.
+    fn test_init_invalid_quorum() {
+        for quorum in [0, 3] {
+            let parameter_bytes = to_bytes(&InitParameter {
+                members: vec![ALICE, BOB, ALICE],
+                voting_period: Duration::from_millis(VOTING_PERIOD),
+                timelock: Duration::from_millis(TIMELOCK),
+                quorum,
+            });
+            let mut ctx = TestInitContext::empty();
+            ctx.set_parameter(&parameter_bytes);
+            let res = dao_init(&ctx, &mut TestStateBuilder::new());
+            claim_eq!(res.map(|_| ()), Err(ContractError::InvalidQuorum.into()));
+        }
+    }
+
+    #[concordium_test]
+    /// Test the full flow of proposing, voting and executing a proposal.
+    fn test_full_flow() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        setup_target(&mut host);
+
+        let id = propose(&mut host, ALICE, &mut logger).expect_report("Proposing should pass");
+        vote(&mut host, ALICE, id, Vote::Yes, 0, &mut logger).expect_report("Voting should pass");
+        vote(&mut host, BOB, id, Vote::No, 50, &mut logger).expect_report("Voting should pass");
+        vote(&mut host, CAROL, id, Vote::Yes, VOTING_PERIOD, &mut logger)
+            .expect_report("Voting at the end should pass");
+
+        let res = execute(&mut host, id, VOTING_PERIOD, &mut logger);
+        claim_eq!(res, Err(ContractError::VotingActive), "Executing while voting should fail");
+        let res = execute(&mut host, id, UNLOCKED - 1, &mut logger);
+        claim_eq!(res, Err(ContractError::TimelockActive), "Executing while locked should fail");
+
+        execute(&mut host, id, UNLOCKED, &mut logger).expect_report("Executing should pass");
+        let proposal = host.state().proposals.get(&id).map(|p| p.clone()).unwrap_abort();
+        claim_eq!((proposal.yes_votes, proposal.no_votes, proposal.executed), (2, 1, true));
+        claim_eq!(host.self_balance(), Amount::from_micro_ccd(90), "The amount should be sent");
+        claim_eq!(logger.logs.len(), 5, "Propose, three votes and execute should be logged");
+        claim_eq!(
+            logger.logs[4],
+            to_bytes(&DaoEvent::Executed {
+                id
+            })
+        );
+
+        let res = execute(&mut host, id, UNLOCKED, &mut logger);
+        claim_eq!(res, Err(ContractError::AlreadyExecuted), "Executing twice should fail");
+    }
+
+    #[concordium_test]
+    /// Test that only members can propose and vote, once and within the voting
+    /// period.
+    fn test_voting_rules() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+
+        let res = propose(&mut host, DAVE, &mut logger);
+        claim_eq!(res, Err(ContractError::NotMember), "Only members can propose");
+        let id = propose(&mut host, ALICE, &mut logger).expect_report("Proposing should pass");
+
+        let res = vote(&mut host, DAVE, id, Vote::Yes, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::NotMember), "Only members can vote");
+        let res = vote(&mut host, ALICE, id + 1, Vote::Yes, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::UnknownProposal));
+        vote(&mut host, ALICE, id, Vote::Yes, 0, &mut logger).expect_report("Voting should pass");
+        let res = vote(&mut host, ALICE, id, Vote::No, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::AlreadyVoted), "Voting twice should fail");
+        let res = vote(&mut host, BOB, id, Vote::Yes, VOTING_PERIOD + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::VotingClosed), "Voting late should fail");
+    }
+
+    #[concordium_test]
+    /// Test that proposals without a majority or below the quorum do not pass.
+    fn test_not_passed() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        setup_target(&mut host);
+
+        let below_quorum = propose(&mut host, ALICE, &mut logger).expect_report("Propose");
+        vote(&mut host, ALICE, below_quorum, Vote::Yes, 0, &mut logger).expect_report("Vote");
+        let res = execute(&mut host, below_quorum, UNLOCKED, &mut logger);
+        claim_eq!(res, Err(ContractError::NotPassed), "A single yes is below the quorum");
+
+        let passed = propose(&mut host, ALICE, &mut logger).expect_report("Propose");
+        vote(&mut host, ALICE, passed, Vote::Yes, 0, &mut logger).expect_report("Vote");
+        vote(&mut host, BOB, passed, Vote::Yes, 0, &mut logger).expect_report("Vote");
+        vote(&mut host, CAROL, passed, Vote::No, 0, &mut logger).expect_report("Vote");
+        execute(&mut host, passed, UNLOCKED, &mut logger).expect_report("Two yes should pass");
+
+        let rejected = propose(&mut host, BOB, &mut logger).expect_report("Propose");
+        vote(&mut host, BOB, rejected, Vote::No, 0, &mut logger).expect_report("Vote");
+        vote(&mut host, CAROL, rejected, Vote::No, 0, &mut logger).expect_report("Vote");
+        let res = execute(&mut host, rejected, UNLOCKED, &mut logger);
+        claim_eq!(res, Err(ContractError::NotPassed), "A majority of no should not pass");
+    }
+
+    #[concordium_test]
+    /// Test that a failing call rejects the execution.
+    fn test_failing_call() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        host.setup_mock_entrypoint(
+            TARGET,
+            OwnedEntrypointName::new_unchecked("receive".into()),
+            MockFn::returning_err::<()>(CallContractError::Trap),
+        );
+
+        let id = propose(&mut host, ALICE, &mut logger).expect_report("Propose");
+        vote(&mut host, ALICE, id, Vote::Yes, 0, &mut logger).expect_report("Vote");
+        vote(&mut host, BOB, id, Vote::Yes, 0, &mut logger).expect_report("Vote");
+        let res = execute(&mut host, id, UNLOCKED, &mut logger);
+        claim_eq!(res, Err(ContractError::InvokeContractError), "A failing call should fail");
+    }
+}
@@ -0,0 +1,370 @@
+//! # Donation matching
+//!
+//! A sponsor sets up a matching campaign for a creator: every donation of a
+//! viewer to the creator made through `donate` is matched 1:1 from a matching
+//! pool, which the sponsor deposits using `fund`. The donation and the match
+//! are sent to the creator right away.
+//!
+//! The matching is bounded in two ways:
+//! - The matches of every donor add up to at most the per-donor cap, such that
+//!   a single donor cannot exhaust the pool.
+//! - The matches add up to at most the pool. When the pool is exhausted,
+//!   donations are still forwarded to the creator, but no longer matched.
+//!
+//! When the campaign has ended, no more donations are accepted, and the
+//! sponsor takes back what is left of the pool using `sweep`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The sponsor, depositing the pool.
+    sponsor:       AccountAddress,
+    /// The creator, receiving the donations and the matches.
+    creator:       AccountAddress,
+    /// The most every donor is matched in total.
+    per_donor_cap: Amount,
+    /// When the campaign ends.
+    end:           Timestamp,
+    /// What is left of the pool.
+    pool:          Amount,
+    /// The donations received so far.
+    total_donated: Amount,
+    /// The matches paid so far.
+    total_matched: Amount,
+    /// The matches paid so far to every donor.
+    matched:       StateMap<AccountAddress, Amount, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The creator, receiving the donations and the matches.
+    pub creator:       AccountAddress,
+    /// The most every donor is matched in total.
+    pub per_donor_cap: Amount,
+    /// When the campaign ends.
+    pub end:           Timestamp,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct CampaignView {
+    pub sponsor:       AccountAddress,
+    pub creator:       AccountAddress,
+    pub per_donor_cap: Amount,
+    pub end:           Timestamp,
+    pub pool:          Amount,
+    pub total_donated: Amount,
+    pub total_matched: Amount,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum MatchingEvent {
+    /// The sponsor deposited into the pool.
+    Fund(Amount),
+    /// A donation was made, and matched with the given amount.
+    Donate {
+        donor:   AccountAddress,
+        amount:  Amount,
+        matched: Amount,
+    },
+    /// The sponsor took back what was left of the pool.
+    Sweep(Amount),
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract donates.
+    OnlyAccount,
+    /// Raised when someone else than the sponsor funds or sweeps the pool.
+    Unauthorized,
+    /// Raised when the campaign ends in the past.
+    EndInThePast,
+    /// Raised when donating or funding after the campaign ended.
+    CampaignEnded,
+    /// Raised when sweeping before the campaign ended.
+    CampaignNotEnded,
+    /// Raised when donating nothing.
+    ZeroDonation,
+    /// Raised when there is nothing left to sweep.
+    NothingToSweep,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Initialize the campaign with an empty pool. The sender becomes the sponsor.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The campaign ends in the past.
+#[init(contract = "donation_matching", parameter = "InitParams", event = "MatchingEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    ensure!(params.end > ctx.metadata().slot_time(), ContractError::EndInThePast.into());
+    Ok(State {
+        sponsor:       ctx.init_origin(),
+        creator:       params.creator,
+        per_donor_cap: params.per_donor_cap,
+        end:           params.end,
+        pool:          Amount::zero(),
+        total_donated: Amount::zero(),
+        total_matched: Amount::zero(),
+        matched:       state_builder.new_map(),
+    })
+}
+
+/// Deposit the CCD sent along into the pool.
+///
+/// It rejects if:
+/// - The sender is not the sponsor.
+/// - The campaign has ended.
+/// - It fails to log the event.
+#[receive(
+    contract = "donation_matching",
+    name = "fund",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_fund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.sponsor), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() < state.end, ContractError::CampaignEnded);
+    state.pool += amount;
+    logger.log(&MatchingEvent::Fund(amount))?;
+    Ok(())
+}
+
+/// Donate the CCD sent along to the creator, matched from the pool up to the
+/// per-donor cap and what is left of the pool.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The campaign has ended.
+/// - The donation is zero.
+/// - It fails to log the event.
+#[receive(
+    contract = "donation_matching",
+    name = "donate",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_donate<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let donor = match ctx.sender() {
+        Address::Account(account_address) => account_address,
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+    };
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() < state.end, ContractError::CampaignEnded);
+    ensure!(amount > Amount::zero(), ContractError::ZeroDonation);
+
+    let mut donor_matched = state.matched.entry(donor).or_insert(Amount::zero());
+    let remaining_cap = state.per_donor_cap - (*donor_matched).min(state.per_donor_cap);
+    let matched = amount.min(remaining_cap).min(state.pool);
+    *donor_matched += matched;
+    drop(donor_matched);
+    state.pool -= matched;
+    state.total_donated += amount;
+    state.total_matched += matched;
+    let creator = state.creator;
+
+    // The transfer always succeeds, since the creator is an account, and the
+    // donation and the match are held by the contract.
+    host.invoke_transfer(&creator, amount + matched).unwrap_abort();
+    logger.log(&MatchingEvent::Donate {
+        donor,
+        amount,
+        matched,
+    })?;
+    Ok(())
+}
+
+/// Send what is left of the pool back to the sponsor, after the campaign has
+/// ended.
+///
+/// It rejects if:
+/// - The sender is not the sponsor.
+/// - The campaign has not ended.
+/// - Nothing is left of the pool.
+/// - It fails to log the event.
+#[receive(
+    contract = "donation_matching",
+    name = "sweep",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_sweep<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    let sponsor = state.sponsor;
+    ensure!(ctx.sender().matches_account(&sponsor), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() >= state.end, ContractError::CampaignNotEnded);
+    let pool = state.pool;
+    ensure!(pool > Amount::zero(), ContractError::NothingToSweep);
+    state.pool = Amount::zero();
+
+    // The transfer always succeeds, since the sponsor is an account and the
+    // pool is held by the contract.
+    host.invoke_transfer(&sponsor, pool).unwrap_abort();
+    logger.log(&MatchingEvent::Sweep(pool))?;
+    Ok(())
+}
+
+/// View the campaign.
+#[receive(contract = "donation_matching", name = "view", return_value = "CampaignView")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<CampaignView> {
+    let state = host.state();
+    Ok(CampaignView {
+        sponsor:       state.sponsor,
+        creator:       state.creator,
+        per_donor_cap: state.per_donor_cap,
+        end:           state.end,
+        pool:          state.pool,
+        total_donated: state.total_donated,
+        total_matched: state.total_matched,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const SPONSOR: AccountAddress = AccountAddress([0; 32]);
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const DONOR_1: AccountAddress = AccountAddress([2; 32]);
+    const DONOR_2: AccountAddress = AccountAddress([3; 32]);
+    const END: u64 = 10_000;
+
+    /// Create a campaign with a per-donor cap of 300 and a pool of 500.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            creator:       CREATOR,
+            per_donor_cap: Amount::from_micro_ccd(300),
+            end:           Timestamp::from_timestamp_millis(END),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(SPONSOR);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+
+        let pool = Amount::from_micro_ccd(500);
+        host.set_self_balance(pool);
+        contract_fund(&ctx_at(SPONSOR, 0), &mut host, pool, &mut TestLogger::init())
+            .expect_report("Funding should pass");
+        host
+    }
+
+    /// Donate, returning the match.
+    fn donate(
+        host: &mut TestHost<State<TestStateApi>>,
+        donor: AccountAddress,
+        micro_ccd: u64,
+    ) -> Amount {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        let mut logger = TestLogger::init();
+        contract_donate(&ctx_at(donor, 0), host, amount, &mut logger)
+            .expect_report("Donating should pass");
+        let event: MatchingEvent = from_bytes(&logger.logs[0]).expect_report("Invalid event");
+        match event {
+            MatchingEvent::Donate {
+                matched,
+                ..
+            } => matched,
+            _ => fail!("Expected a Donate event"),
+        }
+    }
+
+    #[concordium_test]
+    /// Test that donations are matched 1:1 up to the per-donor cap and the
+    /// pool.
+    fn test_donate_caps() {
+        let mut host = new_host();
+        claim_eq!(donate(&mut host, DONOR_1, 200), Amount::from_micro_ccd(200));
+        claim_eq!(donate(&mut host, DONOR_1, 200), Amount::from_micro_ccd(100), "Per-donor cap");
+        claim_eq!(donate(&mut host, DONOR_1, 200), Amount::zero(), "Per-donor cap reached");
+        claim_eq!(donate(&mut host, DONOR_2, 250), Amount::from_micro_ccd(200), "Pool exhausted");
+        claim_eq!(donate(&mut host, DONOR_2, 50), Amount::zero(), "Pool exhausted");
+
+        claim_eq!(host.state().pool, Amount::zero());
+        claim_eq!(host.state().total_donated, Amount::from_micro_ccd(900));
+        claim_eq!(host.state().total_matched, Amount::from_micro_ccd(500));
+        claim_eq!(host.self_balance(), Amount::zero(), "Everything was sent to the creator");
+    }
+
+    #[concordium_test]
+    /// Test that the sponsor sweeps what is left of the pool once the campaign
+    /// has ended, after which no donations are accepted.
+    fn test_sweep() {
+        let mut host = new_host();
+        donate(&mut host, DONOR_1, 100);
+        let res = contract_sweep(&ctx_at(SPONSOR, END - 1), &mut host, &mut TestLogger::init());
+        claim_eq!(res, Err(ContractError::CampaignNotEnded));
+        let res = contract_sweep(&ctx_at(DONOR_1, END), &mut host, &mut TestLogger::init());
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_sweep(&ctx_at(SPONSOR, END), &mut host, &mut TestLogger::init())
+            .expect_report("Sweeping should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (CREATOR, Amount::from_micro_ccd(200)),
+            (SPONSOR, Amount::from_micro_ccd(400))
+        ]);
+        let amount = Amount::from_micro_ccd(100);
+        let res =
+            contract_donate(&ctx_at(DONOR_1, END), &mut host, amount, &mut TestLogger::init());
+        claim_eq!(res, Err(ContractError::CampaignEnded));
+    }
+}
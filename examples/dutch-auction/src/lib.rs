@@ -0,0 +1,365 @@
+//! # Implementation of a Dutch auction smart contract
+//!
+//! The seller (the owner of the contract instance) offers a single item at an
+//! asking price which starts high and declines over time, from the start price
+//! down to the reserve price. The price can decline either linearly, or
+//! stepwise, where it only drops at the end of every step.
+//!
+//! Before the auction starts the asking price is the start price, and once the
+//! auction end is reached the asking price stays at the reserve price until
+//! the item is sold.
+//!
+//! The first account to invoke `buy` with at least the current asking price
+//! wins the item. The asking price is sent to the seller, and any amount paid
+//! in excess of the asking price is refunded to the buyer. After the item has
+//! been sold, all further attempts to buy it are rejected.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// How the asking price declines from the start price to the reserve price.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum Decline {
+    /// The price declines continuously, every millisecond.
+    Linear,
+    /// The price declines in steps of the given duration, i.e., it stays
+    /// constant during a step and drops when the step ends.
+    Stepwise(Duration),
+}
+
+/// The state of the smart contract.
+/// This state can be viewed by querying the node with the command
+/// `concordium-client contract invoke` using the `view` function as entrypoint.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct State {
+    /// The item to be sold (to be displayed by the front-end).
+    item:          String,
+    /// Time when the price starts declining.
+    start:         Timestamp,
+    /// Time when the price reaches the reserve price.
+    end:           Timestamp,
+    /// The asking price at the start of the auction.
+    start_price:   Amount,
+    /// The lowest asking price, reached at the end of the auction.
+    reserve_price: Amount,
+    /// How the asking price declines.
+    decline:       Decline,
+    /// The buyer of the item; The variant `None` represents that the item has
+    /// not been sold yet.
+    sold_to:       Option<AccountAddress>,
+}
+
+impl State {
+    /// Compute the asking price at the given time.
+    fn price_at(&self, now: Timestamp) -> Amount {
+        if now <= self.start {
+            return self.start_price;
+        }
+        if now >= self.end {
+            return self.reserve_price;
+        }
+        let total = self.end.timestamp_millis() - self.start.timestamp_millis();
+        let mut elapsed = now.timestamp_millis() - self.start.timestamp_millis();
+        if let Decline::Stepwise(step) = self.decline {
+            // Only count completed steps.
+            elapsed -= elapsed % step.millis();
+        }
+        let range = self.start_price - self.reserve_price;
+        // The auction has started and not ended, so `total` is positive and larger
+        // than `elapsed`, and the result is less than `range`. Unwrapping is safe.
+        let decline = range.mul_ratio(elapsed, total).unwrap_abort();
+        self.start_price - decline
+    }
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The item to be sold.
+    item:          String,
+    /// Time when the price starts declining, using the RFC 3339 format (https://tools.ietf.org/html/rfc3339)
+    start:         Timestamp,
+    /// Time when the price reaches the reserve price, using the RFC 3339
+    /// format.
+    end:           Timestamp,
+    /// The asking price at the start of the auction.
+    start_price:   Amount,
+    /// The lowest asking price, reached at the end of the auction.
+    reserve_price: Amount,
+    /// How the asking price declines.
+    decline:       Decline,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a start after the end, a reserve price
+    /// above the start price, or a step of zero.
+    InvalidSchedule,
+    /// Raised when a contract tries to buy; Only accounts are allowed to buy.
+    OnlyAccount,
+    /// Raised when trying to buy before the auction has started.
+    NotStarted,
+    /// Raised when the amount paid is below the current asking price.
+    BelowPrice,
+    /// Raised when trying to buy an item which has already been sold.
+    AlreadySold,
+}
+
+/// Init function that creates a new Dutch auction.
+#[init(contract = "dutch_auction", parameter = "InitParameter")]
+fn dutch_auction_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+) -> InitResult<State> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.start < parameter.end, ContractError::InvalidSchedule.into());
+    ensure!(
+        parameter.reserve_price <= parameter.start_price,
+        ContractError::InvalidSchedule.into()
+    );
+    if let Decline::Stepwise(step) = parameter.decline {
+        ensure!(step.millis() > 0, ContractError::InvalidSchedule.into());
+    }
+    Ok(State {
+        item:          parameter.item,
+        start:         parameter.start,
+        end:           parameter.end,
+        start_price:   parameter.start_price,
+        reserve_price: parameter.reserve_price,
+        decline:       parameter.decline,
+        sold_to:       None,
+    })
+}
+
+/// Buy the item at the current asking price. The asking price is sent to the
+/// seller and any overpayment is refunded to the buyer.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The item has already been sold.
+/// - The auction has not started yet.
+/// - The amount is below the current asking price.
+#[receive(contract = "dutch_auction", name = "buy", payable, mutable, error = "ContractError")]
+fn dutch_auction_buy<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+    amount: Amount,
+) -> Result<(), ContractError> {
+    let buyer = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+
+    let state = host.state();
+    ensure!(state.sold_to.is_none(), ContractError::AlreadySold);
+
+    let slot_time = ctx.metadata().slot_time();
+    ensure!(slot_time >= state.start, ContractError::NotStarted);
+
+    let price = state.price_at(slot_time);
+    ensure!(amount >= price, ContractError::BelowPrice);
+
+    host.state_mut().sold_to = Some(buyer);
+
+    // These transfers (given enough NRG of course) always succeed, since both
+    // the seller and the buyer are existing accounts, and the contract has just
+    // received `amount`.
+    host.invoke_transfer(&ctx.owner(), price).unwrap_abort();
+    let refund = amount - price;
+    if refund > Amount::zero() {
+        host.invoke_transfer(&buyer, refund).unwrap_abort();
+    }
+    Ok(())
+}
+
+/// View function that returns the current asking price.
+#[receive(contract = "dutch_auction", name = "currentPrice", return_value = "Amount")]
+fn dutch_auction_current_price<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    Ok(host.state().price_at(ctx.metadata().slot_time()))
+}
+
+/// View function that returns the content of the state.
+#[receive(contract = "dutch_auction", name = "view", return_value = "State")]
+fn dutch_auction_view<'a, 'b, S: HasStateApi>(
+    _ctx: &'a impl HasReceiveContext,
+    host: &'b impl HasHost<State, StateApiType = S>,
+) -> ReceiveResult<&'b State> {
+    Ok(host.state())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const SELLER: AccountAddress = AccountAddress([0; 32]);
+    const BUYER: AccountAddress = AccountAddress([1; 32]);
+    const START: u64 = 1_000;
+    const END: u64 = 11_000;
+    const START_PRICE: Amount = Amount {
+        micro_ccd: 10_000,
+    };
+    const RESERVE_PRICE: Amount = Amount {
+        micro_ccd: 2_000,
+    };
+
+    fn parameter(decline: Decline) -> InitParameter {
+        InitParameter {
+            item: "Sunflowers by Van Gogh".into(),
+            start: Timestamp::from_timestamp_millis(START),
+            end: Timestamp::from_timestamp_millis(END),
+            start_price: START_PRICE,
+            reserve_price: RESERVE_PRICE,
+            decline,
+        }
+    }
+
+    fn initialize(parameter: &InitParameter) -> InitResult<State> {
+        let parameter_bytes = to_bytes(parameter);
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        dutch_auction_init(&ctx, &mut TestStateBuilder::new())
+    }
+
+    fn new_host(decline: Decline) -> TestHost<State> {
+        let state = initialize(&parameter(decline)).expect_report("Initialization should pass");
+        TestHost::new(state, TestStateBuilder::new())
+    }
+
+    fn ctx_at<'a>(slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_at(BUYER, slot_time);
+        ctx.set_owner(SELLER);
+        ctx
+    }
+
+    fn price_at(host: &TestHost<State>, slot_time: u64) -> Amount {
+        dutch_auction_current_price(&ctx_at(slot_time), host)
+            .expect_report("Querying the price should pass")
+    }
+
+    #[concordium_test]
+    /// Test that initialization rejects an inverted schedule, a reserve above
+    /// the start price and a zero step.
+    fn test_init_invalid_schedule() {
+        let mut inverted = parameter(Decline::Linear);
+        inverted.end = Timestamp::from_timestamp_millis(START);
+        claim_eq!(initialize(&inverted).map(|_| ()), Err(ContractError::InvalidSchedule.into()));
+
+        let mut reserve_too_high = parameter(Decline::Linear);
+        reserve_too_high.reserve_price = START_PRICE + Amount::from_micro_ccd(1);
+        claim_eq!(
+            initialize(&reserve_too_high).map(|_| ()),
+            Err(ContractError::InvalidSchedule.into())
+        );
+
+        let zero_step = parameter(Decline::Stepwise(Duration::from_millis(0)));
+        claim_eq!(initialize(&zero_step).map(|_| ()), Err(ContractError::InvalidSchedule.into()));
+    }
+
+    #[concordium_test]
+    /// Test the linear price at, before and after the start and end, and in
+    /// between.
+    fn test_linear_price() {
+        let host = new_host(Decline::Linear);
+
+        claim_eq!(price_at(&host, 0), START_PRICE, "Before the start the price is the start price");
+        claim_eq!(price_at(&host, START), START_PRICE, "At the start the price is the start price");
+        claim_eq!(price_at(&host, START + 1), START_PRICE, "The decline is rounded down");
+        claim_eq!(price_at(&host, START + 5), Amount::from_micro_ccd(9_996));
+        claim_eq!(price_at(&host, 6_000), Amount::from_micro_ccd(6_000), "Halfway price");
+        claim_eq!(price_at(&host, END - 1), Amount::from_micro_ccd(2_001));
+        claim_eq!(price_at(&host, END), RESERVE_PRICE, "At the end the price is the reserve");
+        claim_eq!(price_at(&host, END + 1_000), RESERVE_PRICE, "The price stays at the reserve");
+    }
+
+    #[concordium_test]
+    /// Test that the stepwise price only drops when a step is completed.
+    fn test_stepwise_price() {
+        let host = new_host(Decline::Stepwise(Duration::from_millis(2_500)));
+
+        claim_eq!(price_at(&host, START), START_PRICE);
+        claim_eq!(price_at(&host, START + 2_499), START_PRICE, "The first step is not completed");
+        claim_eq!(price_at(&host, START + 2_500), Amount::from_micro_ccd(8_000));
+        claim_eq!(price_at(&host, START + 7_499), Amount::from_micro_ccd(6_000));
+        claim_eq!(price_at(&host, START + 7_500), Amount::from_micro_ccd(4_000));
+        claim_eq!(price_at(&host, END - 1), Amount::from_micro_ccd(4_000));
+        claim_eq!(price_at(&host, END), RESERVE_PRICE);
+    }
+
+    #[concordium_test]
+    /// Test that the price does not overflow with large amounts and durations.
+    fn test_price_large_values() {
+        let mut parameter = parameter(Decline::Linear);
+        parameter.start = Timestamp::from_timestamp_millis(0);
+        parameter.end = Timestamp::from_timestamp_millis(u64::MAX);
+        parameter.start_price = Amount::from_micro_ccd(u64::MAX);
+        parameter.reserve_price = Amount::zero();
+        let state = initialize(&parameter).expect_report("Initialization should pass");
+        let host = TestHost::new(state, TestStateBuilder::new());
+
+        claim_eq!(price_at(&host, u64::MAX / 2), Amount::from_micro_ccd(u64::MAX / 2 + 1));
+    }
+
+    #[concordium_test]
+    /// Test that buying above the price pays the seller and refunds the excess.
+    fn test_buy_refunds_overpayment() {
+        let mut host = new_host(Decline::Linear);
+        let amount = Amount::from_micro_ccd(7_000);
+        host.set_self_balance(amount);
+
+        dutch_auction_buy(&ctx_at(6_000), &mut host, amount).expect_report("Buying should pass");
+
+        claim_eq!(host.state().sold_to, Some(BUYER), "The buyer should get the item");
+        claim_eq!(
+            host.get_transfers(),
+            [(SELLER, Amount::from_micro_ccd(6_000)), (BUYER, Amount::from_micro_ccd(1_000))],
+            "The seller should get the price and the buyer the excess"
+        );
+        claim_eq!(host.self_balance(), Amount::zero(), "The contract should keep nothing");
+    }
+
+    #[concordium_test]
+    /// Test that buying at exactly the price does not refund anything.
+    fn test_buy_exact_price() {
+        let mut host = new_host(Decline::Linear);
+        host.set_self_balance(RESERVE_PRICE);
+
+        dutch_auction_buy(&ctx_at(END), &mut host, RESERVE_PRICE)
+            .expect_report("Buying at the reserve should pass");
+
+        claim_eq!(host.get_transfers(), [(SELLER, RESERVE_PRICE)]);
+    }
+
+    #[concordium_test]
+    /// Test that buying below the price, before the start or after the item
+    /// was sold is rejected.
+    fn test_buy_rejected() {
+        let mut host = new_host(Decline::Linear);
+
+        let below = Amount::from_micro_ccd(5_999);
+        host.set_self_balance(below);
+        let res = dutch_auction_buy(&ctx_at(6_000), &mut host, below);
+        claim_eq!(res, Err(ContractError::BelowPrice), "Buying below the price should fail");
+
+        host.set_self_balance(START_PRICE);
+        let res = dutch_auction_buy(&ctx_at(START - 1), &mut host, START_PRICE);
+        claim_eq!(res, Err(ContractError::NotStarted), "Buying before the start should fail");
+
+        dutch_auction_buy(&ctx_at(START), &mut host, START_PRICE)
+            .expect_report("Buying at the start should pass");
+
+        host.set_self_balance(START_PRICE);
+        let res = dutch_auction_buy(&ctx_at(END), &mut host, START_PRICE);
+        claim_eq!(res, Err(ContractError::AlreadySold), "Buying a sold item should fail");
+        claim_eq!(host.get_transfers(), [(SELLER, START_PRICE)]);
+    }
+}
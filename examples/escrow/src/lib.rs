@@ -0,0 +1,427 @@
+//! # Implementation of an escrow smart contract with an arbiter
+//!
+//! An instance of this contract holds the payment for a single deal between a
+//! buyer and a seller, with a third party acting as the arbiter. The parties
+//! are fixed when the contract is initialized.
+//!
+//! The escrow goes through the following states:
+//! - `AwaitingDeposit`: The buyer invokes `deposit` with the payment, which
+//!   starts the release timeout.
+//! - `Funded`: The buyer can `release` the payment to the seller, e.g., once
+//!   the goods have been received, and the seller can `refund` the buyer.
+//!   Either party can raise a `dispute` before the timeout. If nobody does
+//!   anything before the timeout, anyone can invoke `claimTimeout` to release
+//!   the payment to the seller.
+//! - `Disputed`: Only the arbiter can `resolve` the dispute, by either
+//!   releasing the payment to the seller or refunding the buyer.
+//! - `Released` or `Refunded`: The escrow is closed.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The states of the escrow.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowState {
+    /// Waiting for the buyer to deposit the payment.
+    AwaitingDeposit,
+    /// The payment is deposited. It is released to the seller if nobody acts
+    /// before the deadline.
+    Funded {
+        /// The last time a dispute can be raised.
+        deadline: Timestamp,
+    },
+    /// The buyer or the seller has raised a dispute, which the arbiter has to
+    /// resolve.
+    Disputed,
+    /// The payment was released to the seller.
+    Released,
+    /// The payment was refunded to the buyer.
+    Refunded,
+}
+
+/// The decision of the arbiter on a dispute.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Release the payment to the seller.
+    ReleaseToSeller,
+    /// Refund the payment to the buyer.
+    RefundBuyer,
+}
+
+/// The state of the smart contract.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct State {
+    /// The account paying for the deal.
+    buyer:           AccountAddress,
+    /// The account receiving the payment.
+    seller:          AccountAddress,
+    /// The account resolving disputes.
+    arbiter:         AccountAddress,
+    /// How long after the deposit the payment is released automatically.
+    release_timeout: Duration,
+    /// The current state of the escrow.
+    state:           EscrowState,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The account paying for the deal.
+    buyer:           AccountAddress,
+    /// The account receiving the payment.
+    seller:          AccountAddress,
+    /// The account resolving disputes.
+    arbiter:         AccountAddress,
+    /// How long after the deposit the payment is released automatically.
+    release_timeout: Duration,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when the buyer, seller and arbiter are not three different
+    /// accounts.
+    InvalidParties,
+    /// Raised when the sender is not allowed to perform the action.
+    Unauthorized,
+    /// Raised when the action is not possible in the current state.
+    InvalidState,
+    /// Raised when depositing without a payment.
+    ZeroDeposit,
+    /// Raised when the deadline is too far in the future to be represented.
+    InvalidTimeout,
+    /// Raised when disputing after the deadline.
+    DeadlinePassed,
+    /// Raised when claiming the payment before the deadline.
+    DeadlineNotReached,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+impl State {
+    /// Ensure the escrow is funded and return the deadline.
+    fn funded_deadline(&self) -> ContractResult<Timestamp> {
+        match self.state {
+            EscrowState::Funded {
+                deadline,
+            } => Ok(deadline),
+            _ => bail!(ContractError::InvalidState),
+        }
+    }
+}
+
+/// Pay out the whole balance to the receiver and move to the final state.
+fn pay_out<S: HasStateApi>(
+    host: &mut impl HasHost<State, StateApiType = S>,
+    receiver: AccountAddress,
+    final_state: EscrowState,
+) {
+    host.state_mut().state = final_state;
+    let balance = host.self_balance();
+    // This transfer (given enough NRG of course) always succeeds, since the
+    // buyer and seller are existing accounts, and the contract holds the
+    // payment.
+    host.invoke_transfer(&receiver, balance).unwrap_abort();
+}
+
+/// Init function that creates a new escrow.
+#[init(contract = "escrow", parameter = "InitParameter")]
+fn escrow_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+) -> InitResult<State> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(
+        parameter.buyer != parameter.seller
+            && parameter.arbiter != parameter.buyer
+            && parameter.arbiter != parameter.seller,
+        ContractError::InvalidParties.into()
+    );
+    Ok(State {
+        buyer:           parameter.buyer,
+        seller:          parameter.seller,
+        arbiter:         parameter.arbiter,
+        release_timeout: parameter.release_timeout,
+        state:           EscrowState::AwaitingDeposit,
+    })
+}
+
+/// Deposit the payment. Only the buyer can deposit, and only once.
+#[receive(contract = "escrow", name = "deposit", payable, mutable, error = "ContractError")]
+fn escrow_deposit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.buyer), ContractError::Unauthorized);
+    ensure_eq!(state.state, EscrowState::AwaitingDeposit, ContractError::InvalidState);
+    ensure!(amount > Amount::zero(), ContractError::ZeroDeposit);
+    let deadline = ctx
+        .metadata()
+        .slot_time()
+        .checked_add(state.release_timeout)
+        .ok_or(ContractError::InvalidTimeout)?;
+    state.state = EscrowState::Funded {
+        deadline,
+    };
+    Ok(())
+}
+
+/// Release the payment to the seller. Only the buyer can release.
+#[receive(contract = "escrow", name = "release", mutable, error = "ContractError")]
+fn escrow_release<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state();
+    ensure!(ctx.sender().matches_account(&state.buyer), ContractError::Unauthorized);
+    state.funded_deadline()?;
+    let seller = state.seller;
+    pay_out(host, seller, EscrowState::Released);
+    Ok(())
+}
+
+/// Refund the payment to the buyer. Only the seller can refund.
+#[receive(contract = "escrow", name = "refund", mutable, error = "ContractError")]
+fn escrow_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state();
+    ensure!(ctx.sender().matches_account(&state.seller), ContractError::Unauthorized);
+    state.funded_deadline()?;
+    let buyer = state.buyer;
+    pay_out(host, buyer, EscrowState::Refunded);
+    Ok(())
+}
+
+/// Raise a dispute. Only the buyer and the seller can raise a dispute, and
+/// only before the deadline.
+#[receive(contract = "escrow", name = "dispute", mutable, error = "ContractError")]
+fn escrow_dispute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    let sender = ctx.sender();
+    ensure!(
+        sender.matches_account(&state.buyer) || sender.matches_account(&state.seller),
+        ContractError::Unauthorized
+    );
+    let deadline = state.funded_deadline()?;
+    ensure!(ctx.metadata().slot_time() <= deadline, ContractError::DeadlinePassed);
+    state.state = EscrowState::Disputed;
+    Ok(())
+}
+
+/// Resolve a dispute, either releasing the payment to the seller or refunding
+/// the buyer. Only the arbiter can resolve disputes.
+#[receive(
+    contract = "escrow",
+    name = "resolve",
+    parameter = "Resolution",
+    mutable,
+    error = "ContractError"
+)]
+fn escrow_resolve<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> ContractResult<()> {
+    let resolution: Resolution = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    ensure!(ctx.sender().matches_account(&state.arbiter), ContractError::Unauthorized);
+    ensure_eq!(state.state, EscrowState::Disputed, ContractError::InvalidState);
+    match resolution {
+        Resolution::ReleaseToSeller => {
+            let seller = state.seller;
+            pay_out(host, seller, EscrowState::Released)
+        }
+        Resolution::RefundBuyer => {
+            let buyer = state.buyer;
+            pay_out(host, buyer, EscrowState::Refunded)
+        }
+    }
+    Ok(())
+}
+
+/// Release the payment to the seller after the deadline has passed without a
+/// dispute. Anyone can invoke this.
+#[receive(contract = "escrow", name = "claimTimeout", mutable, error = "ContractError")]
+fn escrow_claim_timeout<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state();
+    let deadline = state.funded_deadline()?;
+    ensure!(ctx.metadata().slot_time() > deadline, ContractError::DeadlineNotReached);
+    let seller = state.seller;
+    pay_out(host, seller, EscrowState::Released);
+    Ok(())
+}
+
+/// View function that returns the content of the state.
+#[receive(contract = "escrow", name = "view", return_value = "State")]
+fn escrow_view<'a, 'b, S: HasStateApi>(
+    _ctx: &'a impl HasReceiveContext,
+    host: &'b impl HasHost<State, StateApiType = S>,
+) -> ReceiveResult<&'b State> {
+    Ok(host.state())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const BUYER: AccountAddress = AccountAddress([0; 32]);
+    const SELLER: AccountAddress = AccountAddress([1; 32]);
+    const ARBITER: AccountAddress = AccountAddress([2; 32]);
+    const OTHER: AccountAddress = AccountAddress([3; 32]);
+    const DEPOSIT_TIME: u64 = 1_000;
+    const TIMEOUT: u64 = 500;
+    const PAYMENT: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    fn parameter() -> InitParameter {
+        InitParameter {
+            buyer:           BUYER,
+            seller:          SELLER,
+            arbiter:         ARBITER,
+            release_timeout: Duration::from_millis(TIMEOUT),
+        }
+    }
+
+    fn initialize(parameter: &InitParameter) -> InitResult<State> {
+        let parameter_bytes = to_bytes(parameter);
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        escrow_init(&ctx, &mut TestStateBuilder::new())
+    }
+
+    /// Create an escrow in which the buyer has deposited the payment.
+    fn funded_host() -> TestHost<State> {
+        let state = initialize(&parameter()).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, TestStateBuilder::new());
+        host.set_self_balance(PAYMENT);
+        escrow_deposit(&ctx_at(BUYER, DEPOSIT_TIME), &mut host, PAYMENT)
+            .expect_report("Depositing should pass");
+        host
+    }
+
+    fn resolve(
+        host: &mut TestHost<State>,
+        sender: AccountAddress,
+        resolution: Resolution,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&resolution);
+        let mut ctx = ctx_at(sender, DEPOSIT_TIME);
+        ctx.set_parameter(&parameter_bytes);
+        escrow_resolve(&ctx, host)
+    }
+
+    #[concordium_test]
+    /// Test that the parties have to be different accounts.
+    fn test_init_invalid_parties() {
+        let mut parameter = parameter();
+        parameter.arbiter = SELLER;
+        claim_eq!(initialize(&parameter).map(|_| ()), Err(ContractError::InvalidParties.into()));
+    }
+
+    #[concordium_test]
+    /// Test that only the buyer can deposit, only once, and that the deadline
+    /// is set from the deposit time.
+    fn test_deposit() {
+        let state = initialize(&parameter()).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, TestStateBuilder::new());
+        host.set_self_balance(PAYMENT);
+
+        let res = escrow_deposit(&ctx_at(SELLER, DEPOSIT_TIME), &mut host, PAYMENT);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the buyer can deposit");
+        let res = escrow_deposit(&ctx_at(BUYER, DEPOSIT_TIME), &mut host, Amount::zero());
+        claim_eq!(res, Err(ContractError::ZeroDeposit), "The deposit cannot be zero");
+
+        escrow_deposit(&ctx_at(BUYER, DEPOSIT_TIME), &mut host, PAYMENT)
+            .expect_report("Depositing should pass");
+        claim_eq!(host.state().state, EscrowState::Funded {
+            deadline: Timestamp::from_timestamp_millis(DEPOSIT_TIME + TIMEOUT),
+        });
+
+        let res = escrow_deposit(&ctx_at(BUYER, DEPOSIT_TIME), &mut host, PAYMENT);
+        claim_eq!(res, Err(ContractError::InvalidState), "Depositing twice should fail");
+    }
+
+    #[concordium_test]
+    /// Test that the buyer can release to the seller, and the seller can
+    /// refund the buyer, but not the other way around.
+    fn test_release_and_refund() {
+        let mut host = funded_host();
+        let res = escrow_release(&ctx_at(SELLER, DEPOSIT_TIME), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "The seller cannot release");
+        escrow_release(&ctx_at(BUYER, DEPOSIT_TIME), &mut host).expect_report("Release failed");
+        claim_eq!(host.get_transfers(), [(SELLER, PAYMENT)]);
+        claim_eq!(host.state().state, EscrowState::Released);
+
+        let res = escrow_refund(&ctx_at(SELLER, DEPOSIT_TIME), &mut host);
+        claim_eq!(res, Err(ContractError::InvalidState), "A closed escrow cannot be refunded");
+
+        let mut host = funded_host();
+        let res = escrow_refund(&ctx_at(BUYER, DEPOSIT_TIME), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "The buyer cannot refund");
+        escrow_refund(&ctx_at(SELLER, DEPOSIT_TIME), &mut host).expect_report("Refund failed");
+        claim_eq!(host.get_transfers(), [(BUYER, PAYMENT)]);
+        claim_eq!(host.state().state, EscrowState::Refunded);
+    }
+
+    #[concordium_test]
+    /// Test that a dispute blocks releasing and the timeout, and that only the
+    /// arbiter can resolve it.
+    fn test_dispute_resolved_by_arbiter() {
+        let mut host = funded_host();
+        let res = escrow_dispute(&ctx_at(ARBITER, DEPOSIT_TIME), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the parties can dispute");
+        escrow_dispute(&ctx_at(SELLER, DEPOSIT_TIME + TIMEOUT), &mut host)
+            .expect_report("Disputing at the deadline should pass");
+
+        let res = escrow_release(&ctx_at(BUYER, DEPOSIT_TIME), &mut host);
+        claim_eq!(res, Err(ContractError::InvalidState), "A disputed escrow cannot be released");
+        let res = escrow_claim_timeout(&ctx_at(SELLER, DEPOSIT_TIME + TIMEOUT + 1), &mut host);
+        claim_eq!(res, Err(ContractError::InvalidState), "The timeout does not apply");
+        let res = resolve(&mut host, BUYER, Resolution::RefundBuyer);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the arbiter can resolve");
+
+        resolve(&mut host, ARBITER, Resolution::RefundBuyer).expect_report("Resolving failed");
+        claim_eq!(host.get_transfers(), [(BUYER, PAYMENT)]);
+        claim_eq!(host.state().state, EscrowState::Refunded);
+
+        let mut host = funded_host();
+        escrow_dispute(&ctx_at(BUYER, DEPOSIT_TIME), &mut host).expect_report("Dispute failed");
+        resolve(&mut host, ARBITER, Resolution::ReleaseToSeller).expect_report("Resolving failed");
+        claim_eq!(host.get_transfers(), [(SELLER, PAYMENT)]);
+        claim_eq!(host.state().state, EscrowState::Released);
+    }
+
+    #[concordium_test]
+    /// Test that the payment is released to the seller after the deadline, and
+    /// that disputes are no longer possible then.
+    fn test_timeout() {
+        let mut host = funded_host();
+        let res = resolve(&mut host, ARBITER, Resolution::RefundBuyer);
+        claim_eq!(res, Err(ContractError::InvalidState), "Only disputes can be resolved");
+        let res = escrow_claim_timeout(&ctx_at(OTHER, DEPOSIT_TIME + TIMEOUT), &mut host);
+        claim_eq!(res, Err(ContractError::DeadlineNotReached), "Claiming too early should fail");
+        let res = escrow_dispute(&ctx_at(BUYER, DEPOSIT_TIME + TIMEOUT + 1), &mut host);
+        claim_eq!(res, Err(ContractError::DeadlinePassed), "Disputing too late should fail");
+
+        escrow_claim_timeout(&ctx_at(OTHER, DEPOSIT_TIME + TIMEOUT + 1), &mut host)
+            .expect_report("Claiming after the deadline should pass");
+        claim_eq!(host.get_transfers(), [(SELLER, PAYMENT)]);
+        claim_eq!(host.state().state, EscrowState::Released);
+    }
+}
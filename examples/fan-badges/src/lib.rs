@@ -0,0 +1,659 @@
+//! Soulbound fan badges using the Concordium Token Standard CIS2.
+//!
+//! # Description
+//! An instance of this smart contract awards fan badges to the viewers of a
+//! streamer. The contract owner creates the badges using `createBadge`, each
+//! identified by a token ID and with the URL of its metadata, and awards them
+//! using `mint`, e.g. when a viewer reaches a donation milestone. An address
+//! holds at most one of every badge. The owner can allow other addresses, like
+//! contracts awarding badges automatically, to mint as well using `setMinter`.
+//!
+//! The badges are soulbound: they cannot change hands. Every transfer is
+//! rejected, and so is enabling operators, since operators would only be
+//! allowed to transfer. The only ways the balances change are minting by the
+//! contract owner or a minter and burning, which both the holder and the
+//! contract owner can do. The holder can thereby renounce a badge, and the
+//! owner can revoke it.
+//!
+//! Note: The word 'address' refers to either an account address or a
+//! contract address.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// List of supported standards by this contract address.
+const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
+    [CIS0_STANDARD_IDENTIFIER, CIS2_STANDARD_IDENTIFIER];
+
+// Types
+
+/// Contract token ID type, identifying a badge.
+type ContractTokenId = TokenIdU32;
+
+/// Contract token amount.
+/// Since an address holds at most one of every badge, it is fine to use a small
+/// type for representing token amounts.
+type ContractTokenAmount = TokenAmountU8;
+
+/// The parameter for the contract function `createBadge`.
+#[derive(Serialize, SchemaType)]
+struct CreateBadgeParams {
+    /// The token ID of the new badge.
+    badge:        ContractTokenId,
+    /// The metadata URL of the new badge.
+    metadata_url: MetadataUrl,
+}
+
+/// The parameter for the contract function `mint`, which awards a badge to a
+/// number of addresses.
+#[derive(Serialize, SchemaType)]
+struct MintParams {
+    /// The badge to award.
+    badge:   ContractTokenId,
+    /// The addresses to award the badge to.
+    holders: Vec<Address>,
+}
+
+/// The parameter for the contract function `setMinter`.
+#[derive(Serialize, SchemaType)]
+struct SetMinterParams {
+    /// The address to allow or disallow minting.
+    minter:  Address,
+    /// Whether the address is allowed to mint.
+    allowed: bool,
+}
+
+/// The parameter for the contract function `burn`.
+#[derive(Serialize, SchemaType)]
+struct BurnParams {
+    /// The badge to burn.
+    badge:  ContractTokenId,
+    /// The address holding the badge.
+    holder: Address,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// All of the badges, with the metadata URL of each badge.
+    badges:   StateMap<ContractTokenId, MetadataUrl, S>,
+    /// The badges held by each address.
+    holdings: StateSet<(ContractTokenId, Address), S>,
+    /// The addresses allowed to mint besides the contract owner.
+    minters:  StateSet<Address, S>,
+}
+
+/// The custom errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum CustomContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// A badge with the given token ID already exists.
+    TokenIdAlreadyExists,
+    /// The address already holds the badge.
+    AlreadyHolds,
+    /// Badges cannot be transferred, and operators cannot be enabled.
+    NonTransferable,
+}
+
+/// Wrapping the custom errors in a type with CIS2 errors.
+type ContractError = Cis2Error<CustomContractError>;
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to CustomContractError.
+impl From<LogError> for CustomContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping CustomContractError to ContractError
+impl From<CustomContractError> for ContractError {
+    fn from(c: CustomContractError) -> Self { Cis2Error::Custom(c) }
+}
+
+// Functions for creating, updating and querying the contract state.
+impl<S: HasStateApi> State<S> {
+    /// Creates a new state with no badges.
+    fn empty(state_builder: &mut StateBuilder<S>) -> Self {
+        State {
+            badges:   state_builder.new_map(),
+            holdings: state_builder.new_set(),
+            minters:  state_builder.new_set(),
+        }
+    }
+
+    /// Check that the badge currently exists in this contract.
+    #[inline(always)]
+    fn contains_badge(&self, badge: &ContractTokenId) -> bool { self.badges.get(badge).is_some() }
+
+    /// Award a badge to an address.
+    /// Results in an error if the badge does not exist, or the address already
+    /// holds it.
+    fn mint(&mut self, badge: ContractTokenId, holder: Address) -> ContractResult<()> {
+        ensure!(self.contains_badge(&badge), ContractError::InvalidTokenId);
+        ensure!(self.holdings.insert((badge, holder)), CustomContractError::AlreadyHolds.into());
+        Ok(())
+    }
+
+    /// Burn a badge held by an address.
+    /// Results in an error if the badge does not exist, or the address does
+    /// not hold it.
+    fn burn(&mut self, badge: ContractTokenId, holder: Address) -> ContractResult<()> {
+        ensure!(self.contains_badge(&badge), ContractError::InvalidTokenId);
+        ensure!(self.holdings.remove(&(badge, holder)), ContractError::InsufficientFunds);
+        Ok(())
+    }
+
+    /// Get the current balance of a badge for a given address, which is
+    /// either 1 or 0.
+    /// Results in an error if the badge does not exist in the state.
+    fn balance(
+        &self,
+        badge: &ContractTokenId,
+        address: &Address,
+    ) -> ContractResult<ContractTokenAmount> {
+        ensure!(self.contains_badge(badge), ContractError::InvalidTokenId);
+        Ok(u8::from(self.holdings.contains(&(*badge, *address))).into())
+    }
+}
+
+// Contract functions
+
+/// Initialize contract instance with no badges.
+#[init(contract = "fan_badges", event = "Cis2Event<ContractTokenId, ContractTokenAmount>")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    // Construct the initial contract state.
+    Ok(State::empty(state_builder))
+}
+
+/// Create a new badge with the URL of its metadata.
+/// Can only be called by the contract owner.
+/// Logs a `TokenMetadata` event.
+///
+/// It rejects if:
+/// - The sender is not the contract instance owner.
+/// - It fails to parse the parameter.
+/// - The badge already exists.
+/// - Fails to log TokenMetadata event.
+#[receive(
+    contract = "fan_badges",
+    name = "createBadge",
+    parameter = "CreateBadgeParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_create_badge<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    // Parse the parameter.
+    let params: CreateBadgeParams = ctx.parameter_cursor().get()?;
+
+    host.state_mut()
+        .badges
+        .entry(params.badge)
+        .vacant_or(CustomContractError::TokenIdAlreadyExists)?
+        .insert(params.metadata_url.clone());
+
+    // Metadata URL for the badge.
+    logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+        token_id:     params.badge,
+        metadata_url: params.metadata_url,
+    }))?;
+    Ok(())
+}
+
+/// Allow or disallow an address to mint.
+/// Can only be called by the contract owner.
+///
+/// It rejects if:
+/// - The sender is not the contract instance owner.
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "fan_badges",
+    name = "setMinter",
+    parameter = "SetMinterParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_minter<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    // Parse the parameter.
+    let params: SetMinterParams = ctx.parameter_cursor().get()?;
+    if params.allowed {
+        host.state_mut().minters.insert(params.minter);
+    } else {
+        host.state_mut().minters.remove(&params.minter);
+    }
+    Ok(())
+}
+
+/// Award a badge to a number of addresses.
+/// Can only be called by the contract owner or a minter.
+/// Logs a `Mint` event for each address.
+///
+/// It rejects if:
+/// - The sender is neither the contract instance owner nor a minter.
+/// - It fails to parse the parameter.
+/// - The badge does not exist.
+/// - Any of the addresses already holds the badge.
+/// - Fails to log Mint event.
+#[receive(
+    contract = "fan_badges",
+    name = "mint",
+    parameter = "MintParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_mint<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+    ensure!(
+        sender.matches_account(&ctx.owner()) || host.state().minters.contains(&sender),
+        ContractError::Unauthorized
+    );
+    // Parse the parameter.
+    let params: MintParams = ctx.parameter_cursor().get()?;
+
+    for holder in params.holders {
+        host.state_mut().mint(params.badge, holder)?;
+        // Event for the awarded badge.
+        logger.log(&Cis2Event::Mint(MintEvent {
+            token_id: params.badge,
+            amount:   ContractTokenAmount::from(1),
+            owner:    holder,
+        }))?;
+    }
+    Ok(())
+}
+
+/// Burn a badge, either renouncing it as the holder or revoking it as the
+/// contract owner.
+/// Logs a `Burn` event.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is neither the holder nor the contract instance owner.
+/// - The badge does not exist.
+/// - The holder does not hold the badge.
+/// - Fails to log Burn event.
+#[receive(
+    contract = "fan_badges",
+    name = "burn",
+    parameter = "BurnParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_burn<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let params: BurnParams = ctx.parameter_cursor().get()?;
+    let sender = ctx.sender();
+    ensure!(
+        sender == params.holder || sender.matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    host.state_mut().burn(params.badge, params.holder)?;
+    logger.log(&Cis2Event::Burn(BurnEvent {
+        token_id: params.badge,
+        amount:   ContractTokenAmount::from(1),
+        owner:    params.holder,
+    }))?;
+    Ok(())
+}
+
+type TransferParameter = TransferParams<ContractTokenId, ContractTokenAmount>;
+
+/// The badges are soulbound, so every transfer is rejected.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The list of transfers is not empty.
+#[receive(
+    contract = "fan_badges",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError"
+)]
+fn contract_transfer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let TransferParams(transfers): TransferParameter = ctx.parameter_cursor().get()?;
+    ensure!(transfers.is_empty(), CustomContractError::NonTransferable.into());
+    Ok(())
+}
+
+/// Operators would only be allowed to transfer badges on behalf of the
+/// holder, so enabling operators is rejected.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the updates adds an operator.
+#[receive(
+    contract = "fan_badges",
+    name = "updateOperator",
+    parameter = "UpdateOperatorParams",
+    error = "ContractError"
+)]
+fn contract_update_operator<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let UpdateOperatorParams(params) = ctx.parameter_cursor().get()?;
+    // Removing an operator is fine, since no address is an operator.
+    ensure!(
+        params.iter().all(|param| matches!(param.update, OperatorUpdate::Remove)),
+        CustomContractError::NonTransferable.into()
+    );
+    Ok(())
+}
+
+/// Takes a list of queries. Each query is an owner address and some address to
+/// check as an operator of the owner address. Since operators cannot be
+/// enabled, the response is always false.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "fan_badges",
+    name = "operatorOf",
+    parameter = "OperatorOfQueryParams",
+    return_value = "OperatorOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_operator_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<OperatorOfQueryResponse> {
+    // Parse the parameter.
+    let params: OperatorOfQueryParams = ctx.parameter_cursor().get()?;
+    let result = OperatorOfQueryResponse::from(vec![false; params.queries.len()]);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenIDs used by this contract.
+type ContractBalanceOfQueryParams = BalanceOfQueryParams<ContractTokenId>;
+/// Response type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenAmounts used by this contract.
+type ContractBalanceOfQueryResponse = BalanceOfQueryResponse<ContractTokenAmount>;
+
+/// Get the balance of given token IDs and addresses.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "fan_badges",
+    name = "balanceOf",
+    parameter = "ContractBalanceOfQueryParams",
+    return_value = "ContractBalanceOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ContractBalanceOfQueryResponse> {
+    // Parse the parameter.
+    let params: ContractBalanceOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for balance.
+        let amount = host.state().balance(&query.token_id, &query.address)?;
+        response.push(amount);
+    }
+    let result = ContractBalanceOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `tokenMetadata` specialized to the
+/// subset of TokenIDs used by this contract.
+type ContractTokenMetadataQueryParams = TokenMetadataQueryParams<ContractTokenId>;
+
+/// Get the token metadata URLs and checksums given a list of token IDs.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "fan_badges",
+    name = "tokenMetadata",
+    parameter = "ContractTokenMetadataQueryParams",
+    return_value = "TokenMetadataQueryResponse",
+    error = "ContractError"
+)]
+fn contract_token_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<TokenMetadataQueryResponse> {
+    // Parse the parameter.
+    let params: ContractTokenMetadataQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for token_id in params.queries {
+        // Get the metadata URL of the badge, failing if it does not exist.
+        let metadata_url = host
+            .state()
+            .badges
+            .get(&token_id)
+            .map(|url| url.clone())
+            .ok_or(ContractError::InvalidTokenId)?;
+        response.push(metadata_url);
+    }
+    let result = TokenMetadataQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Get the supported standards given a list of standard identifiers.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "fan_badges",
+    name = "supports",
+    parameter = "SupportsQueryParams",
+    return_value = "SupportsQueryResponse",
+    error = "ContractError"
+)]
+fn contract_supports<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<SupportsQueryResponse> {
+    // Parse the parameter.
+    let params: SupportsQueryParams = ctx.parameter_cursor().get()?;
+
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for std_id in params.queries {
+        if SUPPORTS_STANDARDS.contains(&std_id.as_standard_identifier()) {
+            response.push(SupportResult::Support);
+        } else {
+            response.push(SupportResult::NoSupport);
+        }
+    }
+    let result = SupportsQueryResponse::from(response);
+    Ok(result)
+}
+
+// Tests
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0u8; 32]);
+    const OWNER_ADDR: Address = Address::Account(OWNER);
+    const FAN_1: Address = Address::Account(AccountAddress([1u8; 32]));
+    const FAN_2: Address = Address::Account(AccountAddress([2u8; 32]));
+    const BADGE: ContractTokenId = TokenIdU32(1);
+
+    /// Test helper function which creates a host with `BADGE` awarded to
+    /// `FAN_1`.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = State::empty(&mut state_builder);
+        state.badges.insert(BADGE, MetadataUrl {
+            url:  "https://some.example/badge/1".to_string(),
+            hash: None,
+        });
+        state.mint(BADGE, FAN_1).expect_report("Minting should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    /// Test minting awards the badge to every address, and only the contract
+    /// owner and minters can mint.
+    #[concordium_test]
+    fn test_mint() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&MintParams {
+            badge:   BADGE,
+            holders: vec![FAN_2],
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(OWNER);
+        ctx.set_sender(FAN_2);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_mint(&ctx, &mut host, &mut TestLogger::init());
+        claim_eq!(result, Err(ContractError::Unauthorized), "Only the owner can mint");
+
+        ctx.set_sender(OWNER_ADDR);
+        let mut logger = TestLogger::init();
+        let result = contract_mint(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        let balance = host.state().balance(&BADGE, &FAN_2).expect_report("Badge should exist");
+        claim_eq!(balance, 1.into(), "The fan should hold the badge");
+        claim_eq!(
+            logger.logs,
+            [to_bytes(&Cis2Event::Mint(MintEvent {
+                token_id: BADGE,
+                amount:   ContractTokenAmount::from(1),
+                owner:    FAN_2,
+            }))],
+            "Incorrect event emitted"
+        );
+
+        let result = contract_mint(&ctx, &mut host, &mut TestLogger::init());
+        claim_eq!(
+            result,
+            Err(ContractError::Custom(CustomContractError::AlreadyHolds)),
+            "A badge is held at most once"
+        );
+
+        let parameter_bytes = to_bytes(&SetMinterParams {
+            minter:  FAN_1,
+            allowed: true,
+        });
+        let mut minter_ctx = TestReceiveContext::empty();
+        minter_ctx.set_owner(OWNER);
+        minter_ctx.set_sender(OWNER_ADDR);
+        minter_ctx.set_parameter(&parameter_bytes);
+        contract_set_minter(&minter_ctx, &mut host).expect_report("Setting a minter should pass");
+        let parameter_bytes = to_bytes(&MintParams {
+            badge:   BADGE,
+            holders: vec![OWNER_ADDR],
+        });
+        let mut minter_ctx = TestReceiveContext::empty();
+        minter_ctx.set_owner(OWNER);
+        minter_ctx.set_sender(FAN_1);
+        minter_ctx.set_parameter(&parameter_bytes);
+        let result = contract_mint(&minter_ctx, &mut host, &mut TestLogger::init());
+        claim!(result.is_ok(), "Minters can mint");
+    }
+
+    /// Test transfers and enabling operators are rejected.
+    #[concordium_test]
+    fn test_non_transferable() {
+        let host = new_host();
+        let parameter_bytes = to_bytes(&TransferParams::from(vec![Transfer {
+            token_id: BADGE,
+            amount:   ContractTokenAmount::from(1),
+            from:     FAN_1,
+            to:       Receiver::from_account(AccountAddress([2u8; 32])),
+            data:     AdditionalData::empty(),
+        }]));
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(FAN_1);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_transfer(&ctx, &host);
+        claim_eq!(
+            result,
+            Err(ContractError::Custom(CustomContractError::NonTransferable)),
+            "Transfers should be rejected"
+        );
+
+        let parameter_bytes = to_bytes(&UpdateOperatorParams(vec![UpdateOperator {
+            update:   OperatorUpdate::Add,
+            operator: FAN_2,
+        }]));
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_update_operator(&ctx, &host);
+        claim_eq!(
+            result,
+            Err(ContractError::Custom(CustomContractError::NonTransferable)),
+            "Enabling operators should be rejected"
+        );
+    }
+
+    /// Test the holder and the contract owner can burn a badge, but no one
+    /// else.
+    #[concordium_test]
+    fn test_burn() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&BurnParams {
+            badge:  BADGE,
+            holder: FAN_1,
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(OWNER);
+        ctx.set_sender(FAN_2);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_burn(&ctx, &mut host, &mut TestLogger::init());
+        claim_eq!(result, Err(ContractError::Unauthorized), "Only the holder or owner can burn");
+
+        ctx.set_sender(FAN_1);
+        let result = contract_burn(&ctx, &mut host, &mut TestLogger::init());
+        claim!(result.is_ok(), "Results in rejection");
+        let balance = host.state().balance(&BADGE, &FAN_1).expect_report("Badge should exist");
+        claim_eq!(balance, 0.into(), "The badge should be burned");
+
+        ctx.set_sender(OWNER_ADDR);
+        let result = contract_burn(&ctx, &mut host, &mut TestLogger::init());
+        claim_eq!(result, Err(ContractError::InsufficientFunds), "The badge is already burned");
+    }
+}
@@ -0,0 +1,400 @@
+//! # Implementation of a hashed time-lock contract (HTLC)
+//!
+//! A hashed time-lock locks CCD for a receiver, such that:
+//! - the receiver gets the CCD when anyone presents the preimage of a SHA2-256
+//!   hash before a timeout, and
+//! - the sender can refund the CCD after the timeout, if the preimage was not
+//!   presented.
+//!
+//! The contract holds any number of locks, identified by their hash.
+//!
+//! This is the building block of cross-chain atomic swaps: Alice picks a
+//! secret, locks CCD for Bob against its hash, and Bob locks his tokens on
+//! another chain for Alice against the same hash, with a shorter timeout.
+//! Alice claims Bob's tokens by revealing the secret on the other chain, which
+//! lets Bob learn the secret and claim the CCD here. If Alice never claims,
+//! both refund after their timeouts. Claiming logs the preimage in an event,
+//! so it can be observed by the other party.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// A lock of CCD.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Lock {
+    /// The account which locked the CCD, and receives it when refunding.
+    sender:   AccountAddress,
+    /// The account receiving the CCD when claimed.
+    receiver: AccountAddress,
+    /// The amount locked.
+    amount:   Amount,
+    /// The time until which the lock can be claimed, after which it can be
+    /// refunded.
+    timeout:  Timestamp,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The active locks, by their hash. A lock is removed when claimed or
+    /// refunded.
+    locks: StateMap<HashSha2256, Lock, S>,
+}
+
+/// Type of the parameter to the `lock` function.
+#[derive(Serialize, SchemaType)]
+pub struct LockParameter {
+    /// The account receiving the CCD when claimed.
+    pub receiver:  AccountAddress,
+    /// The SHA2-256 hash of the secret preimage.
+    pub hash_lock: HashSha2256,
+    /// The time until which the lock can be claimed, using the RFC 3339 format (https://tools.ietf.org/html/rfc3339)
+    pub timeout:   Timestamp,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum HtlcEvent {
+    /// CCD was locked.
+    Locked {
+        hash_lock: HashSha2256,
+        lock:      Lock,
+    },
+    /// A lock was claimed by presenting its preimage.
+    Claimed {
+        hash_lock: HashSha2256,
+        preimage:  [u8; 32],
+    },
+    /// A lock was refunded after its timeout.
+    Refunded {
+        hash_lock: HashSha2256,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract tries to lock; Only accounts are allowed to lock.
+    OnlyAccount,
+    /// Raised when locking without CCD.
+    ZeroAmount,
+    /// Raised when locking with a timeout which has already passed.
+    TimeoutInPast,
+    /// Raised when a lock with the same hash exists.
+    LockExists,
+    /// Raised when no lock exists for the hash of the preimage, or for the
+    /// hash to refund.
+    UnknownLock,
+    /// Raised when claiming after the timeout.
+    Expired,
+    /// Raised when refunding before the timeout.
+    NotExpired,
+    /// Raised when someone else than the sender of the lock refunds.
+    Unauthorized,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Init function that creates a contract without locks.
+#[init(contract = "htlc", event = "HtlcEvent")]
+fn htlc_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        locks: state_builder.new_map(),
+    })
+}
+
+/// Lock the CCD sent along for the receiver against the hash until the
+/// timeout.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No CCD is sent.
+/// - The timeout has already passed.
+/// - A lock with the same hash exists.
+/// - It fails to log the event.
+#[receive(
+    contract = "htlc",
+    name = "lock",
+    parameter = "LockParameter",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn htlc_lock<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let parameter: LockParameter = ctx.parameter_cursor().get()?;
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    ensure!(amount > Amount::zero(), ContractError::ZeroAmount);
+    ensure!(ctx.metadata().slot_time() < parameter.timeout, ContractError::TimeoutInPast);
+
+    let lock = Lock {
+        sender,
+        receiver: parameter.receiver,
+        amount,
+        timeout: parameter.timeout,
+    };
+    host.state_mut()
+        .locks
+        .entry(parameter.hash_lock)
+        .vacant_or(ContractError::LockExists)?
+        .insert(lock);
+
+    logger.log(&HtlcEvent::Locked {
+        hash_lock: parameter.hash_lock,
+        lock,
+    })?;
+    Ok(())
+}
+
+/// Claim the lock of the hash of the preimage, transferring the CCD to the
+/// receiver. Anyone can claim on behalf of the receiver.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - No lock exists for the hash of the preimage.
+/// - The timeout has passed.
+/// - It fails to log the event.
+#[receive(
+    contract = "htlc",
+    name = "claim",
+    parameter = "[u8; 32]",
+    error = "ContractError",
+    crypto_primitives,
+    enable_logger,
+    mutable
+)]
+fn htlc_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let preimage: [u8; 32] = ctx.parameter_cursor().get()?;
+    let hash_lock = crypto_primitives.hash_sha2_256(&preimage);
+    let state = host.state_mut();
+    let lock = state.locks.get(&hash_lock).map(|lock| *lock).ok_or(ContractError::UnknownLock)?;
+    ensure!(ctx.metadata().slot_time() <= lock.timeout, ContractError::Expired);
+    state.locks.remove(&hash_lock);
+
+    // The transfer succeeds if the receiver is an existing account, since the
+    // contract holds the locked CCD.
+    host.invoke_transfer(&lock.receiver, lock.amount).unwrap_abort();
+    logger.log(&HtlcEvent::Claimed {
+        hash_lock,
+        preimage,
+    })?;
+    Ok(())
+}
+
+/// Refund the lock of the hash to its sender, after the timeout.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - No lock exists for the hash.
+/// - The sender is not the sender of the lock.
+/// - The timeout has not passed.
+/// - It fails to log the event.
+#[receive(
+    contract = "htlc",
+    name = "refund",
+    parameter = "HashSha2256",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn htlc_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let hash_lock: HashSha2256 = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let lock = state.locks.get(&hash_lock).map(|lock| *lock).ok_or(ContractError::UnknownLock)?;
+    ensure!(ctx.sender().matches_account(&lock.sender), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() > lock.timeout, ContractError::NotExpired);
+    state.locks.remove(&hash_lock);
+
+    // The transfer always succeeds, since the sender of the lock is the sender
+    // and the contract holds the locked CCD.
+    host.invoke_transfer(&lock.sender, lock.amount).unwrap_abort();
+    logger.log(&HtlcEvent::Refunded {
+        hash_lock,
+    })?;
+    Ok(())
+}
+
+/// View function that returns the lock of the hash, if any.
+#[receive(
+    contract = "htlc",
+    name = "viewLock",
+    parameter = "HashSha2256",
+    return_value = "Option<Lock>"
+)]
+fn htlc_view_lock<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Lock>> {
+    let hash_lock: HashSha2256 = ctx.parameter_cursor().get()?;
+    Ok(host.state().locks.get(&hash_lock).map(|lock| *lock))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+    const TIMEOUT: u64 = 100;
+    const SECRET: [u8; 32] = [42; 32];
+    const AMOUNT: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    fn hash_lock() -> HashSha2256 { TestCryptoPrimitives::new().hash_sha2_256(&SECRET) }
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let state = htlc_init(&TestInitContext::empty(), &mut state_builder)
+            .expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn lock(
+        host: &mut TestHost<State<TestStateApi>>,
+        amount: Amount,
+        timeout: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&LockParameter {
+            receiver:  BOB,
+            hash_lock: hash_lock(),
+            timeout:   Timestamp::from_timestamp_millis(timeout),
+        });
+        let mut ctx = ctx_at(ALICE, 0);
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(host.self_balance() + amount);
+        htlc_lock(&ctx, host, amount, logger)
+    }
+
+    fn claim(
+        host: &mut TestHost<State<TestStateApi>>,
+        preimage: [u8; 32],
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&preimage);
+        let mut ctx = ctx_at(CAROL, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        htlc_claim(&ctx, host, logger, &TestCryptoPrimitives::new())
+    }
+
+    fn refund(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&hash_lock());
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        htlc_refund(&ctx, host, logger)
+    }
+
+    #[concordium_test]
+    /// Test that presenting the preimage before the timeout transfers the CCD
+    /// to the receiver and logs the preimage.
+    fn test_claim() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        lock(&mut host, AMOUNT, TIMEOUT, &mut logger).expect_report("Locking should pass");
+
+        let res = claim(&mut host, [0; 32], 0, &mut logger);
+        claim_eq!(res, Err(ContractError::UnknownLock), "A wrong preimage should fail");
+        let res = refund(&mut host, ALICE, TIMEOUT, &mut logger);
+        claim_eq!(res, Err(ContractError::NotExpired), "Refunding early should fail");
+
+        claim(&mut host, SECRET, TIMEOUT, &mut logger).expect_report("Claiming should pass");
+        claim_eq!(host.get_transfers(), [(BOB, AMOUNT)], "The receiver should get the CCD");
+        claim_eq!(
+            logger.logs[1],
+            to_bytes(&HtlcEvent::Claimed {
+                hash_lock: hash_lock(),
+                preimage:  SECRET,
+            }),
+            "The preimage should be logged"
+        );
+
+        let res = claim(&mut host, SECRET, TIMEOUT, &mut logger);
+        claim_eq!(res, Err(ContractError::UnknownLock), "Claiming twice should fail");
+        let res = refund(&mut host, ALICE, TIMEOUT + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::UnknownLock), "Refunding a claimed lock should fail");
+    }
+
+    #[concordium_test]
+    /// Test that only the sender can refund, and only after the timeout, after
+    /// which the lock can no longer be claimed.
+    fn test_refund() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        lock(&mut host, AMOUNT, TIMEOUT, &mut logger).expect_report("Locking should pass");
+
+        let res = claim(&mut host, SECRET, TIMEOUT + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::Expired), "Claiming late should fail");
+        let res = refund(&mut host, BOB, TIMEOUT + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the sender can refund");
+
+        refund(&mut host, ALICE, TIMEOUT + 1, &mut logger).expect_report("Refunding should pass");
+        claim_eq!(host.get_transfers(), [(ALICE, AMOUNT)], "The sender should get the CCD back");
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+
+    #[concordium_test]
+    /// Test that invalid locks are rejected.
+    fn test_lock_rejected() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+
+        let res = lock(&mut host, Amount::zero(), TIMEOUT, &mut logger);
+        claim_eq!(res, Err(ContractError::ZeroAmount), "Locking nothing should fail");
+        let res = lock(&mut host, AMOUNT, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::TimeoutInPast), "A past timeout should fail");
+
+        lock(&mut host, AMOUNT, TIMEOUT, &mut logger).expect_report("Locking should pass");
+        let res = lock(&mut host, AMOUNT, TIMEOUT, &mut logger);
+        claim_eq!(res, Err(ContractError::LockExists), "Reusing a hash should fail");
+    }
+}
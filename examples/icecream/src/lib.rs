@@ -13,41 +13,273 @@
 //! ## The Icecream Contract
 //!
 //! The contract is initialised with a contract address to the weather service
-//! contract.
+//! contract and the region to get the weather for.
+//!
+//! The owner maintains a list of approved icecream vendors using the
+//! `add_vendor` and `remove_vendor` functions. Payments can only go to
+//! approved vendors.
 //!
 //! Its primary function is `buy_icecream`, which works as follows:
-//!  - It is called with an `AccountAddress` of the icecream vendor and the
-//!    icecream price as amount.
-//!  - It queries the `Weather` from the weather_service contract.
-//!  - If it's `Weather::Sunny`, the transfer goes through to the icecream
-//!    vendor.
+//!  - It is called with the `Address` of the icecream vendor and the icecream
+//!    price as amount. The vendor can either be an account or a contract.
+//!  - It rejects if the vendor is not approved.
+//!  - It queries the `Weather` from the weather_service contract, unless the
+//!    last queried weather is recent enough to be reused. How long the weather
+//!    is reused is configured by the owner using `set_weather_ttl`.
+//!  - It considers the weather rainy if the probability of precipitation is at
+//!    least the threshold set by the owner with `set_precipitation_threshold`,
+//!    and sunny otherwise.
+//!  - If it's sunny, the transfer goes through to the icecream vendor, minus
+//!    the commission of the middleman. Contract vendors are paid by invoking
+//!    the vendor entrypoint configured by the owner with
+//!    `set_vendor_entrypoint`.
 //!  - Otherwise, the amount is returned to invoker.
+//!  - In both cases a `Purchase` event is logged, with the outcome, and the
+//!    purchase is added to the purchase history.
+//!
+//! The owner can set a refund window using `set_refund_window`. When set,
+//! sunny-day payments are held in escrow for the duration of the window instead
+//! of going to the vendor immediately. During the window the buyer can get the
+//! payment back using `request_refund`, and after the window the vendor can
+//! get the payment using `claim`. The refund window can be at most 30 days.
+//!
+//! The most recent purchases are kept in the purchase history, which can be
+//! viewed a page at a time using `view_purchases`.
+//!
+//! Icecream from several vendors can be bought at once using
+//! `buy_icecream_batch`, which only queries the weather once.
 //!
 //! It also has a `replace_weather_service` function, in which the owner can
-//! replace the weather service.
+//! replace the weather service, and a `force_refresh` function, in which the
+//! owner can update the reused weather immediately.
+//!
+//! The owner can set the commission in basis points using `set_commission`.
+//! The commissions accumulate in the contract and can be withdrawn by the
+//! owner using `withdraw_fees`.
+//!
+//! The owner can pause purchases using `set_paused`, which is built on the
+//! `Pausable` component shared with other examples.
+//!
+//! The owner allowed to replace the weather service is kept by the shared
+//! `Ownable` component and starts out as the account initialising the contract.
+//! It is transferred in two steps: the owner proposes a new owner using
+//! `transfer_ownership`, who then takes over using `accept_ownership`.
+//! This ownership only covers `replace_weather_service`. All other owner-only
+//! functions check the account that created the contract instance, and
+//! `withdraw_fees` pays that account. A transfer does not change this account,
+//! so the new owner cannot set the commission, pause purchases or withdraw
+//! the fees.
 //!
 //!
 //! ## The Weather Service Contract
 //!
-//! The contract is initialised with the `Weather`.
+//! The contract keeps track of the `Weather` in a number of regions, each
+//! identified by a `RegionId`. The weather consists of the temperature, the
+//! probability of precipitation and the wind speed. It is initialised with the
+//! weather of some regions.
 //!
 //! It has `get` and `set` receive functions, which either return or set the
-//! weather. Only the owner can update the weather.
+//! weather of a region, and a `get_simple` function which only tells whether
+//! it is rainy or sunny in a region. Only authorized updaters can update the
+//! weather. The updaters hold the `UPDATER` role of the shared `Roles`
+//! component, which the owner grants and revokes using `authorize` and
+//! `revoke`, and the account initialising the contract is the first updater.
+//! Every change of roles is logged as a `RoleEvent`.
+//!
+//! The time of each update is recorded, and the `get_fresh` function returns
+//! the weather of a region only if it was updated recently enough.
+//!
+//! Every observation is kept in a history, and the `history` function returns
+//! the observations made in a time window, for instance to verify that it
+//! rained in a region on a given day.
+//!
+//! Contracts can `subscribe` to be notified whenever the weather is set, by
+//! having an entrypoint of their choice invoked with the update, and
+//! `unsubscribe` again. A failing subscriber does not prevent the update.
+//!
+//! Updaters can also add forecasts of the weather in a region at future times
+//! using `set_forecast`, and `get_forecast` returns the forecast in effect at a
+//! given time. Forecasts for times that have passed are pruned on every update.
 
 #![cfg_attr(not(feature = "std"), no_std)]
+use components::{
+    ensure_role, ContractPaused, Ownable, OwnableError, OwnershipEvent, Pausable, Role, RoleEvent,
+    Roles,
+};
 use concordium_std::*;
 
-#[derive(Serialize, SchemaType, Clone)]
-struct State {
-    weather_service: ContractAddress,
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    weather_service:         ContractAddress,
+    /// The region to get the weather for.
+    region:                  RegionId,
+    /// The vendors that payments are allowed to go to.
+    vendors:                 StateSet<Address, S>,
+    /// The entrypoint invoked on contract vendors to pay them.
+    vendor_entrypoint:       OwnedEntrypointName,
+    /// The last weather queried from the weather service and the slot time of
+    /// the query.
+    cached_weather:          Option<(Weather, Timestamp)>,
+    /// For how long the cached weather is used instead of querying the weather
+    /// service again.
+    weather_ttl:             Duration,
+    /// The precipitation probability in percent from which the weather is
+    /// considered rainy.
+    precipitation_threshold: u8,
+    /// The commission taken on every purchase, in basis points.
+    commission_bps:          BasisPoints,
+    /// The commissions collected and not yet withdrawn by the owner.
+    fees:                    Amount,
+    /// The most recent purchases, indexed by purchase id.
+    purchases:               StateMap<u64, PurchaseRecord, S>,
+    /// The id of the next purchase.
+    next_purchase_id:        u64,
+    /// For how long payments are held in escrow before the vendor can claim
+    /// them. Payments are not held in escrow if this is zero.
+    refund_window:           Duration,
+    /// The payments held in escrow, indexed by purchase id.
+    escrows:                 StateMap<u64, Escrow, S>,
+    /// Whether purchases are paused.
+    pausable:                Pausable,
+    /// The owner allowed to replace the weather service.
+    ownable:                 Ownable,
 }
 
-#[derive(Serialize, SchemaType, Clone, Copy)]
-enum Weather {
+/// A payment held in escrow.
+#[derive(Debug, Serialize, Clone)]
+struct Escrow {
+    /// The account that bought the icecream.
+    buyer:         AccountAddress,
+    /// The icecream vendor.
+    vendor:        Address,
+    /// The amount paid by the buyer.
+    amount:        Amount,
+    /// The commission kept when the vendor claims the payment, fixed at the
+    /// time of the purchase.
+    fee:           Amount,
+    /// The time at which the refund window closes and the vendor can claim
+    /// the payment.
+    window_closes: Timestamp,
+}
+
+/// The maximum number of purchases kept in the purchase history.
+const MAX_PURCHASE_HISTORY: u64 = 100;
+
+/// The longest time, in days, that sunny-day payments can be held in escrow.
+const MAX_REFUND_WINDOW_DAYS: u64 = 30;
+
+impl<S: HasStateApi> State<S> {
+    /// Add a purchase to the history and return its id. If the history is
+    /// full, the oldest purchase is removed.
+    fn record_purchase(&mut self, record: PurchaseRecord) -> u64 {
+        let id = self.next_purchase_id;
+        if id >= MAX_PURCHASE_HISTORY {
+            self.purchases.remove(&(id - MAX_PURCHASE_HISTORY));
+        }
+        self.purchases.insert(id, record);
+        self.next_purchase_id += 1;
+        id
+    }
+}
+
+/// A weather observation.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+struct Weather {
+    /// The temperature in tenths of a degree Celsius.
+    temperature:               i32,
+    /// The probability of precipitation in percent.
+    precipitation_probability: u8,
+    /// The wind speed in tenths of a meter per second.
+    wind_speed:                u32,
+}
+
+/// A simplified view of the weather.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+enum SimpleWeather {
     Rainy,
     Sunny,
 }
 
+impl Weather {
+    /// Simplify the weather, considering it rainy if the probability of
+    /// precipitation is at least the threshold, given in percent.
+    fn simplify(&self, precipitation_threshold: u8) -> SimpleWeather {
+        if self.precipitation_probability >= precipitation_threshold {
+            SimpleWeather::Rainy
+        } else {
+            SimpleWeather::Sunny
+        }
+    }
+}
+
+/// The precipitation probability from which the weather service considers the
+/// weather rainy in `get_simple`.
+const SIMPLE_PRECIPITATION_THRESHOLD: u8 = 50;
+
+/// The identifier of a region in the weather service.
+type RegionId = u32;
+
+/// A client for the entrypoints of the weather service that the icecream
+/// contract invokes.
+#[concordium_client]
+trait WeatherClient {
+    /// Get the current weather in the given region.
+    fn get(region: RegionId) -> Weather;
+    /// Subscribe the icecream contract to weather updates, which are sent to
+    /// the given entrypoint.
+    fn subscribe(entrypoint: OwnedEntrypointName);
+}
+
+/// The parameter type for the icecream contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The weather service to use.
+    weather_service: ContractAddress,
+    /// The region to get the weather for.
+    region:          RegionId,
+}
+
+/// Information about a purchase, logged in the `Purchase` event and stored in
+/// the purchase history.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+struct PurchaseRecord {
+    /// The account buying the icecream.
+    buyer:     AccountAddress,
+    /// The icecream vendor.
+    vendor:    Address,
+    /// The amount paid by the buyer.
+    amount:    Amount,
+    /// The weather at the time of purchase. The purchase went through to the
+    /// vendor (possibly via escrow) if it was sunny, otherwise the amount was
+    /// returned to the buyer.
+    weather:   SimpleWeather,
+    /// The slot time of the purchase.
+    timestamp: Timestamp,
+}
+
+/// The parameter type for the contract function `view_purchases`.
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewPurchasesParams {
+    /// The number of purchases to skip, counting from the most recent.
+    offset: u64,
+    /// The maximum number of purchases to return.
+    limit:  u32,
+}
+
+/// The events logged by the icecream contract.
+#[derive(Debug, Serial, SchemaType)]
+enum IcecreamEvent {
+    /// A purchase was attempted.
+    Purchase(PurchaseRecord),
+    /// The escrowed payment for the purchase with this id was refunded.
+    Refund(u64),
+    /// The escrowed payment for the purchase with this id was claimed.
+    Claim(u64),
+    /// A step of an ownership transfer.
+    Ownership(OwnershipEvent),
+}
+
 /// The custom errors the contract can produce.
 #[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
 enum ContractError {
@@ -60,70 +292,360 @@ enum ContractError {
     /// Failed contract invoke.
     ContractError,
     Unauthenticated,
+    /// The icecream vendor is not on the list of approved vendors.
+    UnknownVendor,
+    /// The commission is larger than 100%.
+    InvalidCommission,
+    /// There is no forecast for the requested region and time.
+    NoForecast,
+    /// Purchases are paused.
+    ContractPaused,
+    /// Only contracts can call this function.
+    OnlyContract,
+    /// The precipitation threshold is larger than 100%.
+    InvalidThreshold,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// The weather service has no weather for the region.
+    UnknownRegion,
+    /// The weather was observed too long ago.
+    StaleData,
+    /// There is no payment in escrow for the purchase.
+    UnknownPurchase,
+    /// The refund window for the purchase has closed.
+    RefundWindowClosed,
+    /// The refund window for the purchase has not closed yet.
+    RefundWindowOpen,
+    /// The amount does not match the sum of the prices in a batch purchase.
+    AmountMismatch,
+    /// The refund window is longer than `MAX_REFUND_WINDOW_DAYS`.
+    InvalidRefundWindow,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<ContractPaused> for ContractError {
+    fn from(_: ContractPaused) -> Self { Self::ContractPaused }
+}
+
+impl From<OwnableError> for ContractError {
+    fn from(_: OwnableError) -> Self { Self::Unauthenticated }
 }
 
 impl<A> From<CallContractError<A>> for ContractError {
     fn from(_: CallContractError<A>) -> Self { Self::ContractError }
 }
 
+impl<A> From<CallContractTypedError<A>> for ContractError {
+    fn from(_: CallContractTypedError<A>) -> Self { Self::ContractError }
+}
+
 type ContractResult<A> = Result<A, ContractError>;
 
-/// Initialise the contract with the contract address of the weather service.
-#[init(contract = "icecream", parameter = "ContractAddress")]
+/// Initialise the contract with the contract address of the weather service
+/// and the region to get the weather for.
+/// Contract vendors are paid using their `deposit` entrypoint, until the owner
+/// configures otherwise, and the weather is queried on every purchase until the
+/// owner sets a TTL for the weather.
+#[init(contract = "icecream", parameter = "InitParams", event = "IcecreamEvent")]
 fn contract_init<S: HasStateApi>(
     ctx: &impl HasInitContext,
-    _state_builder: &mut StateBuilder<S>,
-) -> InitResult<State> {
-    let weather_service: ContractAddress = ctx.parameter_cursor().get()?;
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
     Ok(State {
-        weather_service,
+        weather_service:         params.weather_service,
+        region:                  params.region,
+        vendors:                 state_builder.new_set(),
+        vendor_entrypoint:       OwnedEntrypointName::new_unchecked("deposit".into()),
+        cached_weather:          None,
+        weather_ttl:             Duration::from_millis(0),
+        precipitation_threshold: SIMPLE_PRECIPITATION_THRESHOLD,
+        commission_bps:          BasisPoints::default(),
+        fees:                    Amount::zero(),
+        purchases:               state_builder.new_map(),
+        next_purchase_id:        0,
+        refund_window:           Duration::from_millis(0),
+        escrows:                 state_builder.new_map(),
+        pausable:                Pausable::new(),
+        ownable:                 Ownable::new(Address::Account(ctx.init_origin())),
     })
 }
 
+/// Query the current weather in the configured region from the weather
+/// service and cache it.
+fn query_weather<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Weather> {
+    let weather_service = WeatherClient::new(host.state().weather_service);
+    let region = host.state().region;
+    let weather = weather_service.get(host, &region)?;
+    host.state_mut().cached_weather = Some((weather, ctx.metadata().slot_time()));
+    Ok(weather)
+}
+
+/// Get the cached weather if it is younger than the TTL, otherwise query the
+/// weather service.
+fn get_weather<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Weather> {
+    let now = ctx.metadata().slot_time();
+    if let Some((weather, queried_at)) = host.state().cached_weather {
+        if let Some(age) = now.duration_since(queried_at) {
+            if age < host.state().weather_ttl {
+                return Ok(weather);
+            }
+        }
+    }
+    query_weather(ctx, host)
+}
+
+/// Pay the vendor, keeping the commission `fee`.
+/// Contract vendors are told who the buyer is.
+fn pay_vendor<S: HasStateApi>(
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    buyer: AccountAddress,
+    vendor: Address,
+    amount: Amount,
+    fee: Amount,
+) -> ContractResult<()> {
+    match vendor {
+        Address::Account(vendor) => host.invoke_transfer(&vendor, amount - fee)?,
+        Address::Contract(vendor) => {
+            let entrypoint = host.state().vendor_entrypoint.clone();
+            host.invoke_contract(&vendor, &buyer, entrypoint.as_entrypoint_name(), amount - fee)?;
+        }
+    }
+    host.state_mut().fees += fee;
+    Ok(())
+}
+
 /// Attempt purchasing icecream from the icecream vendor.
 #[receive(
     contract = "icecream",
     name = "buy_icecream",
-    parameter = "AccountAddress",
+    parameter = "Address",
     payable,
     mutable,
+    enable_logger,
     error = "ContractError"
 )]
 fn contract_buy_icecream<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &mut impl HasHost<State, StateApiType = S>,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
     amount: Amount,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    let weather_service = host.state().weather_service;
-    let icecream_vendor: AccountAddress = ctx.parameter_cursor().get()?;
-
-    let weather = host
-        .invoke_contract_raw(
-            &weather_service,
-            Parameter(&[]),
-            EntrypointName::new_unchecked("get"),
-            Amount::zero(),
-        )?
-        .1;
-    let weather = if let Some(mut weather) = weather {
-        weather.get()?
-    } else {
-        return Err(ContractError::ContractError);
+    host.state().pausable.ensure_not_paused()?;
+    let icecream_vendor: Address = ctx.parameter_cursor().get()?;
+    ensure!(host.state().vendors.contains(&icecream_vendor), ContractError::UnknownVendor);
+
+    let weather = get_weather(ctx, host)?.simplify(host.state().precipitation_threshold);
+    if let SimpleWeather::Rainy = weather {
+        host.invoke_transfer(&ctx.invoker(), amount)?;
+        // We could also abort here, but this is useful to show off some
+        // testing features.
+    }
+    complete_purchase(ctx, host, logger, icecream_vendor, amount, weather)
+}
+
+/// Record and log a purchase, and if it is sunny, pay the vendor or hold the
+/// payment in escrow. Refunding the buyer on rainy days is left to the caller.
+fn complete_purchase<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    vendor: Address,
+    amount: Amount,
+    weather: SimpleWeather,
+) -> ContractResult<()> {
+    let buyer = ctx.invoker();
+    let now = ctx.metadata().slot_time();
+
+    let record = PurchaseRecord {
+        buyer,
+        vendor,
+        amount,
+        weather,
+        timestamp: now,
     };
+    let purchase_id = host.state_mut().record_purchase(record.clone());
 
-    match weather {
-        Weather::Rainy => {
-            host.invoke_transfer(&ctx.invoker(), amount)?;
-            // We could also abort here, but this is useful to show off some
-            // testing features.
+    if let SimpleWeather::Sunny = weather {
+        let refund_window = host.state().refund_window;
+        let fee = host.state().commission_bps.of(amount);
+        if refund_window == Duration::from_millis(0) {
+            pay_vendor(host, buyer, vendor, amount, fee)?;
+        } else {
+            let window_closes = now.checked_add(refund_window).unwrap_abort();
+            host.state_mut().escrows.insert(purchase_id, Escrow {
+                buyer,
+                vendor,
+                amount,
+                fee,
+                window_closes,
+            });
         }
-        Weather::Sunny => host.invoke_transfer(&icecream_vendor, amount)?,
     }
+
+    logger.log(&IcecreamEvent::Purchase(record))?;
+    Ok(())
+}
+
+/// Attempt purchasing icecream from several icecream vendors at once.
+/// The parameter is a list of vendors and the price of the icecream from each
+/// vendor, and the amount must be the sum of the prices. The weather is only
+/// queried once, and on rainy days the whole amount is returned to the
+/// invoker.
+#[receive(
+    contract = "icecream",
+    name = "buy_icecream_batch",
+    parameter = "Vec<(Address, Amount)>",
+    payable,
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn contract_buy_icecream_batch<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    host.state().pausable.ensure_not_paused()?;
+    let items: Vec<(Address, Amount)> = ctx.parameter_cursor().get()?;
+    let mut total = Amount::zero();
+    for (vendor, price) in items.iter() {
+        ensure!(host.state().vendors.contains(vendor), ContractError::UnknownVendor);
+        total = total
+            .micro_ccd
+            .checked_add(price.micro_ccd)
+            .map(Amount::from_micro_ccd)
+            .ok_or(ContractError::AmountMismatch)?;
+    }
+    ensure_eq!(total, amount, ContractError::AmountMismatch);
+
+    let weather = get_weather(ctx, host)?.simplify(host.state().precipitation_threshold);
+    if let SimpleWeather::Rainy = weather {
+        host.invoke_transfer(&ctx.invoker(), amount)?;
+    }
+    for (vendor, price) in items {
+        complete_purchase(ctx, host, logger, vendor, price, weather)?;
+    }
+    Ok(())
+}
+
+/// Get back the escrowed payment for a purchase, while the refund window is
+/// open. Only the buyer can do so.
+#[receive(
+    contract = "icecream",
+    name = "request_refund",
+    parameter = "u64",
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn contract_request_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let purchase_id: u64 = ctx.parameter_cursor().get()?;
+    let escrow = host
+        .state()
+        .escrows
+        .get(&purchase_id)
+        .map(|escrow| escrow.clone())
+        .ok_or(ContractError::UnknownPurchase)?;
+    ensure_eq!(ctx.sender(), Address::Account(escrow.buyer), ContractError::Unauthenticated);
+    ensure!(ctx.metadata().slot_time() < escrow.window_closes, ContractError::RefundWindowClosed);
+
+    host.state_mut().escrows.remove(&purchase_id);
+    host.invoke_transfer(&escrow.buyer, escrow.amount)?;
+    logger.log(&IcecreamEvent::Refund(purchase_id))?;
+    Ok(())
+}
+
+/// Get the escrowed payment for a purchase, after the refund window has
+/// closed. Only the vendor can do so.
+#[receive(
+    contract = "icecream",
+    name = "claim",
+    parameter = "u64",
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn contract_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let purchase_id: u64 = ctx.parameter_cursor().get()?;
+    let escrow = host
+        .state()
+        .escrows
+        .get(&purchase_id)
+        .map(|escrow| escrow.clone())
+        .ok_or(ContractError::UnknownPurchase)?;
+    ensure_eq!(ctx.sender(), escrow.vendor, ContractError::Unauthenticated);
+    ensure!(ctx.metadata().slot_time() >= escrow.window_closes, ContractError::RefundWindowOpen);
+
+    host.state_mut().escrows.remove(&purchase_id);
+    pay_vendor(host, escrow.buyer, escrow.vendor, escrow.amount, escrow.fee)?;
+    logger.log(&IcecreamEvent::Claim(purchase_id))?;
     Ok(())
 }
 
+/// View a page of the purchase history, starting from the most recent
+/// purchase. Returns pairs of purchase id and purchase, with the most recent
+/// purchase first.
+#[receive(
+    contract = "icecream",
+    name = "view_purchases",
+    parameter = "ViewPurchasesParams",
+    return_value = "Vec<(u64, PurchaseRecord)>",
+    error = "ContractError"
+)]
+fn contract_view_purchases<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Vec<(u64, PurchaseRecord)>> {
+    let params: ViewPurchasesParams = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    // The purchase ids are consecutive, so the page is found by counting down
+    // from the most recent id.
+    let newest = match params
+        .offset
+        .checked_add(1)
+        .and_then(|skipped| state.next_purchase_id.checked_sub(skipped))
+    {
+        Some(id) => id,
+        None => return Ok(Vec::new()),
+    };
+    let oldest = state.next_purchase_id.saturating_sub(MAX_PURCHASE_HISTORY);
+    let page = (oldest..=newest)
+        .rev()
+        .take(params.limit as usize)
+        .filter_map(|id| state.purchases.get(&id).map(|record| (id, record.clone())))
+        .collect();
+    Ok(page)
+}
+
 /// Replace the weather service with another.
-/// Only the owner of the contract can do so.
+/// Only the owner kept by the `Ownable` component can do so.
 #[receive(
     contract = "icecream",
     name = "replace_weather_service",
@@ -133,118 +655,829 @@ fn contract_buy_icecream<S: HasStateApi>(
 )]
 fn contract_replace_weather_service<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &mut impl HasHost<State, StateApiType = S>,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
 ) -> ContractResult<()> {
-    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    host.state().ownable.ensure_owner(&ctx.sender())?;
     let new_weather_service: ContractAddress = ctx.parameter_cursor().get()?;
-    host.state_mut().weather_service = new_weather_service;
+    let state = host.state_mut();
+    state.weather_service = new_weather_service;
+    // The cached weather came from the old weather service.
+    state.cached_weather = None;
     Ok(())
 }
 
-//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Propose a new owner, who takes over once they call `accept_ownership`.
+/// Only the current owner can do so.
+#[receive(
+    contract = "icecream",
+    name = "transfer_ownership",
+    parameter = "Address",
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn contract_transfer_ownership<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let new_owner: Address = ctx.parameter_cursor().get()?;
+    let event = host.state_mut().ownable.transfer_ownership(ctx.sender(), new_owner)?;
+    logger.log(&IcecreamEvent::Ownership(event))?;
+    Ok(())
+}
 
-/// Initialse the weather service with the weather.
-#[init(contract = "weather", parameter = "Weather")]
-fn weather_init<S: HasStateApi>(
-    ctx: &impl HasInitContext,
-    _state_builder: &mut StateBuilder<S>,
-) -> InitResult<Weather> {
-    let weather = ctx.parameter_cursor().get()?;
-    Ok(weather)
+/// Accept a proposed ownership transfer, making the sender the owner.
+/// Only the proposed new owner can do so.
+#[receive(
+    contract = "icecream",
+    name = "accept_ownership",
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn contract_accept_ownership<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let event = host.state_mut().ownable.accept_ownership(ctx.sender())?;
+    logger.log(&IcecreamEvent::Ownership(event))?;
+    Ok(())
 }
 
-/// Get the current weather.
-#[receive(contract = "weather", name = "get", return_value = "Weather", error = "ContractError")]
-fn weather_get<S: HasStateApi>(
-    _ctx: &impl HasReceiveContext,
-    host: &impl HasHost<Weather, StateApiType = S>,
-) -> ContractResult<Weather> {
-    Ok(*host.state())
+/// Subscribe to updates from the weather service, so the weather is cached
+/// whenever it is updated instead of only when it is queried.
+/// Only the owner of the contract can do so.
+#[receive(contract = "icecream", name = "subscribe_weather", mutable, error = "ContractError")]
+fn contract_subscribe_weather<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let weather_service = WeatherClient::new(host.state().weather_service);
+    weather_service
+        .subscribe(host, &OwnedEntrypointName::new_unchecked("on_weather_update".into()))?;
+    Ok(())
 }
 
-/// Update the weather.
+/// Receive a weather update from the weather service and cache it, if it is
+/// for the configured region.
+/// Only the weather service can call this.
 #[receive(
-    contract = "weather",
-    name = "set",
-    parameter = "Weather",
+    contract = "icecream",
+    name = "on_weather_update",
+    parameter = "WeatherUpdate",
     mutable,
     error = "ContractError"
 )]
-fn weather_set<S: HasStateApi>(
+fn contract_on_weather_update<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &mut impl HasHost<Weather, StateApiType = S>,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
 ) -> ContractResult<()> {
-    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated); // Only the owner can update the weather.
-    *host.state_mut() = ctx.parameter_cursor().get()?;
+    ensure_eq!(
+        Address::Contract(host.state().weather_service),
+        ctx.sender(),
+        ContractError::Unauthenticated
+    );
+    let update: WeatherUpdate = ctx.parameter_cursor().get()?;
+    if update.region == host.state().region {
+        host.state_mut().cached_weather = Some((update.weather, ctx.metadata().slot_time()));
+    }
     Ok(())
 }
 
-//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-
-#[concordium_cfg_test]
-mod tests {
-    use super::*;
-    use test_infrastructure::*;
+/// Set for how long a queried weather is reused before querying the weather
+/// service again.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "set_weather_ttl",
+    parameter = "Duration",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_set_weather_ttl<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let weather_ttl: Duration = ctx.parameter_cursor().get()?;
+    host.state_mut().weather_ttl = weather_ttl;
+    Ok(())
+}
 
-    const INVOKER_ADDR: AccountAddress = AccountAddress([0; 32]);
-    const WEATHER_SERVICE: ContractAddress = ContractAddress {
-        index:    1,
-        subindex: 0,
-    };
-    const ICECREAM_VENDOR: AccountAddress = AccountAddress([1; 32]);
-    const ICECREAM_PRICE: Amount = Amount {
-        micro_ccd: 6000000, // 6 CCD
-    };
+/// Pause or unpause purchases. While paused, `buy_icecream` and
+/// `buy_icecream_batch` reject with `ContractPaused`.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "set_paused",
+    parameter = "bool",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_set_paused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let paused: bool = ctx.parameter_cursor().get()?;
+    host.state_mut().pausable.set_paused(paused);
+    Ok(())
+}
 
-    #[concordium_test]
-    fn test_sunny_days() {
-        // Arrange
-        let mut ctx = TestReceiveContext::empty();
-        let state = State {
-            weather_service: WEATHER_SERVICE,
-        };
-        let mut host = TestHost::new(state, TestStateBuilder::new());
+/// Set the precipitation probability in percent from which the weather is
+/// considered rainy.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "set_precipitation_threshold",
+    parameter = "u8",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_set_precipitation_threshold<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let threshold: u8 = ctx.parameter_cursor().get()?;
+    ensure!(threshold <= 100, ContractError::InvalidThreshold);
+    host.state_mut().precipitation_threshold = threshold;
+    Ok(())
+}
 
-        // Set up context
-        let parameter = to_bytes(&ICECREAM_VENDOR);
-        ctx.set_owner(INVOKER_ADDR);
-        ctx.set_invoker(INVOKER_ADDR);
-        ctx.set_parameter(&parameter);
-        host.set_self_balance(ICECREAM_PRICE); // This should be the balance prior to the call plus the incoming amount.
+/// Query the weather service and update the cached weather, regardless of the
+/// TTL. Returns the new weather.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "force_refresh",
+    return_value = "Weather",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_force_refresh<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Weather> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    query_weather(ctx, host)
+}
 
-        // Set up a mock invocation for the weather service.
-        host.setup_mock_entrypoint(
-            WEATHER_SERVICE,
-            OwnedEntrypointName::new_unchecked("get".into()),
-            MockFn::returning_ok(Weather::Sunny),
-        );
+/// Set the commission taken on every purchase, in basis points.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "set_commission",
+    parameter = "u16",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_set_commission<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let commission_bps: u16 = ctx.parameter_cursor().get()?;
+    host.state_mut().commission_bps =
+        BasisPoints::new(commission_bps).ok_or(ContractError::InvalidCommission)?;
+    Ok(())
+}
 
-        // Act
-        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE)
-            .expect_report("Calling buy_icecream failed.");
+/// Transfer the collected commissions to the owner.
+/// Only the owner of the contract can do so.
+#[receive(contract = "icecream", name = "withdraw_fees", mutable, error = "ContractError")]
+fn contract_withdraw_fees<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let owner = ctx.owner();
+    ensure_eq!(Address::Account(owner), ctx.sender(), ContractError::Unauthenticated);
+    let fees = mem::replace(&mut host.state_mut().fees, Amount::zero());
+    host.invoke_transfer(&owner, fees)?;
+    Ok(())
+}
 
-        // Assert
-        assert!(host.transfer_occurred(&ICECREAM_VENDOR, ICECREAM_PRICE));
-        assert!(host.get_transfers_to(INVOKER_ADDR).is_empty()); // Check that
-                                                                 // no
-                                                                 // transfers to
+/// Set the entrypoint used to pay contract vendors. The entrypoint is invoked
+/// with the account address of the buyer as parameter.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "set_vendor_entrypoint",
+    parameter = "OwnedEntrypointName",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_set_vendor_entrypoint<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let entrypoint: OwnedEntrypointName = ctx.parameter_cursor().get()?;
+    host.state_mut().vendor_entrypoint = entrypoint;
+    Ok(())
+}
+
+/// Set for how long sunny-day payments are held in escrow. Setting it to zero
+/// disables escrow. Payments already held in escrow are not affected.
+/// The window can be at most `MAX_REFUND_WINDOW_DAYS` days.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "set_refund_window",
+    parameter = "Duration",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_set_refund_window<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let refund_window: Duration = ctx.parameter_cursor().get()?;
+    ensure!(
+        refund_window <= Duration::from_days(MAX_REFUND_WINDOW_DAYS),
+        ContractError::InvalidRefundWindow
+    );
+    host.state_mut().refund_window = refund_window;
+    Ok(())
+}
+
+/// Add a vendor to the list of approved vendors.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "add_vendor",
+    parameter = "Address",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_add_vendor<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let vendor: Address = ctx.parameter_cursor().get()?;
+    host.state_mut().vendors.insert(vendor);
+    Ok(())
+}
+
+/// Remove a vendor from the list of approved vendors.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "icecream",
+    name = "remove_vendor",
+    parameter = "Address",
+    mutable,
+    error = "ContractError"
+)]
+fn contract_remove_vendor<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let vendor: Address = ctx.parameter_cursor().get()?;
+    host.state_mut().vendors.remove(&vendor);
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The weather in a region, and when it was observed.
+#[derive(Serialize, SchemaType, Clone, Copy)]
+struct Observation {
+    /// The observed weather.
+    weather:     Weather,
+    /// The slot time of the update with the observation.
+    observed_at: Timestamp,
+}
+
+/// The state of the weather service.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct WeatherState<S> {
+    /// The latest observation in each region.
+    regions:     StateMap<RegionId, Observation, S>,
+    /// The addresses allowed to update the weather have the `UPDATER` role.
+    roles:       Roles<S>,
+    /// The forecasts for each region, ordered by the time they are for.
+    forecasts:   StateMap<RegionId, Vec<(Timestamp, Weather)>, S>,
+    /// The contracts notified of weather updates, with the entrypoint to
+    /// invoke.
+    subscribers: StateMap<ContractAddress, OwnedEntrypointName, S>,
+    /// Every observation made, with its region, indexed by the order they were
+    /// made in. Since they are made in order of slot time, they are also
+    /// ordered by the time of observation.
+    history:     StateMap<u64, (RegionId, Observation), S>,
+    /// The number of observations in the history.
+    history_len: u64,
+}
+
+impl<S: HasStateApi> WeatherState<S> {
+    /// Update the observation in a region and add it to the history.
+    fn observe(&mut self, region: RegionId, observation: Observation) {
+        self.regions.insert(region, observation);
+        self.history.insert(self.history_len, (region, observation));
+        self.history_len += 1;
+    }
+}
+
+/// The parameter type for the weather contract function `get_fresh`.
+#[derive(Serialize, SchemaType)]
+struct GetFreshParams {
+    /// The region to get the weather for.
+    region:  RegionId,
+    /// The maximum age of the observation.
+    max_age: Duration,
+}
+
+/// The parameter type for the weather contract function `set`.
+#[derive(Serialize, SchemaType)]
+struct SetWeatherParams {
+    /// The region to update.
+    region:  RegionId,
+    /// The new weather in the region.
+    weather: Weather,
+}
+
+/// A weather update sent to the contracts subscribed to the weather service.
+#[derive(Serialize, SchemaType)]
+struct WeatherUpdate {
+    /// The region where the weather was updated.
+    region:  RegionId,
+    /// The new weather in the region.
+    weather: Weather,
+}
+
+/// The parameter type for the weather contract function `history`.
+#[derive(Serialize, SchemaType)]
+struct HistoryParams {
+    /// The start of the time window, inclusive.
+    from: Timestamp,
+    /// The end of the time window, inclusive.
+    to:   Timestamp,
+}
+
+/// The parameter type for the weather contract function `set_forecast`.
+#[derive(Serialize, SchemaType)]
+struct SetForecastParams {
+    /// The region to forecast the weather for.
+    region:    RegionId,
+    /// The forecasted weather, with the time each forecast is for.
+    forecasts: Vec<(Timestamp, Weather)>,
+}
+
+/// The parameter type for the weather contract function `get_forecast`.
+#[derive(Serialize, SchemaType)]
+struct GetForecastParams {
+    /// The region to get the forecast for.
+    region: RegionId,
+    /// The time to get the forecast for.
+    time:   Timestamp,
+}
+
+/// The role of the addresses allowed to update the weather.
+const UPDATER: Role = 0;
+
+/// Initialse the weather service with the weather in some regions.
+#[init(
+    contract = "weather",
+    parameter = "Vec<(RegionId, Weather)>",
+    event = "RoleEvent",
+    enable_logger
+)]
+fn weather_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+    logger: &mut impl HasLogger,
+) -> InitResult<WeatherState<S>> {
+    let observations: Vec<(RegionId, Weather)> = ctx.parameter_cursor().get()?;
+    let observed_at = ctx.metadata().slot_time();
+    let mut roles = Roles::new(state_builder);
+    roles.grant_role(UPDATER, Address::Account(ctx.init_origin()), logger)?;
+    let mut state = WeatherState {
+        regions: state_builder.new_map(),
+        roles,
+        forecasts: state_builder.new_map(),
+        subscribers: state_builder.new_map(),
+        history: state_builder.new_map(),
+        history_len: 0,
+    };
+    for (region, weather) in observations {
+        state.observe(region, Observation {
+            weather,
+            observed_at,
+        });
+    }
+    Ok(state)
+}
+
+/// Get the current weather in a region.
+#[receive(
+    contract = "weather",
+    name = "get",
+    parameter = "RegionId",
+    return_value = "Weather",
+    error = "ContractError"
+)]
+fn weather_get<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<Weather> {
+    let region: RegionId = ctx.parameter_cursor().get()?;
+    let observation = host.state().regions.get(&region).ok_or(ContractError::UnknownRegion)?;
+    Ok(observation.weather)
+}
+
+/// Get a simplified view of the current weather in a region, which is rainy if
+/// the probability of precipitation is 50% or more.
+#[receive(
+    contract = "weather",
+    name = "get_simple",
+    parameter = "RegionId",
+    return_value = "SimpleWeather",
+    error = "ContractError"
+)]
+fn weather_get_simple<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<SimpleWeather> {
+    let region: RegionId = ctx.parameter_cursor().get()?;
+    let observation = host.state().regions.get(&region).ok_or(ContractError::UnknownRegion)?;
+    Ok(observation.weather.simplify(SIMPLE_PRECIPITATION_THRESHOLD))
+}
+
+/// Get the current weather in a region, if it was observed no longer than
+/// `max_age` ago. Rejects with `StaleData` otherwise.
+#[receive(
+    contract = "weather",
+    name = "get_fresh",
+    parameter = "GetFreshParams",
+    return_value = "Weather",
+    error = "ContractError"
+)]
+fn weather_get_fresh<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<Weather> {
+    let params: GetFreshParams = ctx.parameter_cursor().get()?;
+    let observation =
+        host.state().regions.get(&params.region).ok_or(ContractError::UnknownRegion)?;
+    // An observation from the future, relative to the slot time, cannot happen, so
+    // it is treated as stale to be on the safe side.
+    let age = ctx
+        .metadata()
+        .slot_time()
+        .duration_since(observation.observed_at)
+        .ok_or(ContractError::StaleData)?;
+    ensure!(age <= params.max_age, ContractError::StaleData);
+    Ok(observation.weather)
+}
+
+/// Get the observations made in the given time window, in the order they were
+/// made, together with the region of each observation.
+#[receive(
+    contract = "weather",
+    name = "history",
+    parameter = "HistoryParams",
+    return_value = "Vec<(RegionId, Observation)>",
+    error = "ContractError"
+)]
+fn weather_history<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<Vec<(RegionId, Observation)>> {
+    let params: HistoryParams = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    let observed_at = |id: u64| state.history.get(&id).unwrap_abort().1.observed_at;
+    // Find the first observation in the window by binary search, since the
+    // history is ordered by time of observation.
+    let mut low = 0;
+    let mut high = state.history_len;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if observed_at(mid) < params.from {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    let mut observations = Vec::new();
+    for id in low..state.history_len {
+        let entry = state.history.get(&id).unwrap_abort();
+        if entry.1.observed_at > params.to {
+            break;
+        }
+        observations.push(*entry);
+    }
+    Ok(observations)
+}
+
+/// Update the weather in a region.
+/// Only authorized updaters can do so.
+#[receive(
+    contract = "weather",
+    name = "set",
+    parameter = "SetWeatherParams",
+    mutable,
+    error = "ContractError"
+)]
+fn weather_set<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_role!(host.state().roles, UPDATER, ctx.sender(), ContractError::Unauthenticated);
+    let params: SetWeatherParams = ctx.parameter_cursor().get()?;
+    host.state_mut().observe(params.region, Observation {
+        weather:     params.weather,
+        observed_at: ctx.metadata().slot_time(),
+    });
+
+    let update = WeatherUpdate {
+        region:  params.region,
+        weather: params.weather,
+    };
+    let subscribers: Vec<(ContractAddress, OwnedEntrypointName)> = host
+        .state()
+        .subscribers
+        .iter()
+        .map(|(address, entrypoint)| (*address, entrypoint.clone()))
+        .collect();
+    for (address, entrypoint) in subscribers {
+        // A failing subscriber should not prevent the update, nor the
+        // notification of the other subscribers, so the result is ignored.
+        let _ = host.invoke_contract(
+            &address,
+            &update,
+            entrypoint.as_entrypoint_name(),
+            Amount::zero(),
+        );
+    }
+    Ok(())
+}
+
+/// Subscribe the sending contract to weather updates. The given entrypoint is
+/// invoked with a `WeatherUpdate` whenever the weather is set, replacing any
+/// entrypoint from an earlier subscription.
+/// Only contracts can subscribe.
+#[receive(
+    contract = "weather",
+    name = "subscribe",
+    parameter = "OwnedEntrypointName",
+    mutable,
+    error = "ContractError"
+)]
+fn weather_subscribe<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let subscriber = match ctx.sender() {
+        Address::Contract(address) => address,
+        Address::Account(_) => bail!(ContractError::OnlyContract),
+    };
+    let entrypoint: OwnedEntrypointName = ctx.parameter_cursor().get()?;
+    host.state_mut().subscribers.insert(subscriber, entrypoint);
+    Ok(())
+}
+
+/// Unsubscribe the sending contract from weather updates.
+#[receive(contract = "weather", name = "unsubscribe", mutable, error = "ContractError")]
+fn weather_unsubscribe<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    if let Address::Contract(subscriber) = ctx.sender() {
+        host.state_mut().subscribers.remove(&subscriber);
+    }
+    Ok(())
+}
+
+/// Add forecasts for a region, replacing any existing forecast for the same
+/// time. Forecasts for times before the current slot time are pruned.
+/// Only authorized updaters can do so.
+#[receive(
+    contract = "weather",
+    name = "set_forecast",
+    parameter = "SetForecastParams",
+    mutable,
+    error = "ContractError"
+)]
+fn weather_set_forecast<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure_role!(host.state().roles, UPDATER, ctx.sender(), ContractError::Unauthenticated);
+    let params: SetForecastParams = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    let mut forecasts = match host.state().forecasts.get(&params.region) {
+        Some(forecasts) => forecasts.clone(),
+        None => Vec::new(),
+    };
+    for (time, weather) in params.forecasts {
+        match forecasts.binary_search_by_key(&time, |(t, _)| *t) {
+            Ok(i) => forecasts[i].1 = weather,
+            Err(i) => forecasts.insert(i, (time, weather)),
+        }
+    }
+    forecasts.retain(|(time, _)| *time >= now);
+    host.state_mut().forecasts.insert(params.region, forecasts);
+    Ok(())
+}
+
+/// Get the forecast for a region at the given time, which is the latest
+/// forecast for a time at or before it. Rejects with `NoForecast` if there is
+/// no such forecast.
+#[receive(
+    contract = "weather",
+    name = "get_forecast",
+    parameter = "GetForecastParams",
+    return_value = "Weather",
+    error = "ContractError"
+)]
+fn weather_get_forecast<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<WeatherState<S>, StateApiType = S>,
+) -> ContractResult<Weather> {
+    let params: GetForecastParams = ctx.parameter_cursor().get()?;
+    let forecasts = host.state().forecasts.get(&params.region).ok_or(ContractError::NoForecast)?;
+    forecasts
+        .iter()
+        .rev()
+        .find(|(time, _)| *time <= params.time)
+        .map(|(_, weather)| *weather)
+        .ok_or(ContractError::NoForecast)
+}
+
+/// Allow an address to update the weather.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "weather",
+    name = "authorize",
+    parameter = "Address",
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn weather_authorize<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<WeatherState<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let updater: Address = ctx.parameter_cursor().get()?;
+    host.state_mut().roles.grant_role(UPDATER, updater, logger)?;
+    Ok(())
+}
+
+/// Stop an address from updating the weather.
+/// Only the owner of the contract can do so.
+#[receive(
+    contract = "weather",
+    name = "revoke",
+    parameter = "Address",
+    mutable,
+    enable_logger,
+    error = "ContractError"
+)]
+fn weather_revoke<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<WeatherState<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_eq!(Address::Account(ctx.owner()), ctx.sender(), ContractError::Unauthenticated);
+    let updater: Address = ctx.parameter_cursor().get()?;
+    host.state_mut().roles.revoke_role(UPDATER, updater, logger)?;
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::RoleChange;
+    use test_infrastructure::*;
+
+    const INVOKER_ADDR: AccountAddress = AccountAddress([0; 32]);
+    const WEATHER_SERVICE: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const ICECREAM_VENDOR: AccountAddress = AccountAddress([1; 32]);
+    const ICECREAM_PRICE: Amount = Amount {
+        micro_ccd: 6000000, // 6 CCD
+    };
+    const REGION: RegionId = 7;
+    const SUNNY: Weather = Weather {
+        temperature:               250,
+        precipitation_probability: 10,
+        wind_speed:                30,
+    };
+    const RAINY: Weather = Weather {
+        temperature:               120,
+        precipitation_probability: 90,
+        wind_speed:                80,
+    };
+    const CURRENT_TIME: Timestamp = Timestamp::from_timestamp_millis(1_000_000);
+    const OTHER_VENDOR: Address = Address::Account(AccountAddress([2; 32]));
+    const VENDOR_CONTRACT: ContractAddress = ContractAddress {
+        index:    2,
+        subindex: 0,
+    };
+
+    /// The state with the weather service and `ICECREAM_VENDOR` and
+    /// `VENDOR_CONTRACT` as the approved vendors.
+    fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
+        let mut vendors = state_builder.new_set();
+        vendors.insert(Address::Account(ICECREAM_VENDOR));
+        vendors.insert(Address::Contract(VENDOR_CONTRACT));
+        State {
+            weather_service: WEATHER_SERVICE,
+            region: REGION,
+            vendors,
+            vendor_entrypoint: OwnedEntrypointName::new_unchecked("deposit".into()),
+            cached_weather: None,
+            weather_ttl: Duration::from_millis(0),
+            precipitation_threshold: SIMPLE_PRECIPITATION_THRESHOLD,
+            commission_bps: BasisPoints::default(),
+            fees: Amount::zero(),
+            purchases: state_builder.new_map(),
+            next_purchase_id: 0,
+            refund_window: Duration::from_millis(0),
+            escrows: state_builder.new_map(),
+            pausable: Pausable::new(),
+            ownable: Ownable::new(Address::Account(INVOKER_ADDR)),
+        }
+    }
+
+    #[concordium_test]
+    fn test_sunny_days() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE); // This should be the balance prior to the call plus the incoming amount.
+
+        // Set up a mock invocation for the weather service.
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let region: RegionId = from_bytes(parameter.0).expect_report("Invalid region");
+                claim_eq!(region, REGION, "The configured region should be queried.");
+                Ok((false, SUNNY))
+            }),
+        );
+
+        // Act
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        assert!(host.transfer_occurred(&ICECREAM_VENDOR, ICECREAM_PRICE));
+        assert!(host.get_transfers_to(INVOKER_ADDR).is_empty()); // Check that
+                                                                 // no
+                                                                 // transfers to
                                                                  // the invoker
                                                                  // occured.
+        claim_eq!(
+            logger.logs,
+            [to_bytes(&IcecreamEvent::Purchase(PurchaseRecord {
+                buyer:     INVOKER_ADDR,
+                vendor:    Address::Account(ICECREAM_VENDOR),
+                amount:    ICECREAM_PRICE,
+                weather:   SimpleWeather::Sunny,
+                timestamp: CURRENT_TIME,
+            }))],
+            "The purchase should be logged."
+        );
     }
 
     #[concordium_test]
     fn test_rainy_days() {
         // Arrange
         let mut ctx = TestReceiveContext::empty();
-        let state = State {
-            weather_service: WEATHER_SERVICE,
-        };
-        let mut host = TestHost::new(state, TestStateBuilder::new());
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
 
         // Set up context
-        let parameter = to_bytes(&ICECREAM_VENDOR);
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
         ctx.set_owner(INVOKER_ADDR);
         ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
         ctx.set_parameter(&parameter);
         host.set_self_balance(ICECREAM_PRICE);
 
@@ -252,31 +1485,43 @@ mod tests {
         host.setup_mock_entrypoint(
             WEATHER_SERVICE,
             OwnedEntrypointName::new_unchecked("get".into()),
-            MockFn::returning_ok(Weather::Rainy),
+            MockFn::returning_ok(RAINY),
         );
 
         // Act
-        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE)
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
             .expect_report("Calling buy_icecream failed.");
 
         // Assert
         assert!(host.transfer_occurred(&INVOKER_ADDR, ICECREAM_PRICE));
         assert_eq!(host.get_transfers(), &[(INVOKER_ADDR, ICECREAM_PRICE)]); // Check that this is the only transfer.
+        claim_eq!(
+            logger.logs,
+            [to_bytes(&IcecreamEvent::Purchase(PurchaseRecord {
+                buyer:     INVOKER_ADDR,
+                vendor:    Address::Account(ICECREAM_VENDOR),
+                amount:    ICECREAM_PRICE,
+                weather:   SimpleWeather::Rainy,
+                timestamp: CURRENT_TIME,
+            }))],
+            "The refunded purchase should be logged."
+        );
     }
 
     #[concordium_test]
     fn test_missing_icecream_vendor() {
         // Arrange
         let mut ctx = TestReceiveContext::empty();
-        let state = State {
-            weather_service: WEATHER_SERVICE,
-        };
-        let mut host = TestHost::new(state, TestStateBuilder::new());
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
 
         // Set up context
-        let parameter = to_bytes(&ICECREAM_VENDOR);
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
         ctx.set_owner(INVOKER_ADDR);
         ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
         ctx.set_parameter(&parameter);
         host.set_self_balance(ICECREAM_PRICE);
 
@@ -288,11 +1533,11 @@ mod tests {
         host.setup_mock_entrypoint(
             WEATHER_SERVICE,
             OwnedEntrypointName::new_unchecked("get".into()),
-            MockFn::returning_ok(Weather::Sunny),
+            MockFn::returning_ok(SUNNY),
         );
 
         // Act + Assert
-        let result = contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE);
+        let result = contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger);
         claim_eq!(result, Err(ContractError::TransferError));
     }
 
@@ -300,15 +1545,16 @@ mod tests {
     fn test_missing_weather_service() {
         // Arrange
         let mut ctx = TestReceiveContext::empty();
-        let state = State {
-            weather_service: WEATHER_SERVICE,
-        };
-        let mut host = TestHost::new(state, TestStateBuilder::new());
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
 
         // Set up context
-        let parameter = to_bytes(&ICECREAM_VENDOR);
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
         ctx.set_owner(INVOKER_ADDR);
         ctx.set_parameter(&parameter);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
 
         // Set up mock invocation
         host.setup_mock_entrypoint(
@@ -318,7 +1564,1240 @@ mod tests {
         );
 
         // Act + Assert (should panic)
-        let result = contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE);
+        let result = contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger);
         claim_eq!(result, Err(ContractError::ContractError));
     }
+
+    #[concordium_test]
+    fn test_unknown_vendor() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let parameter = to_bytes(&OTHER_VENDOR);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE);
+
+        // Set up mock invocation
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+
+        // Act + Assert
+        let result = contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger);
+        claim_eq!(result, Err(ContractError::UnknownVendor));
+        claim!(host.get_transfers().is_empty(), "No transfers should occur.");
+    }
+
+    #[concordium_test]
+    fn test_add_and_remove_vendor() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&OTHER_VENDOR);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        contract_add_vendor(&ctx, &mut host).expect_report("Adding a vendor failed.");
+        claim!(host.state().vendors.contains(&OTHER_VENDOR), "Vendor should be approved.");
+
+        contract_remove_vendor(&ctx, &mut host).expect_report("Removing a vendor failed.");
+        claim!(!host.state().vendors.contains(&OTHER_VENDOR), "Vendor should be removed.");
+        claim!(
+            host.state().vendors.contains(&Address::Account(ICECREAM_VENDOR)),
+            "Other vendors should remain."
+        );
+    }
+
+    #[concordium_test]
+    fn test_add_vendor_not_owner() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&OTHER_VENDOR);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(OTHER_VENDOR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_add_vendor(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+        claim!(!host.state().vendors.contains(&OTHER_VENDOR), "Vendor should not be approved.");
+    }
+
+    #[concordium_test]
+    fn test_sunny_days_with_commission() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        // A commission of 2.5%.
+        state.commission_bps = BasisPoints::new(250).expect_report("The commission is valid");
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE);
+
+        // Set up mock invocation
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+
+        // Act
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        let fee = Amount::from_micro_ccd(150000);
+        claim_eq!(host.get_transfers(), &[(ICECREAM_VENDOR, ICECREAM_PRICE - fee)]);
+        claim_eq!(host.state().fees, fee);
+    }
+
+    #[concordium_test]
+    fn test_set_commission_too_large() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&(BasisPoints::MAX.get() + 1));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_set_commission(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::InvalidCommission));
+        claim_eq!(host.state().commission_bps, BasisPoints::default());
+    }
+
+    #[concordium_test]
+    fn test_set_refund_window_too_long() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&Duration::from_millis(u64::MAX));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_set_refund_window(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::InvalidRefundWindow));
+        claim_eq!(host.state().refund_window, Duration::from_millis(0));
+    }
+
+    #[concordium_test]
+    fn test_withdraw_fees() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        let fees = Amount::from_micro_ccd(1000);
+        state.fees = fees;
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(fees);
+
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+
+        // Act
+        contract_withdraw_fees(&ctx, &mut host).expect_report("Withdrawing fees failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[(INVOKER_ADDR, fees)]);
+        claim_eq!(host.state().fees, Amount::zero());
+    }
+
+    #[concordium_test]
+    fn test_sunny_days_contract_vendor() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let parameter = to_bytes(&Address::Contract(VENDOR_CONTRACT));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE);
+
+        // Set up mock invocations for the weather service and the vendor.
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+        host.setup_mock_entrypoint(
+            VENDOR_CONTRACT,
+            OwnedEntrypointName::new_unchecked("deposit".into()),
+            MockFn::new_v1(|parameter, amount, _balance, _state| {
+                let buyer: AccountAddress = from_bytes(parameter.0).expect_report("Invalid buyer");
+                claim_eq!(buyer, INVOKER_ADDR, "The buyer should be passed to the vendor.");
+                claim_eq!(amount, ICECREAM_PRICE, "The vendor should receive the price.");
+                Ok((false, ()))
+            }),
+        );
+
+        // Act
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        claim!(host.get_transfers().is_empty(), "No account transfers should occur.");
+    }
+
+    #[concordium_test]
+    fn test_cached_weather_is_reused() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.weather_ttl = Duration::from_minutes(10);
+        state.cached_weather = Some((SUNNY, CURRENT_TIME));
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_parameter(&parameter);
+        ctx.set_metadata_slot_time(
+            CURRENT_TIME.checked_add(Duration::from_minutes(9)).unwrap_abort(),
+        );
+        host.set_self_balance(ICECREAM_PRICE);
+
+        // Set up a weather service which would report rain, to check that it is not
+        // queried.
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(RAINY),
+        );
+
+        // Act
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[(ICECREAM_VENDOR, ICECREAM_PRICE)]);
+    }
+
+    #[concordium_test]
+    fn test_paused() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let parameter = to_bytes(&true);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_parameter(&parameter);
+        contract_set_paused(&ctx, &mut host).expect_report("Pausing failed.");
+
+        // Act
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_parameter(&parameter);
+        let result = contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger);
+
+        // Assert
+        claim_eq!(result, Err(ContractError::ContractPaused));
+        claim!(host.get_transfers().is_empty(), "No transfers should occur.");
+    }
+
+    #[concordium_test]
+    fn test_transfer_ownership() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        let new_owner = Address::Account(ICECREAM_VENDOR);
+        let new_weather_service = ContractAddress {
+            index:    3,
+            subindex: 0,
+        };
+
+        let parameter = to_bytes(&new_owner);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_parameter(&parameter);
+        contract_transfer_ownership(&ctx, &mut host, &mut logger)
+            .expect_report("Transferring the ownership failed.");
+
+        // The new owner cannot replace the weather service before accepting.
+        let parameter = to_bytes(&new_weather_service);
+        ctx.set_sender(new_owner);
+        ctx.set_parameter(&parameter);
+        let result = contract_replace_weather_service(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+
+        // Act
+        contract_accept_ownership(&ctx, &mut host, &mut logger)
+            .expect_report("Accepting the ownership failed.");
+        contract_replace_weather_service(&ctx, &mut host)
+            .expect_report("Replacing the weather service failed.");
+
+        // Assert
+        claim_eq!(host.state().weather_service, new_weather_service);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        let result = contract_replace_weather_service(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Unauthenticated), "The old owner should be replaced.");
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&IcecreamEvent::Ownership(OwnershipEvent::TransferStarted {
+                    owner: Address::Account(INVOKER_ADDR),
+                    new_owner,
+                })),
+                to_bytes(&IcecreamEvent::Ownership(OwnershipEvent::Transferred {
+                    previous_owner: Address::Account(INVOKER_ADDR),
+                    new_owner,
+                })),
+            ],
+            "Both steps of the transfer should be logged."
+        );
+    }
+
+    #[concordium_test]
+    fn test_accept_ownership_not_proposed() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_sender(Address::Account(ICECREAM_VENDOR));
+
+        // Act
+        let result = contract_accept_ownership(&ctx, &mut host, &mut logger);
+
+        // Assert
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+        claim_eq!(host.state().ownable.owner(), Address::Account(INVOKER_ADDR));
+        claim!(logger.logs.is_empty(), "Nothing should be logged.");
+    }
+
+    #[concordium_test]
+    fn test_precipitation_threshold() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let parameter = to_bytes(&5u8);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_parameter(&parameter);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        host.set_self_balance(ICECREAM_PRICE);
+
+        // With a threshold of 5%, a 10% chance of precipitation counts as rain.
+        contract_set_precipitation_threshold(&ctx, &mut host)
+            .expect_report("Setting the threshold failed.");
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+
+        // Act
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_parameter(&parameter);
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[(INVOKER_ADDR, ICECREAM_PRICE)]);
+    }
+
+    #[concordium_test]
+    fn test_set_precipitation_threshold_too_large() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&101u8);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_set_precipitation_threshold(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::InvalidThreshold));
+        claim_eq!(host.state().precipitation_threshold, SIMPLE_PRECIPITATION_THRESHOLD);
+    }
+
+    #[concordium_test]
+    fn test_expired_weather_is_queried() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.weather_ttl = Duration::from_minutes(10);
+        state.cached_weather = Some((SUNNY, CURRENT_TIME));
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let now = CURRENT_TIME.checked_add(Duration::from_minutes(10)).unwrap_abort();
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_parameter(&parameter);
+        ctx.set_metadata_slot_time(now);
+        host.set_self_balance(ICECREAM_PRICE);
+
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(RAINY),
+        );
+
+        // Act
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[(INVOKER_ADDR, ICECREAM_PRICE)]);
+        claim!(
+            host.state().cached_weather == Some((RAINY, now)),
+            "The cached weather should be updated."
+        );
+    }
+
+    #[concordium_test]
+    fn test_force_refresh() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.weather_ttl = Duration::from_minutes(10);
+        state.cached_weather = Some((SUNNY, CURRENT_TIME));
+        let mut host = TestHost::new(state, state_builder);
+
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(RAINY),
+        );
+
+        // Act
+        let weather = contract_force_refresh(&ctx, &mut host).expect_report("Refresh failed.");
+
+        // Assert
+        claim_eq!(weather, RAINY, "The refreshed weather should be returned.");
+        claim_eq!(
+            host.state().cached_weather,
+            Some((RAINY, CURRENT_TIME)),
+            "The cached weather should be updated."
+        );
+    }
+
+    /// A purchase made at the given time, for creating test data.
+    fn purchase_at(timestamp: Timestamp) -> PurchaseRecord {
+        PurchaseRecord {
+            buyer: INVOKER_ADDR,
+            vendor: Address::Account(ICECREAM_VENDOR),
+            amount: ICECREAM_PRICE,
+            weather: SimpleWeather::Sunny,
+            timestamp,
+        }
+    }
+
+    #[concordium_test]
+    fn test_purchase_history_is_bounded() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+
+        for i in 0..MAX_PURCHASE_HISTORY + 5 {
+            let id = state.record_purchase(purchase_at(Timestamp::from_timestamp_millis(i)));
+            claim_eq!(id, i, "Purchase ids should be consecutive.");
+        }
+
+        claim!(state.purchases.get(&4).is_none(), "The oldest purchases should be removed.");
+        claim!(state.purchases.get(&5).is_some(), "The most recent purchases should be kept.");
+        claim_eq!(state.purchases.iter().count() as u64, MAX_PURCHASE_HISTORY);
+    }
+
+    #[concordium_test]
+    fn test_view_purchases_pagination() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        for i in 0..5 {
+            state.record_purchase(purchase_at(Timestamp::from_timestamp_millis(i)));
+        }
+        let host = TestHost::new(state, state_builder);
+
+        // Act
+        let parameter = to_bytes(&ViewPurchasesParams {
+            offset: 1,
+            limit:  2,
+        });
+        ctx.set_parameter(&parameter);
+        let page = contract_view_purchases(&ctx, &host).expect_report("Viewing failed.");
+
+        // Assert
+        let ids: Vec<u64> = page.iter().map(|(id, _)| *id).collect();
+        claim_eq!(ids, vec![3, 2], "The page should skip the most recent purchase.");
+        claim_eq!(page[0].1.timestamp, Timestamp::from_timestamp_millis(3));
+
+        // Paging beyond the history gives an empty page.
+        let parameter = to_bytes(&ViewPurchasesParams {
+            offset: 5,
+            limit:  2,
+        });
+        ctx.set_parameter(&parameter);
+        let page = contract_view_purchases(&ctx, &host).expect_report("Viewing failed.");
+        claim!(page.is_empty(), "The page should be empty.");
+    }
+
+    #[concordium_test]
+    fn test_sunny_days_with_escrow() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.refund_window = Duration::from_minutes(30);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        // Set up context
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE);
+
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+
+        // Act
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // Assert
+        claim!(host.get_transfers().is_empty(), "The payment should be held in escrow.");
+        let escrow = host.state().escrows.get(&0).map(|e| e.clone()).expect_report("No escrow");
+        claim_eq!(escrow.amount, ICECREAM_PRICE);
+        claim_eq!(escrow.fee, Amount::zero());
+        claim_eq!(
+            escrow.window_closes,
+            CURRENT_TIME.checked_add(Duration::from_minutes(30)).unwrap_abort()
+        );
+    }
+
+    /// A host with `ICECREAM_PRICE` held in escrow for purchase `0`, with the
+    /// refund window closing 30 minutes after `CURRENT_TIME`.
+    fn host_with_escrow() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.escrows.insert(0, Escrow {
+            buyer:         INVOKER_ADDR,
+            vendor:        Address::Account(ICECREAM_VENDOR),
+            amount:        ICECREAM_PRICE,
+            fee:           Amount::zero(),
+            window_closes: CURRENT_TIME.checked_add(Duration::from_minutes(30)).unwrap_abort(),
+        });
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(ICECREAM_PRICE);
+        host
+    }
+
+    #[concordium_test]
+    fn test_request_refund() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut host = host_with_escrow();
+        let mut logger = TestLogger::init();
+        let parameter = to_bytes(&0u64);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+
+        // Act
+        contract_request_refund(&ctx, &mut host, &mut logger).expect_report("Refund failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[(INVOKER_ADDR, ICECREAM_PRICE)]);
+        claim!(host.state().escrows.get(&0).is_none(), "The escrow should be removed.");
+        claim_eq!(logger.logs, [to_bytes(&IcecreamEvent::Refund(0))]);
+    }
+
+    #[concordium_test]
+    fn test_request_refund_after_window() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut host = host_with_escrow();
+        let mut logger = TestLogger::init();
+        let parameter = to_bytes(&0u64);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(
+            CURRENT_TIME.checked_add(Duration::from_minutes(30)).unwrap_abort(),
+        );
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_request_refund(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(ContractError::RefundWindowClosed));
+    }
+
+    #[concordium_test]
+    fn test_claim() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut host = host_with_escrow();
+        let mut logger = TestLogger::init();
+        let parameter = to_bytes(&0u64);
+        ctx.set_sender(Address::Account(ICECREAM_VENDOR));
+        ctx.set_metadata_slot_time(
+            CURRENT_TIME.checked_add(Duration::from_minutes(30)).unwrap_abort(),
+        );
+        ctx.set_parameter(&parameter);
+
+        // Act
+        contract_claim(&ctx, &mut host, &mut logger).expect_report("Claim failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[(ICECREAM_VENDOR, ICECREAM_PRICE)]);
+        claim!(host.state().escrows.get(&0).is_none(), "The escrow should be removed.");
+        claim_eq!(logger.logs, [to_bytes(&IcecreamEvent::Claim(0))]);
+    }
+
+    #[concordium_test]
+    fn test_claim_keeps_commission_at_purchase() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.refund_window = Duration::from_minutes(30);
+        // A commission of 2.5%.
+        state.commission_bps = BasisPoints::new(250).expect_report("The commission is valid");
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let parameter = to_bytes(&Address::Account(ICECREAM_VENDOR));
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE);
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+        contract_buy_icecream(&ctx, &mut host, ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream failed.");
+
+        // The owner raises the commission to 100% while the payment is in escrow.
+        let parameter = to_bytes(&BasisPoints::MAX.get());
+        ctx.set_parameter(&parameter);
+        contract_set_commission(&ctx, &mut host).expect_report("Setting the commission failed.");
+
+        // Act
+        let parameter = to_bytes(&0u64);
+        ctx.set_sender(Address::Account(ICECREAM_VENDOR));
+        ctx.set_metadata_slot_time(
+            CURRENT_TIME.checked_add(Duration::from_minutes(30)).unwrap_abort(),
+        );
+        ctx.set_parameter(&parameter);
+        contract_claim(&ctx, &mut host, &mut logger).expect_report("Claim failed.");
+
+        // Assert
+        let fee = Amount::from_micro_ccd(150000);
+        claim_eq!(host.get_transfers(), &[(ICECREAM_VENDOR, ICECREAM_PRICE - fee)]);
+        claim_eq!(host.state().fees, fee);
+    }
+
+    #[concordium_test]
+    fn test_claim_during_window() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut host = host_with_escrow();
+        let mut logger = TestLogger::init();
+        let parameter = to_bytes(&0u64);
+        ctx.set_sender(Address::Account(ICECREAM_VENDOR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_claim(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(ContractError::RefundWindowOpen));
+        claim!(host.get_transfers().is_empty(), "No transfers should occur.");
+    }
+
+    #[concordium_test]
+    fn test_claim_not_vendor() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut host = host_with_escrow();
+        let mut logger = TestLogger::init();
+        let parameter = to_bytes(&0u64);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(
+            CURRENT_TIME.checked_add(Duration::from_minutes(30)).unwrap_abort(),
+        );
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_claim(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+    }
+
+    #[concordium_test]
+    fn test_batch_sunny_days() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.vendors.insert(OTHER_VENDOR);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let other_price = Amount::from_micro_ccd(2000000);
+        let parameter = to_bytes(&vec![
+            (Address::Account(ICECREAM_VENDOR), ICECREAM_PRICE),
+            (OTHER_VENDOR, other_price),
+        ]);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE + other_price);
+
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(SUNNY),
+        );
+
+        // Act
+        contract_buy_icecream_batch(&ctx, &mut host, ICECREAM_PRICE + other_price, &mut logger)
+            .expect_report("Calling buy_icecream_batch failed.");
+
+        // Assert
+        claim_eq!(host.get_transfers(), &[
+            (ICECREAM_VENDOR, ICECREAM_PRICE),
+            (AccountAddress([2; 32]), other_price)
+        ]);
+        claim_eq!(logger.logs.len(), 2, "Each purchase should be logged.");
+        claim_eq!(host.state().next_purchase_id, 2, "Each purchase should be recorded.");
+    }
+
+    #[concordium_test]
+    fn test_batch_rainy_days() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let parameter = to_bytes(&vec![
+            (Address::Account(ICECREAM_VENDOR), ICECREAM_PRICE),
+            (Address::Account(ICECREAM_VENDOR), ICECREAM_PRICE),
+        ]);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+        host.set_self_balance(ICECREAM_PRICE + ICECREAM_PRICE);
+
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("get".into()),
+            MockFn::returning_ok(RAINY),
+        );
+
+        // Act
+        contract_buy_icecream_batch(&ctx, &mut host, ICECREAM_PRICE + ICECREAM_PRICE, &mut logger)
+            .expect_report("Calling buy_icecream_batch failed.");
+
+        // Assert
+        claim_eq!(
+            host.get_transfers(),
+            &[(INVOKER_ADDR, ICECREAM_PRICE + ICECREAM_PRICE)],
+            "Everything should be refunded in a single transfer."
+        );
+    }
+
+    #[concordium_test]
+    fn test_batch_amount_mismatch() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let parameter = to_bytes(&vec![(Address::Account(ICECREAM_VENDOR), ICECREAM_PRICE)]);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_invoker(INVOKER_ADDR);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = contract_buy_icecream_batch(
+            &ctx,
+            &mut host,
+            ICECREAM_PRICE + ICECREAM_PRICE,
+            &mut logger,
+        );
+        claim_eq!(result, Err(ContractError::AmountMismatch));
+    }
+
+    /// The state of a weather service without any observations, with
+    /// `INVOKER_ADDR` as the only updater.
+    fn initial_weather_state<S: HasStateApi>(
+        state_builder: &mut StateBuilder<S>,
+    ) -> WeatherState<S> {
+        let mut roles = Roles::new(state_builder);
+        roles
+            .grant_role(UPDATER, Address::Account(INVOKER_ADDR), &mut TestLogger::init())
+            .expect_report("Granting the updater role failed.");
+        WeatherState {
+            regions: state_builder.new_map(),
+            roles,
+            forecasts: state_builder.new_map(),
+            subscribers: state_builder.new_map(),
+            history: state_builder.new_map(),
+            history_len: 0,
+        }
+    }
+
+    #[concordium_test]
+    fn test_weather_set_and_get_region() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_weather_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&SetWeatherParams {
+            region:  REGION,
+            weather: RAINY,
+        });
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+
+        // Act
+        weather_set(&ctx, &mut host).expect_report("Setting the weather failed.");
+
+        // Assert
+        let parameter = to_bytes(&REGION);
+        ctx.set_parameter(&parameter);
+        let weather = weather_get(&ctx, &host).expect_report("Getting the weather failed.");
+        claim_eq!(weather, RAINY, "The weather should be updated.");
+
+        let parameter = to_bytes(&(REGION + 1));
+        ctx.set_parameter(&parameter);
+        let result = weather_get(&ctx, &host);
+        claim_eq!(result, Err(ContractError::UnknownRegion));
+    }
+
+    #[concordium_test]
+    fn test_weather_set_unauthorized() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_weather_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&SetWeatherParams {
+            region:  REGION,
+            weather: RAINY,
+        });
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(ICECREAM_VENDOR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        let result = weather_set(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+        claim!(host.state().regions.get(&REGION).is_none(), "The weather should not be set.");
+    }
+
+    #[concordium_test]
+    fn test_weather_get_fresh() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_weather_state(&mut state_builder);
+        state.regions.insert(REGION, Observation {
+            weather:     SUNNY,
+            observed_at: CURRENT_TIME,
+        });
+        let host = TestHost::new(state, state_builder);
+        ctx.set_metadata_slot_time(
+            CURRENT_TIME.checked_add(Duration::from_minutes(5)).unwrap_abort(),
+        );
+
+        // Act + Assert
+        let parameter = to_bytes(&GetFreshParams {
+            region:  REGION,
+            max_age: Duration::from_minutes(5),
+        });
+        ctx.set_parameter(&parameter);
+        let result = weather_get_fresh(&ctx, &host);
+        claim_eq!(result, Ok(SUNNY), "An observation within the max age should be returned.");
+
+        let parameter = to_bytes(&GetFreshParams {
+            region:  REGION,
+            max_age: Duration::from_minutes(4),
+        });
+        ctx.set_parameter(&parameter);
+        let result = weather_get_fresh(&ctx, &host);
+        claim_eq!(result, Err(ContractError::StaleData), "An old observation should be rejected.");
+    }
+
+    #[concordium_test]
+    fn test_weather_get_simple() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_weather_state(&mut state_builder);
+        state.regions.insert(REGION, Observation {
+            weather:     SUNNY,
+            observed_at: CURRENT_TIME,
+        });
+        state.regions.insert(REGION + 1, Observation {
+            weather:     RAINY,
+            observed_at: CURRENT_TIME,
+        });
+        let host = TestHost::new(state, state_builder);
+
+        // Act + Assert
+        let parameter = to_bytes(&REGION);
+        ctx.set_parameter(&parameter);
+        claim_eq!(weather_get_simple(&ctx, &host), Ok(SimpleWeather::Sunny));
+
+        let parameter = to_bytes(&(REGION + 1));
+        ctx.set_parameter(&parameter);
+        claim_eq!(weather_get_simple(&ctx, &host), Ok(SimpleWeather::Rainy));
+    }
+
+    #[concordium_test]
+    fn test_weather_set_notifies_subscribers() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_weather_state(&mut state_builder);
+        let subscriber = ContractAddress {
+            index:    3,
+            subindex: 0,
+        };
+        let failing_subscriber = ContractAddress {
+            index:    4,
+            subindex: 0,
+        };
+        state.subscribers.insert(subscriber, OwnedEntrypointName::new_unchecked("notify".into()));
+        state
+            .subscribers
+            .insert(failing_subscriber, OwnedEntrypointName::new_unchecked("notify".into()));
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&SetWeatherParams {
+            region:  REGION,
+            weather: RAINY,
+        });
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+
+        host.setup_mock_entrypoint(
+            subscriber,
+            OwnedEntrypointName::new_unchecked("notify".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let update: WeatherUpdate = from_bytes(parameter.0).expect_report("Invalid update");
+                claim_eq!(update.region, REGION, "The region should be passed on.");
+                claim_eq!(update.weather, RAINY, "The new weather should be passed on.");
+                Ok((false, ()))
+            }),
+        );
+        host.setup_mock_entrypoint(
+            failing_subscriber,
+            OwnedEntrypointName::new_unchecked("notify".into()),
+            MockFn::returning_err::<()>(CallContractError::Trap),
+        );
+
+        // Act
+        let result = weather_set(&ctx, &mut host);
+
+        // Assert
+        claim_eq!(result, Ok(()), "A failing subscriber should not fail the update.");
+        claim!(host.state().regions.get(&REGION).is_some(), "The weather should be set.");
+    }
+
+    #[concordium_test]
+    fn test_weather_subscribe_and_unsubscribe() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_weather_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let entrypoint = OwnedEntrypointName::new_unchecked("on_weather_update".into());
+        let parameter = to_bytes(&entrypoint);
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        claim_eq!(weather_subscribe(&ctx, &mut host), Err(ContractError::OnlyContract));
+
+        ctx.set_sender(Address::Contract(WEATHER_SERVICE));
+        weather_subscribe(&ctx, &mut host).expect_report("Subscribing failed.");
+        claim!(
+            host.state().subscribers.get(&WEATHER_SERVICE).is_some(),
+            "The contract should be subscribed."
+        );
+
+        weather_unsubscribe(&ctx, &mut host).expect_report("Unsubscribing failed.");
+        claim!(
+            host.state().subscribers.get(&WEATHER_SERVICE).is_none(),
+            "The contract should be unsubscribed."
+        );
+    }
+
+    #[concordium_test]
+    fn test_weather_update_is_cached() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&WeatherUpdate {
+            region:  REGION,
+            weather: RAINY,
+        });
+        ctx.set_parameter(&parameter);
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+
+        // Act + Assert
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        let result = contract_on_weather_update(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+        claim_eq!(host.state().cached_weather, None);
+
+        ctx.set_sender(Address::Contract(WEATHER_SERVICE));
+        contract_on_weather_update(&ctx, &mut host).expect_report("Receiving the update failed.");
+        claim_eq!(host.state().cached_weather, Some((RAINY, CURRENT_TIME)));
+    }
+
+    #[concordium_test]
+    fn test_weather_history() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_weather_state(&mut state_builder);
+        let times: Vec<Timestamp> = (0..5)
+            .map(|hours| CURRENT_TIME.checked_add(Duration::from_hours(hours)).unwrap_abort())
+            .collect();
+        for (i, time) in times.iter().enumerate() {
+            state.observe(REGION, Observation {
+                weather:     if i % 2 == 0 {
+                    SUNNY
+                } else {
+                    RAINY
+                },
+                observed_at: *time,
+            });
+        }
+        let host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&HistoryParams {
+            from: times[1],
+            to:   times[3],
+        });
+        ctx.set_parameter(&parameter);
+
+        // Act
+        let history = weather_history(&ctx, &host).expect_report("Getting the history failed.");
+
+        // Assert
+        let observed: Vec<(Weather, Timestamp)> = history
+            .iter()
+            .map(|(_, observation)| (observation.weather, observation.observed_at))
+            .collect();
+        claim_eq!(
+            observed,
+            vec![(RAINY, times[1]), (SUNNY, times[2]), (RAINY, times[3])],
+            "Only the observations in the window should be returned."
+        );
+        claim!(history.iter().all(|(region, _)| *region == REGION));
+    }
+
+    #[concordium_test]
+    fn test_weather_forecast() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_weather_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let in_one_hour = CURRENT_TIME.checked_add(Duration::from_hours(1)).unwrap_abort();
+        let in_two_hours = CURRENT_TIME.checked_add(Duration::from_hours(2)).unwrap_abort();
+
+        let parameter = to_bytes(&SetForecastParams {
+            region:    REGION,
+            forecasts: vec![(in_two_hours, RAINY), (in_one_hour, SUNNY)],
+        });
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        ctx.set_parameter(&parameter);
+
+        // Act
+        weather_set_forecast(&ctx, &mut host).expect_report("Setting the forecast failed.");
+
+        // Assert
+        let get_forecast = |ctx: &mut TestReceiveContext, time| {
+            let parameter = to_bytes(&GetForecastParams {
+                region: REGION,
+                time,
+            });
+            ctx.set_parameter(&parameter);
+            weather_get_forecast(ctx, &host)
+        };
+        claim_eq!(get_forecast(&mut ctx, CURRENT_TIME), Err(ContractError::NoForecast));
+        claim_eq!(get_forecast(&mut ctx, in_one_hour), Ok(SUNNY));
+        let in_ninety_minutes = in_one_hour.checked_add(Duration::from_minutes(30)).unwrap_abort();
+        claim_eq!(get_forecast(&mut ctx, in_ninety_minutes), Ok(SUNNY));
+        claim_eq!(get_forecast(&mut ctx, in_two_hours), Ok(RAINY));
+    }
+
+    #[concordium_test]
+    fn test_weather_forecast_is_pruned() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_weather_state(&mut state_builder);
+        let in_one_hour = CURRENT_TIME.checked_add(Duration::from_hours(1)).unwrap_abort();
+        let in_two_hours = CURRENT_TIME.checked_add(Duration::from_hours(2)).unwrap_abort();
+        state.forecasts.insert(REGION, vec![(CURRENT_TIME, SUNNY), (in_one_hour, SUNNY)]);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter = to_bytes(&SetForecastParams {
+            region:    REGION,
+            forecasts: vec![(in_two_hours, RAINY)],
+        });
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_metadata_slot_time(in_one_hour);
+        ctx.set_parameter(&parameter);
+
+        // Act
+        weather_set_forecast(&ctx, &mut host).expect_report("Setting the forecast failed.");
+
+        // Assert
+        let forecasts = host.state().forecasts.get(&REGION).map(|f| f.clone());
+        claim_eq!(
+            forecasts,
+            Some(vec![(in_one_hour, SUNNY), (in_two_hours, RAINY)]),
+            "Forecasts for past times should be pruned."
+        );
+    }
+
+    #[concordium_test]
+    fn test_weather_authorize_and_revoke() {
+        // Arrange
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_weather_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        let oracle = Address::Contract(ContractAddress {
+            index:    3,
+            subindex: 0,
+        });
+
+        let parameter = to_bytes(&oracle);
+        ctx.set_owner(INVOKER_ADDR);
+        ctx.set_sender(Address::Account(INVOKER_ADDR));
+        ctx.set_parameter(&parameter);
+
+        // Act + Assert
+        weather_authorize(&ctx, &mut host, &mut logger).expect_report("Authorizing failed.");
+        claim!(host.state().roles.has_role(UPDATER, &oracle), "The oracle should be an updater.");
+
+        // The oracle can now update the weather.
+        let parameter = to_bytes(&SetWeatherParams {
+            region:  REGION,
+            weather: SUNNY,
+        });
+        let mut oracle_ctx = TestReceiveContext::empty();
+        oracle_ctx.set_sender(oracle);
+        oracle_ctx.set_metadata_slot_time(CURRENT_TIME);
+        oracle_ctx.set_parameter(&parameter);
+        weather_set(&oracle_ctx, &mut host).expect_report("Updating the weather failed.");
+
+        weather_revoke(&ctx, &mut host, &mut logger).expect_report("Revoking failed.");
+        claim!(!host.state().roles.has_role(UPDATER, &oracle), "The oracle should be revoked.");
+        let result = weather_set(&oracle_ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Unauthenticated));
+
+        let change = RoleChange {
+            role:    UPDATER,
+            address: oracle,
+        };
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&RoleEvent::Granted(change)), to_bytes(&RoleEvent::Revoked(change))],
+            "Granting and revoking the role should be logged."
+        );
+    }
 }
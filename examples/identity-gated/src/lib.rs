@@ -0,0 +1,462 @@
+//! An identity gated ledger smart contract example.
+//!
+//! # Description
+//! A ledger of CCD, where only accounts whose identity satisfies the
+//! requirements set by the owner can participate. It shows how to use the
+//! policies of the sender, which contain the identity attributes revealed in
+//! the credentials of the sending account, to build compliance gated flows.
+//!
+//! The requirements are that every credential of the sender
+//!  - is valid at the time of the transaction,
+//!  - reveals a country of residence which is not in the list of blocked
+//!    countries,
+//!  - reveals a date of birth, making the sender at least the minimum age.
+//!
+//! # Operations
+//! The contract allows for
+//!  - `deposit`ing CCD into the balance of the sender;
+//!  - `transfer`ring CCD from the balance of the sender to another account;
+//!  - `withdraw`ing CCD from the balance of the sender;
+//!  - the owner to `updateRequirements`.
+//!
+//! Depositing and transferring require the sender to satisfy the
+//! requirements, while withdrawing is always possible, ensuring that CCD can
+//! never be stuck in the contract when the requirements change.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The number of milliseconds in a day.
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// The identity requirements for participating.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Requirements {
+    /// The countries of residence, as ISO 3166-1 alpha-2 codes, which are not
+    /// allowed to participate.
+    #[concordium(size_length = 1)]
+    pub blocked_countries: Vec<[u8; 2]>,
+    /// The minimum age, in years, for participating.
+    pub minimum_age:       u16,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The identity requirements for participating.
+    requirements: Requirements,
+    /// The balance of every account which has received CCD.
+    balances:     StateMap<AccountAddress, Amount, S>,
+}
+
+/// The parameter type for the contract function `transfer`.
+#[derive(Serialize, SchemaType)]
+pub struct TransferParameter {
+    /// The account receiving the CCD.
+    pub to:     AccountAddress,
+    /// The amount of CCD to transfer.
+    pub amount: Amount,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when a contract sends CCD; Only accounts have identities.
+    OnlyAccount,
+    /// Raised when someone else than the owner updates the requirements.
+    Unauthorized,
+    /// Raised when a credential of the sender is no longer valid.
+    ExpiredIdentity,
+    /// Raised when a credential of the sender does not reveal the country of
+    /// residence or the date of birth.
+    MissingAttribute,
+    /// Raised when the sender resides in a blocked country.
+    BlockedCountry,
+    /// Raised when the sender is younger than the minimum age.
+    Underage,
+    /// Raised when the balance of the sender is too low.
+    InsufficientFunds,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Convert the number of days since 1970-01-01 to a (year, month, day) date
+/// in the proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Shift the epoch to 0000-03-01, so leap days are at the end of years.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    // January and February belong to the next year.
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format a date as `YYYYMMDD`, the format of the date of birth attribute.
+fn format_date(year: u64, month: u64, day: u64) -> [u8; 8] {
+    let mut date = [b'0'; 8];
+    let mut value = year * 10_000 + month * 100 + day;
+    for digit in date.iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    date
+}
+
+/// Compute the latest date of birth, formatted as `YYYYMMDD`, of someone who
+/// is at least `minimum_age` years old at the given time.
+fn latest_date_of_birth(now: Timestamp, minimum_age: u16) -> [u8; 8] {
+    let (year, month, day) = civil_from_days(now.timestamp_millis() / MILLIS_PER_DAY);
+    format_date(year.saturating_sub(u64::from(minimum_age)), month, day)
+}
+
+/// Ensure every credential of the sender satisfies the requirements.
+fn ensure_eligible(
+    ctx: &impl HasReceiveContext,
+    requirements: &Requirements,
+) -> ContractResult<()> {
+    let now = ctx.metadata().slot_time();
+    let cutoff = latest_date_of_birth(now, requirements.minimum_age);
+    for policy in ctx.policies() {
+        ensure!(now <= policy.valid_to, ContractError::ExpiredIdentity);
+        let mut country = None;
+        let mut date_of_birth = None;
+        for (tag, value) in policy.attributes() {
+            if tag == attributes::COUNTRY_OF_RESIDENCE {
+                country = Some(value);
+            } else if tag == attributes::DOB {
+                date_of_birth = Some(value);
+            }
+        }
+        let country = country.ok_or(ContractError::MissingAttribute)?;
+        ensure!(
+            requirements.blocked_countries.iter().all(|blocked| country.as_ref() != &blocked[..]),
+            ContractError::BlockedCountry
+        );
+        // Dates in the `YYYYMMDD` format compare like their bytes.
+        let date_of_birth = date_of_birth.ok_or(ContractError::MissingAttribute)?;
+        ensure!(date_of_birth.as_ref() <= &cutoff[..], ContractError::Underage);
+    }
+    Ok(())
+}
+
+// Contract functions
+
+/// Initialize the ledger with the identity requirements.
+#[init(contract = "identity_gated", parameter = "Requirements")]
+fn init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let requirements: Requirements = ctx.parameter_cursor().get()?;
+    Ok(State {
+        requirements,
+        balances: state_builder.new_map(),
+    })
+}
+
+/// Deposit CCD into the balance of the sender.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The sender does not satisfy the requirements.
+#[receive(contract = "identity_gated", name = "deposit", error = "ContractError", payable, mutable)]
+fn deposit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    ensure_eligible(ctx, &state.requirements)?;
+    *state.balances.entry(sender).or_insert(Amount::zero()) += amount;
+    Ok(())
+}
+
+/// Transfer CCD from the balance of the sender to the balance of another
+/// account.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The sender does not satisfy the requirements.
+/// - The balance of the sender is too low.
+#[receive(
+    contract = "identity_gated",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError",
+    mutable
+)]
+fn transfer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let param: TransferParameter = ctx.parameter_cursor().get()?;
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    ensure_eligible(ctx, &state.requirements)?;
+    {
+        let mut balance =
+            state.balances.get_mut(&sender).ok_or(ContractError::InsufficientFunds)?;
+        ensure!(*balance >= param.amount, ContractError::InsufficientFunds);
+        *balance -= param.amount;
+    }
+    *state.balances.entry(param.to).or_insert(Amount::zero()) += param.amount;
+    Ok(())
+}
+
+/// Withdraw CCD from the balance of the sender. This is possible regardless
+/// of the requirements.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The balance of the sender is too low.
+#[receive(
+    contract = "identity_gated",
+    name = "withdraw",
+    parameter = "Amount",
+    error = "ContractError",
+    mutable
+)]
+fn withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let amount: Amount = ctx.parameter_cursor().get()?;
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    {
+        let mut balance =
+            host.state_mut().balances.get_mut(&sender).ok_or(ContractError::InsufficientFunds)?;
+        ensure!(*balance >= amount, ContractError::InsufficientFunds);
+        *balance -= amount;
+    }
+    // The transfer always succeeds, since the sender is an existing account,
+    // and the contract holds the balances of all accounts.
+    host.invoke_transfer(&sender, amount).unwrap_abort();
+    Ok(())
+}
+
+/// Replace the identity requirements. Can only be called by the owner.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner of the contract instance.
+#[receive(
+    contract = "identity_gated",
+    name = "updateRequirements",
+    parameter = "Requirements",
+    error = "ContractError",
+    mutable
+)]
+fn update_requirements<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let requirements: Requirements = ctx.parameter_cursor().get()?;
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    host.state_mut().requirements = requirements;
+    Ok(())
+}
+
+/// View the balance of an account.
+#[receive(
+    contract = "identity_gated",
+    name = "balanceOf",
+    parameter = "AccountAddress",
+    return_value = "Amount"
+)]
+fn balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().balances.get(&account).map_or(Amount::zero(), |b| *b))
+}
+
+/// View the identity requirements.
+#[receive(contract = "identity_gated", name = "requirements", return_value = "Requirements")]
+fn requirements<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Requirements> {
+    Ok(host.state().requirements.clone())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    /// 2024-06-15 at midnight UTC.
+    const NOW: u64 = 1_718_409_600_000;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&Requirements {
+            blocked_countries: vec![*b"KP", *b"IR"],
+            minimum_age:       18,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    /// A policy valid until `NOW`, revealing the given country and date of
+    /// birth.
+    fn policy(
+        country: [u8; 2],
+        date_of_birth: [u8; 8],
+    ) -> Policy<Vec<(AttributeTag, AttributeValue)>> {
+        Policy {
+            identity_provider: 0,
+            created_at:        Timestamp::from_timestamp_millis(0),
+            valid_to:          Timestamp::from_timestamp_millis(NOW),
+            items:             vec![
+                (attributes::COUNTRY_OF_RESIDENCE, country.into()),
+                (attributes::DOB, date_of_birth.into()),
+            ],
+        }
+    }
+
+    fn ctx_with<'a>(
+        sender: AccountAddress,
+        policies: Vec<Policy<Vec<(AttributeTag, AttributeValue)>>>,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_owner(OWNER);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(NOW));
+        ctx.set_parameter(parameter_bytes);
+        for policy in policies {
+            ctx.push_policy(policy);
+        }
+        ctx
+    }
+
+    fn deposit_with(
+        host: &mut TestHost<State<TestStateApi>>,
+        policies: Vec<Policy<Vec<(AttributeTag, AttributeValue)>>>,
+    ) -> ContractResult<()> {
+        let amount = Amount::from_micro_ccd(100);
+        host.set_self_balance(host.self_balance() + amount);
+        deposit(&ctx_with(ALICE, policies, &[]), host, amount)
+    }
+
+    #[concordium_test]
+    /// Test converting days since the epoch to dates.
+    fn test_civil_from_days() {
+        claim_eq!(civil_from_days(0), (1970, 1, 1));
+        claim_eq!(civil_from_days(NOW / MILLIS_PER_DAY), (2024, 6, 15));
+        claim_eq!(civil_from_days(11_016), (2000, 2, 29), "Leap days should be handled");
+        claim_eq!(latest_date_of_birth(Timestamp::from_timestamp_millis(NOW), 18), *b"20060615");
+    }
+
+    #[concordium_test]
+    /// Test that eligible accounts can deposit, and that the country, age and
+    /// validity of every credential are checked.
+    fn test_deposit_requirements() {
+        let mut host = new_host();
+        deposit_with(&mut host, vec![policy(*b"DK", *b"20060615")])
+            .expect_report("Turning 18 today should be eligible");
+        deposit_with(&mut host, vec![policy(*b"DK", *b"19800101"), policy(*b"SE", *b"19800101")])
+            .expect_report("Eligible credentials should pass");
+
+        let res = deposit_with(&mut host, vec![policy(*b"DK", *b"20060616")]);
+        claim_eq!(res, Err(ContractError::Underage), "Turning 18 tomorrow should fail");
+        let res = deposit_with(&mut host, vec![
+            policy(*b"DK", *b"19800101"),
+            policy(*b"KP", *b"19800101"),
+        ]);
+        claim_eq!(res, Err(ContractError::BlockedCountry), "Every credential should be checked");
+
+        let mut missing = policy(*b"DK", *b"19800101");
+        missing.items.pop();
+        let res = deposit_with(&mut host, vec![missing]);
+        claim_eq!(res, Err(ContractError::MissingAttribute));
+        let mut expired = policy(*b"DK", *b"19800101");
+        expired.valid_to = Timestamp::from_timestamp_millis(NOW - 1);
+        let res = deposit_with(&mut host, vec![expired]);
+        claim_eq!(res, Err(ContractError::ExpiredIdentity));
+
+        let parameter_bytes = to_bytes(&ALICE);
+        let ctx = ctx_with(ALICE, Vec::new(), &parameter_bytes);
+        claim_eq!(balance_of(&ctx, &host), Ok(Amount::from_micro_ccd(200)));
+    }
+
+    #[concordium_test]
+    /// Test that transferring requires eligibility, while withdrawing does not.
+    fn test_transfer_and_withdraw() {
+        let mut host = new_host();
+        deposit_with(&mut host, vec![policy(*b"DK", *b"19800101")])
+            .expect_report("Depositing should pass");
+
+        let parameter_bytes = to_bytes(&TransferParameter {
+            to:     BOB,
+            amount: Amount::from_micro_ccd(30),
+        });
+        let res = transfer(
+            &ctx_with(ALICE, vec![policy(*b"IR", *b"19800101")], &parameter_bytes),
+            &mut host,
+        );
+        claim_eq!(res, Err(ContractError::BlockedCountry));
+        transfer(&ctx_with(ALICE, vec![policy(*b"DK", *b"19800101")], &parameter_bytes), &mut host)
+            .expect_report("Transferring should pass");
+
+        let parameter_bytes = to_bytes(&Amount::from_micro_ccd(71));
+        let res = withdraw(&ctx_with(ALICE, Vec::new(), &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::InsufficientFunds));
+        let parameter_bytes = to_bytes(&Amount::from_micro_ccd(30));
+        withdraw(&ctx_with(BOB, vec![policy(*b"IR", *b"20100101")], &parameter_bytes), &mut host)
+            .expect_report("Withdrawing should pass regardless of the requirements");
+        claim_eq!(host.get_transfers(), [(BOB, Amount::from_micro_ccd(30))]);
+    }
+
+    #[concordium_test]
+    /// Test that only the owner can update the requirements.
+    fn test_update_requirements() {
+        let mut host = new_host();
+        let new_requirements = Requirements {
+            blocked_countries: Vec::new(),
+            minimum_age:       21,
+        };
+        let parameter_bytes = to_bytes(&new_requirements);
+        let res = update_requirements(&ctx_with(ALICE, Vec::new(), &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        update_requirements(&ctx_with(OWNER, Vec::new(), &parameter_bytes), &mut host)
+            .expect_report("Updating should pass");
+        claim_eq!(host.state().requirements, new_requirements);
+
+        let res = deposit_with(&mut host, vec![policy(*b"KP", *b"20060615")]);
+        claim_eq!(res, Err(ContractError::Underage), "The new minimum age should apply");
+    }
+}
@@ -0,0 +1,346 @@
+//! # A counter smart contract, version 2
+//!
+//! This is a new version of the `integrate` example, which an instance of the
+//! `integrate` contract can be upgraded to. It demonstrates how to change the
+//! layout of the contract state as part of an upgrade.
+//!
+//! In addition to the counters of every account and their total, the state of
+//! this version keeps track of the number of changes to counters made since
+//! the upgrade, which is returned by the new `changes` entrypoint.
+//!
+//! The state written by the first version does not have this field, so it
+//! has to be migrated. This is done by the `migrate` entrypoint, which the
+//! `upgrade` entrypoint of the first version invokes right after the upgrade,
+//! in the same transaction. It can only be invoked by the contract itself.
+#![cfg_attr(not(feature = "std"), no_std)]
+use concordium_std::*;
+
+/// The state of the contract.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The counter of each account. Accounts without a counter are at zero.
+    counters:    StateMap<AccountAddress, u64, S>,
+    /// The total of all counters.
+    total:       u64,
+    /// Whether the counters are paused, in which case they cannot be changed.
+    paused:      bool,
+    /// The slot time of the last change to a counter.
+    last_update: Timestamp,
+    /// The number of changes to counters since the upgrade to this version.
+    changes:     u64,
+}
+
+/// The state of the first version of the contract, which is migrated by
+/// `migrate`.
+#[derive(Serial, DeserialWithState)]
+#[concordium(state_parameter = "S")]
+struct StateV1<S> {
+    /// The counter of each account.
+    counters:    StateMap<AccountAddress, u64, S>,
+    /// The total of all counters.
+    total:       u64,
+    /// Whether the counters are paused, in which case they cannot be changed.
+    paused:      bool,
+    /// The slot time of the last change to a counter.
+    last_update: Timestamp,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The counter of the requested account.
+    counter:     u64,
+    /// The owner of the contract.
+    owner:       AccountAddress,
+    /// Whether the counters are paused.
+    paused:      bool,
+    /// The slot time of the last change to any counter.
+    last_update: Timestamp,
+}
+
+/// The event logged when a counter changes.
+#[derive(Debug, Serial, SchemaType)]
+struct CounterEvent {
+    /// The account which changed its counter.
+    initiator: AccountAddress,
+    /// The value of the counter before the change.
+    old:       u64,
+    /// The value of the counter after the change.
+    new:       u64,
+}
+
+/// The errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum Error {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Decrementing would take the counter below zero.
+    CounterUnderflow,
+    /// Incrementing would take the counter, or the total of all counters, above
+    /// the maximum `u64` value.
+    CounterOverflow,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// The counters are paused.
+    ContractPaused,
+    /// Only the contract itself can call this function.
+    Unauthenticated,
+    /// Only the owner can call this function.
+    NotOwner,
+    /// The state could not be written in the new layout.
+    MigrationFailed,
+}
+
+impl From<LogError> for Error {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, Error>;
+
+/// Initialise the contract without any counters.
+#[init(contract = "integrate", event = "CounterEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        counters:    state_builder.new_map(),
+        total:       0,
+        paused:      false,
+        last_update: ctx.metadata().slot_time(),
+        changes:     0,
+    })
+}
+
+/// Migrate the state written by the first version of the contract to the
+/// layout of this version.
+///
+/// Like the `upgrade` entrypoint of the first version, this is marked as
+/// `low_level`, since the state cannot be read in the layout of this version
+/// before it has been migrated.
+#[receive(contract = "integrate", name = "migrate", error = "Error", low_level)]
+fn contract_migrate<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<S>,
+) -> ContractResult<()> {
+    // Migrating twice would reset the number of changes, so only the upgrade
+    // itself may do it.
+    ensure_eq!(ctx.sender(), Address::Contract(ctx.self_address()), Error::Unauthenticated);
+    migrate_state(host.state_mut())
+}
+
+/// Read the state in the layout of the first version of the contract, and write
+/// it back in the layout of this version.
+fn migrate_state<S: HasStateApi>(state_api: &mut S) -> ContractResult<()> {
+    let old: StateV1<S> = state_api.read_root()?;
+    let new = State {
+        counters:    old.counters,
+        total:       old.total,
+        paused:      old.paused,
+        last_update: old.last_update,
+        changes:     0,
+    };
+    let mut root = state_api.lookup_entry(&[]).ok_or(Error::MigrationFailed)?;
+    new.serial(&mut root).map_err(|_| Error::MigrationFailed)
+}
+
+/// Increment the counter of the invoker by the given step.
+#[receive(contract = "integrate", name = "receive", parameter = "u64", mutable, enable_logger)]
+fn contract_receive<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(!host.state().paused, Error::ContractPaused);
+    let step: u64 = ctx.parameter_cursor().get()?;
+    let invoker = ctx.invoker();
+    let state = host.state_mut();
+    // The total is at least the counter of the invoker, so if the total does not
+    // overflow, neither does the counter.
+    state.total = state.total.checked_add(step).ok_or(Error::CounterOverflow)?;
+    let old = state.counters.entry(invoker).or_insert(0).modify(|counter| {
+        let old = *counter;
+        *counter += step;
+        old
+    });
+    state.changes += 1;
+    state.last_update = ctx.metadata().slot_time();
+    logger.log(&CounterEvent {
+        initiator: invoker,
+        old,
+        new: old + step,
+    })?;
+    Ok(())
+}
+
+/// Decrement the counter of the invoker by the given step.
+/// Rejects with `CounterUnderflow` if the counter would go below zero.
+#[receive(contract = "integrate", name = "decrement", parameter = "u64", mutable, enable_logger)]
+fn contract_decrement<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(!host.state().paused, Error::ContractPaused);
+    let step: u64 = ctx.parameter_cursor().get()?;
+    let invoker = ctx.invoker();
+    let state = host.state_mut();
+    let old = state.counters.get(&invoker).map_or(0, |counter| *counter);
+    let new = old.checked_sub(step).ok_or(Error::CounterUnderflow)?;
+    state.counters.insert(invoker, new);
+    // The total is at least the counter of the invoker, so this cannot underflow.
+    state.total -= step;
+    state.changes += 1;
+    state.last_update = ctx.metadata().slot_time();
+    logger.log(&CounterEvent {
+        initiator: invoker,
+        old,
+        new,
+    })?;
+    Ok(())
+}
+
+/// Reset the counter of the invoker to zero.
+#[receive(contract = "integrate", name = "reset", mutable, enable_logger)]
+fn contract_reset<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(!host.state().paused, Error::ContractPaused);
+    let invoker = ctx.invoker();
+    let state = host.state_mut();
+    if let Some(old) = state.counters.remove_and_get(&invoker) {
+        state.total -= old;
+        state.changes += 1;
+        state.last_update = ctx.metadata().slot_time();
+        logger.log(&CounterEvent {
+            initiator: invoker,
+            old,
+            new: 0,
+        })?;
+    }
+    Ok(())
+}
+
+/// Pause or unpause the counters. While paused, counters cannot be
+/// incremented, decremented or reset.
+/// Only the owner of the contract can do so.
+#[receive(contract = "integrate", name = "set_paused", parameter = "bool", mutable)]
+fn contract_set_paused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), Error::NotOwner);
+    host.state_mut().paused = ctx.parameter_cursor().get()?;
+    Ok(())
+}
+
+/// View the counter of the given account, together with the owner of the
+/// contract, whether it is paused, and the slot time of the last change to any
+/// counter.
+#[receive(
+    contract = "integrate",
+    name = "view",
+    parameter = "AccountAddress",
+    return_value = "ViewState"
+)]
+fn contract_view<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ViewState> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    Ok(ViewState {
+        counter:     state.counters.get(&account).map_or(0, |counter| *counter),
+        owner:       ctx.owner(),
+        paused:      state.paused,
+        last_update: state.last_update,
+    })
+}
+
+/// View the total of all counters.
+#[receive(contract = "integrate", name = "total", return_value = "u64")]
+fn contract_total<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<u64> {
+    Ok(host.state().total)
+}
+
+/// View the number of changes to counters since the upgrade to this version.
+#[receive(contract = "integrate", name = "changes", return_value = "u64")]
+fn contract_changes<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<u64> {
+    Ok(host.state().changes)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([0; 32]);
+    const CURRENT_TIME: Timestamp = Timestamp::from_timestamp_millis(1_000_000);
+
+    #[concordium_test]
+    fn test_migrate_state() {
+        // Write the state in the layout of the first version.
+        let mut state_api = TestStateApi::new();
+        let mut state_builder = StateBuilder::open(state_api.clone());
+        let mut counters = state_builder.new_map();
+        counters.insert(ALICE, 5);
+        let old = StateV1 {
+            counters,
+            total: 5,
+            paused: false,
+            last_update: Timestamp::from_timestamp_millis(0),
+        };
+        let mut root = state_api.create_entry(&[]).expect_report("Creating the root failed.");
+        old.serial(&mut root).expect_report("Writing the old state failed.");
+
+        migrate_state(&mut state_api).expect_report("Migrating the state failed.");
+
+        let new: State<TestStateApi> =
+            state_api.read_root().expect_report("Reading the migrated state failed.");
+        claim_eq!(new.counters.get(&ALICE).map(|counter| *counter), Some(5));
+        claim_eq!(new.total, 5, "The total should be kept.");
+        claim_eq!(new.changes, 0, "No changes should have been made yet.");
+    }
+
+    #[concordium_test]
+    fn test_changes_are_counted() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let state = State {
+            counters:    state_builder.new_map(),
+            total:       0,
+            paused:      false,
+            last_update: Timestamp::from_timestamp_millis(0),
+            changes:     0,
+        };
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_invoker(ALICE);
+
+        let parameter = to_bytes(&3u64);
+        ctx.set_parameter(&parameter);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+        contract_decrement(&ctx, &mut host, &mut logger).expect_report("Decrementing failed.");
+
+        let changes = contract_changes(&ctx, &host).expect_report("Viewing the changes failed.");
+        claim_eq!(changes, 2);
+    }
+}
@@ -0,0 +1,665 @@
+//! # A counter smart contract
+//!
+//! This contract maintains a counter for every account using it, which the
+//! account can move up and down in steps of a chosen size, and reset to zero.
+//! The counters are kept in a `StateMap`, and the contract also keeps track of
+//! the total of all counters.
+//!
+//! The entrypoints are
+//!  - `receive`, which increments the counter of the invoker by the step given
+//!    as the parameter. Rejects with `CounterOverflow` if the counter or the
+//!    total would exceed `u64::MAX`.
+//!  - `decrement`, which decrements the counter of the invoker by the step
+//!    given as the parameter. Rejects with `CounterUnderflow` if the counter
+//!    would go below zero.
+//!  - `reset`, which sets the counter of the invoker back to zero.
+//!  - `view`, which returns the counter of the account given as the parameter,
+//!    together with the owner of the contract, whether it is paused and the
+//!    slot time of the last change to a counter, so front ends can get all of
+//!    it in one query.
+//!  - `total`, which returns the total of all counters.
+//!  - `set_paused`, which pauses or unpauses the counters. While paused,
+//!    `receive`, `decrement` and `reset` reject with `ContractPaused`. Only the
+//!    owner of the contract can pause it.
+//!  - `upgrade`, which upgrades the contract to a new module and optionally
+//!    invokes a migration entrypoint in the new module. Only the owner of the
+//!    contract can upgrade it. The `integrate-v2` example is a new version of
+//!    this contract with a changed state layout and a `migrate` entrypoint.
+//!
+//! Every change of a counter is logged as a `CounterEvent` with the old and the
+//! new value of the counter and the account it belongs to.
+#![cfg_attr(not(feature = "std"), no_std)]
+use components::{ContractPaused, Pausable};
+use concordium_std::*;
+
+/// The state of the contract.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The counter of each account. Accounts without a counter are at zero.
+    counters:    StateMap<AccountAddress, u64, S>,
+    /// The total of all counters.
+    total:       u64,
+    /// Whether the counters are paused, in which case they cannot be changed.
+    pausable:    Pausable,
+    /// The slot time of the last change to a counter.
+    last_update: Timestamp,
+}
+
+/// The parameter type for the contract function `upgrade`.
+/// Takes the new module and optionally an entrypoint to call in the new module
+/// after the upgrade, for instance to migrate the state. The upgrade is
+/// reverted if the entrypoint fails.
+#[derive(Debug, Serialize, SchemaType)]
+struct UpgradeParams {
+    /// The new module reference.
+    module:  ModuleReference,
+    /// Optional entrypoint to call in the new module after upgrade.
+    migrate: Option<(OwnedEntrypointName, OwnedParameter)>,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The counter of the requested account.
+    counter:     u64,
+    /// The owner of the contract.
+    owner:       AccountAddress,
+    /// Whether the counters are paused.
+    paused:      bool,
+    /// The slot time of the last change to any counter.
+    last_update: Timestamp,
+}
+
+/// The event logged when a counter changes.
+#[derive(Debug, Serial, SchemaType)]
+struct CounterEvent {
+    /// The account which changed its counter.
+    initiator: AccountAddress,
+    /// The value of the counter before the change.
+    old:       u64,
+    /// The value of the counter after the change.
+    new:       u64,
+}
+
+/// The errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum Error {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Decrementing would take the counter below zero.
+    CounterUnderflow,
+    /// Incrementing would take the counter, or the total of all counters, above
+    /// the maximum `u64` value.
+    CounterOverflow,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// The counters are paused.
+    ContractPaused,
+    /// Only the owner can call this function.
+    Unauthenticated,
+    /// The migration entrypoint invoked after the upgrade failed.
+    MigrationFailed,
+    /// Upgrade failed because the new module does not exist.
+    FailedUpgradeMissingModule,
+    /// Upgrade failed because the new module does not contain a contract with a
+    /// matching name.
+    FailedUpgradeMissingContract,
+    /// Upgrade failed because the smart contract version of the module is not
+    /// supported.
+    FailedUpgradeUnsupportedModuleVersion,
+}
+
+impl From<LogError> for Error {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<ContractPaused> for Error {
+    fn from(_: ContractPaused) -> Self { Self::ContractPaused }
+}
+
+impl<T> From<CallContractError<T>> for Error {
+    fn from(_cce: CallContractError<T>) -> Self { Self::MigrationFailed }
+}
+
+impl From<UpgradeError> for Error {
+    fn from(ue: UpgradeError) -> Self {
+        match ue {
+            UpgradeError::MissingModule => Self::FailedUpgradeMissingModule,
+            UpgradeError::MissingContract => Self::FailedUpgradeMissingContract,
+            UpgradeError::UnsupportedModuleVersion => Self::FailedUpgradeUnsupportedModuleVersion,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, Error>;
+
+/// Initialise the contract without any counters.
+#[init(contract = "integrate", event = "CounterEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        counters:    state_builder.new_map(),
+        total:       0,
+        pausable:    Pausable::new(),
+        last_update: ctx.metadata().slot_time(),
+    })
+}
+
+/// Increment the counter of the invoker by the given step.
+#[receive(contract = "integrate", name = "receive", parameter = "u64", mutable, enable_logger)]
+fn contract_receive<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    host.state().pausable.ensure_not_paused()?;
+    let step: u64 = ctx.parameter_cursor().get()?;
+    let invoker = ctx.invoker();
+    let state = host.state_mut();
+    // The total is at least the counter of the invoker, so if the total does not
+    // overflow, neither does the counter.
+    state.total = state.total.checked_add(step).ok_or(Error::CounterOverflow)?;
+    let old = state.counters.entry(invoker).or_insert(0).modify(|counter| {
+        let old = *counter;
+        *counter += step;
+        old
+    });
+    state.last_update = ctx.metadata().slot_time();
+    logger.log(&CounterEvent {
+        initiator: invoker,
+        old,
+        new: old + step,
+    })?;
+    Ok(())
+}
+
+/// Decrement the counter of the invoker by the given step.
+/// Rejects with `CounterUnderflow` if the counter would go below zero.
+#[receive(contract = "integrate", name = "decrement", parameter = "u64", mutable, enable_logger)]
+fn contract_decrement<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    host.state().pausable.ensure_not_paused()?;
+    let step: u64 = ctx.parameter_cursor().get()?;
+    let invoker = ctx.invoker();
+    let state = host.state_mut();
+    let old = state.counters.get(&invoker).map_or(0, |counter| *counter);
+    let new = old.checked_sub(step).ok_or(Error::CounterUnderflow)?;
+    state.counters.insert(invoker, new);
+    // The total is at least the counter of the invoker, so this cannot underflow.
+    state.total -= step;
+    state.last_update = ctx.metadata().slot_time();
+    logger.log(&CounterEvent {
+        initiator: invoker,
+        old,
+        new,
+    })?;
+    Ok(())
+}
+
+/// Reset the counter of the invoker to zero.
+#[receive(contract = "integrate", name = "reset", mutable, enable_logger)]
+fn contract_reset<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    host.state().pausable.ensure_not_paused()?;
+    let invoker = ctx.invoker();
+    let state = host.state_mut();
+    if let Some(old) = state.counters.remove_and_get(&invoker) {
+        state.total -= old;
+        state.last_update = ctx.metadata().slot_time();
+        logger.log(&CounterEvent {
+            initiator: invoker,
+            old,
+            new: 0,
+        })?;
+    }
+    Ok(())
+}
+
+/// Pause or unpause the counters. While paused, counters cannot be
+/// incremented, decremented or reset.
+/// Only the owner of the contract can do so.
+#[receive(contract = "integrate", name = "set_paused", parameter = "bool", mutable)]
+fn contract_set_paused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), Error::Unauthenticated);
+    let paused: bool = ctx.parameter_cursor().get()?;
+    host.state_mut().pausable.set_paused(paused);
+    Ok(())
+}
+
+/// View the counter of the given account, together with the owner of the
+/// contract, whether it is paused, and the slot time of the last change to any
+/// counter.
+#[receive(
+    contract = "integrate",
+    name = "view",
+    parameter = "AccountAddress",
+    return_value = "ViewState"
+)]
+fn contract_view<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ViewState> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    Ok(ViewState {
+        counter:     state.counters.get(&account).map_or(0, |counter| *counter),
+        owner:       ctx.owner(),
+        paused:      state.pausable.is_paused(),
+        last_update: state.last_update,
+    })
+}
+
+/// View the total of all counters.
+#[receive(contract = "integrate", name = "total", return_value = "u64")]
+fn contract_total<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<u64> {
+    Ok(host.state().total)
+}
+
+/// Upgrade the contract to a new module and optionally call a migration
+/// entrypoint in the new module afterwards.
+/// Only the owner of the contract can do so.
+///
+/// This function is marked as `low_level`, since the high-level mutable
+/// functions store the state at the end of execution, which would overwrite
+/// the state written by the migration in the new layout. It does not touch the
+/// state, so it is generic over the state of the host, which lets the unit
+/// tests call it with a `TestHost` holding the high-level state.
+#[receive(
+    contract = "integrate",
+    name = "upgrade",
+    parameter = "UpgradeParams",
+    error = "Error",
+    low_level
+)]
+fn contract_upgrade<S>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), Error::Unauthenticated);
+    let params: UpgradeParams = ctx.parameter_cursor().get()?;
+    host.upgrade(params.module)?;
+    if let Some((entrypoint, parameter)) = params.migrate {
+        host.invoke_contract_raw(
+            &ctx.self_address(),
+            parameter.as_parameter(),
+            entrypoint.as_entrypoint_name(),
+            Amount::zero(),
+        )?;
+    }
+    Ok(())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([0; 32]);
+    const BOB: AccountAddress = AccountAddress([1; 32]);
+    const CURRENT_TIME: Timestamp = Timestamp::from_timestamp_millis(1_000_000);
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+
+    fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
+        State {
+            counters:    state_builder.new_map(),
+            total:       0,
+            pausable:    Pausable::new(),
+            last_update: Timestamp::from_timestamp_millis(0),
+        }
+    }
+
+    /// Get the counter of an account through `view`.
+    fn view_for(host: &TestHost<State<TestStateApi>>, account: AccountAddress) -> u64 {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(ALICE);
+        let parameter = to_bytes(&account);
+        ctx.set_parameter(&parameter);
+        contract_view(&ctx, host).expect_report("Viewing the counter failed.").counter
+    }
+
+    #[concordium_test]
+    fn test_receive_and_decrement() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_invoker(ALICE);
+
+        let parameter = to_bytes(&5u64);
+        ctx.set_parameter(&parameter);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+        claim_eq!(view_for(&host, ALICE), 5, "The counter should be incremented by the step.");
+
+        let parameter = to_bytes(&3u64);
+        ctx.set_parameter(&parameter);
+        contract_decrement(&ctx, &mut host, &mut logger).expect_report("Decrementing failed.");
+        claim_eq!(view_for(&host, ALICE), 2, "The counter should be decremented by the step.");
+    }
+
+    #[concordium_test]
+    fn test_decrement_underflow() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.counters.insert(ALICE, 2);
+        state.total = 2;
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_invoker(ALICE);
+
+        let parameter = to_bytes(&3u64);
+        ctx.set_parameter(&parameter);
+        let result = contract_decrement(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(Error::CounterUnderflow));
+        claim_eq!(view_for(&host, ALICE), 2, "The counter should be unchanged.");
+    }
+
+    #[concordium_test]
+    fn test_counters_are_per_account() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+
+        let parameter = to_bytes(&4u64);
+        ctx.set_parameter(&parameter);
+        ctx.set_invoker(ALICE);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+        ctx.set_invoker(BOB);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+
+        claim_eq!(view_for(&host, ALICE), 4);
+        claim_eq!(view_for(&host, BOB), 8);
+        let total = contract_total(&ctx, &host).expect_report("Viewing the total failed.");
+        claim_eq!(total, 12, "The total should be the sum of all counters.");
+
+        // Bob's counter can not be decremented by more than Bob has, even though
+        // the total is larger.
+        let parameter = to_bytes(&9u64);
+        ctx.set_parameter(&parameter);
+        claim_eq!(contract_decrement(&ctx, &mut host, &mut logger), Err(Error::CounterUnderflow));
+
+        contract_reset(&ctx, &mut host, &mut logger).expect_report("Resetting failed.");
+        claim_eq!(view_for(&host, BOB), 0, "The counter should be reset.");
+        claim_eq!(view_for(&host, ALICE), 4, "Other counters should be unaffected.");
+        let total = contract_total(&ctx, &host).expect_report("Viewing the total failed.");
+        claim_eq!(total, 4, "The total should not include the reset counter.");
+    }
+
+    #[concordium_test]
+    fn test_receive_up_to_the_maximum() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.counters.insert(ALICE, u64::MAX - 1);
+        state.total = u64::MAX - 1;
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_invoker(ALICE);
+
+        let parameter = to_bytes(&1u64);
+        ctx.set_parameter(&parameter);
+        contract_receive(&ctx, &mut host, &mut logger)
+            .expect_report("Incrementing to the maximum failed.");
+        claim_eq!(view_for(&host, ALICE), u64::MAX, "The counter should reach the maximum.");
+
+        let result = contract_receive(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(Error::CounterOverflow));
+        claim_eq!(view_for(&host, ALICE), u64::MAX, "The counter should be unchanged.");
+    }
+
+    #[concordium_test]
+    fn test_receive_total_overflow() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.counters.insert(ALICE, u64::MAX);
+        state.total = u64::MAX;
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_invoker(BOB);
+
+        let parameter = to_bytes(&1u64);
+        ctx.set_parameter(&parameter);
+        let result = contract_receive(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(Error::CounterOverflow), "The total should not overflow.");
+        claim_eq!(view_for(&host, BOB), 0, "The counter should be unchanged.");
+    }
+
+    #[concordium_test]
+    fn test_counter_events() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_invoker(ALICE);
+
+        let parameter = to_bytes(&5u64);
+        ctx.set_parameter(&parameter);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+        let parameter = to_bytes(&2u64);
+        ctx.set_parameter(&parameter);
+        contract_decrement(&ctx, &mut host, &mut logger).expect_report("Decrementing failed.");
+        contract_reset(&ctx, &mut host, &mut logger).expect_report("Resetting failed.");
+
+        let event = |old, new| {
+            to_bytes(&CounterEvent {
+                initiator: ALICE,
+                old,
+                new,
+            })
+        };
+        claim_eq!(
+            logger.logs,
+            vec![event(0, 5), event(5, 3), event(3, 0)],
+            "Every change of the counter should be logged."
+        );
+    }
+
+    #[concordium_test]
+    fn test_paused() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_owner(ALICE);
+        ctx.set_invoker(ALICE);
+
+        // Only the owner can pause.
+        let parameter = to_bytes(&true);
+        ctx.set_parameter(&parameter);
+        ctx.set_sender(Address::Account(BOB));
+        claim_eq!(contract_set_paused(&ctx, &mut host), Err(Error::Unauthenticated));
+        ctx.set_sender(Address::Account(ALICE));
+        contract_set_paused(&ctx, &mut host).expect_report("Pausing failed.");
+
+        let parameter = to_bytes(&1u64);
+        ctx.set_parameter(&parameter);
+        let result = contract_receive(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Err(Error::ContractPaused));
+        claim_eq!(view_for(&host, ALICE), 0, "The counter should be unchanged.");
+
+        let parameter = to_bytes(&false);
+        ctx.set_parameter(&parameter);
+        contract_set_paused(&ctx, &mut host).expect_report("Unpausing failed.");
+        let parameter = to_bytes(&1u64);
+        ctx.set_parameter(&parameter);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+        claim_eq!(view_for(&host, ALICE), 1);
+    }
+
+    #[concordium_test]
+    fn test_view() {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(CURRENT_TIME);
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        let mut logger = TestLogger::init();
+        ctx.set_owner(ALICE);
+        ctx.set_invoker(BOB);
+
+        let parameter = to_bytes(&7u64);
+        ctx.set_parameter(&parameter);
+        contract_receive(&ctx, &mut host, &mut logger).expect_report("Incrementing failed.");
+
+        let parameter = to_bytes(&BOB);
+        ctx.set_parameter(&parameter);
+        let view = contract_view(&ctx, &host).expect_report("Viewing the state failed.");
+        claim_eq!(view, ViewState {
+            counter:     7,
+            owner:       ALICE,
+            paused:      false,
+            last_update: CURRENT_TIME,
+        });
+    }
+
+    /// Parameter for `upgrade` to a new module, migrating with a `u64` passed
+    /// to the `migrate` entrypoint.
+    fn upgrade_params(module: ModuleReference, migrate: Option<u64>) -> Vec<u8> {
+        to_bytes(&UpgradeParams {
+            module,
+            migrate: migrate.map(|value| {
+                (
+                    OwnedEntrypointName::new_unchecked("migrate".into()),
+                    OwnedParameter::new_unchecked(to_bytes(&value)),
+                )
+            }),
+        })
+    }
+
+    #[concordium_test]
+    fn test_upgrade_only_owner() {
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        ctx.set_owner(ALICE);
+        ctx.set_sender(Address::Account(BOB));
+        ctx.set_self_address(SELF_ADDRESS);
+
+        // No upgrade is mocked, so the test fails if the module is upgraded.
+        let parameter = upgrade_params(ModuleReference::from([1; 32]), None);
+        ctx.set_parameter(&parameter);
+        claim_eq!(contract_upgrade(&ctx, &mut host), Err(Error::Unauthenticated));
+    }
+
+    #[concordium_test]
+    fn test_upgrade_errors() {
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        ctx.set_owner(ALICE);
+        ctx.set_sender(Address::Account(ALICE));
+        ctx.set_self_address(SELF_ADDRESS);
+
+        let cases = vec![
+            (UpgradeError::MissingModule, Error::FailedUpgradeMissingModule),
+            (UpgradeError::MissingContract, Error::FailedUpgradeMissingContract),
+            (UpgradeError::UnsupportedModuleVersion, Error::FailedUpgradeUnsupportedModuleVersion),
+        ];
+        for (i, (upgrade_error, error)) in cases.into_iter().enumerate() {
+            let module = ModuleReference::from([i as u8; 32]);
+            host.setup_mock_upgrade(module, Err(upgrade_error));
+            let parameter = upgrade_params(module, None);
+            ctx.set_parameter(&parameter);
+            claim_eq!(contract_upgrade(&ctx, &mut host), Err(error));
+        }
+    }
+
+    #[concordium_test]
+    fn test_upgrade_and_migrate() {
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        ctx.set_owner(ALICE);
+        ctx.set_sender(Address::Account(ALICE));
+        ctx.set_self_address(SELF_ADDRESS);
+
+        let module = ModuleReference::from([1; 32]);
+        host.setup_mock_upgrade(module, Ok(()));
+        // The migration stores the value it is given as the total, so the test can
+        // check that it was called with the parameter.
+        host.setup_mock_entrypoint(
+            SELF_ADDRESS,
+            OwnedEntrypointName::new_unchecked("migrate".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, state: &mut State<TestStateApi>| {
+                state.total = from_bytes(parameter.0).expect_report("Invalid migrate parameter");
+                Ok((true, ()))
+            }),
+        );
+
+        let parameter = upgrade_params(module, None);
+        ctx.set_parameter(&parameter);
+        contract_upgrade(&ctx, &mut host).expect_report("Upgrading without migrating failed.");
+        claim_eq!(host.state().total, 0, "The migration should not be called.");
+
+        let parameter = upgrade_params(module, Some(42));
+        ctx.set_parameter(&parameter);
+        contract_upgrade(&ctx, &mut host).expect_report("Upgrading and migrating failed.");
+        claim_eq!(host.state().total, 42, "The migration should be called with the parameter.");
+    }
+
+    #[concordium_test]
+    fn test_upgrade_failed_migration() {
+        let mut ctx = TestReceiveContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+        ctx.set_owner(ALICE);
+        ctx.set_sender(Address::Account(ALICE));
+        ctx.set_self_address(SELF_ADDRESS);
+
+        let module = ModuleReference::from([1; 32]);
+        host.setup_mock_upgrade(module, Ok(()));
+        host.setup_mock_entrypoint(
+            SELF_ADDRESS,
+            OwnedEntrypointName::new_unchecked("migrate".into()),
+            MockFn::returning_err::<()>(CallContractError::Trap),
+        );
+
+        let parameter = upgrade_params(module, Some(42));
+        ctx.set_parameter(&parameter);
+        claim_eq!(contract_upgrade(&ctx, &mut host), Err(Error::MigrationFailed));
+    }
+}
@@ -0,0 +1,473 @@
+//! # A launchpad for creator tokens
+//!
+//! The launchpad launches fungible creator tokens with a fixed supply, and
+//! keeps a registry of the launched tokens by name. The tokens are instances
+//! of the `cis2_fungible` contract from the [`cis2-fungible`] example.
+//!
+//! A smart contract cannot initialize instances of other contracts, so
+//! instead the launchpad publishes the reference of the module containing
+//! `cis2_fungible`, which creators initialize their token instances from.
+//! The launchpad then takes over the token instance, and mints the supply:
+//!
+//! 1. The creator initializes an instance of `cis2_fungible` from the published
+//!    module, and thereby becomes its admin.
+//! 2. The creator reserves a name for the token with `reserve`. The launchpad
+//!    checks that the creator is the admin of the token, and that no tokens
+//!    have been minted.
+//! 3. The creator hands over the admin role of the token to the launchpad using
+//!    `updateAdmin` on the token.
+//! 4. The creator launches the token with `launch`, giving the supply. The
+//!    launchpad checks that it is now the admin of the token, and mints the
+//!    supply to the creator.
+//!
+//! Reserving the name while the creator is still the admin of the token
+//! ensures that no one else can launch it.
+//!
+//! A contract cannot look up the module of another instance, so the launchpad
+//! does not check that the token instance was initialized from the published
+//! module. It only checks the answers the instance gives to its queries, and an
+//! instance of another module can give the same answers while minting more
+//! tokens, or letting a former admin do so. The supply of a launched token is
+//! only fixed if the instance runs `cis2_fungible` from the published module,
+//! in which case the launchpad stays the admin and never mints again. Creators
+//! and users must check the module of the instance off-chain, e.g., with
+//! `concordium-client contract show`, before trusting a launched token.
+//!
+//! [`cis2-fungible`]: ../cis2-fungible
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// The token amount type of `cis2_fungible`.
+type TokenAmount = TokenAmountU64;
+
+/// The longest name of a token, in bytes.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// The status of a token in the registry.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaunchStatus {
+    /// The name is reserved, and the token is waiting to be launched.
+    Reserved,
+    /// The token was launched with the given supply.
+    Launched(TokenAmount),
+}
+
+/// A token in the registry.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Launch {
+    /// The token instance.
+    pub token:   ContractAddress,
+    /// The creator of the token, who receives the supply.
+    pub creator: AccountAddress,
+    /// Whether the token was launched.
+    pub status:  LaunchStatus,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The module which creators initialize their token instances from.
+    token_module: ModuleReference,
+    /// The registry of tokens by name.
+    launches:     StateMap<String, Launch, S>,
+    /// The names of the tokens in the registry, by token instance.
+    names:        StateMap<ContractAddress, String, S>,
+}
+
+/// The parameter type for the contract function `reserve`.
+#[derive(Serialize, SchemaType)]
+pub struct ReserveParams {
+    /// The name of the token.
+    pub name:  String,
+    /// The token instance, which the sender is the admin of.
+    pub token: ContractAddress,
+}
+
+/// The parameter type for the contract function `launch`.
+#[derive(Serialize, SchemaType)]
+pub struct LaunchParams {
+    /// The name of the token.
+    pub name:   String,
+    /// The supply of the token, which is minted to the creator.
+    pub supply: TokenAmount,
+}
+
+/// The parameter type for the contract function `mint` of `cis2_fungible`.
+#[derive(Serialize, SchemaType)]
+struct MintParams {
+    /// The owner of the newly minted tokens.
+    owner:  Address,
+    /// The amount of tokens to mint.
+    amount: TokenAmount,
+}
+
+/// The return type for the contract function `view` of `cis2_fungible`.
+#[derive(Serialize, SchemaType)]
+struct TokenState {
+    /// The admin address.
+    admin:        Address,
+    /// The number of tokens in existence.
+    total_supply: TokenAmount,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum LaunchpadEvent {
+    /// A name was reserved for a token.
+    Reserve {
+        name:    String,
+        token:   ContractAddress,
+        creator: AccountAddress,
+    },
+    /// A token was launched.
+    Launch {
+        name:   String,
+        token:  ContractAddress,
+        supply: TokenAmount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed to invoke the token.
+    InvokeContractError,
+    /// Failed to parse the response of the token.
+    ParseResult,
+    /// Raised when a contract reserves tokens.
+    OnlyAccount,
+    /// Raised when the name is empty or too long.
+    InvalidName,
+    /// Raised when the name is taken.
+    NameTaken,
+    /// Raised when the token is already in the registry.
+    AlreadyRegistered,
+    /// Raised when the sender is not the admin of the token when reserving.
+    NotTokenAdmin,
+    /// Raised when the token has already minted tokens.
+    AlreadyMinted,
+    /// Raised when there is no token with the given name.
+    UnknownName,
+    /// Raised when the sender is not the creator of the token.
+    Unauthorized,
+    /// Raised when the token was already launched.
+    AlreadyLaunched,
+    /// Raised when the launchpad is not the admin of the token when
+    /// launching.
+    LaunchpadNotAdmin,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Query the admin and the total supply of a token instance.
+fn query_token<S: HasStateApi>(
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    token: &ContractAddress,
+) -> ContractResult<TokenState> {
+    let response = host.invoke_contract_read_only(
+        token,
+        &(),
+        EntrypointName::new_unchecked("view"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ContractError::ParseResult)?;
+    response.get().map_err(|_| ContractError::ParseResult)
+}
+
+/// Initialize the launchpad with the module which creators initialize their
+/// token instances from.
+#[init(contract = "launchpad", parameter = "ModuleReference", event = "LaunchpadEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let token_module: ModuleReference = ctx.parameter_cursor().get()?;
+    Ok(State {
+        token_module,
+        launches: state_builder.new_map(),
+        names: state_builder.new_map(),
+    })
+}
+
+/// Reserve a name for a token instance, which the sender is the admin of.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The name is empty or too long.
+/// - The name is taken, or the token is already in the registry.
+/// - The sender is not the admin of the token.
+/// - The token has already minted tokens.
+/// - It fails to log the event.
+#[receive(
+    contract = "launchpad",
+    name = "reserve",
+    parameter = "ReserveParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_reserve<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: ReserveParams = ctx.parameter_cursor().get()?;
+    let creator = sender_account(ctx)?;
+    ensure!(
+        !params.name.is_empty() && params.name.len() <= MAX_NAME_LENGTH,
+        ContractError::InvalidName
+    );
+    ensure!(host.state().launches.get(&params.name).is_none(), ContractError::NameTaken);
+    ensure!(host.state().names.get(&params.token).is_none(), ContractError::AlreadyRegistered);
+
+    let token_state = query_token(host, &params.token)?;
+    ensure!(token_state.admin.matches_account(&creator), ContractError::NotTokenAdmin);
+    ensure_eq!(token_state.total_supply, TokenAmount::from(0), ContractError::AlreadyMinted);
+
+    let state = host.state_mut();
+    state.launches.insert(params.name.clone(), Launch {
+        token: params.token,
+        creator,
+        status: LaunchStatus::Reserved,
+    });
+    state.names.insert(params.token, params.name.clone());
+    logger.log(&LaunchpadEvent::Reserve {
+        name: params.name,
+        token: params.token,
+        creator,
+    })?;
+    Ok(())
+}
+
+/// Launch a token, minting the supply to the creator. The launchpad must be
+/// the admin of the token.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - There is no token with the given name.
+/// - The sender is not the creator of the token.
+/// - The token was already launched.
+/// - The launchpad is not the admin of the token.
+/// - The token has already minted tokens.
+/// - Minting the supply fails.
+/// - It fails to log the event.
+#[receive(
+    contract = "launchpad",
+    name = "launch",
+    parameter = "LaunchParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_launch<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: LaunchParams = ctx.parameter_cursor().get()?;
+    let launch = host.state().launches.get(&params.name).map(|launch| *launch);
+    let launch = launch.ok_or(ContractError::UnknownName)?;
+    ensure!(ctx.sender().matches_account(&launch.creator), ContractError::Unauthorized);
+    ensure_eq!(launch.status, LaunchStatus::Reserved, ContractError::AlreadyLaunched);
+
+    let token_state = query_token(host, &launch.token)?;
+    ensure_eq!(
+        token_state.admin,
+        Address::Contract(ctx.self_address()),
+        ContractError::LaunchpadNotAdmin
+    );
+    ensure_eq!(token_state.total_supply, TokenAmount::from(0), ContractError::AlreadyMinted);
+
+    host.state_mut().launches.entry(params.name.clone()).and_modify(|launch| {
+        launch.status = LaunchStatus::Launched(params.supply);
+    });
+    let mint_params = MintParams {
+        owner:  Address::Account(launch.creator),
+        amount: params.supply,
+    };
+    host.invoke_contract(
+        &launch.token,
+        &mint_params,
+        EntrypointName::new_unchecked("mint"),
+        Amount::zero(),
+    )?;
+    logger.log(&LaunchpadEvent::Launch {
+        name:   params.name,
+        token:  launch.token,
+        supply: params.supply,
+    })?;
+    Ok(())
+}
+
+/// View a token in the registry by name.
+#[receive(
+    contract = "launchpad",
+    name = "launch_of",
+    parameter = "String",
+    return_value = "Option<Launch>"
+)]
+fn contract_launch_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Launch>> {
+    let name: String = ctx.parameter_cursor().get()?;
+    Ok(host.state().launches.get(&name).map(|launch| *launch))
+}
+
+/// View the module which creators initialize their token instances from.
+#[receive(contract = "launchpad", name = "tokenModule", return_value = "ModuleReference")]
+fn contract_token_module<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ModuleReference> {
+    Ok(host.state().token_module)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const OTHER: AccountAddress = AccountAddress([2; 32]);
+    const LAUNCHPAD: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const TOKEN: ContractAddress = ContractAddress {
+        index:    2,
+        subindex: 0,
+    };
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = [7u8; 32];
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    /// Mock the `view` of the token, returning the given admin and no supply.
+    fn mock_token_admin(host: &mut TestHost<State<TestStateApi>>, admin: Address) {
+        host.setup_mock_entrypoint(
+            TOKEN,
+            OwnedEntrypointName::new_unchecked("view".into()),
+            MockFn::returning_ok(TokenState {
+                admin,
+                total_supply: TokenAmount::from(0),
+            }),
+        );
+    }
+
+    fn ctx_with<'a>(sender: AccountAddress, parameter_bytes: &'a [u8]) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_self_address(LAUNCHPAD);
+        ctx.set_parameter(parameter_bytes);
+        ctx
+    }
+
+    fn reserve(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        name: &str,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&ReserveParams {
+            name:  name.into(),
+            token: TOKEN,
+        });
+        contract_reserve(&ctx_with(sender, &parameter_bytes), host, &mut TestLogger::init())
+    }
+
+    fn launch(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&LaunchParams {
+            name:   "STREAM".into(),
+            supply: TokenAmount::from(1_000_000),
+        });
+        contract_launch(&ctx_with(sender, &parameter_bytes), host, &mut TestLogger::init())
+    }
+
+    #[concordium_test]
+    /// Test that only the admin of a token can reserve a name for it, once.
+    fn test_reserve() {
+        let mut host = new_host();
+        mock_token_admin(&mut host, Address::Account(CREATOR));
+        claim_eq!(reserve(&mut host, OTHER, "STREAM"), Err(ContractError::NotTokenAdmin));
+        claim_eq!(reserve(&mut host, CREATOR, ""), Err(ContractError::InvalidName));
+        reserve(&mut host, CREATOR, "STREAM").expect_report("Reserving should pass");
+        claim_eq!(reserve(&mut host, CREATOR, "STREAM"), Err(ContractError::NameTaken));
+        claim_eq!(reserve(&mut host, CREATOR, "OTHER"), Err(ContractError::AlreadyRegistered));
+    }
+
+    #[concordium_test]
+    /// Test that launching mints the supply to the creator, once the launchpad
+    /// is the admin of the token.
+    fn test_launch() {
+        let mut host = new_host();
+        mock_token_admin(&mut host, Address::Account(CREATOR));
+        reserve(&mut host, CREATOR, "STREAM").expect_report("Reserving should pass");
+        claim_eq!(launch(&mut host, CREATOR), Err(ContractError::LaunchpadNotAdmin));
+
+        mock_token_admin(&mut host, Address::Contract(LAUNCHPAD));
+        host.setup_mock_entrypoint(
+            TOKEN,
+            OwnedEntrypointName::new_unchecked("mint".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: MintParams = from_bytes(parameter.0).expect_report("Invalid mint");
+                claim_eq!(params.owner, Address::Account(CREATOR), "Minted to the creator");
+                claim_eq!(params.amount, TokenAmount::from(1_000_000), "Minted the supply");
+                Ok((false, ()))
+            }),
+        );
+        claim_eq!(launch(&mut host, OTHER), Err(ContractError::Unauthorized));
+        launch(&mut host, CREATOR).expect_report("Launching should pass");
+        claim_eq!(launch(&mut host, CREATOR), Err(ContractError::AlreadyLaunched));
+
+        let launched = host.state().launches.get(&"STREAM".to_string()).map(|launch| *launch);
+        claim_eq!(
+            launched,
+            Some(Launch {
+                token:   TOKEN,
+                creator: CREATOR,
+                status:  LaunchStatus::Launched(TokenAmount::from(1_000_000)),
+            })
+        );
+    }
+}
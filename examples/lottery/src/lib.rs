@@ -0,0 +1,522 @@
+//! # Implementation of a lottery smart contract
+//!
+//! The owner of the contract instance runs a lottery, where accounts buy
+//! tickets until the draw time, and the holder of a randomly selected ticket
+//! wins everything paid for the tickets.
+//!
+//! The randomness comes from a commit-reveal scheme. When initializing the
+//! contract, the owner commits to a secret seed by providing its SHA2-256
+//! hash. Every ticket purchase mixes the buyer and the time of the purchase
+//! into the ticket entropy, which the owner cannot predict when committing.
+//! After the draw time and until the reveal deadline, the owner reveals the
+//! seed using `reveal`. The contract checks the seed against the commitment and
+//! selects the winning ticket from the hash of the seed and the ticket entropy.
+//!
+//! If the owner does not reveal the seed before the reveal deadline, e.g.,
+//! because they do not like the outcome, the lottery is cancelled and every
+//! player can `refund` the full price of their tickets.
+//!
+//! The scheme does not stop the owner from rigging the draw as a player. The
+//! owner knows the seed, and the ticket entropy is public, so before every
+//! purchase they can compute which ticket would win if no one else bought
+//! more. By buying tickets, possibly from other accounts, right before the
+//! draw time until one of them would win, the owner takes the prize. A cutoff
+//! for buying before the draw time only moves the moment they do so. Players
+//! must therefore trust the owner not to play, and this example is not
+//! suitable for a lottery with an untrusted owner.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The index of a ticket.
+type TicketIndex = u32;
+
+/// The result of the draw.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Draw {
+    /// The winning ticket.
+    ticket: TicketIndex,
+    /// The holder of the winning ticket.
+    winner: AccountAddress,
+    /// The amount paid to the winner.
+    prize:  Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The price of a ticket.
+    ticket_price:    Amount,
+    /// The last time a ticket can be bought.
+    draw_time:       Timestamp,
+    /// The last time the seed can be revealed.
+    reveal_deadline: Timestamp,
+    /// The hash of the secret seed of the owner.
+    seed_commitment: HashSha2256,
+    /// Entropy accumulated from the ticket purchases.
+    ticket_entropy:  HashSha2256,
+    /// The number of tickets sold.
+    ticket_count:    TicketIndex,
+    /// The holder of every ticket.
+    tickets:         StateMap<TicketIndex, AccountAddress, S>,
+    /// The number of tickets bought by every account.
+    purchases:       StateMap<AccountAddress, u32, S>,
+    /// Whether the seed has been revealed.
+    revealed:        bool,
+    /// The result of the draw; The variant `None` represents that the seed has
+    /// not been revealed yet, or that no tickets were sold.
+    draw:            Option<Draw>,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The price of a ticket.
+    ticket_price:    Amount,
+    /// The last time a ticket can be bought, using the RFC 3339 format (https://tools.ietf.org/html/rfc3339)
+    draw_time:       Timestamp,
+    /// The last time the seed can be revealed, using the RFC 3339 format.
+    reveal_deadline: Timestamp,
+    /// The SHA2-256 hash of the secret seed.
+    seed_commitment: HashSha2256,
+}
+
+/// The `return_value` type of the `view` function.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The price of a ticket.
+    ticket_price:    Amount,
+    /// The last time a ticket can be bought.
+    draw_time:       Timestamp,
+    /// The last time the seed can be revealed.
+    reveal_deadline: Timestamp,
+    /// The number of tickets sold.
+    ticket_count:    TicketIndex,
+    /// Whether the seed has been revealed.
+    revealed:        bool,
+    /// The result of the draw.
+    draw:            Option<Draw>,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a reveal deadline before the draw time.
+    InvalidSchedule,
+    /// Raised when initializing with a ticket price of zero.
+    ZeroTicketPrice,
+    /// Raised when a contract tries to buy tickets; Only accounts are allowed
+    /// to play.
+    OnlyAccount,
+    /// Raised when buying tickets after the draw time.
+    SalesClosed,
+    /// Raised when the amount paid is not a positive multiple of the ticket
+    /// price.
+    InvalidPayment,
+    /// Raised when the number of tickets would overflow.
+    TooManyTickets,
+    /// Raised when someone else than the owner reveals the seed.
+    Unauthorized,
+    /// Raised when revealing before the draw time or after the reveal
+    /// deadline.
+    NotRevealPhase,
+    /// Raised when the revealed seed does not match the commitment.
+    InvalidSeed,
+    /// Raised when revealing the seed a second time.
+    AlreadyRevealed,
+    /// Raised when refunding while the seed can still be revealed, or after it
+    /// was revealed.
+    RefundUnavailable,
+    /// Raised when refunding without tickets, e.g., because they were refunded
+    /// already.
+    NothingToRefund,
+}
+
+/// Build the message mixed into the ticket entropy when a ticket is bought,
+/// i.e., the concatenation of the current entropy, the address of the buyer,
+/// the time of the purchase in milliseconds and the number of tickets bought,
+/// both as little-endian bytes.
+fn entropy_message(
+    entropy: &HashSha2256,
+    buyer: &AccountAddress,
+    slot_time: Timestamp,
+    tickets: u32,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(76);
+    message.extend_from_slice(&entropy.0);
+    message.extend_from_slice(&buyer.0);
+    message.extend_from_slice(&slot_time.timestamp_millis().to_le_bytes());
+    message.extend_from_slice(&tickets.to_le_bytes());
+    message
+}
+
+/// Select the winning ticket from the hash of the seed and the ticket entropy.
+fn winning_ticket(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    seed: &[u8; 32],
+    entropy: &HashSha2256,
+    ticket_count: TicketIndex,
+) -> TicketIndex {
+    let mut message = [0u8; 64];
+    message[..32].copy_from_slice(seed);
+    message[32..].copy_from_slice(&entropy.0);
+    let hash = crypto_primitives.hash_sha2_256(&message);
+    let mut random = [0u8; 8];
+    random.copy_from_slice(&hash.0[..8]);
+    // The modulo bias is negligible, since `ticket_count` is much smaller than
+    // `u64::MAX`.
+    (u64::from_le_bytes(random) % u64::from(ticket_count)) as TicketIndex
+}
+
+/// Init function that creates a new lottery.
+#[init(contract = "lottery", parameter = "InitParameter")]
+fn lottery_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.draw_time < parameter.reveal_deadline, ContractError::InvalidSchedule.into());
+    ensure!(parameter.ticket_price > Amount::zero(), ContractError::ZeroTicketPrice.into());
+    Ok(State {
+        ticket_price:    parameter.ticket_price,
+        draw_time:       parameter.draw_time,
+        reveal_deadline: parameter.reveal_deadline,
+        seed_commitment: parameter.seed_commitment,
+        ticket_entropy:  HashSha2256([0; 32]),
+        ticket_count:    0,
+        tickets:         state_builder.new_map(),
+        purchases:       state_builder.new_map(),
+        revealed:        false,
+        draw:            None,
+    })
+}
+
+/// Buy tickets. The amount paid must be a multiple of the ticket price, and
+/// buys that number of tickets.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The draw time has passed.
+/// - The amount is not a positive multiple of the ticket price.
+/// - The number of tickets would overflow.
+#[receive(
+    contract = "lottery",
+    name = "buyTickets",
+    error = "ContractError",
+    crypto_primitives,
+    payable,
+    mutable
+)]
+fn lottery_buy_tickets<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> Result<(), ContractError> {
+    let buyer = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    let slot_time = ctx.metadata().slot_time();
+    ensure!(slot_time <= state.draw_time, ContractError::SalesClosed);
+
+    let price = state.ticket_price.micro_ccd;
+    ensure!(amount.micro_ccd > 0 && amount.micro_ccd % price == 0, ContractError::InvalidPayment);
+    let tickets = amount.micro_ccd / price;
+    ensure!(tickets <= u64::from(u32::MAX), ContractError::TooManyTickets);
+    let tickets = tickets as u32;
+    let first = state.ticket_count;
+    state.ticket_count = first.checked_add(tickets).ok_or(ContractError::TooManyTickets)?;
+
+    for index in first..state.ticket_count {
+        state.tickets.insert(index, buyer);
+    }
+    *state.purchases.entry(buyer).or_insert(0) += tickets;
+    state.ticket_entropy = crypto_primitives.hash_sha2_256(&entropy_message(
+        &state.ticket_entropy,
+        &buyer,
+        slot_time,
+        tickets,
+    ));
+    Ok(())
+}
+
+/// Reveal the seed, drawing the winning ticket. The holder of the winning
+/// ticket receives the price of all the tickets sold.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+/// - It is before the draw time or after the reveal deadline.
+/// - The seed has already been revealed.
+/// - The seed does not match the commitment.
+#[receive(
+    contract = "lottery",
+    name = "reveal",
+    parameter = "[u8; 32]",
+    error = "ContractError",
+    crypto_primitives,
+    mutable
+)]
+fn lottery_reveal<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> Result<(), ContractError> {
+    let seed: [u8; 32] = ctx.parameter_cursor().get()?;
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    let state = host.state_mut();
+    let slot_time = ctx.metadata().slot_time();
+    ensure!(
+        state.draw_time < slot_time && slot_time <= state.reveal_deadline,
+        ContractError::NotRevealPhase
+    );
+    ensure!(!state.revealed, ContractError::AlreadyRevealed);
+    ensure_eq!(
+        crypto_primitives.hash_sha2_256(&seed),
+        state.seed_commitment,
+        ContractError::InvalidSeed
+    );
+    state.revealed = true;
+
+    if state.ticket_count == 0 {
+        return Ok(());
+    }
+    let ticket =
+        winning_ticket(crypto_primitives, &seed, &state.ticket_entropy, state.ticket_count);
+    let winner = state.tickets.get(&ticket).map(|holder| *holder).unwrap_abort();
+    let prize = host.self_balance();
+    host.state_mut().draw = Some(Draw {
+        ticket,
+        winner,
+        prize,
+    });
+
+    // The transfer always succeeds, since the winner is an existing account
+    // and the contract holds the price of all the tickets.
+    host.invoke_transfer(&winner, prize).unwrap_abort();
+    Ok(())
+}
+
+/// Refund the tickets of the sender, when the owner did not reveal the seed
+/// before the reveal deadline.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The reveal deadline has not passed, or the seed was revealed.
+/// - The sender has no tickets to refund.
+#[receive(contract = "lottery", name = "refund", error = "ContractError", mutable)]
+fn lottery_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let player = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    ensure!(
+        !state.revealed && ctx.metadata().slot_time() > state.reveal_deadline,
+        ContractError::RefundUnavailable
+    );
+    let tickets = state.purchases.remove_and_get(&player).ok_or(ContractError::NothingToRefund)?;
+    // Cannot overflow, since the contract received this amount for the tickets.
+    let refund = Amount::from_micro_ccd(state.ticket_price.micro_ccd * u64::from(tickets));
+
+    // The transfer always succeeds, since the player is the sender and the
+    // contract holds the price of all the tickets.
+    host.invoke_transfer(&player, refund).unwrap_abort();
+    Ok(())
+}
+
+/// View function that returns the general information about the lottery.
+#[receive(contract = "lottery", name = "view", return_value = "ViewState")]
+fn lottery_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        ticket_price:    state.ticket_price,
+        draw_time:       state.draw_time,
+        reveal_deadline: state.reveal_deadline,
+        ticket_count:    state.ticket_count,
+        revealed:        state.revealed,
+        draw:            state.draw,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+    const DRAW_TIME: u64 = 10;
+    const REVEAL_DEADLINE: u64 = 20;
+    const SEED: [u8; 32] = [42; 32];
+    const TICKET_PRICE: Amount = Amount {
+        micro_ccd: 100,
+    };
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            ticket_price:    TICKET_PRICE,
+            draw_time:       Timestamp::from_timestamp_millis(DRAW_TIME),
+            reveal_deadline: Timestamp::from_timestamp_millis(REVEAL_DEADLINE),
+            seed_commitment: TestCryptoPrimitives::new().hash_sha2_256(&SEED),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            lottery_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(sender: AccountAddress, slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_at(sender, slot_time);
+        ctx.set_owner(OWNER);
+        ctx
+    }
+
+    fn buy(
+        host: &mut TestHost<State<TestStateApi>>,
+        buyer: AccountAddress,
+        micro_ccd: u64,
+        slot_time: u64,
+    ) -> Result<(), ContractError> {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        lottery_buy_tickets(&ctx_at(buyer, slot_time), host, amount, &TestCryptoPrimitives::new())
+    }
+
+    fn reveal(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        seed: [u8; 32],
+        slot_time: u64,
+    ) -> Result<(), ContractError> {
+        let parameter_bytes = to_bytes(&seed);
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        lottery_reveal(&ctx, host, &TestCryptoPrimitives::new())
+    }
+
+    #[concordium_test]
+    /// Test a full lottery: the winner is the holder of the ticket selected by
+    /// the seed and the ticket entropy, and receives all ticket sales.
+    fn test_full_lottery() {
+        let mut host = new_host();
+        buy(&mut host, ALICE, 300, 0).expect_report("Buying should pass");
+        buy(&mut host, BOB, 100, 5).expect_report("Buying should pass");
+        buy(&mut host, ALICE, 200, DRAW_TIME).expect_report("Buying at the draw should pass");
+        claim_eq!(host.state().ticket_count, 6);
+
+        let res = reveal(&mut host, OWNER, SEED, DRAW_TIME);
+        claim_eq!(res, Err(ContractError::NotRevealPhase), "Revealing before the draw fails");
+        let res = reveal(&mut host, ALICE, SEED, DRAW_TIME + 1);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the owner can reveal");
+        let res = reveal(&mut host, OWNER, [0; 32], DRAW_TIME + 1);
+        claim_eq!(res, Err(ContractError::InvalidSeed), "Revealing another seed fails");
+
+        reveal(&mut host, OWNER, SEED, REVEAL_DEADLINE).expect_report("Revealing should pass");
+        let expected_ticket =
+            winning_ticket(&TestCryptoPrimitives::new(), &SEED, &host.state().ticket_entropy, 6);
+        let expected_winner = if expected_ticket == 3 {
+            BOB
+        } else {
+            ALICE
+        };
+        let prize = Amount::from_micro_ccd(600);
+        claim_eq!(
+            host.state().draw,
+            Some(Draw {
+                ticket: expected_ticket,
+                winner: expected_winner,
+                prize,
+            })
+        );
+        claim_eq!(host.get_transfers(), [(expected_winner, prize)]);
+
+        let res = reveal(&mut host, OWNER, SEED, REVEAL_DEADLINE);
+        claim_eq!(res, Err(ContractError::AlreadyRevealed), "Revealing twice fails");
+        let res = lottery_refund(&ctx_at(ALICE, REVEAL_DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::RefundUnavailable), "No refunds after a draw");
+    }
+
+    #[concordium_test]
+    /// Test that the ticket entropy depends on the purchases, such that the
+    /// owner cannot predict it when committing to the seed.
+    fn test_entropy_depends_on_purchases() {
+        let mut host_a = new_host();
+        buy(&mut host_a, ALICE, 100, 0).expect_report("Buying should pass");
+        let mut host_b = new_host();
+        buy(&mut host_b, BOB, 100, 0).expect_report("Buying should pass");
+        let mut host_c = new_host();
+        buy(&mut host_c, ALICE, 100, 1).expect_report("Buying should pass");
+
+        let entropy_a = host_a.state().ticket_entropy;
+        claim!(entropy_a != host_b.state().ticket_entropy, "The buyer should matter");
+        claim!(entropy_a != host_c.state().ticket_entropy, "The time should matter");
+    }
+
+    #[concordium_test]
+    /// Test that every player can refund their tickets once, when the owner
+    /// does not reveal the seed.
+    fn test_refund_without_reveal() {
+        let mut host = new_host();
+        buy(&mut host, ALICE, 300, 0).expect_report("Buying should pass");
+        buy(&mut host, BOB, 100, 0).expect_report("Buying should pass");
+
+        let res = lottery_refund(&ctx_at(ALICE, REVEAL_DEADLINE), &mut host);
+        claim_eq!(res, Err(ContractError::RefundUnavailable), "The seed can still be revealed");
+        let res = reveal(&mut host, OWNER, SEED, REVEAL_DEADLINE + 1);
+        claim_eq!(res, Err(ContractError::NotRevealPhase), "Revealing late fails");
+
+        lottery_refund(&ctx_at(ALICE, REVEAL_DEADLINE + 1), &mut host)
+            .expect_report("Refunding should pass");
+        let res = lottery_refund(&ctx_at(ALICE, REVEAL_DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToRefund), "Refunding twice fails");
+        let res = lottery_refund(&ctx_at(CAROL, REVEAL_DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToRefund), "Non-players get nothing");
+        lottery_refund(&ctx_at(BOB, REVEAL_DEADLINE + 1), &mut host)
+            .expect_report("Refunding should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(300)),
+            (BOB, Amount::from_micro_ccd(100))
+        ]);
+        claim_eq!(host.self_balance(), Amount::zero(), "Everything should be refunded");
+    }
+
+    #[concordium_test]
+    /// Test that invalid payments and late purchases are rejected.
+    fn test_buy_rejected() {
+        let mut host = new_host();
+        claim_eq!(buy(&mut host, ALICE, 0, 0), Err(ContractError::InvalidPayment));
+        claim_eq!(buy(&mut host, ALICE, 150, 0), Err(ContractError::InvalidPayment));
+        claim_eq!(buy(&mut host, ALICE, 100, DRAW_TIME + 1), Err(ContractError::SalesClosed));
+        claim_eq!(host.state().ticket_count, 0);
+    }
+
+    #[concordium_test]
+    /// Test that revealing without tickets sold does not draw a winner.
+    fn test_reveal_without_tickets() {
+        let mut host = new_host();
+        reveal(&mut host, OWNER, SEED, DRAW_TIME + 1).expect_report("Revealing should pass");
+        claim!(host.state().revealed);
+        claim_eq!(host.state().draw, None);
+        claim!(host.get_transfers().is_empty());
+    }
+}
@@ -0,0 +1,463 @@
+//! # Loyalty points
+//!
+//! Viewers earn loyalty points off-chain, e.g., for watching streams, which an
+//! authorized backend credits to them using `credit`. Points are not tokens;
+//! they cannot be transferred, only converted. Viewers `convert` their
+//! accumulated points into fungible CIS-2 tokens of a [`cis2-fungible`]
+//! instance at the rate configured by the owner, which is the number of points
+//! per token.
+//!
+//! The contract mints the tokens with a cross-contract call to `mint` of the
+//! token contract, which requires this contract to be its admin, see
+//! `updateAdmin` of `cis2-fungible`. To limit the damage of a compromised
+//! backend, every viewer can convert at most the daily cap of tokens per day.
+//! Days start at midnight UTC.
+//!
+//! [`cis2-fungible`]: ../cis2-fungible
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// The number of milliseconds in a day.
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// The token amount type of `cis2_fungible`.
+type ContractTokenAmount = TokenAmountU64;
+
+/// The configuration set by the owner.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The address allowed to credit points.
+    pub backend:          Address,
+    /// The number of points converted into one token.
+    pub points_per_token: u64,
+    /// The number of tokens every viewer can convert per day.
+    pub daily_cap:        ContractTokenAmount,
+}
+
+/// The points and conversions of a viewer.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Viewer {
+    /// The points which have not been converted.
+    pub points:    u64,
+    /// The day of the latest conversion, counted from 1970-01-01.
+    pub day:       u64,
+    /// The number of tokens converted on that day.
+    pub converted: ContractTokenAmount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The `cis2_fungible` instance minting the tokens.
+    token_contract: ContractAddress,
+    /// The configuration set by the owner.
+    config:         Config,
+    /// The viewers who have been credited points.
+    viewers:        StateMap<AccountAddress, Viewer, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The `cis2_fungible` instance minting the tokens.
+    token_contract: ContractAddress,
+    /// The initial configuration.
+    config:         Config,
+}
+
+/// A credit of points to a viewer.
+#[derive(Serialize, SchemaType, Clone, Copy)]
+pub struct Credit {
+    /// The viewer receiving the points.
+    pub viewer: AccountAddress,
+    /// The number of points.
+    pub points: u64,
+}
+
+/// The parameter type for the contract function `credit`.
+#[derive(Serialize, SchemaType)]
+struct CreditParams {
+    /// The credits to make.
+    #[concordium(size_length = 2)]
+    credits: Vec<Credit>,
+}
+
+/// The parameter type for the contract function `mint` of `cis2_fungible`.
+#[derive(Serialize, SchemaType)]
+struct TokenMintParams {
+    /// The owner of the newly minted tokens.
+    owner:  Address,
+    /// The amount of tokens to mint.
+    amount: ContractTokenAmount,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum LoyaltyEvent {
+    /// Points were credited to a viewer.
+    Credited {
+        viewer: AccountAddress,
+        points: u64,
+    },
+    /// Points of a viewer were converted into tokens.
+    Converted {
+        viewer: AccountAddress,
+        points: u64,
+        tokens: ContractTokenAmount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed to invoke the token contract.
+    InvokeContractError,
+    /// Raised when a contract converts points.
+    OnlyAccount,
+    /// Raised when someone else than the backend credits points, or someone
+    /// else than the owner updates the configuration.
+    Unauthorized,
+    /// Raised when the points of a viewer would overflow.
+    Overflow,
+    /// Raised when the viewer has too few points for the conversion.
+    InsufficientPoints,
+    /// Raised when the conversion would exceed the daily cap.
+    DailyCapExceeded,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Initialize the contract with the token contract and the configuration.
+#[init(contract = "loyalty_points", parameter = "InitParams", event = "LoyaltyEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    Ok(State {
+        token_contract: params.token_contract,
+        config:         params.config,
+        viewers:        state_builder.new_map(),
+    })
+}
+
+/// Credit points to viewers. Can only be called by the backend.
+/// Logs a `Credited` event for every credit.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the backend.
+/// - The points of a viewer would overflow.
+/// - It fails to log the events.
+#[receive(
+    contract = "loyalty_points",
+    name = "credit",
+    parameter = "CreditParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_credit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: CreditParams = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure_eq!(ctx.sender(), state.config.backend, ContractError::Unauthorized);
+    for credit in params.credits {
+        let mut viewer = state.viewers.entry(credit.viewer).or_insert_with(Viewer::default);
+        viewer.points = viewer.points.checked_add(credit.points).ok_or(ContractError::Overflow)?;
+        logger.log(&LoyaltyEvent::Credited {
+            viewer: credit.viewer,
+            points: credit.points,
+        })?;
+    }
+    Ok(())
+}
+
+/// Convert points of the sender into the given amount of tokens, which are
+/// minted to the sender by the token contract.
+/// Logs a `Converted` event.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The sender has too few points.
+/// - The conversion would exceed the daily cap of the sender.
+/// - Minting fails, e.g., because this contract is not the admin of the token
+///   contract.
+/// - It fails to log the event.
+#[receive(
+    contract = "loyalty_points",
+    name = "convert",
+    parameter = "ContractTokenAmount",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_convert<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let tokens: ContractTokenAmount = ctx.parameter_cursor().get()?;
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let today = ctx.metadata().slot_time().timestamp_millis() / MILLIS_PER_DAY;
+    let state = host.state_mut();
+    let config = state.config;
+    let points = tokens.0.checked_mul(config.points_per_token).ok_or(ContractError::Overflow)?;
+    {
+        let mut viewer = state.viewers.get_mut(&sender).ok_or(ContractError::InsufficientPoints)?;
+        ensure!(viewer.points >= points, ContractError::InsufficientPoints);
+        if viewer.day != today {
+            viewer.day = today;
+            viewer.converted = 0.into();
+        }
+        let converted = viewer.converted.0.checked_add(tokens.0).ok_or(ContractError::Overflow)?;
+        ensure!(converted <= config.daily_cap.0, ContractError::DailyCapExceeded);
+        viewer.points -= points;
+        viewer.converted = converted.into();
+    }
+
+    let params = TokenMintParams {
+        owner:  Address::Account(sender),
+        amount: tokens,
+    };
+    let token_contract = state.token_contract;
+    host.invoke_contract(
+        &token_contract,
+        &params,
+        EntrypointName::new_unchecked("mint"),
+        Amount::zero(),
+    )?;
+    logger.log(&LoyaltyEvent::Converted {
+        viewer: sender,
+        points,
+        tokens,
+    })?;
+    Ok(())
+}
+
+/// Replace the configuration. Can only be called by the owner.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner of the contract instance.
+#[receive(
+    contract = "loyalty_points",
+    name = "setConfig",
+    parameter = "Config",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_config<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let config: Config = ctx.parameter_cursor().get()?;
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    host.state_mut().config = config;
+    Ok(())
+}
+
+/// View the points and conversions of a viewer.
+#[receive(
+    contract = "loyalty_points",
+    name = "viewer",
+    parameter = "AccountAddress",
+    return_value = "Viewer"
+)]
+fn contract_viewer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Viewer> {
+    let viewer: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().viewers.get(&viewer).map(|v| *v).unwrap_or_default())
+}
+
+/// View the configuration.
+#[receive(contract = "loyalty_points", name = "config", return_value = "Config")]
+fn contract_config<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Config> {
+    Ok(host.state().config)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const BACKEND: AccountAddress = AccountAddress([1; 32]);
+    const VIEWER: AccountAddress = AccountAddress([2; 32]);
+    const TOKEN: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const CONFIG: Config = Config {
+        backend:          Address::Account(BACKEND),
+        points_per_token: 10,
+        daily_cap:        TokenAmountU64(5),
+    };
+
+    /// Create a host where `VIEWER` has been credited 100 points, and the
+    /// token contract mints to `VIEWER`.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            token_contract: TOKEN,
+            config:         CONFIG,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.setup_mock_entrypoint(
+            TOKEN,
+            OwnedEntrypointName::new_unchecked("mint".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: TokenMintParams = from_bytes(parameter.0).expect_report("Invalid mint");
+                claim_eq!(params.owner, Address::Account(VIEWER), "Minted to the viewer");
+                Ok((false, ()))
+            }),
+        );
+        credit(&mut host, BACKEND, 100, &mut TestLogger::init())
+            .expect_report("Crediting should pass");
+        host
+    }
+
+    fn credit(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        points: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&CreditParams {
+            credits: vec![Credit {
+                viewer: VIEWER,
+                points,
+            }],
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_parameter(&parameter_bytes);
+        contract_credit(&ctx, host, logger)
+    }
+
+    fn convert(
+        host: &mut TestHost<State<TestStateApi>>,
+        tokens: u64,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&TokenAmountU64(tokens));
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(VIEWER));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        contract_convert(&ctx, host, logger)
+    }
+
+    #[concordium_test]
+    /// Test that only the backend can credit points, which are logged.
+    fn test_credit() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        let res = credit(&mut host, VIEWER, 1, &mut logger);
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        credit(&mut host, BACKEND, 20, &mut logger).expect_report("Crediting should pass");
+        claim_eq!(logger.logs, [to_bytes(&LoyaltyEvent::Credited {
+            viewer: VIEWER,
+            points: 20,
+        })]);
+        claim_eq!(host.state().viewers.get(&VIEWER).map(|v| v.points), Some(120));
+    }
+
+    #[concordium_test]
+    /// Test that converting deducts the points at the configured rate and
+    /// mints the tokens, up to the daily cap.
+    fn test_convert() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        convert(&mut host, 3, 0, &mut logger).expect_report("Converting should pass");
+        claim_eq!(logger.logs, [to_bytes(&LoyaltyEvent::Converted {
+            viewer: VIEWER,
+            points: 30,
+            tokens: 3.into(),
+        })]);
+        let res = convert(&mut host, 3, MILLIS_PER_DAY - 1, &mut logger);
+        claim_eq!(res, Err(ContractError::DailyCapExceeded));
+        convert(&mut host, 2, MILLIS_PER_DAY - 1, &mut logger)
+            .expect_report("Converting up to the cap should pass");
+        convert(&mut host, 5, MILLIS_PER_DAY, &mut logger)
+            .expect_report("The cap should reset the next day");
+
+        let res = convert(&mut host, 1, 2 * MILLIS_PER_DAY, &mut logger);
+        claim_eq!(res, Err(ContractError::InsufficientPoints));
+        claim_eq!(
+            host.state().viewers.get(&VIEWER).map(|v| *v),
+            Some(Viewer {
+                points:    0,
+                day:       1,
+                converted: 5.into(),
+            })
+        );
+    }
+
+    #[concordium_test]
+    /// Test that only the owner can update the configuration, and that the
+    /// new rate applies.
+    fn test_set_config() {
+        let mut host = new_host();
+        let config = Config {
+            points_per_token: 50,
+            ..CONFIG
+        };
+        let parameter_bytes = to_bytes(&config);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(OWNER);
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_sender(Address::Account(BACKEND));
+        claim_eq!(contract_set_config(&ctx, &mut host), Err(ContractError::Unauthorized));
+        ctx.set_sender(Address::Account(OWNER));
+        contract_set_config(&ctx, &mut host).expect_report("Updating should pass");
+
+        let mut logger = TestLogger::init();
+        convert(&mut host, 2, 0, &mut logger).expect_report("Converting should pass");
+        let res = convert(&mut host, 1, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::InsufficientPoints), "The new rate should apply");
+    }
+}
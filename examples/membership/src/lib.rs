@@ -0,0 +1,955 @@
+//! Tiered memberships with expiry and renewal using the Concordium Token
+//! Standard CIS2.
+//!
+//! # Description
+//! The owner of the contract instance configures membership tiers with
+//! `set_tier`, each with a price, how long a membership lasts and the base URL
+//! of the metadata of its memberships. Changing a tier only affects later
+//! purchases and renewals.
+//!
+//! Anyone can `join` a tier by paying its price, which mints a new membership
+//! NFT to the sender. The holder of a membership renews it with `renew` by
+//! paying the price of its tier before it expires, which extends the expiry by
+//! the duration of the tier. Expired memberships cannot be renewed, but are
+//! not burned either; the holder joins again instead. All payments are
+//! transferred to the owner right away.
+//!
+//! The metadata URL of a membership encodes its tier and expiry as query
+//! parameters of the base URL of the tier, and a `TokenMetadata` event is
+//! logged whenever it changes, so wallets can show the current expiry.
+//!
+//! Other contracts and dApps gate content using `is_member`, which checks that
+//! an account holds a membership of at least a given tier, which has not
+//! expired. Since memberships are CIS2 tokens, they can be transferred to other
+//! addresses as any other CIS2 token.
+//!
+//! Note: The word 'address' refers to either an account address or a
+//! contract address.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// List of supported standards by this contract address.
+const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
+    [CIS0_STANDARD_IDENTIFIER, CIS2_STANDARD_IDENTIFIER];
+
+// Types
+
+/// Contract token ID type.
+/// The memberships are numbered in the order they are minted.
+type ContractTokenId = TokenIdU32;
+
+/// Contract token amount.
+/// Since the memberships are non-fungible the total supply of any token will
+/// be at most 1 and it is fine to use a small type for representing token
+/// amounts.
+type ContractTokenAmount = TokenAmountU8;
+
+/// Identifies a membership tier. Higher tiers include the lower ones.
+pub type TierId = u8;
+
+/// A membership tier.
+#[derive(Serialize, SchemaType, Clone, Debug, PartialEq, Eq)]
+pub struct Tier {
+    /// The price of joining or renewing.
+    pub price:             Amount,
+    /// How long a membership lasts from joining, or is extended by renewing.
+    pub duration:          Duration,
+    /// The base URL of the metadata of the memberships of the tier.
+    pub metadata_base_url: String,
+}
+
+/// A membership, valid until it expires.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Membership {
+    /// The tier of the membership.
+    pub tier:    TierId,
+    /// When the membership expires.
+    pub expires: Timestamp,
+}
+
+/// The parameter for the contract function `set_tier`.
+#[derive(Serialize, SchemaType)]
+struct SetTierParams {
+    /// The tier to add or change.
+    tier_id: TierId,
+    /// The new configuration of the tier.
+    tier:    Tier,
+}
+
+/// The parameter for the contract function `is_member`.
+#[derive(Serialize, SchemaType)]
+struct IsMemberParams {
+    /// The account to check.
+    account:  AccountAddress,
+    /// The lowest tier the membership must be of.
+    min_tier: TierId,
+}
+
+/// The state for each address.
+#[derive(Serial, DeserialWithState, Deletable, StateClone)]
+#[concordium(state_parameter = "S")]
+struct AddressState<S> {
+    /// The memberships owned by this address.
+    owned_tokens: StateSet<ContractTokenId, S>,
+    /// The address which are currently enabled as operators for this address.
+    operators:    StateSet<Address, S>,
+}
+
+impl<S: HasStateApi> AddressState<S> {
+    fn empty(state_builder: &mut StateBuilder<S>) -> Self {
+        AddressState {
+            owned_tokens: state_builder.new_set(),
+            operators:    state_builder.new_set(),
+        }
+    }
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The state for each address.
+    state:         StateMap<Address, AddressState<S>, S>,
+    /// The configured tiers.
+    tiers:         StateMap<TierId, Tier, S>,
+    /// All of the memberships.
+    all_tokens:    StateMap<ContractTokenId, Membership, S>,
+    /// The token ID of the next membership.
+    next_token_id: u32,
+}
+
+/// The custom errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum CustomContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed to invoke a contract.
+    InvokeContractError,
+    /// Only the owner can configure tiers.
+    Unauthorized,
+    /// There is no tier with the given ID.
+    UnknownTier,
+    /// The CCD sent along is not the price of the tier.
+    WrongPayment,
+    /// Only the holder of a membership can renew it.
+    NotHolder,
+    /// The membership has expired, and can no longer be renewed.
+    Expired,
+    /// The expiry of the membership cannot be represented.
+    ExpiryOverflow,
+    /// All token IDs have been used.
+    NoTokenIdsLeft,
+}
+
+/// Wrapping the custom errors in a type with CIS2 errors.
+type ContractError = Cis2Error<CustomContractError>;
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to CustomContractError.
+impl From<LogError> for CustomContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to CustomContractError.
+impl<T> From<CallContractError<T>> for CustomContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Mapping CustomContractError to ContractError
+impl From<CustomContractError> for ContractError {
+    fn from(c: CustomContractError) -> Self { Cis2Error::Custom(c) }
+}
+
+// Functions for creating, updating and querying the contract state.
+impl<S: HasStateApi> State<S> {
+    /// Creates a new state with no tiers and no memberships.
+    fn empty(state_builder: &mut StateBuilder<S>) -> Self {
+        State {
+            state:         state_builder.new_map(),
+            tiers:         state_builder.new_map(),
+            all_tokens:    state_builder.new_map(),
+            next_token_id: 0,
+        }
+    }
+
+    /// Mint a new membership with a given address as the owner, returning
+    /// the token ID of the membership.
+    fn mint(
+        &mut self,
+        membership: Membership,
+        owner: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<ContractTokenId> {
+        let token_id = TokenIdU32(self.next_token_id);
+        self.next_token_id =
+            self.next_token_id.checked_add(1).ok_or(CustomContractError::NoTokenIdsLeft)?;
+        self.all_tokens.insert(token_id, membership);
+
+        let mut owner_state =
+            self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
+        owner_state.owned_tokens.insert(token_id);
+        Ok(token_id)
+    }
+
+    /// Check that the token ID currently exists in this contract.
+    #[inline(always)]
+    fn contains_token(&self, token_id: &ContractTokenId) -> bool {
+        self.all_tokens.get(token_id).is_some()
+    }
+
+    /// Get the metadata URL of a membership, which is the base URL of its tier
+    /// with the tier and the expiry as query parameters.
+    /// Results in an error if the token ID does not exist in the state.
+    fn metadata_url(&self, token_id: &ContractTokenId) -> ContractResult<MetadataUrl> {
+        let membership = self.all_tokens.get(token_id).ok_or(ContractError::InvalidTokenId)?;
+        let tier = self.tiers.get(&membership.tier).ok_or(ContractError::InvalidTokenId)?;
+        Ok(MetadataUrl {
+            url:  format!(
+                "{}?tier={}&expires={}",
+                tier.metadata_base_url,
+                membership.tier,
+                membership.expires.timestamp_millis()
+            ),
+            hash: None,
+        })
+    }
+
+    /// Get the current balance of a given token ID for a given address.
+    /// Results in an error if the token ID does not exist in the state.
+    /// Since this contract only contains NFTs, the balance will always be
+    /// either 1 or 0.
+    fn balance(
+        &self,
+        token_id: &ContractTokenId,
+        address: &Address,
+    ) -> ContractResult<ContractTokenAmount> {
+        ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
+        let balance = self
+            .state
+            .get(address)
+            .map(|address_state| u8::from(address_state.owned_tokens.contains(token_id)))
+            .unwrap_or(0);
+        Ok(balance.into())
+    }
+
+    /// Check if an address owns a membership of at least the given tier, which
+    /// has not expired at the given time.
+    fn is_member(&self, address: &Address, min_tier: TierId, now: Timestamp) -> bool {
+        self.state
+            .get(address)
+            .map(|address_state| {
+                address_state.owned_tokens.iter().any(|token_id| {
+                    self.all_tokens
+                        .get(&*token_id)
+                        .map(|membership| membership.tier >= min_tier && now < membership.expires)
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Check if a given address is an operator of a given owner address.
+    fn is_operator(&self, address: &Address, owner: &Address) -> bool {
+        self.state
+            .get(owner)
+            .map(|address_state| address_state.operators.contains(address))
+            .unwrap_or(false)
+    }
+
+    /// Update the state with a transfer of some token.
+    /// Results in an error if the token ID does not exist in the state or if
+    /// the from address have insufficient tokens to do the transfer.
+    fn transfer(
+        &mut self,
+        token_id: &ContractTokenId,
+        amount: ContractTokenAmount,
+        from: &Address,
+        to: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<()> {
+        ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
+        // A zero transfer does not modify the state.
+        if amount == 0.into() {
+            return Ok(());
+        }
+        // Since this contract only contains NFTs, no one will have an amount greater
+        // than 1. And since the amount cannot be the zero at this point, the
+        // address must have insufficient funds for any amount other than 1.
+        ensure_eq!(amount, 1.into(), ContractError::InsufficientFunds);
+
+        {
+            let mut from_address_state =
+                self.state.get_mut(from).ok_or(ContractError::InsufficientFunds)?;
+            // Find and remove the token from the owner, if nothing is removed, we know the
+            // address did not own the token.
+            let from_had_the_token = from_address_state.owned_tokens.remove(token_id);
+            ensure!(from_had_the_token, ContractError::InsufficientFunds);
+        }
+
+        // Add the token to the new owner.
+        let mut to_address_state =
+            self.state.entry(*to).or_insert_with(|| AddressState::empty(state_builder));
+        to_address_state.owned_tokens.insert(*token_id);
+        Ok(())
+    }
+
+    /// Update the state adding a new operator for a given address.
+    /// Succeeds even if the `operator` is already an operator for the
+    /// `address`.
+    fn add_operator(
+        &mut self,
+        owner: &Address,
+        operator: &Address,
+        state_builder: &mut StateBuilder<S>,
+    ) {
+        let mut owner_state =
+            self.state.entry(*owner).or_insert_with(|| AddressState::empty(state_builder));
+        owner_state.operators.insert(*operator);
+    }
+
+    /// Update the state removing an operator for a given address.
+    /// Succeeds even if the `operator` is _not_ an operator for the `address`.
+    fn remove_operator(&mut self, owner: &Address, operator: &Address) {
+        self.state.entry(*owner).and_modify(|address_state| {
+            address_state.operators.remove(operator);
+        });
+    }
+}
+
+// Contract functions
+
+/// Initialize contract instance with no tiers.
+#[init(contract = "membership", event = "Cis2Event<ContractTokenId, ContractTokenAmount>")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    // Construct the initial contract state.
+    Ok(State::empty(state_builder))
+}
+
+/// Add or change a tier. Only the owner of the contract instance can configure
+/// tiers.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+#[receive(
+    contract = "membership",
+    name = "set_tier",
+    parameter = "SetTierParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_tier<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let params: SetTierParams = ctx.parameter_cursor().get()?;
+    ensure!(ctx.sender().matches_account(&ctx.owner()), CustomContractError::Unauthorized.into());
+    host.state_mut().tiers.insert(params.tier_id, params.tier);
+    Ok(())
+}
+
+/// Join a tier, paying its price, which is sent to the owner. Mints a new
+/// membership to the sender, which expires when the duration of the tier has
+/// passed.
+/// Logs a `Mint` and a `TokenMetadata` event for the membership.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The tier does not exist.
+/// - The amount sent along is not the price of the tier.
+/// - The expiry of the membership overflows.
+/// - Fails to log Mint event
+/// - Fails to log TokenMetadata event
+#[receive(
+    contract = "membership",
+    name = "join",
+    parameter = "TierId",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_join<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let tier_id: TierId = ctx.parameter_cursor().get()?;
+    let owner = ctx.sender();
+
+    let (state, builder) = host.state_and_builder();
+    let tier = state.tiers.get(&tier_id).ok_or(CustomContractError::UnknownTier)?.clone();
+    ensure_eq!(amount, tier.price, CustomContractError::WrongPayment.into());
+    let expires = ctx
+        .metadata()
+        .slot_time()
+        .checked_add(tier.duration)
+        .ok_or(CustomContractError::ExpiryOverflow)?;
+
+    // Mint the membership in the state.
+    let token_id = state.mint(
+        Membership {
+            tier: tier_id,
+            expires,
+        },
+        &owner,
+        builder,
+    )?;
+
+    // Event for minted membership.
+    logger.log(&Cis2Event::Mint(MintEvent {
+        token_id,
+        amount: ContractTokenAmount::from(1),
+        owner,
+    }))?;
+
+    // Metadata URL for the membership.
+    logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+        token_id,
+        metadata_url: state.metadata_url(&token_id)?,
+    }))?;
+
+    // The transfer always succeeds, since the owner is an account and the
+    // payment was just received by the contract.
+    host.invoke_transfer(&ctx.owner(), amount).unwrap_abort();
+    Ok(())
+}
+
+/// Renew a membership held by the sender, paying the current price of its
+/// tier, which is sent to the owner. Extends the expiry by the duration of the
+/// tier.
+/// Logs a `TokenMetadata` event with the new expiry.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The `token_id` does not exist.
+/// - The sender does not hold the membership.
+/// - The membership has expired.
+/// - The amount sent along is not the price of the tier.
+/// - The expiry of the membership overflows.
+/// - Fails to log TokenMetadata event
+#[receive(
+    contract = "membership",
+    name = "renew",
+    parameter = "ContractTokenId",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_renew<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let token_id: ContractTokenId = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    let membership =
+        state.all_tokens.get(&token_id).map(|m| *m).ok_or(ContractError::InvalidTokenId)?;
+    ensure!(
+        state.balance(&token_id, &ctx.sender())? == 1.into(),
+        CustomContractError::NotHolder.into()
+    );
+    ensure!(ctx.metadata().slot_time() < membership.expires, CustomContractError::Expired.into());
+    let tier = state.tiers.get(&membership.tier).ok_or(CustomContractError::UnknownTier)?.clone();
+    ensure_eq!(amount, tier.price, CustomContractError::WrongPayment.into());
+    let expires =
+        membership.expires.checked_add(tier.duration).ok_or(CustomContractError::ExpiryOverflow)?;
+    state.all_tokens.insert(token_id, Membership {
+        tier: membership.tier,
+        expires,
+    });
+
+    // Metadata URL with the new expiry.
+    logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+        token_id,
+        metadata_url: state.metadata_url(&token_id)?,
+    }))?;
+
+    // The transfer always succeeds, since the owner is an account and the
+    // payment was just received by the contract.
+    host.invoke_transfer(&ctx.owner(), amount).unwrap_abort();
+    Ok(())
+}
+
+/// Check whether an account holds a membership of at least a given tier, which
+/// has not expired.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "membership",
+    name = "is_member",
+    parameter = "IsMemberParams",
+    return_value = "bool",
+    error = "ContractError"
+)]
+fn contract_is_member<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<bool> {
+    // Parse the parameter.
+    let params: IsMemberParams = ctx.parameter_cursor().get()?;
+    let now = ctx.metadata().slot_time();
+    Ok(host.state().is_member(&Address::Account(params.account), params.min_tier, now))
+}
+
+/// Get the membership of a token ID, i.e. the tier and the expiry.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The `token_id` does not exist.
+#[receive(
+    contract = "membership",
+    name = "membership",
+    parameter = "ContractTokenId",
+    return_value = "Membership",
+    error = "ContractError"
+)]
+fn contract_membership<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Membership> {
+    // Parse the parameter.
+    let token_id: ContractTokenId = ctx.parameter_cursor().get()?;
+    host.state().all_tokens.get(&token_id).map(|m| *m).ok_or(ContractError::InvalidTokenId)
+}
+
+type TransferParameter = TransferParams<ContractTokenId, ContractTokenAmount>;
+
+/// Execute a list of token transfers, in the order of the list.
+///
+/// Logs a `Transfer` event and invokes a receive hook function for every
+/// transfer in the list.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the transfers fail to be executed, which could be if:
+///     - The `token_id` does not exist.
+///     - The sender is not the owner of the token, or an operator for this
+///       specific `token_id` and `from` address.
+///     - The token is not owned by the `from`.
+/// - Fails to log event.
+/// - Any of the receive hook function calls rejects.
+#[receive(
+    contract = "membership",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_transfer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let TransferParams(transfers): TransferParameter = ctx.parameter_cursor().get()?;
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+
+    for Transfer {
+        token_id,
+        amount,
+        from,
+        to,
+        data,
+    } in transfers
+    {
+        let (state, builder) = host.state_and_builder();
+        // Authenticate the sender for this transfer
+        ensure!(from == sender || state.is_operator(&sender, &from), ContractError::Unauthorized);
+        let to_address = to.address();
+        // Update the contract state
+        state.transfer(&token_id, amount, &from, &to_address, builder)?;
+
+        // Log transfer event
+        logger.log(&Cis2Event::Transfer(TransferEvent {
+            token_id,
+            amount,
+            from,
+            to: to_address,
+        }))?;
+
+        // If the receiver is a contract: invoke the receive hook function.
+        if let Receiver::Contract(address, function) = to {
+            let parameter = OnReceivingCis2Params {
+                token_id,
+                amount,
+                from,
+                data,
+            };
+            host.invoke_contract(
+                &address,
+                &parameter,
+                function.as_entrypoint_name(),
+                Amount::zero(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Enable or disable addresses as operators of the sender address.
+/// Logs an `UpdateOperator` event.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Fails to log event.
+#[receive(
+    contract = "membership",
+    name = "updateOperator",
+    parameter = "UpdateOperatorParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_operator<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Parse the parameter.
+    let UpdateOperatorParams(params) = ctx.parameter_cursor().get()?;
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+    let (state, builder) = host.state_and_builder();
+    for param in params {
+        // Update the operator in the state.
+        match param.update {
+            OperatorUpdate::Add => state.add_operator(&sender, &param.operator, builder),
+            OperatorUpdate::Remove => state.remove_operator(&sender, &param.operator),
+        }
+
+        // Log the appropriate event
+        logger.log(&Cis2Event::<ContractTokenId, ContractTokenAmount>::UpdateOperator(
+            UpdateOperatorEvent {
+                owner:    sender,
+                operator: param.operator,
+                update:   param.update,
+            },
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Takes a list of queries. Each query is an owner address and some address to
+/// check as an operator of the owner address.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "membership",
+    name = "operatorOf",
+    parameter = "OperatorOfQueryParams",
+    return_value = "OperatorOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_operator_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<OperatorOfQueryResponse> {
+    // Parse the parameter.
+    let params: OperatorOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for address being an operator of owner.
+        let is_operator = host.state().is_operator(&query.address, &query.owner);
+        response.push(is_operator);
+    }
+    let result = OperatorOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenIDs used by this contract.
+type ContractBalanceOfQueryParams = BalanceOfQueryParams<ContractTokenId>;
+/// Response type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenAmounts used by this contract.
+type ContractBalanceOfQueryResponse = BalanceOfQueryResponse<ContractTokenAmount>;
+
+/// Get the balance of given token IDs and addresses.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "membership",
+    name = "balanceOf",
+    parameter = "ContractBalanceOfQueryParams",
+    return_value = "ContractBalanceOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ContractBalanceOfQueryResponse> {
+    // Parse the parameter.
+    let params: ContractBalanceOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for balance.
+        let amount = host.state().balance(&query.token_id, &query.address)?;
+        response.push(amount);
+    }
+    let result = ContractBalanceOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `tokenMetadata` specialized to the
+/// subset of TokenIDs used by this contract.
+type ContractTokenMetadataQueryParams = TokenMetadataQueryParams<ContractTokenId>;
+
+/// Get the token metadata URLs and checksums given a list of token IDs.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+#[receive(
+    contract = "membership",
+    name = "tokenMetadata",
+    parameter = "ContractTokenMetadataQueryParams",
+    return_value = "TokenMetadataQueryResponse",
+    error = "ContractError"
+)]
+fn contract_token_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<TokenMetadataQueryResponse> {
+    // Parse the parameter.
+    let params: ContractTokenMetadataQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for token_id in params.queries {
+        // Get the metadata URL of the token, failing if it does not exist.
+        let metadata_url = host.state().metadata_url(&token_id)?;
+        response.push(metadata_url);
+    }
+    let result = TokenMetadataQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Get the supported standards given a list of standard identifiers.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "membership",
+    name = "supports",
+    parameter = "SupportsQueryParams",
+    return_value = "SupportsQueryResponse",
+    error = "ContractError"
+)]
+fn contract_supports<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<SupportsQueryResponse> {
+    // Parse the parameter.
+    let params: SupportsQueryParams = ctx.parameter_cursor().get()?;
+
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for std_id in params.queries {
+        if SUPPORTS_STANDARDS.contains(&std_id.as_standard_identifier()) {
+            response.push(SupportResult::Support);
+        } else {
+            response.push(SupportResult::NoSupport);
+        }
+    }
+    let result = SupportsQueryResponse::from(response);
+    Ok(result)
+}
+
+// Tests
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0u8; 32]);
+    const MEMBER: AccountAddress = AccountAddress([1u8; 32]);
+    const MEMBER_ADDR: Address = Address::Account(MEMBER);
+    const FRIEND: AccountAddress = AccountAddress([2u8; 32]);
+    const SILVER: TierId = 1;
+    const GOLD: TierId = 2;
+    const PRICE: Amount = Amount::from_micro_ccd(5_000);
+    const DURATION: u64 = 3_600_000;
+
+    /// Test helper function which creates a context with the given sender and
+    /// slot time, where `OWNER` owns the contract instance.
+    fn ctx_at<'a>(sender: AccountAddress, slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_at(sender, slot_time);
+        ctx.set_owner(OWNER);
+        ctx
+    }
+
+    /// Test helper function which creates a host where `OWNER` has configured
+    /// the `SILVER` tier.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let state = State::empty(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter_bytes = to_bytes(&SetTierParams {
+            tier_id: SILVER,
+            tier:    Tier {
+                price:             PRICE,
+                duration:          Duration::from_millis(DURATION),
+                metadata_base_url: "https://some.example/silver".to_string(),
+            },
+        });
+        let mut ctx = ctx_at(OWNER, 0);
+        ctx.set_parameter(&parameter_bytes);
+        contract_set_tier(&ctx, &mut host).expect_report("Setting the tier should pass");
+        host
+    }
+
+    /// Test helper function which joins `SILVER` as `MEMBER`.
+    fn join(host: &mut TestHost<State<TestStateApi>>, logger: &mut TestLogger) {
+        let parameter_bytes = to_bytes(&SILVER);
+        let mut ctx = ctx_at(MEMBER, 0);
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(host.self_balance() + PRICE);
+        contract_join(&ctx, host, PRICE, logger).expect_report("Joining should pass");
+    }
+
+    /// Test helper function which renews the first membership at the given
+    /// time.
+    fn renew(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&TokenIdU32(0));
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(host.self_balance() + PRICE);
+        contract_renew(&ctx, host, PRICE, logger)
+    }
+
+    /// Test helper function which checks the membership of an account at the
+    /// given time.
+    fn is_member(
+        host: &TestHost<State<TestStateApi>>,
+        account: AccountAddress,
+        min_tier: TierId,
+        slot_time: u64,
+    ) -> bool {
+        let parameter_bytes = to_bytes(&IsMemberParams {
+            account,
+            min_tier,
+        });
+        let mut ctx = ctx_at(account, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        contract_is_member(&ctx, host).expect_report("Checking membership should pass")
+    }
+
+    /// Test joining mints a membership to the sender, pays the owner and logs
+    /// the metadata URL encoding the tier and expiry.
+    #[concordium_test]
+    fn test_join() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        join(&mut host, &mut logger);
+
+        let token_id = TokenIdU32(0);
+        let balance = host
+            .state()
+            .balance(&token_id, &MEMBER_ADDR)
+            .expect_report("Token is expected to exist");
+        claim_eq!(balance, 1.into(), "The member should own the membership");
+        claim_eq!(host.get_transfers(), [(OWNER, PRICE)], "The owner should be paid");
+        claim_eq!(
+            logger.logs[1],
+            to_bytes(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+                token_id,
+                metadata_url: MetadataUrl {
+                    url:  "https://some.example/silver?tier=1&expires=3600000".to_string(),
+                    hash: None,
+                },
+            })),
+            "Incorrect metadata URL"
+        );
+        claim!(is_member(&host, MEMBER, SILVER, DURATION - 1), "Member until it expires");
+        claim!(!is_member(&host, MEMBER, GOLD, 0), "Not a member of a higher tier");
+        claim!(!is_member(&host, MEMBER, SILVER, DURATION), "No longer a member once expired");
+    }
+
+    /// Test renewing before the expiry extends the membership, and that it can
+    /// only be renewed by its holder.
+    #[concordium_test]
+    fn test_renew() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        join(&mut host, &mut logger);
+
+        let result = renew(&mut host, FRIEND, 0, &mut logger);
+        claim_eq!(result, Err(ContractError::Custom(CustomContractError::NotHolder)));
+        renew(&mut host, MEMBER, DURATION - 1, &mut logger).expect_report("Renewing should pass");
+
+        claim_eq!(
+            host.state().all_tokens.get(&TokenIdU32(0)).map(|m| *m),
+            Some(Membership {
+                tier:    SILVER,
+                expires: Timestamp::from_timestamp_millis(2 * DURATION),
+            })
+        );
+        claim!(is_member(&host, MEMBER, SILVER, 2 * DURATION - 1), "The membership is extended");
+        claim_eq!(host.get_transfers(), [(OWNER, PRICE), (OWNER, PRICE)]);
+    }
+
+    /// Test expired memberships cannot be renewed, and that only the owner can
+    /// configure tiers.
+    #[concordium_test]
+    fn test_renew_expired() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        join(&mut host, &mut logger);
+
+        let result = renew(&mut host, MEMBER, DURATION, &mut logger);
+        claim_eq!(result, Err(ContractError::Custom(CustomContractError::Expired)));
+
+        let parameter_bytes = to_bytes(&SetTierParams {
+            tier_id: GOLD,
+            tier:    Tier {
+                price:             PRICE,
+                duration:          Duration::from_millis(DURATION),
+                metadata_base_url: "https://some.example/gold".to_string(),
+            },
+        });
+        let mut ctx = ctx_at(MEMBER, 0);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_set_tier(&ctx, &mut host);
+        claim_eq!(result, Err(ContractError::Custom(CustomContractError::Unauthorized)));
+    }
+}
@@ -0,0 +1,333 @@
+//! # Merkle airdrop
+//!
+//! An airdrop of CCD to a list of accounts, each entitled to its own amount,
+//! where the contract only stores the root of a Merkle tree of the list rather
+//! than every recipient. This keeps the cost of setting up the airdrop
+//! independent of the number of recipients.
+//!
+//! The leaves of the tree are the serialized pairs `(account, amount)`, and the
+//! tree is built as described in the `merkle` module of `concordium-std`. The
+//! tree is built off-chain, and every recipient is given the proof for their
+//! leaf, which they present when they `claim` their amount before the
+//! deadline. Every account can only claim once.
+//!
+//! # Operations
+//! The contract allows for
+//!  - anyone to `fund` the airdrop;
+//!  - recipients to `claim` their amount with a proof, until the deadline;
+//!  - the owner to `withdrawRemaining` CCD after the deadline.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The root of the Merkle tree of the recipients and their amounts.
+    root:     HashSha2256,
+    /// The time until which recipients can claim.
+    deadline: Timestamp,
+    /// The accounts which have claimed.
+    claimed:  StateSet<AccountAddress, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The root of the Merkle tree of the recipients and their amounts.
+    pub root:     HashSha2256,
+    /// The time until which recipients can claim.
+    pub deadline: Timestamp,
+}
+
+/// The parameter type for the contract function `claim`.
+#[derive(Serialize, SchemaType)]
+pub struct ClaimParams {
+    /// The amount the sender is entitled to.
+    pub amount: Amount,
+    /// The proof that the sender and the amount is a leaf of the tree.
+    pub proof:  Vec<HashSha2256>,
+}
+
+/// The events logged by the contract.
+#[concordium_event]
+#[derive(Debug)]
+enum AirdropEvent {
+    /// An account claimed its amount.
+    Claimed {
+        /// The account which claimed.
+        account: AccountAddress,
+        /// The amount it received.
+        amount:  Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseParams)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract claims tokens.
+    OnlyAccount,
+    /// Raised when claiming after the deadline.
+    DeadlinePassed,
+    /// Raised when withdrawing before the deadline.
+    DeadlineNotPassed,
+    /// Raised when an account claims again.
+    AlreadyClaimed,
+    /// Raised when the proof does not show that the sender is entitled to the
+    /// amount.
+    InvalidProof,
+    /// Raised when the contract has too little CCD to pay the claim.
+    InsufficientFunds,
+    /// Raised when someone else than the owner withdraws.
+    Unauthorized,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// The leaf of the Merkle tree for the account and amount.
+pub fn leaf(account: AccountAddress, amount: Amount) -> Vec<u8> { to_bytes(&(account, amount)) }
+
+/// Init function that creates an airdrop for the recipients of the Merkle tree
+/// with the given root.
+#[init(contract = "merkle_airdrop", parameter = "InitParams", event = "AirdropEvent")]
+fn airdrop_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    Ok(State {
+        root:     params.root,
+        deadline: params.deadline,
+        claimed:  state_builder.new_set(),
+    })
+}
+
+/// Fund the airdrop with the CCD sent along.
+#[receive(contract = "merkle_airdrop", name = "fund", payable)]
+fn airdrop_fund<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Claim the amount the sender is entitled to, by presenting the proof that
+/// the sender and the amount is a leaf of the tree.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The deadline has passed.
+/// - The sender has already claimed.
+/// - The proof is invalid.
+/// - The contract has too little CCD to pay the amount.
+/// - It fails to log the event.
+#[receive(
+    contract = "merkle_airdrop",
+    name = "claim",
+    parameter = "ClaimParams",
+    error = "ContractError",
+    crypto_primitives,
+    enable_logger,
+    mutable
+)]
+fn airdrop_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let params: ClaimParams = ctx.parameter_cursor().get()?;
+    let account = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account) => account,
+    };
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() <= state.deadline, ContractError::DeadlinePassed);
+    ensure!(
+        merkle::verify_proof(
+            crypto_primitives,
+            &state.root,
+            &leaf(account, params.amount),
+            &params.proof
+        ),
+        ContractError::InvalidProof
+    );
+    ensure!(state.claimed.insert(account), ContractError::AlreadyClaimed);
+
+    host.invoke_transfer(&account, params.amount).map_err(|_| ContractError::InsufficientFunds)?;
+    logger.log_event(&AirdropEvent::Claimed {
+        account,
+        amount: params.amount,
+    })?;
+    Ok(())
+}
+
+/// Withdraw the CCD which was not claimed, after the deadline.
+///
+/// It rejects if:
+/// - The sender is not the owner.
+/// - The deadline has not passed.
+#[receive(contract = "merkle_airdrop", name = "withdrawRemaining", error = "ContractError")]
+fn airdrop_withdraw_remaining<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let owner = ctx.owner();
+    ensure!(ctx.sender().matches_account(&owner), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() > host.state().deadline, ContractError::DeadlineNotPassed);
+    // The transfer always succeeds, since the owner is the sender.
+    host.invoke_transfer(&owner, host.self_balance()).unwrap_abort();
+    Ok(())
+}
+
+/// View whether the account has claimed.
+#[receive(
+    contract = "merkle_airdrop",
+    name = "claimed",
+    parameter = "AccountAddress",
+    return_value = "bool"
+)]
+fn airdrop_claimed<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<bool> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().claimed.contains(&account))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const DEADLINE: u64 = 100;
+
+    /// The recipients of the airdrop and their amounts.
+    fn recipients() -> Vec<(AccountAddress, Amount)> {
+        (1..=5u8)
+            .map(|i| (AccountAddress([i; 32]), Amount::from_micro_ccd(u64::from(i) * 100)))
+            .collect()
+    }
+
+    fn leaves() -> Vec<Vec<u8>> {
+        recipients().into_iter().map(|(account, amount)| leaf(account, amount)).collect()
+    }
+
+    fn new_host(balance: Amount) -> TestHost<State<TestStateApi>> {
+        let root = merkle::compute_root(&TestCryptoPrimitives::new(), &leaves())
+            .expect("There are leaves");
+        let parameter_bytes = to_bytes(&InitParams {
+            root,
+            deadline: Timestamp::from_timestamp_millis(DEADLINE),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            airdrop_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(balance);
+        host
+    }
+
+    fn claim(
+        host: &mut TestHost<State<TestStateApi>>,
+        index: usize,
+        amount: Amount,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let proof = merkle::compute_proof(&TestCryptoPrimitives::new(), &leaves(), index)
+            .expect("Index is in bounds");
+        let parameter_bytes = to_bytes(&ClaimParams {
+            amount,
+            proof,
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(recipients()[index].0));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        airdrop_claim(&ctx, host, &mut TestLogger::init(), &TestCryptoPrimitives::new())
+    }
+
+    #[concordium_test]
+    /// Test that every recipient can claim their amount once, with their proof.
+    fn test_claim() {
+        let mut host = new_host(Amount::from_micro_ccd(1_500));
+        for (index, (account, amount)) in recipients().into_iter().enumerate() {
+            claim(&mut host, index, amount, DEADLINE).expect_report("Claiming should pass");
+            claim_eq!(host.state().claimed.contains(&account), true);
+            let res = claim(&mut host, index, amount, DEADLINE);
+            claim_eq!(res, Err(ContractError::AlreadyClaimed), "Claiming twice should fail");
+        }
+        claim_eq!(host.get_transfers(), recipients());
+    }
+
+    #[concordium_test]
+    /// Test that claiming a different amount, or with the proof of another
+    /// recipient, fails.
+    fn test_claim_invalid_proof() {
+        let mut host = new_host(Amount::from_micro_ccd(1_500));
+        let (_, amount) = recipients()[0];
+        let res = claim(&mut host, 0, amount + Amount::from_micro_ccd(1), DEADLINE);
+        claim_eq!(res, Err(ContractError::InvalidProof), "A larger amount should fail");
+
+        let proof = merkle::compute_proof(&TestCryptoPrimitives::new(), &leaves(), 1)
+            .expect("Index is in bounds");
+        let parameter_bytes = to_bytes(&ClaimParams {
+            amount,
+            proof,
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(recipients()[0].0));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(DEADLINE));
+        ctx.set_parameter(&parameter_bytes);
+        let res =
+            airdrop_claim(&ctx, &mut host, &mut TestLogger::init(), &TestCryptoPrimitives::new());
+        claim_eq!(res, Err(ContractError::InvalidProof), "Another proof should fail");
+        claim!(host.get_transfers().is_empty());
+    }
+
+    #[concordium_test]
+    /// Test that claims fail after the deadline, or when the airdrop lacks
+    /// funds, and that the owner can only withdraw after the deadline.
+    fn test_deadline_and_withdraw() {
+        let mut host = new_host(Amount::from_micro_ccd(150));
+        let (_, amount) = recipients()[1];
+        let res = claim(&mut host, 1, amount, DEADLINE);
+        claim_eq!(res, Err(ContractError::InsufficientFunds));
+        let (_, amount) = recipients()[0];
+        let res = claim(&mut host, 0, amount, DEADLINE + 1);
+        claim_eq!(res, Err(ContractError::DeadlinePassed), "Claiming late should fail");
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(OWNER);
+        ctx.set_sender(Address::Account(OWNER));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(DEADLINE));
+        let res = airdrop_withdraw_remaining(&ctx, &host);
+        claim_eq!(res, Err(ContractError::DeadlineNotPassed));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(DEADLINE + 1));
+        airdrop_withdraw_remaining(&ctx, &host).expect_report("Withdrawing should pass");
+        claim_eq!(host.get_transfers(), [(OWNER, Amount::from_micro_ccd(150))]);
+    }
+}
@@ -0,0 +1,591 @@
+//! # Crowdfunding with funds released per milestone
+//!
+//! A variant of the [`crowdfunding`] example, where the funds are not handed
+//! to the owner of the contract instance, i.e. the creator, in one go. Instead
+//! the campaign is split into milestones, and the funds are released in
+//! tranches when the backers approve each milestone.
+//!
+//! Until the deadline, any account can `contribute` CCD to the campaign. If
+//! the goal is not reached by the deadline, the backers `exit` with their full
+//! contributions. Otherwise the milestones follow, in order:
+//!
+//! 1. When the creator has completed a milestone, they open a vote on it with
+//!    `openVote`.
+//! 2. Until the vote ends, every backer can `vote` for or against approving the
+//!    milestone once, weighted by their contributions.
+//! 3. When the vote has ended, anyone can `closeVote`. The milestone is
+//!    approved if the contributions voting for it exceed those voting against
+//!    it, and the tranche of the milestone is then released to the creator. The
+//!    tranches are given in basis points of the total raised, and the last
+//!    tranche releases whatever is left.
+//!
+//! If a milestone is not approved the campaign fails, and every backer can
+//! `exit` with their share of the funds which have not been released,
+//! proportional to their contributions.
+//!
+//! [`crowdfunding`]: ../crowdfunding
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// The status of the campaign.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The campaign is raising funds, or working through the milestones.
+    Running,
+    /// A milestone was not approved, and the backers can exit with their share
+    /// of the funds which were not released at that point.
+    Failed {
+        remaining: Amount,
+    },
+}
+
+/// A vote on approving a milestone.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vote {
+    /// When the vote ends.
+    pub ends:       Timestamp,
+    /// The contributions voting for approving the milestone.
+    pub approvals:  Amount,
+    /// The contributions voting against approving the milestone.
+    pub rejections: Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The amount the campaign aims to raise.
+    goal:           Amount,
+    /// The last time a contribution can be made.
+    deadline:       Timestamp,
+    /// The tranche of every milestone, in basis points of the total raised.
+    tranches:       Vec<BasisPoints>,
+    /// How long a vote lasts.
+    vote_duration:  Duration,
+    /// The total amount contributed.
+    total_raised:   Amount,
+    /// The total amount released to the creator.
+    released:       Amount,
+    /// The total amount contributed by every backer. Entries are removed when
+    /// backers exit.
+    contributions:  StateMap<AccountAddress, Amount, S>,
+    /// The index of the next milestone to be approved.
+    next_milestone: u32,
+    /// The vote on the next milestone, if one is open.
+    vote:           Option<Vote>,
+    /// The backers who voted on the next milestone.
+    voters:         StateSet<(u32, AccountAddress), S>,
+    /// The status of the campaign.
+    status:         Status,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The amount the campaign aims to raise.
+    goal:          Amount,
+    /// The last time a contribution can be made.
+    deadline:      Timestamp,
+    /// The tranche of every milestone, in basis points of the total raised.
+    tranches:      Vec<BasisPoints>,
+    /// How long a vote lasts.
+    vote_duration: Duration,
+}
+
+/// The `return_value` type of the `view` function.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    goal:           Amount,
+    deadline:       Timestamp,
+    tranches:       Vec<BasisPoints>,
+    total_raised:   Amount,
+    released:       Amount,
+    next_milestone: u32,
+    vote:           Option<Vote>,
+    status:         Status,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a goal of zero.
+    ZeroGoal,
+    /// Raised when the tranches are empty or do not add up to 10000 basis
+    /// points.
+    InvalidTranches,
+    /// Raised when a contract contributes, votes or exits.
+    OnlyAccount,
+    /// Raised when contributing without sending any CCD.
+    ZeroContribution,
+    /// Raised when contributing after the deadline.
+    CampaignEnded,
+    /// Raised when opening a vote or exiting before the deadline.
+    CampaignActive,
+    /// Raised when someone else than the owner opens a vote.
+    Unauthorized,
+    /// Raised when opening a vote while the goal was not reached.
+    GoalNotReached,
+    /// Raised when opening a vote after the campaign failed or all milestones
+    /// were approved.
+    NotRunning,
+    /// Raised when opening a vote while one is open.
+    VoteOpen,
+    /// Raised when voting or closing while no vote is open.
+    NoVoteOpen,
+    /// Raised when voting after the vote ended.
+    VoteEnded,
+    /// Raised when closing a vote before it ended.
+    VoteNotEnded,
+    /// Raised when voting without contributions.
+    NotBacker,
+    /// Raised when voting twice on a milestone.
+    AlreadyVoted,
+    /// Raised when exiting while the goal was reached and the campaign has not
+    /// failed.
+    CannotExit,
+    /// Raised when exiting without contributions, e.g., because the backer
+    /// exited already.
+    NothingToReclaim,
+    /// Raised when the end of the vote cannot be represented.
+    Overflow,
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+impl<S: HasStateApi> State<S> {
+    /// Whether the total raised reached the goal.
+    fn goal_reached(&self) -> bool { self.total_raised >= self.goal }
+
+    /// Whether all milestones were approved.
+    fn completed(&self) -> bool { self.next_milestone as usize == self.tranches.len() }
+
+    /// The amount released when the next milestone is approved.
+    fn next_tranche(&self) -> Amount {
+        if self.next_milestone as usize + 1 == self.tranches.len() {
+            // The last tranche releases whatever is left.
+            return self.total_raised - self.released;
+        }
+        self.tranches[self.next_milestone as usize].of(self.total_raised)
+    }
+}
+
+/// Init function that starts a new campaign.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The goal is zero.
+/// - The tranches are empty or do not add up to 10000 basis points.
+#[init(contract = "milestone_crowdfunding", parameter = "InitParameter")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.goal > Amount::zero(), ContractError::ZeroGoal.into());
+    let total = parameter
+        .tranches
+        .iter()
+        .try_fold(BasisPoints::default(), |total, bps| total.checked_add(*bps));
+    ensure!(
+        !parameter.tranches.is_empty() && total == Some(BasisPoints::MAX),
+        ContractError::InvalidTranches.into()
+    );
+    Ok(State {
+        goal:           parameter.goal,
+        deadline:       parameter.deadline,
+        tranches:       parameter.tranches,
+        vote_duration:  parameter.vote_duration,
+        total_raised:   Amount::zero(),
+        released:       Amount::zero(),
+        contributions:  state_builder.new_map(),
+        next_milestone: 0,
+        vote:           None,
+        voters:         state_builder.new_set(),
+        status:         Status::Running,
+    })
+}
+
+/// Contribute the CCD sent along to the campaign. An account can contribute
+/// several times.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The deadline has passed.
+/// - No CCD is sent.
+#[receive(
+    contract = "milestone_crowdfunding",
+    name = "contribute",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn contract_contribute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let backer = sender_account(ctx)?;
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() <= state.deadline, ContractError::CampaignEnded);
+    ensure!(amount > Amount::zero(), ContractError::ZeroContribution);
+
+    *state.contributions.entry(backer).or_insert(Amount::zero()) += amount;
+    state.total_raised += amount;
+    Ok(())
+}
+
+/// Open a vote on approving the next milestone. Only the owner can open votes.
+///
+/// It rejects if:
+/// - The sender is not the owner.
+/// - The deadline has not passed.
+/// - The goal was not reached.
+/// - The campaign failed, or all milestones were approved.
+/// - A vote is already open.
+#[receive(contract = "milestone_crowdfunding", name = "openVote", error = "ContractError", mutable)]
+fn contract_open_vote<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    ensure!(now > state.deadline, ContractError::CampaignActive);
+    ensure!(state.goal_reached(), ContractError::GoalNotReached);
+    ensure!(state.status == Status::Running && !state.completed(), ContractError::NotRunning);
+    ensure!(state.vote.is_none(), ContractError::VoteOpen);
+    state.vote = Some(Vote {
+        ends:       now.checked_add(state.vote_duration).ok_or(ContractError::Overflow)?,
+        approvals:  Amount::zero(),
+        rejections: Amount::zero(),
+    });
+    Ok(())
+}
+
+/// Vote for (`true`) or against (`false`) approving the milestone of the open
+/// vote, weighted by the contributions of the sender.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No vote is open, or the vote has ended.
+/// - The sender has no contributions.
+/// - The sender already voted on the milestone.
+#[receive(
+    contract = "milestone_crowdfunding",
+    name = "vote",
+    parameter = "bool",
+    error = "ContractError",
+    mutable
+)]
+fn contract_vote<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let approve: bool = ctx.parameter_cursor().get()?;
+    let backer = sender_account(ctx)?;
+    let state = host.state_mut();
+    let mut vote = state.vote.ok_or(ContractError::NoVoteOpen)?;
+    ensure!(ctx.metadata().slot_time() < vote.ends, ContractError::VoteEnded);
+    let weight = state.contributions.get(&backer).map(|c| *c).ok_or(ContractError::NotBacker)?;
+    ensure!(state.voters.insert((state.next_milestone, backer)), ContractError::AlreadyVoted);
+
+    if approve {
+        vote.approvals += weight;
+    } else {
+        vote.rejections += weight;
+    }
+    state.vote = Some(vote);
+    Ok(())
+}
+
+/// Close the open vote after it ended. If the milestone was approved, its
+/// tranche is released to the owner, and otherwise the campaign fails.
+///
+/// It rejects if:
+/// - No vote is open.
+/// - The vote has not ended.
+#[receive(
+    contract = "milestone_crowdfunding",
+    name = "closeVote",
+    error = "ContractError",
+    mutable
+)]
+fn contract_close_vote<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    let vote = state.vote.ok_or(ContractError::NoVoteOpen)?;
+    ensure!(ctx.metadata().slot_time() >= vote.ends, ContractError::VoteNotEnded);
+    state.vote = None;
+
+    if vote.approvals > vote.rejections {
+        let tranche = state.next_tranche();
+        state.released += tranche;
+        state.next_milestone += 1;
+        // The transfer always succeeds, since the owner is an account and the
+        // contract holds the funds which were not released.
+        host.invoke_transfer(&ctx.owner(), tranche).unwrap_abort();
+    } else {
+        state.status = Status::Failed {
+            remaining: state.total_raised - state.released,
+        };
+    }
+    Ok(())
+}
+
+/// Exit the campaign, reclaiming the full contributions of the sender if the
+/// goal was not reached, or their share of the funds which were not released
+/// if the campaign failed.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The deadline has not passed.
+/// - The goal was reached, and the campaign has not failed.
+/// - The sender has no contributions to reclaim.
+#[receive(contract = "milestone_crowdfunding", name = "exit", error = "ContractError", mutable)]
+fn contract_exit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let backer = sender_account(ctx)?;
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() > state.deadline, ContractError::CampaignActive);
+    let remaining = match state.status {
+        Status::Failed {
+            remaining,
+        } => remaining,
+        Status::Running if !state.goal_reached() => state.total_raised,
+        Status::Running => bail!(ContractError::CannotExit),
+    };
+    let contribution =
+        state.contributions.remove_and_get(&backer).ok_or(ContractError::NothingToReclaim)?;
+    // The backer contributed, so the total raised is positive. Since the remaining
+    // funds are at most the total raised, the share is at most the contribution,
+    // and unwrapping is safe.
+    let share =
+        contribution.mul_ratio(remaining.micro_ccd, state.total_raised.micro_ccd).unwrap_abort();
+
+    // The transfer always succeeds, since the backer is the sender and the
+    // contract holds the funds which were not released.
+    host.invoke_transfer(&backer, share).unwrap_abort();
+    Ok(())
+}
+
+/// View function that returns the total contributed by the given account.
+#[receive(
+    contract = "milestone_crowdfunding",
+    name = "contributionOf",
+    parameter = "AccountAddress",
+    return_value = "Amount"
+)]
+fn contract_contribution_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let backer: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().contributions.get(&backer).map(|c| *c).unwrap_or_else(Amount::zero))
+}
+
+/// View function that returns the general information about the campaign.
+#[receive(contract = "milestone_crowdfunding", name = "view", return_value = "ViewState")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        goal:           state.goal,
+        deadline:       state.deadline,
+        tranches:       state.tranches.clone(),
+        total_raised:   state.total_raised,
+        released:       state.released,
+        next_milestone: state.next_milestone,
+        vote:           state.vote,
+        status:         state.status,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const DEADLINE: u64 = 100;
+    const VOTE_DURATION: u64 = 50;
+    const GOAL: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    fn tranches(tranches: &[u16]) -> Vec<BasisPoints> {
+        tranches
+            .iter()
+            .map(|bps| BasisPoints::new(*bps).expect_report("The tranche should be valid"))
+            .collect()
+    }
+
+    /// Create a campaign with three milestones releasing 20%, 30% and 50%.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            goal:          GOAL,
+            deadline:      Timestamp::from_timestamp_millis(DEADLINE),
+            tranches:      tranches(&[2_000, 3_000, 5_000]),
+            vote_duration: Duration::from_millis(VOTE_DURATION),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(sender: AccountAddress, slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_at(sender, slot_time);
+        ctx.set_owner(OWNER);
+        ctx
+    }
+
+    fn contribute(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        micro_ccd: u64,
+    ) {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_contribute(&ctx_at(sender, 0), host, amount)
+            .expect_report("Contributing should pass");
+    }
+
+    fn vote(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+        approve: bool,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&approve);
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        contract_vote(&ctx, host)
+    }
+
+    /// Open a vote at the given time, let `ALICE` and `BOB` vote, and close
+    /// it.
+    fn run_vote(
+        host: &mut TestHost<State<TestStateApi>>,
+        slot_time: u64,
+        alice_approves: bool,
+        bob_approves: bool,
+    ) {
+        contract_open_vote(&ctx_at(OWNER, slot_time), host).expect_report("Opening should pass");
+        vote(host, ALICE, slot_time, alice_approves).expect_report("Voting should pass");
+        vote(host, BOB, slot_time, bob_approves).expect_report("Voting should pass");
+        contract_close_vote(&ctx_at(OWNER, slot_time + VOTE_DURATION), host)
+            .expect_report("Closing should pass");
+    }
+
+    #[concordium_test]
+    /// Test that the tranches must add up to 10000 basis points.
+    fn test_init_invalid_tranches() {
+        let parameter_bytes = to_bytes(&InitParameter {
+            goal:          GOAL,
+            deadline:      Timestamp::from_timestamp_millis(DEADLINE),
+            tranches:      tranches(&[2_000, 3_000]),
+            vote_duration: Duration::from_millis(VOTE_DURATION),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let res = contract_init(&ctx, &mut TestStateBuilder::new());
+        claim_eq!(res.map(|_| ()), Err(ContractError::InvalidTranches.into()));
+    }
+
+    #[concordium_test]
+    /// Test that approved milestones release their tranches, with the last
+    /// releasing whatever is left.
+    fn test_milestones_approved() {
+        let mut host = new_host();
+        contribute(&mut host, ALICE, 333);
+        contribute(&mut host, BOB, 667);
+
+        let res = contract_open_vote(&ctx_at(OWNER, DEADLINE), &mut host);
+        claim_eq!(res, Err(ContractError::CampaignActive), "Voting starts after the deadline");
+        let start = DEADLINE + 1;
+        run_vote(&mut host, start, false, true);
+        claim_eq!(vote(&mut host, ALICE, start, true), Err(ContractError::NoVoteOpen));
+        run_vote(&mut host, start + VOTE_DURATION, true, true);
+        run_vote(&mut host, start + 2 * VOTE_DURATION, false, true);
+
+        claim_eq!(host.get_transfers(), [
+            (OWNER, Amount::from_micro_ccd(200)),
+            (OWNER, Amount::from_micro_ccd(300)),
+            (OWNER, Amount::from_micro_ccd(500))
+        ]);
+        let res = contract_open_vote(&ctx_at(OWNER, start + 3 * VOTE_DURATION), &mut host);
+        claim_eq!(res, Err(ContractError::NotRunning), "All milestones were approved");
+        let res = contract_exit(&ctx_at(ALICE, start + 3 * VOTE_DURATION), &mut host);
+        claim_eq!(res, Err(ContractError::CannotExit));
+    }
+
+    #[concordium_test]
+    /// Test that backers vote once, and exit with their share of what is left
+    /// when a milestone is rejected.
+    fn test_milestone_rejected() {
+        let mut host = new_host();
+        contribute(&mut host, ALICE, 250);
+        contribute(&mut host, BOB, 750);
+        let start = DEADLINE + 1;
+        run_vote(&mut host, start, false, true);
+
+        contract_open_vote(&ctx_at(OWNER, start + VOTE_DURATION), &mut host)
+            .expect_report("Opening should pass");
+        vote(&mut host, ALICE, start + VOTE_DURATION, true).expect_report("Voting should pass");
+        let res = vote(&mut host, ALICE, start + VOTE_DURATION, true);
+        claim_eq!(res, Err(ContractError::AlreadyVoted));
+        vote(&mut host, BOB, start + VOTE_DURATION, false).expect_report("Voting should pass");
+        contract_close_vote(&ctx_at(ALICE, start + 2 * VOTE_DURATION), &mut host)
+            .expect_report("Closing should pass");
+        claim_eq!(host.state().status, Status::Failed {
+            remaining: Amount::from_micro_ccd(800),
+        });
+
+        for backer in [ALICE, BOB] {
+            contract_exit(&ctx_at(backer, start + 2 * VOTE_DURATION), &mut host)
+                .expect_report("Exiting should pass");
+        }
+        claim_eq!(host.get_transfers(), [
+            (OWNER, Amount::from_micro_ccd(200)),
+            (ALICE, Amount::from_micro_ccd(200)),
+            (BOB, Amount::from_micro_ccd(600))
+        ]);
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+
+    #[concordium_test]
+    /// Test that backers exit with their full contributions when the goal is
+    /// missed.
+    fn test_goal_missed() {
+        let mut host = new_host();
+        contribute(&mut host, ALICE, 400);
+        let res = contract_exit(&ctx_at(ALICE, DEADLINE), &mut host);
+        claim_eq!(res, Err(ContractError::CampaignActive));
+        let res = contract_open_vote(&ctx_at(OWNER, DEADLINE + 1), &mut host);
+        claim_eq!(res, Err(ContractError::GoalNotReached));
+        contract_exit(&ctx_at(ALICE, DEADLINE + 1), &mut host).expect_report("Exiting should pass");
+        claim_eq!(host.get_transfers(), [(ALICE, Amount::from_micro_ccd(400))]);
+    }
+}
@@ -0,0 +1,599 @@
+//! An M-of-N multisig wallet smart contract example.
+//!
+//! # Description
+//! The contract holds CCD on behalf of a set of owner accounts. Anyone can
+//! deposit CCD, but spending requires the approval of a threshold of the
+//! owners.
+//!
+//! An owner `propose`s an action, which counts as their approval, and the
+//! other owners `approve` it. As soon as the number of approvals from current
+//! owners reaches the threshold, the action is executed and the proposal is
+//! removed. An action is one of:
+//! - transferring CCD to an account,
+//! - invoking a contract entrypoint with a parameter and an amount of CCD,
+//! - adding an owner, removing an owner or changing the threshold, which allows
+//!   the owners to rotate themselves.
+//!
+//! Proposals expire after the proposal time-to-live set at initialization,
+//! after which they can no longer be approved.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::{collections::BTreeSet, *};
+
+/// Identifier of a proposal.
+pub type ProposalId = u64;
+
+/// An action the owners can propose.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Transfer CCD to an account.
+    Transfer {
+        to:     AccountAddress,
+        amount: Amount,
+    },
+    /// Invoke an entrypoint of a contract with a serialized parameter and an
+    /// amount of CCD.
+    Call {
+        contract:   ContractAddress,
+        entrypoint: OwnedEntrypointName,
+        #[concordium(size_length = 2)]
+        parameter:  Vec<u8>,
+        amount:     Amount,
+    },
+    /// Add an account to the owners.
+    AddOwner(AccountAddress),
+    /// Remove an account from the owners.
+    RemoveOwner(AccountAddress),
+    /// Change the number of approvals needed to execute a proposal.
+    ChangeThreshold(u8),
+}
+
+/// A pending proposal.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    /// The proposed action.
+    action:     Action,
+    /// The time after which the proposal can no longer be approved.
+    expires_at: Timestamp,
+    /// The owners who approved the proposal.
+    #[concordium(size_length = 1)]
+    approvals:  BTreeSet<AccountAddress>,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The accounts allowed to propose and approve.
+    owners:           BTreeSet<AccountAddress>,
+    /// The number of approvals needed to execute a proposal.
+    threshold:        u8,
+    /// How long a proposal can be approved after it was proposed.
+    proposal_ttl:     Duration,
+    /// The identifier of the next proposal.
+    next_proposal_id: ProposalId,
+    /// The pending proposals.
+    proposals:        StateMap<ProposalId, Proposal, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The accounts allowed to propose and approve.
+    #[concordium(size_length = 1)]
+    owners:       BTreeSet<AccountAddress>,
+    /// The number of approvals needed to execute a proposal.
+    threshold:    u8,
+    /// How long a proposal can be approved after it was proposed.
+    proposal_ttl: Duration,
+}
+
+/// The `return_value` type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The accounts allowed to propose and approve.
+    #[concordium(size_length = 1)]
+    owners:       BTreeSet<AccountAddress>,
+    /// The number of approvals needed to execute a proposal.
+    threshold:    u8,
+    /// How long a proposal can be approved after it was proposed.
+    proposal_ttl: Duration,
+    /// The pending proposals.
+    proposals:    Vec<(ProposalId, Proposal)>,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum MultisigEvent {
+    /// An owner proposed an action.
+    Proposed {
+        id:       ProposalId,
+        proposer: AccountAddress,
+    },
+    /// An owner approved a proposal.
+    Approved {
+        id:    ProposalId,
+        owner: AccountAddress,
+    },
+    /// A proposal reached the threshold and its action was executed.
+    Executed {
+        id: ProposalId,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when the sender is not an owner.
+    NotOwner,
+    /// Raised when the threshold is zero or larger than the number of owners.
+    InvalidThreshold,
+    /// Raised when adding an existing owner or removing an unknown owner.
+    InvalidOwner,
+    /// Raised when the proposal does not exist.
+    UnknownProposal,
+    /// Raised when approving an expired proposal.
+    ProposalExpired,
+    /// Raised when an owner approves the same proposal twice.
+    AlreadyApproved,
+    /// Raised when the contract does not hold enough CCD for a transfer.
+    InsufficientFunds,
+    /// Raised when transferring CCD to an account that does not exist.
+    MissingAccount,
+    /// Raised when invoking a contract failed.
+    InvokeContractError,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Mapping errors related to transfers to ContractError.
+impl From<TransferError> for ContractError {
+    fn from(te: TransferError) -> Self {
+        match te {
+            TransferError::AmountTooLarge => Self::InsufficientFunds,
+            TransferError::MissingAccount => Self::MissingAccount,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Ensure the threshold is reachable by the owners.
+fn ensure_valid_threshold(owners: &BTreeSet<AccountAddress>, threshold: u8) -> ContractResult<()> {
+    ensure!(
+        threshold > 0 && usize::from(threshold) <= owners.len(),
+        ContractError::InvalidThreshold
+    );
+    Ok(())
+}
+
+/// Get the sender if it is an owner.
+fn sender_owner<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+) -> ContractResult<AccountAddress> {
+    match ctx.sender() {
+        Address::Account(account) if state.owners.contains(&account) => Ok(account),
+        _ => bail!(ContractError::NotOwner),
+    }
+}
+
+/// Execute the proposal if it is approved by at least the threshold of current
+/// owners. Returns whether the proposal was executed.
+fn execute_if_approved<S: HasStateApi>(
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    id: ProposalId,
+    logger: &mut impl HasLogger,
+) -> ContractResult<bool> {
+    let state = host.state_mut();
+    let action = {
+        let proposal = state.proposals.get(&id).ok_or(ContractError::UnknownProposal)?;
+        // Approvals of removed owners do not count.
+        let approvals = proposal.approvals.intersection(&state.owners).count();
+        if approvals < usize::from(state.threshold) {
+            return Ok(false);
+        }
+        proposal.action.clone()
+    };
+    // Remove the proposal before executing it, such that a contract invoked by
+    // the action cannot execute it again.
+    state.proposals.remove(&id);
+
+    match action {
+        Action::Transfer {
+            to,
+            amount,
+        } => host.invoke_transfer(&to, amount)?,
+        Action::Call {
+            contract,
+            entrypoint,
+            parameter,
+            amount,
+        } => {
+            host.invoke_contract_raw(
+                &contract,
+                Parameter(&parameter),
+                entrypoint.as_entrypoint_name(),
+                amount,
+            )?;
+        }
+        Action::AddOwner(owner) => {
+            ensure!(state.owners.insert(owner), ContractError::InvalidOwner);
+        }
+        Action::RemoveOwner(owner) => {
+            ensure!(state.owners.remove(&owner), ContractError::InvalidOwner);
+            ensure_valid_threshold(&state.owners, state.threshold)?;
+        }
+        Action::ChangeThreshold(threshold) => {
+            ensure_valid_threshold(&state.owners, threshold)?;
+            state.threshold = threshold;
+        }
+    }
+    logger.log(&MultisigEvent::Executed {
+        id,
+    })?;
+    Ok(true)
+}
+
+/// Initialize the wallet with the owners, the threshold and the proposal
+/// time-to-live. CCD sent along is deposited.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The threshold is zero or larger than the number of owners.
+#[init(contract = "multisig", parameter = "InitParams", event = "MultisigEvent", payable)]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+    _amount: Amount,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    ensure_valid_threshold(&params.owners, params.threshold)?;
+    Ok(State {
+        owners:           params.owners,
+        threshold:        params.threshold,
+        proposal_ttl:     params.proposal_ttl,
+        next_proposal_id: 0,
+        proposals:        state_builder.new_map(),
+    })
+}
+
+/// Deposit CCD. Anyone can deposit.
+#[receive(contract = "multisig", name = "deposit", payable)]
+fn contract_deposit<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Propose an action, counting as the approval of the proposer. The action is
+/// executed right away if the threshold is one. Returns the ID of the
+/// proposal.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not an owner.
+/// - The action is executed right away and fails.
+/// - It fails to log the events.
+#[receive(
+    contract = "multisig",
+    name = "propose",
+    parameter = "Action",
+    return_value = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_propose<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ProposalId> {
+    let action: Action = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let proposer = sender_owner(ctx, state)?;
+    let expires_at = ctx
+        .metadata()
+        .slot_time()
+        .checked_add(state.proposal_ttl)
+        .unwrap_or_else(|| Timestamp::from_timestamp_millis(u64::MAX));
+
+    let id = state.next_proposal_id;
+    state.next_proposal_id += 1;
+    let mut approvals = BTreeSet::new();
+    approvals.insert(proposer);
+    state.proposals.insert(id, Proposal {
+        action,
+        expires_at,
+        approvals,
+    });
+
+    logger.log(&MultisigEvent::Proposed {
+        id,
+        proposer,
+    })?;
+    execute_if_approved(host, id, logger)?;
+    Ok(id)
+}
+
+/// Approve a proposal. The action is executed once the threshold is reached.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not an owner.
+/// - The proposal does not exist, e.g., because it was executed already.
+/// - The proposal has expired.
+/// - The sender has already approved the proposal.
+/// - The action is executed and fails, e.g., because of insufficient funds.
+/// - It fails to log the events.
+#[receive(
+    contract = "multisig",
+    name = "approve",
+    parameter = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_approve<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let owner = sender_owner(ctx, state)?;
+    {
+        let mut proposal = state.proposals.entry(id).occupied_or(ContractError::UnknownProposal)?;
+        ensure!(ctx.metadata().slot_time() <= proposal.expires_at, ContractError::ProposalExpired);
+        ensure!(proposal.approvals.insert(owner), ContractError::AlreadyApproved);
+    }
+
+    logger.log(&MultisigEvent::Approved {
+        id,
+        owner,
+    })?;
+    execute_if_approved(host, id, logger)?;
+    Ok(())
+}
+
+/// View the owners, the threshold and the pending proposals.
+#[receive(contract = "multisig", name = "view", return_value = "ViewState")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        owners:       state.owners.clone(),
+        threshold:    state.threshold,
+        proposal_ttl: state.proposal_ttl,
+        proposals:    state.proposals.iter().map(|(id, p)| (*id, p.clone())).collect(),
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([0; 32]);
+    const BOB: AccountAddress = AccountAddress([1; 32]);
+    const CAROL: AccountAddress = AccountAddress([2; 32]);
+    const DAVE: AccountAddress = AccountAddress([3; 32]);
+    const TARGET: ContractAddress = ContractAddress {
+        index:    7,
+        subindex: 0,
+    };
+    const TTL: u64 = 100;
+    const BALANCE: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    fn initial_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            owners:       [ALICE, BOB, CAROL].iter().copied().collect(),
+            threshold:    2,
+            proposal_ttl: Duration::from_millis(TTL),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = contract_init(&ctx, &mut state_builder, Amount::zero())
+            .expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(BALANCE);
+        host
+    }
+
+    fn propose(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        action: &Action,
+        logger: &mut TestLogger,
+    ) -> ContractResult<ProposalId> {
+        let parameter_bytes = to_bytes(action);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        ctx.set_parameter(&parameter_bytes);
+        contract_propose(&ctx, host, logger)
+    }
+
+    fn approve(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        id: ProposalId,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&id);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        contract_approve(&ctx, host, logger)
+    }
+
+    #[concordium_test]
+    /// Test that the threshold has to be reachable.
+    fn test_init_invalid_threshold() {
+        for threshold in [0, 3] {
+            let parameter_bytes = to_bytes(&InitParams {
+                owners: [ALICE, BOB].iter().copied().collect(),
+                threshold,
+                proposal_ttl: Duration::from_millis(TTL),
+            });
+            let mut ctx = TestInitContext::empty();
+            ctx.set_parameter(&parameter_bytes);
+            let res = contract_init(&ctx, &mut TestStateBuilder::new(), Amount::zero());
+            claim_eq!(res.map(|_| ()), Err(ContractError::InvalidThreshold.into()));
+        }
+    }
+
+    #[concordium_test]
+    /// Test the full flow of proposing, approving and executing a transfer.
+    fn test_transfer_flow() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        let action = Action::Transfer {
+            to:     DAVE,
+            amount: Amount::from_micro_ccd(400),
+        };
+
+        let id = propose(&mut host, ALICE, &action, &mut logger).expect_report("Propose failed");
+        claim!(host.get_transfers().is_empty(), "One approval is below the threshold");
+
+        let res = approve(&mut host, ALICE, id, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::AlreadyApproved), "Approving twice should fail");
+        let res = approve(&mut host, DAVE, id, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::NotOwner), "Only owners can approve");
+
+        approve(&mut host, BOB, id, TTL, &mut logger).expect_report("Approve failed");
+        claim_eq!(host.get_transfers(), [(DAVE, Amount::from_micro_ccd(400))]);
+        claim!(host.state().proposals.get(&id).is_none(), "The proposal should be removed");
+        claim_eq!(logger.logs, [
+            to_bytes(&MultisigEvent::Proposed {
+                id,
+                proposer: ALICE,
+            }),
+            to_bytes(&MultisigEvent::Approved {
+                id,
+                owner: BOB,
+            }),
+            to_bytes(&MultisigEvent::Executed {
+                id,
+            }),
+        ]);
+
+        let res = approve(&mut host, CAROL, id, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::UnknownProposal), "Executed proposals are removed");
+    }
+
+    #[concordium_test]
+    /// Test that expired proposals cannot be approved, and that transfers
+    /// exceeding the balance fail.
+    fn test_expiry_and_insufficient_funds() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+
+        let id = propose(
+            &mut host,
+            ALICE,
+            &Action::Transfer {
+                to:     DAVE,
+                amount: BALANCE + Amount::from_micro_ccd(1),
+            },
+            &mut logger,
+        )
+        .expect_report("Propose failed");
+        let res = approve(&mut host, BOB, id, TTL + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::ProposalExpired), "Expired proposals should fail");
+        let res = approve(&mut host, BOB, id, TTL, &mut logger);
+        claim_eq!(res, Err(ContractError::InsufficientFunds), "Overspending should fail");
+    }
+
+    #[concordium_test]
+    /// Test that a contract call is executed with the proposed parameter and
+    /// amount.
+    fn test_contract_call() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        host.setup_mock_entrypoint(
+            TARGET,
+            OwnedEntrypointName::new_unchecked("receive".into()),
+            MockFn::new_v1(|parameter, amount, _balance, _state| {
+                claim_eq!(parameter.0, [1, 2, 3], "The proposed parameter should be passed");
+                claim_eq!(amount, Amount::from_micro_ccd(10), "The amount should be sent");
+                Ok((false, ()))
+            }),
+        );
+        let action = Action::Call {
+            contract:   TARGET,
+            entrypoint: OwnedEntrypointName::new_unchecked("receive".into()),
+            parameter:  vec![1, 2, 3],
+            amount:     Amount::from_micro_ccd(10),
+        };
+
+        let id = propose(&mut host, CAROL, &action, &mut logger).expect_report("Propose failed");
+        approve(&mut host, ALICE, id, 0, &mut logger).expect_report("Approve failed");
+        claim!(host.state().proposals.get(&id).is_none(), "The call should be executed");
+    }
+
+    #[concordium_test]
+    /// Test rotating the owners: replacing an owner, after which the approvals
+    /// of the removed owner no longer count.
+    fn test_owner_rotation() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+
+        let transfer = Action::Transfer {
+            to:     DAVE,
+            amount: Amount::from_micro_ccd(1),
+        };
+        let pending = propose(&mut host, CAROL, &transfer, &mut logger).expect_report("Propose");
+
+        let add = propose(&mut host, ALICE, &Action::AddOwner(DAVE), &mut logger)
+            .expect_report("Propose add");
+        approve(&mut host, BOB, add, 0, &mut logger).expect_report("Approve add");
+        let remove = propose(&mut host, DAVE, &Action::RemoveOwner(CAROL), &mut logger)
+            .expect_report("Propose remove");
+        approve(&mut host, ALICE, remove, 0, &mut logger).expect_report("Approve remove");
+        claim_eq!(host.state().owners, [ALICE, BOB, DAVE].iter().copied().collect());
+
+        let res = approve(&mut host, CAROL, pending, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::NotOwner), "Removed owners cannot approve");
+        approve(&mut host, DAVE, pending, 0, &mut logger).expect_report("Approve pending");
+        claim!(host.get_transfers().is_empty(), "Carol's approval should no longer count");
+        approve(&mut host, BOB, pending, 0, &mut logger).expect_report("Approve pending");
+        claim_eq!(host.get_transfers(), [(DAVE, Amount::from_micro_ccd(1))]);
+
+        // Raising the threshold above the number of owners fails on execution.
+        let change = propose(&mut host, ALICE, &Action::ChangeThreshold(4), &mut logger)
+            .expect_report("Propose change");
+        let res = approve(&mut host, BOB, change, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::InvalidThreshold), "Unreachable threshold");
+    }
+}
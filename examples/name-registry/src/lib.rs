@@ -0,0 +1,484 @@
+//! A name registry smart contract example.
+//!
+//! # Description
+//! A contract mapping human-readable names to an address and a record of
+//! arbitrary data, like website URLs or accounts in other systems. Compared to
+//! the `nametoken` example, names are plain entries in a
+//! `StateMap<String, Entry>`, rather than CIS-2 tokens.
+//!
+//! # Operations
+//! The contract allows for
+//!  - `register`ing a name, which is possible if the name is not registered, or
+//!    its registration has expired;
+//!  - `renew`ing a name, extending its registration by one registration period;
+//!  - `update`ing the address and data of a name;
+//!  - `transfer`ring the ownership of a name to another account;
+//!  - `resolve`ing a name to its entry.
+//!
+//! Registering and renewing require paying the registration fee set at
+//! initialization, which is transferred to the owner of the contract instance.
+//! Names expire one registration period after being registered or renewed,
+//! after which they can no longer be updated, transferred or renewed, and can
+//! be registered by anyone.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The maximum length of a name in bytes.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// The registration of a name.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The account owning the name.
+    owner:   AccountAddress,
+    /// The address the name resolves to.
+    address: Address,
+    /// Arbitrary data attached to the name.
+    #[concordium(size_length = 2)]
+    data:    Vec<u8>,
+    /// The time the registration expires.
+    expires: Timestamp,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The fee for registering or renewing a name.
+    registration_fee:    Amount,
+    /// How long a registration or renewal lasts.
+    registration_period: Duration,
+    /// The registered names, including expired ones.
+    names:               StateMap<String, Entry, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The fee for registering or renewing a name.
+    registration_fee:    Amount,
+    /// How long a registration or renewal lasts.
+    registration_period: Duration,
+}
+
+/// The parameter type for the contract functions `register` and `update`.
+#[derive(Serialize, SchemaType)]
+pub struct NameParameter {
+    /// The name.
+    pub name:    String,
+    /// The address the name resolves to.
+    pub address: Address,
+    /// Arbitrary data attached to the name.
+    #[concordium(size_length = 2)]
+    pub data:    Vec<u8>,
+}
+
+/// The parameter type for the contract function `transfer`.
+#[derive(Serialize, SchemaType)]
+pub struct TransferParameter {
+    /// The name.
+    pub name:      String,
+    /// The account receiving the ownership of the name.
+    pub new_owner: AccountAddress,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when a contract tries to register; Only accounts can own names.
+    OnlyAccount,
+    /// Raised when the name is empty, too long, or contains other characters
+    /// than lowercase ASCII letters, digits and hyphens.
+    InvalidName,
+    /// Raised when the amount paid is not the registration fee.
+    InvalidFee,
+    /// Raised when registering a name which is registered and not expired.
+    NameTaken,
+    /// Raised when the name has never been registered.
+    UnknownName,
+    /// Raised when the registration of the name has expired.
+    NameExpired,
+    /// Raised when someone else than the owner updates or transfers a name.
+    Unauthorized,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Check that the name is non-empty, at most [`MAX_NAME_LENGTH`] bytes long,
+/// and consists of lowercase ASCII letters, digits and hyphens only.
+fn ensure_valid_name(name: &str) -> ContractResult<()> {
+    ensure!(
+        !name.is_empty()
+            && name.len() <= MAX_NAME_LENGTH
+            && name.bytes().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-'),
+        ContractError::InvalidName
+    );
+    Ok(())
+}
+
+/// Compute the expiry of a registration made or extended at the given time.
+fn expiry_after(time: Timestamp, period: Duration) -> Timestamp {
+    time.checked_add(period).unwrap_or_else(|| Timestamp::from_timestamp_millis(u64::MAX))
+}
+
+/// Get the entry of the name, if it is not expired.
+fn active_entry<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+    name: &String,
+) -> ContractResult<Entry> {
+    let entry = state.names.get(name).map(|e| e.clone()).ok_or(ContractError::UnknownName)?;
+    ensure!(ctx.metadata().slot_time() < entry.expires, ContractError::NameExpired);
+    Ok(entry)
+}
+
+/// Get the entry of the name, if it is not expired and owned by the sender.
+fn owned_entry<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+    name: &String,
+) -> ContractResult<Entry> {
+    let entry = active_entry(ctx, state, name)?;
+    ensure!(ctx.sender().matches_account(&entry.owner), ContractError::Unauthorized);
+    Ok(entry)
+}
+
+/// Transfer the registration fee to the owner of the contract instance.
+fn collect_fee<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    ensure_eq!(amount, host.state().registration_fee, ContractError::InvalidFee);
+    if amount > Amount::zero() {
+        // The transfer always succeeds, since the owner of the contract
+        // instance is an existing account, and the fee was just received.
+        host.invoke_transfer(&ctx.owner(), amount).unwrap_abort();
+    }
+    Ok(())
+}
+
+// Contract functions
+
+/// Initialize the registry with the registration fee and period.
+#[init(contract = "name_registry", parameter = "InitParameter")]
+fn init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let param: InitParameter = ctx.parameter_cursor().get()?;
+    Ok(State {
+        registration_fee:    param.registration_fee,
+        registration_period: param.registration_period,
+        names:               state_builder.new_map(),
+    })
+}
+
+/// Register a name for the sender, which is possible if the name has never
+/// been registered, or its registration has expired.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The name is invalid.
+/// - The amount is not the registration fee.
+/// - The name is registered and not expired.
+#[receive(
+    contract = "name_registry",
+    name = "register",
+    parameter = "NameParameter",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn register<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let param: NameParameter = ctx.parameter_cursor().get()?;
+    let owner = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    ensure_valid_name(&param.name)?;
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    if let Some(entry) = state.names.get(&param.name) {
+        ensure!(entry.expires <= now, ContractError::NameTaken);
+    }
+    let entry = Entry {
+        owner,
+        address: param.address,
+        data: param.data,
+        expires: expiry_after(now, state.registration_period),
+    };
+    state.names.insert(param.name, entry);
+    collect_fee(ctx, host, amount)
+}
+
+/// Renew the registration of a name, extending it by one registration period
+/// from its current expiry. Anyone can renew a name.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The name has never been registered, or its registration has expired.
+/// - The amount is not the registration fee.
+#[receive(
+    contract = "name_registry",
+    name = "renew",
+    parameter = "String",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn renew<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let name: String = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let mut entry = active_entry(ctx, state, &name)?;
+    entry.expires = expiry_after(entry.expires, state.registration_period);
+    state.names.insert(name, entry);
+    collect_fee(ctx, host, amount)
+}
+
+/// Update the address and data of a name owned by the sender.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The name has never been registered, or its registration has expired.
+/// - The sender is not the owner of the name.
+#[receive(
+    contract = "name_registry",
+    name = "update",
+    parameter = "NameParameter",
+    error = "ContractError",
+    mutable
+)]
+fn update<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let param: NameParameter = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let mut entry = owned_entry(ctx, state, &param.name)?;
+    entry.address = param.address;
+    entry.data = param.data;
+    state.names.insert(param.name, entry);
+    Ok(())
+}
+
+/// Transfer the ownership of a name owned by the sender. The address and data
+/// of the name are kept.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The name has never been registered, or its registration has expired.
+/// - The sender is not the owner of the name.
+#[receive(
+    contract = "name_registry",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError",
+    mutable
+)]
+fn transfer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let param: TransferParameter = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let mut entry = owned_entry(ctx, state, &param.name)?;
+    entry.owner = param.new_owner;
+    state.names.insert(param.name, entry);
+    Ok(())
+}
+
+/// Resolve a name to its entry.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The name has never been registered, or its registration has expired.
+#[receive(
+    contract = "name_registry",
+    name = "resolve",
+    parameter = "String",
+    return_value = "Entry",
+    error = "ContractError"
+)]
+fn resolve<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Entry> {
+    let name: String = ctx.parameter_cursor().get()?;
+    active_entry(ctx, host.state(), &name)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const FEE: Amount = Amount {
+        micro_ccd: 500,
+    };
+    const PERIOD: u64 = 1_000;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            registration_fee:    FEE,
+            registration_period: Duration::from_millis(PERIOD),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(
+        sender: AccountAddress,
+        slot_time: u64,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_with_parameter(sender, slot_time, parameter_bytes);
+        ctx.set_owner(OWNER);
+        ctx
+    }
+
+    fn name_parameter(name: &str, data: &[u8]) -> NameParameter {
+        NameParameter {
+            name:    name.into(),
+            address: Address::Account(BOB),
+            data:    data.to_vec(),
+        }
+    }
+
+    fn register_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        name: &str,
+        slot_time: u64,
+        fee: Amount,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&name_parameter(name, b"https://alice.example"));
+        host.set_self_balance(host.self_balance() + fee);
+        register(&ctx_at(sender, slot_time, &parameter_bytes), host, fee)
+    }
+
+    fn resolve_at(
+        host: &TestHost<State<TestStateApi>>,
+        name: &str,
+        slot_time: u64,
+    ) -> ContractResult<Entry> {
+        let parameter_bytes = to_bytes(&String::from(name));
+        resolve(&ctx_at(ALICE, slot_time, &parameter_bytes), host)
+    }
+
+    #[concordium_test]
+    /// Test registering and resolving a name, and that the fee is forwarded.
+    fn test_register_and_resolve() {
+        let mut host = new_host();
+        register_at(&mut host, ALICE, "alice", 0, FEE).expect_report("Registering should pass");
+
+        claim_eq!(
+            resolve_at(&host, "alice", PERIOD - 1),
+            Ok(Entry {
+                owner:   ALICE,
+                address: Address::Account(BOB),
+                data:    b"https://alice.example".to_vec(),
+                expires: Timestamp::from_timestamp_millis(PERIOD),
+            })
+        );
+        claim_eq!(resolve_at(&host, "alice", PERIOD), Err(ContractError::NameExpired));
+        claim_eq!(resolve_at(&host, "bob", 0), Err(ContractError::UnknownName));
+        claim_eq!(host.get_transfers(), [(OWNER, FEE)], "The fee should go to the owner");
+    }
+
+    #[concordium_test]
+    /// Test that names cannot be taken until they expire, and that invalid
+    /// names and fees are rejected.
+    fn test_register_rejected() {
+        let mut host = new_host();
+        register_at(&mut host, ALICE, "alice", 0, FEE).expect_report("Registering should pass");
+
+        let res = register_at(&mut host, BOB, "alice", PERIOD - 1, FEE);
+        claim_eq!(res, Err(ContractError::NameTaken), "Taking a registered name should fail");
+        let too_long = "a".repeat(MAX_NAME_LENGTH + 1);
+        for name in ["", "Alice", "alice.ccd", too_long.as_str()] {
+            let res = register_at(&mut host, BOB, name, 0, FEE);
+            claim_eq!(res, Err(ContractError::InvalidName), "Invalid names should fail");
+        }
+        let res = register_at(&mut host, BOB, "bob", 0, FEE + Amount::from_micro_ccd(1));
+        claim_eq!(res, Err(ContractError::InvalidFee), "Overpaying should fail");
+
+        register_at(&mut host, BOB, "alice", PERIOD, FEE)
+            .expect_report("Registering an expired name should pass");
+        let entry = resolve_at(&host, "alice", PERIOD).expect_report("Resolving should pass");
+        claim_eq!(entry.owner, BOB, "The expired name should be taken over");
+        claim_eq!(entry.expires, Timestamp::from_timestamp_millis(2 * PERIOD));
+    }
+
+    #[concordium_test]
+    /// Test that renewing extends the registration from its current expiry,
+    /// and that expired names cannot be renewed.
+    fn test_renew() {
+        let mut host = new_host();
+        register_at(&mut host, ALICE, "alice", 0, FEE).expect_report("Registering should pass");
+
+        let parameter_bytes = to_bytes(&String::from("alice"));
+        host.set_self_balance(FEE);
+        renew(&ctx_at(BOB, 10, &parameter_bytes), &mut host, FEE)
+            .expect_report("Renewing should pass");
+        let entry = resolve_at(&host, "alice", 0).expect_report("Resolving should pass");
+        claim_eq!(entry.expires, Timestamp::from_timestamp_millis(2 * PERIOD));
+        claim_eq!(entry.owner, ALICE, "Renewing does not change the owner");
+
+        let res = renew(&ctx_at(ALICE, 2 * PERIOD, &parameter_bytes), &mut host, FEE);
+        claim_eq!(res, Err(ContractError::NameExpired), "Renewing an expired name should fail");
+        let res = renew(&ctx_at(ALICE, 0, &parameter_bytes), &mut host, Amount::zero());
+        claim_eq!(res, Err(ContractError::InvalidFee), "Renewing without the fee should fail");
+    }
+
+    #[concordium_test]
+    /// Test that only the owner can update and transfer a name.
+    fn test_update_and_transfer() {
+        let mut host = new_host();
+        register_at(&mut host, ALICE, "alice", 0, FEE).expect_report("Registering should pass");
+
+        let update_bytes = to_bytes(&name_parameter("alice", b"new data"));
+        let res = update(&ctx_at(BOB, 0, &update_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the owner can update");
+        update(&ctx_at(ALICE, 0, &update_bytes), &mut host).expect_report("Updating should pass");
+        let entry = resolve_at(&host, "alice", 0).expect_report("Resolving should pass");
+        claim_eq!(entry.data, b"new data".to_vec());
+
+        let transfer_bytes = to_bytes(&TransferParameter {
+            name:      "alice".into(),
+            new_owner: BOB,
+        });
+        let res = transfer(&ctx_at(BOB, 0, &transfer_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the owner can transfer");
+        transfer(&ctx_at(ALICE, 0, &transfer_bytes), &mut host)
+            .expect_report("Transferring should pass");
+        let res = update(&ctx_at(ALICE, 0, &update_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "The previous owner cannot update");
+        update(&ctx_at(BOB, 0, &update_bytes), &mut host)
+            .expect_report("The new owner should be able to update");
+
+        let res = transfer(&ctx_at(BOB, PERIOD, &transfer_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::NameExpired), "Expired names cannot be transferred");
+    }
+}
@@ -501,7 +501,7 @@ fn build_token_metadata_url(token_id: &ContractTokenId) -> String {
 
 /// Initialize contract instance with no token types initially.
 /// Set the account that initialised the contract to be admin
-#[init(contract = "NameToken")]
+#[init(contract = "NameToken", event = "Cis2Event<ContractTokenId, ContractTokenAmount>")]
 fn contract_init<S: HasStateApi>(
     ctx: &impl HasInitContext,
     state_builder: &mut StateBuilder<S>,
@@ -1882,4 +1882,21 @@ mod tests {
         let err = result.expect_err_report("Expected to fail");
         claim_eq!(err, ContractError::Unauthorized, "Error is expected to be Unathorized");
     }
+
+    /// Test that the event type declared on the init function is embedded in
+    /// the module schema. Run with `--features build-schema`.
+    #[concordium_test]
+    #[cfg(feature = "build-schema")]
+    fn test_event_schema() {
+        // The schema is written to memory prefixed by its length as a `u32`.
+        let schema = unsafe {
+            let ptr = concordium_event_schema_contract_init();
+            let len = u32::from_le_bytes(*(ptr as *const [u8; 4]));
+            core::slice::from_raw_parts(ptr.add(4), len as usize)
+        };
+        let event_type =
+            <Cis2Event<ContractTokenId, ContractTokenAmount> as schema::SchemaType>::get_type();
+        claim!(matches!(event_type, schema::Type::TaggedEnum(_)), "Events are a tagged enum");
+        claim_eq!(schema, &to_bytes(&event_type)[..], "The event schema should be embedded");
+    }
 }
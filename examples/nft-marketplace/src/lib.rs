@@ -0,0 +1,687 @@
+//! An NFT marketplace smart contract example for CIS2 tokens.
+//!
+//! # Description
+//! Accounts can list CIS2 tokens they own for sale at a price in CCD. Before
+//! listing a token, the owner has to enable this contract as an operator of
+//! their address in the token contract, which allows the marketplace to
+//! transfer the token when it is bought. Listing checks both the ownership and
+//! the operator approval by querying the token contract.
+//!
+//! When a token is bought, the price is split between the seller, the original
+//! creator of the token and the owner of the marketplace:
+//! - The creator of a token is the account listing it for the first time on
+//!   this marketplace. The creator chooses a royalty in basis points, which is
+//!   paid to them on every later sale as well.
+//! - The owner of the marketplace receives the platform fee, which is set in
+//!   basis points when the contract is initialized.
+//! - The seller receives the rest.
+//!
+//! The contract logs an event whenever a token is listed, delisted or sold.
+//!
+//! The marketplace works with any CIS2 contract, for instance the `cis2-nft`
+//! example, as it uses token IDs of arbitrary size.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// Token ID type of the listed tokens. Using `TokenIdVec` allows listing
+/// tokens from any CIS2 contract, since all token IDs serialize the same way.
+type ContractTokenId = TokenIdVec;
+
+/// Token amount type used when querying and transferring the listed tokens.
+type ContractTokenAmount = TokenAmountU64;
+
+/// A token, identified by the contract and the token ID.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    /// The address of the CIS2 contract.
+    pub contract: ContractAddress,
+    /// The ID of the token in the CIS2 contract.
+    pub id:       ContractTokenId,
+}
+
+/// A token listed for sale.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Listing {
+    /// The account selling the token.
+    pub seller: AccountAddress,
+    /// The price of the token.
+    pub price:  Amount,
+}
+
+/// The royalty paid to the creator of a token on every sale.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Royalty {
+    /// The account which first listed the token.
+    pub creator:     AccountAddress,
+    /// The royalty in basis points of the price.
+    pub royalty_bps: BasisPoints,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The platform fee in basis points of the price, paid to the owner.
+    platform_fee_bps: BasisPoints,
+    /// The tokens currently listed for sale.
+    listings:         StateMap<TokenInfo, Listing, S>,
+    /// The royalty of every token which has been listed.
+    royalties:        StateMap<TokenInfo, Royalty, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The platform fee in basis points of the price.
+    platform_fee_bps: u16,
+}
+
+/// The parameter type for the contract function `list`.
+#[derive(Serialize, SchemaType)]
+pub struct ListParams {
+    /// The token to list.
+    pub token:       TokenInfo,
+    /// The price of the token.
+    pub price:       Amount,
+    /// The royalty in basis points of the price. It is only used when the
+    /// token is listed for the first time.
+    pub royalty_bps: u16,
+}
+
+/// A listing in the `return_value` of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ListingView {
+    /// The listed token.
+    token:   TokenInfo,
+    /// The listing.
+    listing: Listing,
+    /// The royalty of the token.
+    royalty: Royalty,
+}
+
+/// The `return_value` type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The platform fee in basis points of the price.
+    platform_fee_bps: BasisPoints,
+    /// The tokens currently listed for sale.
+    listings:         Vec<ListingView>,
+}
+
+/// The event logged when a token is sold.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct SaleEvent {
+    /// The sold token.
+    pub token:        TokenInfo,
+    /// The account which sold the token.
+    pub seller:       AccountAddress,
+    /// The account which bought the token.
+    pub buyer:        AccountAddress,
+    /// The price paid for the token.
+    pub price:        Amount,
+    /// The royalty paid to the creator.
+    pub royalty:      Amount,
+    /// The platform fee paid to the owner.
+    pub platform_fee: Amount,
+}
+
+/// The events logged by the contract.
+#[concordium_event]
+#[derive(Debug, PartialEq, Eq)]
+pub enum MarketplaceEvent {
+    /// A token was listed for sale, or the price of a listing was updated.
+    Listed(TokenInfo, Listing),
+    /// A token was removed from sale by the seller.
+    Delisted(TokenInfo),
+    /// A token was sold.
+    Sold(SaleEvent),
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when the platform fee is above 100%.
+    InvalidPlatformFee,
+    /// Raised when the royalty and the platform fee together are above 100%.
+    InvalidRoyalty,
+    /// Only accounts can list and buy tokens.
+    OnlyAccount,
+    /// Raised when listing a token which the sender does not own.
+    NotTokenOwner,
+    /// Raised when listing a token without enabling the marketplace as an
+    /// operator.
+    NotOperator,
+    /// Raised when the token is not listed.
+    NotListed,
+    /// Raised when someone other than the seller delists a token.
+    NotSeller,
+    /// Raised when the amount paid is not the price of the token.
+    WrongAmount,
+    /// Raised when invoking the token contract failed.
+    InvokeContractError,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Query the balance of the owner in the token contract.
+fn query_balance<S: HasStateApi>(
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    token: &TokenInfo,
+    owner: AccountAddress,
+) -> ContractResult<ContractTokenAmount> {
+    let parameter = BalanceOfQueryParams {
+        queries: vec![BalanceOfQuery {
+            token_id: token.id.clone(),
+            address:  Address::Account(owner),
+        }],
+    };
+    let response = host.invoke_contract_read_only(
+        &token.contract,
+        &parameter,
+        EntrypointName::new_unchecked("balanceOf"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ContractError::InvokeContractError)?;
+    let balances: BalanceOfQueryResponse<ContractTokenAmount> = response.get()?;
+    balances.0.first().copied().ok_or(ContractError::InvokeContractError)
+}
+
+/// Query whether the operator is enabled for the owner in the token contract.
+fn query_is_operator<S: HasStateApi>(
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    token: &TokenInfo,
+    owner: AccountAddress,
+    operator: ContractAddress,
+) -> ContractResult<bool> {
+    let parameter = OperatorOfQueryParams {
+        queries: vec![OperatorOfQuery {
+            owner:   Address::Account(owner),
+            address: Address::Contract(operator),
+        }],
+    };
+    let response = host.invoke_contract_read_only(
+        &token.contract,
+        &parameter,
+        EntrypointName::new_unchecked("operatorOf"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ContractError::InvokeContractError)?;
+    let operators: OperatorOfQueryResponse = response.get()?;
+    operators.0.first().copied().ok_or(ContractError::InvokeContractError)
+}
+
+/// Initialize the marketplace with the platform fee.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The platform fee is above 100%.
+#[init(contract = "nft_marketplace", parameter = "InitParams", event = "MarketplaceEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    let platform_fee_bps =
+        BasisPoints::new(params.platform_fee_bps).ok_or(ContractError::InvalidPlatformFee)?;
+    Ok(State {
+        platform_fee_bps,
+        listings: state_builder.new_map(),
+        royalties: state_builder.new_map(),
+    })
+}
+
+/// List a token for sale, or update the price of an existing listing. The
+/// first account to list a token becomes its creator and sets its royalty.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The sender does not own the token.
+/// - The marketplace is not an operator of the sender in the token contract.
+/// - The token is listed for the first time and the royalty together with the
+///   platform fee is above 100%.
+/// - It fails to log the event.
+#[receive(
+    contract = "nft_marketplace",
+    name = "list",
+    parameter = "ListParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_list<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: ListParams = ctx.parameter_cursor().get()?;
+    let seller = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+
+    let balance = query_balance(host, &params.token, seller)?;
+    ensure!(balance.0 > 0, ContractError::NotTokenOwner);
+    ensure!(
+        query_is_operator(host, &params.token, seller, ctx.self_address())?,
+        ContractError::NotOperator
+    );
+
+    let state = host.state_mut();
+    if state.royalties.get(&params.token).is_none() {
+        let royalty_bps = BasisPoints::new(params.royalty_bps)
+            .filter(|royalty_bps| royalty_bps.checked_add(state.platform_fee_bps).is_some())
+            .ok_or(ContractError::InvalidRoyalty)?;
+        state.royalties.insert(params.token.clone(), Royalty {
+            creator: seller,
+            royalty_bps,
+        });
+    }
+    let listing = Listing {
+        seller,
+        price: params.price,
+    };
+    state.listings.insert(params.token.clone(), listing);
+
+    logger.log_event(&MarketplaceEvent::Listed(params.token, listing))?;
+    Ok(())
+}
+
+/// Remove a token from sale.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The token is not listed.
+/// - The sender is not the seller.
+/// - It fails to log the event.
+#[receive(
+    contract = "nft_marketplace",
+    name = "delist",
+    parameter = "TokenInfo",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_delist<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let token: TokenInfo = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let seller = state.listings.get(&token).map(|l| l.seller).ok_or(ContractError::NotListed)?;
+    ensure!(ctx.sender().matches_account(&seller), ContractError::NotSeller);
+    state.listings.remove(&token);
+
+    logger.log_event(&MarketplaceEvent::Delisted(token))?;
+    Ok(())
+}
+
+/// Buy a listed token by paying exactly its price. The token is transferred
+/// from the seller to the buyer, and the price is split between the creator,
+/// the owner of the marketplace and the seller.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The token is not listed.
+/// - The amount is not the price of the token.
+/// - The token contract rejects the transfer, e.g., because the seller no
+///   longer owns the token or has disabled the marketplace as an operator.
+/// - It fails to log the event.
+#[receive(
+    contract = "nft_marketplace",
+    name = "buy",
+    parameter = "TokenInfo",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_buy<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let token: TokenInfo = ctx.parameter_cursor().get()?;
+    let buyer = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+
+    let state = host.state_mut();
+    let listing = state.listings.get(&token).map(|l| *l).ok_or(ContractError::NotListed)?;
+    ensure_eq!(amount, listing.price, ContractError::WrongAmount);
+    let royalty_info = state.royalties.get(&token).map(|r| *r).unwrap_abort();
+    let royalty = royalty_info.royalty_bps.of(amount);
+    let platform_fee = state.platform_fee_bps.of(amount);
+    state.listings.remove(&token);
+
+    let transfer = Transfer {
+        token_id: token.id.clone(),
+        amount:   ContractTokenAmount::from(1),
+        from:     Address::Account(listing.seller),
+        to:       Receiver::from_account(buyer),
+        data:     AdditionalData::empty(),
+    };
+    host.invoke_contract(
+        &token.contract,
+        &TransferParams::from(vec![transfer]),
+        EntrypointName::new_unchecked("transfer"),
+        Amount::zero(),
+    )?;
+
+    // These transfers (given enough NRG of course) always succeed, since the
+    // receivers are existing accounts, and the contract has just received
+    // `amount`.
+    // Since the royalty and the platform fee are at most 100% together, the
+    // seller gets a non-negative amount.
+    for (receiver, part) in [
+        (royalty_info.creator, royalty),
+        (ctx.owner(), platform_fee),
+        (listing.seller, amount - royalty - platform_fee),
+    ] {
+        if part > Amount::zero() {
+            host.invoke_transfer(&receiver, part).unwrap_abort();
+        }
+    }
+
+    logger.log_event(&MarketplaceEvent::Sold(SaleEvent {
+        token,
+        seller: listing.seller,
+        buyer,
+        price: amount,
+        royalty,
+        platform_fee,
+    }))?;
+    Ok(())
+}
+
+/// View the platform fee and the current listings.
+#[receive(contract = "nft_marketplace", name = "view", return_value = "ViewState")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    let listings = state
+        .listings
+        .iter()
+        .map(|(token, listing)| ListingView {
+            token:   token.clone(),
+            listing: *listing,
+            royalty: state.royalties.get(&*token).map(|r| *r).unwrap_abort(),
+        })
+        .collect();
+    Ok(ViewState {
+        platform_fee_bps: state.platform_fee_bps,
+        listings,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const MARKETPLACE: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const NFT_CONTRACT: ContractAddress = ContractAddress {
+        index:    2,
+        subindex: 0,
+    };
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const BUYER: AccountAddress = AccountAddress([2; 32]);
+    const RESELLER: AccountAddress = AccountAddress([3; 32]);
+    const PLATFORM_FEE_BPS: u16 = 250;
+    const ROYALTY_BPS: u16 = 1_000;
+    const PRICE: Amount = Amount {
+        micro_ccd: 10_000,
+    };
+
+    fn token() -> TokenInfo {
+        TokenInfo {
+            contract: NFT_CONTRACT,
+            // The serialization of `TokenIdU32(42)` used by the `cis2-nft` example.
+            id:       TokenIdVec(vec![42, 0, 0, 0]),
+        }
+    }
+
+    fn initial_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            platform_fee_bps: PLATFORM_FEE_BPS,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn receive_ctx<'a>(
+        sender: AccountAddress,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_owner(OWNER);
+        ctx.set_self_address(MARKETPLACE);
+        ctx.set_parameter(parameter_bytes);
+        ctx
+    }
+
+    /// Mock the queries of the NFT contract, answering that `owner` owns the
+    /// token and whether the marketplace is an operator of `owner`.
+    fn mock_queries(
+        host: &mut TestHost<State<TestStateApi>>,
+        owner: AccountAddress,
+        operator: bool,
+    ) {
+        host.setup_mock_entrypoint(
+            NFT_CONTRACT,
+            OwnedEntrypointName::new_unchecked("balanceOf".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let params: BalanceOfQueryParams<ContractTokenId> =
+                    from_bytes(parameter.0).expect_report("Invalid balanceOf parameter");
+                let balance = if params.queries[0].address.matches_account(&owner) {
+                    1
+                } else {
+                    0
+                };
+                Ok((false, BalanceOfQueryResponse(vec![ContractTokenAmount::from(balance)])))
+            }),
+        );
+        host.setup_mock_entrypoint(
+            NFT_CONTRACT,
+            OwnedEntrypointName::new_unchecked("operatorOf".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let params: OperatorOfQueryParams =
+                    from_bytes(parameter.0).expect_report("Invalid operatorOf parameter");
+                claim_eq!(params.queries[0].address, Address::Contract(MARKETPLACE));
+                Ok((false, OperatorOfQueryResponse(vec![operator])))
+            }),
+        );
+    }
+
+    /// Mock the transfer entrypoint of the NFT contract, checking that the
+    /// token is transferred from the seller to the buyer.
+    fn mock_transfer(host: &mut TestHost<State<TestStateApi>>, seller: AccountAddress) {
+        host.setup_mock_entrypoint(
+            NFT_CONTRACT,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let params: TransferParams<ContractTokenId, ContractTokenAmount> =
+                    from_bytes(parameter.0).expect_report("Invalid transfer parameter");
+                claim_eq!(params.0.len(), 1);
+                let transfer = &params.0[0];
+                claim_eq!(transfer.token_id, token().id);
+                claim_eq!(transfer.amount, ContractTokenAmount::from(1));
+                claim_eq!(transfer.from, Address::Account(seller));
+                claim!(matches!(transfer.to, Receiver::Account(BUYER)));
+                Ok((false, ()))
+            }),
+        );
+    }
+
+    fn list(
+        host: &mut TestHost<State<TestStateApi>>,
+        seller: AccountAddress,
+        royalty_bps: u16,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&ListParams {
+            token: token(),
+            price: PRICE,
+            royalty_bps,
+        });
+        contract_list(&receive_ctx(seller, &parameter_bytes), host, logger)
+    }
+
+    fn buy(
+        host: &mut TestHost<State<TestStateApi>>,
+        amount: Amount,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&token());
+        host.set_self_balance(amount);
+        contract_buy(&receive_ctx(BUYER, &parameter_bytes), host, amount, logger)
+    }
+
+    /// Test listing a token and buying it, paying the royalty, the platform
+    /// fee and the seller.
+    #[concordium_test]
+    fn test_list_and_buy() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        mock_queries(&mut host, CREATOR, true);
+        mock_transfer(&mut host, CREATOR);
+
+        list(&mut host, CREATOR, ROYALTY_BPS, &mut logger).expect_report("Listing should pass");
+        buy(&mut host, PRICE, &mut logger).expect_report("Buying should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (CREATOR, Amount::from_micro_ccd(1_000)),
+            (OWNER, Amount::from_micro_ccd(250)),
+            (CREATOR, Amount::from_micro_ccd(8_750)),
+        ]);
+        claim!(host.state().listings.get(&token()).is_none(), "The listing should be removed");
+        claim_eq!(logger.logs, [
+            to_bytes(&MarketplaceEvent::Listed(token(), Listing {
+                seller: CREATOR,
+                price:  PRICE,
+            })),
+            to_bytes(&MarketplaceEvent::Sold(SaleEvent {
+                token:        token(),
+                seller:       CREATOR,
+                buyer:        BUYER,
+                price:        PRICE,
+                royalty:      Amount::from_micro_ccd(1_000),
+                platform_fee: Amount::from_micro_ccd(250),
+            })),
+        ]);
+
+        let res = buy(&mut host, PRICE, &mut logger);
+        claim_eq!(res, Err(ContractError::NotListed), "Buying a sold token should fail");
+    }
+
+    /// Test that a resale pays the royalty to the original creator, and that
+    /// the royalty cannot be changed by the reseller.
+    #[concordium_test]
+    fn test_resale_pays_creator() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        mock_queries(&mut host, CREATOR, true);
+        list(&mut host, CREATOR, ROYALTY_BPS, &mut logger).expect_report("Listing should pass");
+        contract_delist(&receive_ctx(CREATOR, &to_bytes(&token())), &mut host, &mut logger)
+            .expect_report("Delisting should pass");
+
+        mock_queries(&mut host, RESELLER, true);
+        mock_transfer(&mut host, RESELLER);
+        list(&mut host, RESELLER, 0, &mut logger).expect_report("Relisting should pass");
+        buy(&mut host, PRICE, &mut logger).expect_report("Buying should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (CREATOR, Amount::from_micro_ccd(1_000)),
+            (OWNER, Amount::from_micro_ccd(250)),
+            (RESELLER, Amount::from_micro_ccd(8_750)),
+        ]);
+    }
+
+    /// Test that listing requires owning the token, approving the marketplace
+    /// as an operator and a valid royalty.
+    #[concordium_test]
+    fn test_list_rejected() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+
+        mock_queries(&mut host, CREATOR, true);
+        let res = list(&mut host, RESELLER, ROYALTY_BPS, &mut logger);
+        claim_eq!(res, Err(ContractError::NotTokenOwner), "Only the owner can list");
+
+        let res =
+            list(&mut host, CREATOR, BasisPoints::MAX.get() - PLATFORM_FEE_BPS + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::InvalidRoyalty), "The royalty should be capped");
+
+        mock_queries(&mut host, CREATOR, false);
+        let res = list(&mut host, CREATOR, ROYALTY_BPS, &mut logger);
+        claim_eq!(res, Err(ContractError::NotOperator), "The marketplace should be an operator");
+
+        claim!(logger.logs.is_empty(), "Nothing should be logged");
+    }
+
+    /// Test that buying requires the exact price, and only the seller can
+    /// delist.
+    #[concordium_test]
+    fn test_buy_and_delist_rejected() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        mock_queries(&mut host, CREATOR, true);
+        list(&mut host, CREATOR, ROYALTY_BPS, &mut logger).expect_report("Listing should pass");
+
+        let res = buy(&mut host, PRICE - Amount::from_micro_ccd(1), &mut logger);
+        claim_eq!(res, Err(ContractError::WrongAmount), "Underpaying should fail");
+
+        let parameter_bytes = to_bytes(&token());
+        let res = contract_delist(&receive_ctx(BUYER, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::NotSeller), "Only the seller can delist");
+
+        // The seller revoked the operator approval after listing.
+        host.setup_mock_entrypoint(
+            NFT_CONTRACT,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::returning_err::<()>(CallContractError::Trap),
+        );
+        let res = buy(&mut host, PRICE, &mut logger);
+        claim_eq!(res, Err(ContractError::InvokeContractError), "A failed transfer should fail");
+    }
+}
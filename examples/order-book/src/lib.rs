@@ -0,0 +1,759 @@
+//! A limit order book smart contract example, trading a CIS-2 token against
+//! CCD.
+//!
+//! # Description
+//! An instance of this contract is an exchange for a single token of a CIS-2
+//! contract, set at initialization. Accounts place limit orders to buy or sell
+//! an amount of the token at a price in CCD per unit of the token.
+//!
+//! An order is matched against the orders on the other side of the book when
+//! it is placed, as long as the prices cross, starting from the best price and,
+//! for equal prices, the oldest order. Trades happen at the price of the order
+//! already in the book. What remains of the order after matching is added to
+//! the book, where it can be partially filled by later orders.
+//!
+//! The contract holds the funds of the orders in the book: buy orders are
+//! placed by sending the CCD for the full order along, and sell orders are
+//! placed after making the contract an operator of the seller in the token
+//! contract, which allows the contract to transfer the tokens of the order to
+//! itself. Trades are settled by transferring the tokens to the buyers and the
+//! CCD to the sellers. If a buy order trades at a lower price than its limit,
+//! the difference is refunded to the buyer.
+//!
+//! Note: The book is kept sorted in a vector in the contract state, which is
+//! simple but makes inserting and matching orders linear in the size of the
+//! book.
+//!
+//! # Operations
+//! The contract allows for
+//!  - `placeOrder`, which matches an order, and adds the remainder to the book;
+//!  - `cancelOrder`, which removes an order of the sender from the book, and
+//!    refunds what remains of it;
+//!  - `viewBook`, which returns the orders in the book, best first.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// Token ID type of the traded token. Using `TokenIdVec` allows trading tokens
+/// from any CIS2 contract, since all token IDs serialize the same way.
+type ContractTokenId = TokenIdVec;
+
+/// Token amount type used when trading and transferring the token.
+type ContractTokenAmount = TokenAmountU64;
+
+/// The identifier of an order.
+pub type OrderId = u64;
+
+/// A token, identified by the contract and the token ID.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    /// The address of the CIS2 contract.
+    pub contract: ContractAddress,
+    /// The ID of the token in the CIS2 contract.
+    pub id:       ContractTokenId,
+}
+
+/// The side of an order.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Buying the token for CCD.
+    Buy,
+    /// Selling the token for CCD.
+    Sell,
+}
+
+/// An order in the book.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Order {
+    /// The account which placed the order.
+    pub owner:     AccountAddress,
+    /// The side of the order.
+    pub side:      Side,
+    /// The limit price in CCD per unit of the token.
+    pub price:     Amount,
+    /// The amount of the token which has not been traded yet.
+    pub remaining: ContractTokenAmount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The traded token.
+    token:         TokenInfo,
+    /// The identifier of the next order.
+    next_order_id: OrderId,
+    /// The prices and identifiers of the buy orders in the book, sorted by
+    /// descending price and then by age.
+    bids:          Vec<(Amount, OrderId)>,
+    /// The prices and identifiers of the sell orders in the book, sorted by
+    /// ascending price and then by age.
+    asks:          Vec<(Amount, OrderId)>,
+    /// The orders in the book.
+    orders:        StateMap<OrderId, Order, S>,
+}
+
+/// The parameter type for the contract function `placeOrder`.
+#[derive(Serialize, SchemaType)]
+pub struct PlaceOrderParams {
+    /// The side of the order.
+    pub side:   Side,
+    /// The limit price in CCD per unit of the token.
+    pub price:  Amount,
+    /// The amount of the token to trade.
+    pub amount: ContractTokenAmount,
+}
+
+/// The return type of the contract function `viewBook`.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct BookView {
+    /// The buy orders, best first.
+    pub bids: Vec<(OrderId, Order)>,
+    /// The sell orders, best first.
+    pub asks: Vec<(OrderId, Order)>,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum OrderBookEvent {
+    /// An order was placed.
+    Place {
+        order_id: OrderId,
+        owner:    AccountAddress,
+        side:     Side,
+        price:    Amount,
+        amount:   ContractTokenAmount,
+    },
+    /// Two orders traded an amount of the token at a price.
+    Trade {
+        buy_order:  OrderId,
+        sell_order: OrderId,
+        price:      Amount,
+        amount:     ContractTokenAmount,
+    },
+    /// An order was cancelled, and what remained of it was refunded.
+    Cancel {
+        order_id:  OrderId,
+        remaining: ContractTokenAmount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed invoking the token contract.
+    InvokeContractError,
+    /// Raised when a contract places an order; Only accounts can trade.
+    OnlyAccount,
+    /// Raised when the price or the amount of an order is zero.
+    InvalidOrder,
+    /// Raised when the CCD sent along is not the cost of a buy order, or CCD
+    /// is sent along with a sell order.
+    WrongDeposit,
+    /// Raised when the cost of an order overflows.
+    Overflow,
+    /// Raised when the order is not in the book.
+    UnknownOrder,
+    /// Raised when someone else than the owner cancels an order.
+    Unauthorized,
+    /// Raised when receiving other tokens than the traded token.
+    UnexpectedToken,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Compute the cost in CCD of an amount of the token at a price.
+fn cost(price: Amount, amount: ContractTokenAmount) -> ContractResult<Amount> {
+    let micro_ccd = price.micro_ccd.checked_mul(amount.0).ok_or(ContractError::Overflow)?;
+    Ok(Amount::from_micro_ccd(micro_ccd))
+}
+
+/// Insert an order into one side of the book, after the orders with a better
+/// or equal price.
+fn insert_into_book(book: &mut Vec<(Amount, OrderId)>, side: Side, price: Amount, id: OrderId) {
+    let position = book
+        .iter()
+        .position(|(other, _)| match side {
+            Side::Buy => *other < price,
+            Side::Sell => *other > price,
+        })
+        .unwrap_or_else(|| book.len());
+    book.insert(position, (price, id));
+}
+
+/// Transfer the traded token from the contract to the given accounts.
+fn transfer_tokens<S: HasStateApi>(
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    self_address: ContractAddress,
+    transfers: Vec<(AccountAddress, ContractTokenAmount)>,
+) -> ContractResult<()> {
+    if transfers.is_empty() {
+        return Ok(());
+    }
+    let token = host.state().token.clone();
+    let transfers = transfers
+        .into_iter()
+        .map(|(to, amount)| Transfer {
+            token_id: token.id.clone(),
+            amount,
+            from: Address::Contract(self_address),
+            to: Receiver::from_account(to),
+            data: AdditionalData::empty(),
+        })
+        .collect();
+    host.invoke_contract(
+        &token.contract,
+        &TransferParams::from(transfers),
+        EntrypointName::new_unchecked("transfer"),
+        Amount::zero(),
+    )?;
+    Ok(())
+}
+
+// Contract functions
+
+/// Initialize the exchange for the given token, with an empty book.
+#[init(contract = "order_book", parameter = "TokenInfo", event = "OrderBookEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let token: TokenInfo = ctx.parameter_cursor().get()?;
+    Ok(State {
+        token,
+        next_order_id: 0,
+        bids: Vec::new(),
+        asks: Vec::new(),
+        orders: state_builder.new_map(),
+    })
+}
+
+/// Place an order, match it against the other side of the book, and add what
+/// remains of it to the book. Returns the identifier of the order.
+///
+/// Buy orders must send the cost of the full order along. Sell orders require
+/// the contract to be an operator of the sender in the token contract.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The price or the amount is zero.
+/// - The CCD sent along is not the cost of a buy order, or is not zero for a
+///   sell order.
+/// - It fails to transfer the tokens of a sell order to the contract.
+/// - It fails to transfer the traded tokens to the buyers.
+/// - It fails to log the events.
+#[receive(
+    contract = "order_book",
+    name = "placeOrder",
+    parameter = "PlaceOrderParams",
+    return_value = "OrderId",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_place_order<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<OrderId> {
+    let params: PlaceOrderParams = ctx.parameter_cursor().get()?;
+    let owner = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    ensure!(
+        params.price > Amount::zero() && params.amount > ContractTokenAmount::from(0),
+        ContractError::InvalidOrder
+    );
+    let self_address = ctx.self_address();
+    match params.side {
+        Side::Buy => {
+            ensure_eq!(amount, cost(params.price, params.amount)?, ContractError::WrongDeposit)
+        }
+        Side::Sell => {
+            ensure_eq!(amount, Amount::zero(), ContractError::WrongDeposit);
+            // Take the tokens of the order into custody before matching.
+            let token = host.state().token.clone();
+            let transfer = Transfer {
+                token_id: token.id,
+                amount:   params.amount,
+                from:     Address::Account(owner),
+                to:       Receiver::from_contract(
+                    self_address,
+                    OwnedEntrypointName::new_unchecked("onReceivingCIS2".into()),
+                ),
+                data:     AdditionalData::empty(),
+            };
+            host.invoke_contract(
+                &token.contract,
+                &TransferParams::from(vec![transfer]),
+                EntrypointName::new_unchecked("transfer"),
+                Amount::zero(),
+            )?;
+        }
+    }
+
+    let state = host.state_mut();
+    let order_id = state.next_order_id;
+    state.next_order_id += 1;
+    logger.log(&OrderBookEvent::Place {
+        order_id,
+        owner,
+        side: params.side,
+        price: params.price,
+        amount: params.amount,
+    })?;
+
+    // The settlement of the trades, carried out after matching.
+    let mut ccd_transfers: Vec<(AccountAddress, Amount)> = Vec::new();
+    let mut token_transfers: Vec<(AccountAddress, ContractTokenAmount)> = Vec::new();
+    let mut remaining = params.amount;
+    let zero = ContractTokenAmount::from(0);
+    loop {
+        let opposite = match params.side {
+            Side::Buy => &mut state.asks,
+            Side::Sell => &mut state.bids,
+        };
+        let (resting_price, resting_id) = match opposite.first() {
+            Some(best) => *best,
+            None => break,
+        };
+        let crosses = match params.side {
+            Side::Buy => resting_price <= params.price,
+            Side::Sell => resting_price >= params.price,
+        };
+        if remaining == zero || !crosses {
+            break;
+        }
+
+        let mut resting = state.orders.get(&resting_id).map(|o| *o).unwrap_abort();
+        let fill = remaining.min(resting.remaining);
+        remaining -= fill;
+        resting.remaining -= fill;
+        if resting.remaining == zero {
+            opposite.remove(0);
+            state.orders.remove(&resting_id);
+        } else {
+            state.orders.insert(resting_id, resting);
+        }
+
+        // Trades happen at the price of the resting order. The costs cannot
+        // overflow, since they are at most the cost of the buy order.
+        let trade_cost = cost(resting_price, fill)?;
+        let (buy_order, sell_order) = match params.side {
+            Side::Buy => {
+                token_transfers.push((owner, fill));
+                ccd_transfers.push((resting.owner, trade_cost));
+                let refund = cost(params.price, fill)? - trade_cost;
+                if refund > Amount::zero() {
+                    ccd_transfers.push((owner, refund));
+                }
+                (order_id, resting_id)
+            }
+            Side::Sell => {
+                token_transfers.push((resting.owner, fill));
+                ccd_transfers.push((owner, trade_cost));
+                (resting_id, order_id)
+            }
+        };
+        logger.log(&OrderBookEvent::Trade {
+            buy_order,
+            sell_order,
+            price: resting_price,
+            amount: fill,
+        })?;
+    }
+
+    if remaining > zero {
+        let book = match params.side {
+            Side::Buy => &mut state.bids,
+            Side::Sell => &mut state.asks,
+        };
+        insert_into_book(book, params.side, params.price, order_id);
+        state.orders.insert(order_id, Order {
+            owner,
+            side: params.side,
+            price: params.price,
+            remaining,
+        });
+    }
+
+    for (receiver, ccd) in ccd_transfers {
+        // The transfers always succeed, since the receivers are existing
+        // accounts, and the contract holds the CCD of the buy orders.
+        host.invoke_transfer(&receiver, ccd).unwrap_abort();
+    }
+    transfer_tokens(host, self_address, token_transfers)?;
+    Ok(order_id)
+}
+
+/// Cancel an order of the sender, removing it from the book and refunding
+/// what remains of it.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The order is not in the book.
+/// - The sender is not the owner of the order.
+/// - It fails to transfer the tokens of a sell order back.
+/// - It fails to log the event.
+#[receive(
+    contract = "order_book",
+    name = "cancelOrder",
+    parameter = "OrderId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_cancel_order<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let order_id: OrderId = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let order = state.orders.get(&order_id).map(|o| *o).ok_or(ContractError::UnknownOrder)?;
+    ensure!(ctx.sender().matches_account(&order.owner), ContractError::Unauthorized);
+    state.orders.remove(&order_id);
+    let book = match order.side {
+        Side::Buy => &mut state.bids,
+        Side::Sell => &mut state.asks,
+    };
+    book.retain(|(_, id)| *id != order_id);
+    logger.log(&OrderBookEvent::Cancel {
+        order_id,
+        remaining: order.remaining,
+    })?;
+
+    match order.side {
+        Side::Buy => {
+            // The transfer always succeeds, since the owner is the sender and
+            // the contract holds the CCD of the order.
+            host.invoke_transfer(&order.owner, cost(order.price, order.remaining)?).unwrap_abort();
+        }
+        Side::Sell => {
+            transfer_tokens(host, ctx.self_address(), vec![(order.owner, order.remaining)])?
+        }
+    }
+    Ok(())
+}
+
+/// Accept the traded token when taking sell orders into custody. Rejects any
+/// other tokens.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the token contract, or the token is not the traded
+///   token.
+#[receive(
+    contract = "order_book",
+    name = "onReceivingCIS2",
+    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    error = "ContractError"
+)]
+fn contract_on_receiving_cis2<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let params: OnReceivingCis2Params<ContractTokenId, ContractTokenAmount> =
+        ctx.parameter_cursor().get()?;
+    let token = &host.state().token;
+    ensure!(
+        ctx.sender() == Address::Contract(token.contract) && params.token_id == token.id,
+        ContractError::UnexpectedToken
+    );
+    Ok(())
+}
+
+/// View the orders in the book, best first.
+#[receive(contract = "order_book", name = "viewBook", return_value = "BookView")]
+fn contract_view_book<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<BookView> {
+    let state = host.state();
+    let orders_of = |book: &Vec<(Amount, OrderId)>| -> Vec<(OrderId, Order)> {
+        book.iter().map(|(_, id)| (*id, state.orders.get(id).map(|o| *o).unwrap_abort())).collect()
+    };
+    Ok(BookView {
+        bids: orders_of(&state.bids),
+        asks: orders_of(&state.asks),
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const TOKEN_CONTRACT: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index:    2,
+        subindex: 0,
+    };
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CHARLIE: AccountAddress = AccountAddress([3; 32]);
+
+    fn token() -> TokenInfo {
+        TokenInfo {
+            contract: TOKEN_CONTRACT,
+            id:       TokenIdVec(vec![1]),
+        }
+    }
+
+    fn ccd(micro_ccd: u64) -> Amount { Amount::from_micro_ccd(micro_ccd) }
+
+    fn tokens(amount: u64) -> ContractTokenAmount { ContractTokenAmount::from(amount) }
+
+    /// Set up a host with an empty book, and a token contract accepting every
+    /// transfer of the traded token.
+    fn initial_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&token());
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.setup_mock_entrypoint(
+            TOKEN_CONTRACT,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: TransferParams<ContractTokenId, ContractTokenAmount> =
+                    from_bytes(parameter.0).expect_report("Invalid transfer parameter");
+                for transfer in params.0 {
+                    claim_eq!(transfer.token_id, token().id);
+                    // Tokens are either taken into custody or sent out of it.
+                    let into_custody = matches!(transfer.to, Receiver::Contract(SELF_ADDRESS, _));
+                    claim_eq!(transfer.from == Address::Contract(SELF_ADDRESS), !into_custody);
+                }
+                Ok((false, ()))
+            }),
+        );
+        host
+    }
+
+    fn place(
+        host: &mut TestHost<State<TestStateApi>>,
+        owner: AccountAddress,
+        side: Side,
+        price: u64,
+        amount: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<OrderId> {
+        let parameter_bytes = to_bytes(&PlaceOrderParams {
+            side,
+            price: ccd(price),
+            amount: tokens(amount),
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(owner));
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx.set_parameter(&parameter_bytes);
+        let deposit = match side {
+            Side::Buy => ccd(price * amount),
+            Side::Sell => Amount::zero(),
+        };
+        host.set_self_balance(host.self_balance() + deposit);
+        contract_place_order(&ctx, host, deposit, logger)
+    }
+
+    fn cancel(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        order_id: OrderId,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&order_id);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx.set_parameter(&parameter_bytes);
+        contract_cancel_order(&ctx, host, logger)
+    }
+
+    fn book(host: &TestHost<State<TestStateApi>>) -> BookView {
+        contract_view_book(&TestReceiveContext::empty(), host).expect_report("Viewing should pass")
+    }
+
+    fn trade(buy_order: OrderId, sell_order: OrderId, price: u64, amount: u64) -> Vec<u8> {
+        to_bytes(&OrderBookEvent::Trade {
+            buy_order,
+            sell_order,
+            price: ccd(price),
+            amount: tokens(amount),
+        })
+    }
+
+    #[concordium_test]
+    /// Test that a buy order partially fills a resting sell order at the price
+    /// of the sell order, refunding the price difference to the buyer.
+    fn test_buy_fills_resting_sell() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        place(&mut host, ALICE, Side::Sell, 5, 10, &mut logger)
+            .expect_report("Selling should pass");
+        place(&mut host, BOB, Side::Buy, 6, 4, &mut logger).expect_report("Buying should pass");
+
+        claim_eq!(logger.logs[2], trade(1, 0, 5, 4));
+        claim_eq!(host.get_transfers(), [(ALICE, ccd(20)), (BOB, ccd(4))]);
+        claim_eq!(book(&host), BookView {
+            bids: Vec::new(),
+            asks: vec![(0, Order {
+                owner:     ALICE,
+                side:      Side::Sell,
+                price:     ccd(5),
+                remaining: tokens(6),
+            })],
+        });
+    }
+
+    #[concordium_test]
+    /// Test that an order is matched by best price and then by age, and that
+    /// the remainder is added to the book.
+    fn test_price_time_priority() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        place(&mut host, ALICE, Side::Sell, 7, 5, &mut logger).expect_report("Selling should pass");
+        place(&mut host, BOB, Side::Sell, 6, 5, &mut logger).expect_report("Selling should pass");
+        place(&mut host, ALICE, Side::Sell, 6, 5, &mut logger).expect_report("Selling should pass");
+        place(&mut host, CHARLIE, Side::Buy, 6, 12, &mut logger)
+            .expect_report("Buying should pass");
+
+        claim_eq!(logger.logs[4..], [trade(3, 1, 6, 5), trade(3, 2, 6, 5)]);
+        let book = book(&host);
+        claim_eq!(book.bids, [(3, Order {
+            owner:     CHARLIE,
+            side:      Side::Buy,
+            price:     ccd(6),
+            remaining: tokens(2),
+        })]);
+        claim_eq!(book.asks.iter().map(|(id, _)| *id).collect::<Vec<_>>(), [0]);
+        claim_eq!(host.self_balance(), ccd(12), "The CCD of the resting bid should be held");
+    }
+
+    #[concordium_test]
+    /// Test that a sell order fills resting buy orders at their prices.
+    fn test_sell_fills_resting_buys() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        place(&mut host, ALICE, Side::Buy, 8, 3, &mut logger).expect_report("Buying should pass");
+        place(&mut host, BOB, Side::Buy, 9, 3, &mut logger).expect_report("Buying should pass");
+        place(&mut host, CHARLIE, Side::Sell, 8, 4, &mut logger)
+            .expect_report("Selling should pass");
+
+        claim_eq!(logger.logs[3..], [trade(1, 2, 9, 3), trade(0, 2, 8, 1)]);
+        claim_eq!(host.get_transfers(), [(CHARLIE, ccd(27)), (CHARLIE, ccd(8))]);
+        claim_eq!(book(&host).bids, [(0, Order {
+            owner:     ALICE,
+            side:      Side::Buy,
+            price:     ccd(8),
+            remaining: tokens(2),
+        })]);
+    }
+
+    #[concordium_test]
+    /// Test that only the owner can cancel an order, and that cancelling
+    /// refunds what remains of it.
+    fn test_cancel_order() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        place(&mut host, ALICE, Side::Buy, 5, 10, &mut logger).expect_report("Buying should pass");
+        place(&mut host, BOB, Side::Sell, 5, 4, &mut logger).expect_report("Selling should pass");
+
+        claim_eq!(cancel(&mut host, BOB, 0, &mut logger), Err(ContractError::Unauthorized));
+        claim_eq!(cancel(&mut host, BOB, 1, &mut logger), Err(ContractError::UnknownOrder));
+        cancel(&mut host, ALICE, 0, &mut logger).expect_report("Cancelling should pass");
+        claim_eq!(host.get_transfers(), [(BOB, ccd(20)), (ALICE, ccd(30))]);
+        claim_eq!(host.self_balance(), Amount::zero());
+        claim_eq!(book(&host), BookView {
+            bids: Vec::new(),
+            asks: Vec::new(),
+        });
+    }
+
+    #[concordium_test]
+    /// Test that orders with a zero price or amount, or the wrong deposit, are
+    /// rejected.
+    fn test_place_order_rejected() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        let res = place(&mut host, ALICE, Side::Buy, 0, 10, &mut logger);
+        claim_eq!(res, Err(ContractError::InvalidOrder));
+        let res = place(&mut host, ALICE, Side::Sell, 5, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::InvalidOrder));
+
+        let parameter_bytes = to_bytes(&PlaceOrderParams {
+            side:   Side::Buy,
+            price:  ccd(5),
+            amount: tokens(10),
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(ALICE));
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx.set_parameter(&parameter_bytes);
+        let res = contract_place_order(&ctx, &mut host, ccd(49), &mut logger);
+        claim_eq!(res, Err(ContractError::WrongDeposit));
+    }
+
+    #[concordium_test]
+    /// Test many crossing orders, checking after every order that the book is
+    /// sorted, does not cross, and that the contract holds exactly the CCD of
+    /// the resting buy orders.
+    fn test_many_orders() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        let accounts = [ALICE, BOB, CHARLIE];
+        for i in 0..60u64 {
+            let side = if i % 2 == 0 {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let price = 10 + (i * 7) % 11;
+            let amount = 1 + (i * 5) % 9;
+            place(&mut host, accounts[(i % 3) as usize], side, price, amount, &mut logger)
+                .expect_report("Placing should pass");
+
+            let book = book(&host);
+            claim!(book.bids.windows(2).all(|w| w[0].1.price >= w[1].1.price));
+            claim!(book.asks.windows(2).all(|w| w[0].1.price <= w[1].1.price));
+            if let (Some(bid), Some(ask)) = (book.bids.first(), book.asks.first()) {
+                claim!(bid.1.price < ask.1.price, "The book should not cross");
+            }
+            let held = book.bids.iter().fold(Amount::zero(), |held, (_, order)| {
+                held + cost(order.price, order.remaining).unwrap_abort()
+            });
+            claim_eq!(host.self_balance(), held);
+        }
+    }
+}
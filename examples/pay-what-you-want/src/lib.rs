@@ -0,0 +1,411 @@
+//! # Pay what you want
+//!
+//! Creators offer content with `setContent`, giving the minimum payment for it
+//! and a bonus threshold. Buyers `pay` any amount at or above the minimum for
+//! a content ID, which is transferred to the creator right away. Every payment
+//! is logged as an event, so dApps can unlock the content for the payer.
+//!
+//! Buyers paying more than the bonus threshold above the minimum are awarded a
+//! bonus badge. The badge is a token of a [`fan-badges`] instance, which the
+//! owner of that instance allows this contract to mint using `setMinter`. The
+//! contract awards the badge with a cross-contract call to `mint`, after
+//! checking with `balanceOf` that the payer does not hold it already.
+//!
+//! [`fan-badges`]: ../fan-badges
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// The token ID type of `fan_badges`.
+type BadgeId = TokenIdU32;
+
+/// The token amount type of `fan_badges`.
+type BadgeAmount = TokenAmountU8;
+
+/// Identifies a piece of content.
+pub type ContentId = u64;
+
+/// A piece of content offered by a creator.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Content {
+    /// The creator, who receives the payments.
+    pub creator:         AccountAddress,
+    /// The minimum payment.
+    pub minimum:         Amount,
+    /// How much a payment must exceed the minimum by to award the badge.
+    pub bonus_threshold: Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The `fan_badges` instance of the bonus badge.
+    badge_contract: ContractAddress,
+    /// The bonus badge.
+    badge:          BadgeId,
+    /// The content offered by creators.
+    contents:       StateMap<ContentId, Content, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The `fan_badges` instance of the bonus badge.
+    badge_contract: ContractAddress,
+    /// The bonus badge.
+    badge:          BadgeId,
+}
+
+/// The parameter type for the contract function `setContent`. The sender
+/// becomes the creator.
+#[derive(Serialize, SchemaType)]
+struct SetContentParams {
+    /// The ID of the content.
+    content_id:      ContentId,
+    /// The minimum payment.
+    minimum:         Amount,
+    /// How much a payment must exceed the minimum by to award the badge.
+    bonus_threshold: Amount,
+}
+
+/// The parameter type for the contract function `mint` of `fan_badges`.
+#[derive(Serialize, SchemaType)]
+struct BadgeMintParams {
+    /// The badge to award.
+    badge:   BadgeId,
+    /// The addresses to award the badge to.
+    holders: Vec<Address>,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum PaymentEvent {
+    /// A payment was made for a piece of content.
+    Payment {
+        content_id: ContentId,
+        payer:      AccountAddress,
+        amount:     Amount,
+    },
+    /// The payer was awarded the bonus badge.
+    Bonus {
+        content_id: ContentId,
+        payer:      AccountAddress,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed to invoke the badge contract.
+    InvokeContractError,
+    /// Failed to parse the response of the badge contract.
+    ParseResult,
+    /// Raised when a contract pays or sets the content.
+    OnlyAccount,
+    /// Raised when changing content offered by another creator.
+    Unauthorized,
+    /// Raised when paying for content which is not offered.
+    UnknownContent,
+    /// Raised when paying less than the minimum.
+    BelowMinimum,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Query whether an address holds the badge, using `balanceOf` of the badge
+/// contract.
+fn holds_badge<S: HasStateApi>(
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    address: Address,
+) -> ContractResult<bool> {
+    let state = host.state();
+    let query = BalanceOfQueryParams {
+        queries: vec![BalanceOfQuery {
+            token_id: state.badge,
+            address,
+        }],
+    };
+    let response = host.invoke_contract_read_only(
+        &state.badge_contract,
+        &query,
+        EntrypointName::new_unchecked("balanceOf"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ContractError::ParseResult)?;
+    let balances: BalanceOfQueryResponse<BadgeAmount> =
+        response.get().map_err(|_| ContractError::ParseResult)?;
+    Ok(balances.0.first().map_or(false, |balance| *balance > 0.into()))
+}
+
+/// Initialize the contract with the bonus badge.
+#[init(contract = "pay_what_you_want", parameter = "InitParams", event = "PaymentEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    Ok(State {
+        badge_contract: params.badge_contract,
+        badge:          params.badge,
+        contents:       state_builder.new_map(),
+    })
+}
+
+/// Offer a piece of content with the sender as the creator, or change the
+/// minimum and bonus threshold of content offered by the sender.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The content is offered by another creator.
+#[receive(
+    contract = "pay_what_you_want",
+    name = "setContent",
+    parameter = "SetContentParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_content<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let params: SetContentParams = ctx.parameter_cursor().get()?;
+    let creator = sender_account(ctx)?;
+    let state = host.state_mut();
+    if let Some(content) = state.contents.get(&params.content_id) {
+        ensure_eq!(content.creator, creator, ContractError::Unauthorized);
+    }
+    state.contents.insert(params.content_id, Content {
+        creator,
+        minimum: params.minimum,
+        bonus_threshold: params.bonus_threshold,
+    });
+    Ok(())
+}
+
+/// Pay for a piece of content, sending at least the minimum along, which is
+/// transferred to the creator. Awards the bonus badge to the sender if the
+/// payment exceeds the minimum by more than the bonus threshold, unless the
+/// sender holds it already.
+/// Logs a `Payment` event, and a `Bonus` event when the badge is awarded.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The content is not offered.
+/// - The amount sent along is below the minimum.
+/// - Querying or minting the badge fails, e.g., because this contract is not a
+///   minter of the badge contract.
+/// - It fails to log the events.
+#[receive(
+    contract = "pay_what_you_want",
+    name = "pay",
+    parameter = "ContentId",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_pay<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let content_id: ContentId = ctx.parameter_cursor().get()?;
+    let payer = sender_account(ctx)?;
+    let content =
+        host.state().contents.get(&content_id).map(|c| *c).ok_or(ContractError::UnknownContent)?;
+    ensure!(amount >= content.minimum, ContractError::BelowMinimum);
+
+    // The transfer always succeeds, since the creator is an account and the
+    // payment was just received by the contract.
+    host.invoke_transfer(&content.creator, amount).unwrap_abort();
+    logger.log(&PaymentEvent::Payment {
+        content_id,
+        payer,
+        amount,
+    })?;
+
+    if amount - content.minimum > content.bonus_threshold
+        && !holds_badge(host, Address::Account(payer))?
+    {
+        let params = BadgeMintParams {
+            badge:   host.state().badge,
+            holders: vec![Address::Account(payer)],
+        };
+        let badge_contract = host.state().badge_contract;
+        host.invoke_contract(
+            &badge_contract,
+            &params,
+            EntrypointName::new_unchecked("mint"),
+            Amount::zero(),
+        )?;
+        logger.log(&PaymentEvent::Bonus {
+            content_id,
+            payer,
+        })?;
+    }
+    Ok(())
+}
+
+/// View a piece of content, if it is offered.
+#[receive(
+    contract = "pay_what_you_want",
+    name = "content",
+    parameter = "ContentId",
+    return_value = "Option<Content>"
+)]
+fn contract_content<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Content>> {
+    let content_id: ContentId = ctx.parameter_cursor().get()?;
+    Ok(host.state().contents.get(&content_id).map(|c| *c))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const BUYER: AccountAddress = AccountAddress([2; 32]);
+    const BADGES: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const BADGE: BadgeId = TokenIdU32(3);
+    const CONTENT: ContentId = 7;
+    const MINIMUM: Amount = Amount::from_micro_ccd(1_000);
+    const BONUS_THRESHOLD: Amount = Amount::from_micro_ccd(500);
+
+    /// Create a host where `CREATOR` offers `CONTENT`.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            badge_contract: BADGES,
+            badge:          BADGE,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter_bytes = to_bytes(&SetContentParams {
+            content_id:      CONTENT,
+            minimum:         MINIMUM,
+            bonus_threshold: BONUS_THRESHOLD,
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(CREATOR));
+        ctx.set_parameter(&parameter_bytes);
+        contract_set_content(&ctx, &mut host).expect_report("Setting the content should pass");
+        host
+    }
+
+    /// Mock `balanceOf` and `mint` of the badge contract, where `BUYER` holds
+    /// the given balance of the badge.
+    fn mock_badges(host: &mut TestHost<State<TestStateApi>>, balance: u8) {
+        host.setup_mock_entrypoint(
+            BADGES,
+            OwnedEntrypointName::new_unchecked("balanceOf".into()),
+            MockFn::returning_ok(BalanceOfQueryResponse::from(vec![BadgeAmount::from(balance)])),
+        );
+        host.setup_mock_entrypoint(
+            BADGES,
+            OwnedEntrypointName::new_unchecked("mint".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: BadgeMintParams = from_bytes(parameter.0).expect_report("Invalid mint");
+                claim_eq!(params.badge, BADGE, "The bonus badge should be minted");
+                claim_eq!(params.holders, [Address::Account(BUYER)], "Minted to the buyer");
+                Ok((false, ()))
+            }),
+        );
+    }
+
+    fn pay(
+        host: &mut TestHost<State<TestStateApi>>,
+        amount: Amount,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&CONTENT);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(BUYER));
+        ctx.set_parameter(&parameter_bytes);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_pay(&ctx, host, amount, logger)
+    }
+
+    #[concordium_test]
+    /// Test that payments at or above the minimum are forwarded to the creator
+    /// and logged, without a badge up to the bonus threshold.
+    fn test_pay() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        let res = pay(&mut host, MINIMUM - Amount::from_micro_ccd(1), &mut logger);
+        claim_eq!(res, Err(ContractError::BelowMinimum));
+
+        pay(&mut host, MINIMUM + BONUS_THRESHOLD, &mut logger).expect_report("Paying should pass");
+        claim_eq!(host.get_transfers(), [(CREATOR, MINIMUM + BONUS_THRESHOLD)]);
+        claim_eq!(logger.logs, [to_bytes(&PaymentEvent::Payment {
+            content_id: CONTENT,
+            payer:      BUYER,
+            amount:     MINIMUM + BONUS_THRESHOLD,
+        })]);
+    }
+
+    #[concordium_test]
+    /// Test that paying above the bonus threshold mints the badge, unless the
+    /// buyer holds it already.
+    fn test_bonus_badge() {
+        let mut host = new_host();
+        let amount = MINIMUM + BONUS_THRESHOLD + Amount::from_micro_ccd(1);
+        mock_badges(&mut host, 0);
+        let mut logger = TestLogger::init();
+        pay(&mut host, amount, &mut logger).expect_report("Paying should pass");
+        claim_eq!(
+            logger.logs[1],
+            to_bytes(&PaymentEvent::Bonus {
+                content_id: CONTENT,
+                payer:      BUYER,
+            })
+        );
+
+        mock_badges(&mut host, 1);
+        let mut logger = TestLogger::init();
+        pay(&mut host, amount, &mut logger).expect_report("Paying should pass");
+        claim_eq!(logger.logs.len(), 1, "The badge is awarded once");
+    }
+}
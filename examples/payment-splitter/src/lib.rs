@@ -0,0 +1,338 @@
+//! # Implementation of a payment splitter smart contract
+//!
+//! The contract receives CCD and splits it among a fixed set of payees, set
+//! when initializing the contract, according to their shares. Every payee is
+//! entitled to the fraction of all CCD ever received by the contract given by
+//! their shares over the total shares.
+//!
+//! The CCD is not pushed to the payees when it is received. Instead, anyone
+//! can invoke `release` for a payee, which transfers the CCD the payee is
+//! entitled to, but has not been released to them yet.
+//!
+//! The amount a payee is entitled to is rounded down to whole microCCD. The
+//! remainder stays in the contract, and is released once enough CCD has been
+//! received for it to add up to whole microCCD for the payees.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The shares of a payee and the amount released to them so far.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Payee {
+    /// The number of shares held by the payee.
+    shares:   u64,
+    /// The amount released to the payee so far.
+    released: Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The sum of the shares of all payees.
+    total_shares:   u64,
+    /// The sum of the amounts released to all payees.
+    total_released: Amount,
+    /// The payees.
+    payees:         StateMap<AccountAddress, Payee, S>,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The payees with their shares.
+    #[concordium(size_length = 2)]
+    payees: Vec<(AccountAddress, u64)>,
+}
+
+/// The `return_value` type of the `view` function.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The sum of the shares of all payees.
+    total_shares:   u64,
+    /// The sum of the amounts released to all payees.
+    total_released: Amount,
+    /// The payees with their shares and released amounts.
+    payees:         Vec<(AccountAddress, Payee)>,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing without payees.
+    NoPayees,
+    /// Raised when initializing with a payee without shares.
+    ZeroShares,
+    /// Raised when initializing with the same payee twice.
+    DuplicatePayee,
+    /// Raised when initializing with shares adding up to more than `u64::MAX`.
+    SharesOverflow,
+    /// Raised when releasing to an account which is not a payee.
+    UnknownPayee,
+    /// Raised when releasing to a payee who is not entitled to any CCD.
+    NothingToRelease,
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Compute the amount which can be released to the payee, given the
+    /// current balance of the contract.
+    fn releasable(&self, payee: &Payee, balance: Amount) -> Amount {
+        // Everything received by the contract, i.e., both released and not.
+        let total_received = balance + self.total_released;
+        // The shares of the payee are part of the total shares, so the result is
+        // at most `total_received` and unwrapping is safe.
+        let entitled = total_received.mul_ratio(payee.shares, self.total_shares).unwrap_abort();
+        entitled - payee.released
+    }
+}
+
+/// Init function that sets up the payees with their shares.
+#[init(contract = "payment_splitter", parameter = "InitParameter")]
+fn payment_splitter_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(!parameter.payees.is_empty(), ContractError::NoPayees.into());
+
+    let mut state = State {
+        total_shares:   0,
+        total_released: Amount::zero(),
+        payees:         state_builder.new_map(),
+    };
+    for (account, shares) in parameter.payees {
+        ensure!(shares > 0, ContractError::ZeroShares.into());
+        state.total_shares =
+            state.total_shares.checked_add(shares).ok_or(ContractError::SharesOverflow)?;
+        state.payees.entry(account).vacant_or(ContractError::DuplicatePayee)?.insert(Payee {
+            shares,
+            released: Amount::zero(),
+        });
+    }
+    Ok(state)
+}
+
+/// Receive CCD to be split among the payees. Anyone can deposit.
+#[receive(contract = "payment_splitter", name = "deposit", payable)]
+fn payment_splitter_deposit<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Release the CCD the given payee is entitled to, but which has not been
+/// released to them yet. Anyone can release to a payee.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The account is not a payee.
+/// - The payee is not entitled to any CCD.
+#[receive(
+    contract = "payment_splitter",
+    name = "release",
+    parameter = "AccountAddress",
+    error = "ContractError",
+    mutable
+)]
+fn payment_splitter_release<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    let balance = host.self_balance();
+    let state = host.state_mut();
+    let payee = state.payees.get(&account).map(|p| *p).ok_or(ContractError::UnknownPayee)?;
+    let amount = state.releasable(&payee, balance);
+    ensure!(amount > Amount::zero(), ContractError::NothingToRelease);
+
+    state.total_released += amount;
+    if let Some(mut payee) = state.payees.get_mut(&account) {
+        payee.released += amount;
+    }
+
+    // The transfer succeeds if the payee is an existing account, since the
+    // amount released is at most the balance of the contract.
+    host.invoke_transfer(&account, amount).unwrap_abort();
+    Ok(())
+}
+
+/// View function that returns the amount which can be released to the given
+/// payee.
+#[receive(
+    contract = "payment_splitter",
+    name = "releasable",
+    parameter = "AccountAddress",
+    return_value = "Amount",
+    error = "ContractError"
+)]
+fn payment_splitter_releasable<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> Result<Amount, ContractError> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    let payee = state.payees.get(&account).map(|p| *p).ok_or(ContractError::UnknownPayee)?;
+    Ok(state.releasable(&payee, host.self_balance()))
+}
+
+/// View function that returns the payees with their shares and released
+/// amounts.
+#[receive(contract = "payment_splitter", name = "view", return_value = "ViewState")]
+fn payment_splitter_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        total_shares:   state.total_shares,
+        total_released: state.total_released,
+        payees:         state.payees.iter().map(|(account, payee)| (*account, *payee)).collect(),
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+    const DAVE: AccountAddress = AccountAddress([4; 32]);
+
+    fn initialize(payees: Vec<(AccountAddress, u64)>) -> InitResult<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            payees,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        payment_splitter_init(&ctx, &mut TestStateBuilder::new())
+    }
+
+    fn new_host(payees: Vec<(AccountAddress, u64)>) -> TestHost<State<TestStateApi>> {
+        let state = initialize(payees).expect_report("Initialization should pass");
+        TestHost::new(state, TestStateBuilder::new())
+    }
+
+    fn deposit(host: &mut TestHost<State<TestStateApi>>, micro_ccd: u64) {
+        host.set_self_balance(host.self_balance() + Amount::from_micro_ccd(micro_ccd));
+    }
+
+    fn release(
+        host: &mut TestHost<State<TestStateApi>>,
+        account: AccountAddress,
+    ) -> Result<(), ContractError> {
+        let parameter_bytes = to_bytes(&account);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        payment_splitter_release(&ctx, host)
+    }
+
+    fn releasable(
+        host: &TestHost<State<TestStateApi>>,
+        account: AccountAddress,
+    ) -> Result<Amount, ContractError> {
+        let parameter_bytes = to_bytes(&account);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        payment_splitter_releasable(&ctx, host)
+    }
+
+    #[concordium_test]
+    /// Test that initialization rejects no payees, zero shares, duplicate
+    /// payees and overflowing shares.
+    fn test_init_invalid_payees() {
+        claim_eq!(initialize(vec![]).map(|_| ()), Err(ContractError::NoPayees.into()));
+        claim_eq!(
+            initialize(vec![(ALICE, 1), (BOB, 0)]).map(|_| ()),
+            Err(ContractError::ZeroShares.into())
+        );
+        claim_eq!(
+            initialize(vec![(ALICE, 1), (BOB, 2), (ALICE, 3)]).map(|_| ()),
+            Err(ContractError::DuplicatePayee.into())
+        );
+        claim_eq!(
+            initialize(vec![(ALICE, u64::MAX), (BOB, 1)]).map(|_| ()),
+            Err(ContractError::SharesOverflow.into())
+        );
+    }
+
+    #[concordium_test]
+    /// Test that deposits are split according to the shares, also across
+    /// several deposits and releases.
+    fn test_weighted_split() {
+        let mut host = new_host(vec![(ALICE, 1), (BOB, 2), (CAROL, 3)]);
+        deposit(&mut host, 600);
+
+        claim_eq!(releasable(&host, ALICE), Ok(Amount::from_micro_ccd(100)));
+        claim_eq!(releasable(&host, BOB), Ok(Amount::from_micro_ccd(200)));
+        claim_eq!(releasable(&host, CAROL), Ok(Amount::from_micro_ccd(300)));
+
+        release(&mut host, BOB).expect_report("Releasing should pass");
+        deposit(&mut host, 1_200);
+        claim_eq!(
+            releasable(&host, ALICE),
+            Ok(Amount::from_micro_ccd(300)),
+            "Releasing to a payee does not change the amounts of the others"
+        );
+        claim_eq!(releasable(&host, BOB), Ok(Amount::from_micro_ccd(400)));
+
+        release(&mut host, ALICE).expect_report("Releasing should pass");
+        release(&mut host, BOB).expect_report("Releasing should pass");
+        release(&mut host, CAROL).expect_report("Releasing should pass");
+        claim_eq!(host.get_transfers(), [
+            (BOB, Amount::from_micro_ccd(200)),
+            (ALICE, Amount::from_micro_ccd(300)),
+            (BOB, Amount::from_micro_ccd(400)),
+            (CAROL, Amount::from_micro_ccd(900)),
+        ]);
+        claim_eq!(host.state().total_released, Amount::from_micro_ccd(1_800));
+        claim_eq!(host.self_balance(), Amount::zero(), "Everything should be released");
+    }
+
+    #[concordium_test]
+    /// Test that the remainder of the division stays in the contract and is
+    /// released once it adds up to whole microCCD.
+    fn test_remainder() {
+        let mut host = new_host(vec![(ALICE, 1), (BOB, 1), (CAROL, 1)]);
+        deposit(&mut host, 100);
+        for payee in [ALICE, BOB, CAROL] {
+            release(&mut host, payee).expect_report("Releasing should pass");
+        }
+        claim_eq!(host.self_balance(), Amount::from_micro_ccd(1), "The remainder should stay");
+        claim_eq!(release(&mut host, ALICE), Err(ContractError::NothingToRelease));
+
+        deposit(&mut host, 2);
+        for payee in [ALICE, BOB, CAROL] {
+            claim_eq!(releasable(&host, payee), Ok(Amount::from_micro_ccd(1)));
+            release(&mut host, payee).expect_report("Releasing should pass");
+        }
+        claim_eq!(host.self_balance(), Amount::zero(), "The remainder should be released");
+        claim_eq!(host.state().total_released, Amount::from_micro_ccd(102));
+    }
+
+    #[concordium_test]
+    /// Test that the split does not overflow with large amounts and shares.
+    fn test_large_values() {
+        let mut host = new_host(vec![(ALICE, u64::MAX - 1), (BOB, 1)]);
+        deposit(&mut host, u64::MAX);
+        claim_eq!(releasable(&host, ALICE), Ok(Amount::from_micro_ccd(u64::MAX - 1)));
+        claim_eq!(releasable(&host, BOB), Ok(Amount::from_micro_ccd(1)));
+    }
+
+    #[concordium_test]
+    /// Test that releasing to an unknown payee or without funds is rejected.
+    fn test_release_rejected() {
+        let mut host = new_host(vec![(ALICE, 1), (BOB, 1)]);
+        claim_eq!(release(&mut host, DAVE), Err(ContractError::UnknownPayee));
+        claim_eq!(releasable(&host, DAVE), Err(ContractError::UnknownPayee));
+        claim_eq!(release(&mut host, ALICE), Err(ContractError::NothingToRelease));
+        claim!(host.get_transfers().is_empty());
+    }
+}
@@ -0,0 +1,533 @@
+//! A continuous payment streaming smart contract example.
+//!
+//! # Description
+//! A payer opens a stream to a recipient by depositing CCD, which is streamed
+//! to the recipient at a fixed rate per second from a start time, until the
+//! deposit is exhausted. The recipient can withdraw the CCD streamed so far at
+//! any time.
+//!
+//! Either party can cancel a stream, settling it pro rata: the recipient
+//! receives the CCD streamed until the cancellation which has not been
+//! withdrawn, and the payer receives the rest of the deposit.
+//!
+//! To avoid rounding, the deposit must be a whole number of seconds of
+//! streaming, that is, a multiple of the rate. The amount streamed at any time
+//! is then exactly the rate times the number of whole seconds elapsed.
+//!
+//! # Operations
+//! The contract allows for
+//!  - `createStream`, which opens a stream from the sender with the CCD sent
+//!    along as the deposit;
+//!  - `withdraw`, which transfers the CCD streamed but not yet withdrawn to the
+//!    recipient;
+//!  - `cancel`, which settles and closes the stream;
+//!  - `balanceOf`, which returns the CCD of a stream available to the recipient
+//!    and the payer at the current time.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The number of milliseconds in a second.
+const MILLIS_PER_SECOND: u64 = 1000;
+
+/// The identifier of a stream.
+pub type StreamId = u64;
+
+/// A stream of CCD from a payer to a recipient.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Stream {
+    /// The account paying into the stream.
+    pub payer:     AccountAddress,
+    /// The account receiving the stream.
+    pub recipient: AccountAddress,
+    /// The CCD deposited by the payer.
+    pub deposit:   Amount,
+    /// The CCD streamed per second.
+    pub rate:      Amount,
+    /// The time the stream starts.
+    pub start:     Timestamp,
+    /// The CCD the recipient has withdrawn so far.
+    pub withdrawn: Amount,
+}
+
+impl Stream {
+    /// Compute the CCD streamed at the given time, counting whole seconds
+    /// since the start only.
+    fn streamed_at(&self, now: Timestamp) -> Amount {
+        let elapsed_seconds = now.timestamp_millis().saturating_sub(self.start.timestamp_millis())
+            / MILLIS_PER_SECOND;
+        // The deposit is a multiple of the rate, so the stream ends after this
+        // many seconds.
+        let duration_seconds = self.deposit.micro_ccd / self.rate.micro_ccd;
+        // The product is at most the deposit, so it does not overflow.
+        Amount::from_micro_ccd(self.rate.micro_ccd * elapsed_seconds.min(duration_seconds))
+    }
+
+    /// Compute the CCD the recipient can withdraw at the given time.
+    fn withdrawable_at(&self, now: Timestamp) -> Amount { self.streamed_at(now) - self.withdrawn }
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The identifier of the next stream.
+    next_stream_id: StreamId,
+    /// The open streams.
+    streams:        StateMap<StreamId, Stream, S>,
+}
+
+/// The parameter type for the contract function `createStream`.
+#[derive(Serialize, SchemaType)]
+pub struct CreateStreamParameter {
+    /// The account receiving the stream.
+    pub recipient: AccountAddress,
+    /// The CCD streamed per second.
+    pub rate:      Amount,
+    /// The time the stream starts. Streams cannot start in the past.
+    pub start:     Timestamp,
+}
+
+/// The return type of the contract function `balanceOf`.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct StreamBalance {
+    /// The CCD the recipient can withdraw.
+    pub recipient: Amount,
+    /// The CCD which would be returned to the payer on cancellation.
+    pub payer:     Amount,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum StreamEvent {
+    /// A stream was created.
+    Create {
+        stream_id: StreamId,
+        payer:     AccountAddress,
+        recipient: AccountAddress,
+        deposit:   Amount,
+        rate:      Amount,
+        start:     Timestamp,
+    },
+    /// The recipient withdrew CCD from a stream.
+    Withdraw {
+        stream_id: StreamId,
+        amount:    Amount,
+    },
+    /// A stream was cancelled, and settled with the given amounts.
+    Cancel {
+        stream_id:        StreamId,
+        recipient_amount: Amount,
+        payer_amount:     Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract creates a stream; Only accounts can pay.
+    OnlyAccount,
+    /// Raised when the rate is zero, or the deposit is zero or not a multiple
+    /// of the rate.
+    InvalidDeposit,
+    /// Raised when creating a stream starting in the past.
+    StartInPast,
+    /// Raised when the stream does not exist.
+    UnknownStream,
+    /// Raised when someone else than the recipient withdraws, or someone else
+    /// than the payer or recipient cancels.
+    Unauthorized,
+    /// Raised when withdrawing while nothing is left to withdraw.
+    NothingToWithdraw,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+// Contract functions
+
+/// Initialize the contract with no streams.
+#[init(contract = "payment_stream", event = "StreamEvent")]
+fn init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        next_stream_id: 0,
+        streams:        state_builder.new_map(),
+    })
+}
+
+/// Open a stream from the sender, with the CCD sent along as the deposit.
+/// Returns the identifier of the stream.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The rate is zero, or the deposit is zero or not a multiple of the rate.
+/// - The stream starts in the past.
+/// - It fails to log the event.
+#[receive(
+    contract = "payment_stream",
+    name = "createStream",
+    parameter = "CreateStreamParameter",
+    return_value = "StreamId",
+    error = "ContractError",
+    payable,
+    enable_logger,
+    mutable
+)]
+fn create_stream<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<StreamId> {
+    let param: CreateStreamParameter = ctx.parameter_cursor().get()?;
+    let payer = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    ensure!(
+        param.rate > Amount::zero()
+            && amount > Amount::zero()
+            && amount.micro_ccd % param.rate.micro_ccd == 0,
+        ContractError::InvalidDeposit
+    );
+    ensure!(param.start >= ctx.metadata().slot_time(), ContractError::StartInPast);
+
+    let state = host.state_mut();
+    let stream_id = state.next_stream_id;
+    state.next_stream_id += 1;
+    state.streams.insert(stream_id, Stream {
+        payer,
+        recipient: param.recipient,
+        deposit: amount,
+        rate: param.rate,
+        start: param.start,
+        withdrawn: Amount::zero(),
+    });
+    logger.log(&StreamEvent::Create {
+        stream_id,
+        payer,
+        recipient: param.recipient,
+        deposit: amount,
+        rate: param.rate,
+        start: param.start,
+    })?;
+    Ok(stream_id)
+}
+
+/// Transfer the CCD streamed but not yet withdrawn to the recipient. Can only
+/// be called by the recipient. The stream is closed once fully withdrawn.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The stream does not exist.
+/// - The sender is not the recipient.
+/// - Nothing is left to withdraw.
+/// - It fails to log the event.
+#[receive(
+    contract = "payment_stream",
+    name = "withdraw",
+    parameter = "StreamId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let stream_id: StreamId = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let (recipient, amount, finished) = {
+        let mut stream = state.streams.get_mut(&stream_id).ok_or(ContractError::UnknownStream)?;
+        ensure!(ctx.sender().matches_account(&stream.recipient), ContractError::Unauthorized);
+        let amount = stream.withdrawable_at(ctx.metadata().slot_time());
+        ensure!(amount > Amount::zero(), ContractError::NothingToWithdraw);
+        stream.withdrawn += amount;
+        (stream.recipient, amount, stream.withdrawn == stream.deposit)
+    };
+    if finished {
+        state.streams.remove(&stream_id);
+    }
+    logger.log(&StreamEvent::Withdraw {
+        stream_id,
+        amount,
+    })?;
+    // The transfer always succeeds, since the recipient is the sender and the
+    // contract holds the deposit.
+    host.invoke_transfer(&recipient, amount).unwrap_abort();
+    Ok(())
+}
+
+/// Cancel a stream, transferring the CCD streamed but not yet withdrawn to the
+/// recipient, and the rest of the deposit to the payer. Can be called by both
+/// the payer and the recipient.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The stream does not exist.
+/// - The sender is neither the payer nor the recipient.
+/// - It fails to log the event.
+#[receive(
+    contract = "payment_stream",
+    name = "cancel",
+    parameter = "StreamId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn cancel<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let stream_id: StreamId = ctx.parameter_cursor().get()?;
+    let stream = host.state().streams.get(&stream_id).map(|s| *s);
+    let stream = stream.ok_or(ContractError::UnknownStream)?;
+    let sender = ctx.sender();
+    ensure!(
+        sender.matches_account(&stream.payer) || sender.matches_account(&stream.recipient),
+        ContractError::Unauthorized
+    );
+    host.state_mut().streams.remove(&stream_id);
+
+    let now = ctx.metadata().slot_time();
+    let recipient_amount = stream.withdrawable_at(now);
+    let payer_amount = stream.deposit - stream.streamed_at(now);
+    logger.log(&StreamEvent::Cancel {
+        stream_id,
+        recipient_amount,
+        payer_amount,
+    })?;
+    // The transfers always succeed, since the payer and recipient are existing
+    // accounts, and the contract holds the part of the deposit not withdrawn.
+    if recipient_amount > Amount::zero() {
+        host.invoke_transfer(&stream.recipient, recipient_amount).unwrap_abort();
+    }
+    if payer_amount > Amount::zero() {
+        host.invoke_transfer(&stream.payer, payer_amount).unwrap_abort();
+    }
+    Ok(())
+}
+
+/// View the CCD of a stream available to the recipient and payer at the
+/// current time.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The stream does not exist.
+#[receive(
+    contract = "payment_stream",
+    name = "balanceOf",
+    parameter = "StreamId",
+    return_value = "StreamBalance",
+    error = "ContractError"
+)]
+fn balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<StreamBalance> {
+    let stream_id: StreamId = ctx.parameter_cursor().get()?;
+    let stream = host.state().streams.get(&stream_id).ok_or(ContractError::UnknownStream)?;
+    let now = ctx.metadata().slot_time();
+    Ok(StreamBalance {
+        recipient: stream.withdrawable_at(now),
+        payer:     stream.deposit - stream.streamed_at(now),
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const PAYER: AccountAddress = AccountAddress([0; 32]);
+    const RECIPIENT: AccountAddress = AccountAddress([1; 32]);
+    const OTHER: AccountAddress = AccountAddress([2; 32]);
+    const RATE: Amount = Amount {
+        micro_ccd: 3,
+    };
+    const DEPOSIT: Amount = Amount {
+        micro_ccd: 30,
+    };
+    const START: u64 = 10_000;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let ctx = TestInitContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn create_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        slot_time: u64,
+        deposit: Amount,
+    ) -> ContractResult<StreamId> {
+        let parameter_bytes = to_bytes(&CreateStreamParameter {
+            recipient: RECIPIENT,
+            rate:      RATE,
+            start:     Timestamp::from_timestamp_millis(START),
+        });
+        host.set_self_balance(host.self_balance() + deposit);
+        let mut logger = TestLogger::init();
+        create_stream(
+            &ctx_with_parameter(PAYER, slot_time, &parameter_bytes),
+            host,
+            deposit,
+            &mut logger,
+        )
+    }
+
+    fn balance_at(host: &TestHost<State<TestStateApi>>, slot_time: u64) -> StreamBalance {
+        let parameter_bytes = to_bytes(&0u64);
+        balance_of(&ctx_with_parameter(OTHER, slot_time, &parameter_bytes), host)
+            .expect_report("Querying the balance should pass")
+    }
+
+    #[concordium_test]
+    /// Test that the deposit must be a whole number of seconds of streaming,
+    /// and that streams cannot start in the past.
+    fn test_create_stream_rejected() {
+        let mut host = new_host();
+        let res = create_at(&mut host, 0, Amount::from_micro_ccd(31));
+        claim_eq!(res, Err(ContractError::InvalidDeposit), "The deposit must be a multiple");
+        let res = create_at(&mut host, 0, Amount::zero());
+        claim_eq!(res, Err(ContractError::InvalidDeposit), "The deposit cannot be zero");
+        let res = create_at(&mut host, START + 1, DEPOSIT);
+        claim_eq!(res, Err(ContractError::StartInPast));
+        claim_eq!(create_at(&mut host, START, DEPOSIT), Ok(0));
+    }
+
+    #[concordium_test]
+    /// Test that the CCD streams per whole second, and stops when the deposit
+    /// is exhausted.
+    fn test_streamed_amount() {
+        let mut host = new_host();
+        create_at(&mut host, 0, DEPOSIT).expect_report("Creating should pass");
+
+        claim_eq!(balance_at(&host, 0), StreamBalance {
+            recipient: Amount::zero(),
+            payer:     DEPOSIT,
+        });
+        claim_eq!(balance_at(&host, START + 1_999).recipient, Amount::from_micro_ccd(3));
+        claim_eq!(balance_at(&host, START + 2_000).recipient, Amount::from_micro_ccd(6));
+        claim_eq!(balance_at(&host, START + 10_000), StreamBalance {
+            recipient: DEPOSIT,
+            payer:     Amount::zero(),
+        });
+        claim_eq!(balance_at(&host, START + 20_000).recipient, DEPOSIT);
+    }
+
+    #[concordium_test]
+    /// Test that the recipient can withdraw what has streamed, and that the
+    /// stream is closed once fully withdrawn.
+    fn test_withdraw() {
+        let mut host = new_host();
+        create_at(&mut host, 0, DEPOSIT).expect_report("Creating should pass");
+        let parameter_bytes = to_bytes(&0u64);
+        let mut logger = TestLogger::init();
+
+        let res = withdraw(
+            &ctx_with_parameter(RECIPIENT, START + 999, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::NothingToWithdraw));
+        let res = withdraw(
+            &ctx_with_parameter(PAYER, START + 4_000, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the recipient can withdraw");
+        withdraw(
+            &ctx_with_parameter(RECIPIENT, START + 4_000, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        )
+        .expect_report("Withdrawing should pass");
+        withdraw(
+            &ctx_with_parameter(RECIPIENT, START + 60_000, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        )
+        .expect_report("Withdrawing the rest should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (RECIPIENT, Amount::from_micro_ccd(12)),
+            (RECIPIENT, Amount::from_micro_ccd(18)),
+        ]);
+        let res = withdraw(
+            &ctx_with_parameter(RECIPIENT, START + 60_000, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::UnknownStream), "The stream should be closed");
+    }
+
+    #[concordium_test]
+    /// Test that cancelling settles the stream pro rata, taking previous
+    /// withdrawals into account.
+    fn test_cancel() {
+        let mut host = new_host();
+        create_at(&mut host, 0, DEPOSIT).expect_report("Creating should pass");
+        let parameter_bytes = to_bytes(&0u64);
+        let mut logger = TestLogger::init();
+
+        withdraw(
+            &ctx_with_parameter(RECIPIENT, START + 2_000, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        )
+        .expect_report("Withdrawing should pass");
+        let res = cancel(
+            &ctx_with_parameter(OTHER, START + 5_500, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the parties can cancel");
+        cancel(&ctx_with_parameter(PAYER, START + 5_500, &parameter_bytes), &mut host, &mut logger)
+            .expect_report("Cancelling should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (RECIPIENT, Amount::from_micro_ccd(6)),
+            (RECIPIENT, Amount::from_micro_ccd(9)),
+            (PAYER, Amount::from_micro_ccd(15)),
+        ]);
+        claim_eq!(
+            logger.logs.last(),
+            Some(&to_bytes(&StreamEvent::Cancel {
+                stream_id:        0,
+                recipient_amount: Amount::from_micro_ccd(9),
+                payer_amount:     Amount::from_micro_ccd(15),
+            }))
+        );
+        let res = cancel(
+            &ctx_with_parameter(RECIPIENT, START + 5_500, &parameter_bytes),
+            &mut host,
+            &mut logger,
+        );
+        claim_eq!(res, Err(ContractError::UnknownStream), "The stream should be closed");
+    }
+}
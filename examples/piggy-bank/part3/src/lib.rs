@@ -0,0 +1,340 @@
+//! Piggy bank smart contract with interest.
+//!
+//! Extends the piggy bank from part 1 and 2: anyone can insert CCD, and the
+//! savings grow with interest over time, at a yearly rate set when the piggy
+//! bank is set up. The owner can withdraw part of the savings at any time, or
+//! smash the piggy bank to retrieve everything.
+//!
+//! The interest is simulated: it is added to the savings as bookkeeping, but
+//! no CCD is created. For the interest to be withdrawn, it must be backed by
+//! CCD which someone has inserted using `fund`, which, unlike `insert`, does
+//! not count towards the savings.
+//!
+//! This smart contract module is developed as part of the
+//! [Piggy Bank Tutorial](https://developer.concordium.software/en/mainnet/smart-contracts/tutorials/piggy-bank).
+//!
+//! Covers:
+//! - Time-dependent state, using the slot time from the context.
+//! - Fixed-point arithmetic using basis points.
+//! - Parameters of init and receive functions.
+//! - Unit testing with different slot times.
+
+// Pulling in everything from the smart contract standard library.
+use concordium_std::*;
+
+/// The number of milliseconds in a year of 365 days.
+const MILLIS_PER_YEAR: u64 = 365 * 24 * 60 * 60 * 1000;
+
+/// The number of basis points in 100%.
+const BASIS_POINTS: u64 = 10_000;
+
+/// The state of the piggy bank.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq, Clone, Copy)]
+pub struct PiggyBankState {
+    /// Whether the piggy bank has been smashed, preventing further CCD to be
+    /// inserted.
+    smashed:           bool,
+    /// The yearly interest rate in basis points, i.e. hundredths of a percent.
+    rate_basis_points: u32,
+    /// The CCD inserted and the interest accrued until `last_accrual`, minus
+    /// the CCD withdrawn.
+    savings:           Amount,
+    /// The time the interest was last added to the savings.
+    last_accrual:      Timestamp,
+}
+
+impl PiggyBankState {
+    /// Compute the savings at the given time, including the interest accrued
+    /// since the last accrual. The interest is simple within the period since
+    /// the last accrual, and compounds every time it is added to the savings.
+    fn savings_at(&self, now: Timestamp) -> Amount {
+        let elapsed = now.timestamp_millis().saturating_sub(self.last_accrual.timestamp_millis());
+        // The interest for a whole year, where the rate can be above 100%.
+        let yearly = self.savings.mul_ratio(u64::from(self.rate_basis_points), BASIS_POINTS);
+        let interest = yearly.and_then(|yearly| yearly.mul_ratio(elapsed, MILLIS_PER_YEAR));
+        // Interest beyond the total supply of CCD is not meaningful, so it is
+        // capped rather than overflowing.
+        let interest = interest.unwrap_or(Amount::from_micro_ccd(u64::MAX));
+        Amount::from_micro_ccd(self.savings.micro_ccd.saturating_add(interest.micro_ccd))
+    }
+
+    /// Add the interest accrued until the given time to the savings.
+    fn accrue(&mut self, now: Timestamp) {
+        self.savings = self.savings_at(now);
+        self.last_accrual = now;
+    }
+}
+
+/// Setup a new intact piggy bank with the yearly interest rate, given in basis
+/// points, as the parameter.
+#[init(contract = "PiggyBankInterest", parameter = "u32")]
+fn piggy_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+) -> InitResult<PiggyBankState> {
+    let rate_basis_points: u32 = ctx.parameter_cursor().get()?;
+    Ok(PiggyBankState {
+        smashed: false,
+        rate_basis_points,
+        savings: Amount::zero(),
+        last_accrual: ctx.metadata().slot_time(),
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Reject, Serial, SchemaType)]
+enum PiggyBankError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Someone else than the owner withdrawing or smashing.
+    NotOwner,
+    /// The piggy bank has been smashed already.
+    AlreadySmashed,
+    /// Withdrawing more than the savings.
+    InsufficientSavings,
+    /// Withdrawing interest which is not backed by CCD.
+    InsufficientFunds,
+    TransferError, // If this occurs, there is a bug in the contract.
+}
+
+/// Insert some CCD into the savings of a piggy bank, allowed by anyone.
+#[receive(
+    contract = "PiggyBankInterest",
+    name = "insert",
+    payable,
+    mutable,
+    error = "PiggyBankError"
+)]
+fn piggy_insert<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<PiggyBankState, StateApiType = S>,
+    amount: Amount,
+) -> Result<(), PiggyBankError> {
+    let state = host.state_mut();
+    // Ensure the piggy bank has not been smashed already.
+    ensure!(!state.smashed, PiggyBankError::AlreadySmashed);
+    // The interest until now is computed on the savings before the insertion.
+    state.accrue(ctx.metadata().slot_time());
+    state.savings += amount;
+    Ok(())
+}
+
+/// Insert some CCD backing the interest, allowed by anyone. The CCD does not
+/// count towards the savings.
+#[receive(contract = "PiggyBankInterest", name = "fund", payable, error = "PiggyBankError")]
+fn piggy_fund<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<PiggyBankState, StateApiType = S>,
+    _amount: Amount,
+) -> Result<(), PiggyBankError> {
+    // Ensure the piggy bank has not been smashed already.
+    ensure!(!host.state().smashed, PiggyBankError::AlreadySmashed);
+    Ok(())
+}
+
+/// Withdraw part of the savings, only allowed by the owner.
+#[receive(
+    contract = "PiggyBankInterest",
+    name = "withdraw",
+    parameter = "Amount",
+    mutable,
+    error = "PiggyBankError"
+)]
+fn piggy_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<PiggyBankState, StateApiType = S>,
+) -> Result<(), PiggyBankError> {
+    let amount: Amount = ctx.parameter_cursor().get()?;
+    let owner = ctx.owner();
+    // Ensure only the owner can withdraw.
+    ensure!(ctx.sender().matches_account(&owner), PiggyBankError::NotOwner);
+    // Ensure the interest withdrawn is backed by CCD.
+    ensure!(amount <= host.self_balance(), PiggyBankError::InsufficientFunds);
+
+    let state = host.state_mut();
+    ensure!(!state.smashed, PiggyBankError::AlreadySmashed);
+    state.accrue(ctx.metadata().slot_time());
+    ensure!(amount <= state.savings, PiggyBankError::InsufficientSavings);
+    state.savings -= amount;
+
+    // The transfer can never fail, since the owner is known to exist, and the
+    // contract has sufficient balance.
+    let transfer_result = host.invoke_transfer(&owner, amount);
+    ensure!(transfer_result.is_ok(), PiggyBankError::TransferError);
+    Ok(())
+}
+
+/// Smash a piggy bank retrieving all the CCD, only allowed by the owner.
+#[receive(contract = "PiggyBankInterest", name = "smash", mutable, error = "PiggyBankError")]
+fn piggy_smash<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<PiggyBankState, StateApiType = S>,
+) -> Result<(), PiggyBankError> {
+    let owner = ctx.owner();
+    // Ensure only the owner can smash the piggy bank.
+    ensure!(ctx.sender().matches_account(&owner), PiggyBankError::NotOwner);
+
+    let state = host.state_mut();
+    ensure!(!state.smashed, PiggyBankError::AlreadySmashed);
+    state.smashed = true;
+    state.savings = Amount::zero();
+    state.last_accrual = ctx.metadata().slot_time();
+
+    // Transfer the whole balance, including CCD inserted using `fund`, to the
+    // contract owner.
+    let balance = host.self_balance();
+    let transfer_result = host.invoke_transfer(&owner, balance);
+    ensure!(transfer_result.is_ok(), PiggyBankError::TransferError);
+    Ok(())
+}
+
+/// View the state of the piggy bank, the savings including the interest
+/// accrued until now, and the balance.
+#[receive(
+    contract = "PiggyBankInterest",
+    name = "view",
+    return_value = "(PiggyBankState, Amount, Amount)"
+)]
+fn piggy_view<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<PiggyBankState, StateApiType = S>,
+) -> ReceiveResult<(PiggyBankState, Amount, Amount)> {
+    let state = *host.state();
+    let savings = state.savings_at(ctx.metadata().slot_time());
+    Ok((state, savings, host.self_balance()))
+}
+
+// Unit tests for the smart contract "PiggyBankInterest"
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    // Pulling in the testing utils found in concordium_std.
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0u8; 32]);
+    const YEAR: u64 = MILLIS_PER_YEAR;
+
+    // Setup an intact piggy bank with a 5% yearly interest rate, created at
+    // time zero.
+    fn setup_host() -> TestHost<PiggyBankState> {
+        let parameter_bytes = to_bytes(&500u32);
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        let state = piggy_init(&ctx, &mut TestStateBuilder::new())
+            .expect_report("Contract initialization results in error.");
+        TestHost::new(state, TestStateBuilder::new())
+    }
+
+    // Setup a context with the owner as the sender at the given time.
+    fn owner_ctx<'a>(slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(OWNER);
+        ctx.set_sender(Address::Account(OWNER));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx
+    }
+
+    // Insert CCD into the savings at the given time.
+    fn insert_at(host: &mut TestHost<PiggyBankState>, slot_time: u64, amount: Amount) {
+        host.set_self_balance(host.self_balance() + amount);
+        piggy_insert(&owner_ctx(slot_time), host, amount)
+            .expect_report("Inserting CCD results in error.");
+    }
+
+    // Withdraw CCD from the savings at the given time.
+    fn withdraw_at(
+        host: &mut TestHost<PiggyBankState>,
+        slot_time: u64,
+        amount: Amount,
+    ) -> Result<(), PiggyBankError> {
+        let parameter_bytes = to_bytes(&amount);
+        let mut ctx = owner_ctx(slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        piggy_withdraw(&ctx, host)
+    }
+
+    // Get the savings at the given time.
+    fn savings_at(host: &TestHost<PiggyBankState>, slot_time: u64) -> Amount {
+        let (_, savings, _) =
+            piggy_view(&owner_ctx(slot_time), host).expect_report("Viewing results in error.");
+        savings
+    }
+
+    #[concordium_test]
+    fn test_interest_accrual() {
+        // Setup
+        let mut host = setup_host();
+        insert_at(&mut host, 0, Amount::from_micro_ccd(1_000_000));
+
+        // The interest grows linearly until it is added to the savings.
+        claim_eq!(savings_at(&host, YEAR / 2), Amount::from_micro_ccd(1_025_000));
+        claim_eq!(savings_at(&host, YEAR), Amount::from_micro_ccd(1_050_000));
+
+        // Inserting adds the interest until now to the savings, after which it
+        // compounds.
+        insert_at(&mut host, YEAR, Amount::from_micro_ccd(950_000));
+        claim_eq!(savings_at(&host, YEAR), Amount::from_micro_ccd(2_000_000));
+        claim_eq!(savings_at(&host, 2 * YEAR), Amount::from_micro_ccd(2_100_000));
+    }
+
+    #[concordium_test]
+    fn test_withdraw_partially() {
+        // Setup
+        let mut host = setup_host();
+        insert_at(&mut host, 0, Amount::from_micro_ccd(1_000_000));
+
+        // Withdraw part of the savings.
+        withdraw_at(&mut host, YEAR, Amount::from_micro_ccd(400_000))
+            .expect_report("Withdrawing results in error.");
+        claim_eq!(host.get_transfers(), [(OWNER, Amount::from_micro_ccd(400_000))]);
+        claim_eq!(savings_at(&host, YEAR), Amount::from_micro_ccd(650_000));
+
+        // The interest is only withdrawable once backed by CCD.
+        let result = withdraw_at(&mut host, YEAR, Amount::from_micro_ccd(650_000));
+        claim_eq!(result, Err(PiggyBankError::InsufficientFunds));
+        host.set_self_balance(host.self_balance() + Amount::from_micro_ccd(100_000));
+        piggy_fund(&owner_ctx(YEAR), &host, Amount::from_micro_ccd(100_000))
+            .expect_report("Funding results in error.");
+        let result = withdraw_at(&mut host, YEAR, Amount::from_micro_ccd(650_001));
+        claim_eq!(result, Err(PiggyBankError::InsufficientSavings));
+        withdraw_at(&mut host, YEAR, Amount::from_micro_ccd(650_000))
+            .expect_report("Withdrawing results in error.");
+        claim_eq!(savings_at(&host, 2 * YEAR), Amount::zero());
+    }
+
+    #[concordium_test]
+    fn test_withdraw_not_owner() {
+        // Setup
+        let mut host = setup_host();
+        insert_at(&mut host, 0, Amount::from_micro_ccd(100));
+        let parameter_bytes = to_bytes(&Amount::from_micro_ccd(100));
+        let mut ctx = owner_ctx(0);
+        ctx.set_sender(Address::Account(AccountAddress([1u8; 32])));
+        ctx.set_parameter(&parameter_bytes);
+
+        // Trigger the withdrawal
+        let result = piggy_withdraw(&ctx, &mut host);
+
+        claim_eq!(result, Err(PiggyBankError::NotOwner), "Expected to fail with error NotOwner.");
+    }
+
+    #[concordium_test]
+    fn test_smash() {
+        // Setup
+        let mut host = setup_host();
+        insert_at(&mut host, 0, Amount::from_micro_ccd(100));
+        host.set_self_balance(host.self_balance() + Amount::from_micro_ccd(20));
+
+        // Trigger the smash
+        piggy_smash(&owner_ctx(YEAR), &mut host).expect_report("Smashing results in error.");
+
+        // Everything, including the funding, goes to the owner.
+        claim_eq!(host.get_transfers(), [(OWNER, Amount::from_micro_ccd(120))]);
+        claim!(host.state().smashed, "Piggy bank should be smashed.");
+        let result = piggy_insert(&owner_ctx(YEAR), &mut host, Amount::from_micro_ccd(1));
+        claim_eq!(result, Err(PiggyBankError::AlreadySmashed));
+        let result = withdraw_at(&mut host, YEAR, Amount::zero());
+        claim_eq!(result, Err(PiggyBankError::AlreadySmashed));
+    }
+}
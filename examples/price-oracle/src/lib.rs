@@ -0,0 +1,716 @@
+//! # A price oracle aggregating the reports of several reporters
+//!
+//! This module contains two contracts: a price oracle, and a consumer which
+//! demonstrates how other contracts read the price from the oracle.
+//!
+//! ## The Price Oracle Contract
+//!
+//! The owner of the oracle maintains a list of whitelisted reporters, each
+//! with the public key they sign their observations with, using
+//! `addReporter` and `removeReporter`.
+//!
+//! A reporter observes a price at a point in time, and signs the observation.
+//! Anyone, e.g. the reporter or a relayer, can then submit the signed
+//! observation using `submit`. The signed message is the address of the oracle
+//! instance followed by the serialized observation, such that observations
+//! cannot be replayed on other oracles. Only the latest observation of every
+//! reporter is kept, and an observation must be newer than the previous one
+//! of the reporter and must not be from the future.
+//!
+//! Consumers read the price using `price`, which aggregates the reports which
+//! are recent enough, i.e. are at most `freshness` old:
+//!  - It computes the median of the fresh reports.
+//!  - It discards the reports deviating more than `max_deviation` basis points
+//!    from the median as outliers.
+//!  - If at least `min_reports` reports remain, it returns the median of the
+//!    remaining reports. Otherwise it rejects, since there is no agreement on
+//!    the price.
+//!
+//! The owner configures the freshness window, the outlier tolerance and the
+//! minimum number of reports using `updateConfig`.
+//!
+//! ## The Oracle Consumer Contract
+//!
+//! The consumer is initialized with the address of an oracle. Its `update`
+//! function reads the price from the oracle and stores it, and rejects if the
+//! oracle has no price. The stored price can be viewed using `view`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The outlier tolerance can be at most 100%, i.e. 10000 basis points.
+const MAX_BPS: u16 = 10000;
+
+/// A price observed by a reporter at a point in time.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Observation {
+    /// The observed price, in the unit agreed on by the reporters.
+    pub price:     u64,
+    /// The time of the observation.
+    pub timestamp: Timestamp,
+}
+
+/// The configuration of the aggregation of reports.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// How old a report can be and still be used.
+    pub freshness:     Duration,
+    /// How many basis points a report can deviate from the median of the fresh
+    /// reports without being discarded as an outlier.
+    pub max_deviation: u16,
+    /// The minimum number of fresh reports which are not outliers.
+    pub min_reports:   u32,
+}
+
+/// The state of the price oracle.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The account which manages the reporters and the configuration.
+    owner:     AccountAddress,
+    /// The configuration of the aggregation of reports.
+    config:    Config,
+    /// The public keys of the whitelisted reporters.
+    reporters: StateMap<AccountAddress, PublicKeyEd25519, S>,
+    /// The latest observation of every reporter.
+    reports:   StateMap<AccountAddress, Observation, S>,
+}
+
+/// The parameter type for the oracle function `addReporter`.
+#[derive(Serialize, SchemaType)]
+pub struct AddReporterParams {
+    /// The reporter.
+    pub reporter:   AccountAddress,
+    /// The public key the reporter signs observations with.
+    pub public_key: PublicKeyEd25519,
+}
+
+/// The parameter type for the oracle function `submit`.
+#[derive(Serialize, SchemaType)]
+pub struct SubmitParams {
+    /// The reporter who signed the observation.
+    pub reporter:    AccountAddress,
+    /// The observation.
+    pub observation: Observation,
+    /// The signature of the reporter on the observation.
+    pub signature:   SignatureEd25519,
+}
+
+/// The return type of the oracle function `price`.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct PriceView {
+    /// The median of the fresh reports which are not outliers.
+    pub price:   u64,
+    /// The number of reports the price is the median of.
+    pub reports: u32,
+    /// The time of the oldest of these reports.
+    pub oldest:  Timestamp,
+}
+
+/// The events logged by the price oracle.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum OracleEvent {
+    /// A reporter was added to the whitelist.
+    AddReporter(AccountAddress),
+    /// A reporter was removed from the whitelist, together with its report.
+    RemoveReporter(AccountAddress),
+    /// A reporter reported an observation.
+    Report {
+        reporter:    AccountAddress,
+        observation: Observation,
+    },
+}
+
+/// The different errors the price oracle can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum OracleError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when someone else than the owner manages the oracle.
+    Unauthorized,
+    /// Raised when the outlier tolerance is above 10000 basis points, or the
+    /// minimum number of reports is zero.
+    InvalidConfig,
+    /// Raised when adding a reporter which is already whitelisted.
+    ReporterExists,
+    /// Raised when the reporter is not whitelisted.
+    UnknownReporter,
+    /// Raised when the signature is not valid for the key of the reporter.
+    WrongSignature,
+    /// Raised when the observation is not newer than the previous observation
+    /// of the reporter.
+    OutdatedObservation,
+    /// Raised when the observation is from the future.
+    FutureObservation,
+    /// Raised when there are fewer than `min_reports` fresh reports which are
+    /// not outliers.
+    NotEnoughReports,
+}
+
+type OracleResult<A> = Result<A, OracleError>;
+
+/// Mapping the logging errors to OracleError.
+impl From<LogError> for OracleError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Check the configuration.
+fn ensure_valid_config(config: &Config) -> OracleResult<()> {
+    ensure!(config.max_deviation <= MAX_BPS && config.min_reports > 0, OracleError::InvalidConfig);
+    Ok(())
+}
+
+/// The message a reporter signs: The address of the oracle followed by the
+/// observation.
+fn observation_message(oracle: ContractAddress, observation: &Observation) -> Vec<u8> {
+    let mut message = to_bytes(&oracle);
+    message.extend(to_bytes(observation));
+    message
+}
+
+/// The median of a non-empty, sorted list of prices, rounded down.
+fn median(prices: &[u64]) -> u64 {
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        prices[middle]
+    } else {
+        // The sum of two prices can exceed 64 bits.
+        ((u128::from(prices[middle - 1]) + u128::from(prices[middle])) / 2) as u64
+    }
+}
+
+/// Whether the price deviates at most `max_deviation` basis points from the
+/// median.
+fn within_tolerance(price: u64, median: u64, max_deviation: u16) -> bool {
+    let difference = if price > median {
+        price - median
+    } else {
+        median - price
+    };
+    u128::from(difference) * u128::from(MAX_BPS) <= u128::from(median) * u128::from(max_deviation)
+}
+
+// Price oracle functions
+
+/// Initialize the oracle without reporters. The sender becomes the owner.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The configuration is invalid.
+#[init(contract = "price_oracle", parameter = "Config", event = "OracleEvent")]
+fn oracle_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let config: Config = ctx.parameter_cursor().get()?;
+    ensure_valid_config(&config)?;
+    Ok(State {
+        owner: ctx.init_origin(),
+        config,
+        reporters: state_builder.new_map(),
+        reports: state_builder.new_map(),
+    })
+}
+
+/// Add a reporter to the whitelist.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+/// - The reporter is already whitelisted.
+/// - It fails to log the event.
+#[receive(
+    contract = "price_oracle",
+    name = "addReporter",
+    parameter = "AddReporterParams",
+    error = "OracleError",
+    enable_logger,
+    mutable
+)]
+fn oracle_add_reporter<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> OracleResult<()> {
+    let params: AddReporterParams = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.owner), OracleError::Unauthorized);
+    state
+        .reporters
+        .entry(params.reporter)
+        .vacant_or(OracleError::ReporterExists)?
+        .insert(params.public_key);
+    logger.log(&OracleEvent::AddReporter(params.reporter))?;
+    Ok(())
+}
+
+/// Remove a reporter from the whitelist, together with its report.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+/// - The reporter is not whitelisted.
+/// - It fails to log the event.
+#[receive(
+    contract = "price_oracle",
+    name = "removeReporter",
+    parameter = "AccountAddress",
+    error = "OracleError",
+    enable_logger,
+    mutable
+)]
+fn oracle_remove_reporter<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> OracleResult<()> {
+    let reporter: AccountAddress = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.owner), OracleError::Unauthorized);
+    ensure!(state.reporters.remove_and_get(&reporter).is_some(), OracleError::UnknownReporter);
+    state.reports.remove(&reporter);
+    logger.log(&OracleEvent::RemoveReporter(reporter))?;
+    Ok(())
+}
+
+/// Update the configuration of the aggregation of reports.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+/// - The configuration is invalid.
+#[receive(
+    contract = "price_oracle",
+    name = "updateConfig",
+    parameter = "Config",
+    error = "OracleError",
+    mutable
+)]
+fn oracle_update_config<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> OracleResult<()> {
+    let config: Config = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.owner), OracleError::Unauthorized);
+    ensure_valid_config(&config)?;
+    state.config = config;
+    Ok(())
+}
+
+/// Submit an observation signed by a reporter, replacing the previous report
+/// of the reporter.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The reporter is not whitelisted.
+/// - The signature is not valid for the key of the reporter.
+/// - The observation is from the future, or is not newer than the previous
+///   observation of the reporter.
+/// - It fails to log the event.
+#[receive(
+    contract = "price_oracle",
+    name = "submit",
+    parameter = "SubmitParams",
+    error = "OracleError",
+    crypto_primitives,
+    enable_logger,
+    mutable
+)]
+fn oracle_submit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> OracleResult<()> {
+    let params: SubmitParams = ctx.parameter_cursor().get()?;
+    let observation = params.observation;
+    let state = host.state_mut();
+    let key = state.reporters.get(&params.reporter).map(|k| *k);
+    let key = key.ok_or(OracleError::UnknownReporter)?;
+    let message = observation_message(ctx.self_address(), &observation);
+    ensure!(
+        crypto_primitives.verify_ed25519_signature(key, params.signature, &message),
+        OracleError::WrongSignature
+    );
+    ensure!(observation.timestamp <= ctx.metadata().slot_time(), OracleError::FutureObservation);
+    if let Some(previous) = state.reports.get(&params.reporter) {
+        ensure!(observation.timestamp > previous.timestamp, OracleError::OutdatedObservation);
+    }
+    state.reports.insert(params.reporter, observation);
+    logger.log(&OracleEvent::Report {
+        reporter: params.reporter,
+        observation,
+    })?;
+    Ok(())
+}
+
+/// Get the median of the fresh reports which are not outliers.
+///
+/// It rejects if:
+/// - There are fewer than `min_reports` fresh reports which are not outliers.
+#[receive(
+    contract = "price_oracle",
+    name = "price",
+    return_value = "PriceView",
+    error = "OracleError"
+)]
+fn oracle_price<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> OracleResult<PriceView> {
+    let state = host.state();
+    let config = state.config;
+    let now = ctx.metadata().slot_time();
+    let mut fresh: Vec<Observation> = state
+        .reports
+        .iter()
+        .map(|(_, observation)| *observation)
+        .filter(|observation| {
+            observation.timestamp.checked_add(config.freshness).map_or(true, |until| until >= now)
+        })
+        .collect();
+    ensure!(!fresh.is_empty(), OracleError::NotEnoughReports);
+    fresh.sort_by_key(|observation| observation.price);
+    let prices: Vec<u64> = fresh.iter().map(|observation| observation.price).collect();
+    let fresh_median = median(&prices);
+
+    let agreeing: Vec<&Observation> = fresh
+        .iter()
+        .filter(|observation| {
+            within_tolerance(observation.price, fresh_median, config.max_deviation)
+        })
+        .collect();
+    ensure!(agreeing.len() >= config.min_reports as usize, OracleError::NotEnoughReports);
+    let prices: Vec<u64> = agreeing.iter().map(|observation| observation.price).collect();
+    // There is at least one agreeing report, since `min_reports` is not zero.
+    let oldest = agreeing.iter().map(|observation| observation.timestamp).min().unwrap_abort();
+    Ok(PriceView {
+        price: median(&prices),
+        reports: agreeing.len() as u32,
+        oldest,
+    })
+}
+
+/// View the configuration of the oracle.
+#[receive(contract = "price_oracle", name = "config", return_value = "Config")]
+fn oracle_config<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Config> {
+    Ok(host.state().config)
+}
+
+// Oracle consumer functions
+
+/// The state of the oracle consumer.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ConsumerState {
+    /// The oracle to read the price from.
+    oracle:     ContractAddress,
+    /// The latest price read from the oracle.
+    last_price: Option<PriceView>,
+}
+
+/// The different errors the oracle consumer can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ConsumerError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when the oracle rejects, e.g. because it has no price, or returns
+    /// something else than a price.
+    OracleFailed,
+}
+
+/// Mapping errors related to contract invocations to ConsumerError.
+impl<T> From<CallContractError<T>> for ConsumerError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::OracleFailed }
+}
+
+/// Initialize the consumer with the oracle to read the price from.
+#[init(contract = "oracle_consumer", parameter = "ContractAddress")]
+fn consumer_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+) -> InitResult<ConsumerState> {
+    let oracle: ContractAddress = ctx.parameter_cursor().get()?;
+    Ok(ConsumerState {
+        oracle,
+        last_price: None,
+    })
+}
+
+/// Read the price from the oracle and store it.
+///
+/// It rejects if:
+/// - The oracle rejects, e.g. because it has no price.
+/// - The oracle returns something else than a price.
+#[receive(
+    contract = "oracle_consumer",
+    name = "update",
+    return_value = "PriceView",
+    error = "ConsumerError",
+    mutable
+)]
+fn consumer_update<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<ConsumerState, StateApiType = S>,
+) -> Result<PriceView, ConsumerError> {
+    let oracle = host.state().oracle;
+    let response = host.invoke_contract_read_only(
+        &oracle,
+        &(),
+        EntrypointName::new_unchecked("price"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ConsumerError::OracleFailed)?;
+    let price: PriceView = response.get().map_err(|_| ConsumerError::OracleFailed)?;
+    host.state_mut().last_price = Some(price);
+    Ok(price)
+}
+
+/// View the state of the consumer.
+#[receive(contract = "oracle_consumer", name = "view", return_value = "ConsumerState")]
+fn consumer_view<'a, 'b, S: HasStateApi>(
+    _ctx: &'a impl HasReceiveContext,
+    host: &'b impl HasHost<ConsumerState, StateApiType = S>,
+) -> ReceiveResult<&'b ConsumerState> {
+    Ok(host.state())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const REPORTERS: [AccountAddress; 4] = [
+        AccountAddress([1; 32]),
+        AccountAddress([2; 32]),
+        AccountAddress([3; 32]),
+        AccountAddress([4; 32]),
+    ];
+    const ORACLE: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+
+    /// Reports are fresh for a minute, may deviate 5% from the median, and
+    /// three reports must agree.
+    fn config() -> Config {
+        Config {
+            freshness:     Duration::from_millis(60_000),
+            max_deviation: 500,
+            min_reports:   3,
+        }
+    }
+
+    /// The public key of a reporter.
+    fn key_of(index: usize) -> PublicKeyEd25519 { PublicKeyEd25519([index as u8 + 1; 32]) }
+
+    /// A signature which the crypto mock accepts for the key. The mock does not
+    /// check the message, so the signature starts with the key.
+    fn signature_of(key: PublicKeyEd25519) -> SignatureEd25519 {
+        let mut signature = [0; 64];
+        signature[..32].copy_from_slice(&key.0);
+        SignatureEd25519(signature)
+    }
+
+    fn crypto_primitives() -> TestCryptoPrimitives {
+        let crypto_primitives = TestCryptoPrimitives::new();
+        crypto_primitives.setup_verify_ed25519_signature_mock(|key, signature, _message| {
+            signature.0[..32] == key.0
+        });
+        crypto_primitives
+    }
+
+    fn ctx_at<'a>(
+        sender: AccountAddress,
+        slot_time: u64,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_with_parameter(sender, slot_time, parameter_bytes);
+        ctx.set_self_address(ORACLE);
+        ctx
+    }
+
+    /// Set up an oracle with the four reporters whitelisted.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&config());
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(OWNER);
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            oracle_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        for (index, reporter) in REPORTERS.iter().enumerate() {
+            let parameter_bytes = to_bytes(&AddReporterParams {
+                reporter:   *reporter,
+                public_key: key_of(index),
+            });
+            oracle_add_reporter(
+                &ctx_at(OWNER, 0, &parameter_bytes),
+                &mut host,
+                &mut TestLogger::init(),
+            )
+            .expect_report("Adding a reporter should pass");
+        }
+        host
+    }
+
+    fn submit(
+        host: &mut TestHost<State<TestStateApi>>,
+        index: usize,
+        price: u64,
+        timestamp: u64,
+        slot_time: u64,
+    ) -> OracleResult<()> {
+        let parameter_bytes = to_bytes(&SubmitParams {
+            reporter:    REPORTERS[index],
+            observation: Observation {
+                price,
+                timestamp: Timestamp::from_timestamp_millis(timestamp),
+            },
+            signature:   signature_of(key_of(index)),
+        });
+        oracle_submit(
+            &ctx_at(OWNER, slot_time, &parameter_bytes),
+            host,
+            &mut TestLogger::init(),
+            &crypto_primitives(),
+        )
+    }
+
+    fn price_at(host: &TestHost<State<TestStateApi>>, slot_time: u64) -> OracleResult<PriceView> {
+        oracle_price(&ctx_at(OWNER, slot_time, &[]), host)
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that the price is the median of the reports which are not
+    /// outliers, and that it requires enough of them.
+    fn test_price_median() {
+        let mut host = new_host();
+        submit(&mut host, 0, 100, 1_000, 1_000).expect_report("Submitting should pass");
+        submit(&mut host, 1, 500, 2_000, 2_000).expect_report("Submitting should pass");
+        submit(&mut host, 2, 103, 3_000, 3_000).expect_report("Submitting should pass");
+        // The median of all reports is 103, and 500 is an outlier.
+        claim_eq!(price_at(&host, 3_000), Err(OracleError::NotEnoughReports));
+
+        submit(&mut host, 3, 101, 4_000, 4_000).expect_report("Submitting should pass");
+        // The median of all reports is 102, and the median without 500 is 101.
+        claim_eq!(
+            price_at(&host, 4_000),
+            Ok(PriceView {
+                price:   101,
+                reports: 3,
+                oldest:  Timestamp::from_timestamp_millis(1_000),
+            })
+        );
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that reports older than the freshness window are not used.
+    fn test_price_freshness() {
+        let mut host = new_host();
+        submit(&mut host, 0, 100, 1_000, 1_000).expect_report("Submitting should pass");
+        submit(&mut host, 1, 102, 2_000, 2_000).expect_report("Submitting should pass");
+        submit(&mut host, 2, 104, 3_000, 3_000).expect_report("Submitting should pass");
+        claim_eq!(price_at(&host, 61_000).map(|view| view.price), Ok(102));
+        claim_eq!(price_at(&host, 61_001), Err(OracleError::NotEnoughReports));
+
+        submit(&mut host, 0, 106, 61_000, 61_001).expect_report("Submitting should pass");
+        claim_eq!(price_at(&host, 61_001).map(|view| view.price), Ok(104));
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that observations must be signed by a whitelisted reporter, and be
+    /// newer than the previous one without being from the future.
+    fn test_submit_rejected() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SubmitParams {
+            reporter:    REPORTERS[0],
+            observation: Observation {
+                price:     100,
+                timestamp: Timestamp::from_timestamp_millis(1_000),
+            },
+            signature:   signature_of(key_of(1)),
+        });
+        let res = oracle_submit(
+            &ctx_at(OWNER, 1_000, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+            &crypto_primitives(),
+        );
+        claim_eq!(res, Err(OracleError::WrongSignature), "Reporter 1 cannot sign for reporter 0");
+
+        claim_eq!(submit(&mut host, 0, 100, 1_001, 1_000), Err(OracleError::FutureObservation));
+        submit(&mut host, 0, 100, 1_000, 1_000).expect_report("Submitting should pass");
+        let res = submit(&mut host, 0, 100, 1_000, 2_000);
+        claim_eq!(res, Err(OracleError::OutdatedObservation), "Observations cannot be replayed");
+
+        let parameter_bytes = to_bytes(&REPORTERS[0]);
+        let res = oracle_remove_reporter(
+            &ctx_at(REPORTERS[1], 0, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(OracleError::Unauthorized));
+        oracle_remove_reporter(
+            &ctx_at(OWNER, 0, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        )
+        .expect_report("Removing a reporter should pass");
+        claim!(host.state().reports.get(&REPORTERS[0]).is_none(), "The report should be removed");
+        claim_eq!(submit(&mut host, 0, 100, 2_000, 2_000), Err(OracleError::UnknownReporter));
+    }
+
+    #[concordium_test]
+    /// Test that the consumer stores the price read from the oracle.
+    fn test_consumer_update() {
+        let parameter_bytes = to_bytes(&ORACLE);
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let state = consumer_init(&ctx, &mut TestStateBuilder::new())
+            .expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, TestStateBuilder::new());
+
+        let price = PriceView {
+            price:   101,
+            reports: 3,
+            oldest:  Timestamp::from_timestamp_millis(1_000),
+        };
+        host.setup_mock_entrypoint(
+            ORACLE,
+            OwnedEntrypointName::new_unchecked("price".into()),
+            MockFn::returning_ok(price),
+        );
+        let res = consumer_update(&TestReceiveContext::empty(), &mut host);
+        claim_eq!(res, Ok(price));
+        claim_eq!(host.state().last_price, Some(price));
+
+        host.setup_mock_entrypoint(
+            ORACLE,
+            OwnedEntrypointName::new_unchecked("price".into()),
+            MockFn::returning_ok(()),
+        );
+        let res = consumer_update(&TestReceiveContext::empty(), &mut host);
+        claim_eq!(res, Err(ConsumerError::OracleFailed), "The oracle must return a price");
+    }
+}
@@ -0,0 +1,625 @@
+//! # A parametric rain insurance smart contract
+//!
+//! The insurer sells insurance against rain, which pays out a fixed amount if
+//! it rained in a region at some point during the insured period. Whether it
+//! rained is decided by the history of observations of the weather service
+//! contract from the [icecream](../icecream) example, which is queried using
+//! its `history` function. The weather is considered rainy if the probability
+//! of precipitation is at least the threshold set at initialization.
+//!
+//! Customers buy a policy using `buy`, choosing the region, the insured period
+//! and the payout. The premium is a fixed number of basis points of the
+//! payout, and must be sent along.
+//!
+//! The contract pools the premiums together with the funds added by the
+//! insurer using `fund`. It only sells a policy if the pool can cover the
+//! payouts of all the policies which have not been settled, including the new
+//! one. These payouts are reserved, and the insurer can only `withdraw` the
+//! funds which are not reserved.
+//!
+//! After the insured period, anyone can `claim` a policy, typically the
+//! holder. The payout is transferred to the holder if the weather service
+//! observed rain in the region during the period. Otherwise the premium is
+//! kept by the pool. Either way the policy is settled, and its payout is no
+//! longer reserved.
+//!
+//! Note: The weather service returns every observation made during the period,
+//! in all regions, so the insured period should be short enough for the
+//! response to fit within the energy of a transaction.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The identifier of a region in the weather service.
+pub type RegionId = u32;
+
+/// The identifier of a policy.
+pub type PolicyId = u64;
+
+/// A weather observation, as returned by the weather service.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weather {
+    /// The temperature in tenths of a degree Celsius.
+    pub temperature:               i32,
+    /// The probability of precipitation in percent.
+    pub precipitation_probability: u8,
+    /// The wind speed in tenths of a meter per second.
+    pub wind_speed:                u32,
+}
+
+/// The weather in a region, and when it was observed, as returned by the
+/// weather service.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Observation {
+    /// The observed weather.
+    pub weather:     Weather,
+    /// The slot time of the update with the observation.
+    pub observed_at: Timestamp,
+}
+
+/// The parameter type for the weather service function `history`.
+#[derive(Serialize, SchemaType)]
+pub struct HistoryParams {
+    /// The start of the time window, inclusive.
+    pub from: Timestamp,
+    /// The end of the time window, inclusive.
+    pub to:   Timestamp,
+}
+
+/// An insurance policy.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Policy {
+    /// The account receiving the payout.
+    pub holder: AccountAddress,
+    /// The insured region.
+    pub region: RegionId,
+    /// The start of the insured period, inclusive.
+    pub start:  Timestamp,
+    /// The end of the insured period, inclusive.
+    pub end:    Timestamp,
+    /// The amount paid out if it rains.
+    pub payout: Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The insurer, who funds the pool.
+    owner:                   AccountAddress,
+    /// The weather service deciding whether it rained.
+    weather_service:         ContractAddress,
+    /// The premium in basis points of the payout.
+    premium_basis_points:    BasisPoints,
+    /// The probability of precipitation in percent from which the weather is
+    /// considered rainy.
+    precipitation_threshold: u8,
+    /// The sum of the payouts of the policies which have not been settled.
+    reserved:                Amount,
+    /// The identifier of the next policy.
+    next_policy_id:          PolicyId,
+    /// The policies which have not been settled.
+    policies:                StateMap<PolicyId, Policy, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The weather service deciding whether it rained.
+    pub weather_service:         ContractAddress,
+    /// The premium in basis points of the payout.
+    pub premium_basis_points:    u16,
+    /// The probability of precipitation in percent from which the weather is
+    /// considered rainy.
+    pub precipitation_threshold: u8,
+}
+
+/// The parameter type for the contract function `buy`.
+#[derive(Serialize, SchemaType)]
+pub struct BuyParams {
+    /// The insured region.
+    pub region: RegionId,
+    /// The start of the insured period, inclusive.
+    pub start:  Timestamp,
+    /// The end of the insured period, inclusive.
+    pub end:    Timestamp,
+    /// The amount paid out if it rains.
+    pub payout: Amount,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct PoolView {
+    /// The funds in the pool.
+    pub balance:  Amount,
+    /// The funds reserved for the payouts of the policies which have not been
+    /// settled.
+    pub reserved: Amount,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum InsuranceEvent {
+    /// A policy was bought.
+    Buy {
+        policy_id: PolicyId,
+        policy:    Policy,
+    },
+    /// A policy was settled, and paid out if it rained.
+    Claim {
+        policy_id: PolicyId,
+        paid_out:  bool,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed querying the weather service, or it returned something else than
+    /// observations.
+    InvokeContractError,
+    /// Raised when initializing with a premium above 10000 basis points or a
+    /// threshold above 100 percent.
+    InvalidConfig,
+    /// Raised when a contract buys a policy; Only accounts can be paid out.
+    OnlyAccount,
+    /// Raised when someone else than the owner withdraws.
+    Unauthorized,
+    /// Raised when the insured period has started, or ends before it starts.
+    InvalidPeriod,
+    /// Raised when the payout is zero.
+    InvalidPayout,
+    /// Raised when the CCD sent along is not the premium.
+    WrongPremium,
+    /// Raised when the pool cannot cover a new policy, or a withdrawal would
+    /// take reserved funds.
+    InsufficientFunds,
+    /// Raised when the policy does not exist or has been settled.
+    UnknownPolicy,
+    /// Raised when claiming before the end of the insured period.
+    PeriodNotOver,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Compute the premium of a payout, rounding up.
+fn premium(payout: Amount, premium_basis_points: BasisPoints) -> Amount {
+    // The rest of the payout is rounded down, so the premium is rounded up.
+    payout - premium_basis_points.complement().of(payout)
+}
+
+/// Query the weather service whether it rained in the region of the policy
+/// during the insured period.
+fn rained<S: HasStateApi>(
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    policy: &Policy,
+) -> ContractResult<bool> {
+    let state = host.state();
+    let parameter = HistoryParams {
+        from: policy.start,
+        to:   policy.end,
+    };
+    let response = host.invoke_contract_read_only(
+        &state.weather_service,
+        &parameter,
+        EntrypointName::new_unchecked("history"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ContractError::InvokeContractError)?;
+    let observations: Vec<(RegionId, Observation)> =
+        response.get().map_err(|_| ContractError::InvokeContractError)?;
+    Ok(observations.iter().any(|(region, observation)| {
+        *region == policy.region
+            && observation.weather.precipitation_probability >= state.precipitation_threshold
+    }))
+}
+
+/// Initialize the contract with an empty pool. The sender becomes the insurer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The premium is above 10000 basis points, or the threshold is above 100
+///   percent.
+#[init(contract = "rain_insurance", parameter = "InitParams", event = "InsuranceEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    ensure!(params.precipitation_threshold <= 100, ContractError::InvalidConfig.into());
+    Ok(State {
+        owner:                   ctx.init_origin(),
+        weather_service:         params.weather_service,
+        premium_basis_points:    BasisPoints::new(params.premium_basis_points)
+            .ok_or(ContractError::InvalidConfig)?,
+        precipitation_threshold: params.precipitation_threshold,
+        reserved:                Amount::zero(),
+        next_policy_id:          0,
+        policies:                state_builder.new_map(),
+    })
+}
+
+/// Add the CCD sent along to the pool. Anyone can fund the pool.
+#[receive(contract = "rain_insurance", name = "fund", payable)]
+fn contract_fund<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Withdraw funds which are not reserved for payouts to the insurer.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the insurer.
+/// - The amount exceeds the funds which are not reserved.
+#[receive(
+    contract = "rain_insurance",
+    name = "withdraw",
+    parameter = "Amount",
+    error = "ContractError",
+    mutable
+)]
+fn contract_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let amount: Amount = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    let owner = state.owner;
+    ensure!(ctx.sender().matches_account(&owner), ContractError::Unauthorized);
+    ensure!(state.reserved + amount <= host.self_balance(), ContractError::InsufficientFunds);
+    // The transfer always succeeds, since the insurer is the sender and the
+    // amount is available.
+    host.invoke_transfer(&owner, amount).unwrap_abort();
+    Ok(())
+}
+
+/// Buy a policy for the sender, sending the premium along. Returns the
+/// identifier of the policy.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The insured period has started, or ends before it starts.
+/// - The payout is zero.
+/// - The CCD sent along is not the premium.
+/// - The pool cannot cover the payout in addition to the reserved funds.
+/// - It fails to log the event.
+#[receive(
+    contract = "rain_insurance",
+    name = "buy",
+    parameter = "BuyParams",
+    return_value = "PolicyId",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_buy<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<PolicyId> {
+    let params: BuyParams = ctx.parameter_cursor().get()?;
+    let holder = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    ensure!(
+        ctx.metadata().slot_time() < params.start && params.start <= params.end,
+        ContractError::InvalidPeriod
+    );
+    ensure!(params.payout > Amount::zero(), ContractError::InvalidPayout);
+    ensure_eq!(
+        amount,
+        premium(params.payout, host.state().premium_basis_points),
+        ContractError::WrongPremium
+    );
+    // The balance includes the premium sent along.
+    let balance = host.self_balance();
+    let state = host.state_mut();
+    let reserved = state.reserved + params.payout;
+    ensure!(reserved <= balance, ContractError::InsufficientFunds);
+
+    let policy_id = state.next_policy_id;
+    let policy = Policy {
+        holder,
+        region: params.region,
+        start: params.start,
+        end: params.end,
+        payout: params.payout,
+    };
+    state.reserved = reserved;
+    state.next_policy_id += 1;
+    state.policies.insert(policy_id, policy);
+    logger.log(&InsuranceEvent::Buy {
+        policy_id,
+        policy,
+    })?;
+    Ok(policy_id)
+}
+
+/// Settle a policy after the insured period, paying out to the holder if the
+/// weather service observed rain in the region during the period. Returns
+/// whether it paid out.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The policy does not exist or has been settled.
+/// - The insured period is not over.
+/// - It fails to query the weather service.
+/// - It fails to log the event.
+#[receive(
+    contract = "rain_insurance",
+    name = "claim",
+    parameter = "PolicyId",
+    return_value = "bool",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<bool> {
+    let policy_id: PolicyId = ctx.parameter_cursor().get()?;
+    let policy = host.state().policies.get(&policy_id).map(|p| *p);
+    let policy = policy.ok_or(ContractError::UnknownPolicy)?;
+    ensure!(ctx.metadata().slot_time() > policy.end, ContractError::PeriodNotOver);
+    let paid_out = rained(host, &policy)?;
+
+    let state = host.state_mut();
+    state.policies.remove(&policy_id);
+    state.reserved -= policy.payout;
+    if paid_out {
+        // The transfer always succeeds, since the holder is an existing account
+        // and the payout was reserved.
+        host.invoke_transfer(&policy.holder, policy.payout).unwrap_abort();
+    }
+    logger.log(&InsuranceEvent::Claim {
+        policy_id,
+        paid_out,
+    })?;
+    Ok(paid_out)
+}
+
+/// View a policy which has not been settled.
+#[receive(
+    contract = "rain_insurance",
+    name = "policy",
+    parameter = "PolicyId",
+    return_value = "Policy",
+    error = "ContractError"
+)]
+fn contract_policy<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Policy> {
+    let policy_id: PolicyId = ctx.parameter_cursor().get()?;
+    host.state().policies.get(&policy_id).map(|p| *p).ok_or(ContractError::UnknownPolicy)
+}
+
+/// View the funds in the pool, and how much of them are reserved.
+#[receive(contract = "rain_insurance", name = "view", return_value = "PoolView")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<PoolView> {
+    Ok(PoolView {
+        balance:  host.self_balance(),
+        reserved: host.state().reserved,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const INSURER: AccountAddress = AccountAddress([0; 32]);
+    const CUSTOMER: AccountAddress = AccountAddress([1; 32]);
+    const WEATHER_SERVICE: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const RAINY_REGION: RegionId = 1;
+    const DRY_REGION: RegionId = 2;
+    /// The only rain is observed at this time, in the rainy region.
+    const RAIN_TIME: u64 = 5_000;
+    const PREMIUM_BASIS_POINTS: u16 = 1_000;
+    const PAYOUT: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+    const PREMIUM: Amount = Amount {
+        micro_ccd: 100,
+    };
+
+    /// The history of the weather service: Every second it is dry in both
+    /// regions, except for rain in the rainy region at `RAIN_TIME`.
+    fn history(params: &HistoryParams) -> Vec<(RegionId, Observation)> {
+        let mut observations = Vec::new();
+        let from = (params.from.timestamp_millis() + 999) / 1_000;
+        let to = params.to.timestamp_millis() / 1_000;
+        for second in from..=to {
+            let observed_at = second * 1_000;
+            for region in [RAINY_REGION, DRY_REGION].iter() {
+                let rain = *region == RAINY_REGION && observed_at == RAIN_TIME;
+                observations.push((*region, Observation {
+                    weather:     Weather {
+                        temperature:               150,
+                        precipitation_probability: if rain {
+                            80
+                        } else {
+                            10
+                        },
+                        wind_speed:                30,
+                    },
+                    observed_at: Timestamp::from_timestamp_millis(observed_at),
+                }));
+            }
+        }
+        observations
+    }
+
+    /// Set up a contract with the pool funded by the insurer, and the weather
+    /// service.
+    fn new_host(funds: Amount) -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            weather_service:         WEATHER_SERVICE,
+            premium_basis_points:    PREMIUM_BASIS_POINTS,
+            precipitation_threshold: 50,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(INSURER);
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(funds);
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("history".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: HistoryParams =
+                    from_bytes(parameter.0).expect_report("Invalid history parameter");
+                Ok((false, history(&params)))
+            }),
+        );
+        host
+    }
+
+    fn buy(
+        host: &mut TestHost<State<TestStateApi>>,
+        region: RegionId,
+        start: u64,
+        end: u64,
+        premium: Amount,
+    ) -> ContractResult<PolicyId> {
+        let parameter_bytes = to_bytes(&BuyParams {
+            region,
+            start: Timestamp::from_timestamp_millis(start),
+            end: Timestamp::from_timestamp_millis(end),
+            payout: PAYOUT,
+        });
+        host.set_self_balance(host.self_balance() + premium);
+        let ctx = ctx_with_parameter(CUSTOMER, 0, &parameter_bytes);
+        contract_buy(&ctx, host, premium, &mut TestLogger::init())
+    }
+
+    fn claim_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        policy_id: PolicyId,
+        slot_time: u64,
+    ) -> ContractResult<bool> {
+        let parameter_bytes = to_bytes(&policy_id);
+        let ctx = ctx_with_parameter(CUSTOMER, slot_time, &parameter_bytes);
+        contract_claim(&ctx, host, &mut TestLogger::init())
+    }
+
+    #[concordium_test]
+    /// Test that a policy is only sold for a future period, for the premium,
+    /// and if the pool can cover the payout.
+    fn test_buy() {
+        let mut host = new_host(PAYOUT);
+        let res = buy(&mut host, RAINY_REGION, 0, 10_000, PREMIUM);
+        claim_eq!(res, Err(ContractError::InvalidPeriod), "The period must be in the future");
+        let res = buy(&mut host, RAINY_REGION, 10_000, 9_000, PREMIUM);
+        claim_eq!(res, Err(ContractError::InvalidPeriod), "The period must not end before start");
+        let res = buy(&mut host, RAINY_REGION, 1_000, 10_000, Amount::from_micro_ccd(99));
+        claim_eq!(res, Err(ContractError::WrongPremium));
+
+        let id = buy(&mut host, RAINY_REGION, 1_000, 10_000, PREMIUM)
+            .expect_report("Buying should pass");
+        claim_eq!(id, 0);
+        // The pool cannot cover two payouts.
+        let res = buy(&mut host, DRY_REGION, 1_000, 10_000, PREMIUM);
+        claim_eq!(res, Err(ContractError::InsufficientFunds));
+        claim_eq!(host.state().reserved, PAYOUT);
+    }
+
+    #[concordium_test]
+    /// Test that the holder is paid out if it rained in the region during the
+    /// period, and that a policy can only be settled once.
+    fn test_claim_rain() {
+        let mut host = new_host(PAYOUT);
+        let id = buy(&mut host, RAINY_REGION, 1_000, RAIN_TIME, PREMIUM)
+            .expect_report("Buying should pass");
+
+        claim_eq!(claim_at(&mut host, id, RAIN_TIME), Err(ContractError::PeriodNotOver));
+        claim_eq!(claim_at(&mut host, id, RAIN_TIME + 1), Ok(true));
+        claim_eq!(host.get_transfers(), [(CUSTOMER, PAYOUT)]);
+        claim_eq!(host.state().reserved, Amount::zero());
+        claim_eq!(claim_at(&mut host, id, RAIN_TIME + 1), Err(ContractError::UnknownPolicy));
+    }
+
+    #[concordium_test]
+    /// Test that the premium is kept by the pool if it did not rain in the
+    /// region during the period, and that the funds are released.
+    fn test_claim_no_rain() {
+        let mut host = new_host(Amount::from_micro_ccd(2_000));
+        let dry_region =
+            buy(&mut host, DRY_REGION, 1_000, 10_000, PREMIUM).expect_report("Buying should pass");
+        let dry_period = buy(&mut host, RAINY_REGION, RAIN_TIME + 1, 10_000, PREMIUM)
+            .expect_report("Buying should pass");
+
+        claim_eq!(claim_at(&mut host, dry_region, 10_001), Ok(false));
+        claim_eq!(claim_at(&mut host, dry_period, 10_001), Ok(false));
+        claim!(host.get_transfers().is_empty(), "Nothing should be paid out");
+        claim_eq!(
+            contract_view(&TestReceiveContext::empty(), &host),
+            Ok(PoolView {
+                balance:  Amount::from_micro_ccd(2_200),
+                reserved: Amount::zero(),
+            })
+        );
+    }
+
+    #[concordium_test]
+    /// Test that only the insurer can withdraw, and only funds which are not
+    /// reserved.
+    fn test_withdraw() {
+        let mut host = new_host(PAYOUT);
+        buy(&mut host, RAINY_REGION, 1_000, 10_000, PREMIUM).expect_report("Buying should pass");
+
+        let parameter_bytes = to_bytes(&PREMIUM);
+        let res = contract_withdraw(&ctx_with_parameter(CUSTOMER, 0, &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        let parameter_bytes = to_bytes(&(PREMIUM + Amount::from_micro_ccd(1)));
+        let res = contract_withdraw(&ctx_with_parameter(INSURER, 0, &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::InsufficientFunds));
+        let parameter_bytes = to_bytes(&PREMIUM);
+        contract_withdraw(&ctx_with_parameter(INSURER, 0, &parameter_bytes), &mut host)
+            .expect_report("Withdrawing should pass");
+        claim_eq!(host.get_transfers(), [(INSURER, PREMIUM)]);
+    }
+}
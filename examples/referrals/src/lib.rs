@@ -0,0 +1,441 @@
+//! # Referral rewards
+//!
+//! Users of a platform `register` with this contract, optionally naming the
+//! registered user who referred them. Payments to the platform are made
+//! through `pay`. The first qualifying payment of a referred user, i.e. the
+//! first payment of at least the minimum payment, credits a share of the
+//! payment to the referrer. The share is given in basis points, and the owner
+//! of the contract, i.e. the platform, can change it with `setReward`.
+//!
+//! The rewards are credited to the balances of the referrers, which they
+//! `withdraw` whenever they want. The rest of the payments is revenue of the
+//! platform, which the owner withdraws with `withdrawRevenue`.
+//!
+//! Users cannot refer themselves, and the referrer must already be registered
+//! when the referred user registers. Since users register once, this also
+//! rules out referral cycles.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// A registered user.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct User {
+    /// The user who referred this user, if any.
+    pub referrer:  Option<AccountAddress>,
+    /// Whether the user made a qualifying payment.
+    pub qualified: bool,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The platform, receiving the revenue.
+    owner:       AccountAddress,
+    /// The share of the first qualifying payment of a referred user credited
+    /// to the referrer, in basis points.
+    reward_bps:  BasisPoints,
+    /// The minimum qualifying payment.
+    min_payment: Amount,
+    /// The registered users.
+    users:       StateMap<AccountAddress, User, S>,
+    /// The rewards which the referrers have not withdrawn.
+    balances:    StateMap<AccountAddress, Amount, S>,
+    /// The revenue which the owner has not withdrawn.
+    revenue:     Amount,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The share of the first qualifying payment of a referred user credited
+    /// to the referrer, in basis points.
+    pub reward_bps:  u16,
+    /// The minimum qualifying payment.
+    pub min_payment: Amount,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum ReferralEvent {
+    /// A user registered.
+    Register {
+        user:     AccountAddress,
+        referrer: Option<AccountAddress>,
+    },
+    /// A referrer was rewarded for the first qualifying payment of a user.
+    Reward {
+        referrer: AccountAddress,
+        user:     AccountAddress,
+        amount:   Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract registers, pays or withdraws.
+    OnlyAccount,
+    /// Raised when the reward is more than 10000 basis points.
+    InvalidReward,
+    /// Raised when the sender is already registered.
+    AlreadyRegistered,
+    /// Raised when a user names themselves as the referrer.
+    SelfReferral,
+    /// Raised when the referrer is not registered.
+    UnknownReferrer,
+    /// Raised when the sender is not registered.
+    NotRegistered,
+    /// Raised when someone else than the owner changes the reward or withdraws
+    /// the revenue.
+    Unauthorized,
+    /// Raised when there is nothing to withdraw.
+    NothingToWithdraw,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+/// Initialize the contract with the reward and the minimum qualifying payment.
+/// The sender becomes the owner.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The reward is more than 10000 basis points.
+#[init(contract = "referrals", parameter = "InitParams", event = "ReferralEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    let reward_bps = BasisPoints::new(params.reward_bps).ok_or(ContractError::InvalidReward)?;
+    Ok(State {
+        owner: ctx.init_origin(),
+        reward_bps,
+        min_payment: params.min_payment,
+        users: state_builder.new_map(),
+        balances: state_builder.new_map(),
+        revenue: Amount::zero(),
+    })
+}
+
+/// Register the sender, optionally naming the user who referred them.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The sender is already registered.
+/// - The sender names themselves as the referrer.
+/// - The referrer is not registered.
+/// - It fails to log the event.
+#[receive(
+    contract = "referrals",
+    name = "register",
+    parameter = "Option<AccountAddress>",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_register<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let referrer: Option<AccountAddress> = ctx.parameter_cursor().get()?;
+    let user = sender_account(ctx)?;
+    let state = host.state_mut();
+    if let Some(referrer) = referrer {
+        ensure!(referrer != user, ContractError::SelfReferral);
+        ensure!(state.users.get(&referrer).is_some(), ContractError::UnknownReferrer);
+    }
+    state.users.entry(user).vacant_or(ContractError::AlreadyRegistered)?.insert(User {
+        referrer,
+        qualified: false,
+    });
+    logger.log(&ReferralEvent::Register {
+        user,
+        referrer,
+    })?;
+    Ok(())
+}
+
+/// Pay the platform. If this is the first qualifying payment of a referred
+/// user, the reward is credited to the referrer.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The sender is not registered.
+/// - It fails to log the event.
+#[receive(
+    contract = "referrals",
+    name = "pay",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_pay<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let user = sender_account(ctx)?;
+    let state = host.state_mut();
+    let mut user_state = state.users.get_mut(&user).ok_or(ContractError::NotRegistered)?;
+
+    let mut reward = Amount::zero();
+    if !user_state.qualified && amount >= state.min_payment {
+        user_state.qualified = true;
+        if let Some(referrer) = user_state.referrer {
+            reward = state.reward_bps.of(amount);
+            *state.balances.entry(referrer).or_insert(Amount::zero()) += reward;
+            logger.log(&ReferralEvent::Reward {
+                referrer,
+                user,
+                amount: reward,
+            })?;
+        }
+    }
+    state.revenue += amount - reward;
+    Ok(())
+}
+
+/// Withdraw the rewards credited to the sender.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The sender has no rewards to withdraw.
+#[receive(contract = "referrals", name = "withdraw", error = "ContractError", mutable)]
+fn contract_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let referrer = sender_account(ctx)?;
+    let amount = host
+        .state_mut()
+        .balances
+        .remove_and_get(&referrer)
+        .ok_or(ContractError::NothingToWithdraw)?;
+    // The transfer always succeeds, since the referrer is the sender and the
+    // rewards are held by the contract.
+    host.invoke_transfer(&referrer, amount).unwrap_abort();
+    Ok(())
+}
+
+/// Withdraw the revenue to the owner.
+///
+/// It rejects if:
+/// - The sender is not the owner.
+/// - There is no revenue to withdraw.
+#[receive(contract = "referrals", name = "withdrawRevenue", error = "ContractError", mutable)]
+fn contract_withdraw_revenue<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    let owner = state.owner;
+    ensure!(ctx.sender().matches_account(&owner), ContractError::Unauthorized);
+    let revenue = state.revenue;
+    ensure!(revenue > Amount::zero(), ContractError::NothingToWithdraw);
+    state.revenue = Amount::zero();
+    // The transfer always succeeds, since the owner is an account and the
+    // revenue is held by the contract.
+    host.invoke_transfer(&owner, revenue).unwrap_abort();
+    Ok(())
+}
+
+/// Change the reward for future qualifying payments.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+/// - The reward is more than 10000 basis points.
+#[receive(
+    contract = "referrals",
+    name = "setReward",
+    parameter = "u16",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_reward<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let reward_bps: u16 = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.owner), ContractError::Unauthorized);
+    state.reward_bps = BasisPoints::new(reward_bps).ok_or(ContractError::InvalidReward)?;
+    Ok(())
+}
+
+/// View a registered user.
+#[receive(
+    contract = "referrals",
+    name = "user",
+    parameter = "AccountAddress",
+    return_value = "Option<User>"
+)]
+fn contract_user<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<User>> {
+    let user: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().users.get(&user).map(|user| *user))
+}
+
+/// View the rewards credited to a referrer.
+#[receive(
+    contract = "referrals",
+    name = "balanceOf",
+    parameter = "AccountAddress",
+    return_value = "Amount"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let referrer: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().balances.get(&referrer).map_or(Amount::zero(), |b| *b))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+    const MIN_PAYMENT: Amount = Amount::from_micro_ccd(1_000);
+
+    fn ctx_with<'a>(sender: AccountAddress, parameter_bytes: &'a [u8]) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_parameter(parameter_bytes);
+        ctx
+    }
+
+    /// Create a host with a reward of 10%, where `ALICE` is registered.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            reward_bps:  1_000,
+            min_payment: MIN_PAYMENT,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(OWNER);
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        register(&mut host, ALICE, None).expect_report("Registering should pass");
+        host
+    }
+
+    fn register(
+        host: &mut TestHost<State<TestStateApi>>,
+        user: AccountAddress,
+        referrer: Option<AccountAddress>,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&referrer);
+        contract_register(&ctx_with(user, &parameter_bytes), host, &mut TestLogger::init())
+    }
+
+    fn pay(
+        host: &mut TestHost<State<TestStateApi>>,
+        user: AccountAddress,
+        micro_ccd: u64,
+    ) -> ContractResult<()> {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_pay(&ctx_with(user, &[]), host, amount, &mut TestLogger::init())
+    }
+
+    fn balance_of(host: &TestHost<State<TestStateApi>>, referrer: AccountAddress) -> Amount {
+        let parameter_bytes = to_bytes(&referrer);
+        contract_balance_of(&ctx_with(referrer, &parameter_bytes), host)
+            .expect_report("Querying should pass")
+    }
+
+    #[concordium_test]
+    /// Test that users cannot refer themselves, be referred by unregistered
+    /// users or register twice.
+    fn test_register() {
+        let mut host = new_host();
+        claim_eq!(register(&mut host, BOB, Some(BOB)), Err(ContractError::SelfReferral));
+        claim_eq!(register(&mut host, BOB, Some(CAROL)), Err(ContractError::UnknownReferrer));
+        register(&mut host, BOB, Some(ALICE)).expect_report("Registering should pass");
+        claim_eq!(register(&mut host, BOB, None), Err(ContractError::AlreadyRegistered));
+        claim_eq!(register(&mut host, ALICE, Some(BOB)), Err(ContractError::AlreadyRegistered));
+        claim_eq!(pay(&mut host, CAROL, 1_000), Err(ContractError::NotRegistered));
+    }
+
+    #[concordium_test]
+    /// Test that only the first qualifying payment of a referred user rewards
+    /// the referrer.
+    fn test_reward_first_qualifying_payment() {
+        let mut host = new_host();
+        register(&mut host, BOB, Some(ALICE)).expect_report("Registering should pass");
+
+        pay(&mut host, BOB, 999).expect_report("Paying should pass");
+        claim_eq!(balance_of(&host, ALICE), Amount::zero(), "The payment does not qualify");
+        pay(&mut host, BOB, 5_000).expect_report("Paying should pass");
+        claim_eq!(balance_of(&host, ALICE), Amount::from_micro_ccd(500));
+        pay(&mut host, BOB, 5_000).expect_report("Paying should pass");
+        claim_eq!(balance_of(&host, ALICE), Amount::from_micro_ccd(500), "Rewarded only once");
+        claim_eq!(host.state().revenue, Amount::from_micro_ccd(10_499));
+
+        pay(&mut host, ALICE, 5_000).expect_report("Paying should pass");
+        claim_eq!(host.state().revenue, Amount::from_micro_ccd(15_499), "No referrer to reward");
+    }
+
+    #[concordium_test]
+    /// Test that referrers withdraw their rewards and the owner the revenue.
+    fn test_withdraw() {
+        let mut host = new_host();
+        register(&mut host, BOB, Some(ALICE)).expect_report("Registering should pass");
+        pay(&mut host, BOB, 2_000).expect_report("Paying should pass");
+
+        contract_withdraw(&ctx_with(ALICE, &[]), &mut host)
+            .expect_report("Withdrawing should pass");
+        claim_eq!(
+            contract_withdraw(&ctx_with(ALICE, &[]), &mut host),
+            Err(ContractError::NothingToWithdraw)
+        );
+        claim_eq!(
+            contract_withdraw_revenue(&ctx_with(ALICE, &[]), &mut host),
+            Err(ContractError::Unauthorized)
+        );
+        contract_withdraw_revenue(&ctx_with(OWNER, &[]), &mut host)
+            .expect_report("Withdrawing the revenue should pass");
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(200)),
+            (OWNER, Amount::from_micro_ccd(1_800))
+        ]);
+    }
+}
@@ -0,0 +1,449 @@
+//! # Revenue sharing for a stream
+//!
+//! An instance of this contract shares the revenue of a stream among a list of
+//! beneficiaries, e.g. the streamer, the editors and the platform, each with a
+//! share in basis points. The shares add up to 10000 basis points, i.e. 100%.
+//!
+//! Payments for the stream are sent to `pay`, and are collected in the current
+//! payout epoch. When the epoch has lasted the epoch length, anyone can
+//! `closeEpoch`, which splits the revenue of the epoch among the beneficiaries
+//! according to their shares, rounding down. What remains after rounding is
+//! carried over to the next epoch. The beneficiaries `withdraw` their part of
+//! the revenue whenever they want.
+//!
+//! The admin, who initialized the contract, can change the shares using
+//! `updateShares`. The new shares take effect when the current epoch closes,
+//! such that the revenue of an epoch is always split by the shares which were
+//! in effect when the epoch started.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// A beneficiary and its share in basis points.
+pub type Share = (AccountAddress, BasisPoints);
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The account which can change the shares.
+    admin:          AccountAddress,
+    /// The shares in effect in the current epoch.
+    shares:         Vec<Share>,
+    /// The shares which take effect when the current epoch closes, if any.
+    pending_shares: Option<Vec<Share>>,
+    /// How long an epoch lasts at least.
+    epoch_length:   Duration,
+    /// The number of the current epoch.
+    epoch:          u64,
+    /// The start of the current epoch.
+    epoch_start:    Timestamp,
+    /// The revenue of the current epoch, including what was carried over.
+    epoch_revenue:  Amount,
+    /// The revenue which the beneficiaries have not withdrawn.
+    balances:       StateMap<AccountAddress, Amount, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The initial shares.
+    pub shares:       Vec<Share>,
+    /// How long an epoch lasts at least.
+    pub epoch_length: Duration,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct SplitView {
+    pub admin:          AccountAddress,
+    pub shares:         Vec<Share>,
+    pub pending_shares: Option<Vec<Share>>,
+    pub epoch:          u64,
+    pub epoch_start:    Timestamp,
+    pub epoch_revenue:  Amount,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum SplitEvent {
+    /// An epoch was closed, and its revenue split.
+    CloseEpoch {
+        epoch:   u64,
+        revenue: Amount,
+    },
+    /// The admin changed the shares, which take effect in the next epoch.
+    UpdateShares(Vec<Share>),
+    /// A beneficiary withdrew its balance.
+    Withdraw {
+        beneficiary: AccountAddress,
+        amount:      Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when the shares do not add up to 10000 basis points, or list a
+    /// beneficiary more than once.
+    InvalidShares,
+    /// Raised when the epoch length is zero.
+    InvalidEpochLength,
+    /// Raised when someone else than the admin changes the shares.
+    Unauthorized,
+    /// Raised when closing an epoch before it has lasted the epoch length.
+    EpochNotOver,
+    /// Raised when the sender has nothing to withdraw.
+    NothingToWithdraw,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Check that the shares add up to 10000 basis points, and list every
+/// beneficiary once.
+fn ensure_valid_shares(shares: &[Share]) -> ContractResult<()> {
+    let total =
+        shares.iter().try_fold(BasisPoints::default(), |total, (_, bps)| total.checked_add(*bps));
+    ensure_eq!(total, Some(BasisPoints::MAX), ContractError::InvalidShares);
+    for (i, (beneficiary, _)) in shares.iter().enumerate() {
+        ensure!(
+            shares[i + 1..].iter().all(|(other, _)| other != beneficiary),
+            ContractError::InvalidShares
+        );
+    }
+    Ok(())
+}
+
+/// Initialize the contract with the shares, starting the first epoch. The
+/// sender becomes the admin.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The shares are invalid.
+/// - The epoch length is zero.
+#[init(contract = "revenue_split", parameter = "InitParams", event = "SplitEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    ensure_valid_shares(&params.shares)?;
+    ensure!(params.epoch_length.millis() > 0, ContractError::InvalidEpochLength.into());
+    Ok(State {
+        admin:          ctx.init_origin(),
+        shares:         params.shares,
+        pending_shares: None,
+        epoch_length:   params.epoch_length,
+        epoch:          0,
+        epoch_start:    ctx.metadata().slot_time(),
+        epoch_revenue:  Amount::zero(),
+        balances:       state_builder.new_map(),
+    })
+}
+
+/// Pay the CCD sent along for the stream, adding it to the revenue of the
+/// current epoch.
+#[receive(contract = "revenue_split", name = "pay", payable, mutable)]
+fn contract_pay<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ReceiveResult<()> {
+    host.state_mut().epoch_revenue += amount;
+    Ok(())
+}
+
+/// Close the current epoch, splitting its revenue among the beneficiaries, and
+/// start the next epoch with the pending shares, if any.
+///
+/// It rejects if:
+/// - The epoch has not lasted the epoch length.
+/// - It fails to log the event.
+#[receive(
+    contract = "revenue_split",
+    name = "closeEpoch",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_close_epoch<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    let epoch_end = state.epoch_start.checked_add(state.epoch_length);
+    ensure!(epoch_end.map_or(false, |end| now >= end), ContractError::EpochNotOver);
+
+    let revenue = state.epoch_revenue;
+    let mut distributed = Amount::zero();
+    for (beneficiary, bps) in state.shares.iter() {
+        let part = bps.of(revenue);
+        *state.balances.entry(*beneficiary).or_insert(Amount::zero()) += part;
+        distributed += part;
+    }
+    logger.log(&SplitEvent::CloseEpoch {
+        epoch: state.epoch,
+        revenue,
+    })?;
+
+    // What remains after rounding down is carried over to the next epoch.
+    state.epoch_revenue = revenue - distributed;
+    state.epoch += 1;
+    state.epoch_start = now;
+    if let Some(shares) = state.pending_shares.take() {
+        state.shares = shares;
+    }
+    Ok(())
+}
+
+/// Change the shares, taking effect when the current epoch closes. Replaces
+/// earlier changes which have not taken effect.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the admin.
+/// - The shares are invalid.
+/// - It fails to log the event.
+#[receive(
+    contract = "revenue_split",
+    name = "updateShares",
+    parameter = "Vec<Share>",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_shares<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let shares: Vec<Share> = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.admin), ContractError::Unauthorized);
+    ensure_valid_shares(&shares)?;
+    logger.log(&SplitEvent::UpdateShares(shares.clone()))?;
+    state.pending_shares = Some(shares);
+    Ok(())
+}
+
+/// Withdraw the balance of the sender.
+///
+/// It rejects if:
+/// - The sender has nothing to withdraw.
+/// - It fails to log the event.
+#[receive(
+    contract = "revenue_split",
+    name = "withdraw",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let beneficiary = match ctx.sender() {
+        Address::Account(account_address) => account_address,
+        Address::Contract(_) => bail!(ContractError::NothingToWithdraw),
+    };
+    let amount = host
+        .state_mut()
+        .balances
+        .remove_and_get(&beneficiary)
+        .ok_or(ContractError::NothingToWithdraw)?;
+    ensure!(amount > Amount::zero(), ContractError::NothingToWithdraw);
+    // The transfer always succeeds, since the beneficiary is the sender and
+    // the balance is held by the contract.
+    host.invoke_transfer(&beneficiary, amount).unwrap_abort();
+    logger.log(&SplitEvent::Withdraw {
+        beneficiary,
+        amount,
+    })?;
+    Ok(())
+}
+
+/// View the shares and the current epoch.
+#[receive(contract = "revenue_split", name = "view", return_value = "SplitView")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<SplitView> {
+    let state = host.state();
+    Ok(SplitView {
+        admin:          state.admin,
+        shares:         state.shares.clone(),
+        pending_shares: state.pending_shares.clone(),
+        epoch:          state.epoch,
+        epoch_start:    state.epoch_start,
+        epoch_revenue:  state.epoch_revenue,
+    })
+}
+
+/// View the balance of a beneficiary.
+#[receive(
+    contract = "revenue_split",
+    name = "balanceOf",
+    parameter = "AccountAddress",
+    return_value = "Amount"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let beneficiary: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().balances.get(&beneficiary).map_or(Amount::zero(), |b| *b))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const STREAMER: AccountAddress = AccountAddress([1; 32]);
+    const EDITOR: AccountAddress = AccountAddress([2; 32]);
+    const PLATFORM: AccountAddress = AccountAddress([3; 32]);
+    const EPOCH_LENGTH: u64 = 1_000;
+
+    fn share(beneficiary: AccountAddress, bps: u16) -> Share {
+        (beneficiary, BasisPoints::new(bps).expect_report("The share should be valid"))
+    }
+
+    fn initialize(shares: Vec<Share>) -> InitResult<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            shares,
+            epoch_length: Duration::from_millis(EPOCH_LENGTH),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(STREAMER);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        ctx.set_parameter(&parameter_bytes);
+        contract_init(&ctx, &mut TestStateBuilder::new())
+    }
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let state =
+            initialize(vec![share(STREAMER, 7_000), share(EDITOR, 2_000), share(PLATFORM, 1_000)])
+                .expect_report("Initialization should pass");
+        TestHost::new(state, TestStateBuilder::new())
+    }
+
+    fn pay(host: &mut TestHost<State<TestStateApi>>, micro_ccd: u64) {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_pay(&TestReceiveContext::empty(), host, amount)
+            .expect_report("Paying should pass");
+    }
+
+    fn close_at(host: &mut TestHost<State<TestStateApi>>, slot_time: u64) -> ContractResult<()> {
+        contract_close_epoch(
+            &ctx_with_parameter(STREAMER, slot_time, &[]),
+            host,
+            &mut TestLogger::init(),
+        )
+    }
+
+    fn balance_of(host: &TestHost<State<TestStateApi>>, beneficiary: AccountAddress) -> Amount {
+        let parameter_bytes = to_bytes(&beneficiary);
+        contract_balance_of(&ctx_with_parameter(beneficiary, 0, &parameter_bytes), host)
+            .expect_report("Querying should pass")
+    }
+
+    #[concordium_test]
+    /// Test that the shares must add up to 10000 basis points, and list every
+    /// beneficiary once.
+    fn test_init_invalid_shares() {
+        let res = initialize(vec![share(STREAMER, 7_000), share(EDITOR, 2_000)]).map(|_| ());
+        claim_eq!(res, Err(ContractError::InvalidShares.into()));
+        let res = initialize(vec![share(STREAMER, 7_000), share(STREAMER, 3_000)]).map(|_| ());
+        claim_eq!(res, Err(ContractError::InvalidShares.into()));
+        let res = initialize(vec![share(STREAMER, 7_000), share(EDITOR, 7_000)]).map(|_| ());
+        claim_eq!(res, Err(ContractError::InvalidShares.into()), "The shares exceed 100%");
+        claim!(initialize(vec![share(STREAMER, 10_000)]).is_ok(), "A single beneficiary is valid");
+    }
+
+    #[concordium_test]
+    /// Test that closing an epoch splits its revenue by the shares, carrying
+    /// over what remains after rounding down.
+    fn test_close_epoch() {
+        let mut host = new_host();
+        pay(&mut host, 1_000);
+        pay(&mut host, 9);
+        claim_eq!(close_at(&mut host, EPOCH_LENGTH - 1), Err(ContractError::EpochNotOver));
+        close_at(&mut host, EPOCH_LENGTH).expect_report("Closing should pass");
+
+        claim_eq!(balance_of(&host, STREAMER), Amount::from_micro_ccd(706));
+        claim_eq!(balance_of(&host, EDITOR), Amount::from_micro_ccd(201));
+        claim_eq!(balance_of(&host, PLATFORM), Amount::from_micro_ccd(100));
+        claim_eq!(host.state().epoch_revenue, Amount::from_micro_ccd(2), "2 should carry over");
+        claim_eq!(host.state().epoch, 1);
+        claim_eq!(close_at(&mut host, 2 * EPOCH_LENGTH - 1), Err(ContractError::EpochNotOver));
+    }
+
+    #[concordium_test]
+    /// Test that changed shares only apply from the next epoch.
+    fn test_update_shares() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&vec![share(STREAMER, 5_000), share(EDITOR, 5_000)]);
+        let res = contract_update_shares(
+            &ctx_with_parameter(EDITOR, 0, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_update_shares(
+            &ctx_with_parameter(STREAMER, 0, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        )
+        .expect_report("Updating should pass");
+
+        pay(&mut host, 1_000);
+        close_at(&mut host, EPOCH_LENGTH).expect_report("Closing should pass");
+        claim_eq!(balance_of(&host, EDITOR), Amount::from_micro_ccd(200), "The old shares apply");
+        pay(&mut host, 1_000);
+        close_at(&mut host, 2 * EPOCH_LENGTH).expect_report("Closing should pass");
+        claim_eq!(balance_of(&host, EDITOR), Amount::from_micro_ccd(700), "The new shares apply");
+        claim_eq!(balance_of(&host, PLATFORM), Amount::from_micro_ccd(100));
+    }
+
+    #[concordium_test]
+    /// Test that beneficiaries withdraw their balances.
+    fn test_withdraw() {
+        let mut host = new_host();
+        pay(&mut host, 1_000);
+        close_at(&mut host, EPOCH_LENGTH).expect_report("Closing should pass");
+
+        contract_withdraw(&ctx_with_parameter(EDITOR, 0, &[]), &mut host, &mut TestLogger::init())
+            .expect_report("Withdrawing should pass");
+        let res = contract_withdraw(
+            &ctx_with_parameter(EDITOR, 0, &[]),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::NothingToWithdraw));
+        claim_eq!(host.get_transfers(), [(EDITOR, Amount::from_micro_ccd(200))]);
+        claim_eq!(host.self_balance(), Amount::from_micro_ccd(800));
+    }
+}
@@ -0,0 +1,453 @@
+//! # Royalties on resales of content NFTs
+//!
+//! This contract receives the proceeds of secondary sales of content NFTs,
+//! and splits them between the seller and the original creator of the token
+//! according to an on-chain royalty table. A marketplace, or the buyer
+//! directly, sends the proceeds of a sale to `distribute`, naming the sold
+//! token and the seller.
+//!
+//! The royalty table has two levels:
+//! - The admin, who initialized the contract, sets the royalty of a collection,
+//!   i.e. of all tokens of a CIS2 contract, using `setCollectionRoyalty`.
+//! - The creator of a collection can override the royalty of individual tokens
+//!   of the collection using `setTokenRoyalty`, e.g. to credit a guest creator
+//!   of a single piece of content.
+//!
+//! The royalty of a token is its override if there is one, and otherwise the
+//! royalty of its collection. Tokens without a royalty pay all of the
+//! proceeds to the seller. Every distribution is logged, which allows
+//! accounting for the royalties off-chain.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// Token ID type of the tokens. Using `TokenIdVec` allows royalties on tokens
+/// from any CIS2 contract, since all token IDs serialize the same way.
+type ContractTokenId = TokenIdVec;
+
+/// A token, identified by the contract and the token ID.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    /// The address of the CIS2 contract.
+    pub contract: ContractAddress,
+    /// The ID of the token in the CIS2 contract.
+    pub id:       ContractTokenId,
+}
+
+/// The royalty paid to the creator on every resale.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Royalty {
+    /// The creator receiving the royalty.
+    pub creator:     AccountAddress,
+    /// The royalty in basis points of the proceeds.
+    pub royalty_bps: BasisPoints,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The account which sets the royalties of collections.
+    admin:       AccountAddress,
+    /// The royalty of every collection, by CIS2 contract.
+    collections: StateMap<ContractAddress, Royalty, S>,
+    /// The royalty of tokens overriding the royalty of their collection.
+    overrides:   StateMap<TokenInfo, Royalty, S>,
+}
+
+/// The parameter type for the contract function `setCollectionRoyalty`.
+#[derive(Serialize, SchemaType)]
+pub struct SetCollectionRoyaltyParams {
+    /// The CIS2 contract.
+    pub contract: ContractAddress,
+    /// The royalty of the collection, or `None` to remove it.
+    pub royalty:  Option<Royalty>,
+}
+
+/// The parameter type for the contract function `setTokenRoyalty`.
+#[derive(Serialize, SchemaType)]
+pub struct SetTokenRoyaltyParams {
+    /// The token.
+    pub token:   TokenInfo,
+    /// The royalty overriding the royalty of the collection, or `None` to
+    /// remove the override.
+    pub royalty: Option<Royalty>,
+}
+
+/// The parameter type for the contract function `distribute`.
+#[derive(Serialize, SchemaType)]
+pub struct DistributeParams {
+    /// The sold token.
+    pub token:  TokenInfo,
+    /// The account which sold the token.
+    pub seller: AccountAddress,
+}
+
+/// The event logged when proceeds are distributed.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct DistributeEvent {
+    /// The sold token.
+    pub token:    TokenInfo,
+    /// The account which sold the token.
+    pub seller:   AccountAddress,
+    /// The proceeds of the sale.
+    pub proceeds: Amount,
+    /// The creator receiving the royalty, if the token has a royalty.
+    pub creator:  Option<AccountAddress>,
+    /// The royalty paid to the creator.
+    pub royalty:  Amount,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum RoyaltyEvent {
+    /// The royalty of a collection was set or removed.
+    CollectionRoyalty(ContractAddress, Option<Royalty>),
+    /// The royalty override of a token was set or removed.
+    TokenRoyalty(TokenInfo, Option<Royalty>),
+    /// The proceeds of a sale were distributed.
+    Distribute(DistributeEvent),
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when the sender is not allowed to set the royalty.
+    Unauthorized,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+impl<S: HasStateApi> State<S> {
+    /// The royalty of a token, which is its override if there is one, and
+    /// otherwise the royalty of its collection.
+    fn royalty_of(&self, token: &TokenInfo) -> Option<Royalty> {
+        self.overrides
+            .get(token)
+            .or_else(|| self.collections.get(&token.contract))
+            .map(|royalty| *royalty)
+    }
+}
+
+/// Initialize the contract with an empty royalty table. The sender becomes the
+/// admin.
+#[init(contract = "royalties", event = "RoyaltyEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        admin:       ctx.init_origin(),
+        collections: state_builder.new_map(),
+        overrides:   state_builder.new_map(),
+    })
+}
+
+/// Set or remove the royalty of a collection.
+///
+/// It rejects if:
+/// - It fails to parse the parameter, e.g., the royalty is above 100%.
+/// - The sender is not the admin.
+/// - It fails to log the event.
+#[receive(
+    contract = "royalties",
+    name = "setCollectionRoyalty",
+    parameter = "SetCollectionRoyaltyParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_set_collection_royalty<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: SetCollectionRoyaltyParams = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.admin), ContractError::Unauthorized);
+    match params.royalty {
+        Some(royalty) => {
+            state.collections.insert(params.contract, royalty);
+        }
+        None => state.collections.remove(&params.contract),
+    }
+    logger.log(&RoyaltyEvent::CollectionRoyalty(params.contract, params.royalty))?;
+    Ok(())
+}
+
+/// Set or remove the royalty override of a token. Only the creator of the
+/// collection of the token can override its royalty.
+///
+/// It rejects if:
+/// - It fails to parse the parameter, e.g., the royalty is above 100%.
+/// - The collection of the token has no royalty, or the sender is not the
+///   creator of the collection.
+/// - It fails to log the event.
+#[receive(
+    contract = "royalties",
+    name = "setTokenRoyalty",
+    parameter = "SetTokenRoyaltyParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_set_token_royalty<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: SetTokenRoyaltyParams = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let is_creator = state
+        .collections
+        .get(&params.token.contract)
+        .map_or(false, |royalty| ctx.sender().matches_account(&royalty.creator));
+    ensure!(is_creator, ContractError::Unauthorized);
+    match params.royalty {
+        Some(royalty) => {
+            state.overrides.insert(params.token.clone(), royalty);
+        }
+        None => state.overrides.remove(&params.token),
+    }
+    logger.log(&RoyaltyEvent::TokenRoyalty(params.token, params.royalty))?;
+    Ok(())
+}
+
+/// Distribute the proceeds of a sale, sent along, between the seller and the
+/// creator receiving the royalty of the token.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - It fails to log the event.
+#[receive(
+    contract = "royalties",
+    name = "distribute",
+    parameter = "DistributeParams",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_distribute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    proceeds: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: DistributeParams = ctx.parameter_cursor().get()?;
+    let royalty = host.state().royalty_of(&params.token);
+    let royalty_amount = royalty.map_or(Amount::zero(), |royalty| royalty.royalty_bps.of(proceeds));
+
+    // The transfers always succeed, since the recipients are accounts and the
+    // proceeds were just received by the contract.
+    if let Some(royalty) = royalty {
+        if royalty_amount > Amount::zero() {
+            host.invoke_transfer(&royalty.creator, royalty_amount).unwrap_abort();
+        }
+    }
+    let seller_amount = proceeds - royalty_amount;
+    if seller_amount > Amount::zero() {
+        host.invoke_transfer(&params.seller, seller_amount).unwrap_abort();
+    }
+
+    logger.log(&RoyaltyEvent::Distribute(DistributeEvent {
+        token: params.token,
+        seller: params.seller,
+        proceeds,
+        creator: royalty.map(|royalty| royalty.creator),
+        royalty: royalty_amount,
+    }))?;
+    Ok(())
+}
+
+/// View the royalty of a token.
+#[receive(
+    contract = "royalties",
+    name = "royaltyOf",
+    parameter = "TokenInfo",
+    return_value = "Option<Royalty>"
+)]
+fn contract_royalty_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Royalty>> {
+    let token: TokenInfo = ctx.parameter_cursor().get()?;
+    Ok(host.state().royalty_of(&token))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ADMIN: AccountAddress = AccountAddress([0; 32]);
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const GUEST: AccountAddress = AccountAddress([2; 32]);
+    const SELLER: AccountAddress = AccountAddress([3; 32]);
+    const COLLECTION: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+
+    fn token(id: u8) -> TokenInfo {
+        TokenInfo {
+            contract: COLLECTION,
+            id:       TokenIdVec(vec![id]),
+        }
+    }
+
+    fn royalty(creator: AccountAddress, royalty_bps: u16) -> Royalty {
+        Royalty {
+            creator,
+            royalty_bps: BasisPoints::new(royalty_bps).expect_report("The royalty should be valid"),
+        }
+    }
+
+    fn ctx_with<'a>(sender: AccountAddress, parameter_bytes: &'a [u8]) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_parameter(parameter_bytes);
+        ctx
+    }
+
+    /// Create a host where the collection has a royalty of 5% to `CREATOR`.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(ADMIN);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter_bytes = to_bytes(&SetCollectionRoyaltyParams {
+            contract: COLLECTION,
+            royalty:  Some(royalty(CREATOR, 500)),
+        });
+        contract_set_collection_royalty(
+            &ctx_with(ADMIN, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        )
+        .expect_report("Setting the royalty should pass");
+        host
+    }
+
+    fn distribute(host: &mut TestHost<State<TestStateApi>>, token: TokenInfo, micro_ccd: u64) {
+        let proceeds = Amount::from_micro_ccd(micro_ccd);
+        let parameter_bytes = to_bytes(&DistributeParams {
+            token,
+            seller: SELLER,
+        });
+        host.set_self_balance(host.self_balance() + proceeds);
+        contract_distribute(
+            &ctx_with(SELLER, &parameter_bytes),
+            host,
+            proceeds,
+            &mut TestLogger::init(),
+        )
+        .expect_report("Distributing should pass");
+    }
+
+    #[concordium_test]
+    /// Test that the proceeds are split by the royalty of the collection.
+    fn test_distribute_collection_royalty() {
+        let mut host = new_host();
+        distribute(&mut host, token(1), 10_000);
+        claim_eq!(host.get_transfers(), [
+            (CREATOR, Amount::from_micro_ccd(500)),
+            (SELLER, Amount::from_micro_ccd(9_500))
+        ]);
+
+        let unknown = TokenInfo {
+            contract: ContractAddress {
+                index:    2,
+                subindex: 0,
+            },
+            id:       TokenIdVec(vec![1]),
+        };
+        let mut host = new_host();
+        distribute(&mut host, unknown, 10_000);
+        claim_eq!(
+            host.get_transfers(),
+            [(SELLER, Amount::from_micro_ccd(10_000))],
+            "Tokens without a royalty pay all to the seller"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that only the creator of the collection overrides the royalty of a
+    /// token, and the override applies to that token only.
+    fn test_token_override() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SetTokenRoyaltyParams {
+            token:   token(2),
+            royalty: Some(royalty(GUEST, 1_000)),
+        });
+        let res = contract_set_token_royalty(
+            &ctx_with(GUEST, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_set_token_royalty(
+            &ctx_with(CREATOR, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        )
+        .expect_report("Overriding should pass");
+
+        distribute(&mut host, token(2), 10_000);
+        distribute(&mut host, token(1), 10_000);
+        claim_eq!(host.get_transfers(), [
+            (GUEST, Amount::from_micro_ccd(1_000)),
+            (SELLER, Amount::from_micro_ccd(9_000)),
+            (CREATOR, Amount::from_micro_ccd(500)),
+            (SELLER, Amount::from_micro_ccd(9_500))
+        ]);
+    }
+
+    #[concordium_test]
+    /// Test that only the admin sets the royalty of a collection, and at most
+    /// 100%.
+    fn test_set_collection_royalty() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SetCollectionRoyaltyParams {
+            contract: COLLECTION,
+            royalty:  Some(royalty(CREATOR, 10_000)),
+        });
+        let res = contract_set_collection_royalty(
+            &ctx_with(CREATOR, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized));
+
+        // The parameter with a royalty of 10001 basis points, which is above 100%.
+        let parameter_bytes = to_bytes(&(COLLECTION, Some((CREATOR, 10_001u16))));
+        let res = contract_set_collection_royalty(
+            &ctx_with(ADMIN, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::ParseParams));
+    }
+}
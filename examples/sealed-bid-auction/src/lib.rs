@@ -0,0 +1,541 @@
+//! # Implementation of a sealed-bid auction smart contract
+//!
+//! The auction runs in two phases, such that no bidder can see the bids of the
+//! others before placing their own.
+//!
+//! In the commit phase, which lasts until `commit_end`, accounts invoke
+//! `commit` with a commitment to their bid, that is, the SHA2-256 hash of the
+//! message built by [`commitment_message`] from their address, the bid and a
+//! secret salt. Together with the commitment they send a deposit, which has to
+//! cover the bid. The deposit can be larger than the bid, to hide the size of
+//! the bid.
+//!
+//! In the reveal phase, which lasts from `commit_end` until `reveal_end`, the
+//! bidders invoke `reveal` with their bid and salt. The contract recomputes the
+//! hash using the crypto primitives of the host and checks it against the
+//! commitment. Whenever a revealed bid is not the highest so far, its deposit
+//! is refunded right away. The deposit of the highest bidder is kept until the
+//! auction is finalized.
+//!
+//! After `reveal_end`, any account can finalize the auction. The owner of the
+//! contract (the seller) receives the winning bid and the rest of the winner's
+//! deposit is refunded. The deposits of bidders who never revealed their bid
+//! are slashed and also sent to the seller. This can be done only once.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// A commitment to a bid, together with the deposit sent along with it.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Commitment {
+    /// The hash of the message built by [`commitment_message`].
+    hash:     HashSha2256,
+    /// The amount sent together with the commitment.
+    deposit:  Amount,
+    /// Whether the bid has been revealed.
+    revealed: bool,
+}
+
+/// The highest revealed bid.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct HighestBid {
+    /// The account which placed the bid.
+    bidder: AccountAddress,
+    /// The revealed bid.
+    amount: Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The item to be sold (to be displayed by the front-end).
+    item:        String,
+    /// The last time a commitment can be made.
+    commit_end:  Timestamp,
+    /// The last time a bid can be revealed.
+    reveal_end:  Timestamp,
+    /// The commitment of every bidder.
+    commitments: StateMap<AccountAddress, Commitment, S>,
+    /// The highest revealed bid so far; The variant `None` represents that no
+    /// bid has been revealed yet.
+    highest_bid: Option<HighestBid>,
+    /// Whether the auction has been finalized.
+    finalized:   bool,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The item to be sold.
+    item:       String,
+    /// The last time a commitment can be made.
+    commit_end: Timestamp,
+    /// The last time a bid can be revealed.
+    reveal_end: Timestamp,
+}
+
+/// Type of the parameter to the `reveal` function.
+#[derive(Serialize, SchemaType)]
+pub struct RevealParameter {
+    /// The committed bid.
+    pub bid:  Amount,
+    /// The secret salt used in the commitment.
+    pub salt: [u8; 32],
+}
+
+/// The `return_value` type of the `view` function.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The item to be sold.
+    item:        String,
+    /// The last time a commitment can be made.
+    commit_end:  Timestamp,
+    /// The last time a bid can be revealed.
+    reveal_end:  Timestamp,
+    /// The highest revealed bid so far.
+    highest_bid: Option<HighestBid>,
+    /// Whether the auction has been finalized.
+    finalized:   bool,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a reveal phase ending before the commit
+    /// phase.
+    InvalidPhases,
+    /// Raised when a contract tries to bid; Only accounts are allowed to bid.
+    OnlyAccount,
+    /// Raised when committing after the commit phase has ended.
+    CommitPhaseOver,
+    /// Raised when committing without a deposit.
+    ZeroDeposit,
+    /// Raised when an account commits a second time.
+    AlreadyCommitted,
+    /// Raised when revealing outside of the reveal phase.
+    NotRevealPhase,
+    /// Raised when revealing without a commitment.
+    NotCommitted,
+    /// Raised when revealing a bid a second time.
+    AlreadyRevealed,
+    /// Raised when the revealed bid and salt do not match the commitment.
+    InvalidReveal,
+    /// Raised when the revealed bid is larger than the deposit.
+    BidExceedsDeposit,
+    /// Raised when finalizing before the reveal phase has ended.
+    AuctionStillActive,
+    /// Raised when finalizing an auction that is already finalized.
+    AlreadyFinalized,
+}
+
+/// Build the message a bidder commits to, i.e., the concatenation of the
+/// address of the bidder, the bid in micro CCD as little-endian bytes, and the
+/// salt. The address is included so other accounts cannot copy a commitment.
+pub fn commitment_message(bidder: &AccountAddress, bid: Amount, salt: &[u8; 32]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(72);
+    message.extend_from_slice(&bidder.0);
+    message.extend_from_slice(&bid.micro_ccd.to_le_bytes());
+    message.extend_from_slice(salt);
+    message
+}
+
+/// Init function that creates a new sealed-bid auction.
+#[init(contract = "sealed_bid_auction", parameter = "InitParameter")]
+fn sealed_bid_auction_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.commit_end < parameter.reveal_end, ContractError::InvalidPhases.into());
+    Ok(State {
+        item:        parameter.item,
+        commit_end:  parameter.commit_end,
+        reveal_end:  parameter.reveal_end,
+        commitments: state_builder.new_map(),
+        highest_bid: None,
+        finalized:   false,
+    })
+}
+
+/// Commit to a bid. The amount sent along is kept as a deposit, which has to
+/// cover the bid when it is revealed.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The commit phase has ended.
+/// - No deposit is sent.
+/// - The sender has already committed to a bid.
+#[receive(
+    contract = "sealed_bid_auction",
+    name = "commit",
+    parameter = "HashSha2256",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn sealed_bid_auction_commit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> Result<(), ContractError> {
+    let hash: HashSha2256 = ctx.parameter_cursor().get()?;
+    let bidder = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() <= state.commit_end, ContractError::CommitPhaseOver);
+    ensure!(amount > Amount::zero(), ContractError::ZeroDeposit);
+
+    state.commitments.entry(bidder).vacant_or(ContractError::AlreadyCommitted)?.insert(
+        Commitment {
+            hash,
+            deposit: amount,
+            revealed: false,
+        },
+    );
+    Ok(())
+}
+
+/// Reveal a committed bid. If the bid is not the highest so far, the deposit
+/// is refunded. If it is the highest, the deposit of the previous highest
+/// bidder is refunded instead. Ties are won by the bid revealed first.
+///
+/// A bid exceeding the deposit is rejected, meaning the deposit is slashed
+/// when the auction is finalized.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - It is not the reveal phase.
+/// - The sender has not committed, or has already revealed.
+/// - The bid and salt do not match the commitment.
+/// - The bid is larger than the deposit.
+#[receive(
+    contract = "sealed_bid_auction",
+    name = "reveal",
+    parameter = "RevealParameter",
+    error = "ContractError",
+    crypto_primitives,
+    mutable
+)]
+fn sealed_bid_auction_reveal<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> Result<(), ContractError> {
+    let params: RevealParameter = ctx.parameter_cursor().get()?;
+    let bidder = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    let slot_time = ctx.metadata().slot_time();
+    ensure!(
+        state.commit_end < slot_time && slot_time <= state.reveal_end,
+        ContractError::NotRevealPhase
+    );
+
+    let deposit = {
+        let mut commitment =
+            state.commitments.entry(bidder).occupied_or(ContractError::NotCommitted)?;
+        ensure!(!commitment.revealed, ContractError::AlreadyRevealed);
+        let hash =
+            crypto_primitives.hash_sha2_256(&commitment_message(&bidder, params.bid, &params.salt));
+        ensure_eq!(hash, commitment.hash, ContractError::InvalidReveal);
+        ensure!(params.bid <= commitment.deposit, ContractError::BidExceedsDeposit);
+        commitment.revealed = true;
+        commitment.deposit
+    };
+
+    let is_highest = match state.highest_bid {
+        Some(highest) => params.bid > highest.amount,
+        None => true,
+    };
+    // These transfers (given enough NRG of course) always succeed, since the
+    // bidders are existing accounts and the contract holds their deposits.
+    if is_highest {
+        let previous = state.highest_bid.replace(HighestBid {
+            bidder,
+            amount: params.bid,
+        });
+        if let Some(previous) = previous {
+            let previous_deposit =
+                state.commitments.get(&previous.bidder).map(|c| c.deposit).unwrap_abort();
+            host.invoke_transfer(&previous.bidder, previous_deposit).unwrap_abort();
+        }
+    } else {
+        host.invoke_transfer(&bidder, deposit).unwrap_abort();
+    }
+    Ok(())
+}
+
+/// Finalize the auction. The seller receives the winning bid together with the
+/// slashed deposits of the bidders who did not reveal, and the rest of the
+/// deposit of the winner is refunded.
+///
+/// It rejects if:
+/// - The reveal phase has not ended.
+/// - The auction has already been finalized.
+#[receive(contract = "sealed_bid_auction", name = "finalize", error = "ContractError", mutable)]
+fn sealed_bid_auction_finalize<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let state = host.state_mut();
+    ensure!(!state.finalized, ContractError::AlreadyFinalized);
+    ensure!(ctx.metadata().slot_time() > state.reveal_end, ContractError::AuctionStillActive);
+    state.finalized = true;
+
+    let slashed = state
+        .commitments
+        .iter()
+        .filter(|(_, commitment)| !commitment.revealed)
+        .fold(Amount::zero(), |sum, (_, commitment)| sum + commitment.deposit);
+
+    let (proceeds, refund) = match state.highest_bid {
+        Some(highest) => {
+            let deposit = state.commitments.get(&highest.bidder).map(|c| c.deposit).unwrap_abort();
+            (slashed + highest.amount, Some((highest.bidder, deposit - highest.amount)))
+        }
+        None => (slashed, None),
+    };
+
+    // These transfers (given enough NRG of course) always succeed, since the
+    // owner and the winner are existing accounts, and the contract holds the
+    // deposits.
+    if proceeds > Amount::zero() {
+        host.invoke_transfer(&ctx.owner(), proceeds).unwrap_abort();
+    }
+    if let Some((winner, refund)) = refund {
+        if refund > Amount::zero() {
+            host.invoke_transfer(&winner, refund).unwrap_abort();
+        }
+    }
+    Ok(())
+}
+
+/// View function that returns the general information about the auction.
+#[receive(contract = "sealed_bid_auction", name = "view", return_value = "ViewState")]
+fn sealed_bid_auction_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        item:        state.item.clone(),
+        commit_end:  state.commit_end,
+        reveal_end:  state.reveal_end,
+        highest_bid: state.highest_bid,
+        finalized:   state.finalized,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const SELLER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CAROL: AccountAddress = AccountAddress([3; 32]);
+    const COMMIT_END: u64 = 10;
+    const REVEAL_END: u64 = 20;
+    const SALT: [u8; 32] = [42; 32];
+
+    fn commitment(bidder: AccountAddress, bid: u64) -> HashSha2256 {
+        TestCryptoPrimitives::new().hash_sha2_256(&commitment_message(
+            &bidder,
+            Amount::from_micro_ccd(bid),
+            &SALT,
+        ))
+    }
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            item:       "The Scream by Munch".into(),
+            commit_end: Timestamp::from_timestamp_millis(COMMIT_END),
+            reveal_end: Timestamp::from_timestamp_millis(REVEAL_END),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = sealed_bid_auction_init(&ctx, &mut state_builder)
+            .expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(sender: AccountAddress, slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_at(sender, slot_time);
+        ctx.set_owner(SELLER);
+        ctx
+    }
+
+    fn commit(
+        host: &mut TestHost<State<TestStateApi>>,
+        bidder: AccountAddress,
+        hash: HashSha2256,
+        deposit: u64,
+    ) -> Result<(), ContractError> {
+        let parameter_bytes = to_bytes(&hash);
+        let mut ctx = ctx_at(bidder, COMMIT_END);
+        ctx.set_parameter(&parameter_bytes);
+        let deposit = Amount::from_micro_ccd(deposit);
+        host.set_self_balance(host.self_balance() + deposit);
+        sealed_bid_auction_commit(&ctx, host, deposit)
+    }
+
+    fn reveal(
+        host: &mut TestHost<State<TestStateApi>>,
+        bidder: AccountAddress,
+        bid: u64,
+        slot_time: u64,
+    ) -> Result<(), ContractError> {
+        let parameter_bytes = to_bytes(&RevealParameter {
+            bid:  Amount::from_micro_ccd(bid),
+            salt: SALT,
+        });
+        let mut ctx = ctx_at(bidder, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        sealed_bid_auction_reveal(&ctx, host, &TestCryptoPrimitives::new())
+    }
+
+    fn finalize(host: &mut TestHost<State<TestStateApi>>) -> Result<(), ContractError> {
+        sealed_bid_auction_finalize(&ctx_at(CAROL, REVEAL_END + 1), host)
+    }
+
+    #[concordium_test]
+    /// Test a full auction: Alice and Bob reveal, Carol does not. Alice is
+    /// refunded when Bob reveals a higher bid, and Carol's deposit is slashed.
+    fn test_full_auction() {
+        let mut host = new_host();
+
+        commit(&mut host, ALICE, commitment(ALICE, 100), 150).expect_report("Commit Alice");
+        commit(&mut host, BOB, commitment(BOB, 300), 400).expect_report("Commit Bob");
+        commit(&mut host, CAROL, commitment(CAROL, 500), 500).expect_report("Commit Carol");
+
+        reveal(&mut host, ALICE, 100, COMMIT_END + 1).expect_report("Reveal Alice");
+        reveal(&mut host, BOB, 300, REVEAL_END).expect_report("Reveal Bob");
+
+        let res = reveal(&mut host, CAROL, 500, REVEAL_END + 1);
+        claim_eq!(res, Err(ContractError::NotRevealPhase), "Revealing too late should fail");
+
+        let res = finalize(&mut host);
+        claim_eq!(res, Ok(()), "Finalizing should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(150)),
+            (SELLER, Amount::from_micro_ccd(800)),
+            (BOB, Amount::from_micro_ccd(100)),
+        ]);
+        claim_eq!(host.self_balance(), Amount::zero(), "The contract should keep nothing");
+
+        let view = sealed_bid_auction_view(&ctx_at(CAROL, REVEAL_END + 1), &host)
+            .expect_report("View should pass");
+        claim_eq!(
+            view.highest_bid,
+            Some(HighestBid {
+                bidder: BOB,
+                amount: Amount::from_micro_ccd(300),
+            }),
+            "Bob should win"
+        );
+        claim!(view.finalized, "The auction should be finalized");
+
+        let res = finalize(&mut host);
+        claim_eq!(res, Err(ContractError::AlreadyFinalized), "Finalizing twice should fail");
+    }
+
+    #[concordium_test]
+    /// Test that a lower bid revealed after the highest one is refunded right
+    /// away, and that ties are won by the first reveal.
+    fn test_losers_refunded() {
+        let mut host = new_host();
+
+        commit(&mut host, ALICE, commitment(ALICE, 300), 300).expect_report("Commit Alice");
+        commit(&mut host, BOB, commitment(BOB, 300), 300).expect_report("Commit Bob");
+        commit(&mut host, CAROL, commitment(CAROL, 100), 200).expect_report("Commit Carol");
+
+        reveal(&mut host, ALICE, 300, COMMIT_END + 1).expect_report("Reveal Alice");
+        reveal(&mut host, BOB, 300, COMMIT_END + 1).expect_report("Reveal Bob");
+        reveal(&mut host, CAROL, 100, COMMIT_END + 1).expect_report("Reveal Carol");
+
+        claim_eq!(host.get_transfers(), [
+            (BOB, Amount::from_micro_ccd(300)),
+            (CAROL, Amount::from_micro_ccd(200)),
+        ]);
+        claim_eq!(host.state().highest_bid.map(|h| h.bidder), Some(ALICE), "Alice should win");
+    }
+
+    #[concordium_test]
+    /// Test that reveals not matching the commitment, or exceeding the deposit,
+    /// are rejected.
+    fn test_invalid_reveal() {
+        let mut host = new_host();
+
+        commit(&mut host, ALICE, commitment(ALICE, 100), 100).expect_report("Commit Alice");
+        // Bob copies the commitment of Alice.
+        commit(&mut host, BOB, commitment(ALICE, 100), 100).expect_report("Commit Bob");
+        commit(&mut host, CAROL, commitment(CAROL, 500), 100).expect_report("Commit Carol");
+
+        let res = reveal(&mut host, ALICE, 200, COMMIT_END + 1);
+        claim_eq!(res, Err(ContractError::InvalidReveal), "Revealing another bid should fail");
+
+        let res = reveal(&mut host, BOB, 100, COMMIT_END + 1);
+        claim_eq!(res, Err(ContractError::InvalidReveal), "A copied commitment should fail");
+
+        let res = reveal(&mut host, CAROL, 500, COMMIT_END + 1);
+        claim_eq!(res, Err(ContractError::BidExceedsDeposit), "Underfunded bid should fail");
+
+        reveal(&mut host, ALICE, 100, COMMIT_END + 1).expect_report("Reveal Alice");
+        let res = reveal(&mut host, ALICE, 100, COMMIT_END + 1);
+        claim_eq!(res, Err(ContractError::AlreadyRevealed), "Revealing twice should fail");
+    }
+
+    #[concordium_test]
+    /// Test the phase boundaries of committing, revealing and finalizing.
+    fn test_phases() {
+        let mut host = new_host();
+
+        commit(&mut host, ALICE, commitment(ALICE, 100), 100).expect_report("Commit Alice");
+        let res = commit(&mut host, ALICE, commitment(ALICE, 200), 200);
+        claim_eq!(res, Err(ContractError::AlreadyCommitted), "Committing twice should fail");
+        let res = commit(&mut host, BOB, commitment(BOB, 0), 0);
+        claim_eq!(res, Err(ContractError::ZeroDeposit), "Committing without deposit should fail");
+
+        let parameter_bytes = to_bytes(&commitment(BOB, 100));
+        let mut ctx = ctx_at(BOB, COMMIT_END + 1);
+        ctx.set_parameter(&parameter_bytes);
+        let res = sealed_bid_auction_commit(&ctx, &mut host, Amount::from_micro_ccd(100));
+        claim_eq!(res, Err(ContractError::CommitPhaseOver), "Committing too late should fail");
+
+        let res = reveal(&mut host, ALICE, 100, COMMIT_END);
+        claim_eq!(res, Err(ContractError::NotRevealPhase), "Revealing too early should fail");
+        let res = reveal(&mut host, BOB, 100, COMMIT_END + 1);
+        claim_eq!(res, Err(ContractError::NotCommitted), "Revealing without commit should fail");
+
+        let res = sealed_bid_auction_finalize(&ctx_at(CAROL, REVEAL_END), &mut host);
+        claim_eq!(res, Err(ContractError::AuctionStillActive), "Finalizing early should fail");
+    }
+
+    #[concordium_test]
+    /// Test that finalizing without any reveals slashes all deposits.
+    fn test_no_reveals() {
+        let mut host = new_host();
+
+        commit(&mut host, ALICE, commitment(ALICE, 100), 100).expect_report("Commit Alice");
+        commit(&mut host, BOB, commitment(BOB, 200), 250).expect_report("Commit Bob");
+
+        finalize(&mut host).expect_report("Finalizing should pass");
+
+        claim_eq!(host.get_transfers(), [(SELLER, Amount::from_micro_ccd(350))]);
+    }
+}
@@ -0,0 +1,551 @@
+//! # Escrow for scheduled livestream sessions
+//!
+//! Streamers schedule paid sessions with `createSession`, giving the price of a
+//! ticket and when the session starts. Viewers pay for a ticket with
+//! `buyTicket` before the session starts, and the payments are held by the
+//! contract until the outcome of the session is known:
+//!
+//! - Once the session has started, the streamer can mark it as delivered with
+//!   `markDelivered`, or the oracle, an account trusted to observe the streams,
+//!   can attest that it happened with `attest`. Either way, the payments are
+//!   released to the streamer.
+//! - The streamer can cancel the session with `cancel` until it is delivered,
+//!   after which the viewers reclaim their payments with `reclaim`.
+//! - If the session has not been delivered when the grace period after its
+//!   start has passed, it can no longer be delivered and the viewers reclaim
+//!   their payments with `reclaim`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// Identifies a session in this contract.
+pub type SessionId = u64;
+
+/// The status of a session.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionStatus {
+    /// The session is scheduled, and the payments are held by the contract.
+    Scheduled,
+    /// The session was delivered, and the payments released to the streamer.
+    Delivered,
+    /// The streamer cancelled the session, and the viewers can reclaim their
+    /// payments.
+    Cancelled,
+}
+
+/// A scheduled session.
+#[derive(Serialize, SchemaType, Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    /// The streamer, who receives the payments when the session is delivered.
+    pub streamer:  AccountAddress,
+    /// The price of a ticket.
+    pub price:     Amount,
+    /// When the session starts.
+    pub starts_at: Timestamp,
+    /// The status of the session.
+    pub status:    SessionStatus,
+    /// The payments held for the session.
+    pub held:      Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The account which can attest that sessions happened.
+    oracle:          AccountAddress,
+    /// How long after its start a session can be delivered.
+    grace_period:    Duration,
+    /// The sessions.
+    sessions:        StateMap<SessionId, Session, S>,
+    /// The viewers which paid for a ticket to a session, with their payment.
+    tickets:         StateMap<(SessionId, AccountAddress), Amount, S>,
+    /// The ID of the next session.
+    next_session_id: SessionId,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The account which can attest that sessions happened.
+    pub oracle:       AccountAddress,
+    /// How long after its start a session can be delivered.
+    pub grace_period: Duration,
+}
+
+/// The parameter type for the contract function `createSession`.
+#[derive(Serialize, SchemaType)]
+pub struct CreateSessionParams {
+    /// The price of a ticket.
+    pub price:     Amount,
+    /// When the session starts.
+    pub starts_at: Timestamp,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when a contract creates a session, buys a ticket or reclaims a
+    /// refund.
+    OnlyAccount,
+    /// Raised when there is no session with the given ID.
+    UnknownSession,
+    /// Raised when creating a session which has already started.
+    StartInThePast,
+    /// Raised when buying a ticket after the session started.
+    SessionStarted,
+    /// Raised when the CCD sent along is not the price of a ticket.
+    WrongPayment,
+    /// Raised when the viewer already paid for a ticket.
+    AlreadyPaid,
+    /// Raised when the sender is neither the streamer nor the oracle, as
+    /// required.
+    Unauthorized,
+    /// Raised when delivering a session before it started.
+    NotStarted,
+    /// Raised when delivering a session after the grace period.
+    GracePeriodOver,
+    /// Raised when the session is no longer scheduled.
+    NotScheduled,
+    /// Raised when reclaiming a payment for a session which was delivered, or
+    /// could still be.
+    NotRefundable,
+    /// Raised when the viewer has no payment to reclaim.
+    NoTicket,
+    /// Raised when the end of the grace period cannot be represented.
+    Overflow,
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// The end of the grace period of a session, after which it can no longer be
+/// delivered.
+fn grace_period_end(session: &Session, grace_period: Duration) -> ContractResult<Timestamp> {
+    session.starts_at.checked_add(grace_period).ok_or(ContractError::Overflow)
+}
+
+/// Initialize the contract with the oracle and the grace period.
+#[init(contract = "session_escrow", parameter = "InitParams")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    Ok(State {
+        oracle:          params.oracle,
+        grace_period:    params.grace_period,
+        sessions:        state_builder.new_map(),
+        tickets:         state_builder.new_map(),
+        next_session_id: 0,
+    })
+}
+
+/// Schedule a session with the sender as the streamer, returning the ID of the
+/// session.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The session starts in the past.
+#[receive(
+    contract = "session_escrow",
+    name = "createSession",
+    parameter = "CreateSessionParams",
+    return_value = "SessionId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_create_session<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<SessionId> {
+    let params: CreateSessionParams = ctx.parameter_cursor().get()?;
+    let streamer = sender_account(ctx)?;
+    ensure!(params.starts_at > ctx.metadata().slot_time(), ContractError::StartInThePast);
+
+    let state = host.state_mut();
+    let session_id = state.next_session_id;
+    state.next_session_id += 1;
+    state.sessions.insert(session_id, Session {
+        streamer,
+        price: params.price,
+        starts_at: params.starts_at,
+        status: SessionStatus::Scheduled,
+        held: Amount::zero(),
+    });
+    Ok(session_id)
+}
+
+/// Pay for a ticket to a session, sending along the price of the ticket.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The session does not exist, or is no longer scheduled.
+/// - The session has started.
+/// - The amount sent along is not the price of a ticket.
+/// - The sender already paid for a ticket.
+#[receive(
+    contract = "session_escrow",
+    name = "buyTicket",
+    parameter = "SessionId",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn contract_buy_ticket<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let session_id: SessionId = ctx.parameter_cursor().get()?;
+    let viewer = sender_account(ctx)?;
+    let state = host.state_mut();
+    let mut session = state.sessions.get_mut(&session_id).ok_or(ContractError::UnknownSession)?;
+    ensure_eq!(session.status, SessionStatus::Scheduled, ContractError::NotScheduled);
+    ensure!(ctx.metadata().slot_time() < session.starts_at, ContractError::SessionStarted);
+    ensure_eq!(amount, session.price, ContractError::WrongPayment);
+
+    state.tickets.entry((session_id, viewer)).vacant_or(ContractError::AlreadyPaid)?.insert(amount);
+    session.held += amount;
+    Ok(())
+}
+
+/// Release the payments for a session to the streamer.
+///
+/// It rejects if:
+/// - The session does not exist, or is no longer scheduled.
+/// - The session has not started.
+/// - The grace period of the session is over.
+/// - The sender is not allowed to release the payments.
+fn deliver<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    session_id: SessionId,
+    is_authorized: impl FnOnce(&State<S>, &Session) -> bool,
+) -> ContractResult<()> {
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    let grace_period = state.grace_period;
+    let (streamer, held) = {
+        let session = state.sessions.get(&session_id).ok_or(ContractError::UnknownSession)?;
+        ensure!(is_authorized(state, &session), ContractError::Unauthorized);
+        ensure_eq!(session.status, SessionStatus::Scheduled, ContractError::NotScheduled);
+        ensure!(now >= session.starts_at, ContractError::NotStarted);
+        ensure!(now < grace_period_end(&session, grace_period)?, ContractError::GracePeriodOver);
+        (session.streamer, session.held)
+    };
+    state.sessions.entry(session_id).and_modify(|session| {
+        session.status = SessionStatus::Delivered;
+        session.held = Amount::zero();
+    });
+    // The transfer always succeeds, since the streamer is an account and the
+    // payments are held by the contract.
+    host.invoke_transfer(&streamer, held).unwrap_abort();
+    Ok(())
+}
+
+/// Mark a session as delivered, releasing the payments to the streamer. Only
+/// the streamer can mark a session as delivered.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The session does not exist, or is no longer scheduled.
+/// - The sender is not the streamer.
+/// - The session has not started.
+/// - The grace period of the session is over.
+#[receive(
+    contract = "session_escrow",
+    name = "markDelivered",
+    parameter = "SessionId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_mark_delivered<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let session_id: SessionId = ctx.parameter_cursor().get()?;
+    deliver(ctx, host, session_id, |_, session| ctx.sender().matches_account(&session.streamer))
+}
+
+/// Attest that a session happened, releasing the payments to the streamer.
+/// Only the oracle can attest sessions.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The session does not exist, or is no longer scheduled.
+/// - The sender is not the oracle.
+/// - The session has not started.
+/// - The grace period of the session is over.
+#[receive(
+    contract = "session_escrow",
+    name = "attest",
+    parameter = "SessionId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_attest<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let session_id: SessionId = ctx.parameter_cursor().get()?;
+    deliver(ctx, host, session_id, |state, _| ctx.sender().matches_account(&state.oracle))
+}
+
+/// Cancel a session, letting the viewers reclaim their payments. Only the
+/// streamer can cancel a session.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The session does not exist, or is no longer scheduled.
+/// - The sender is not the streamer.
+#[receive(
+    contract = "session_escrow",
+    name = "cancel",
+    parameter = "SessionId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_cancel<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let session_id: SessionId = ctx.parameter_cursor().get()?;
+    let mut session =
+        host.state_mut().sessions.get_mut(&session_id).ok_or(ContractError::UnknownSession)?;
+    ensure!(ctx.sender().matches_account(&session.streamer), ContractError::Unauthorized);
+    ensure_eq!(session.status, SessionStatus::Scheduled, ContractError::NotScheduled);
+    session.status = SessionStatus::Cancelled;
+    Ok(())
+}
+
+/// Reclaim the payment for a ticket to a session, which was cancelled or not
+/// delivered within the grace period.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The session does not exist.
+/// - The session was delivered, or is scheduled and the grace period is not
+///   over.
+/// - The sender has no payment to reclaim.
+#[receive(
+    contract = "session_escrow",
+    name = "reclaim",
+    parameter = "SessionId",
+    error = "ContractError",
+    mutable
+)]
+fn contract_reclaim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let session_id: SessionId = ctx.parameter_cursor().get()?;
+    let viewer = sender_account(ctx)?;
+    let now = ctx.metadata().slot_time();
+    let state = host.state_mut();
+    let grace_period = state.grace_period;
+    let mut session = state.sessions.get_mut(&session_id).ok_or(ContractError::UnknownSession)?;
+    let refundable = match session.status {
+        SessionStatus::Scheduled => now >= grace_period_end(&session, grace_period)?,
+        SessionStatus::Delivered => false,
+        SessionStatus::Cancelled => true,
+    };
+    ensure!(refundable, ContractError::NotRefundable);
+
+    let amount =
+        state.tickets.remove_and_get(&(session_id, viewer)).ok_or(ContractError::NoTicket)?;
+    session.held -= amount;
+    drop(session);
+    // The transfer always succeeds, since the viewer is an account and the
+    // payment is held by the contract.
+    host.invoke_transfer(&viewer, amount).unwrap_abort();
+    Ok(())
+}
+
+/// View a session.
+#[receive(
+    contract = "session_escrow",
+    name = "session",
+    parameter = "SessionId",
+    return_value = "Option<Session>"
+)]
+fn contract_session<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Session>> {
+    let session_id: SessionId = ctx.parameter_cursor().get()?;
+    Ok(host.state().sessions.get(&session_id).map(|session| session.clone()))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const ORACLE: AccountAddress = AccountAddress([0; 32]);
+    const STREAMER: AccountAddress = AccountAddress([1; 32]);
+    const VIEWER_1: AccountAddress = AccountAddress([2; 32]);
+    const VIEWER_2: AccountAddress = AccountAddress([3; 32]);
+    const PRICE: Amount = Amount::from_micro_ccd(1_000);
+    const STARTS_AT: u64 = 10_000;
+    const GRACE_PERIOD: u64 = 5_000;
+    const SESSION: SessionId = 0;
+
+    /// Create a host with a session by `STREAMER`, which both viewers paid
+    /// for.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            oracle:       ORACLE,
+            grace_period: Duration::from_millis(GRACE_PERIOD),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+
+        let parameter_bytes = to_bytes(&CreateSessionParams {
+            price:     PRICE,
+            starts_at: Timestamp::from_timestamp_millis(STARTS_AT),
+        });
+        let session_id =
+            contract_create_session(&ctx_with_parameter(STREAMER, 0, &parameter_bytes), &mut host)
+                .expect_report("Creating the session should pass");
+        claim_eq!(session_id, SESSION);
+
+        let parameter_bytes = to_bytes(&SESSION);
+        for viewer in [VIEWER_1, VIEWER_2] {
+            host.set_self_balance(host.self_balance() + PRICE);
+            contract_buy_ticket(&ctx_with_parameter(viewer, 0, &parameter_bytes), &mut host, PRICE)
+                .expect_report("Buying a ticket should pass");
+        }
+        host
+    }
+
+    #[concordium_test]
+    /// Test that tickets can only be bought before the session starts, for the
+    /// price, once.
+    fn test_buy_ticket() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SESSION);
+        let res = contract_buy_ticket(
+            &ctx_with_parameter(VIEWER_1, 0, &parameter_bytes),
+            &mut host,
+            PRICE,
+        );
+        claim_eq!(res, Err(ContractError::AlreadyPaid));
+        let res = contract_buy_ticket(
+            &ctx_with_parameter(ORACLE, 0, &parameter_bytes),
+            &mut host,
+            Amount::zero(),
+        );
+        claim_eq!(res, Err(ContractError::WrongPayment));
+        let res = contract_buy_ticket(
+            &ctx_with_parameter(ORACLE, STARTS_AT, &parameter_bytes),
+            &mut host,
+            PRICE,
+        );
+        claim_eq!(res, Err(ContractError::SessionStarted));
+        let session = host.state().sessions.get(&SESSION).map(|s| s.clone());
+        claim_eq!(session.map(|s| s.held), Some(Amount::from_micro_ccd(2_000)));
+    }
+
+    #[concordium_test]
+    /// Test that the payments are released to the streamer when the session is
+    /// marked as delivered within the grace period.
+    fn test_mark_delivered() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SESSION);
+        let res = contract_mark_delivered(
+            &ctx_with_parameter(STREAMER, STARTS_AT - 1, &parameter_bytes),
+            &mut host,
+        );
+        claim_eq!(res, Err(ContractError::NotStarted));
+        let res = contract_mark_delivered(
+            &ctx_with_parameter(VIEWER_1, STARTS_AT, &parameter_bytes),
+            &mut host,
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_mark_delivered(
+            &ctx_with_parameter(STREAMER, STARTS_AT, &parameter_bytes),
+            &mut host,
+        )
+        .expect_report("Marking as delivered should pass");
+
+        claim_eq!(host.get_transfers(), [(STREAMER, Amount::from_micro_ccd(2_000))]);
+        let res = contract_reclaim(
+            &ctx_with_parameter(VIEWER_1, STARTS_AT + GRACE_PERIOD, &parameter_bytes),
+            &mut host,
+        );
+        claim_eq!(res, Err(ContractError::NotRefundable));
+        let res =
+            contract_attest(&ctx_with_parameter(ORACLE, STARTS_AT, &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::NotScheduled), "The payments are released once");
+    }
+
+    #[concordium_test]
+    /// Test that the oracle can attest the session, but not after the grace
+    /// period, after which the viewers reclaim their payments.
+    fn test_attest_and_reclaim() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SESSION);
+        let deadline = STARTS_AT + GRACE_PERIOD;
+        let res = contract_reclaim(
+            &ctx_with_parameter(VIEWER_1, deadline - 1, &parameter_bytes),
+            &mut host,
+        );
+        claim_eq!(res, Err(ContractError::NotRefundable));
+        let res =
+            contract_attest(&ctx_with_parameter(ORACLE, deadline, &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::GracePeriodOver));
+
+        contract_reclaim(&ctx_with_parameter(VIEWER_1, deadline, &parameter_bytes), &mut host)
+            .expect_report("Reclaiming should pass");
+        let res =
+            contract_reclaim(&ctx_with_parameter(VIEWER_1, deadline, &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::NoTicket));
+        claim_eq!(host.get_transfers(), [(VIEWER_1, PRICE)]);
+
+        let mut host = new_host();
+        contract_attest(&ctx_with_parameter(ORACLE, deadline - 1, &parameter_bytes), &mut host)
+            .expect_report("Attesting should pass");
+        claim_eq!(host.get_transfers(), [(STREAMER, Amount::from_micro_ccd(2_000))]);
+    }
+
+    #[concordium_test]
+    /// Test that the viewers reclaim their payments as soon as the streamer
+    /// cancels the session.
+    fn test_cancel() {
+        let mut host = new_host();
+        let parameter_bytes = to_bytes(&SESSION);
+        let res = contract_cancel(&ctx_with_parameter(ORACLE, 0, &parameter_bytes), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_cancel(&ctx_with_parameter(STREAMER, 0, &parameter_bytes), &mut host)
+            .expect_report("Cancelling should pass");
+
+        let res = contract_mark_delivered(
+            &ctx_with_parameter(STREAMER, STARTS_AT, &parameter_bytes),
+            &mut host,
+        );
+        claim_eq!(res, Err(ContractError::NotScheduled));
+        for viewer in [VIEWER_1, VIEWER_2] {
+            contract_reclaim(&ctx_with_parameter(viewer, 0, &parameter_bytes), &mut host)
+                .expect_report("Reclaiming should pass");
+        }
+        claim_eq!(host.get_transfers(), [(VIEWER_1, PRICE), (VIEWER_2, PRICE)]);
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+}
@@ -0,0 +1,435 @@
+//! A counter smart contract example supporting sponsored transactions, using
+//! the `permit` entrypoint of the CIS-3 standard.
+//!
+//! # Description
+//! Every account has a counter, which it can `increment`. Instead of sending
+//! the transaction itself, an account can sign a permit message describing
+//! the call, and have a sponsor, like a service account, submit the message
+//! using `permit`. The sponsor pays the transaction fees, while the contract
+//! executes the call on behalf of the signer.
+//!
+//! A permit message is only valid for this contract instance, a single
+//! entrypoint, until its timestamp, and with the current nonce of the signer,
+//! which is incremented by every permit, preventing replays. The message is
+//! signed with the keys of the account, and the signatures are checked against
+//! the public keys and thresholds of the account on chain.
+//!
+//! Anyone can query the counter of an account with `view`, the nonce of an
+//! account with `nonceOf`, and which entrypoints can be called through a permit
+//! with `supportsPermit`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// The entrypoints which can be called through a permit.
+const PERMIT_ENTRYPOINTS: [&str; 1] = ["increment"];
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The counter of every account which has incremented it.
+    counters: StateMap<AccountAddress, u64, S>,
+    /// The nonce of the next permit of every account which has used a
+    /// permit.
+    nonces:   StateMap<AccountAddress, u64, S>,
+}
+
+/// The parameter type for the contract function `permit`.
+#[derive(Serialize, SchemaType)]
+pub struct PermitParam {
+    /// The signatures on the message by the keys of the signer.
+    pub signature: AccountSignatures,
+    /// The account which signed the message.
+    pub signer:    AccountAddress,
+    /// The signed message.
+    pub message:   PermitMessage,
+}
+
+/// The parameter type for the contract function `supportsPermit`.
+#[derive(Serialize, SchemaType)]
+pub struct SupportsPermitQueryParams {
+    /// The entrypoints to query.
+    #[concordium(size_length = 2)]
+    pub queries: Vec<OwnedEntrypointName>,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum CounterEvent {
+    /// The counter of an account was incremented to a new value.
+    Increment {
+        account: AccountAddress,
+        value:   u64,
+    },
+    /// A permit of an account with the given nonce was executed.
+    Nonce {
+        account: AccountAddress,
+        nonce:   u64,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract calls an entrypoint for accounts only.
+    OnlyAccount,
+    /// Raised when incrementing would overflow the counter.
+    Overflow,
+    /// Raised when the signer does not exist.
+    MissingAccount,
+    /// Raised when the signatures could not be parsed by the chain.
+    MalformedSignature,
+    /// Raised when the message is meant for another contract instance.
+    WrongContract,
+    /// Raised when the entrypoint of the message cannot be called through a
+    /// permit.
+    WrongEntryPoint,
+    /// Raised when the message can no longer be used.
+    Expired,
+    /// Raised when the nonce of the message is not the nonce of the signer.
+    NonceMismatch,
+    /// Raised when the signatures are not valid for the keys of the signer.
+    WrongSignature,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the errors of checking signatures to ContractError.
+impl From<CheckAccountSignatureError> for ContractError {
+    fn from(e: CheckAccountSignatureError) -> Self {
+        match e {
+            CheckAccountSignatureError::MissingAccount => Self::MissingAccount,
+            CheckAccountSignatureError::MalformedData => Self::MalformedSignature,
+        }
+    }
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+/// Check whether the entrypoint can be called through a permit.
+fn is_permit_entrypoint(entry_point: &OwnedEntrypointName) -> bool {
+    PERMIT_ENTRYPOINTS
+        .iter()
+        .any(|name| entry_point.as_entrypoint_name() == EntrypointName::new_unchecked(name))
+}
+
+/// Increment the counter of the account, whether the account called
+/// `increment` directly or through a permit.
+fn increment_for<S: HasStateApi>(
+    state: &mut State<S>,
+    account: AccountAddress,
+    by: u64,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let mut counter = state.counters.entry(account).or_insert(0);
+    *counter = counter.checked_add(by).ok_or(ContractError::Overflow)?;
+    logger.log(&CounterEvent::Increment {
+        account,
+        value: *counter,
+    })?;
+    Ok(())
+}
+
+// Contract functions
+
+/// Initialize the contract with no counters.
+#[init(contract = "sponsored_counter", event = "CounterEvent")]
+fn init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        counters: state_builder.new_map(),
+        nonces:   state_builder.new_map(),
+    })
+}
+
+/// Increment the counter of the sender by the given amount.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The counter would overflow.
+/// - It fails to log the event.
+#[receive(
+    contract = "sponsored_counter",
+    name = "increment",
+    parameter = "u64",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn increment<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let by: u64 = ctx.parameter_cursor().get()?;
+    let account = sender_account(ctx)?;
+    increment_for(host.state_mut(), account, by, logger)
+}
+
+/// Execute a call signed by an account on its behalf. Anyone can submit the
+/// signed message.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The message is meant for another contract instance.
+/// - The message has expired.
+/// - The signer does not exist.
+/// - The nonce of the message is not the nonce of the signer.
+/// - The signatures are not valid for the keys of the signer.
+/// - The entrypoint of the message cannot be called through a permit.
+/// - The call on behalf of the signer rejects.
+/// - It fails to log the events.
+#[receive(
+    contract = "sponsored_counter",
+    name = "permit",
+    parameter = "PermitParam",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn permit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let param: PermitParam = ctx.parameter_cursor().get()?;
+    let message = &param.message;
+    ensure!(message.contract_address == ctx.self_address(), ContractError::WrongContract);
+    ensure!(ctx.metadata().slot_time() < message.timestamp, ContractError::Expired);
+    ensure!(is_permit_entrypoint(&message.entry_point), ContractError::WrongEntryPoint);
+    // `increment` is the only entrypoint which can be called through a permit.
+    let by: u64 = message.parameter()?;
+    ensure!(
+        host.check_permit_signature(param.signer, &param.signature, message)?,
+        ContractError::WrongSignature
+    );
+
+    let state = host.state_mut();
+    {
+        let mut nonce = state.nonces.entry(param.signer).or_insert(0);
+        ensure_eq!(message.nonce, *nonce, ContractError::NonceMismatch);
+        *nonce += 1;
+    }
+    logger.log(&CounterEvent::Nonce {
+        account: param.signer,
+        nonce:   message.nonce,
+    })?;
+    increment_for(state, param.signer, by, logger)
+}
+
+/// View the counter of an account, which is zero if it has never been
+/// incremented.
+#[receive(
+    contract = "sponsored_counter",
+    name = "view",
+    parameter = "AccountAddress",
+    return_value = "u64"
+)]
+fn view<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<u64> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().counters.get(&account).map_or(0, |c| *c))
+}
+
+/// View the nonce the next permit of an account must use.
+#[receive(
+    contract = "sponsored_counter",
+    name = "nonceOf",
+    parameter = "AccountAddress",
+    return_value = "u64"
+)]
+fn nonce_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<u64> {
+    let account: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().nonces.get(&account).map_or(0, |n| *n))
+}
+
+/// Query which of the given entrypoints can be called through a permit,
+/// returning the answers in the order of the queries.
+#[receive(
+    contract = "sponsored_counter",
+    name = "supportsPermit",
+    parameter = "SupportsPermitQueryParams",
+    return_value = "Vec<bool>"
+)]
+fn supports_permit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Vec<bool>> {
+    let params: SupportsPermitQueryParams = ctx.parameter_cursor().get()?;
+    Ok(params.queries.iter().map(is_permit_entrypoint).collect())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const SPONSOR: AccountAddress = AccountAddress([2; 32]);
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index:    10,
+        subindex: 0,
+    };
+    const EXPIRY: u64 = 100;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let ctx = TestInitContext::empty();
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(
+        sender: AccountAddress,
+        slot_time: u64,
+        parameter_bytes: &'a [u8],
+    ) -> TestReceiveContext<'a> {
+        let mut ctx = testing::ctx_with_parameter(sender, slot_time, parameter_bytes);
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx
+    }
+
+    fn permit_param(nonce: u64, entry_point: &str, by: u64) -> PermitParam {
+        PermitParam {
+            signature: AccountSignatures::default(),
+            signer:    ALICE,
+            message:   PermitMessage::new(
+                SELF_ADDRESS,
+                nonce,
+                Timestamp::from_timestamp_millis(EXPIRY),
+                OwnedEntrypointName::new_unchecked(entry_point.into()),
+                &by,
+            ),
+        }
+    }
+
+    fn counter_of(host: &TestHost<State<TestStateApi>>, account: AccountAddress) -> u64 {
+        let parameter_bytes = to_bytes(&account);
+        view(&ctx_at(ALICE, 0, &parameter_bytes), host).expect_report("Viewing should pass")
+    }
+
+    #[concordium_test]
+    /// Test that accounts can increment their own counter directly.
+    fn test_increment() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        let parameter_bytes = to_bytes(&5u64);
+        increment(&ctx_at(ALICE, 0, &parameter_bytes), &mut host, &mut logger)
+            .expect_report("Incrementing should pass");
+        increment(&ctx_at(ALICE, 0, &parameter_bytes), &mut host, &mut logger)
+            .expect_report("Incrementing should pass");
+
+        claim_eq!(counter_of(&host, ALICE), 10);
+        claim_eq!(counter_of(&host, SPONSOR), 0);
+        claim_eq!(
+            logger.logs.last(),
+            Some(&to_bytes(&CounterEvent::Increment {
+                account: ALICE,
+                value:   10,
+            }))
+        );
+    }
+
+    #[concordium_test]
+    /// Test that a sponsor can increment the counter of an account through a
+    /// permit, and that the permit cannot be replayed.
+    fn test_permit() {
+        let mut host = new_host();
+        host.setup_check_account_signature_mock(|account, _signatures, data| {
+            let signed = [
+                to_bytes(&permit_param(0, "increment", 3).message),
+                to_bytes(&permit_param(1, "increment", 4).message),
+                to_bytes(&permit_param(2, "view", 0).message),
+            ];
+            account == ALICE && signed.iter().any(|m| &m[..] == data)
+        });
+        let mut logger = TestLogger::init();
+
+        let mut submit = |param: &PermitParam| {
+            let parameter_bytes = to_bytes(param);
+            permit(&ctx_at(SPONSOR, 0, &parameter_bytes), &mut host, &mut logger)
+        };
+        submit(&permit_param(0, "increment", 3)).expect_report("The permit should pass");
+        let res = submit(&permit_param(0, "increment", 3));
+        claim_eq!(res, Err(ContractError::NonceMismatch), "Permits cannot be replayed");
+        let res = submit(&permit_param(1, "increment", 5));
+        claim_eq!(res, Err(ContractError::WrongSignature), "The payload is signed");
+        submit(&permit_param(1, "increment", 4)).expect_report("The permit should pass");
+        let res = submit(&permit_param(2, "view", 0));
+        claim_eq!(res, Err(ContractError::WrongEntryPoint));
+
+        claim_eq!(counter_of(&host, ALICE), 7);
+        claim_eq!(counter_of(&host, SPONSOR), 0, "The sponsor's counter is unchanged");
+        let parameter_bytes = to_bytes(&ALICE);
+        claim_eq!(nonce_of(&ctx_at(ALICE, 0, &parameter_bytes), &host), Ok(2));
+    }
+
+    #[concordium_test]
+    /// Test that permits are rejected if they are meant for another contract,
+    /// have expired, or the signer does not exist.
+    fn test_permit_rejected() {
+        let mut host = new_host();
+        host.make_account_missing(ALICE);
+        let mut logger = TestLogger::init();
+
+        let parameter_bytes = to_bytes(&permit_param(0, "increment", 3));
+        let res = permit(&ctx_at(SPONSOR, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::MissingAccount));
+        let res = permit(&ctx_at(SPONSOR, EXPIRY, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::Expired));
+
+        let mut param = permit_param(0, "increment", 3);
+        param.message.contract_address = ContractAddress {
+            index:    11,
+            subindex: 0,
+        };
+        let parameter_bytes = to_bytes(&param);
+        let res = permit(&ctx_at(SPONSOR, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::WrongContract));
+        claim_eq!(counter_of(&host, ALICE), 0);
+    }
+
+    #[concordium_test]
+    /// Test querying which entrypoints can be called through a permit.
+    fn test_supports_permit() {
+        let host = new_host();
+        let parameter_bytes = to_bytes(&SupportsPermitQueryParams {
+            queries: vec![
+                OwnedEntrypointName::new_unchecked("increment".into()),
+                OwnedEntrypointName::new_unchecked("view".into()),
+            ],
+        });
+        let res = supports_permit(&ctx_at(ALICE, 0, &parameter_bytes), &host);
+        claim_eq!(res, Ok(vec![true, false]));
+    }
+}
@@ -0,0 +1,91 @@
+#!/usr/bin/env bash
+# Compare the energy used by operations on a `StateMap` and a `StateBTreeMap`
+# holding the same keys, for collections of different sizes. For every size,
+# the keys are inserted in batches of 100, and the energy of inserting the
+# last batch, of finding the smallest key and of counting the keys in a
+# quarter of the range of keys is reported.
+#
+# Requires `cargo concordium` to be installed. Run from any directory:
+#
+#     examples/state-btree-benchmark/benchmark.sh
+set -euo pipefail
+
+cd "$(dirname "$0")"
+out_dir="$(mktemp -d)"
+trap 'rm -rf "$out_dir"' EXIT
+
+module="$out_dir/module.wasm.v1"
+contract="state_btree_benchmark"
+energy=100000000
+batch=100
+sizes=(100 500 1000)
+
+cargo concordium build --out "$module" >/dev/null 2>&1
+
+# Print the energy reported by `cargo concordium run`.
+energy_spent() {
+    sed -n 's/.*Interpreter energy spent is \([0-9]*\).*/\1/p'
+}
+
+# Write the bytes of an unsigned little-endian integer of the given number of
+# bytes.
+le_bytes() {
+    local value=$1 bytes=$2
+    for ((i = 0; i < bytes; i++)); do
+        printf "\\x$(printf %02x $((value >> (8 * i) & 0xff)))"
+    done
+}
+
+# Write the parameter for inserting a batch of keys, starting from the key
+# with the given index.
+insert_parameter() {
+    local collection=$1 from=$2
+    {
+        printf "\\x$(printf %02x "$collection")"
+        le_bytes "$from" 8
+        le_bytes "$batch" 4
+    } >"$out_dir/insert"
+}
+
+# Run an update of the state and print the energy used. The updated state
+# replaces the state.
+update() {
+    local state=$1 entrypoint=$2 parameter=$3
+    cargo concordium run update --module "$module" --contract "$contract" \
+        --entrypoint "$entrypoint" --state-bin "$state" --parameter-bin "$parameter" \
+        --energy "$energy" --out-bin "$state.new" 2>&1 | energy_spent
+    if [ -f "$state.new" ]; then
+        mv "$state.new" "$state"
+    fi
+}
+
+printf "%-6s %-10s %14s %14s %14s\n" size collection "insert $batch" smallest range
+for collection in 0 1; do
+    name=$([ "$collection" = 0 ] && echo StateMap || echo StateBTreeMap)
+    state="$out_dir/state-$collection"
+    cargo concordium run init --module "$module" --contract "$contract" \
+        --energy "$energy" --out-bin "$state" >/dev/null 2>&1
+
+    printf "\\x$(printf %02x "$collection")" >"$out_dir/smallest"
+    {
+        printf "\\x$(printf %02x "$collection")"
+        le_bytes $((1 << 62)) 8
+        le_bytes $(((1 << 62) + (1 << 62) - 1)) 8
+    } >"$out_dir/range"
+
+    inserted=0
+    for size in "${sizes[@]}"; do
+        while [ $((inserted + batch)) -lt "$size" ]; do
+            insert_parameter "$collection" "$inserted"
+            update "$state" insert "$out_dir/insert" >/dev/null
+            inserted=$((inserted + batch))
+        done
+        insert_parameter "$collection" "$inserted"
+        insert_energy=$(update "$state" insert "$out_dir/insert")
+        inserted=$((inserted + batch))
+        smallest_energy=$(update "$state" smallest "$out_dir/smallest")
+        range_energy=$(update "$state" range "$out_dir/range")
+        printf "%-6s %-10s %14s %14s %14s\n" "$size" "$name" "$insert_energy" \
+            "$smallest_energy" "$range_energy"
+    done
+done
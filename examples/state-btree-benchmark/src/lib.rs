@@ -0,0 +1,194 @@
+//! # A benchmark of the `StateBTreeMap` against the `StateMap`
+//!
+//! The contract keeps the same keys in a [`StateMap`] and in a
+//! [`StateBTreeMap`], and has entrypoints doing the same operations on either
+//! collection: inserting keys, finding the smallest key, and counting the keys
+//! in a range. Since the [`StateMap`] orders its keys by their serialization,
+//! finding the smallest key or the keys in a range requires iterating over the
+//! whole map, whereas the [`StateBTreeMap`] only visits the nodes of the tree
+//! on the path to the keys. Insertions on the other hand are cheaper for the
+//! [`StateMap`], which does not maintain a tree.
+//!
+//! The script `benchmark.sh` runs the entrypoints with `cargo concordium run`
+//! for collections of different sizes, and reports the energy used.
+#![cfg_attr(not(feature = "std"), no_std)]
+use concordium_std::*;
+
+/// The contract state.
+#[derive(Serial, DeserialWithState)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The keys in a map, ordered by their serialization.
+    map:   StateMap<u64, u64, S>,
+    /// The same keys in a map ordered by the keys.
+    btree: StateBTreeMap<u64, u64, S>,
+}
+
+/// Which of the collections to operate on.
+#[derive(Serialize, SchemaType, Clone, Copy)]
+enum Collection {
+    Map,
+    BTree,
+}
+
+/// The parameter of the `insert` entrypoint.
+#[derive(Serialize, SchemaType)]
+struct InsertParams {
+    /// The collection to insert the keys into.
+    collection: Collection,
+    /// The index of the first key to insert, see [`key`].
+    from:       u64,
+    /// The number of keys to insert.
+    count:      u32,
+}
+
+/// The parameter of the `range` entrypoint.
+#[derive(Serialize, SchemaType)]
+struct RangeParams {
+    /// The collection to count the keys of.
+    collection: Collection,
+    /// The smallest key of the range.
+    from:       u64,
+    /// The largest key of the range.
+    to:         u64,
+}
+
+/// The `n`th key inserted, which spreads the keys over the whole range of
+/// `u64` so they are not inserted in order.
+fn key(n: u64) -> u64 { n.wrapping_mul(0x9e37_79b9_7f4a_7c15) }
+
+/// Set up the contract with empty collections.
+#[init(contract = "state_btree_benchmark")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        map:   state_builder.new_map(),
+        btree: state_builder.new_btree_map(),
+    })
+}
+
+/// Insert `count` keys into the collection, starting from the key with index
+/// `from`.
+#[receive(contract = "state_btree_benchmark", name = "insert", parameter = "InsertParams", mutable)]
+fn contract_insert<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<()> {
+    let params: InsertParams = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    for n in params.from..params.from + u64::from(params.count) {
+        match params.collection {
+            Collection::Map => {
+                state.map.insert(key(n), n);
+            }
+            Collection::BTree => {
+                state.btree.insert(key(n), n);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Find the smallest key in the collection.
+#[receive(
+    contract = "state_btree_benchmark",
+    name = "smallest",
+    parameter = "Collection",
+    return_value = "Option<u64>"
+)]
+fn contract_smallest<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<u64>> {
+    let collection: Collection = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    Ok(match collection {
+        Collection::Map => state.map.iter().map(|(key, _)| *key).min(),
+        Collection::BTree => state.btree.first_key().map(|key| *key),
+    })
+}
+
+/// Count the keys in a range of the collection.
+#[receive(
+    contract = "state_btree_benchmark",
+    name = "range",
+    parameter = "RangeParams",
+    return_value = "u32"
+)]
+fn contract_range<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<u32> {
+    let params: RangeParams = ctx.parameter_cursor().get()?;
+    let state = host.state();
+    let count = match params.collection {
+        Collection::Map => {
+            state.map.iter().filter(|(key, _)| (params.from..=params.to).contains(&**key)).count()
+        }
+        Collection::BTree => state.btree.range(params.from..=params.to).count(),
+    };
+    Ok(count as u32)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    fn insert(
+        host: &mut TestHost<State<TestStateApi>>,
+        collection: Collection,
+        from: u64,
+        count: u32,
+    ) {
+        let parameter = to_bytes(&InsertParams {
+            collection,
+            from,
+            count,
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_parameter(&parameter);
+        contract_insert(&ctx, host).expect_report("Inserting fails");
+    }
+
+    /// Both collections give the same results.
+    #[concordium_test]
+    fn test_collections_agree() {
+        let mut state_builder = TestStateBuilder::new();
+        let state = contract_init(&TestInitContext::empty(), &mut state_builder)
+            .expect_report("Init fails");
+        let mut host = TestHost::new(state, state_builder);
+        insert(&mut host, Collection::Map, 0, 40);
+        insert(&mut host, Collection::BTree, 0, 40);
+        insert(&mut host, Collection::Map, 40, 10);
+        insert(&mut host, Collection::BTree, 40, 10);
+        claim_eq!(host.state().btree.len(), 50);
+
+        let smallest = |collection| {
+            let parameter = to_bytes(&collection);
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_parameter(&parameter);
+            contract_smallest(&ctx, &host).expect_report("Finding the smallest key fails")
+        };
+        let expected = (0..50).map(key).min();
+        claim_eq!(smallest(Collection::Map), expected);
+        claim_eq!(smallest(Collection::BTree), expected);
+
+        let range = |collection| {
+            let parameter = to_bytes(&RangeParams {
+                collection,
+                from: u64::MAX / 4,
+                to: u64::MAX / 2,
+            });
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_parameter(&parameter);
+            contract_range(&ctx, &host).expect_report("Counting the keys fails")
+        };
+        let expected =
+            (0..50).map(key).filter(|k| (u64::MAX / 4..=u64::MAX / 2).contains(k)).count();
+        claim_eq!(range(Collection::Map) as usize, expected);
+        claim_eq!(range(Collection::BTree) as usize, expected);
+    }
+}
@@ -0,0 +1,473 @@
+//! # Creator subscription tiers
+//!
+//! Creators define subscription tiers using `add_tier`, each with a price, a
+//! duration, and the identifier of the benefits it gives, which dApps map to
+//! the content it unlocks. The tiers of a creator are numbered in the order
+//! they were added, and the creator can change a tier using `update_tier`,
+//! which only affects later subscriptions.
+//!
+//! Viewers `subscribe` to a tier of a creator by sending the price along,
+//! which is transferred to the creator right away. A viewer has at most one
+//! subscription per creator, which expires after the duration of the tier. If
+//! the viewer renews before the subscription expires, the duration is added to
+//! the current expiry, otherwise it starts when subscribing. Subscribing to
+//! another tier replaces the tier of the subscription.
+//!
+//! Other contracts and dApps gate content using `is_subscribed`, which tells
+//! whether a viewer has an active subscription to a creator, or `subscription`,
+//! which also tells the tier and its benefits.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// The identifier of a tier of a creator.
+pub type TierId = u32;
+
+/// A subscription tier.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tier {
+    /// The price of a subscription.
+    pub price:       Amount,
+    /// How long a subscription lasts.
+    pub duration:    Duration,
+    /// The identifier of the benefits of the tier.
+    pub benefits_id: u32,
+}
+
+/// The subscription of a viewer to a creator.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subscription {
+    /// The tier subscribed to.
+    pub tier:        TierId,
+    /// The benefits of the tier when subscribing.
+    pub benefits_id: u32,
+    /// The time the subscription expires.
+    pub expires:     Timestamp,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The tiers of every creator, indexed by their identifier.
+    tiers:         StateMap<AccountAddress, Vec<Tier>, S>,
+    /// The latest subscription of every viewer to every creator.
+    subscriptions: StateMap<(AccountAddress, AccountAddress), Subscription, S>,
+}
+
+/// The parameter type for the contract function `update_tier`.
+#[derive(Serialize, SchemaType)]
+pub struct UpdateTierParams {
+    /// The tier to update.
+    pub tier_id: TierId,
+    /// The new tier.
+    pub tier:    Tier,
+}
+
+/// The parameter type for the contract function `subscribe`.
+#[derive(Serialize, SchemaType)]
+pub struct SubscribeParams {
+    /// The creator to subscribe to.
+    pub creator: AccountAddress,
+    /// The tier to subscribe to.
+    pub tier_id: TierId,
+}
+
+/// The parameter type for the contract functions `is_subscribed` and
+/// `subscription`.
+#[derive(Serialize, SchemaType)]
+pub struct SubscriptionQuery {
+    pub viewer:  AccountAddress,
+    pub creator: AccountAddress,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum TierEvent {
+    /// A creator added or updated a tier.
+    Tier {
+        creator: AccountAddress,
+        tier_id: TierId,
+        tier:    Tier,
+    },
+    /// A viewer subscribed to a tier of a creator.
+    Subscribe {
+        viewer:       AccountAddress,
+        creator:      AccountAddress,
+        subscription: Subscription,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when a contract is a creator or a viewer; Only accounts can be.
+    OnlyAccount,
+    /// Raised when the duration of a tier is zero.
+    InvalidTier,
+    /// Raised when the creator does not have the tier.
+    UnknownTier,
+    /// Raised when the CCD sent along is not the price of the tier.
+    WrongPrice,
+    /// Raised when the expiry of the subscription overflows.
+    Overflow,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+/// Get the subscription of the viewer to the creator, if it is active.
+fn active_subscription<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Subscription>> {
+    let query: SubscriptionQuery = ctx.parameter_cursor().get()?;
+    let subscription = host.state().subscriptions.get(&(query.viewer, query.creator)).map(|s| *s);
+    let now = ctx.metadata().slot_time();
+    Ok(subscription.filter(|subscription| now < subscription.expires))
+}
+
+/// Initialize the contract without tiers.
+#[init(contract = "subscription_tiers", event = "TierEvent")]
+fn contract_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        tiers:         state_builder.new_map(),
+        subscriptions: state_builder.new_map(),
+    })
+}
+
+/// Add a tier for the sender. Returns the identifier of the tier.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The duration is zero.
+/// - It fails to log the event.
+#[receive(
+    contract = "subscription_tiers",
+    name = "add_tier",
+    parameter = "Tier",
+    return_value = "TierId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_add_tier<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<TierId> {
+    let tier: Tier = ctx.parameter_cursor().get()?;
+    let creator = sender_account(ctx)?;
+    ensure!(tier.duration.millis() > 0, ContractError::InvalidTier);
+    let mut tiers = host.state_mut().tiers.entry(creator).or_insert_with(Vec::new);
+    let tier_id = tiers.len() as TierId;
+    tiers.push(tier);
+    logger.log(&TierEvent::Tier {
+        creator,
+        tier_id,
+        tier,
+    })?;
+    Ok(tier_id)
+}
+
+/// Update a tier of the sender. Active subscriptions keep the expiry and
+/// benefits they were bought with.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The sender does not have the tier.
+/// - The duration is zero.
+/// - It fails to log the event.
+#[receive(
+    contract = "subscription_tiers",
+    name = "update_tier",
+    parameter = "UpdateTierParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_tier<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: UpdateTierParams = ctx.parameter_cursor().get()?;
+    let creator = sender_account(ctx)?;
+    ensure!(params.tier.duration.millis() > 0, ContractError::InvalidTier);
+    {
+        let mut tiers = host.state().tiers.get_mut(&creator).ok_or(ContractError::UnknownTier)?;
+        let tier =
+            tiers.iter_mut().nth(params.tier_id as usize).ok_or(ContractError::UnknownTier)?;
+        *tier = params.tier;
+    }
+    logger.log(&TierEvent::Tier {
+        creator,
+        tier_id: params.tier_id,
+        tier: params.tier,
+    })?;
+    Ok(())
+}
+
+/// Subscribe the sender to a tier of a creator, sending the price along, which
+/// is transferred to the creator. Returns the subscription.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - The creator does not have the tier.
+/// - The CCD sent along is not the price of the tier.
+/// - The expiry of the subscription overflows.
+/// - It fails to log the event.
+#[receive(
+    contract = "subscription_tiers",
+    name = "subscribe",
+    parameter = "SubscribeParams",
+    return_value = "Subscription",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_subscribe<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<Subscription> {
+    let params: SubscribeParams = ctx.parameter_cursor().get()?;
+    let viewer = sender_account(ctx)?;
+    let state = host.state_mut();
+    let tier = state
+        .tiers
+        .get(&params.creator)
+        .and_then(|tiers| tiers.get(params.tier_id as usize).copied())
+        .ok_or(ContractError::UnknownTier)?;
+    ensure_eq!(amount, tier.price, ContractError::WrongPrice);
+
+    // Renewing before the subscription expires extends it.
+    let now = ctx.metadata().slot_time();
+    let start = match state.subscriptions.get(&(viewer, params.creator)) {
+        Some(current) if current.expires > now => current.expires,
+        _ => now,
+    };
+    let subscription = Subscription {
+        tier:        params.tier_id,
+        benefits_id: tier.benefits_id,
+        expires:     start.checked_add(tier.duration).ok_or(ContractError::Overflow)?,
+    };
+    state.subscriptions.insert((viewer, params.creator), subscription);
+
+    // The transfer always succeeds, since the creator is an existing account
+    // and the price was sent along.
+    host.invoke_transfer(&params.creator, amount).unwrap_abort();
+    logger.log(&TierEvent::Subscribe {
+        viewer,
+        creator: params.creator,
+        subscription,
+    })?;
+    Ok(subscription)
+}
+
+/// Whether the viewer has an active subscription to the creator.
+#[receive(
+    contract = "subscription_tiers",
+    name = "is_subscribed",
+    parameter = "SubscriptionQuery",
+    return_value = "bool"
+)]
+fn contract_is_subscribed<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<bool> {
+    Ok(active_subscription(ctx, host)?.is_some())
+}
+
+/// Get the active subscription of the viewer to the creator, if any.
+#[receive(
+    contract = "subscription_tiers",
+    name = "subscription",
+    parameter = "SubscriptionQuery",
+    return_value = "Option<Subscription>"
+)]
+fn contract_subscription<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Subscription>> {
+    active_subscription(ctx, host)
+}
+
+/// View the tiers of a creator.
+#[receive(
+    contract = "subscription_tiers",
+    name = "tiers",
+    parameter = "AccountAddress",
+    return_value = "Vec<Tier>"
+)]
+fn contract_tiers<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Vec<Tier>> {
+    let creator: AccountAddress = ctx.parameter_cursor().get()?;
+    Ok(host.state().tiers.get(&creator).map(|tiers| tiers.clone()).unwrap_or_default())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const VIEWER: AccountAddress = AccountAddress([2; 32]);
+    const MONTH: u64 = 30 * 24 * 60 * 60 * 1000;
+
+    fn tier(micro_ccd: u64, benefits_id: u32) -> Tier {
+        Tier {
+            price: Amount::from_micro_ccd(micro_ccd),
+            duration: Duration::from_millis(MONTH),
+            benefits_id,
+        }
+    }
+
+    /// Set up a host where the creator has a basic and a premium tier.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let state = contract_init(&TestInitContext::empty(), &mut state_builder)
+            .expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        for tier in [tier(100, 1), tier(500, 2)].iter() {
+            let parameter_bytes = to_bytes(tier);
+            contract_add_tier(
+                &ctx_with_parameter(CREATOR, 0, &parameter_bytes),
+                &mut host,
+                &mut TestLogger::init(),
+            )
+            .expect_report("Adding a tier should pass");
+        }
+        host
+    }
+
+    fn subscribe_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        tier_id: TierId,
+        micro_ccd: u64,
+        slot_time: u64,
+    ) -> ContractResult<Subscription> {
+        let parameter_bytes = to_bytes(&SubscribeParams {
+            creator: CREATOR,
+            tier_id,
+        });
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        let ctx = ctx_with_parameter(VIEWER, slot_time, &parameter_bytes);
+        contract_subscribe(&ctx, host, amount, &mut TestLogger::init())
+    }
+
+    fn is_subscribed_at(host: &TestHost<State<TestStateApi>>, slot_time: u64) -> bool {
+        let parameter_bytes = to_bytes(&SubscriptionQuery {
+            viewer:  VIEWER,
+            creator: CREATOR,
+        });
+        contract_is_subscribed(&ctx_with_parameter(VIEWER, slot_time, &parameter_bytes), host)
+            .expect_report("Querying should pass")
+    }
+
+    #[concordium_test]
+    /// Test that subscribing pays the creator, and that the subscription is
+    /// active until it expires.
+    fn test_subscribe() {
+        let mut host = new_host();
+        claim_eq!(subscribe_at(&mut host, 2, 100, 0), Err(ContractError::UnknownTier));
+        claim_eq!(subscribe_at(&mut host, 0, 99, 0), Err(ContractError::WrongPrice));
+        claim!(!is_subscribed_at(&host, 0), "Nobody has subscribed");
+
+        let subscription =
+            subscribe_at(&mut host, 0, 100, 1_000).expect_report("Subscribing should pass");
+        claim_eq!(subscription, Subscription {
+            tier:        0,
+            benefits_id: 1,
+            expires:     Timestamp::from_timestamp_millis(1_000 + MONTH),
+        });
+        claim_eq!(host.get_transfers(), [(CREATOR, Amount::from_micro_ccd(100))]);
+        claim!(is_subscribed_at(&host, MONTH + 999), "The subscription should be active");
+        claim!(!is_subscribed_at(&host, MONTH + 1_000), "The subscription should have expired");
+    }
+
+    #[concordium_test]
+    /// Test that renewing before expiry extends the subscription, and that
+    /// subscribing after expiry starts a new one.
+    fn test_renew() {
+        let mut host = new_host();
+        subscribe_at(&mut host, 0, 100, 0).expect_report("Subscribing should pass");
+        let renewed =
+            subscribe_at(&mut host, 1, 500, MONTH - 1).expect_report("Renewing should pass");
+        claim_eq!(renewed.expires, Timestamp::from_timestamp_millis(2 * MONTH));
+        claim_eq!(renewed.benefits_id, 2, "The premium tier should replace the basic tier");
+
+        let lapsed =
+            subscribe_at(&mut host, 0, 100, 3 * MONTH).expect_report("Subscribing should pass");
+        claim_eq!(lapsed.expires, Timestamp::from_timestamp_millis(4 * MONTH));
+    }
+
+    #[concordium_test]
+    /// Test that updating a tier applies to later subscriptions only.
+    fn test_update_tier() {
+        let mut host = new_host();
+        subscribe_at(&mut host, 0, 100, 0).expect_report("Subscribing should pass");
+        let parameter_bytes = to_bytes(&UpdateTierParams {
+            tier_id: 0,
+            tier:    tier(150, 3),
+        });
+        let res = contract_update_tier(
+            &ctx_with_parameter(VIEWER, 0, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::UnknownTier), "Only the creator has the tier");
+        contract_update_tier(
+            &ctx_with_parameter(CREATOR, 0, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        )
+        .expect_report("Updating should pass");
+
+        let parameter_bytes = to_bytes(&SubscriptionQuery {
+            viewer:  VIEWER,
+            creator: CREATOR,
+        });
+        let subscription =
+            contract_subscription(&ctx_with_parameter(VIEWER, 0, &parameter_bytes), &host)
+                .expect_report("Querying should pass");
+        claim_eq!(subscription.map(|s| s.benefits_id), Some(1));
+        claim_eq!(subscribe_at(&mut host, 0, 100, 0), Err(ContractError::WrongPrice));
+        let renewed = subscribe_at(&mut host, 0, 150, 0).expect_report("Renewing should pass");
+        claim_eq!(renewed.benefits_id, 3);
+    }
+}
@@ -0,0 +1,447 @@
+//! A recurring subscription payments smart contract example.
+//!
+//! # Description
+//! A merchant, the account initializing the contract, offers a subscription
+//! for a fixed fee per billing period. Subscribers prepay by depositing a
+//! balance, from which the merchant pulls the fee once per billing period.
+//!
+//! # Operations
+//! The contract allows for
+//!  - `subscribe`, which starts a subscription of the sender with the CCD sent
+//!    along as the balance;
+//!  - `topUp`, which adds the CCD sent along to the balance of the sender;
+//!  - `charge`, which the merchant uses to pull the fee from the balance of a
+//!    subscriber, once the billing period is due;
+//!  - `cancel`, which ends the subscription of the sender, and returns the
+//!    remaining balance.
+//!
+//! The first billing period is due when subscribing, and every following one
+//! a billing period after the previous one was due. If the merchant misses
+//! charging some billing periods, they can catch up by charging repeatedly.
+//! Every charge is recorded by an event.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// The subscription of an account.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Subscription {
+    /// The prepaid balance which fees are charged from.
+    pub balance:  Amount,
+    /// The time the next billing period is due.
+    pub next_due: Timestamp,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The account receiving the fees.
+    merchant:       AccountAddress,
+    /// The fee per billing period.
+    fee:            Amount,
+    /// The duration of a billing period.
+    billing_period: Duration,
+    /// The active subscriptions.
+    subscriptions:  StateMap<AccountAddress, Subscription, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The fee per billing period.
+    fee:            Amount,
+    /// The duration of a billing period.
+    billing_period: Duration,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum SubscriptionEvent {
+    /// An account subscribed.
+    Subscribe {
+        subscriber: AccountAddress,
+        balance:    Amount,
+    },
+    /// The fee of the billing period which was due at `due` was charged.
+    Charge {
+        subscriber: AccountAddress,
+        fee:        Amount,
+        due:        Timestamp,
+    },
+    /// An account cancelled its subscription, and the remaining balance was
+    /// refunded.
+    Cancel {
+        subscriber: AccountAddress,
+        refund:     Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when initializing with a billing period of zero.
+    InvalidBillingPeriod,
+    /// Raised when a contract subscribes; Only accounts can subscribe.
+    OnlyAccount,
+    /// Raised when subscribing while already subscribed.
+    AlreadySubscribed,
+    /// Raised when the account is not subscribed.
+    NotSubscribed,
+    /// Raised when someone else than the merchant charges.
+    Unauthorized,
+    /// Raised when charging before the next billing period is due.
+    NotDue,
+    /// Raised when the balance of the subscriber does not cover the fee.
+    InsufficientBalance,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+// Contract functions
+
+/// Initialize the contract with the fee and billing period. The sender becomes
+/// the merchant.
+#[init(contract = "subscription", parameter = "InitParameter", event = "SubscriptionEvent")]
+fn init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let param: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(param.billing_period.millis() > 0, ContractError::InvalidBillingPeriod.into());
+    Ok(State {
+        merchant:       ctx.init_origin(),
+        fee:            param.fee,
+        billing_period: param.billing_period,
+        subscriptions:  state_builder.new_map(),
+    })
+}
+
+/// Subscribe the sender, with the CCD sent along as the balance. The first
+/// billing period is due immediately.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The sender is already subscribed.
+/// - It fails to log the event.
+#[receive(
+    contract = "subscription",
+    name = "subscribe",
+    error = "ContractError",
+    payable,
+    enable_logger,
+    mutable
+)]
+fn subscribe<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let subscriber = sender_account(ctx)?;
+    host.state_mut()
+        .subscriptions
+        .entry(subscriber)
+        .vacant_or(ContractError::AlreadySubscribed)?
+        .insert(Subscription {
+            balance:  amount,
+            next_due: ctx.metadata().slot_time(),
+        });
+    logger.log(&SubscriptionEvent::Subscribe {
+        subscriber,
+        balance: amount,
+    })?;
+    Ok(())
+}
+
+/// Add the CCD sent along to the balance of the sender.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The sender is not subscribed.
+#[receive(contract = "subscription", name = "topUp", error = "ContractError", payable, mutable)]
+fn top_up<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let subscriber = sender_account(ctx)?;
+    let mut subscription =
+        host.state_mut().subscriptions.get_mut(&subscriber).ok_or(ContractError::NotSubscribed)?;
+    subscription.balance += amount;
+    Ok(())
+}
+
+/// Charge the fee of the billing period which is due from the balance of a
+/// subscriber, and transfer it to the merchant. Can only be called by the
+/// merchant.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the merchant.
+/// - The account is not subscribed.
+/// - The next billing period is not due yet.
+/// - The balance of the subscriber does not cover the fee.
+/// - It fails to log the event.
+#[receive(
+    contract = "subscription",
+    name = "charge",
+    parameter = "AccountAddress",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn charge<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let subscriber: AccountAddress = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let merchant = state.merchant;
+    let fee = state.fee;
+    let billing_period = state.billing_period;
+    ensure!(ctx.sender().matches_account(&merchant), ContractError::Unauthorized);
+
+    let due = {
+        let mut subscription =
+            state.subscriptions.get_mut(&subscriber).ok_or(ContractError::NotSubscribed)?;
+        let due = subscription.next_due;
+        ensure!(ctx.metadata().slot_time() >= due, ContractError::NotDue);
+        ensure!(subscription.balance >= fee, ContractError::InsufficientBalance);
+        subscription.balance -= fee;
+        subscription.next_due = due
+            .checked_add(billing_period)
+            .unwrap_or_else(|| Timestamp::from_timestamp_millis(u64::MAX));
+        due
+    };
+    logger.log(&SubscriptionEvent::Charge {
+        subscriber,
+        fee,
+        due,
+    })?;
+    if fee > Amount::zero() {
+        // The transfer always succeeds, since the merchant is the sender and the
+        // fee was deducted from a balance held by the contract.
+        host.invoke_transfer(&merchant, fee).unwrap_abort();
+    }
+    Ok(())
+}
+
+/// End the subscription of the sender, and refund the remaining balance.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The sender is not subscribed.
+/// - It fails to log the event.
+#[receive(
+    contract = "subscription",
+    name = "cancel",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn cancel<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let subscriber = sender_account(ctx)?;
+    let subscription = host
+        .state_mut()
+        .subscriptions
+        .remove_and_get(&subscriber)
+        .ok_or(ContractError::NotSubscribed)?;
+    logger.log(&SubscriptionEvent::Cancel {
+        subscriber,
+        refund: subscription.balance,
+    })?;
+    if subscription.balance > Amount::zero() {
+        // The transfer always succeeds, since the subscriber is the sender and
+        // the contract holds the balance.
+        host.invoke_transfer(&subscriber, subscription.balance).unwrap_abort();
+    }
+    Ok(())
+}
+
+/// View the subscription of an account.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The account is not subscribed.
+#[receive(
+    contract = "subscription",
+    name = "subscriptionOf",
+    parameter = "AccountAddress",
+    return_value = "Subscription",
+    error = "ContractError"
+)]
+fn subscription_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Subscription> {
+    let subscriber: AccountAddress = ctx.parameter_cursor().get()?;
+    host.state().subscriptions.get(&subscriber).map(|s| *s).ok_or(ContractError::NotSubscribed)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const MERCHANT: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const FEE: Amount = Amount {
+        micro_ccd: 100,
+    };
+    const PERIOD: u64 = 1_000;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            fee:            FEE,
+            billing_period: Duration::from_millis(PERIOD),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(MERCHANT);
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn subscribe_alice(host: &mut TestHost<State<TestStateApi>>, balance: Amount) {
+        host.set_self_balance(host.self_balance() + balance);
+        let mut logger = TestLogger::init();
+        subscribe(&ctx_with_parameter(ALICE, 0, &[]), host, balance, &mut logger)
+            .expect_report("Subscribing should pass");
+    }
+
+    fn charge_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&ALICE);
+        charge(&ctx_with_parameter(MERCHANT, slot_time, &parameter_bytes), host, logger)
+    }
+
+    #[concordium_test]
+    /// Test that the merchant can charge once per billing period, and that
+    /// every charge is logged.
+    fn test_charge() {
+        let mut host = new_host();
+        subscribe_alice(&mut host, Amount::from_micro_ccd(250));
+        let mut logger = TestLogger::init();
+
+        charge_at(&mut host, 0, &mut logger).expect_report("The first period is due immediately");
+        let res = charge_at(&mut host, PERIOD - 1, &mut logger);
+        claim_eq!(res, Err(ContractError::NotDue), "Charging twice in a period should fail");
+        charge_at(&mut host, PERIOD, &mut logger).expect_report("The second period is due");
+        let res = charge_at(&mut host, 2 * PERIOD, &mut logger);
+        claim_eq!(res, Err(ContractError::InsufficientBalance));
+
+        claim_eq!(host.get_transfers(), [(MERCHANT, FEE), (MERCHANT, FEE)]);
+        claim_eq!(logger.logs, [
+            to_bytes(&SubscriptionEvent::Charge {
+                subscriber: ALICE,
+                fee:        FEE,
+                due:        Timestamp::from_timestamp_millis(0),
+            }),
+            to_bytes(&SubscriptionEvent::Charge {
+                subscriber: ALICE,
+                fee:        FEE,
+                due:        Timestamp::from_timestamp_millis(PERIOD),
+            }),
+        ]);
+    }
+
+    #[concordium_test]
+    /// Test that the merchant can catch up on missed billing periods, but not
+    /// charge ahead.
+    fn test_charge_missed_periods() {
+        let mut host = new_host();
+        subscribe_alice(&mut host, Amount::from_micro_ccd(1_000));
+        let mut logger = TestLogger::init();
+
+        for _ in 0..3 {
+            charge_at(&mut host, 2 * PERIOD + 1, &mut logger)
+                .expect_report("Missed periods should be chargeable");
+        }
+        let res = charge_at(&mut host, 2 * PERIOD + 1, &mut logger);
+        claim_eq!(res, Err(ContractError::NotDue), "Future periods should not be chargeable");
+
+        let parameter_bytes = to_bytes(&ALICE);
+        claim_eq!(
+            subscription_of(&ctx_with_parameter(ALICE, 0, &parameter_bytes), &host),
+            Ok(Subscription {
+                balance:  Amount::from_micro_ccd(700),
+                next_due: Timestamp::from_timestamp_millis(3 * PERIOD),
+            })
+        );
+    }
+
+    #[concordium_test]
+    /// Test that only the merchant can charge, and only subscribers.
+    fn test_charge_rejected() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        let res = charge_at(&mut host, 0, &mut logger);
+        claim_eq!(res, Err(ContractError::NotSubscribed));
+
+        subscribe_alice(&mut host, FEE);
+        let parameter_bytes = to_bytes(&ALICE);
+        let res = charge(&ctx_with_parameter(ALICE, 0, &parameter_bytes), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the merchant can charge");
+    }
+
+    #[concordium_test]
+    /// Test topping up, and that cancelling refunds the remaining balance.
+    fn test_top_up_and_cancel() {
+        let mut host = new_host();
+        subscribe_alice(&mut host, FEE);
+        let mut logger = TestLogger::init();
+        let res = subscribe(&ctx_with_parameter(ALICE, 0, &[]), &mut host, FEE, &mut logger);
+        claim_eq!(res, Err(ContractError::AlreadySubscribed));
+
+        host.set_self_balance(host.self_balance() + FEE);
+        top_up(&ctx_with_parameter(ALICE, 0, &[]), &mut host, FEE)
+            .expect_report("Topping up should pass");
+        charge_at(&mut host, 0, &mut logger).expect_report("Charging should pass");
+
+        cancel(&ctx_with_parameter(ALICE, 10, &[]), &mut host, &mut logger)
+            .expect_report("Cancelling should pass");
+        claim_eq!(host.get_transfers(), [(MERCHANT, FEE), (ALICE, FEE)]);
+        claim_eq!(
+            logger.logs.last(),
+            Some(&to_bytes(&SubscriptionEvent::Cancel {
+                subscriber: ALICE,
+                refund:     FEE,
+            }))
+        );
+        let res = cancel(&ctx_with_parameter(ALICE, 10, &[]), &mut host, &mut logger);
+        claim_eq!(res, Err(ContractError::NotSubscribed), "Cancelling twice should fail");
+    }
+}
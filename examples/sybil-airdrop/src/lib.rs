@@ -0,0 +1,404 @@
+//! # Sybil-resistant airdrop
+//!
+//! An airdrop of a fixed amount of CCD per person, rather than per account.
+//! Since a person can create many accounts from the same identity, counting
+//! claims per account does not stop one person from claiming many times.
+//! Instead, each claim is booked under an identity key derived from the
+//! identity attributes revealed in the credentials of the claiming account.
+//!
+//! The identity key of a credential is the SHA2-256 hash of its identity
+//! provider and the values of the configured identity attributes, e.g., the
+//! type, issuer and number of the identity document. Two accounts created
+//! from the same identity reveal the same values, and therefore share the key.
+//!
+//! Every credential of a claiming account must
+//!  - be valid at the time of the claim,
+//!  - be issued by one of the accepted identity providers,
+//!  - reveal a country of residence among the allowed countries, if any are
+//!    configured,
+//!  - reveal all the identity attributes,
+//!  - have an identity key which has not claimed yet.
+//!
+//! # Operations
+//! The contract allows for
+//!  - anyone to `fund` the airdrop;
+//!  - eligible accounts to `claim` the amount per claim, once per identity,
+//!    until the airdrop ends;
+//!  - the owner to `withdrawRemaining` CCD after the airdrop ends.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The identity constraints for claiming.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Constraints {
+    /// The identity providers whose credentials are accepted.
+    pub identity_providers: Vec<IdentityProvider>,
+    /// The countries of residence, as ISO 3166-1 alpha-2 codes, which are
+    /// allowed to claim. Every country is allowed if empty.
+    #[concordium(size_length = 1)]
+    pub allowed_countries:  Vec<[u8; 2]>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The identity constraints for claiming.
+    constraints:         Constraints,
+    /// The tags of the attributes identifying a person, from which the
+    /// identity key is derived.
+    #[concordium(size_length = 1)]
+    identity_attributes: Vec<u8>,
+    /// The amount of CCD transferred per claim.
+    amount_per_claim:    Amount,
+    /// The time after which no more claims are accepted.
+    ends:                Timestamp,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The identity constraints for claiming.
+    constraints:         Constraints,
+    /// The tags of the attributes identifying a person. These cannot change,
+    /// since that would change the identity keys of everyone who claimed.
+    identity_attributes: Vec<u8>,
+    /// The amount of CCD transferred per claim.
+    amount_per_claim:    Amount,
+    /// The time after which no more claims are accepted.
+    ends:                Timestamp,
+    /// The number of claims so far.
+    claims:              u32,
+    /// The identity keys which have claimed.
+    claimed:             StateSet<HashSha2256, S>,
+}
+
+/// The return type for the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct ViewState {
+    pub constraints:      Constraints,
+    pub amount_per_claim: Amount,
+    pub ends:             Timestamp,
+    pub claims:           u32,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when a contract claims; Only accounts have identities.
+    OnlyAccount,
+    /// Raised when someone else than the owner withdraws.
+    Unauthorized,
+    /// Raised when claiming after the airdrop ended.
+    Ended,
+    /// Raised when withdrawing before the airdrop ended.
+    NotEnded,
+    /// Raised when a credential of the sender is no longer valid.
+    ExpiredIdentity,
+    /// Raised when a credential of the sender is issued by an identity
+    /// provider which is not accepted.
+    UnknownIdentityProvider,
+    /// Raised when a credential of the sender does not reveal the country of
+    /// residence or one of the identity attributes.
+    MissingAttribute,
+    /// Raised when the sender resides in a country which is not allowed.
+    CountryNotAllowed,
+    /// Raised when the identity of a credential of the sender has claimed
+    /// already.
+    AlreadyClaimed,
+    /// Raised when the contract holds too little CCD for the claim.
+    InsufficientFunds,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Check that a credential satisfies the constraints, and derive its identity
+/// key.
+fn identity_key(
+    policy: &impl HasPolicy,
+    state: &State<impl HasStateApi>,
+    now: Timestamp,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<HashSha2256> {
+    ensure!(now <= policy.valid_to(), ContractError::ExpiredIdentity);
+    ensure!(
+        state.constraints.identity_providers.contains(&policy.identity_provider()),
+        ContractError::UnknownIdentityProvider
+    );
+    let revealed: Vec<(AttributeTag, AttributeValue)> = policy.attributes().collect();
+    let value_of = |tag: AttributeTag| {
+        revealed
+            .iter()
+            .find(|(attribute_tag, _)| *attribute_tag == tag)
+            .map(|(_, value)| value)
+            .ok_or(ContractError::MissingAttribute)
+    };
+
+    let country = value_of(attributes::COUNTRY_OF_RESIDENCE)?;
+    let allowed_countries = &state.constraints.allowed_countries;
+    ensure!(
+        allowed_countries.is_empty()
+            || allowed_countries.iter().any(|allowed| country.as_ref() == &allowed[..]),
+        ContractError::CountryNotAllowed
+    );
+
+    // Prefix every value with its length, so different values cannot
+    // concatenate to the same bytes.
+    let mut key = policy.identity_provider().to_le_bytes().to_vec();
+    for tag in state.identity_attributes.iter() {
+        let value = value_of(AttributeTag(*tag))?.as_ref();
+        key.push(value.len() as u8);
+        key.extend_from_slice(value);
+    }
+    Ok(crypto_primitives.hash_sha2_256(&key))
+}
+
+// Contract functions
+
+/// Initialize the airdrop with the constraints and the amount per claim.
+#[init(contract = "sybil_airdrop", parameter = "InitParams")]
+fn init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    Ok(State {
+        constraints:         params.constraints,
+        identity_attributes: params.identity_attributes,
+        amount_per_claim:    params.amount_per_claim,
+        ends:                params.ends,
+        claims:              0,
+        claimed:             state_builder.new_set(),
+    })
+}
+
+/// Fund the airdrop with the CCD sent along.
+#[receive(contract = "sybil_airdrop", name = "fund", payable)]
+fn fund<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Claim the amount per claim, booking the claim under the identity keys of
+/// all credentials of the sender.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - The airdrop has ended.
+/// - A credential of the sender does not satisfy the constraints.
+/// - The identity of a credential of the sender has claimed already.
+/// - The contract holds too little CCD.
+#[receive(
+    contract = "sybil_airdrop",
+    name = "claim",
+    error = "ContractError",
+    crypto_primitives,
+    mutable
+)]
+fn claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let sender = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let now = ctx.metadata().slot_time();
+    let amount = host.state().amount_per_claim;
+    ensure!(host.self_balance() >= amount, ContractError::InsufficientFunds);
+    let state = host.state_mut();
+    ensure!(now <= state.ends, ContractError::Ended);
+
+    let mut keys = Vec::new();
+    for policy in ctx.policies() {
+        let key = identity_key(&policy, state, now, crypto_primitives)?;
+        ensure!(!state.claimed.contains(&key), ContractError::AlreadyClaimed);
+        keys.push(key);
+    }
+    for key in keys {
+        state.claimed.insert(key);
+    }
+    state.claims += 1;
+
+    // The transfer always succeeds, since the sender is an existing account,
+    // and the balance of the contract was checked above.
+    host.invoke_transfer(&sender, amount).unwrap_abort();
+    Ok(())
+}
+
+/// Withdraw the remaining CCD to the owner, after the airdrop ended.
+///
+/// It rejects if:
+/// - The sender is not the owner of the contract instance.
+/// - The airdrop has not ended.
+#[receive(contract = "sybil_airdrop", name = "withdrawRemaining", error = "ContractError", mutable)]
+fn withdraw_remaining<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let owner = ctx.owner();
+    ensure!(ctx.sender().matches_account(&owner), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() > host.state().ends, ContractError::NotEnded);
+
+    // The transfer always succeeds, since the owner is an existing account,
+    // and the contract holds its own balance.
+    let balance = host.self_balance();
+    host.invoke_transfer(&owner, balance).unwrap_abort();
+    Ok(())
+}
+
+/// View the constraints, the amount per claim, the end and the number of
+/// claims.
+#[receive(contract = "sybil_airdrop", name = "view", return_value = "ViewState")]
+fn view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        constraints:      state.constraints.clone(),
+        amount_per_claim: state.amount_per_claim,
+        ends:             state.ends,
+        claims:           state.claims,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const PROVIDER: IdentityProvider = 7;
+    const AMOUNT: Amount = Amount::from_micro_ccd(100);
+    const ENDS: u64 = 1_000;
+
+    /// Create a funded airdrop for residents of Denmark and Sweden, identifying
+    /// people by the number of their identity document.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            constraints:         Constraints {
+                identity_providers: vec![PROVIDER],
+                allowed_countries:  vec![*b"DK", *b"SE"],
+            },
+            identity_attributes: vec![attributes::ID_DOC_NUMBER.0],
+            amount_per_claim:    AMOUNT,
+            ends:                Timestamp::from_timestamp_millis(ENDS),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(Amount::from_micro_ccd(1_000));
+        host
+    }
+
+    /// A policy of `PROVIDER` valid until `ENDS`, revealing the given country
+    /// and document number.
+    fn policy(
+        country: [u8; 2],
+        document_number: [u8; 4],
+    ) -> Policy<Vec<(AttributeTag, AttributeValue)>> {
+        Policy {
+            identity_provider: PROVIDER,
+            created_at:        Timestamp::from_timestamp_millis(0),
+            valid_to:          Timestamp::from_timestamp_millis(ENDS),
+            items:             vec![
+                (attributes::COUNTRY_OF_RESIDENCE, country.into()),
+                (attributes::ID_DOC_NUMBER, document_number.into()),
+            ],
+        }
+    }
+
+    fn claim_with(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        policies: Vec<Policy<Vec<(AttributeTag, AttributeValue)>>>,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        for policy in policies {
+            ctx.push_policy(policy);
+        }
+        claim(&ctx, host, &TestCryptoPrimitives::new())
+    }
+
+    #[concordium_test]
+    /// Test that every identity can claim once, regardless of the account it
+    /// claims from.
+    fn test_claim_once_per_identity() {
+        let mut host = new_host();
+        claim_with(&mut host, ALICE, vec![policy(*b"DK", *b"A123")], 0)
+            .expect_report("Claiming should pass");
+        let res = claim_with(&mut host, BOB, vec![policy(*b"DK", *b"A123")], 0);
+        claim_eq!(res, Err(ContractError::AlreadyClaimed), "Another account of the same identity");
+        let res =
+            claim_with(&mut host, BOB, vec![policy(*b"SE", *b"B456"), policy(*b"DK", *b"A123")], 0);
+        claim_eq!(res, Err(ContractError::AlreadyClaimed), "Every credential should be checked");
+
+        claim_with(&mut host, BOB, vec![policy(*b"SE", *b"B456")], ENDS)
+            .expect_report("Claiming with another identity should pass");
+        claim_eq!(host.get_transfers(), [(ALICE, AMOUNT), (BOB, AMOUNT)]);
+        claim_eq!(host.state().claims, 2);
+    }
+
+    #[concordium_test]
+    /// Test that the constraints are checked for every credential.
+    fn test_constraints() {
+        let mut host = new_host();
+        let res = claim_with(&mut host, ALICE, vec![policy(*b"NO", *b"A123")], 0);
+        claim_eq!(res, Err(ContractError::CountryNotAllowed));
+
+        let mut other_provider = policy(*b"DK", *b"A123");
+        other_provider.identity_provider = PROVIDER + 1;
+        let res = claim_with(&mut host, ALICE, vec![other_provider], 0);
+        claim_eq!(res, Err(ContractError::UnknownIdentityProvider));
+
+        let mut missing = policy(*b"DK", *b"A123");
+        missing.items.pop();
+        let res = claim_with(&mut host, ALICE, vec![missing], 0);
+        claim_eq!(res, Err(ContractError::MissingAttribute));
+
+        let mut expired = policy(*b"DK", *b"A123");
+        expired.valid_to = Timestamp::from_timestamp_millis(0);
+        let res = claim_with(&mut host, ALICE, vec![expired], 1);
+        claim_eq!(res, Err(ContractError::ExpiredIdentity));
+
+        let res = claim_with(&mut host, ALICE, vec![policy(*b"DK", *b"A123")], ENDS + 1);
+        claim_eq!(res, Err(ContractError::Ended));
+        claim_eq!(host.state().claims, 0, "Failed claims should not be booked");
+    }
+
+    #[concordium_test]
+    /// Test that only the owner can withdraw the remaining CCD, and only after
+    /// the airdrop ended.
+    fn test_withdraw_remaining() {
+        let mut host = new_host();
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(OWNER);
+        ctx.set_sender(Address::Account(ALICE));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(ENDS + 1));
+        claim_eq!(withdraw_remaining(&ctx, &mut host), Err(ContractError::Unauthorized));
+
+        ctx.set_sender(Address::Account(OWNER));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(ENDS));
+        claim_eq!(withdraw_remaining(&ctx, &mut host), Err(ContractError::NotEnded));
+
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(ENDS + 1));
+        withdraw_remaining(&ctx, &mut host).expect_report("Withdrawing should pass");
+        claim_eq!(host.get_transfers(), [(OWNER, Amount::from_micro_ccd(1_000))]);
+    }
+}
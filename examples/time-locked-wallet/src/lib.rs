@@ -0,0 +1,280 @@
+//! # Implementation of a time-locked wallet smart contract
+//!
+//! A depositor locks CCD for a beneficiary until a release time, by sending it
+//! along when initializing the contract. Anyone can `deposit` more CCD into the
+//! wallet afterwards.
+//!
+//! Before the release time, nobody can withdraw the CCD. From the release time
+//! on, the beneficiary can `withdraw` everything in the wallet, and only the
+//! beneficiary can.
+//!
+//! If the depositor made the lock extendable, they can `extend` it to a later
+//! release time, as long as the current release time has not been reached.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The state of the smart contract.
+/// This state can be viewed by querying the node with the command
+/// `concordium-client contract invoke` using the `view` function as entrypoint.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct State {
+    /// The account which locked the CCD.
+    depositor:    AccountAddress,
+    /// The account which can withdraw the CCD once released.
+    beneficiary:  AccountAddress,
+    /// The time from which the beneficiary can withdraw the CCD.
+    release_time: Timestamp,
+    /// Whether the depositor can extend the lock.
+    extendable:   bool,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The account which can withdraw the CCD once released.
+    beneficiary:  AccountAddress,
+    /// The time from which the beneficiary can withdraw the CCD, using the RFC 3339 format (https://tools.ietf.org/html/rfc3339)
+    release_time: Timestamp,
+    /// Whether the depositor can extend the lock.
+    extendable:   bool,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a release time which has already been
+    /// reached.
+    ReleaseTimeInPast,
+    /// Raised when someone else than the beneficiary withdraws, or someone
+    /// else than the depositor extends.
+    Unauthorized,
+    /// Raised when withdrawing before the release time.
+    StillLocked,
+    /// Raised when withdrawing from an empty wallet.
+    NothingToWithdraw,
+    /// Raised when extending a lock which is not extendable.
+    NotExtendable,
+    /// Raised when extending a lock whose release time has been reached.
+    AlreadyReleased,
+    /// Raised when extending to a release time which is not later than the
+    /// current one.
+    InvalidExtension,
+}
+
+/// Init function that locks the CCD sent along for the beneficiary. The sender
+/// of the transaction becomes the depositor.
+#[init(contract = "time_locked_wallet", parameter = "InitParameter", payable)]
+fn wallet_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+    _amount: Amount,
+) -> InitResult<State> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(
+        ctx.metadata().slot_time() < parameter.release_time,
+        ContractError::ReleaseTimeInPast.into()
+    );
+    Ok(State {
+        depositor:    ctx.init_origin(),
+        beneficiary:  parameter.beneficiary,
+        release_time: parameter.release_time,
+        extendable:   parameter.extendable,
+    })
+}
+
+/// Deposit more CCD into the wallet. Anyone can deposit, also after the
+/// release time.
+#[receive(contract = "time_locked_wallet", name = "deposit", payable)]
+fn wallet_deposit<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Transfer all the CCD in the wallet to the beneficiary.
+///
+/// It rejects if:
+/// - The sender is not the beneficiary.
+/// - The release time has not been reached.
+/// - The wallet is empty.
+#[receive(contract = "time_locked_wallet", name = "withdraw", mutable, error = "ContractError")]
+fn wallet_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let state = host.state();
+    ensure!(ctx.sender().matches_account(&state.beneficiary), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() >= state.release_time, ContractError::StillLocked);
+    let beneficiary = state.beneficiary;
+
+    let balance = host.self_balance();
+    ensure!(balance > Amount::zero(), ContractError::NothingToWithdraw);
+    // The transfer always succeeds, since the beneficiary is the sender and the
+    // balance is available.
+    host.invoke_transfer(&beneficiary, balance).unwrap_abort();
+    Ok(())
+}
+
+/// Extend the lock to a later release time.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the depositor.
+/// - The lock is not extendable.
+/// - The current release time has been reached.
+/// - The new release time is not later than the current one.
+#[receive(
+    contract = "time_locked_wallet",
+    name = "extend",
+    parameter = "Timestamp",
+    mutable,
+    error = "ContractError"
+)]
+fn wallet_extend<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let release_time: Timestamp = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.depositor), ContractError::Unauthorized);
+    ensure!(state.extendable, ContractError::NotExtendable);
+    ensure!(ctx.metadata().slot_time() < state.release_time, ContractError::AlreadyReleased);
+    ensure!(release_time > state.release_time, ContractError::InvalidExtension);
+    state.release_time = release_time;
+    Ok(())
+}
+
+/// View function that returns the content of the state.
+#[receive(contract = "time_locked_wallet", name = "view", return_value = "State")]
+fn wallet_view<'a, 'b, S: HasStateApi>(
+    _ctx: &'a impl HasReceiveContext,
+    host: &'b impl HasHost<State, StateApiType = S>,
+) -> ReceiveResult<&'b State> {
+    Ok(host.state())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const DEPOSITOR: AccountAddress = AccountAddress([0; 32]);
+    const BENEFICIARY: AccountAddress = AccountAddress([1; 32]);
+    const RELEASE_TIME: u64 = 1_000;
+    const LOCKED: Amount = Amount {
+        micro_ccd: 500,
+    };
+
+    fn initialize(slot_time: u64, extendable: bool) -> InitResult<State> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            beneficiary: BENEFICIARY,
+            release_time: Timestamp::from_timestamp_millis(RELEASE_TIME),
+            extendable,
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(DEPOSITOR);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        wallet_init(&ctx, &mut TestStateBuilder::new(), LOCKED)
+    }
+
+    fn new_host(extendable: bool) -> TestHost<State> {
+        let state = initialize(0, extendable).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, TestStateBuilder::new());
+        host.set_self_balance(LOCKED);
+        host
+    }
+
+    fn extend_at(
+        host: &mut TestHost<State>,
+        sender: AccountAddress,
+        slot_time: u64,
+        release_time: u64,
+    ) -> Result<(), ContractError> {
+        let parameter_bytes = to_bytes(&Timestamp::from_timestamp_millis(release_time));
+        let mut ctx = ctx_at(sender, slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        wallet_extend(&ctx, host)
+    }
+
+    #[concordium_test]
+    /// Test that initialization rejects a release time which has been reached.
+    fn test_init_release_time_in_past() {
+        let res = initialize(RELEASE_TIME, false);
+        claim_eq!(res.map(|_| ()), Err(ContractError::ReleaseTimeInPast.into()));
+        claim!(initialize(RELEASE_TIME - 1, false).is_ok(), "The release time is in the future");
+    }
+
+    #[concordium_test]
+    /// Test that nobody can withdraw before the release time, and that the
+    /// beneficiary can withdraw everything from the release time on.
+    fn test_withdraw_boundary() {
+        let mut host = new_host(false);
+
+        let res = wallet_withdraw(&ctx_at(BENEFICIARY, RELEASE_TIME - 1), &mut host);
+        claim_eq!(res, Err(ContractError::StillLocked), "Withdrawing just before should fail");
+        let res = wallet_withdraw(&ctx_at(DEPOSITOR, RELEASE_TIME), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the beneficiary can withdraw");
+        claim!(host.get_transfers().is_empty(), "No CCD should be transferred");
+
+        wallet_withdraw(&ctx_at(BENEFICIARY, RELEASE_TIME), &mut host)
+            .expect_report("Withdrawing at the release time should pass");
+        claim_eq!(host.get_transfers(), [(BENEFICIARY, LOCKED)]);
+        claim_eq!(host.self_balance(), Amount::zero());
+
+        let res = wallet_withdraw(&ctx_at(BENEFICIARY, RELEASE_TIME + 1), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToWithdraw));
+    }
+
+    #[concordium_test]
+    /// Test that deposits after initialization are withdrawn as well.
+    fn test_deposit() {
+        let mut host = new_host(false);
+        let deposit = Amount::from_micro_ccd(250);
+        host.set_self_balance(LOCKED + deposit);
+        wallet_deposit(&ctx_at(DEPOSITOR, 0), &host, deposit)
+            .expect_report("Depositing should pass");
+
+        wallet_withdraw(&ctx_at(BENEFICIARY, RELEASE_TIME), &mut host)
+            .expect_report("Withdrawing should pass");
+        claim_eq!(host.get_transfers(), [(BENEFICIARY, LOCKED + deposit)]);
+    }
+
+    #[concordium_test]
+    /// Test that the depositor can extend the lock before the release time, and
+    /// only to a later time.
+    fn test_extend() {
+        let mut host = new_host(true);
+
+        let res = extend_at(&mut host, BENEFICIARY, 0, 2 * RELEASE_TIME);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the depositor can extend");
+        let res = extend_at(&mut host, DEPOSITOR, 0, RELEASE_TIME);
+        claim_eq!(res, Err(ContractError::InvalidExtension), "The release time must be later");
+        extend_at(&mut host, DEPOSITOR, RELEASE_TIME - 1, 2 * RELEASE_TIME)
+            .expect_report("Extending just before the release time should pass");
+
+        let res = wallet_withdraw(&ctx_at(BENEFICIARY, 2 * RELEASE_TIME - 1), &mut host);
+        claim_eq!(res, Err(ContractError::StillLocked), "The new release time should apply");
+        let res = extend_at(&mut host, DEPOSITOR, 2 * RELEASE_TIME, 3 * RELEASE_TIME);
+        claim_eq!(res, Err(ContractError::AlreadyReleased), "Released locks cannot be extended");
+        wallet_withdraw(&ctx_at(BENEFICIARY, 2 * RELEASE_TIME), &mut host)
+            .expect_report("Withdrawing at the new release time should pass");
+    }
+
+    #[concordium_test]
+    /// Test that locks which are not extendable cannot be extended.
+    fn test_extend_not_extendable() {
+        let mut host = new_host(false);
+        let res = extend_at(&mut host, DEPOSITOR, 0, 2 * RELEASE_TIME);
+        claim_eq!(res, Err(ContractError::NotExtendable));
+        claim_eq!(host.state().release_time, Timestamp::from_timestamp_millis(RELEASE_TIME));
+    }
+}
@@ -0,0 +1,429 @@
+//! # A tipping smart contract for creators
+//!
+//! Creators register with the contract using `registerCreator`, and get a
+//! creator id which viewers tag their tips with. A creator id belongs to the
+//! account which registered it, and the same account can register several ids,
+//! e.g. one for every channel.
+//!
+//! Viewers `tip` a creator by sending CCD along with the creator id, and
+//! optionally the SHA-256 hash of a message, which is stored off-chain, e.g.
+//! by the front end showing it on the stream. The platform fee, a number of
+//! basis points of the tip set at initialization, is deducted, and the rest is
+//! added to the balance of the creator. Every tip is logged as an event, which
+//! the front end can follow to show tips as they arrive.
+//!
+//! Creators `withdraw` their balance to their account whenever they want, and
+//! the platform owner withdraws the fees using `withdrawFees`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The identifier of a creator.
+pub type CreatorId = u64;
+
+/// A creator, and the tips it has not withdrawn.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Creator {
+    /// The account which registered the creator id, and withdraws the tips.
+    pub account: AccountAddress,
+    /// The tips which have not been withdrawn, after fees.
+    pub balance: Amount,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The platform owner, who receives the fees.
+    owner:            AccountAddress,
+    /// The fee in basis points of every tip.
+    fee_basis_points: BasisPoints,
+    /// The fees which have not been withdrawn.
+    fees:             Amount,
+    /// The identifier of the next creator.
+    next_creator_id:  CreatorId,
+    /// The registered creators.
+    creators:         StateMap<CreatorId, Creator, S>,
+}
+
+/// The parameter type for the contract function `tip`.
+#[derive(Serialize, SchemaType)]
+pub struct TipParams {
+    /// The creator to tip.
+    pub creator:      CreatorId,
+    /// The SHA-256 hash of the message sent with the tip, if any.
+    pub message_hash: Option<[u8; 32]>,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum TipEvent {
+    /// A creator id was registered.
+    Register {
+        creator: CreatorId,
+        account: AccountAddress,
+    },
+    /// A viewer tipped a creator.
+    Tip {
+        creator:      CreatorId,
+        viewer:       Address,
+        /// The tip, including the fee.
+        amount:       Amount,
+        fee:          Amount,
+        message_hash: Option<[u8; 32]>,
+    },
+    /// A creator withdrew its balance.
+    Withdraw {
+        creator: CreatorId,
+        amount:  Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when initializing with a fee above 10000 basis points.
+    InvalidFee,
+    /// Raised when a contract registers a creator id; Only accounts can
+    /// withdraw tips.
+    OnlyAccount,
+    /// Raised when the creator id is not registered.
+    UnknownCreator,
+    /// Raised when tipping without sending CCD along.
+    ZeroTip,
+    /// Raised when someone else than the account of the creator, or the owner
+    /// for fees, withdraws.
+    Unauthorized,
+    /// Raised when there is nothing to withdraw.
+    NothingToWithdraw,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Initialize the contract with the fee in basis points. The sender becomes
+/// the platform owner.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The fee is above 10000 basis points.
+#[init(contract = "tipping", parameter = "u16", event = "TipEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let fee_basis_points: u16 = ctx.parameter_cursor().get()?;
+    let fee_basis_points = BasisPoints::new(fee_basis_points).ok_or(ContractError::InvalidFee)?;
+    Ok(State {
+        owner: ctx.init_origin(),
+        fee_basis_points,
+        fees: Amount::zero(),
+        next_creator_id: 0,
+        creators: state_builder.new_map(),
+    })
+}
+
+/// Register a new creator id for the sender. Returns the creator id.
+///
+/// It rejects if:
+/// - The sender is a contract.
+/// - It fails to log the event.
+#[receive(
+    contract = "tipping",
+    name = "registerCreator",
+    return_value = "CreatorId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_register_creator<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<CreatorId> {
+    let account = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    let creator = state.next_creator_id;
+    state.next_creator_id += 1;
+    state.creators.insert(creator, Creator {
+        account,
+        balance: Amount::zero(),
+    });
+    logger.log(&TipEvent::Register {
+        creator,
+        account,
+    })?;
+    Ok(creator)
+}
+
+/// Tip a creator the CCD sent along, minus the platform fee.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - No CCD is sent along.
+/// - The creator id is not registered.
+/// - It fails to log the event.
+#[receive(
+    contract = "tipping",
+    name = "tip",
+    parameter = "TipParams",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_tip<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let params: TipParams = ctx.parameter_cursor().get()?;
+    ensure!(amount > Amount::zero(), ContractError::ZeroTip);
+    let state = host.state_mut();
+    let fee = state.fee_basis_points.of(amount);
+    {
+        let mut creator =
+            state.creators.get_mut(&params.creator).ok_or(ContractError::UnknownCreator)?;
+        creator.balance += amount - fee;
+    }
+    state.fees += fee;
+    logger.log(&TipEvent::Tip {
+        creator: params.creator,
+        viewer: ctx.sender(),
+        amount,
+        fee,
+        message_hash: params.message_hash,
+    })?;
+    Ok(())
+}
+
+/// Withdraw the balance of a creator to its account.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The creator id is not registered.
+/// - The sender is not the account of the creator.
+/// - The balance is zero.
+/// - It fails to log the event.
+#[receive(
+    contract = "tipping",
+    name = "withdraw",
+    parameter = "CreatorId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_withdraw<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let creator_id: CreatorId = ctx.parameter_cursor().get()?;
+    let (account, amount) = {
+        let mut creator =
+            host.state().creators.get_mut(&creator_id).ok_or(ContractError::UnknownCreator)?;
+        ensure!(ctx.sender().matches_account(&creator.account), ContractError::Unauthorized);
+        ensure!(creator.balance > Amount::zero(), ContractError::NothingToWithdraw);
+        let amount = creator.balance;
+        creator.balance = Amount::zero();
+        (creator.account, amount)
+    };
+    // The transfer always succeeds, since the account is the sender and the
+    // balance is held by the contract.
+    host.invoke_transfer(&account, amount).unwrap_abort();
+    logger.log(&TipEvent::Withdraw {
+        creator: creator_id,
+        amount,
+    })?;
+    Ok(())
+}
+
+/// Withdraw the platform fees to the owner.
+///
+/// It rejects if:
+/// - The sender is not the owner.
+/// - There are no fees.
+#[receive(contract = "tipping", name = "withdrawFees", error = "ContractError", mutable)]
+fn contract_withdraw_fees<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    let owner = state.owner;
+    ensure!(ctx.sender().matches_account(&owner), ContractError::Unauthorized);
+    let fees = state.fees;
+    ensure!(fees > Amount::zero(), ContractError::NothingToWithdraw);
+    state.fees = Amount::zero();
+    // The transfer always succeeds, since the owner is the sender and the fees
+    // are held by the contract.
+    host.invoke_transfer(&owner, fees).unwrap_abort();
+    Ok(())
+}
+
+/// View a creator and its balance.
+#[receive(
+    contract = "tipping",
+    name = "creator",
+    parameter = "CreatorId",
+    return_value = "Creator",
+    error = "ContractError"
+)]
+fn contract_creator<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<Creator> {
+    let creator_id: CreatorId = ctx.parameter_cursor().get()?;
+    host.state().creators.get(&creator_id).map(|c| *c).ok_or(ContractError::UnknownCreator)
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const CREATOR: AccountAddress = AccountAddress([1; 32]);
+    const VIEWER: AccountAddress = AccountAddress([2; 32]);
+    const FEE_BASIS_POINTS: u16 = 500;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&FEE_BASIS_POINTS);
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(OWNER);
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_from<'a>(sender: AccountAddress, parameter_bytes: &'a [u8]) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(sender));
+        ctx.set_parameter(parameter_bytes);
+        ctx
+    }
+
+    fn register(host: &mut TestHost<State<TestStateApi>>) -> CreatorId {
+        contract_register_creator(&ctx_from(CREATOR, &[]), host, &mut TestLogger::init())
+            .expect_report("Registering should pass")
+    }
+
+    fn tip(
+        host: &mut TestHost<State<TestStateApi>>,
+        creator: CreatorId,
+        micro_ccd: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&TipParams {
+            creator,
+            message_hash: Some([7; 32]),
+        });
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_tip(&ctx_from(VIEWER, &parameter_bytes), host, amount, logger)
+    }
+
+    fn balance_of(host: &TestHost<State<TestStateApi>>, creator: CreatorId) -> Amount {
+        let parameter_bytes = to_bytes(&creator);
+        contract_creator(&ctx_from(VIEWER, &parameter_bytes), host)
+            .expect_report("The creator should exist")
+            .balance
+    }
+
+    #[concordium_test]
+    /// Test that tips are added to the balance of the creator after the fee,
+    /// and logged.
+    fn test_tip() {
+        let mut host = new_host();
+        let first = register(&mut host);
+        let second = register(&mut host);
+        claim_eq!((first, second), (0, 1));
+
+        let mut logger = TestLogger::init();
+        tip(&mut host, second, 1_000, &mut logger).expect_report("Tipping should pass");
+        tip(&mut host, second, 99, &mut logger).expect_report("Tipping should pass");
+        claim_eq!(
+            logger.logs[0],
+            to_bytes(&TipEvent::Tip {
+                creator:      second,
+                viewer:       Address::Account(VIEWER),
+                amount:       Amount::from_micro_ccd(1_000),
+                fee:          Amount::from_micro_ccd(50),
+                message_hash: Some([7; 32]),
+            })
+        );
+        // The fee of 99 micro CCD rounds down to 4.
+        claim_eq!(balance_of(&host, second), Amount::from_micro_ccd(1_045));
+        claim_eq!(balance_of(&host, first), Amount::zero());
+        claim_eq!(host.state().fees, Amount::from_micro_ccd(54));
+    }
+
+    #[concordium_test]
+    /// Test that tips must be for a registered creator, and not be zero.
+    fn test_tip_rejected() {
+        let mut host = new_host();
+        let mut logger = TestLogger::init();
+        claim_eq!(tip(&mut host, 0, 1_000, &mut logger), Err(ContractError::UnknownCreator));
+        let creator = register(&mut host);
+        claim_eq!(tip(&mut host, creator, 0, &mut logger), Err(ContractError::ZeroTip));
+        claim!(logger.logs.is_empty(), "No tip should be logged");
+    }
+
+    #[concordium_test]
+    /// Test that only the account of the creator withdraws the balance, and
+    /// only the owner withdraws the fees.
+    fn test_withdraw() {
+        let mut host = new_host();
+        let creator = register(&mut host);
+        tip(&mut host, creator, 1_000, &mut TestLogger::init())
+            .expect_report("Tipping should pass");
+
+        let parameter_bytes = to_bytes(&creator);
+        let res = contract_withdraw(
+            &ctx_from(VIEWER, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_withdraw(&ctx_from(CREATOR, &parameter_bytes), &mut host, &mut TestLogger::init())
+            .expect_report("Withdrawing should pass");
+        let res = contract_withdraw(
+            &ctx_from(CREATOR, &parameter_bytes),
+            &mut host,
+            &mut TestLogger::init(),
+        );
+        claim_eq!(res, Err(ContractError::NothingToWithdraw));
+
+        let res = contract_withdraw_fees(&ctx_from(CREATOR, &[]), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized));
+        contract_withdraw_fees(&ctx_from(OWNER, &[]), &mut host)
+            .expect_report("Withdrawing fees should pass");
+        claim_eq!(host.get_transfers(), [
+            (CREATOR, Amount::from_micro_ccd(950)),
+            (OWNER, Amount::from_micro_ccd(50))
+        ]);
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+}
@@ -0,0 +1,466 @@
+//! A token-weighted voting smart contract example.
+//!
+//! # Description
+//! A contract that allows for conducting an election with several voting
+//! options, where the weight of a vote is the balance of the voter in a token
+//! of a CIS-2 contract, such as the `cis2-fungible` or `cis2-multi` examples.
+//! The token is set when the election is initialized, together with an
+//! `end_time` after which no voting is possible.
+//!
+//! When an account votes, the contract queries `balanceOf` of the token
+//! contract, and counts the balance at that moment towards the chosen voting
+//! option. An account can change its vote until the `end_time`, in which case
+//! its previous vote is withdrawn from the tally and its balance is queried
+//! again.
+//!
+//! Note: The balances are read at the time of voting, and the tokens are not
+//! locked. Tokens transferred to another account after voting can therefore be
+//! counted again by the receiving account. Elections which must prevent this
+//! should use a token contract supporting snapshots, or lock the tokens for
+//! the duration of the election.
+//!
+//! If the query of the token contract fails, the vote is rejected with an
+//! error describing whether the token contract could not be found, rejected
+//! the query, or returned a response which could not be parsed.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_cis2::*;
+use concordium_std::*;
+
+/// The human-readable description of a voting option.
+type VotingOption = String;
+/// The voting options are stored in a vector. The vector index is used to refer
+/// to a specific voting option.
+type VoteIndex = u32;
+
+/// Token ID type of the voting token. Using `TokenIdVec` allows using a token
+/// from any CIS-2 contract, since all token IDs serialize the same way.
+type ContractTokenId = TokenIdVec;
+
+/// Token amount type of the balances and the tally. Token amounts serialize the
+/// same way regardless of their size, so balances of tokens using smaller
+/// amount types can be parsed as well.
+type ContractTokenAmount = TokenAmountU128;
+
+/// The vote of an account and the weight it was counted with.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Ballot {
+    /// The index of the voting option voted for.
+    vote_index: VoteIndex,
+    /// The balance of the voter at the time of voting.
+    weight:     ContractTokenAmount,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The description of the election.
+    description: String,
+    /// A vector of all voting options.
+    options:     Vec<VotingOption>,
+    /// The last timestamp that an account can vote.
+    end_time:    Timestamp,
+    /// The CIS-2 contract of the voting token.
+    token:       ContractAddress,
+    /// The ID of the voting token in the CIS-2 contract.
+    token_id:    ContractTokenId,
+}
+
+/// The `return_value` type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct VotingView {
+    /// The description of the election.
+    description: String,
+    /// The last timestamp that an account can vote.
+    end_time:    Timestamp,
+    /// A vector of all voting options.
+    options:     Vec<VotingOption>,
+    /// The CIS-2 contract of the voting token.
+    token:       ContractAddress,
+    /// The ID of the voting token in the CIS-2 contract.
+    token_id:    ContractTokenId,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The description of the election.
+    description: String,
+    /// A vector of all voting options.
+    options:     Vec<VotingOption>,
+    /// The last timestamp that an account can vote.
+    end_time:    Timestamp,
+    /// The CIS-2 contract of the voting token.
+    token:       ContractAddress,
+    /// The ID of the voting token in the CIS-2 contract.
+    token_id:    ContractTokenId,
+    /// The map connects a voter to their vote and its weight.
+    ballots:     StateMap<AccountAddress, Ballot, S>,
+    /// The map connects the index of a voting option to the total weight of
+    /// the votes it received so far.
+    tally:       StateMap<VoteIndex, ContractTokenAmount, S>,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when the vote is placed after the election has ended.
+    VotingFinished,
+    /// Raised when voting for a voting index that does not exist.
+    InvalidVoteIndex,
+    /// Raised when a smart contract tries to participate in the election. Only
+    /// accounts are allowed to vote.
+    ContractVoter,
+    /// Raised when the voter does not hold any of the voting token.
+    NoVotingPower,
+    /// Raised when the token contract or its `balanceOf` entrypoint does not
+    /// exist.
+    TokenContractUnavailable,
+    /// Raised when the token contract rejects the `balanceOf` query, e.g.,
+    /// because the token ID is unknown.
+    TokenQueryRejected,
+    /// Raised when the response of the token contract cannot be parsed.
+    TokenQueryMalformed,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// The event is logged when a new (or replacement) vote is cast by an account.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct VoteEvent {
+    /// The account that casts the vote.
+    voter:      AccountAddress,
+    /// The index of the voting option that the account is voting for.
+    vote_index: VoteIndex,
+    /// The weight the vote is counted with.
+    weight:     ContractTokenAmount,
+}
+
+/// The event logged by this smart contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum Event {
+    /// The event is logged when a new (or replacement) vote is cast by an
+    /// account.
+    Vote(VoteEvent),
+}
+
+/// Query the balance of the voter in the voting token.
+fn query_voting_power<S: HasStateApi>(
+    host: &impl HasHost<State<S>, StateApiType = S>,
+    voter: AccountAddress,
+) -> ContractResult<ContractTokenAmount> {
+    let state = host.state();
+    let parameter = BalanceOfQueryParams {
+        queries: vec![BalanceOfQuery {
+            token_id: state.token_id.clone(),
+            address:  Address::Account(voter),
+        }],
+    };
+    let response = host
+        .invoke_contract_read_only(
+            &state.token,
+            &parameter,
+            EntrypointName::new_unchecked("balanceOf"),
+            Amount::zero(),
+        )
+        .map_err(|error| match error {
+            CallContractError::MissingContract | CallContractError::MissingEntrypoint => {
+                ContractError::TokenContractUnavailable
+            }
+            _ => ContractError::TokenQueryRejected,
+        })?;
+    let mut response = response.ok_or(ContractError::TokenQueryMalformed)?;
+    let balances: BalanceOfQueryResponse<ContractTokenAmount> =
+        response.get().map_err(|_| ContractError::TokenQueryMalformed)?;
+    balances.0.first().copied().ok_or(ContractError::TokenQueryMalformed)
+}
+
+// Contract functions
+
+/// Initialize the contract instance and start the election.
+#[init(contract = "token_voting", parameter = "InitParameter", event = "Event")]
+fn init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let param: InitParameter = ctx.parameter_cursor().get()?;
+    Ok(State {
+        description: param.description,
+        options:     param.options,
+        end_time:    param.end_time,
+        token:       param.token,
+        token_id:    param.token_id,
+        ballots:     state_builder.new_map(),
+        tally:       state_builder.new_map(),
+    })
+}
+
+/// Vote for a specific voting option, weighted by the current balance of the
+/// voter in the voting token. Each account can change its selected voting
+/// option as often as it desires until the `end_time` is reached, which also
+/// updates the weight to its current balance.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - A contract tries to vote.
+/// - It is past the `end_time`.
+/// - The voting index does not exist.
+/// - The query of the token contract fails.
+/// - The voter does not hold any of the voting token.
+/// - It fails to log the event.
+#[receive(
+    contract = "token_voting",
+    name = "vote",
+    mutable,
+    enable_logger,
+    parameter = "VoteIndex",
+    error = "ContractError"
+)]
+fn vote<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(ctx.metadata().slot_time() <= host.state().end_time, ContractError::VotingFinished);
+    let voter = match ctx.sender() {
+        Address::Account(acc) => acc,
+        Address::Contract(_) => bail!(ContractError::ContractVoter),
+    };
+    let vote_index: VoteIndex = ctx.parameter_cursor().get()?;
+    ensure!((vote_index as usize) < host.state().options.len(), ContractError::InvalidVoteIndex);
+
+    let weight = query_voting_power(host, voter)?;
+    ensure!(weight > ContractTokenAmount::from(0), ContractError::NoVotingPower);
+
+    let state = host.state_mut();
+    let new_ballot = Ballot {
+        vote_index,
+        weight,
+    };
+    if let Some(old_ballot) = state.ballots.insert(voter, new_ballot) {
+        // Withdraw the previous vote from the tally.
+        *state.tally.entry(old_ballot.vote_index).or_insert(old_ballot.weight) -= old_ballot.weight;
+    }
+    *state.tally.entry(vote_index).or_insert(ContractTokenAmount::from(0)) += weight;
+
+    logger.log(&Event::Vote(VoteEvent {
+        voter,
+        vote_index,
+        weight,
+    }))?;
+    Ok(())
+}
+
+/// Get the total weight of the votes for a specific voting option.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "token_voting",
+    name = "getNumberOfVotes",
+    parameter = "VoteIndex",
+    return_value = "ContractTokenAmount"
+)]
+fn get_votes<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ContractTokenAmount> {
+    let vote_index: VoteIndex = ctx.parameter_cursor().get()?;
+    Ok(host.state().tally.get(&vote_index).map(|votes| *votes).unwrap_or_default())
+}
+
+/// Get the election information.
+#[receive(contract = "token_voting", name = "view", return_value = "VotingView")]
+fn view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<VotingView> {
+    let state = host.state();
+    Ok(VotingView {
+        description: state.description.clone(),
+        end_time:    state.end_time,
+        options:     state.options.clone(),
+        token:       state.token,
+        token_id:    state.token_id.clone(),
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([0; 32]);
+    const BOB: AccountAddress = AccountAddress([1; 32]);
+    const CAROL: AccountAddress = AccountAddress([2; 32]);
+    const TOKEN: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const END_TIME: u64 = 100;
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            description: "Next feature".into(),
+            options:     vec!["A".into(), "B".into()],
+            end_time:    Timestamp::from_timestamp_millis(END_TIME),
+            token:       TOKEN,
+            token_id:    TokenIdVec(vec![]),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state = init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    /// Mock `balanceOf` of the token contract, answering with the given
+    /// balances of Alice and Bob, and zero for everyone else. The balances are
+    /// returned as `TokenAmountU64`, as done by the CIS-2 examples.
+    fn mock_balances(host: &mut TestHost<State<TestStateApi>>, alice: u64, bob: u64) {
+        host.setup_mock_entrypoint(
+            TOKEN,
+            OwnedEntrypointName::new_unchecked("balanceOf".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let params: BalanceOfQueryParams<ContractTokenId> =
+                    from_bytes(parameter.0).expect_report("Invalid balanceOf parameter");
+                let address = params.queries[0].address;
+                let balance = if address.matches_account(&ALICE) {
+                    alice
+                } else if address.matches_account(&BOB) {
+                    bob
+                } else {
+                    0
+                };
+                Ok((false, BalanceOfQueryResponse(vec![TokenAmountU64::from(balance)])))
+            }),
+        );
+    }
+
+    fn vote_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        voter: AccountAddress,
+        vote_index: VoteIndex,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&vote_index);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(voter));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        vote(&ctx, host, &mut TestLogger::init())
+    }
+
+    fn votes(host: &TestHost<State<TestStateApi>>, vote_index: VoteIndex) -> ContractTokenAmount {
+        let parameter_bytes = to_bytes(&vote_index);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        get_votes(&ctx, host).expect_report("Querying the votes should pass")
+    }
+
+    #[concordium_test]
+    /// Test that votes are weighted by the balance of the voter.
+    fn test_weighted_votes() {
+        let mut host = new_host();
+        mock_balances(&mut host, 100, 30);
+
+        vote_at(&mut host, ALICE, 0, 0).expect_report("Voting should pass");
+        vote_at(&mut host, BOB, 1, END_TIME).expect_report("Voting at the end should pass");
+
+        claim_eq!(votes(&host, 0), ContractTokenAmount::from(100));
+        claim_eq!(votes(&host, 1), ContractTokenAmount::from(30));
+    }
+
+    #[concordium_test]
+    /// Test that changing a vote withdraws the previous vote and counts the
+    /// current balance.
+    fn test_change_vote() {
+        let mut host = new_host();
+        mock_balances(&mut host, 100, 30);
+        vote_at(&mut host, ALICE, 0, 0).expect_report("Voting should pass");
+        vote_at(&mut host, BOB, 0, 0).expect_report("Voting should pass");
+
+        mock_balances(&mut host, 60, 30);
+        vote_at(&mut host, ALICE, 1, 10).expect_report("Changing the vote should pass");
+        claim_eq!(votes(&host, 0), ContractTokenAmount::from(30));
+        claim_eq!(votes(&host, 1), ContractTokenAmount::from(60));
+
+        vote_at(&mut host, ALICE, 1, 20).expect_report("Voting again should pass");
+        claim_eq!(votes(&host, 1), ContractTokenAmount::from(60), "The vote is counted once");
+    }
+
+    #[concordium_test]
+    /// Test that votes without voting power, after the end, for an unknown
+    /// option or from contracts are rejected.
+    fn test_vote_rejected() {
+        let mut host = new_host();
+        mock_balances(&mut host, 100, 30);
+
+        claim_eq!(vote_at(&mut host, CAROL, 0, 0), Err(ContractError::NoVotingPower));
+        claim_eq!(vote_at(&mut host, ALICE, 0, END_TIME + 1), Err(ContractError::VotingFinished));
+        claim_eq!(vote_at(&mut host, ALICE, 2, 0), Err(ContractError::InvalidVoteIndex));
+
+        let parameter_bytes = to_bytes(&0u32);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Contract(TOKEN));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        ctx.set_parameter(&parameter_bytes);
+        let res = vote(&ctx, &mut host, &mut TestLogger::init());
+        claim_eq!(res, Err(ContractError::ContractVoter));
+        claim_eq!(votes(&host, 0), ContractTokenAmount::from(0));
+    }
+
+    #[concordium_test]
+    /// Test that failing queries of the token contract reject the vote with a
+    /// descriptive error.
+    fn test_query_failures() {
+        let mut host = new_host();
+        let balance_of = OwnedEntrypointName::new_unchecked("balanceOf".into());
+
+        host.setup_mock_entrypoint(
+            TOKEN,
+            balance_of.clone(),
+            MockFn::returning_err::<()>(CallContractError::MissingContract),
+        );
+        claim_eq!(vote_at(&mut host, ALICE, 0, 0), Err(ContractError::TokenContractUnavailable));
+
+        host.setup_mock_entrypoint(
+            TOKEN,
+            balance_of.clone(),
+            MockFn::returning_err::<()>(CallContractError::Trap),
+        );
+        claim_eq!(vote_at(&mut host, ALICE, 0, 0), Err(ContractError::TokenQueryRejected));
+
+        host.setup_mock_entrypoint(TOKEN, balance_of.clone(), MockFn::returning_ok(()));
+        claim_eq!(vote_at(&mut host, ALICE, 0, 0), Err(ContractError::TokenQueryMalformed));
+
+        host.setup_mock_entrypoint(
+            TOKEN,
+            balance_of,
+            MockFn::new_v1(|_parameter, _amount, _balance, _state| {
+                Ok((false, BalanceOfQueryResponse::<TokenAmountU64>(vec![])))
+            }),
+        );
+        claim_eq!(vote_at(&mut host, ALICE, 0, 0), Err(ContractError::TokenQueryMalformed));
+        claim!(host.state().ballots.iter().next().is_none(), "No vote should be counted");
+    }
+}
@@ -0,0 +1,606 @@
+//! A treasury custodying platform fees, released through proposals approved
+//! by a committee and delayed by a timelock.
+//!
+//! # Description
+//! Contracts collecting platform fees forward them to the treasury using
+//! `deposit`, which anyone can call. The treasury keeps the total deposited by
+//! every address, and logs every deposit.
+//!
+//! Funds are only released through proposals. A member of the committee
+//! `propose`s an action, which counts as their approval, and the other members
+//! `approve` it. An action is one of:
+//! - withdrawing CCD to an account,
+//! - replacing the committee and the threshold of approvals.
+//!
+//! As soon as the number of approvals from current members reaches the
+//! threshold, the proposal is queued, and anyone can `execute` it once the
+//! timelock has passed since. The timelock gives depositors and the public time
+//! to react to a queued proposal, e.g., by no longer forwarding fees, before
+//! funds leave the treasury. When executed, the approvals are counted again, so
+//! approvals of members removed in the meantime no longer count.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::{collections::BTreeSet, *};
+
+/// Identifier of a proposal.
+pub type ProposalId = u64;
+
+/// An action the committee can propose.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Withdraw CCD to an account.
+    Withdraw {
+        to:     AccountAddress,
+        amount: Amount,
+    },
+    /// Replace the committee and the number of approvals needed to queue a
+    /// proposal.
+    UpdateCommittee {
+        #[concordium(size_length = 1)]
+        members:   BTreeSet<AccountAddress>,
+        threshold: u8,
+    },
+}
+
+/// A pending proposal.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    /// The proposed action.
+    action:    Action,
+    /// The members who approved the proposal.
+    #[concordium(size_length = 1)]
+    approvals: BTreeSet<AccountAddress>,
+    /// The time after which the proposal can be executed, once it reached the
+    /// threshold.
+    eta:       Option<Timestamp>,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S> {
+    /// The accounts allowed to propose and approve.
+    members:          BTreeSet<AccountAddress>,
+    /// The number of approvals needed to queue a proposal.
+    threshold:        u8,
+    /// How long a proposal is queued before it can be executed.
+    timelock:         Duration,
+    /// The identifier of the next proposal.
+    next_proposal_id: ProposalId,
+    /// The pending proposals.
+    proposals:        StateMap<ProposalId, Proposal, S>,
+    /// The total deposited by every address.
+    deposits:         StateMap<Address, Amount, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+struct InitParams {
+    /// The accounts allowed to propose and approve.
+    #[concordium(size_length = 1)]
+    members:   BTreeSet<AccountAddress>,
+    /// The number of approvals needed to queue a proposal.
+    threshold: u8,
+    /// How long a proposal is queued before it can be executed.
+    timelock:  Duration,
+}
+
+/// The `return_value` type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    /// The accounts allowed to propose and approve.
+    #[concordium(size_length = 1)]
+    members:   BTreeSet<AccountAddress>,
+    /// The number of approvals needed to queue a proposal.
+    threshold: u8,
+    /// How long a proposal is queued before it can be executed.
+    timelock:  Duration,
+    /// The pending proposals.
+    proposals: Vec<(ProposalId, Proposal)>,
+}
+
+/// The events logged by the contract.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub enum TreasuryEvent {
+    /// CCD was deposited.
+    Deposited {
+        from:   Address,
+        amount: Amount,
+    },
+    /// A member proposed an action.
+    Proposed {
+        id:       ProposalId,
+        proposer: AccountAddress,
+    },
+    /// A member approved a proposal.
+    Approved {
+        id:     ProposalId,
+        member: AccountAddress,
+    },
+    /// A proposal reached the threshold, and can be executed after the time.
+    Queued {
+        id:  ProposalId,
+        eta: Timestamp,
+    },
+    /// A proposal was executed.
+    Executed {
+        id: ProposalId,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Serialize, Debug, PartialEq, Eq, Reject, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Raised when the sender is not a member of the committee.
+    NotMember,
+    /// Raised when the threshold is zero or larger than the number of members.
+    InvalidThreshold,
+    /// Raised when the proposal does not exist.
+    UnknownProposal,
+    /// Raised when a member approves the same proposal twice.
+    AlreadyApproved,
+    /// Raised when executing a proposal which has not reached the threshold of
+    /// current members.
+    NotApproved,
+    /// Raised when executing a proposal before the timelock has passed.
+    Timelocked,
+    /// Raised when the treasury does not hold enough CCD for a withdrawal.
+    InsufficientFunds,
+    /// Raised when withdrawing CCD to an account that does not exist.
+    MissingAccount,
+}
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to transfers to ContractError.
+impl From<TransferError> for ContractError {
+    fn from(te: TransferError) -> Self {
+        match te {
+            TransferError::AmountTooLarge => Self::InsufficientFunds,
+            TransferError::MissingAccount => Self::MissingAccount,
+        }
+    }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Ensure the threshold is reachable by the members.
+fn ensure_valid_threshold(members: &BTreeSet<AccountAddress>, threshold: u8) -> ContractResult<()> {
+    ensure!(
+        threshold > 0 && usize::from(threshold) <= members.len(),
+        ContractError::InvalidThreshold
+    );
+    Ok(())
+}
+
+/// Get the sender if it is a member of the committee.
+fn sender_member<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+) -> ContractResult<AccountAddress> {
+    match ctx.sender() {
+        Address::Account(account) if state.members.contains(&account) => Ok(account),
+        _ => bail!(ContractError::NotMember),
+    }
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Whether the proposal is approved by at least the threshold of current
+    /// members.
+    fn is_approved(&self, proposal: &Proposal) -> bool {
+        // Approvals of removed members do not count.
+        proposal.approvals.intersection(&self.members).count() >= usize::from(self.threshold)
+    }
+}
+
+/// Queue the proposal if it is approved, and not queued already.
+fn queue_if_approved<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &mut State<S>,
+    id: ProposalId,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let mut proposal = state.proposals.get(&id).ok_or(ContractError::UnknownProposal)?.clone();
+    if proposal.eta.is_some() || !state.is_approved(&proposal) {
+        return Ok(());
+    }
+    let eta = ctx
+        .metadata()
+        .slot_time()
+        .checked_add(state.timelock)
+        .unwrap_or_else(|| Timestamp::from_timestamp_millis(u64::MAX));
+    proposal.eta = Some(eta);
+    state.proposals.insert(id, proposal);
+    logger.log(&TreasuryEvent::Queued {
+        id,
+        eta,
+    })?;
+    Ok(())
+}
+
+/// Initialize the treasury with the committee, the threshold and the timelock.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The threshold is zero or larger than the number of members.
+#[init(contract = "treasury", parameter = "InitParams", event = "TreasuryEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    ensure_valid_threshold(&params.members, params.threshold)?;
+    Ok(State {
+        members:          params.members,
+        threshold:        params.threshold,
+        timelock:         params.timelock,
+        next_proposal_id: 0,
+        proposals:        state_builder.new_map(),
+        deposits:         state_builder.new_map(),
+    })
+}
+
+/// Deposit the CCD sent along, e.g., fees forwarded by another contract.
+/// Anyone can deposit.
+///
+/// It rejects if:
+/// - It fails to log the event.
+#[receive(
+    contract = "treasury",
+    name = "deposit",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_deposit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let from = ctx.sender();
+    *host.state_mut().deposits.entry(from).or_insert(Amount::zero()) += amount;
+    logger.log(&TreasuryEvent::Deposited {
+        from,
+        amount,
+    })?;
+    Ok(())
+}
+
+/// Propose an action, counting as the approval of the proposer. The proposal is
+/// queued right away if the threshold is one. Returns the ID of the proposal.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not a member.
+/// - It fails to log the events.
+#[receive(
+    contract = "treasury",
+    name = "propose",
+    parameter = "Action",
+    return_value = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_propose<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ProposalId> {
+    let action: Action = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let proposer = sender_member(ctx, state)?;
+
+    let id = state.next_proposal_id;
+    state.next_proposal_id += 1;
+    let mut approvals = BTreeSet::new();
+    approvals.insert(proposer);
+    state.proposals.insert(id, Proposal {
+        action,
+        approvals,
+        eta: None,
+    });
+
+    logger.log(&TreasuryEvent::Proposed {
+        id,
+        proposer,
+    })?;
+    queue_if_approved(ctx, state, id, logger)?;
+    Ok(id)
+}
+
+/// Approve a proposal. The proposal is queued once the threshold is reached.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not a member.
+/// - The proposal does not exist, e.g., because it was executed already.
+/// - The sender has already approved the proposal.
+/// - It fails to log the events.
+#[receive(
+    contract = "treasury",
+    name = "approve",
+    parameter = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_approve<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let member = sender_member(ctx, state)?;
+    {
+        let mut proposal = state.proposals.entry(id).occupied_or(ContractError::UnknownProposal)?;
+        ensure!(proposal.approvals.insert(member), ContractError::AlreadyApproved);
+    }
+
+    logger.log(&TreasuryEvent::Approved {
+        id,
+        member,
+    })?;
+    queue_if_approved(ctx, state, id, logger)
+}
+
+/// Execute a queued proposal after its timelock. Anyone can execute.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The proposal does not exist, e.g., because it was executed already.
+/// - The proposal is not queued, or no longer approved by the threshold of
+///   current members.
+/// - The timelock has not passed.
+/// - The action fails, e.g., because of insufficient funds or an unreachable
+///   threshold.
+/// - It fails to log the event.
+#[receive(
+    contract = "treasury",
+    name = "execute",
+    parameter = "ProposalId",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_execute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let proposal = state.proposals.get(&id).ok_or(ContractError::UnknownProposal)?.clone();
+    let eta = proposal.eta.ok_or(ContractError::NotApproved)?;
+    ensure!(state.is_approved(&proposal), ContractError::NotApproved);
+    ensure!(ctx.metadata().slot_time() >= eta, ContractError::Timelocked);
+    // Remove the proposal before executing it, such that it cannot be executed
+    // again.
+    state.proposals.remove(&id);
+
+    match proposal.action {
+        Action::Withdraw {
+            to,
+            amount,
+        } => host.invoke_transfer(&to, amount)?,
+        Action::UpdateCommittee {
+            members,
+            threshold,
+        } => {
+            ensure_valid_threshold(&members, threshold)?;
+            state.members = members;
+            state.threshold = threshold;
+        }
+    }
+    logger.log(&TreasuryEvent::Executed {
+        id,
+    })?;
+    Ok(())
+}
+
+/// View the total deposited by an address.
+#[receive(
+    contract = "treasury",
+    name = "depositsOf",
+    parameter = "Address",
+    return_value = "Amount"
+)]
+fn contract_deposits_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let address: Address = ctx.parameter_cursor().get()?;
+    Ok(host.state().deposits.get(&address).map(|a| *a).unwrap_or_else(Amount::zero))
+}
+
+/// View the committee, the threshold, the timelock and the pending proposals.
+#[receive(contract = "treasury", name = "view", return_value = "ViewState")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        members:   state.members.clone(),
+        threshold: state.threshold,
+        timelock:  state.timelock,
+        proposals: state.proposals.iter().map(|(id, p)| (*id, p.clone())).collect(),
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([0; 32]);
+    const BOB: AccountAddress = AccountAddress([1; 32]);
+    const CAROL: AccountAddress = AccountAddress([2; 32]);
+    const DAVE: AccountAddress = AccountAddress([3; 32]);
+    const FEE_COLLECTOR: ContractAddress = ContractAddress {
+        index:    7,
+        subindex: 0,
+    };
+    const TIMELOCK: u64 = 100;
+
+    fn initial_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            members:   [ALICE, BOB, CAROL].iter().copied().collect(),
+            threshold: 2,
+            timelock:  Duration::from_millis(TIMELOCK),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn ctx_at<'a>(sender: Address, slot_time: u64) -> TestReceiveContext<'a> {
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(sender);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx
+    }
+
+    fn deposit(host: &mut TestHost<State<TestStateApi>>, micro_ccd: u64, logger: &mut TestLogger) {
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        contract_deposit(&ctx_at(Address::Contract(FEE_COLLECTOR), 0), host, amount, logger)
+            .expect_report("Depositing should pass");
+    }
+
+    fn propose(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        action: &Action,
+        logger: &mut TestLogger,
+    ) -> ContractResult<ProposalId> {
+        let parameter_bytes = to_bytes(action);
+        let mut ctx = ctx_at(Address::Account(sender), 0);
+        ctx.set_parameter(&parameter_bytes);
+        contract_propose(&ctx, host, logger)
+    }
+
+    fn approve(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        id: ProposalId,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&id);
+        let mut ctx = ctx_at(Address::Account(sender), 0);
+        ctx.set_parameter(&parameter_bytes);
+        contract_approve(&ctx, host, logger)
+    }
+
+    fn execute(
+        host: &mut TestHost<State<TestStateApi>>,
+        id: ProposalId,
+        slot_time: u64,
+        logger: &mut TestLogger,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&id);
+        let mut ctx = ctx_at(Address::Account(DAVE), slot_time);
+        ctx.set_parameter(&parameter_bytes);
+        contract_execute(&ctx, host, logger)
+    }
+
+    #[concordium_test]
+    /// Test the full flow of depositing fees, and proposing, approving and
+    /// executing a withdrawal after the timelock.
+    fn test_withdrawal_flow() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        deposit(&mut host, 1_000, &mut logger);
+        let action = Action::Withdraw {
+            to:     DAVE,
+            amount: Amount::from_micro_ccd(400),
+        };
+
+        let id = propose(&mut host, ALICE, &action, &mut logger).expect_report("Propose failed");
+        let res = execute(&mut host, id, TIMELOCK, &mut logger);
+        claim_eq!(res, Err(ContractError::NotApproved), "One approval is below the threshold");
+        let res = approve(&mut host, DAVE, id, &mut logger);
+        claim_eq!(res, Err(ContractError::NotMember), "Only members can approve");
+
+        approve(&mut host, BOB, id, &mut logger).expect_report("Approve failed");
+        let res = execute(&mut host, id, TIMELOCK - 1, &mut logger);
+        claim_eq!(res, Err(ContractError::Timelocked), "Executing early should fail");
+        execute(&mut host, id, TIMELOCK, &mut logger).expect_report("Execute failed");
+        claim_eq!(host.get_transfers(), [(DAVE, Amount::from_micro_ccd(400))]);
+        claim_eq!(logger.logs, [
+            to_bytes(&TreasuryEvent::Deposited {
+                from:   Address::Contract(FEE_COLLECTOR),
+                amount: Amount::from_micro_ccd(1_000),
+            }),
+            to_bytes(&TreasuryEvent::Proposed {
+                id,
+                proposer: ALICE,
+            }),
+            to_bytes(&TreasuryEvent::Approved {
+                id,
+                member: BOB,
+            }),
+            to_bytes(&TreasuryEvent::Queued {
+                id,
+                eta: Timestamp::from_timestamp_millis(TIMELOCK),
+            }),
+            to_bytes(&TreasuryEvent::Executed {
+                id,
+            }),
+        ]);
+
+        let res = execute(&mut host, id, TIMELOCK, &mut logger);
+        claim_eq!(res, Err(ContractError::UnknownProposal), "Executed proposals are removed");
+    }
+
+    #[concordium_test]
+    /// Test replacing the committee, after which approvals of removed members
+    /// no longer count for queued proposals.
+    fn test_update_committee() {
+        let mut host = initial_host();
+        let mut logger = TestLogger::init();
+        deposit(&mut host, 1_000, &mut logger);
+
+        let withdraw = Action::Withdraw {
+            to:     CAROL,
+            amount: Amount::from_micro_ccd(1_000),
+        };
+        let pending = propose(&mut host, CAROL, &withdraw, &mut logger).expect_report("Propose");
+        approve(&mut host, ALICE, pending, &mut logger).expect_report("Approve pending");
+
+        let update = Action::UpdateCommittee {
+            members:   [ALICE, BOB, DAVE].iter().copied().collect(),
+            threshold: 2,
+        };
+        let id = propose(&mut host, ALICE, &update, &mut logger).expect_report("Propose update");
+        approve(&mut host, BOB, id, &mut logger).expect_report("Approve update");
+        execute(&mut host, id, TIMELOCK, &mut logger).expect_report("Execute update");
+        claim_eq!(host.state().members, [ALICE, BOB, DAVE].iter().copied().collect());
+
+        let res = execute(&mut host, pending, TIMELOCK, &mut logger);
+        claim_eq!(res, Err(ContractError::NotApproved), "Carol's approval no longer counts");
+        claim!(host.get_transfers().is_empty());
+    }
+}
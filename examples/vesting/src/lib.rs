@@ -0,0 +1,405 @@
+//! # Implementation of a vesting smart contract
+//!
+//! A funder locks an amount of CCD for a beneficiary when initializing the
+//! contract. The CCD vests according to a schedule: nothing is vested before
+//! the cliff has passed, after which the vested amount grows linearly with the
+//! time elapsed since the start, until everything is vested at the end of the
+//! vesting duration. Note that at the cliff, the amount which vested linearly
+//! since the start becomes available at once.
+//!
+//! The beneficiary can `claim` the vested CCD which has not been claimed yet at
+//! any time.
+//!
+//! If the funder made the vesting revocable, they can `revoke` it, in which
+//! case the unvested CCD is returned to the funder, and the CCD vested at the
+//! time of revocation remains claimable by the beneficiary.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The state of the smart contract.
+/// This state can be viewed by querying the node with the command
+/// `concordium-client contract invoke` using the `view` function as entrypoint.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct State {
+    /// The account which locked the CCD.
+    funder:      AccountAddress,
+    /// The account the CCD vests for.
+    beneficiary: AccountAddress,
+    /// Time when the vesting starts.
+    start:       Timestamp,
+    /// The duration after the start before which nothing is vested.
+    cliff:       Duration,
+    /// The duration after the start at which everything is vested.
+    duration:    Duration,
+    /// Whether the funder can revoke the unvested CCD.
+    revocable:   bool,
+    /// Whether the funder has revoked the vesting.
+    revoked:     bool,
+    /// The total amount vesting. When revoked, this is the amount which was
+    /// vested at the time of revocation.
+    total:       Amount,
+    /// The amount the beneficiary has claimed so far.
+    claimed:     Amount,
+}
+
+impl State {
+    /// Compute the amount vested at the given time.
+    fn vested_at(&self, now: Timestamp) -> Amount {
+        if self.revoked {
+            return self.total;
+        }
+        let elapsed = now.timestamp_millis().saturating_sub(self.start.timestamp_millis());
+        if elapsed < self.cliff.millis() {
+            return Amount::zero();
+        }
+        if elapsed >= self.duration.millis() {
+            return self.total;
+        }
+        // The duration is positive and larger than the elapsed time, so the
+        // result is less than `total` and unwrapping is safe.
+        self.total.mul_ratio(elapsed, self.duration.millis()).unwrap_abort()
+    }
+
+    /// Compute the amount which is vested, but not claimed yet, at the given
+    /// time.
+    fn claimable_at(&self, now: Timestamp) -> Amount { self.vested_at(now) - self.claimed }
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The account the CCD vests for.
+    beneficiary: AccountAddress,
+    /// Time when the vesting starts, using the RFC 3339 format (https://tools.ietf.org/html/rfc3339)
+    start:       Timestamp,
+    /// The duration after the start before which nothing is vested.
+    cliff:       Duration,
+    /// The duration after the start at which everything is vested.
+    duration:    Duration,
+    /// Whether the funder can revoke the unvested CCD.
+    revocable:   bool,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with a duration of zero or a cliff after the
+    /// end of the vesting.
+    InvalidSchedule,
+    /// Raised when someone else than the beneficiary claims, or someone else
+    /// than the funder revokes.
+    Unauthorized,
+    /// Raised when claiming while no vested CCD is left to claim.
+    NothingToClaim,
+    /// Raised when revoking a vesting which is not revocable.
+    NotRevocable,
+    /// Raised when revoking a vesting which has already been revoked.
+    AlreadyRevoked,
+}
+
+/// Init function that locks the CCD sent along for the beneficiary. The sender
+/// of the transaction becomes the funder.
+#[init(contract = "vesting", parameter = "InitParameter", payable)]
+fn vesting_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    _state_builder: &mut StateBuilder<S>,
+    amount: Amount,
+) -> InitResult<State> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.duration.millis() > 0, ContractError::InvalidSchedule.into());
+    ensure!(parameter.cliff <= parameter.duration, ContractError::InvalidSchedule.into());
+    Ok(State {
+        funder:      ctx.init_origin(),
+        beneficiary: parameter.beneficiary,
+        start:       parameter.start,
+        cliff:       parameter.cliff,
+        duration:    parameter.duration,
+        revocable:   parameter.revocable,
+        revoked:     false,
+        total:       amount,
+        claimed:     Amount::zero(),
+    })
+}
+
+/// Transfer the vested CCD which has not been claimed yet to the beneficiary.
+///
+/// It rejects if:
+/// - The sender is not the beneficiary.
+/// - There is no vested CCD left to claim.
+#[receive(contract = "vesting", name = "claim", mutable, error = "ContractError")]
+fn vesting_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.beneficiary), ContractError::Unauthorized);
+
+    let claimable = state.claimable_at(ctx.metadata().slot_time());
+    ensure!(claimable > Amount::zero(), ContractError::NothingToClaim);
+    state.claimed += claimable;
+    let beneficiary = state.beneficiary;
+
+    // The transfer always succeeds, since the beneficiary is the sender and the
+    // contract holds all the CCD which has not been claimed.
+    host.invoke_transfer(&beneficiary, claimable).unwrap_abort();
+    Ok(())
+}
+
+/// Revoke the vesting, returning the unvested CCD to the funder. The CCD vested
+/// at this time remains claimable by the beneficiary.
+///
+/// It rejects if:
+/// - The sender is not the funder.
+/// - The vesting is not revocable.
+/// - The vesting has already been revoked.
+#[receive(contract = "vesting", name = "revoke", mutable, error = "ContractError")]
+fn vesting_revoke<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State, StateApiType = S>,
+) -> Result<(), ContractError> {
+    let state = host.state_mut();
+    ensure!(ctx.sender().matches_account(&state.funder), ContractError::Unauthorized);
+    ensure!(state.revocable, ContractError::NotRevocable);
+    ensure!(!state.revoked, ContractError::AlreadyRevoked);
+
+    let vested = state.vested_at(ctx.metadata().slot_time());
+    let unvested = state.total - vested;
+    state.total = vested;
+    state.revoked = true;
+    let funder = state.funder;
+
+    if unvested > Amount::zero() {
+        // The transfer always succeeds, since the funder is the sender and the
+        // contract holds all the CCD which has not vested.
+        host.invoke_transfer(&funder, unvested).unwrap_abort();
+    }
+    Ok(())
+}
+
+/// View function that returns the amount the beneficiary can claim now.
+#[receive(contract = "vesting", name = "claimable", return_value = "Amount")]
+fn vesting_claimable<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    Ok(host.state().claimable_at(ctx.metadata().slot_time()))
+}
+
+/// View function that returns the content of the state.
+#[receive(contract = "vesting", name = "view", return_value = "State")]
+fn vesting_view<'a, 'b, S: HasStateApi>(
+    _ctx: &'a impl HasReceiveContext,
+    host: &'b impl HasHost<State, StateApiType = S>,
+) -> ReceiveResult<&'b State> {
+    Ok(host.state())
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_at;
+    use test_infrastructure::*;
+
+    const FUNDER: AccountAddress = AccountAddress([0; 32]);
+    const BENEFICIARY: AccountAddress = AccountAddress([1; 32]);
+    const START: u64 = 1_000;
+    const CLIFF: u64 = 2_500;
+    const DURATION: u64 = 10_000;
+    const TOTAL: Amount = Amount {
+        micro_ccd: 10_000,
+    };
+
+    fn parameter(revocable: bool) -> InitParameter {
+        InitParameter {
+            beneficiary: BENEFICIARY,
+            start: Timestamp::from_timestamp_millis(START),
+            cliff: Duration::from_millis(CLIFF),
+            duration: Duration::from_millis(DURATION),
+            revocable,
+        }
+    }
+
+    fn initialize(parameter: &InitParameter, amount: Amount) -> InitResult<State> {
+        let parameter_bytes = to_bytes(parameter);
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(FUNDER);
+        ctx.set_parameter(&parameter_bytes);
+        vesting_init(&ctx, &mut TestStateBuilder::new(), amount)
+    }
+
+    fn new_host(revocable: bool) -> TestHost<State> {
+        let state =
+            initialize(&parameter(revocable), TOTAL).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, TestStateBuilder::new());
+        host.set_self_balance(TOTAL);
+        host
+    }
+
+    fn claimable_at(host: &TestHost<State>, slot_time: u64) -> Amount {
+        vesting_claimable(&ctx_at(BENEFICIARY, slot_time), host)
+            .expect_report("Querying the claimable amount should pass")
+    }
+
+    #[concordium_test]
+    /// Test that initialization rejects a zero duration and a cliff after the
+    /// end.
+    fn test_init_invalid_schedule() {
+        let mut zero_duration = parameter(false);
+        zero_duration.cliff = Duration::from_millis(0);
+        zero_duration.duration = Duration::from_millis(0);
+        claim_eq!(
+            initialize(&zero_duration, TOTAL).map(|_| ()),
+            Err(ContractError::InvalidSchedule.into())
+        );
+
+        let mut late_cliff = parameter(false);
+        late_cliff.cliff = Duration::from_millis(DURATION + 1);
+        claim_eq!(
+            initialize(&late_cliff, TOTAL).map(|_| ()),
+            Err(ContractError::InvalidSchedule.into())
+        );
+
+        let mut cliff_at_end = parameter(false);
+        cliff_at_end.cliff = Duration::from_millis(DURATION);
+        claim!(initialize(&cliff_at_end, TOTAL).is_ok(), "A cliff at the end is allowed");
+    }
+
+    #[concordium_test]
+    /// Test the vested amount at, before and after the start, the cliff and
+    /// the end, and in between.
+    fn test_schedule() {
+        let host = new_host(false);
+
+        claim_eq!(claimable_at(&host, 0), Amount::zero(), "Nothing is vested before the start");
+        claim_eq!(claimable_at(&host, START), Amount::zero(), "Nothing is vested at the start");
+        claim_eq!(
+            claimable_at(&host, START + CLIFF - 1),
+            Amount::zero(),
+            "Nothing is vested before the cliff"
+        );
+        claim_eq!(
+            claimable_at(&host, START + CLIFF),
+            Amount::from_micro_ccd(2_500),
+            "The amount vested since the start becomes available at the cliff"
+        );
+        claim_eq!(claimable_at(&host, START + 5_000), Amount::from_micro_ccd(5_000), "Halfway");
+        claim_eq!(claimable_at(&host, START + DURATION - 1), Amount::from_micro_ccd(9_999));
+        claim_eq!(claimable_at(&host, START + DURATION), TOTAL, "Everything vests at the end");
+        claim_eq!(claimable_at(&host, u64::MAX), TOTAL, "The vested amount stays at the total");
+    }
+
+    #[concordium_test]
+    /// Test that the vested amount is rounded down and does not overflow with
+    /// large amounts and durations.
+    fn test_schedule_large_values() {
+        let mut parameter = parameter(false);
+        parameter.start = Timestamp::from_timestamp_millis(0);
+        parameter.cliff = Duration::from_millis(0);
+        parameter.duration = Duration::from_millis(u64::MAX);
+        let total = Amount::from_micro_ccd(u64::MAX);
+        let state = initialize(&parameter, total).expect_report("Initialization should pass");
+        let host = TestHost::new(state, TestStateBuilder::new());
+
+        claim_eq!(claimable_at(&host, 1), Amount::from_micro_ccd(1));
+        claim_eq!(claimable_at(&host, u64::MAX / 2), Amount::from_micro_ccd(u64::MAX / 2));
+        claim_eq!(claimable_at(&host, u64::MAX), total);
+
+        let mut parameter = parameter;
+        parameter.duration = Duration::from_millis(3);
+        let state = initialize(&parameter, Amount::from_micro_ccd(10))
+            .expect_report("Initialization should pass");
+        let host = TestHost::new(state, TestStateBuilder::new());
+        claim_eq!(claimable_at(&host, 1), Amount::from_micro_ccd(3), "10 / 3 rounds down");
+        claim_eq!(claimable_at(&host, 2), Amount::from_micro_ccd(6), "20 / 3 rounds down");
+    }
+
+    #[concordium_test]
+    /// Test claiming several times over the vesting period.
+    fn test_claim() {
+        let mut host = new_host(false);
+
+        let res = vesting_claim(&ctx_at(BENEFICIARY, START + CLIFF - 1), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToClaim), "Nothing is claimable before the cliff");
+
+        vesting_claim(&ctx_at(BENEFICIARY, START + 5_000), &mut host)
+            .expect_report("Claiming halfway should pass");
+        let res = vesting_claim(&ctx_at(BENEFICIARY, START + 5_000), &mut host);
+        claim_eq!(res, Err(ContractError::NothingToClaim), "Claiming twice should fail");
+        claim_eq!(claimable_at(&host, START + 6_000), Amount::from_micro_ccd(1_000));
+
+        vesting_claim(&ctx_at(BENEFICIARY, START + DURATION + 1), &mut host)
+            .expect_report("Claiming after the end should pass");
+        claim_eq!(host.get_transfers(), [
+            (BENEFICIARY, Amount::from_micro_ccd(5_000)),
+            (BENEFICIARY, Amount::from_micro_ccd(5_000))
+        ]);
+        claim_eq!(host.state().claimed, TOTAL);
+        claim_eq!(host.self_balance(), Amount::zero(), "Everything should be claimed");
+    }
+
+    #[concordium_test]
+    /// Test that only the beneficiary can claim and only the funder can revoke.
+    fn test_unauthorized() {
+        let mut host = new_host(true);
+
+        let res = vesting_claim(&ctx_at(FUNDER, START + DURATION), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the beneficiary can claim");
+        let res = vesting_revoke(&ctx_at(BENEFICIARY, START), &mut host);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the funder can revoke");
+        claim!(host.get_transfers().is_empty());
+    }
+
+    #[concordium_test]
+    /// Test that revoking returns the unvested CCD to the funder and freezes
+    /// the vested amount.
+    fn test_revoke() {
+        let mut host = new_host(true);
+
+        vesting_claim(&ctx_at(BENEFICIARY, START + 3_000), &mut host)
+            .expect_report("Claiming should pass");
+        vesting_revoke(&ctx_at(FUNDER, START + 4_000), &mut host)
+            .expect_report("Revoking should pass");
+        let res = vesting_revoke(&ctx_at(FUNDER, START + 4_000), &mut host);
+        claim_eq!(res, Err(ContractError::AlreadyRevoked), "Revoking twice should fail");
+
+        claim_eq!(
+            claimable_at(&host, START + DURATION),
+            Amount::from_micro_ccd(1_000),
+            "Only the amount vested at revocation remains claimable"
+        );
+        vesting_claim(&ctx_at(BENEFICIARY, START + DURATION), &mut host)
+            .expect_report("Claiming after revocation should pass");
+        claim_eq!(host.get_transfers(), [
+            (BENEFICIARY, Amount::from_micro_ccd(3_000)),
+            (FUNDER, Amount::from_micro_ccd(6_000)),
+            (BENEFICIARY, Amount::from_micro_ccd(1_000)),
+        ]);
+        claim_eq!(host.self_balance(), Amount::zero(), "Everything should be paid out");
+    }
+
+    #[concordium_test]
+    /// Test revoking before the cliff and after the end, and that a vesting
+    /// which is not revocable cannot be revoked.
+    fn test_revoke_boundaries() {
+        let mut host = new_host(true);
+        vesting_revoke(&ctx_at(FUNDER, START + CLIFF - 1), &mut host)
+            .expect_report("Revoking before the cliff should pass");
+        claim_eq!(host.get_transfers(), [(FUNDER, TOTAL)], "Everything returns to the funder");
+        claim_eq!(claimable_at(&host, START + DURATION), Amount::zero());
+
+        let mut host = new_host(true);
+        vesting_revoke(&ctx_at(FUNDER, START + DURATION), &mut host)
+            .expect_report("Revoking after the end should pass");
+        claim!(host.get_transfers().is_empty(), "Nothing is returned when everything vested");
+        claim_eq!(claimable_at(&host, START + DURATION), TOTAL);
+
+        let mut host = new_host(false);
+        let res = vesting_revoke(&ctx_at(FUNDER, START), &mut host);
+        claim_eq!(res, Err(ContractError::NotRevocable), "Revoking should fail");
+    }
+}
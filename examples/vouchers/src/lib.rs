@@ -0,0 +1,305 @@
+//! # Gift vouchers
+//!
+//! Anyone can `buy` a voucher worth the CCD sent along, identified by the
+//! SHA2-256 hash of a secret code picked by the purchaser. The purchaser gifts
+//! the code off-chain, e.g., as a link or a printed card, and whoever presents
+//! the code using `redeem` before the voucher expires gets its value
+//! transferred to their account, to spend on, e.g., a subscription. After the
+//! voucher expires without being redeemed, the purchaser gets its value back
+//! using `refund`.
+//!
+//! Since only the hash of the code is stored, the code is secret until the
+//! voucher is redeemed. Note that the code is public once a redemption is sent,
+//! so a voucher is like cash: whoever gets their redemption in first gets the
+//! value.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use components::{sender_account, OnlyAccount};
+use concordium_std::*;
+
+/// A voucher.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub struct Voucher {
+    /// The account which bought the voucher, and gets it refunded.
+    pub purchaser: AccountAddress,
+    /// The value of the voucher.
+    pub amount:    Amount,
+    /// The time until which the voucher can be redeemed, after which it can be
+    /// refunded.
+    pub expires:   Timestamp,
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The vouchers which were neither redeemed nor refunded, by the hash of
+    /// their code.
+    vouchers: StateMap<HashSha2256, Voucher, S>,
+}
+
+/// The parameter type for the contract function `buy`.
+#[derive(Serialize, SchemaType)]
+pub struct BuyParams {
+    /// The SHA2-256 hash of the secret code.
+    pub code_hash: HashSha2256,
+    /// The time until which the voucher can be redeemed.
+    pub expires:   Timestamp,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when a contract buys or redeems a voucher.
+    OnlyAccount,
+    /// Raised when buying a voucher without CCD.
+    ZeroAmount,
+    /// Raised when buying a voucher which has already expired.
+    ExpiryInPast,
+    /// Raised when a voucher with the same code exists.
+    VoucherExists,
+    /// Raised when no voucher exists for the code, or for the hash to refund.
+    UnknownVoucher,
+    /// Raised when redeeming after the voucher expired.
+    Expired,
+    /// Raised when refunding before the voucher expired.
+    NotExpired,
+    /// Raised when someone else than the purchaser refunds.
+    Unauthorized,
+}
+
+impl From<OnlyAccount> for ContractError {
+    fn from(_: OnlyAccount) -> Self { Self::OnlyAccount }
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Init function that creates a contract without vouchers.
+#[init(contract = "vouchers")]
+fn vouchers_init<S: HasStateApi>(
+    _ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    Ok(State {
+        vouchers: state_builder.new_map(),
+    })
+}
+
+/// Buy a voucher worth the CCD sent along for the hash of a secret code.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No CCD is sent.
+/// - The expiry has already passed.
+/// - A voucher with the same code exists.
+#[receive(
+    contract = "vouchers",
+    name = "buy",
+    parameter = "BuyParams",
+    error = "ContractError",
+    payable,
+    mutable
+)]
+fn vouchers_buy<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+) -> ContractResult<()> {
+    let params: BuyParams = ctx.parameter_cursor().get()?;
+    let purchaser = sender_account(ctx)?;
+    ensure!(amount > Amount::zero(), ContractError::ZeroAmount);
+    ensure!(params.expires > ctx.metadata().slot_time(), ContractError::ExpiryInPast);
+    let voucher = Voucher {
+        purchaser,
+        amount,
+        expires: params.expires,
+    };
+    host.state_mut()
+        .vouchers
+        .entry(params.code_hash)
+        .vacant_or(ContractError::VoucherExists)?
+        .insert(voucher);
+    Ok(())
+}
+
+/// Redeem the voucher of the secret code, transferring its value to the
+/// sender.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No voucher exists for the hash of the code.
+/// - The voucher has expired.
+#[receive(
+    contract = "vouchers",
+    name = "redeem",
+    parameter = "[u8; 32]",
+    error = "ContractError",
+    crypto_primitives,
+    mutable
+)]
+fn vouchers_redeem<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let code: [u8; 32] = ctx.parameter_cursor().get()?;
+    let redeemer = sender_account(ctx)?;
+    let code_hash = crypto_primitives.hash_sha2_256(&code);
+    let state = host.state_mut();
+    let voucher =
+        state.vouchers.get(&code_hash).map(|v| *v).ok_or(ContractError::UnknownVoucher)?;
+    ensure!(ctx.metadata().slot_time() <= voucher.expires, ContractError::Expired);
+    state.vouchers.remove(&code_hash);
+
+    // The transfer always succeeds, since the redeemer is the sender and the
+    // contract holds the value of the voucher.
+    host.invoke_transfer(&redeemer, voucher.amount).unwrap_abort();
+    Ok(())
+}
+
+/// Refund the voucher of the hash to its purchaser, after it expired.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - No voucher exists for the hash.
+/// - The sender is not the purchaser of the voucher.
+/// - The voucher has not expired.
+#[receive(
+    contract = "vouchers",
+    name = "refund",
+    parameter = "HashSha2256",
+    error = "ContractError",
+    mutable
+)]
+fn vouchers_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let code_hash: HashSha2256 = ctx.parameter_cursor().get()?;
+    let state = host.state_mut();
+    let voucher =
+        state.vouchers.get(&code_hash).map(|v| *v).ok_or(ContractError::UnknownVoucher)?;
+    ensure!(ctx.sender().matches_account(&voucher.purchaser), ContractError::Unauthorized);
+    ensure!(ctx.metadata().slot_time() > voucher.expires, ContractError::NotExpired);
+    state.vouchers.remove(&code_hash);
+
+    // The transfer always succeeds, since the purchaser is the sender and the
+    // contract holds the value of the voucher.
+    host.invoke_transfer(&voucher.purchaser, voucher.amount).unwrap_abort();
+    Ok(())
+}
+
+/// View the voucher of the hash, if it was neither redeemed nor refunded.
+#[receive(
+    contract = "vouchers",
+    name = "voucher",
+    parameter = "HashSha2256",
+    return_value = "Option<Voucher>"
+)]
+fn vouchers_voucher<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Option<Voucher>> {
+    let code_hash: HashSha2256 = ctx.parameter_cursor().get()?;
+    Ok(host.state().vouchers.get(&code_hash).map(|v| *v))
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const EXPIRES: u64 = 100;
+    const CODE: [u8; 32] = [42; 32];
+    const AMOUNT: Amount = Amount {
+        micro_ccd: 1_000,
+    };
+
+    fn code_hash() -> HashSha2256 { TestCryptoPrimitives::new().hash_sha2_256(&CODE) }
+
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let mut state_builder = TestStateBuilder::new();
+        let state = vouchers_init(&TestInitContext::empty(), &mut state_builder)
+            .expect_report("Initialization should pass");
+        TestHost::new(state, state_builder)
+    }
+
+    fn buy(host: &mut TestHost<State<TestStateApi>>, amount: Amount) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&BuyParams {
+            code_hash: code_hash(),
+            expires:   Timestamp::from_timestamp_millis(EXPIRES),
+        });
+        host.set_self_balance(host.self_balance() + amount);
+        vouchers_buy(&ctx_with_parameter(ALICE, 0, &parameter_bytes), host, amount)
+    }
+
+    fn redeem(
+        host: &mut TestHost<State<TestStateApi>>,
+        code: [u8; 32],
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&code);
+        vouchers_redeem(
+            &ctx_with_parameter(BOB, slot_time, &parameter_bytes),
+            host,
+            &TestCryptoPrimitives::new(),
+        )
+    }
+
+    fn refund(
+        host: &mut TestHost<State<TestStateApi>>,
+        sender: AccountAddress,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&code_hash());
+        vouchers_refund(&ctx_with_parameter(sender, slot_time, &parameter_bytes), host)
+    }
+
+    #[concordium_test]
+    /// Test that presenting the code before the voucher expires transfers its
+    /// value to the redeemer, once.
+    fn test_redeem() {
+        let mut host = new_host();
+        buy(&mut host, AMOUNT).expect_report("Buying should pass");
+        let res = buy(&mut host, AMOUNT);
+        claim_eq!(res, Err(ContractError::VoucherExists), "Codes are unique");
+
+        let res = redeem(&mut host, [0; 32], 0);
+        claim_eq!(res, Err(ContractError::UnknownVoucher), "A wrong code should fail");
+        let res = refund(&mut host, ALICE, EXPIRES);
+        claim_eq!(res, Err(ContractError::NotExpired), "Refunding early should fail");
+
+        redeem(&mut host, CODE, EXPIRES).expect_report("Redeeming should pass");
+        let res = redeem(&mut host, CODE, EXPIRES);
+        claim_eq!(res, Err(ContractError::UnknownVoucher), "Redeeming twice should fail");
+        claim_eq!(host.get_transfers(), [(BOB, AMOUNT)]);
+    }
+
+    #[concordium_test]
+    /// Test that only the purchaser can refund, and only after the voucher
+    /// expired, after which it can no longer be redeemed.
+    fn test_refund() {
+        let mut host = new_host();
+        let res = buy(&mut host, Amount::zero());
+        claim_eq!(res, Err(ContractError::ZeroAmount));
+        buy(&mut host, AMOUNT).expect_report("Buying should pass");
+
+        let res = redeem(&mut host, CODE, EXPIRES + 1);
+        claim_eq!(res, Err(ContractError::Expired), "Redeeming late should fail");
+        let res = refund(&mut host, BOB, EXPIRES + 1);
+        claim_eq!(res, Err(ContractError::Unauthorized), "Only the purchaser can refund");
+
+        refund(&mut host, ALICE, EXPIRES + 1).expect_report("Refunding should pass");
+        claim_eq!(host.get_transfers(), [(ALICE, AMOUNT)]);
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+}
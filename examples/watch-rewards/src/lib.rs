@@ -0,0 +1,402 @@
+//! A contract rewarding viewers with CCD for the time they watched streams, as
+//! attested by an oracle.
+//!
+//! # Description
+//! Watch time is tracked off-chain. Per epoch, an oracle signs a report of the
+//! minutes a viewer watched a stream, using the key configured by the owner of
+//! the contract instance. Anyone, typically the viewer or a service claiming on
+//! their behalf, can submit a signed report using `claim`, which pays the
+//! viewer a fixed reward per attested minute.
+//!
+//! Every report can only be claimed once, i.e. a viewer is rewarded once per
+//! stream and epoch. The rewards of a viewer are capped per epoch, across all
+//! streams, and a claim exceeding the cap only pays what is left of it.
+//! Reports can only be claimed for epochs which have started.
+//!
+//! The rewards are paid from the balance of the contract, which anyone can
+//! top up using `fund`. The owner can rotate the key of the oracle using
+//! `setOracleKey`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The key the oracle signs reports with.
+    oracle_key:           PublicKeyEd25519,
+    /// The reward per attested minute.
+    reward_per_minute:    Amount,
+    /// The most a viewer can be rewarded per epoch.
+    max_reward_per_epoch: Amount,
+    /// When the first epoch started.
+    start:                Timestamp,
+    /// The length of an epoch.
+    epoch_length:         Duration,
+    /// The reports which were claimed, by viewer, stream and epoch.
+    claimed:              StateSet<(AccountAddress, u64, u64), S>,
+    /// The rewards of every viewer per epoch.
+    rewards:              StateMap<(AccountAddress, u64), Amount, S>,
+}
+
+/// Type of the parameter to the `init` function.
+#[derive(Serialize, SchemaType)]
+struct InitParameter {
+    /// The key the oracle signs reports with.
+    oracle_key:           PublicKeyEd25519,
+    /// The reward per attested minute.
+    reward_per_minute:    Amount,
+    /// The most a viewer can be rewarded per epoch.
+    max_reward_per_epoch: Amount,
+    /// The length of an epoch.
+    epoch_length:         Duration,
+}
+
+/// A report of the time a viewer watched a stream in an epoch.
+#[derive(Serialize, SchemaType, Clone)]
+pub struct WatchReport {
+    /// The contract instance the report is meant for.
+    pub contract_address: ContractAddress,
+    /// The viewer who watched the stream.
+    pub viewer:           AccountAddress,
+    /// The stream which was watched.
+    pub stream_id:        u64,
+    /// The epoch the stream was watched in.
+    pub epoch:            u64,
+    /// The minutes the viewer watched the stream in the epoch.
+    pub minutes:          u32,
+}
+
+/// The parameter type for the contract function `claim`.
+#[derive(Serialize, SchemaType)]
+pub struct ClaimParam {
+    /// The report.
+    pub report:    WatchReport,
+    /// The signature of the oracle on the serialized report.
+    pub signature: SignatureEd25519,
+}
+
+/// The parameter type for the contract function `rewardsOf`.
+#[derive(Serialize, SchemaType)]
+struct RewardsOfParam {
+    viewer: AccountAddress,
+    epoch:  u64,
+}
+
+/// The `return_value` type of the `view` function.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+struct ViewState {
+    oracle_key:           PublicKeyEd25519,
+    reward_per_minute:    Amount,
+    max_reward_per_epoch: Amount,
+    start:                Timestamp,
+    epoch_length:         Duration,
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Raised when initializing with an epoch length of zero.
+    ZeroEpochLength,
+    /// Raised when someone else than the owner sets the key of the oracle.
+    Unauthorized,
+    /// Raised when the report is meant for another contract instance.
+    WrongContract,
+    /// Raised when the epoch of the report has not started.
+    FutureEpoch,
+    /// Raised when the signature is not valid for the key of the oracle.
+    WrongSignature,
+    /// Raised when the report was claimed already.
+    AlreadyClaimed,
+    /// Raised when the viewer reached the cap of the epoch.
+    EpochCapReached,
+    /// Raised when the contract cannot pay the reward.
+    InsufficientFunds,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+impl<S: HasStateApi> State<S> {
+    /// The epoch at the given time.
+    fn epoch_at(&self, time: Timestamp) -> u64 {
+        time.duration_since(self.start)
+            .map_or(0, |elapsed| elapsed.millis() / self.epoch_length.millis())
+    }
+}
+
+/// Init function that configures the oracle and the rewards. The first epoch
+/// starts when the contract instance is created.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The epoch length is zero.
+#[init(contract = "watch_rewards", parameter = "InitParameter")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let parameter: InitParameter = ctx.parameter_cursor().get()?;
+    ensure!(parameter.epoch_length.millis() > 0, ContractError::ZeroEpochLength.into());
+    Ok(State {
+        oracle_key:           parameter.oracle_key,
+        reward_per_minute:    parameter.reward_per_minute,
+        max_reward_per_epoch: parameter.max_reward_per_epoch,
+        start:                ctx.metadata().slot_time(),
+        epoch_length:         parameter.epoch_length,
+        claimed:              state_builder.new_set(),
+        rewards:              state_builder.new_map(),
+    })
+}
+
+/// Add the CCD sent along to the funds the rewards are paid from.
+#[receive(contract = "watch_rewards", name = "fund", payable)]
+fn contract_fund<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+    _amount: Amount,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+/// Set the key the oracle signs reports with. Reports signed with the previous
+/// key can no longer be claimed.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is not the owner.
+#[receive(
+    contract = "watch_rewards",
+    name = "setOracleKey",
+    parameter = "PublicKeyEd25519",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_oracle_key<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    ensure!(ctx.sender().matches_account(&ctx.owner()), ContractError::Unauthorized);
+    host.state_mut().oracle_key = ctx.parameter_cursor().get()?;
+    Ok(())
+}
+
+/// Claim the reward for a report signed by the oracle, which is paid to the
+/// viewer of the report. The reward is capped by what is left of the cap of the
+/// viewer for the epoch.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The report is meant for another contract instance.
+/// - The epoch of the report has not started.
+/// - The signature is not valid for the key of the oracle.
+/// - The report was claimed already.
+/// - The viewer reached the cap of the epoch.
+/// - The balance of the contract is less than the reward.
+#[receive(
+    contract = "watch_rewards",
+    name = "claim",
+    parameter = "ClaimParam",
+    error = "ContractError",
+    crypto_primitives,
+    mutable
+)]
+fn contract_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let param: ClaimParam = ctx.parameter_cursor().get()?;
+    let report = param.report;
+    ensure!(report.contract_address == ctx.self_address(), ContractError::WrongContract);
+    let balance = host.self_balance();
+    let state = host.state_mut();
+    ensure!(report.epoch <= state.epoch_at(ctx.metadata().slot_time()), ContractError::FutureEpoch);
+    ensure!(
+        crypto_primitives.verify_ed25519_signature(
+            state.oracle_key,
+            param.signature,
+            &to_bytes(&report)
+        ),
+        ContractError::WrongSignature
+    );
+    ensure!(
+        !state.claimed.contains(&(report.viewer, report.stream_id, report.epoch)),
+        ContractError::AlreadyClaimed
+    );
+
+    let reward = {
+        let mut rewards =
+            state.rewards.entry((report.viewer, report.epoch)).or_insert(Amount::zero());
+        let left = state.max_reward_per_epoch - *rewards;
+        ensure!(left > Amount::zero(), ContractError::EpochCapReached);
+        let earned = state.reward_per_minute.micro_ccd.saturating_mul(report.minutes.into());
+        let reward = Amount::from_micro_ccd(earned).min(left);
+        ensure!(reward <= balance, ContractError::InsufficientFunds);
+        *rewards += reward;
+        reward
+    };
+    state.claimed.insert((report.viewer, report.stream_id, report.epoch));
+
+    // The transfer always succeeds, since the balance of the contract covers
+    // the reward.
+    host.invoke_transfer(&report.viewer, reward).unwrap_abort();
+    Ok(())
+}
+
+/// View function that returns the rewards of a viewer in an epoch.
+#[receive(
+    contract = "watch_rewards",
+    name = "rewardsOf",
+    parameter = "RewardsOfParam",
+    return_value = "Amount"
+)]
+fn contract_rewards_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<Amount> {
+    let param: RewardsOfParam = ctx.parameter_cursor().get()?;
+    let rewards = host.state().rewards.get(&(param.viewer, param.epoch));
+    Ok(rewards.map(|r| *r).unwrap_or_else(Amount::zero))
+}
+
+/// View function that returns the configuration of the contract.
+#[receive(contract = "watch_rewards", name = "view", return_value = "ViewState")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewState> {
+    let state = host.state();
+    Ok(ViewState {
+        oracle_key:           state.oracle_key,
+        reward_per_minute:    state.reward_per_minute,
+        max_reward_per_epoch: state.max_reward_per_epoch,
+        start:                state.start,
+        epoch_length:         state.epoch_length,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const OWNER: AccountAddress = AccountAddress([0; 32]);
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const ORACLE_KEY: PublicKeyEd25519 = PublicKeyEd25519([10; 32]);
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index:    10,
+        subindex: 0,
+    };
+    const EPOCH_LENGTH: u64 = 100;
+
+    /// Create an instance paying 10 microCCD per minute, and at most 500
+    /// microCCD per epoch.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParameter {
+            oracle_key:           ORACLE_KEY,
+            reward_per_minute:    Amount::from_micro_ccd(10),
+            max_reward_per_epoch: Amount::from_micro_ccd(500),
+            epoch_length:         Duration::from_millis(EPOCH_LENGTH),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.set_self_balance(Amount::from_micro_ccd(10_000));
+        host
+    }
+
+    fn report(stream_id: u64, epoch: u64, minutes: u32) -> WatchReport {
+        WatchReport {
+            contract_address: SELF_ADDRESS,
+            viewer: ALICE,
+            stream_id,
+            epoch,
+            minutes,
+        }
+    }
+
+    /// Mock the signature verification accepting signatures of the oracle on
+    /// the given reports.
+    fn signing(reports: Vec<WatchReport>) -> TestCryptoPrimitives {
+        let crypto_primitives = TestCryptoPrimitives::new();
+        crypto_primitives.setup_verify_ed25519_signature_mock(move |key, _signature, message| {
+            key == ORACLE_KEY && reports.iter().any(|r| &to_bytes(r)[..] == message)
+        });
+        crypto_primitives
+    }
+
+    fn claim(
+        host: &mut TestHost<State<TestStateApi>>,
+        crypto_primitives: &TestCryptoPrimitives,
+        slot_time: u64,
+        report: WatchReport,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&ClaimParam {
+            report,
+            signature: SignatureEd25519([1; 64]),
+        });
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Account(OWNER));
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(slot_time));
+        ctx.set_parameter(&parameter_bytes);
+        contract_claim(&ctx, host, crypto_primitives)
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that signed reports pay the viewer once, and only for epochs which
+    /// have started.
+    fn test_claim() {
+        let mut host = new_host();
+        let crypto_primitives = signing(vec![report(1, 0, 20), report(2, 1, 5)]);
+
+        claim(&mut host, &crypto_primitives, 0, report(1, 0, 20))
+            .expect_report("Claiming should pass");
+        let res = claim(&mut host, &crypto_primitives, 0, report(1, 0, 20));
+        claim_eq!(res, Err(ContractError::AlreadyClaimed));
+        let res = claim(&mut host, &crypto_primitives, 0, report(1, 0, 30));
+        claim_eq!(res, Err(ContractError::WrongSignature), "The minutes are signed");
+        let res = claim(&mut host, &crypto_primitives, EPOCH_LENGTH - 1, report(2, 1, 5));
+        claim_eq!(res, Err(ContractError::FutureEpoch));
+        claim(&mut host, &crypto_primitives, EPOCH_LENGTH, report(2, 1, 5))
+            .expect_report("Claiming should pass");
+
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(200)),
+            (ALICE, Amount::from_micro_ccd(50))
+        ]);
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that the rewards of a viewer are capped per epoch.
+    fn test_epoch_cap() {
+        let mut host = new_host();
+        let crypto_primitives =
+            signing(vec![report(1, 0, 30), report(2, 0, 30), report(3, 0, 1), report(3, 1, 1)]);
+
+        claim(&mut host, &crypto_primitives, EPOCH_LENGTH, report(1, 0, 30))
+            .expect_report("Claiming should pass");
+        claim(&mut host, &crypto_primitives, EPOCH_LENGTH, report(2, 0, 30))
+            .expect_report("Claiming what is left of the cap should pass");
+        let res = claim(&mut host, &crypto_primitives, EPOCH_LENGTH, report(3, 0, 1));
+        claim_eq!(res, Err(ContractError::EpochCapReached));
+        claim(&mut host, &crypto_primitives, EPOCH_LENGTH, report(3, 1, 1))
+            .expect_report("The cap is per epoch");
+
+        claim_eq!(host.get_transfers(), [
+            (ALICE, Amount::from_micro_ccd(300)),
+            (ALICE, Amount::from_micro_ccd(200)),
+            (ALICE, Amount::from_micro_ccd(10))
+        ]);
+    }
+}
@@ -0,0 +1,575 @@
+//! # A prediction market on the weather
+//!
+//! An instance of this contract is a market on whether it will be sunny or
+//! rainy in a region at a point in time, the target time. The weather is
+//! decided by the weather service contract from the [icecream](../icecream)
+//! example.
+//!
+//! Until the target time, anyone can `stake` CCD on sunny or rainy, any number
+//! of times and on both outcomes.
+//!
+//! From the target time on, anyone can `settle` the market. The contract
+//! queries the `history` of the weather service for the observations made
+//! since the target time, and the first observation in the region decides the
+//! outcome. Like the `get_simple` function of the weather service, the weather
+//! is rainy if the probability of precipitation is 50% or more, and sunny
+//! otherwise. If there has been no observation in the region yet, the market
+//! cannot be settled until there is.
+//!
+//! Once settled, the winners `claim` their stake back, together with a part of
+//! the stakes on the losing outcome in proportion to their stake. The parts are
+//! rounded down, so a few micro CCD can remain in the contract. If nobody
+//! staked on the outcome, everyone can claim their stakes back.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use concordium_std::*;
+
+/// The precipitation probability from which the weather is considered rainy,
+/// as in `get_simple` of the weather service.
+const PRECIPITATION_THRESHOLD: u8 = 50;
+
+/// The identifier of a region in the weather service.
+pub type RegionId = u32;
+
+/// A weather observation, as returned by the weather service.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weather {
+    /// The temperature in tenths of a degree Celsius.
+    pub temperature:               i32,
+    /// The probability of precipitation in percent.
+    pub precipitation_probability: u8,
+    /// The wind speed in tenths of a meter per second.
+    pub wind_speed:                u32,
+}
+
+/// The weather in a region, and when it was observed, as returned by the
+/// weather service.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Observation {
+    /// The observed weather.
+    pub weather:     Weather,
+    /// The slot time of the update with the observation.
+    pub observed_at: Timestamp,
+}
+
+/// The parameter type for the weather service function `history`.
+#[derive(Serialize, SchemaType)]
+pub struct HistoryParams {
+    /// The start of the time window, inclusive.
+    pub from: Timestamp,
+    /// The end of the time window, inclusive.
+    pub to:   Timestamp,
+}
+
+/// The outcomes of the market.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimpleWeather {
+    Rainy,
+    Sunny,
+}
+
+/// The stakes of an account on each outcome.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stakes {
+    pub rainy: Amount,
+    pub sunny: Amount,
+}
+
+impl Stakes {
+    /// No stake on either outcome.
+    fn empty() -> Self {
+        Stakes {
+            rainy: Amount::zero(),
+            sunny: Amount::zero(),
+        }
+    }
+
+    /// The stake on an outcome.
+    fn on(&self, outcome: SimpleWeather) -> Amount {
+        match outcome {
+            SimpleWeather::Rainy => self.rainy,
+            SimpleWeather::Sunny => self.sunny,
+        }
+    }
+
+    /// The stake on an outcome, mutably.
+    fn on_mut(&mut self, outcome: SimpleWeather) -> &mut Amount {
+        match outcome {
+            SimpleWeather::Rainy => &mut self.rainy,
+            SimpleWeather::Sunny => &mut self.sunny,
+        }
+    }
+}
+
+/// The contract state.
+#[derive(Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+struct State<S: HasStateApi> {
+    /// The weather service deciding the outcome.
+    weather_service: ContractAddress,
+    /// The region the market is on.
+    region:          RegionId,
+    /// The time the market is on. Staking closes at this time.
+    target_time:     Timestamp,
+    /// The total stakes on each outcome.
+    pools:           Stakes,
+    /// The outcome, once the market is settled.
+    outcome:         Option<SimpleWeather>,
+    /// The stakes of every account which has not claimed.
+    stakes:          StateMap<AccountAddress, Stakes, S>,
+}
+
+/// The parameter type for the contract function `init`.
+#[derive(Serialize, SchemaType)]
+pub struct InitParams {
+    /// The weather service deciding the outcome.
+    pub weather_service: ContractAddress,
+    /// The region the market is on.
+    pub region:          RegionId,
+    /// The time the market is on. Staking closes at this time.
+    pub target_time:     Timestamp,
+}
+
+/// The return type of the contract function `view`.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub struct MarketView {
+    pub region:      RegionId,
+    pub target_time: Timestamp,
+    pub pools:       Stakes,
+    pub outcome:     Option<SimpleWeather>,
+}
+
+/// The events logged by the contract.
+#[derive(Serialize, SchemaType, Debug, PartialEq, Eq)]
+pub enum MarketEvent {
+    /// An account staked on an outcome.
+    Stake {
+        account: AccountAddress,
+        outcome: SimpleWeather,
+        amount:  Amount,
+    },
+    /// The market was settled.
+    Settle(SimpleWeather),
+    /// An account claimed its winnings.
+    Claim {
+        account: AccountAddress,
+        amount:  Amount,
+    },
+}
+
+/// The different errors the contract can produce.
+#[derive(Debug, PartialEq, Eq, Clone, Reject, Serial, SchemaType)]
+enum ContractError {
+    /// Failed parsing the parameter.
+    #[from(ParseError)]
+    ParseParams,
+    /// Failed logging: Log is full.
+    LogFull,
+    /// Failed logging: Log is malformed.
+    LogMalformed,
+    /// Failed querying the weather service, or it returned something else than
+    /// observations.
+    InvokeContractError,
+    /// Raised when initializing with a target time which has been reached.
+    TargetTimeInPast,
+    /// Raised when a contract stakes; Only accounts can claim winnings.
+    OnlyAccount,
+    /// Raised when staking without sending CCD along.
+    ZeroStake,
+    /// Raised when staking from the target time on.
+    StakingClosed,
+    /// Raised when settling before the target time.
+    TargetTimeNotReached,
+    /// Raised when there has been no observation in the region since the
+    /// target time.
+    NoObservation,
+    /// Raised when settling a market which has been settled.
+    AlreadySettled,
+    /// Raised when claiming before the market is settled.
+    NotSettled,
+    /// Raised when the sender has no winnings to claim.
+    NothingToClaim,
+}
+
+type ContractResult<A> = Result<A, ContractError>;
+
+/// Mapping the logging errors to ContractError.
+impl From<LogError> for ContractError {
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
+/// Mapping errors related to contract invocations to ContractError.
+impl<T> From<CallContractError<T>> for ContractError {
+    fn from(_cce: CallContractError<T>) -> Self { Self::InvokeContractError }
+}
+
+/// Compute the winnings of a stake on the outcome: The stake itself, and the
+/// part of the losing pool in proportion to the stake, rounded down. If nobody
+/// staked on the outcome, every stake is returned.
+fn winnings(stakes: &Stakes, pools: &Stakes, outcome: SimpleWeather) -> Amount {
+    let winning_pool = pools.on(outcome);
+    if winning_pool == Amount::zero() {
+        return stakes.rainy + stakes.sunny;
+    }
+    let losing_pool = (pools.rainy + pools.sunny) - winning_pool;
+    let stake = stakes.on(outcome);
+    // Since `stake <= winning_pool` the part is at most `losing_pool`, and
+    // unwrapping is safe.
+    let part = losing_pool.mul_ratio(stake.micro_ccd, winning_pool.micro_ccd).unwrap_abort();
+    stake + part
+}
+
+/// Initialize the market.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The target time has been reached.
+#[init(contract = "weather_prediction", parameter = "InitParams", event = "MarketEvent")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+    ensure!(
+        ctx.metadata().slot_time() < params.target_time,
+        ContractError::TargetTimeInPast.into()
+    );
+    Ok(State {
+        weather_service: params.weather_service,
+        region:          params.region,
+        target_time:     params.target_time,
+        pools:           Stakes::empty(),
+        outcome:         None,
+        stakes:          state_builder.new_map(),
+    })
+}
+
+/// Stake the CCD sent along on an outcome.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The sender is a contract.
+/// - No CCD is sent along.
+/// - The target time has been reached.
+/// - It fails to log the event.
+#[receive(
+    contract = "weather_prediction",
+    name = "stake",
+    parameter = "SimpleWeather",
+    error = "ContractError",
+    enable_logger,
+    payable,
+    mutable
+)]
+fn contract_stake<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let outcome: SimpleWeather = ctx.parameter_cursor().get()?;
+    let account = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    ensure!(amount > Amount::zero(), ContractError::ZeroStake);
+    let state = host.state_mut();
+    ensure!(ctx.metadata().slot_time() < state.target_time, ContractError::StakingClosed);
+
+    *state.pools.on_mut(outcome) += amount;
+    let mut stakes = state.stakes.entry(account).or_insert(Stakes::empty());
+    *stakes.on_mut(outcome) += amount;
+    logger.log(&MarketEvent::Stake {
+        account,
+        outcome,
+        amount,
+    })?;
+    Ok(())
+}
+
+/// Settle the market using the first observation in the region made since the
+/// target time.
+///
+/// It rejects if:
+/// - The target time has not been reached.
+/// - The market has been settled.
+/// - It fails to query the weather service.
+/// - There has been no observation in the region since the target time.
+/// - It fails to log the event.
+#[receive(
+    contract = "weather_prediction",
+    name = "settle",
+    return_value = "SimpleWeather",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_settle<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<SimpleWeather> {
+    let state = host.state();
+    let now = ctx.metadata().slot_time();
+    ensure!(now >= state.target_time, ContractError::TargetTimeNotReached);
+    ensure!(state.outcome.is_none(), ContractError::AlreadySettled);
+
+    let parameter = HistoryParams {
+        from: state.target_time,
+        to:   now,
+    };
+    let response = host.invoke_contract_read_only(
+        &state.weather_service,
+        &parameter,
+        EntrypointName::new_unchecked("history"),
+        Amount::zero(),
+    )?;
+    let mut response = response.ok_or(ContractError::InvokeContractError)?;
+    let observations: Vec<(RegionId, Observation)> =
+        response.get().map_err(|_| ContractError::InvokeContractError)?;
+    // The observations are ordered by the time they were made.
+    let (_, observation) = observations
+        .iter()
+        .find(|(region, _)| *region == state.region)
+        .ok_or(ContractError::NoObservation)?;
+    let outcome = if observation.weather.precipitation_probability >= PRECIPITATION_THRESHOLD {
+        SimpleWeather::Rainy
+    } else {
+        SimpleWeather::Sunny
+    };
+
+    host.state_mut().outcome = Some(outcome);
+    logger.log(&MarketEvent::Settle(outcome))?;
+    Ok(outcome)
+}
+
+/// Claim the winnings of the sender. Returns the amount transferred.
+///
+/// It rejects if:
+/// - The market has not been settled.
+/// - The sender has no winnings, because it has not staked on the outcome, or
+///   has claimed already.
+/// - It fails to log the event.
+#[receive(
+    contract = "weather_prediction",
+    name = "claim",
+    return_value = "Amount",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<Amount> {
+    let account = match ctx.sender() {
+        Address::Contract(_) => bail!(ContractError::OnlyAccount),
+        Address::Account(account_address) => account_address,
+    };
+    let state = host.state_mut();
+    let outcome = state.outcome.ok_or(ContractError::NotSettled)?;
+    let stakes = state.stakes.get(&account).map(|s| *s).ok_or(ContractError::NothingToClaim)?;
+    let amount = winnings(&stakes, &state.pools, outcome);
+    ensure!(amount > Amount::zero(), ContractError::NothingToClaim);
+    state.stakes.remove(&account);
+
+    // The transfer always succeeds, since the account is the sender and the
+    // winnings are part of the stakes held by the contract.
+    host.invoke_transfer(&account, amount).unwrap_abort();
+    logger.log(&MarketEvent::Claim {
+        account,
+        amount,
+    })?;
+    Ok(amount)
+}
+
+/// View the market.
+#[receive(contract = "weather_prediction", name = "view", return_value = "MarketView")]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<MarketView> {
+    let state = host.state();
+    Ok(MarketView {
+        region:      state.region,
+        target_time: state.target_time,
+        pools:       state.pools,
+        outcome:     state.outcome,
+    })
+}
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use components::testing::ctx_with_parameter;
+    use test_infrastructure::*;
+
+    const ALICE: AccountAddress = AccountAddress([1; 32]);
+    const BOB: AccountAddress = AccountAddress([2; 32]);
+    const CHARLIE: AccountAddress = AccountAddress([3; 32]);
+    const WEATHER_SERVICE: ContractAddress = ContractAddress {
+        index:    1,
+        subindex: 0,
+    };
+    const REGION: RegionId = 1;
+    const OTHER_REGION: RegionId = 2;
+    const TARGET_TIME: u64 = 10_000;
+
+    /// An observation made at the time.
+    fn observation(precipitation_probability: u8, observed_at: u64) -> Observation {
+        Observation {
+            weather:     Weather {
+                temperature: 150,
+                precipitation_probability,
+                wind_speed: 30,
+            },
+            observed_at: Timestamp::from_timestamp_millis(observed_at),
+        }
+    }
+
+    /// Set up a market, and a weather service whose history has an observation
+    /// in the other region just after the target time, and rain in the region
+    /// a second after the target time, which is followed by sun.
+    fn new_host() -> TestHost<State<TestStateApi>> {
+        let parameter_bytes = to_bytes(&InitParams {
+            weather_service: WEATHER_SERVICE,
+            region:          REGION,
+            target_time:     Timestamp::from_timestamp_millis(TARGET_TIME),
+        });
+        let mut ctx = TestInitContext::empty();
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(0));
+        ctx.set_parameter(&parameter_bytes);
+        let mut state_builder = TestStateBuilder::new();
+        let state =
+            contract_init(&ctx, &mut state_builder).expect_report("Initialization should pass");
+        let mut host = TestHost::new(state, state_builder);
+        host.setup_mock_entrypoint(
+            WEATHER_SERVICE,
+            OwnedEntrypointName::new_unchecked("history".into()),
+            MockFn::new_v1(|parameter, _amount, _balance, _state| {
+                let params: HistoryParams =
+                    from_bytes(parameter.0).expect_report("Invalid history parameter");
+                let history = [
+                    (REGION, observation(0, TARGET_TIME - 1)),
+                    (OTHER_REGION, observation(0, TARGET_TIME)),
+                    (REGION, observation(90, TARGET_TIME + 1_000)),
+                    (REGION, observation(0, TARGET_TIME + 2_000)),
+                ];
+                let window: Vec<(RegionId, Observation)> = history
+                    .iter()
+                    .filter(|(_, o)| params.from <= o.observed_at && o.observed_at <= params.to)
+                    .copied()
+                    .collect();
+                Ok((false, window))
+            }),
+        );
+        host
+    }
+
+    fn stake_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        account: AccountAddress,
+        outcome: SimpleWeather,
+        micro_ccd: u64,
+        slot_time: u64,
+    ) -> ContractResult<()> {
+        let parameter_bytes = to_bytes(&outcome);
+        let amount = Amount::from_micro_ccd(micro_ccd);
+        host.set_self_balance(host.self_balance() + amount);
+        let ctx = ctx_with_parameter(account, slot_time, &parameter_bytes);
+        contract_stake(&ctx, host, amount, &mut TestLogger::init())
+    }
+
+    fn settle_at(
+        host: &mut TestHost<State<TestStateApi>>,
+        slot_time: u64,
+    ) -> ContractResult<SimpleWeather> {
+        contract_settle(&ctx_with_parameter(ALICE, slot_time, &[]), host, &mut TestLogger::init())
+    }
+
+    fn claim(
+        host: &mut TestHost<State<TestStateApi>>,
+        account: AccountAddress,
+    ) -> ContractResult<Amount> {
+        contract_claim(
+            &ctx_with_parameter(account, TARGET_TIME + 5_000, &[]),
+            host,
+            &mut TestLogger::init(),
+        )
+    }
+
+    #[concordium_test]
+    /// Test that staking is possible until the target time, on both outcomes.
+    fn test_stake() {
+        let mut host = new_host();
+        let res = stake_at(&mut host, ALICE, SimpleWeather::Sunny, 0, 0);
+        claim_eq!(res, Err(ContractError::ZeroStake));
+        stake_at(&mut host, ALICE, SimpleWeather::Sunny, 300, 0)
+            .expect_report("Staking should pass");
+        stake_at(&mut host, ALICE, SimpleWeather::Rainy, 100, TARGET_TIME - 1)
+            .expect_report("Staking just before the target time should pass");
+        let res = stake_at(&mut host, BOB, SimpleWeather::Rainy, 100, TARGET_TIME);
+        claim_eq!(res, Err(ContractError::StakingClosed));
+
+        claim_eq!(
+            host.state().stakes.get(&ALICE).map(|s| *s),
+            Some(Stakes {
+                rainy: Amount::from_micro_ccd(100),
+                sunny: Amount::from_micro_ccd(300),
+            })
+        );
+        claim_eq!(host.state().pools.rainy + host.state().pools.sunny, Amount::from_micro_ccd(400));
+    }
+
+    #[concordium_test]
+    /// Test that the market is settled by the first observation in the region
+    /// since the target time, and only once.
+    fn test_settle() {
+        let mut host = new_host();
+        claim_eq!(settle_at(&mut host, TARGET_TIME - 1), Err(ContractError::TargetTimeNotReached));
+        claim_eq!(settle_at(&mut host, TARGET_TIME + 999), Err(ContractError::NoObservation));
+        claim_eq!(settle_at(&mut host, TARGET_TIME + 5_000), Ok(SimpleWeather::Rainy));
+        claim_eq!(host.state().outcome, Some(SimpleWeather::Rainy));
+        claim_eq!(settle_at(&mut host, TARGET_TIME + 5_000), Err(ContractError::AlreadySettled));
+    }
+
+    #[concordium_test]
+    /// Test that the winners split the losing pool in proportion to their
+    /// stakes, and can only claim once.
+    fn test_claim_pro_rata() {
+        let mut host = new_host();
+        stake_at(&mut host, ALICE, SimpleWeather::Sunny, 300, 0)
+            .expect_report("Staking should pass");
+        stake_at(&mut host, BOB, SimpleWeather::Rainy, 100, 0).expect_report("Staking should pass");
+        stake_at(&mut host, CHARLIE, SimpleWeather::Rainy, 300, 0)
+            .expect_report("Staking should pass");
+        claim_eq!(claim(&mut host, BOB), Err(ContractError::NotSettled));
+        settle_at(&mut host, TARGET_TIME + 5_000).expect_report("Settling should pass");
+
+        claim_eq!(claim(&mut host, BOB), Ok(Amount::from_micro_ccd(175)));
+        claim_eq!(claim(&mut host, CHARLIE), Ok(Amount::from_micro_ccd(525)));
+        claim_eq!(claim(&mut host, ALICE), Err(ContractError::NothingToClaim));
+        claim_eq!(claim(&mut host, BOB), Err(ContractError::NothingToClaim));
+        claim_eq!(host.self_balance(), Amount::zero());
+    }
+
+    #[concordium_test]
+    /// Test that every stake is returned if nobody staked on the outcome.
+    fn test_claim_no_winners() {
+        let mut host = new_host();
+        stake_at(&mut host, ALICE, SimpleWeather::Sunny, 300, 0)
+            .expect_report("Staking should pass");
+        stake_at(&mut host, BOB, SimpleWeather::Sunny, 100, 0).expect_report("Staking should pass");
+        settle_at(&mut host, TARGET_TIME + 5_000).expect_report("Settling should pass");
+
+        claim_eq!(claim(&mut host, ALICE), Ok(Amount::from_micro_ccd(300)));
+        claim_eq!(claim(&mut host, BOB), Ok(Amount::from_micro_ccd(100)));
+    }
+}